@@ -0,0 +1,58 @@
+use nix::unistd::Pid;
+use std::fs;
+use std::io;
+
+/// The load address of the dynamic linker
+pub const AT_BASE: usize = 7;
+/// The entry point of the executable, where it is loaded
+pub const AT_ENTRY: usize = 9;
+
+/// The auxiliary vector the kernel passes to a new program, `/proc/<pid>/auxv`: where the
+/// executable, the dynamic linker and the vdso were loaded.
+#[derive(Debug, Clone, Default)]
+pub struct Auxv {
+    entries: Vec<(usize, usize)>,
+}
+
+impl Auxv {
+    /// # brief
+    /// Parse the content of a `/proc/<pid>/auxv` file: pairs of native words, ended by
+    /// `AT_NULL`. A truncated pair at the end is ignored.
+    pub fn parse(bytes: &[u8]) -> Self {
+        let word = std::mem::size_of::<usize>();
+        let entries = bytes
+            .chunks_exact(2 * word)
+            .map(|pair| {
+                let key = usize::from_ne_bytes(pair[..word].try_into().unwrap());
+                let value = usize::from_ne_bytes(pair[word..].try_into().unwrap());
+                (key, value)
+            })
+            .take_while(|(key, _)| *key != 0)
+            .collect();
+        Auxv { entries }
+    }
+
+    /// # brief
+    /// Get the value of an entry, e.g. `AT_ENTRY`.
+    pub fn get(&self, key: usize) -> Option<usize> {
+        self.entries.iter().find(|(entry_key, _)| *entry_key == key).map(|(_, value)| *value)
+    }
+
+    /// # brief
+    /// Get the runtime entry point of the executable.
+    pub fn entry(&self) -> Option<usize> {
+        self.get(AT_ENTRY)
+    }
+
+    /// # brief
+    /// Get where the dynamic linker was loaded, `None` for a static executable.
+    pub fn interpreter_base(&self) -> Option<usize> {
+        self.get(AT_BASE).filter(|base| *base != 0)
+    }
+}
+
+/// # brief
+/// Read the auxiliary vector of a process.
+pub fn read_auxv(pid: Pid) -> io::Result<Auxv> {
+    Ok(Auxv::parse(&fs::read(format!("/proc/{}/auxv", pid))?))
+}
@@ -0,0 +1,546 @@
+use crate::dwarf_data::Line;
+use std::fmt;
+use std::time::SystemTime;
+
+/// Where the location of a breakpoint is in the current program, kept up to date when it is
+/// set, installed and looked up again after a reload
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// The trap is at this address
+    Resolved(usize),
+    /// Nothing matches the location in the current debug information, e.g. its function was
+    /// removed before a reload; it is looked up again at the next reload
+    Pending,
+    /// The location was found but can't have a trap, for the reason given
+    Broken(String),
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Resolution::Resolved(_) => write!(f, "resolved"),
+            Resolution::Pending => write!(f, "pending"),
+            Resolution::Broken(reason) => write!(f, "broken: {}", reason),
+        }
+    }
+}
+
+/// A breakpoint set by the user, as listed by `info break`.
+///
+/// The installed traps and their original bytes are kept apart, in the `breakpoints` map of the
+/// debugger; a record is what survives reloading the target with `file`.
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    pub number: usize,
+    /// The location as typed, e.g. `main`, `foo.c:12` or `*0x401136`
+    pub spec: String,
+    /// The address of the trap, or why there is none
+    pub resolution: Resolution,
+    /// The function containing the address and the offset into it, recorded when the breakpoint
+    /// is set. An address breakpoint is moved along with its function when the target changes.
+    pub anchor: Option<(String, usize)>,
+    /// `thread N`: only stops of this thread are reported
+    pub thread: Option<usize>,
+    /// `if <cond>`: only stops where the expression is non-zero are reported
+    pub condition: Option<String>,
+    /// `force`: the address is not checked to begin an instruction
+    pub force: bool,
+    /// For a line location, the line with code it resolved to: the next one when the line
+    /// typed is a comment or blank
+    pub line: Option<Line>,
+    /// Set by `dprintf`: a hit prints the message and resumes instead of stopping
+    pub dprintf: Option<Dprintf>,
+    /// A disabled breakpoint keeps its record but has no trap, see `enable` and `disable`
+    pub enabled: bool,
+    /// `group=<name>`: the group it is enabled, disabled and deleted with, at most one
+    pub group: Option<String>,
+    /// When the location has code at several addresses, e.g. a line of a header included by
+    /// several compilation units, all of them, the first one being `resolution`. Empty for a
+    /// breakpoint with one address.
+    pub locations: Vec<BreakpointLocation>,
+}
+
+/// One of the addresses of a breakpoint with several, listed by `info break` as `N.M`
+#[derive(Debug, Clone)]
+pub struct BreakpointLocation {
+    pub addr: usize,
+    pub line: Option<Line>,
+    /// `disable N.M` removes the trap of this location only
+    pub enabled: bool,
+}
+
+/// The message of a `dprintf`, printed by deet each time its breakpoint is hit
+#[derive(Debug, Clone)]
+pub struct Dprintf {
+    /// The format as typed, with its escapes, e.g. `state=%d\n`
+    pub format: String,
+    /// The expressions giving the values of the conversions, in order
+    pub args: Vec<String>,
+}
+
+/// A part of the format of a `dprintf`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Text(String),
+    /// `%d`, `%u`, `%x`, `%s` or `%p`
+    Conversion(char),
+}
+
+/// What follows the location in the arguments of `break`
+#[derive(Debug, Default)]
+pub struct Qualifiers {
+    pub thread: Option<usize>,
+    pub condition: Option<String>,
+    pub force: bool,
+    pub group: Option<String>,
+    /// `disabled`: the breakpoint is set without a trap, as saved by `save session`
+    pub disabled: bool,
+}
+
+/// The most changes `info break history` keeps
+pub const MAX_HISTORY: usize = 100;
+
+/// A change of the breakpoints made by the user. Every change of the list goes through
+/// `Debugger::apply`, which records it in the history.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Created(Breakpoint),
+    Deleted(Breakpoint),
+    ConditionChanged { number: usize, old: Option<String>, new: Option<String> },
+    /// By `enable` or `disable`
+    Toggled { number: usize, enabled: bool },
+    /// By `group add`
+    Regrouped { number: usize, old: Option<String>, new: Option<String> },
+    /// By `enable N.M` or `disable N.M`, for the location `location` (from 1) of a breakpoint
+    LocationToggled { number: usize, location: usize, enabled: bool },
+}
+
+impl Change {
+    /// # brief
+    /// Get the number of the breakpoint changed.
+    pub fn number(&self) -> usize {
+        match self {
+            Change::Created(bp) | Change::Deleted(bp) => bp.number,
+            Change::ConditionChanged { number, .. }
+            | Change::Toggled { number, .. }
+            | Change::Regrouped { number, .. }
+            | Change::LocationToggled { number, .. } => *number,
+        }
+    }
+
+    /// # brief
+    /// Get the change that reverses this one, for `break undo`.
+    pub fn inverse(&self) -> Change {
+        match self {
+            Change::Created(bp) => Change::Deleted(bp.clone()),
+            Change::Deleted(bp) => Change::Created(bp.clone()),
+            Change::ConditionChanged { number, old, new } => {
+                Change::ConditionChanged { number: *number, old: new.clone(), new: old.clone() }
+            }
+            Change::Toggled { number, enabled } => Change::Toggled { number: *number, enabled: !enabled },
+            Change::Regrouped { number, old, new } => Change::Regrouped { number: *number, old: new.clone(), new: old.clone() },
+            Change::LocationToggled { number, location, enabled } => {
+                Change::LocationToggled { number: *number, location: *location, enabled: !enabled }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Created(bp) => write!(f, "created {} ({})", bp.number, bp.spec),
+            Change::Deleted(bp) => write!(f, "deleted {} ({})", bp.number, bp.spec),
+            Change::ConditionChanged { number, new: Some(condition), .. } => {
+                write!(f, "condition of {} set to {}", number, condition)
+            }
+            Change::ConditionChanged { number, new: None, .. } => write!(f, "condition of {} removed", number),
+            Change::Toggled { number, enabled: true } => write!(f, "enabled {}", number),
+            Change::Toggled { number, enabled: false } => write!(f, "disabled {}", number),
+            Change::Regrouped { number, new: Some(group), .. } => write!(f, "moved {} to group {}", number, group),
+            Change::Regrouped { number, new: None, .. } => write!(f, "removed {} from its group", number),
+            Change::LocationToggled { number, location, enabled: true } => write!(f, "enabled {}.{}", number, location),
+            Change::LocationToggled { number, location, enabled: false } => write!(f, "disabled {}.{}", number, location),
+        }
+    }
+}
+
+/// A change in the history of the breakpoints, with when and by which command it was made
+#[derive(Debug, Clone)]
+pub struct Mutation {
+    pub change: Change,
+    pub time: SystemTime,
+    /// The command line that made the change
+    pub command: String,
+    /// Whether the change was made by `break undo`, which is not undone itself
+    pub is_undo: bool,
+    /// Whether `break undo` reversed the change
+    pub undone: bool,
+}
+
+/// # brief
+/// Check the name of a breakpoint group: a word of letters, digits, `_` and `-`.
+pub fn check_group_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("Invalid group name \"{}\": use letters, digits, _ and -", name));
+    }
+    Ok(())
+}
+
+/// # brief
+/// Check whether a breakpoint location is the bare name of a function, such as `parse` or
+/// `plugin::init`, which a shared library loaded later may define, rather than an address, a
+/// line or a file:line.
+pub fn is_function_name(location: &str) -> bool {
+    location.split("::").all(|part| {
+        let mut chars = part.chars();
+        chars.next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// # brief
+/// Split the arguments of `break` into the location and the `force`, `thread N`, `group=<name>`,
+/// `disabled` and `if <cond>` qualifiers, e.g. `foo group=parsing if $rdi == 3 thread 1`. A
+/// `thread N` or a `group=<name>` after the condition ends it.
+///
+/// # return
+/// * `Ok((location, qualifiers))`, or `Err(String)` for a malformed qualifier.
+pub fn parse_qualifiers(text: &str) -> Result<(String, Qualifiers), String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let location = words
+        .first()
+        .ok_or("Usage: break <location> [force] [thread N] [group=<name>] [disabled] [if <cond>]")?
+        .to_string();
+    let parse_thread = |word: Option<&&str>| -> Result<usize, String> {
+        let word = word.ok_or("thread requires a thread number")?;
+        word.parse().or(Err(format!("Invalid thread number \"{}\"", word)))
+    };
+    let parse_group = |name: &str| -> Result<String, String> {
+        check_group_name(name)?;
+        Ok(name.to_string())
+    };
+    let mut qualifiers = Qualifiers::default();
+    let mut index = 1;
+    while index < words.len() {
+        match words[index] {
+            "force" => {
+                qualifiers.force = true;
+                index += 1;
+            }
+            "disabled" => {
+                qualifiers.disabled = true;
+                index += 1;
+            }
+            "thread" => {
+                qualifiers.thread = Some(parse_thread(words.get(index + 1))?);
+                index += 2;
+            }
+            word if word.starts_with("group=") => {
+                qualifiers.group = Some(parse_group(&word["group=".len()..])?);
+                index += 1;
+            }
+            "if" => {
+                let mut rest = &words[index + 1..];
+                loop {
+                    if rest.len() >= 2 && rest[rest.len() - 2] == "thread" {
+                        qualifiers.thread = Some(parse_thread(rest.last())?);
+                        rest = &rest[..rest.len() - 2];
+                    } else if let Some(name) = rest.last().and_then(|word| word.strip_prefix("group=")) {
+                        qualifiers.group = Some(parse_group(name)?);
+                        rest = &rest[..rest.len() - 1];
+                    } else {
+                        break;
+                    }
+                }
+                if rest.is_empty() {
+                    return Err("if requires a condition".to_string());
+                }
+                qualifiers.condition = Some(rest.join(" "));
+                break;
+            }
+            other => return Err(format!("Unexpected \"{}\" after the location, expected force, thread, group=, disabled or if", other)),
+        }
+    }
+    Ok((location, qualifiers))
+}
+
+impl Dprintf {
+    /// # brief
+    /// Parse the arguments of `dprintf <location>, "format", args... [thread N] [if <cond>]`,
+    /// as split by `tokenize`, which removed the quotes of the format.
+    ///
+    /// # return
+    /// * `Ok((text, Dprintf))` - the location with the qualifiers, for `parse_qualifiers`, and
+    /// the message; or `Err(String)` if the format is missing or doesn't match the arguments.
+    pub fn parse(words: &[String]) -> Result<(String, Dprintf), String> {
+        const USAGE: &str = "Usage: dprintf <location>, \"format\", args... [thread N] [if <cond>]";
+        let location = words.first().ok_or(USAGE)?.trim_end_matches(',').to_string();
+        let mut rest = &words[1..];
+        if rest.first().map(|word| word.as_str()) == Some(",") {
+            rest = &rest[1..];
+        }
+        let format = rest.first().ok_or(USAGE)?;
+        // the comma after the format sticks to it once the quotes are gone
+        let format = format.strip_suffix(',').unwrap_or(format).to_string();
+        rest = &rest[1..];
+        let end = rest.iter().position(|word| ["if", "thread", "force"].contains(&word.as_str())).unwrap_or(rest.len());
+        let args: Vec<String> = rest[..end]
+            .join(" ")
+            .split(',')
+            .map(|arg| arg.trim().to_string())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        let dprintf = Dprintf { format, args };
+        let conversions = dprintf.pieces()?.iter().filter(|piece| matches!(piece, Piece::Conversion(_))).count();
+        if conversions != dprintf.args.len() {
+            return Err(format!("The format has {} conversions but {} arguments are given", conversions, dprintf.args.len()));
+        }
+        Ok((std::iter::once(location).chain(rest[end..].iter().cloned()).collect::<Vec<_>>().join(" "), dprintf))
+    }
+
+    /// # brief
+    /// Split the format into text, with its escapes replaced, and conversions.
+    ///
+    /// # return
+    /// * The pieces, or `Err(String)` for a conversion other than `%d`, `%u`, `%x`, `%s`, `%p`
+    /// and `%%`.
+    pub fn pieces(&self) -> Result<Vec<Piece>, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = self.format.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(other) => text.push(other),
+                    None => text.push('\\'),
+                },
+                '%' => match chars.next() {
+                    Some('%') => text.push('%'),
+                    Some(conversion @ ('d' | 'u' | 'x' | 's' | 'p')) => {
+                        if !text.is_empty() {
+                            pieces.push(Piece::Text(std::mem::take(&mut text)));
+                        }
+                        pieces.push(Piece::Conversion(conversion));
+                    }
+                    Some(other) => return Err(format!("Unsupported conversion %{} in the format, expected %d, %u, %x, %s or %p", other)),
+                    None => return Err("The format ends with a lone %".to_string()),
+                },
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(pieces)
+    }
+
+    /// # brief
+    /// Render the message with the values of the arguments.
+    ///
+    /// # param
+    /// - `values` : The values of `args`, in order.
+    /// - `string` : Reads the NUL-terminated string at an address, for `%s`.
+    pub fn render(&self, values: &[u64], string: impl Fn(u64) -> String) -> String {
+        let mut message = String::new();
+        let mut values = values.iter().copied();
+        for piece in self.pieces().unwrap_or_default() {
+            let conversion = match piece {
+                Piece::Text(text) => {
+                    message.push_str(&text);
+                    continue;
+                }
+                Piece::Conversion(conversion) => conversion,
+            };
+            let value = values.next().unwrap_or(0);
+            match conversion {
+                'd' => message.push_str(&(value as i64).to_string()),
+                'u' => message.push_str(&value.to_string()),
+                'x' => message.push_str(&format!("{:x}", value)),
+                'p' => message.push_str(&format!("{:#x}", value)),
+                _ => message.push_str(&string(value)),
+            }
+        }
+        message
+    }
+
+    /// # brief
+    /// Get the format in quotes, as `parse` reads it back.
+    pub fn quoted_format(&self) -> String {
+        if self.format.contains('"') {
+            format!("'{}'", self.format)
+        } else {
+            format!("\"{}\"", self.format)
+        }
+    }
+}
+
+impl Breakpoint {
+    /// # brief
+    /// Whether the breakpoint was set at a raw address (`break *addr`) rather than at a line or
+    /// a function, which can be looked up again.
+    pub fn is_address(&self) -> bool {
+        self.spec.starts_with('*')
+    }
+
+    /// # brief
+    /// Get the address of the trap, `None` if the breakpoint is pending or broken.
+    pub fn addr(&self) -> Option<usize> {
+        match self.resolution {
+            Resolution::Resolved(addr) => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// # brief
+    /// Get the addresses of the enabled locations, whether or not the breakpoint itself is
+    /// enabled: the traps it has once it is.
+    pub fn enabled_locations(&self) -> Vec<usize> {
+        if self.locations.is_empty() {
+            return self.addr().into_iter().collect();
+        }
+        self.locations.iter().filter(|location| location.enabled).map(|location| location.addr).collect()
+    }
+
+    /// # brief
+    /// Get the addresses of the traps the breakpoint needs in the program, none if it is
+    /// disabled, pending or broken.
+    pub fn traps(&self) -> Vec<usize> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.enabled_locations()
+    }
+
+    /// # brief
+    /// Whether the breakpoint needs a trap at `addr`, i.e. a stop there is one of its hits.
+    pub fn has_trap(&self, addr: usize) -> bool {
+        self.traps().contains(&addr)
+    }
+
+    /// # brief
+    /// Record the addresses the location resolved to, keeping which ones were disabled when
+    /// there are as many as before. A single address leaves the breakpoint without locations.
+    pub fn set_locations(&mut self, resolved: &[(usize, Option<Line>)]) {
+        let keep = resolved.len() == self.locations.len();
+        self.locations = if resolved.len() < 2 {
+            Vec::new()
+        } else {
+            resolved
+                .iter()
+                .enumerate()
+                .map(|(index, (addr, line))| BreakpointLocation {
+                    addr: *addr,
+                    line: line.clone(),
+                    enabled: !keep || self.locations[index].enabled,
+                })
+                .collect()
+        };
+    }
+
+    /// # brief
+    /// Get the number (from 1) of the location at `addr`, `None` for a breakpoint with a
+    /// single address.
+    pub fn location_number(&self, addr: usize) -> Option<usize> {
+        self.locations.iter().position(|location| location.addr == addr).map(|index| index + 1)
+    }
+
+    /// # brief
+    /// Get the arguments of `break` that set this breakpoint again, e.g. `foo.c:12 thread 1 if
+    /// $rdi == 3`.
+    pub fn command_args(&self) -> String {
+        let mut args = self.spec.clone();
+        if self.force {
+            args.push_str(" force");
+        }
+        if let Some(thread) = self.thread {
+            args.push_str(&format!(" thread {}", thread));
+        }
+        if let Some(group) = &self.group {
+            args.push_str(&format!(" group={}", group));
+        }
+        if !self.enabled {
+            args.push_str(" disabled");
+        }
+        if let Some(condition) = &self.condition {
+            args.push_str(&format!(" if {}", condition));
+        }
+        args
+    }
+
+    /// # brief
+    /// Get the command that sets this breakpoint again: `break` with `command_args`, or
+    /// `dprintf` with the location, the message and the qualifiers.
+    pub fn command(&self) -> String {
+        let dprintf = match &self.dprintf {
+            Some(dprintf) => dprintf,
+            None => return format!("break {}", self.command_args()),
+        };
+        let args = self.command_args();
+        let (location, qualifiers) = args.split_at(self.spec.len());
+        let mut command = format!("dprintf {}, {}", location, dprintf.quoted_format());
+        for arg in dprintf.args.iter() {
+            command.push_str(&format!(", {}", arg));
+        }
+        command + qualifiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_bare_names_may_be_pending_on_a_library() {
+        for location in ["plugin_init", "_start2", "plugin::init"] {
+            assert!(is_function_name(location), "{}", location);
+        }
+        for location in ["", "12", "*0x401000", "main.c:12", "+3", ".", "a::", "2d", "f()"] {
+            assert!(!is_function_name(location), "{}", location);
+        }
+    }
+
+    #[test]
+    fn group_names_are_words() {
+        assert!(check_group_name("io-path_2").is_ok());
+        for name in ["", "a b", "x=y", "é"] {
+            assert_eq!(check_group_name(name), Err(format!("Invalid group name \"{}\": use letters, digits, _ and -", name)));
+        }
+    }
+
+    #[test]
+    fn qualifiers_take_a_group_before_or_after_the_condition() {
+        let (location, qualifiers) = parse_qualifiers("parse group=parsing disabled").unwrap();
+        assert_eq!((location.as_str(), qualifiers.group.as_deref(), qualifiers.disabled), ("parse", Some("parsing"), true));
+
+        let (_, qualifiers) = parse_qualifiers("parse if $rdi == 3 group=parsing").unwrap();
+        assert_eq!((qualifiers.condition.as_deref(), qualifiers.group.as_deref()), (Some("$rdi == 3"), Some("parsing")));
+        assert!(!qualifiers.force);
+
+        assert_eq!(
+            parse_qualifiers("parse group=").unwrap_err(),
+            "Invalid group name \"\": use letters, digits, _ and -"
+        );
+        assert_eq!(parse_qualifiers("parse if group=parsing").unwrap_err(), "if requires a condition");
+        assert_eq!(
+            parse_qualifiers("parse group parsing").unwrap_err(),
+            "Unexpected \"group\" after the location, expected force, thread, group=, disabled or if"
+        );
+    }
+
+    #[test]
+    fn qualifiers_take_a_thread_before_or_after_the_condition() {
+        let (_, qualifiers) = parse_qualifiers("parse thread 2 if $rdi == 3").unwrap();
+        assert_eq!((qualifiers.thread, qualifiers.condition.as_deref()), (Some(2), Some("$rdi == 3")));
+
+        let (_, qualifiers) = parse_qualifiers("parse if $rdi == 3 thread 2 group=parsing").unwrap();
+        assert_eq!((qualifiers.thread, qualifiers.condition.as_deref()), (Some(2), Some("$rdi == 3")));
+        assert_eq!(qualifiers.group.as_deref(), Some("parsing"));
+
+        assert_eq!(parse_qualifiers("parse thread").unwrap_err(), "thread requires a thread number");
+        assert_eq!(parse_qualifiers("parse thread two").unwrap_err(), "Invalid thread number \"two\"");
+    }
+
+}
@@ -0,0 +1,56 @@
+use nix::sys::signal::{signal, SigHandler, Signal};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the SIGINT handler installed while a token is alive
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Lets a long read-only command, such as a scan of all the debug information, be stopped
+/// before it ends: by Ctrl+C, or by answering "n" to the pager. The command checks the token
+/// between two steps and prints what it found so far.
+///
+/// deet ignores SIGINT so that Ctrl+C reaches the inferior while it runs; a token catches it
+/// for the time it lives only, like the wait of `--wait-for`.
+pub struct CancelToken {
+    cancelled: Cell<bool>,
+    /// The SIGINT handling to put back, `None` if SIGINT could not be caught
+    previous: Option<SigHandler>,
+}
+
+impl CancelToken {
+    /// # brief
+    /// Create a token that Ctrl+C cancels until it is dropped.
+    pub fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        let previous = unsafe { signal(Signal::SIGINT, SigHandler::Handler(interrupt)) }.ok();
+        CancelToken { cancelled: Cell::new(false), previous }
+    }
+
+    /// # brief
+    /// Cancel the command, e.g. when the rest of its output is declined.
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    /// # brief
+    /// Check whether the command should stop: it was cancelled, or Ctrl+C was pressed since
+    /// the token was created.
+    pub fn is_cancelled(&self) -> bool {
+        if self.previous.is_some() && INTERRUPTED.swap(false, Ordering::SeqCst) {
+            self.cancelled.set(true);
+        }
+        self.cancelled.get()
+    }
+}
+
+impl Drop for CancelToken {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous {
+            unsafe { signal(Signal::SIGINT, previous) }.ok();
+        }
+    }
+}
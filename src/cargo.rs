@@ -0,0 +1,452 @@
+//! The `--cargo` launcher: build a test or an example with cargo and find its executable in the
+//! JSON messages of the build, instead of looking for the hashed file under `target/debug/deps`.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// A JSON value, as much of it as the cargo messages need
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    /// # brief
+    /// Get the member `key` of an object, `None` for a missing member or another kind of value.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// A recursive descent parser over the characters of one JSON document
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+            None => Err(format!("expected '{}', found the end", expected)),
+        }
+    }
+
+    /// # brief
+    /// Read the letters of `true`, `false` or `null` after their first one.
+    fn keyword(&mut self, rest: &str, value: Json) -> Result<Json, String> {
+        for expected in rest.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err("invalid literal".to_string());
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some('n') => self.keyword("ull", Json::Null),
+            Some('t') => self.keyword("rue", Json::Bool(true)),
+            Some('f') => self.keyword("alse", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => {
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Json::Array(items)),
+                        _ => return Err("expected ',' or ']' in an array".to_string()),
+                    }
+                }
+            }
+            Some('{') => {
+                let mut members = BTreeMap::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.expect('"')?;
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    members.insert(key, self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Json::Object(members)),
+                        _ => return Err("expected ',' or '}' in an object".to_string()),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut text = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                        break;
+                    }
+                    text.push(c);
+                    self.chars.next();
+                }
+                text.parse().map(Json::Number).map_err(|_| format!("invalid number {}", text))
+            }
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    /// # brief
+    /// Read a string after its opening quote, unescaping it.
+    fn string(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        loop {
+            match self.chars.next().ok_or("unterminated string")? {
+                '"' => return Ok(text),
+                '\\' => match self.chars.next().ok_or("unterminated string")? {
+                    'n' => text.push('\n'),
+                    't' => text.push('\t'),
+                    'r' => text.push('\r'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'u' => {
+                        let code = self.hex4()?;
+                        // a character outside the basic plane comes as a surrogate pair
+                        let code = if (0xd800..0xdc00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            0x10000 + ((code - 0xd800) << 10) + (self.hex4()? - 0xdc00)
+                        } else {
+                            code
+                        };
+                        text.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    }
+                    other => text.push(other),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape \\u{}", digits))
+    }
+}
+
+/// # brief
+/// Parse one JSON document, such as a line of cargo's `--message-format=json` output.
+pub fn parse_json(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { chars: text.chars().peekable() };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{}' after the value", c)),
+    }
+}
+
+/// An executable built by cargo, from a `compiler-artifact` message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    /// The name of the target, e.g. the crate or the example
+    pub name: String,
+    /// The kinds of the target, e.g. `lib`, `bin`, `test` or `example`
+    pub kinds: Vec<String>,
+    /// Whether it is a test harness, built by `cargo test`
+    pub test: bool,
+    pub executable: String,
+}
+
+/// What a build said: the executables built, the compiler messages as cargo renders them, and
+/// whether it succeeded
+#[derive(Debug, Default)]
+pub struct BuildOutput {
+    pub artifacts: Vec<Artifact>,
+    pub diagnostics: Vec<String>,
+    pub success: Option<bool>,
+}
+
+impl BuildOutput {
+    /// # brief
+    /// Take in one line of cargo's JSON messages. Lines that are not JSON, e.g. written by a
+    /// build script, and the messages of other kinds are ignored.
+    pub fn add_message(&mut self, line: &str) {
+        let message = match parse_json(line) {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        match message.get("reason").and_then(Json::as_str) {
+            Some("compiler-artifact") => {
+                let executable = match message.get("executable").and_then(Json::as_str) {
+                    Some(executable) => executable.to_string(),
+                    None => return,
+                };
+                let target = message.get("target");
+                let name = target.and_then(|target| target.get("name")).and_then(Json::as_str).unwrap_or("");
+                let kinds = target
+                    .and_then(|target| target.get("kind"))
+                    .and_then(Json::as_array)
+                    .map(|kinds| kinds.iter().filter_map(Json::as_str).map(str::to_string).collect())
+                    .unwrap_or_default();
+                let test = message.get("profile").and_then(|profile| profile.get("test")).and_then(Json::as_bool);
+                self.artifacts.push(Artifact { name: name.to_string(), kinds, test: test.unwrap_or(false), executable });
+            }
+            Some("compiler-message") => {
+                let rendered = message.get("message").and_then(|message| message.get("rendered")).and_then(Json::as_str);
+                if let Some(rendered) = rendered {
+                    self.diagnostics.push(rendered.to_string());
+                }
+            }
+            Some("build-finished") => self.success = message.get("success").and_then(Json::as_bool),
+            _ => {}
+        }
+    }
+}
+
+/// The program `--cargo` found, and the arguments to run it with
+#[derive(Debug)]
+pub struct Launch {
+    pub executable: String,
+    pub args: Vec<String>,
+}
+
+/// # brief
+/// Run a cargo build with JSON messages, printing the compiler messages as they arrive, the way
+/// cargo prints them.
+fn build(args: &[String]) -> Result<BuildOutput, String> {
+    let mut child = Command::new("cargo")
+        .args(args)
+        .arg("--message-format=json")
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => {
+                "cargo was not found; build the program and give the path of the executable instead".to_string()
+            }
+            _ => format!("could not run cargo: {}", err),
+        })?;
+    let mut output = BuildOutput::default();
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let line = line.map_err(|err| format!("could not read the output of cargo: {}", err))?;
+        let shown = output.diagnostics.len();
+        output.add_message(&line);
+        for diagnostic in &output.diagnostics[shown..] {
+            eprint!("{}", diagnostic);
+        }
+    }
+    let status = child.wait().map_err(|err| format!("could not wait for cargo: {}", err))?;
+    if !status.success() || output.success == Some(false) {
+        return Err(format!("cargo {} failed", args.join(" ")));
+    }
+    Ok(output)
+}
+
+/// # brief
+/// Check whether the test harness `executable` has a test whose name contains `filter`, with
+/// `--list`.
+fn has_test(executable: &str, filter: &str) -> bool {
+    let listing = match Command::new(executable).arg("--list").stderr(Stdio::null()).output() {
+        Ok(listing) => listing,
+        Err(_) => return false,
+    };
+    String::from_utf8_lossy(&listing.stdout)
+        .lines()
+        .filter_map(|line| line.strip_suffix(": test"))
+        .any(|name| name.contains(filter))
+}
+
+/// # brief
+/// Pick the one executable among `candidates`, or explain why there is none.
+fn only_one(candidates: Vec<&Artifact>, what: &str, hint: &str) -> Result<String, String> {
+    match candidates.as_slice() {
+        [artifact] => Ok(artifact.executable.clone()),
+        [] => Err(format!("cargo built no {}", what)),
+        _ => {
+            let names: Vec<&str> = candidates.iter().map(|artifact| artifact.executable.as_str()).collect();
+            Err(format!("cargo built several {}: {}; {}", what, names.join(", "), hint))
+        }
+    }
+}
+
+/// # brief
+/// Handle `--cargo <args>`: build with cargo and find the program to debug.
+///
+/// - `test [filter] [cargo options...]` builds the tests with `cargo test --no-run`; the program
+/// is the test harness holding a test matching the filter, run with the filter and
+/// `--nocapture`.
+/// - `run [cargo options...] [-- program arguments...]` builds with `cargo build`, e.g. with
+/// `--example foo` or `--bin foo`; the program is the binary or example built.
+///
+/// # return
+/// * `Ok(Launch)` - the executable and its arguments, or `Err(String)` describing why the build
+/// failed or which executable to pick.
+pub fn launch(args: &[String]) -> Result<Launch, String> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("test") => {
+            let filter = args.get(1).filter(|arg| !arg.starts_with('-')).cloned();
+            let options = &args[if filter.is_some() { 2 } else { 1 }..];
+            let mut cargo_args = vec!["test".to_string(), "--no-run".to_string()];
+            cargo_args.extend(options.iter().cloned());
+            let output = build(&cargo_args)?;
+            let mut harnesses: Vec<&Artifact> = output.artifacts.iter().filter(|artifact| artifact.test).collect();
+            if let (Some(filter), true) = (&filter, harnesses.len() > 1) {
+                harnesses.retain(|artifact| has_test(&artifact.executable, filter));
+            }
+            let executable = only_one(harnesses, "test executable matching the filter", "choose one with --test <name> or --lib")?;
+            let mut args: Vec<String> = filter.into_iter().collect();
+            args.push("--nocapture".to_string());
+            Ok(Launch { executable, args })
+        }
+        Some("run") => {
+            let (options, program_args) = match args.iter().position(|arg| arg == "--") {
+                Some(end) => (&args[1..end], args[end + 1..].to_vec()),
+                None => (&args[1..], Vec::new()),
+            };
+            let mut cargo_args = vec!["build".to_string()];
+            cargo_args.extend(options.iter().cloned());
+            let output = build(&cargo_args)?;
+            let programs: Vec<&Artifact> = output
+                .artifacts
+                .iter()
+                .filter(|artifact| !artifact.test && artifact.kinds.iter().any(|kind| kind == "bin" || kind == "example"))
+                .collect();
+            let executable = only_one(programs, "program", "choose one with --bin <name> or --example <name>")?;
+            Ok(Launch { executable, args: program_args })
+        }
+        _ => Err("--cargo expects test [filter] [options...] or run [options...] [-- arguments...]".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The messages of `cargo test --no-run --message-format=json` for a crate with a library,
+    /// a binary and an integration test, cut to the members deet reads
+    const TEST_BUILD: &[&str] = &[
+        r#"{"reason":"compiler-artifact","package_id":"dep 0.1.0","target":{"kind":["lib"],"name":"dep"},"profile":{"test":false},"executable":null}"#,
+        r#"{"reason":"compiler-message","package_id":"demo 0.1.0","message":{"rendered":"warning: unused variable: `x`\n --> src/lib.rs:2:9\n","level":"warning"}}"#,
+        r#"{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["lib"],"name":"demo"},"profile":{"test":true},"executable":"/work/demo/target/debug/deps/demo-1a2b3c"}"#,
+        r#"{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["bin"],"name":"demo"},"profile":{"test":true},"executable":"/work/demo/target/debug/deps/demo-4d5e6f"}"#,
+        r#"{"reason":"compiler-artifact","package_id":"demo 0.1.0","target":{"kind":["test"],"name":"parsing"},"profile":{"test":true},"executable":"/work/demo/target/debug/deps/parsing-7a8b9c"}"#,
+        "build script output, not JSON",
+        r#"{"reason":"build-finished","success":true}"#,
+    ];
+
+    fn build_output(lines: &[&str]) -> BuildOutput {
+        let mut output = BuildOutput::default();
+        for line in lines {
+            output.add_message(line);
+        }
+        output
+    }
+
+    #[test]
+    fn parses_json_values() {
+        let value = parse_json(r#" {"a": [1, -2.5e1, true, null], "b": "x\"é😀\n"} "#).unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Json::Array(vec![Json::Number(1.0), Json::Number(-25.0), Json::Bool(true), Json::Null]))
+        );
+        assert_eq!(value.get("b").and_then(Json::as_str), Some("x\"é😀\n"));
+        assert_eq!(parse_json("[]"), Ok(Json::Array(vec![])));
+        assert_eq!(parse_json("{}"), Ok(Json::Object(BTreeMap::new())));
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        for text in ["", "{", "[1,]", r#"{"a" 1}"#, r#""open"#, "tru", "1 2", r#""\uzzzz""#] {
+            assert!(parse_json(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn collects_artifacts_diagnostics_and_result() {
+        let output = build_output(TEST_BUILD);
+        // the library of the dependency has no executable
+        let executables: Vec<&str> = output.artifacts.iter().map(|artifact| artifact.executable.as_str()).collect();
+        assert_eq!(
+            executables,
+            vec![
+                "/work/demo/target/debug/deps/demo-1a2b3c",
+                "/work/demo/target/debug/deps/demo-4d5e6f",
+                "/work/demo/target/debug/deps/parsing-7a8b9c"
+            ]
+        );
+        assert_eq!(
+            output.artifacts[2],
+            Artifact {
+                name: "parsing".to_string(),
+                kinds: vec!["test".to_string()],
+                test: true,
+                executable: "/work/demo/target/debug/deps/parsing-7a8b9c".to_string(),
+            }
+        );
+        assert_eq!(output.diagnostics, vec!["warning: unused variable: `x`\n --> src/lib.rs:2:9\n".to_string()]);
+        assert_eq!(output.success, Some(true));
+    }
+
+    #[test]
+    fn failed_build_is_recorded() {
+        let output = build_output(&[r#"{"reason":"build-finished","success":false}"#]);
+        assert_eq!(output.success, Some(false));
+        assert!(build_output(&[]).success.is_none());
+    }
+
+    #[test]
+    fn several_test_executables_are_ambiguous() {
+        let output = build_output(TEST_BUILD);
+        let harnesses: Vec<&Artifact> = output.artifacts.iter().filter(|artifact| artifact.test).collect();
+        let err = only_one(harnesses, "test executable", "choose one with --test <name> or --lib").unwrap_err();
+        assert!(err.starts_with("cargo built several test executable: /work/demo/target/debug/deps/demo-1a2b3c, "), "{}", err);
+        assert!(err.ends_with("; choose one with --test <name> or --lib"), "{}", err);
+        assert_eq!(only_one(Vec::new(), "program", "").unwrap_err(), "cargo built no program");
+        let only = &output.artifacts[2];
+        assert_eq!(only_one(vec![only], "program", ""), Ok(only.executable.clone()));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::inferior::Frame;
+use crate::target::TargetAccess;
+use nix::unistd::Pid;
+use std::cell::Cell;
+
+thread_local! {
+    /// Whether `Context::registers` is reading the registers of the program, see `reading`
+    static READING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// # brief
+/// Whether the registers are being read for the context. A target asserts it where it reads
+/// the registers of a stop, so that no inspection path reads them on its own.
+pub fn reading() -> bool {
+    READING.with(|reading| reading.get())
+}
+
+/// Which program and thread a stop is of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identity {
+    /// The number of the inferior, see `inferior <n>`
+    pub inferior: usize,
+    /// The thread, `None` when the target doesn't tell its threads apart
+    pub thread: Option<Pid>,
+}
+
+/// What the inspection commands (`print`, `x`, `list`, `info`, `bt`...) look at: the frame
+/// selected by `frame`, `up` or `down`, and the registers of the selected thread, read from the
+/// program once per stop rather than once per lookup. Every inspection path gets the registers
+/// from here, so that they all agree on the frame they show. The inferior keeps the selected
+/// thread, see `Inferior::select_thread`, and selecting another one is a new stop here.
+///
+/// The dispatcher calls `on_resume` before any command that may run the program or change it,
+/// which puts the selection back on frame 0, as gdb does: `frame 2; finish` finishes frame 0.
+/// `handle_status` calls `on_stop` for each stop, so neither the registers nor the frame
+/// selected before a stop are used after it. The context knows which inferior and thread it is
+/// of, and asserts that it is asked about them only.
+pub struct Context {
+    /// The program and thread of this stop, `None` until the registers are first read after
+    /// the program resumed
+    stop: Cell<Option<Identity>>,
+    /// The selected frame with its number in the backtrace, `None` for frame 0
+    frame: Option<(usize, Frame)>,
+    /// The registers of the selected thread, `None` until they are first needed at this stop
+    registers: Cell<Option<libc::user_regs_struct>>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context { stop: Cell::new(None), frame: None, registers: Cell::new(None) }
+    }
+
+    /// # brief
+    /// Get the number of the selected frame in the backtrace, 0 for the innermost.
+    pub fn frame(&self) -> usize {
+        self.frame.as_ref().map_or(0, |(number, _)| *number)
+    }
+
+    /// # brief
+    /// Get the selected frame, `None` when it is frame 0, the one the program stopped in.
+    pub fn selected_frame(&self) -> Option<&Frame> {
+        self.frame.as_ref().map(|(_, frame)| frame)
+    }
+
+    /// # brief
+    /// Select the frame the inspection commands look at until the program resumes.
+    ///
+    /// # param
+    /// - `number` : The number of the frame in the backtrace.
+    /// - `frame` : The frame, from the stack walk of this stop.
+    pub fn select_frame(&mut self, number: usize, frame: Frame) {
+        self.frame = if number == 0 { None } else { Some((number, frame)) };
+    }
+
+    /// # brief
+    /// Get the registers of frame 0, reading them from the program the first time
+    /// they are asked for at this stop.
+    ///
+    /// # param
+    /// - `access` : The program stopped.
+    /// - `identity` : Its inferior and selected thread, those of this stop.
+    pub fn registers(&self, access: &dyn TargetAccess, identity: Identity) -> Result<libc::user_regs_struct, nix::Error> {
+        let stop = self.stop.get();
+        debug_assert!(
+            stop.map_or(true, |stop| stop == identity),
+            "the registers of {:?} asked for at a stop of {:?}, which was not followed by on_stop",
+            identity,
+            stop
+        );
+        if stop != Some(identity) {
+            // what was read is of another program or thread
+            self.stop.set(Some(identity));
+            self.registers.set(None);
+        }
+        if let Some(regs) = self.registers.get() {
+            return Ok(regs);
+        }
+        READING.with(|reading| reading.set(true));
+        let regs = access.registers();
+        READING.with(|reading| reading.set(false));
+        let regs = regs?;
+        self.registers.set(Some(regs));
+        Ok(regs)
+    }
+
+    /// # brief
+    /// Get the registers as the selected frame sees them. An outer frame runs at its return
+    /// address with its own %rbp, the other registers are those of frame 0.
+    pub fn frame_registers(&self, access: &dyn TargetAccess, identity: Identity) -> Result<libc::user_regs_struct, nix::Error> {
+        let mut regs = self.registers(access, identity)?;
+        if let Some(frame) = self.selected_frame().filter(|frame| !frame.innermost) {
+            regs.rip = frame.rip as u64;
+            regs.rbp = frame.rbp as u64;
+        }
+        Ok(regs)
+    }
+
+    /// # brief
+    /// Forget the context before the program runs: the registers will change, and the
+    /// selection goes back to frame 0.
+    pub fn on_resume(&mut self) {
+        self.stop.set(None);
+        self.frame = None;
+        self.registers.set(None);
+    }
+
+    /// # brief
+    /// Forget the registers read and the frame selected before the program stopped, or before
+    /// another inferior or thread was selected.
+    ///
+    /// # param
+    /// - `identity` : The inferior and thread of the new stop.
+    pub fn on_stop(&mut self, identity: Identity) {
+        self.stop.set(Some(identity));
+        self.frame = None;
+        self.registers.set(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stopped program whose registers only change when told, counting the reads and
+    /// those made outside of the context
+    struct Program {
+        rip: Cell<u64>,
+        reads: Cell<usize>,
+        raw_reads: Cell<usize>,
+    }
+
+    impl Program {
+        fn stopped_at(rip: u64) -> Self {
+            Program { rip: Cell::new(rip), reads: Cell::new(0), raw_reads: Cell::new(0) }
+        }
+    }
+
+    /// # brief
+    /// The stop of thread `number` of the program, the first being the main thread.
+    fn thread(number: i32) -> Identity {
+        Identity { inferior: 1, thread: Some(Pid::from_raw(1000 + number)) }
+    }
+
+    impl TargetAccess for Program {
+        fn read_bytes(&self, _addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+            Ok(vec![0; len])
+        }
+
+        fn registers(&self) -> Result<libc::user_regs_struct, nix::Error> {
+            self.reads.set(self.reads.get() + 1);
+            if !reading() {
+                self.raw_reads.set(self.raw_reads.get() + 1);
+            }
+            let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+            regs.rip = self.rip.get();
+            regs.rbp = 0x7ffc_0000_1000;
+            Ok(regs)
+        }
+    }
+
+    /// The frame 2 of a backtrace, which returns to 0x401234
+    fn caller() -> Frame {
+        Frame { rip: 0x401234, rbp: 0x7ffc_0000_2000, function: Some("main".to_string()), line: None, innermost: false, inlined: false }
+    }
+
+    #[test]
+    fn registers_are_read_once_per_stop() {
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        assert_eq!(context.registers(&program, thread(1)).unwrap().rip, 0x401100);
+        assert_eq!(context.registers(&program, thread(1)).unwrap().rip, 0x401100);
+        assert_eq!(program.reads.get(), 1);
+        program.rip.set(0x401180);
+        context.on_stop(thread(1));
+        assert_eq!(context.registers(&program, thread(1)).unwrap().rip, 0x401180);
+        assert_eq!(program.reads.get(), 2);
+    }
+
+    #[test]
+    fn selected_frame_sees_its_own_pc_and_frame_pointer() {
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.select_frame(2, caller());
+        assert_eq!(context.frame(), 2);
+        let regs = context.frame_registers(&program, thread(1)).unwrap();
+        assert_eq!((regs.rip, regs.rbp), (0x401234, 0x7ffc_0000_2000));
+        // frame 0 is still where the program stopped
+        assert_eq!(context.registers(&program, thread(1)).unwrap().rip, 0x401100);
+    }
+
+    #[test]
+    fn frame_zero_is_the_innermost() {
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.select_frame(2, caller());
+        context.select_frame(0, Frame { innermost: true, ..caller() });
+        assert_eq!(context.frame(), 0);
+        assert!(context.selected_frame().is_none());
+        assert_eq!(context.frame_registers(&program, thread(1)).unwrap().rip, 0x401100);
+    }
+
+    #[test]
+    fn resume_goes_back_to_frame_zero() {
+        // frame 2; continue; print x looks at frame 0 of the new stop
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.select_frame(2, caller());
+        context.on_resume();
+        program.rip.set(0x401180);
+        context.on_stop(thread(1));
+        assert_eq!(context.frame(), 0);
+        let regs = context.frame_registers(&program, thread(1)).unwrap();
+        assert_eq!((regs.rip, regs.rbp), (0x401180, 0x7ffc_0000_1000));
+    }
+
+    #[test]
+    fn resume_drops_the_selection_before_the_command_runs() {
+        // frame 2; finish acts on frame 0
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.select_frame(2, caller());
+        context.on_resume();
+        assert!(context.selected_frame().is_none());
+        assert_eq!(context.frame_registers(&program, thread(1)).unwrap().rip, 0x401100);
+    }
+
+    #[test]
+    fn registers_are_read_for_the_context_only() {
+        let program = Program::stopped_at(0x401100);
+        let context = Context::new();
+        context.registers(&program, thread(1)).unwrap();
+        assert_eq!((program.reads.get(), program.raw_reads.get()), (1, 0));
+        assert!(!reading());
+        program.registers().unwrap();
+        assert_eq!(program.raw_reads.get(), 1);
+    }
+
+    #[test]
+    fn the_first_read_after_a_resume_tells_whose_stop_it_is() {
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.on_stop(thread(1));
+        context.on_resume();
+        // the program stopped in thread 2, which the context was not told of
+        context.registers(&program, thread(2)).unwrap();
+        context.registers(&program, thread(2)).unwrap();
+        assert_eq!(program.reads.get(), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "which was not followed by on_stop")]
+    fn registers_of_another_thread_are_not_those_of_this_stop() {
+        let program = Program::stopped_at(0x401100);
+        let mut context = Context::new();
+        context.on_stop(thread(1));
+        context.registers(&program, thread(1)).unwrap();
+        // thread apply switched threads without telling the context
+        context.registers(&program, thread(2)).unwrap();
+    }
+}
@@ -0,0 +1,399 @@
+use crate::maps::Mapping;
+use crate::target::TargetAccess;
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::fs;
+use std::os::unix::fs::FileExt;
+
+/// The ELF type of a core dump
+const ET_CORE: usize = 4;
+/// The ELF machine of x86_64
+const EM_X86_64: usize = 62;
+/// The program header types of the memory of the program and of its notes
+const PT_LOAD: usize = 1;
+const PT_NOTE: usize = 4;
+/// The note of a thread's state, the first one is the thread that crashed
+const NT_PRSTATUS: usize = 1;
+/// The note of the files mapped by the program, "FILE"
+const NT_FILE: usize = 0x4649_4c45;
+/// Where the signal, the pid and the registers are in `struct elf_prstatus` on x86_64
+const PRSTATUS_CURSIG: usize = 12;
+const PRSTATUS_PID: usize = 32;
+const PRSTATUS_REGS: usize = 112;
+
+/// A part of the memory of the program, as the kernel dumped it
+#[derive(Debug, Clone)]
+struct Segment {
+    start: usize,
+    end: usize,
+    /// Where its contents are in the core file
+    offset: usize,
+    /// How many bytes were dumped, fewer than its size for the mapped files left out
+    dumped: usize,
+    /// `PF_X`, `PF_W` and `PF_R`
+    flags: usize,
+}
+
+/// A file mapped by the program, from the `NT_FILE` note
+#[derive(Debug, Clone)]
+struct MappedFile {
+    start: usize,
+    end: usize,
+    /// Where the mapping starts in the file, in bytes
+    offset: usize,
+    path: String,
+}
+
+/// A core dump of a crashed program, read in place of a process: `deet --core <file>`. Its
+/// memory is what the kernel dumped, and the code it left out is read from the mapped files.
+pub struct Core {
+    data: Vec<u8>,
+    segments: Vec<Segment>,
+    files: Vec<MappedFile>,
+    pid: i32,
+    signal: i32,
+    registers: libc::user_regs_struct,
+}
+
+/// # brief
+/// Read a little-endian word of `size` bytes at `offset`, `None` past the end of `data`.
+fn word(data: &[u8], offset: usize, size: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    Some(bytes.iter().rev().fold(0, |word, byte| word << 8 | *byte as usize))
+}
+
+/// # brief
+/// Split the notes of a `PT_NOTE` segment into their types and descriptions. A truncated
+/// note ends the list.
+fn notes(data: &[u8]) -> Vec<(usize, &[u8])> {
+    let align = |size: usize| (size + 3) & !3;
+    let mut notes = Vec::new();
+    let mut offset = 0;
+    while let (Some(name_size), Some(size), Some(kind)) = (word(data, offset, 4), word(data, offset + 4, 4), word(data, offset + 8, 4)) {
+        let start = offset + 12 + align(name_size);
+        match data.get(start..start + size) {
+            Some(description) => notes.push((kind, description)),
+            None => break,
+        }
+        offset = start + align(size);
+    }
+    notes
+}
+
+/// # brief
+/// Parse the `NT_FILE` note: the number of mappings and the page size, a start, end and
+/// offset in pages for each, then their paths one after the other.
+fn mapped_files(note: &[u8]) -> Vec<MappedFile> {
+    let (count, page_size) = match (word(note, 0, 8), word(note, 8, 8)) {
+        (Some(count), Some(page_size)) => (count, page_size),
+        _ => return Vec::new(),
+    };
+    let paths_start = 16usize.saturating_add(count.saturating_mul(24));
+    let mut paths = note.get(paths_start..).unwrap_or_default().split(|byte| *byte == 0);
+    (0..count)
+        .map_while(|index| {
+            let entry = 16 + index * 24;
+            Some(MappedFile {
+                start: word(note, entry, 8)?,
+                end: word(note, entry + 8, 8)?,
+                offset: word(note, entry + 16, 8)?.checked_mul(page_size)?,
+                path: String::from_utf8_lossy(paths.next()?).into_owned(),
+            })
+        })
+        .collect()
+}
+
+/// # brief
+/// Read the registers of `pr_reg`, which on x86_64 is laid out as `user_regs_struct`.
+fn prstatus_registers(prstatus: &[u8]) -> Option<libc::user_regs_struct> {
+    let size = std::mem::size_of::<libc::user_regs_struct>();
+    let bytes = prstatus.get(PRSTATUS_REGS..PRSTATUS_REGS + size)?;
+    // the struct is only made of 64-bit registers, any bytes are a valid value of it
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const libc::user_regs_struct) })
+}
+
+impl Core {
+    /// # brief
+    /// Read a core dump from a file.
+    ///
+    /// # return
+    /// * The core, or `Err(String)` if it can't be read or is not the core dump of an x86_64
+    ///   program.
+    pub fn open(path: &str) -> Result<Core, String> {
+        let data = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        Core::parse(data).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// # brief
+    /// Parse a core dump: its memory segments, the files the program mapped and the state of
+    /// the thread that crashed.
+    pub fn parse(data: Vec<u8>) -> Result<Core, String> {
+        if data.len() < 64 || &data[..4] != b"\x7fELF" {
+            return Err("not in executable format: file format not recognized".to_string());
+        }
+        if data[4] != 2 || data[5] != 1 || word(&data, 0x12, 2) != Some(EM_X86_64) {
+            return Err("not the core dump of an x86_64 program".to_string());
+        }
+        if word(&data, 0x10, 2) != Some(ET_CORE) {
+            return Err("is not a core dump".to_string());
+        }
+        let (table, entry_size, count) = match (word(&data, 0x20, 8), word(&data, 0x36, 2), word(&data, 0x38, 2)) {
+            (Some(table), Some(entry_size), Some(count)) => (table, entry_size, count),
+            _ => return Err("truncated ELF header".to_string()),
+        };
+        let mut segments = Vec::new();
+        let mut files = Vec::new();
+        let mut prstatus = None;
+        for index in 0..count {
+            let header = table + index * entry_size;
+            let field = |offset: usize| word(&data, header + offset, if offset == 4 { 4 } else { 8 });
+            let (kind, flags, offset, start, dumped, size) = match (word(&data, header, 4), field(4), field(8), field(16), field(32), field(40)) {
+                (Some(kind), Some(flags), Some(offset), Some(start), Some(dumped), Some(size)) => (kind, flags, offset, start, dumped, size),
+                _ => return Err("truncated program headers".to_string()),
+            };
+            match kind {
+                PT_LOAD => segments.push(Segment { start, end: start + size, offset, dumped, flags }),
+                PT_NOTE => {
+                    let contents = data.get(offset..offset + dumped).ok_or("truncated notes")?;
+                    for (kind, description) in notes(contents) {
+                        match kind {
+                            NT_PRSTATUS if prstatus.is_none() => prstatus = Some(description.to_vec()),
+                            NT_FILE => files = mapped_files(description),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let prstatus = prstatus.ok_or("no thread state in the core dump")?;
+        let registers = prstatus_registers(&prstatus).ok_or("truncated thread state")?;
+        Ok(Core {
+            pid: word(&prstatus, PRSTATUS_PID, 4).unwrap_or(0) as i32,
+            signal: word(&prstatus, PRSTATUS_CURSIG, 2).unwrap_or(0) as i32,
+            data,
+            segments,
+            files,
+            registers,
+        })
+    }
+
+    /// # brief
+    /// Get the pid of the program when it crashed.
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// # brief
+    /// Get the signal the program was terminated with, `None` for a core dumped by gcore.
+    pub fn signal(&self) -> Option<Signal> {
+        Signal::from_c_int(self.signal).ok()
+    }
+
+    /// # brief
+    /// Get the path of the executable that dumped the core, the first file it mapped.
+    pub fn executable(&self) -> Option<&str> {
+        self.files.first().map(|file| file.path.as_str())
+    }
+
+    /// # brief
+    /// Get the memory mappings of the program, as `/proc/<pid>/maps` showed them. The kernel
+    /// doesn't record which one was the stack: it is the writable mapping %rsp points into.
+    pub fn mappings(&self) -> Vec<Mapping> {
+        let rsp = self.registers.rsp as usize;
+        self.segments
+            .iter()
+            .map(|segment| {
+                let file = self.files.iter().find(|file| file.start == segment.start);
+                let perms = [(4, 'r'), (2, 'w'), (1, 'x')]
+                    .iter()
+                    .map(|(flag, letter)| if segment.flags & flag != 0 { *letter } else { '-' })
+                    .chain(std::iter::once('p'))
+                    .collect();
+                let path = match file {
+                    Some(file) => file.path.clone(),
+                    None if segment.flags & 2 != 0 && (segment.start..segment.end).contains(&rsp) => "[stack]".to_string(),
+                    None => String::new(),
+                };
+                Mapping { start: segment.start, end: segment.end, perms, offset: file.map_or(0, |file| file.offset), path }
+            })
+            .collect()
+    }
+
+    /// # brief
+    /// Read what the memory held at `addr`, up to `len` bytes and until the end of the part
+    /// it is in: a dumped segment, else the file mapped there.
+    fn read_piece(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        if let Some(segment) = self.segments.iter().find(|segment| segment.start <= addr && addr < segment.start + segment.dumped) {
+            let start = segment.offset + addr - segment.start;
+            let end = segment.offset + segment.dumped.min(addr - segment.start + len);
+            return self.data.get(start..end).map(|bytes| bytes.to_vec());
+        }
+        let file = self.files.iter().find(|file| file.start <= addr && addr < file.end)?;
+        let mut bytes = vec![0; len.min(file.end - addr)];
+        let opened = fs::File::open(&file.path).ok()?;
+        opened.read_exact_at(&mut bytes, (file.offset + addr - file.start) as u64).ok()?;
+        Some(bytes)
+    }
+}
+
+impl TargetAccess for Core {
+    fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            match self.read_piece(addr + bytes.len(), len - bytes.len()) {
+                Some(piece) if !piece.is_empty() => bytes.extend(piece),
+                _ => return Err(nix::Error::Sys(Errno::EIO)),
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn registers(&self) -> Result<libc::user_regs_struct, nix::Error> {
+        Ok(self.registers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+
+    /// Where the dumped memory of `core_dump` starts, the stack of its program
+    const STACK: usize = 0x7ffc_0000_0000;
+
+    /// # brief
+    /// Lay out a note of the kind `kind`, named "CORE" as the kernel names them.
+    fn note(kind: usize, description: &[u8]) -> Vec<u8> {
+        let mut note = Vec::new();
+        for word in [5, description.len(), kind] {
+            note.extend((word as u32).to_le_bytes());
+        }
+        note.extend(b"CORE\0\0\0\0");
+        note.extend(description);
+        note.resize((note.len() + 3) & !3, 0);
+        note
+    }
+
+    /// # brief
+    /// A core dump of process 1234 killed by SIGSEGV at `fixture::TEXT + 8`, with a page of
+    /// `file` mapped at `fixture::TEXT` and left out of the dump, and `stack` dumped at `STACK`.
+    fn core_dump(file: &str, stack: &[u8]) -> Vec<u8> {
+        let mut prstatus = vec![0; 336];
+        prstatus[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2].copy_from_slice(&11u16.to_le_bytes());
+        prstatus[PRSTATUS_PID..PRSTATUS_PID + 4].copy_from_slice(&1234u32.to_le_bytes());
+        // rbp, rip and rsp are the registers 4, 16 and 19 of pr_reg
+        for (register, value) in [(4, STACK + 0x20), (16, fixture::TEXT + 8), (19, STACK + 0x10)] {
+            let offset = PRSTATUS_REGS + register * 8;
+            prstatus[offset..offset + 8].copy_from_slice(&(value as u64).to_le_bytes());
+        }
+        let mut mapped = Vec::new();
+        for word in [1, 0x1000, fixture::TEXT, fixture::TEXT + 0x1000, 0] {
+            mapped.extend((word as u64).to_le_bytes());
+        }
+        mapped.extend(file.as_bytes());
+        mapped.push(0);
+        let notes = [note(NT_PRSTATUS, &prstatus), note(NT_FILE, &mapped)].concat();
+
+        let headers = 64 + 3 * 56;
+        let memory = headers + notes.len();
+        let mut core = vec![0; 64];
+        core[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        core[0x10..0x12].copy_from_slice(&(ET_CORE as u16).to_le_bytes());
+        core[0x12..0x14].copy_from_slice(&(EM_X86_64 as u16).to_le_bytes());
+        core[0x20..0x28].copy_from_slice(&64u64.to_le_bytes());
+        core[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        core[0x38..0x3a].copy_from_slice(&3u16.to_le_bytes());
+        let segments = [
+            (PT_NOTE, 4, headers, 0, notes.len(), notes.len()),
+            (PT_LOAD, 4 | 1, memory, fixture::TEXT, 0, 0x1000),
+            (PT_LOAD, 4 | 2, memory, STACK, stack.len(), stack.len()),
+        ];
+        for (kind, flags, offset, start, dumped, size) in segments {
+            core.extend((kind as u32).to_le_bytes());
+            core.extend((flags as u32).to_le_bytes());
+            for word in [offset, start, start, dumped, size, 0x1000] {
+                core.extend((word as u64).to_le_bytes());
+            }
+        }
+        core.extend(notes);
+        core.extend(stack);
+        core
+    }
+
+    /// # brief
+    /// Write a page of code for `core_dump` to map: the bytes 0, 1, 2... in turn.
+    fn text_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("deet-core-{}-{}", std::process::id(), name));
+        let text: Vec<u8> = (0..0x1000).map(|offset| offset as u8).collect();
+        fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn the_state_of_the_crashed_thread_is_read_from_its_note() {
+        let core = Core::parse(core_dump("/usr/bin/prog", &[0; 0x40])).unwrap();
+        assert_eq!((core.pid(), core.signal()), (1234, Some(Signal::SIGSEGV)));
+        let regs = core.registers().unwrap();
+        assert_eq!((regs.rip, regs.rsp, regs.rbp), ((fixture::TEXT + 8) as u64, (STACK + 0x10) as u64, (STACK + 0x20) as u64));
+        assert_eq!(core.executable(), Some("/usr/bin/prog"));
+    }
+
+    #[test]
+    fn memory_is_read_from_the_dump_else_from_the_mapped_file() {
+        let file = text_file("memory");
+        let stack: Vec<u8> = (0..0x40).map(|offset| 0x80 | offset as u8).collect();
+        let core = Core::parse(core_dump(&file, &stack)).unwrap();
+        assert_eq!(core.read_bytes(STACK + 0x3e, 2).unwrap(), [0xbe, 0xbf]);
+        // the code was left out of the dump
+        assert_eq!(core.read_bytes(fixture::TEXT + 0xffe, 2).unwrap(), [0xfe, 0xff]);
+        // past the end of the dumped memory, or where nothing was mapped
+        assert!(matches!(core.read_bytes(STACK + 0x3f, 2), Err(nix::Error::Sys(Errno::EIO))));
+        assert!(core.read_bytes(fixture::TEXT + 0xfff, 2).is_err());
+        assert!(core.read_bytes(0x1000, 1).is_err());
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn the_writable_mapping_holding_the_stack_pointer_is_the_stack() {
+        let core = Core::parse(core_dump("/usr/bin/prog", &[0; 0x40])).unwrap();
+        let mappings: Vec<(usize, String, String)> =
+            core.mappings().into_iter().map(|mapping| (mapping.start, mapping.perms, mapping.path)).collect();
+        assert_eq!(
+            mappings,
+            [(fixture::TEXT, "r-xp".to_string(), "/usr/bin/prog".to_string()), (STACK, "rw-p".to_string(), "[stack]".to_string())]
+        );
+    }
+
+    #[test]
+    fn what_is_not_a_core_dump_is_refused() {
+        let mut executable = core_dump("/usr/bin/prog", &[]);
+        executable[0x10] = 2;
+        assert_eq!(Core::parse(executable).err().unwrap(), "is not a core dump");
+        assert_eq!(Core::parse(b"#!/bin/sh\n".to_vec()).err().unwrap(), "not in executable format: file format not recognized");
+        let mut prstatus_left_out = core_dump("/usr/bin/prog", &[]);
+        prstatus_left_out[0x38] = 0;
+        assert_eq!(Core::parse(prstatus_left_out).err().unwrap(), "no thread state in the core dump");
+    }
+
+    #[test]
+    fn the_core_of_a_crash_shows_the_frame_and_code_it_crashed_in() {
+        let program = fixture::build("crash", &[("crash.c", fixture::CRASH)], &["-g", "-O0", "-no-pie", "-fno-omit-frame-pointer", "crash.c"]);
+        let path = match fixture::dump_core(&program) {
+            Some(path) => path,
+            // the kernel pipes the cores to a program, nothing to read here
+            None => return,
+        };
+        let core = Core::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(core.signal(), Some(Signal::SIGSEGV));
+        assert_eq!(core.executable(), Some(program.as_str()));
+        let regs = core.registers().unwrap();
+        // the only variable of main is right below its frame pointer, in the red zone
+        assert_eq!(core.read_bytes(regs.rbp as usize - 8, 8).unwrap(), 0x1122_3344_5566_7788u64.to_le_bytes());
+        // the code is read from the executable, which a program without PIE maps from 0x400000
+        let code = fs::read(&program).unwrap();
+        let rip = regs.rip as usize;
+        assert_eq!(core.read_bytes(rip, 4).unwrap(), code[rip - 0x400000..rip - 0x400000 + 4]);
+    }
+}
@@ -0,0 +1,176 @@
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the kernel sends the core of a crashed process, by `/proc/sys/kernel/core_pattern`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoreDestination {
+    /// A file, relative to the working directory of the process when the pattern is relative
+    File(String),
+    /// A program the core is piped to, such as systemd-coredump or apport
+    Piped(String),
+    /// The pattern can't be followed, with why
+    Unknown(String),
+}
+
+/// What the specifiers of a pattern stand for
+pub struct Crash<'a> {
+    pub pid: Pid,
+    /// The path of the executable, of which `%e` is the name
+    pub executable: &'a str,
+    /// The time of the crash in seconds since the epoch, for `%t`
+    pub time: u64,
+    /// Whether `/proc/sys/kernel/core_uses_pid` appends the pid to a pattern without `%p`
+    pub uses_pid: bool,
+}
+
+/// # brief
+/// Whether the default action of `signal` dumps a core, see signal(7).
+pub fn dumps_core(signal: Signal) -> bool {
+    match signal {
+        Signal::SIGQUIT | Signal::SIGILL | Signal::SIGTRAP | Signal::SIGABRT | Signal::SIGBUS | Signal::SIGFPE => true,
+        Signal::SIGSEGV | Signal::SIGXCPU | Signal::SIGXFSZ | Signal::SIGSYS => true,
+        _ => false,
+    }
+}
+
+/// # brief
+/// Expand a core pattern for a crash: `%p`, `%P` and `%i`, `%I` are the process and thread id
+/// (deet's inferiors are not in pid namespaces), `%e` is the name of the executable cut to 15
+/// characters as the kernel does, `%t` the time, `%h` the host name, `%u` and `%g` the ids of
+/// deet's user, and `%%` a percent sign.
+///
+/// # param
+/// - `pattern` : The contents of `core_pattern`, the newline aside.
+/// - `crash` : The process that crashed.
+pub fn expand(pattern: &str, crash: &Crash) -> CoreDestination {
+    if let Some(program) = pattern.strip_prefix('|') {
+        return CoreDestination::Piped(program.split_whitespace().next().unwrap_or("").to_string());
+    }
+    if pattern.is_empty() {
+        return CoreDestination::File("core".to_string());
+    }
+    let name: String = Path::new(crash.executable)
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().chars().take(15).collect());
+    let mut path = String::new();
+    let mut has_pid = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('p') | Some('P') | Some('i') | Some('I') => {
+                path.push_str(&crash.pid.to_string());
+                has_pid = true;
+            }
+            Some('e') => path.push_str(&name),
+            Some('t') => path.push_str(&crash.time.to_string()),
+            Some('h') => path.push_str(fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default().trim()),
+            Some('u') => path.push_str(&nix::unistd::getuid().to_string()),
+            Some('g') => path.push_str(&nix::unistd::getgid().to_string()),
+            Some('%') => path.push('%'),
+            Some(other) => return CoreDestination::Unknown(format!("core_pattern uses %{}, which deet can't expand", other)),
+            // the kernel drops a lone % at the end
+            None => {}
+        }
+    }
+    if crash.uses_pid && !has_pid {
+        path.push_str(&format!(".{}", crash.pid));
+    }
+    CoreDestination::File(path)
+}
+
+/// # brief
+/// Find where the core of the process `pid` running `executable`, which just crashed, went.
+pub fn core_destination(pid: Pid, executable: &str) -> CoreDestination {
+    let pattern = match fs::read_to_string("/proc/sys/kernel/core_pattern") {
+        Ok(pattern) => pattern,
+        Err(err) => return CoreDestination::Unknown(format!("core_pattern can't be read: {}", err)),
+    };
+    let crash = Crash {
+        pid,
+        executable,
+        time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+        uses_pid: fs::read_to_string("/proc/sys/kernel/core_uses_pid").map_or(false, |value| value.trim() == "1"),
+    };
+    expand(pattern.trim_end_matches('\n'), &crash)
+}
+
+/// # brief
+/// Explain why a crash that should have dumped a core didn't, with `set coredump on`.
+pub fn explain_missing_core(pid: Pid, executable: &str) -> String {
+    match core_destination(pid, executable) {
+        CoreDestination::Piped(program) => format!("core_pattern pipes cores to {}, which decides whether to keep them", program),
+        CoreDestination::Unknown(reason) => reason,
+        CoreDestination::File(path) => {
+            let dir = Path::new(&path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let suid_dumpable = fs::read_to_string("/proc/sys/fs/suid_dumpable").map_or(false, |value| value.trim() != "0");
+            if nix::unistd::access(dir, nix::unistd::AccessFlags::W_OK).is_err() {
+                format!("{} is not writable, so {} can't be created", dir.display(), path)
+            } else if !suid_dumpable {
+                format!(
+                    "the kernel writes no core for a program that is not readable or changed its credentials, see fs.suid_dumpable; it would be {}",
+                    path
+                )
+            } else {
+                format!("the file system refused {}, or a file of that name can't be replaced", path)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crash(uses_pid: bool) -> Crash<'static> {
+        Crash { pid: Pid::from_raw(4242), executable: "/usr/local/bin/a-rather-long-program-name", time: 1700000000, uses_pid }
+    }
+
+    #[test]
+    fn expands_specifiers() {
+        let cases = [
+            ("core", "core"),
+            ("core.%p", "core.4242"),
+            ("core.%P.%i.%I", "core.4242.4242.4242"),
+            ("/var/crash/%e-%t", "/var/crash/a-rather-long-p-1700000000"),
+            ("100%%-%p", "100%-4242"),
+            ("trailing%", "trailing"),
+        ];
+        for (pattern, expected) in cases.iter() {
+            assert_eq!(expand(pattern, &crash(false)), CoreDestination::File(expected.to_string()), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn empty_pattern_is_core() {
+        assert_eq!(expand("", &crash(false)), CoreDestination::File("core".to_string()));
+    }
+
+    #[test]
+    fn piped_pattern_names_the_program() {
+        assert_eq!(
+            expand("|/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h", &crash(false)),
+            CoreDestination::Piped("/usr/lib/systemd/systemd-coredump".to_string())
+        );
+    }
+
+    #[test]
+    fn core_uses_pid_appends_the_pid_once() {
+        assert_eq!(expand("core", &crash(true)), CoreDestination::File("core.4242".to_string()));
+        assert_eq!(expand("core.%p", &crash(true)), CoreDestination::File("core.4242".to_string()));
+    }
+
+    #[test]
+    fn unknown_specifier_is_reported() {
+        match expand("core.%z", &crash(false)) {
+            CoreDestination::Unknown(reason) => assert!(reason.contains("%z"), "{}", reason),
+            other => panic!("expected Unknown, got {:?}", other),
+        }
+    }
+}
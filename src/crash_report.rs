@@ -0,0 +1,268 @@
+use crate::core_pattern::{self, CoreDestination, Crash};
+use crate::output::{Line, Stream};
+use nix::unistd::Pid;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The lines of captured output a report ends with
+pub const OUTPUT_LINES: usize = 100;
+
+/// The innermost frames whose variables a report shows
+pub const FRAMES_WITH_LOCALS: usize = 3;
+
+/// A frame of a report, with its variables
+pub struct ReportFrame {
+    /// The function and line, as `backtrace` shows them
+    pub description: String,
+    /// The names and values of the variables, empty for an inlined frame or a frame without
+    /// debug information
+    pub variables: Vec<(String, String)>,
+}
+
+/// What `set crash-report` writes for a crash, so that a run nobody watched, such as one of
+/// `run --until-failure`, can be looked into afterwards. The stack and the registers are only
+/// known for a stop by a signal; after an exit, the process is gone.
+pub struct CrashReport {
+    /// The target and the arguments of the run
+    pub argv: Vec<String>,
+    pub pid: Pid,
+    /// How the run stopped or ended, as `run --until-failure` summarizes it
+    pub status: String,
+    /// The name of the signal it stopped or died by
+    pub signal: Option<String>,
+    /// The address of the fault, from the siginfo of the signal
+    pub fault_address: Option<usize>,
+    /// The lines of `backtrace`
+    pub backtrace: Vec<String>,
+    /// The innermost frames, at most `FRAMES_WITH_LOCALS`
+    pub frames: Vec<ReportFrame>,
+    /// The last lines of the captured output, at most `OUTPUT_LINES`
+    pub output: Vec<Line>,
+    pub registers: Vec<(&'static str, u64)>,
+}
+
+impl CrashReport {
+    /// # brief
+    /// Render the report as text, one section after the other.
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("deet crash report\n");
+        text.push_str(&format!("argv: {}\n", self.argv.join(" ")));
+        text.push_str(&format!("pid: {}\n", self.pid));
+        text.push_str(&format!("status: {}\n", self.status));
+        if let Some(signal) = &self.signal {
+            text.push_str(&format!("signal: {}\n", signal));
+        }
+        if let Some(addr) = self.fault_address {
+            text.push_str(&format!("fault address: {:#x}\n", addr));
+        }
+        if !self.backtrace.is_empty() {
+            text.push_str("\nbacktrace:\n");
+            for line in self.backtrace.iter() {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+        for (index, frame) in self.frames.iter().enumerate() {
+            text.push_str(&format!("\nlocals of frame #{}, {}:\n", index, frame.description));
+            if frame.variables.is_empty() {
+                text.push_str("  (none known)\n");
+            }
+            for (name, value) in frame.variables.iter() {
+                text.push_str(&format!("  {} = {}\n", name, value));
+            }
+        }
+        text.push_str(&format!("\noutput (last {} lines):\n", OUTPUT_LINES));
+        if self.output.is_empty() {
+            text.push_str("  (none captured)\n");
+        }
+        // the marks of `info output`
+        for line in self.output.iter() {
+            match line.stream {
+                Stream::Stdout => text.push_str(&format!("{:>6}  {}\n", line.seq, line.text)),
+                Stream::Stderr => text.push_str(&format!("{:>6}! {}\n", line.seq, line.text)),
+            }
+        }
+        if !self.registers.is_empty() {
+            text.push_str("\nregisters:\n");
+            for (name, value) in self.registers.iter() {
+                text.push_str(&format!("  {:<10}{:#x}\n", name, value));
+            }
+        }
+        text
+    }
+
+    /// # brief
+    /// Render the report as a JSON object with the same sections. Addresses and register
+    /// values are hexadecimal strings, a 64-bit value doesn't fit a JSON number everywhere.
+    pub fn to_json(&self) -> String {
+        let strings = |items: &[String]| items.iter().map(|item| json_string(item)).collect::<Vec<_>>().join(", ");
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        let frames: Vec<String> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                let variables: Vec<String> = frame
+                    .variables
+                    .iter()
+                    .map(|(name, value)| format!("{{\"name\": {}, \"value\": {}}}", json_string(name), json_string(value)))
+                    .collect();
+                format!("{{\"frame\": {}, \"locals\": [{}]}}", json_string(&frame.description), variables.join(", "))
+            })
+            .collect();
+        let output: Vec<String> = self
+            .output
+            .iter()
+            .map(|line| format!("{{\"stream\": \"{}\", \"text\": {}}}", line.stream, json_string(&line.text)))
+            .collect();
+        let registers: Vec<String> =
+            self.registers.iter().map(|(name, value)| format!("\"{}\": \"{:#x}\"", name, value)).collect();
+        let fields = [
+            format!("\"argv\": [{}]", strings(&self.argv)),
+            format!("\"pid\": {}", self.pid),
+            format!("\"status\": {}", json_string(&self.status)),
+            format!("\"signal\": {}", optional(self.signal.as_ref().map(|signal| json_string(signal)))),
+            format!("\"fault_address\": {}", optional(self.fault_address.map(|addr| format!("\"{:#x}\"", addr)))),
+            format!("\"backtrace\": [{}]", strings(&self.backtrace)),
+            format!("\"frames\": [{}]", frames.join(", ")),
+            format!("\"output\": [{}]", output.join(", ")),
+            format!("\"registers\": {{{}}}", registers.join(", ")),
+        ];
+        format!("{{\n  {}\n}}\n", fields.join(",\n  "))
+    }
+}
+
+/// # brief
+/// Quote a string for JSON.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// # brief
+/// Expand the path of `set crash-report` for a crash of the process `pid`, with the
+/// specifiers of a core pattern: `%p` the pid, `%t` the time, `%e` the name of the executable,
+/// `%%` a percent sign.
+///
+/// # return
+/// * The path, or `Err(String)` if the pattern can't be expanded.
+pub fn report_path(pattern: &str, pid: Pid, executable: &str) -> Result<String, String> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    match core_pattern::expand(pattern, &Crash { pid, executable, time, uses_pid: false }) {
+        CoreDestination::File(path) => Ok(path),
+        CoreDestination::Piped(_) => Err("a crash report can't be piped to a program".to_string()),
+        CoreDestination::Unknown(_) => Err(format!("{} has a specifier deet can't expand", pattern)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// The report of a stop by SIGSEGV in `parse`, called by `main`.
+    fn segfault() -> CrashReport {
+        CrashReport {
+            argv: vec!["./prog".to_string(), "in put".to_string()],
+            pid: Pid::from_raw(42),
+            status: "stopped by SIGSEGV".to_string(),
+            signal: Some("SIGSEGV".to_string()),
+            fault_address: Some(0x10),
+            backtrace: vec!["#0 parse (main.c:12)".to_string(), "#1 main (main.c:30)".to_string()],
+            frames: vec![
+                ReportFrame { description: "parse (main.c:12)".to_string(), variables: vec![("line".to_string(), "0x0".to_string())] },
+                ReportFrame { description: "main (main.c:30)".to_string(), variables: Vec::new() },
+            ],
+            output: vec![
+                Line { seq: 1, stream: Stream::Stdout, text: "reading \"in put\"".to_string() },
+                Line { seq: 2, stream: Stream::Stderr, text: "warning".to_string() },
+            ],
+            registers: vec![("rip", 0x401136), ("rsp", 0x7ffc0000)],
+        }
+    }
+
+    #[test]
+    fn text_report_has_every_section() {
+        let text = segfault().to_text();
+        let expected = "deet crash report
+argv: ./prog in put
+pid: 42
+status: stopped by SIGSEGV
+signal: SIGSEGV
+fault address: 0x10
+
+backtrace:
+  #0 parse (main.c:12)
+  #1 main (main.c:30)
+
+locals of frame #0, parse (main.c:12):
+  line = 0x0
+
+locals of frame #1, main (main.c:30):
+  (none known)
+
+output (last 100 lines):
+     1  reading \"in put\"
+     2! warning
+
+registers:
+  rip       0x401136
+  rsp       0x7ffc0000
+";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn text_report_of_an_exit_has_no_stack() {
+        let report = CrashReport {
+            status: "exited with status 1".to_string(),
+            signal: None,
+            fault_address: None,
+            backtrace: Vec::new(),
+            frames: Vec::new(),
+            output: Vec::new(),
+            registers: Vec::new(),
+            ..segfault()
+        };
+        assert_eq!(
+            report.to_text(),
+            "deet crash report\nargv: ./prog in put\npid: 42\nstatus: exited with status 1\n\noutput (last 100 lines):\n  (none captured)\n"
+        );
+        assert!(report.to_json().contains("\"signal\": null,\n  \"fault_address\": null,"));
+    }
+
+    #[test]
+    fn json_report_quotes_strings_and_hex_values() {
+        let json = segfault().to_json();
+        assert!(json.starts_with("{\n  \"argv\": [\"./prog\", \"in put\"],\n  \"pid\": 42,\n"));
+        assert!(json.contains("\"fault_address\": \"0x10\""));
+        assert!(json.contains("{\"frame\": \"parse (main.c:12)\", \"locals\": [{\"name\": \"line\", \"value\": \"0x0\"}]}"));
+        assert!(json.contains("{\"stream\": \"stdout\", \"text\": \"reading \\\"in put\\\"\"}"));
+        assert!(json.contains("\"registers\": {\"rip\": \"0x401136\", \"rsp\": \"0x7ffc0000\"}"));
+        assert!(json.ends_with("}\n"));
+    }
+
+    #[test]
+    fn json_strings_escape_controls() {
+        assert_eq!(json_string("a\"b\\c\nd\te\u{1}"), "\"a\\\"b\\\\c\\nd\\te\\u0001\"");
+    }
+
+    #[test]
+    fn report_paths_expand_the_pid_and_refuse_pipes() {
+        let pid = Pid::from_raw(42);
+        assert_eq!(report_path("/tmp/crash-%e-%p.txt", pid, "/bin/prog"), Ok("/tmp/crash-prog-42.txt".to_string()));
+        let path = report_path("%t", pid, "prog").unwrap();
+        assert!(path.parse::<u64>().is_ok(), "{}", path);
+        assert_eq!(report_path("|/bin/cat", pid, "prog"), Err("a crash report can't be piped to a program".to_string()));
+        assert_eq!(report_path("%z", pid, "prog"), Err("%z has a specifier deet can't expand".to_string()));
+    }
+}
@@ -1,26 +1,571 @@
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::inferior::{Inferior,Status};
-use crate::debugger_command::DebuggerCommand;
-use crate::dwarf_data::{DwarfData, Error as DwarfError};
+use crate::inferior::{self, FdInfo, Frame, Inferior, PatchGuard, Stack, Status, TraceError};
+use crate::debugger_command::{self, DebuggerCommand};
+use crate::dwarf_data::{
+    DwarfData, Error as DwarfError, File, FrameBase, Function, Line, Location, Member, Type, TypeDefinition, TypeKind, Variable,
+};
+use crate::source::{self, SubstitutePaths};
+use crate::expr::{self, Expr, Scope};
+use crate::registers;
+use crate::maps::{self, HeapUsage, SharedLibrary};
+use crate::snapshot::{self, Snapshot};
+use crate::hint::DeetHelper;
+use crate::tui::SourcePane;
+use crate::breakpoint::{self, Breakpoint, Change, Dprintf, Mutation, Qualifiers, Resolution};
+use crate::pretty::{self, ValueReader};
+use crate::wait_for;
+use crate::preflight;
+use crate::regex::Regex;
+use crate::skip::Skip;
+use crate::disasm::{self, Boundary};
+use crate::remote::{self, Remote};
+use crate::target::TargetAccess;
+use crate::ui::{self, Pager, Table};
+use crate::output::{Capture, Stream};
+use crate::core_pattern::{self, CoreDestination};
+use crate::stats;
+use crate::symtab::SymbolTable;
+use crate::syscalls;
+use crate::cancel::CancelToken;
+use crate::auxv;
+use crate::runs::{Outcome, Run};
+use crate::crash_report::{self, CrashReport, ReportFrame};
+use crate::context::{Context, Identity};
+use crate::core_file::Core;
+use crate::session::{SavedBreakpoint, Session};
+
+/// The startup choices given on the deet command line
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// The path to the target program
+    pub target: String,
+    /// The default arguments of the target, as set by `set args`
+    pub args: Vec<String>,
+    /// A process to attach to right away
+    pub pid: Option<i32>,
+    /// A core dump to inspect instead of a process, `--core`
+    pub core: Option<String>,
+    /// Commands executed before the prompt, given with `-ex`
+    pub commands: Vec<String>,
+    /// Quit after the `-ex` commands instead of prompting
+    pub batch: bool,
+    /// Start with the source pane enabled
+    pub tui: bool,
+    /// A session saved by `save session` to restore before the `-ex` commands
+    pub session: Option<String>,
+    /// Wait for a process running this executable and attach to it
+    pub wait_for: Option<String>,
+    /// The time between two scans for that process, and how long to wait at most
+    pub wait_interval: Option<Duration>,
+    pub wait_timeout: Option<Duration>,
+    /// Leave out the startup messages and the progress of loading, `-q`
+    pub quiet: bool,
+    /// Make the output the same every run, for golden tests: no address space randomization,
+    /// no times, and addresses relative to the executable
+    pub deterministic: bool,
+    /// Refuse everything that would change the program, `--read-only`
+    pub read_only: bool,
+}
+
+/// The per-process state of an inferior that is not selected. The selected inferior's state
+/// lives directly in the `Debugger` fields of the same names, and is swapped with one of these
+/// by `inferior <n>`.
+struct InferiorState {
+    id: usize,
+    target: String,
+    args: Vec<String>,
+    inferior: Option<Inferior>,
+    remote: Option<Remote>,
+    core: Option<Core>,
+    debug_data: DwarfData,
+    target_mtime: Option<SystemTime>,
+    breakpoints: HashMap<usize, u8>,
+    traps_installed: bool,
+    breakpoint_list: Vec<Breakpoint>,
+    breakpoint_history: VecDeque<Mutation>,
+    step_over_points: HashMap<usize, u8>,
+    current_line: Option<Line>,
+    snapshots: HashMap<String, Snapshot>,
+    heap_at_stops: (Option<HeapUsage>, Option<HeapUsage>),
+    last_stop: Option<(String, Line, usize)>,
+    libraries: Vec<SharedLibrary>,
+}
+
+/// An entry of the value history, `$N`
+#[derive(Clone)]
+struct HistoryValue {
+    /// The value, or the address of an aggregate
+    value: u64,
+    /// The type of a source variable, `None` for the result of an expression
+    ty: Option<Type>,
+    /// The bytes of a source variable as read
+    bytes: Vec<u8>,
+    /// Where a source variable lives in memory, read again by `print $N`
+    addr: Option<usize>,
+    /// The process the value was read from, to tell values of an earlier run
+    pid: Option<nix::unistd::Pid>,
+}
+
+/// Where the value of a variable is at the current stop
+enum Place {
+    Memory(usize),
+    /// The value itself, read from a register
+    Register(u64),
+}
+
+/// # brief
+/// Find where the value of a variable is at `pc`, reading registers with `register`.
+///
+/// # param
+/// - `var` : The variable.
+/// - `func` : The function owning it, `None` for a global.
+/// - `pc` : The pc picking the entry of a location list.
+/// - `register` : Reads a register by DWARF register number.
+fn variable_place(
+    var: &Variable,
+    func: Option<&Function>,
+    pc: usize,
+    register: &dyn Fn(u16) -> Result<u64, String>,
+) -> Result<Place, String> {
+    match var.location.at(pc) {
+        Location::Address(addr) => Ok(Place::Memory(addr)),
+        Location::FramePointerOffset(offset) => match func.map_or(FrameBase::Cfa, |func| func.frame_base) {
+            // the canonical frame address is 16 bytes above the saved %rbp
+            FrameBase::Cfa => register(DWARF_RBP).map(|rbp| Place::Memory((rbp as isize + 16 + offset) as usize)),
+            FrameBase::Register(regnum, base) => {
+                register(regnum).map(|value| Place::Memory((value as i64 + base + offset as i64) as usize))
+            }
+        },
+        Location::Register(regnum) => register(regnum).map(Place::Register),
+        Location::RegisterOffset(regnum, offset) => {
+            register(regnum).map(|value| Place::Memory((value as i64 + offset) as usize))
+        }
+        Location::List(_) => Err("nested location list".to_string()),
+        Location::Unavailable(reason) => Err(reason),
+    }
+}
+
+/// # brief
+/// Read a register by DWARF register number in a frame of the backtrace. All registers are
+/// known in the innermost frame, `None` being frame 0, the outer frames only know their %rbp.
+///
+/// # param
+/// - `regs` : The registers of frame 0.
+/// - `frame` : The frame.
+/// - `regnum` : The DWARF register number.
+fn frame_register(regs: &libc::user_regs_struct, frame: Option<&Frame>, regnum: u16) -> Result<u64, String> {
+    match frame {
+        Some(frame) if !frame.innermost => {
+            if regnum == DWARF_RBP {
+                Ok(frame.rbp as u64)
+            } else {
+                Err("not saved in this frame".to_string())
+            }
+        }
+        _ => registers::get_dwarf_register(regs, regnum).ok_or(format!("unknown DWARF register {}", regnum)),
+    }
+}
+
+/// # brief
+/// Find the address of an address breakpoint anchored at `offset` bytes into the function
+/// `name`, among the functions of that name in the program loaded again.
+///
+/// # return
+/// * `Resolved` when a single function is still long enough, `Pending` when there is none and
+/// `Broken` otherwise.
+fn reanchor(name: &str, offset: usize, functions: &[&Function]) -> Resolution {
+    match functions {
+        [func] if offset < func.text_length => Resolution::Resolved(func.address + offset),
+        [func] => Resolution::Broken(format!("{} is now shorter than offset {:#x} ({} bytes)", name, offset, func.text_length)),
+        [] => Resolution::Pending,
+        _ => Resolution::Broken(format!("function {} is ambiguous", name)),
+    }
+}
+
+/// # brief
+/// Count a stop among the stops at the same line just before it, for `set print
+/// repeated-stops off`. A stop is the same as the previous one when the report shows the same:
+/// the function and the line, wherever in the line the program stopped.
+///
+/// # param
+/// - `last` : The previous stop, with how many times in a row it was reported.
+/// - `line` : The line of the new stop.
+/// - `func` : The function of the new stop.
+///
+/// # return
+/// * The new stop with its count, or `None` for a stop without a line or a function, which is
+/// never the same as another one.
+fn count_stop(last: Option<(String, Line, usize)>, line: Option<&Line>, func: Option<&str>) -> Option<(String, Line, usize)> {
+    let (line, func) = (line?, func?);
+    let line = Line { address: 0, is_stmt: false, ..line.clone() };
+    let repeats = match last {
+        Some((last_func, last_line, count)) if last_func == func && last_line == line => count + 1,
+        _ => 1,
+    };
+    Some((func.to_string(), line, repeats))
+}
+
+/// # brief
+/// Compare the descriptors open at `fd baseline` with those open now. A number reused for
+/// another file shows as closed and opened.
+///
+/// # return
+/// * The descriptors closed (`-`) and opened (`+`) since, by number, the closed one first.
+fn fd_changes<'a>(before: &'a [FdInfo], now: &'a [FdInfo]) -> Vec<(&'static str, &'a FdInfo)> {
+    let same = |a: &FdInfo, b: &FdInfo| a.fd == b.fd && a.target == b.target;
+    let mut changes: Vec<(&str, &FdInfo)> = before
+        .iter()
+        .filter(|old| !now.iter().any(|new| same(old, new)))
+        .map(|old| ("-", old))
+        .chain(now.iter().filter(|new| !before.iter().any(|old| same(old, new))).map(|new| ("+", new)))
+        .collect();
+    changes.sort_by_key(|(mark, fd)| (fd.fd, *mark == "+"));
+    changes
+}
+
+/// # brief
+/// Read the `on` or `off` of a `set` command, a missing one meaning `on`; anything else is
+/// reported.
+///
+/// # return
+/// * The setting, `None` for neither.
+fn parse_on_off(arg: Option<&String>) -> Option<bool> {
+    match arg.map(|s| s.as_str()) {
+        Some("on") | None => Some(true),
+        Some("off") => Some(false),
+        Some(other) => {
+            println!("\"on\" or \"off\" expected, not \"{}\".", other);
+            None
+        }
+    }
+}
+
+/// # brief
+/// Check whether the code at `addr` is the first instruction of a function `step --into`
+/// should enter: the function `name`, or without a name any function with line information
+/// that is not skipped.
+fn enters_function(debug_data: &DwarfData, skips: &[Skip], addr: usize, name: Option<&str>) -> bool {
+    let (file, func) = match debug_data.function_containing(addr) {
+        Some(found) => found,
+        None => return false,
+    };
+    if func.address != addr {
+        return false;
+    }
+    match name {
+        Some(name) => func.name == name,
+        None => {
+            let file = match debug_data.line_at(addr as u64) {
+                Some(line) => line.file,
+                None => file.name.clone(),
+            };
+            !skips.iter().any(|skip| skip.matches(&func.name, &file))
+        }
+    }
+}
+
+/// # brief
+/// Describe where a variable is stored, for `info scope` and `info address`.
+fn describe_location(location: &Location) -> String {
+    let register = |regnum: &u16| match registers::dwarf_register_name(*regnum) {
+        Some(name) => format!("${}", name),
+        None => format!("DWARF register {}", regnum),
+    };
+    match location {
+        Location::FramePointerOffset(offset) => format!("at frame offset {}", offset),
+        Location::Address(addr) => format!("at address {:#x}", addr),
+        Location::Register(regnum) => format!("in register {}", register(regnum)),
+        Location::RegisterOffset(regnum, offset) => format!("at {}{:+}", register(regnum), offset),
+        Location::List(entries) => {
+            let ranges: Vec<String> = entries
+                .iter()
+                .map(|(range, location)| format!("{} in {:#x}-{:#x}", describe_location(location), range.start, range.end))
+                .collect();
+            format!("at a location depending on the pc: {}", ranges.join("; "))
+        }
+        Location::Unavailable(reason) => format!("has no location ({})", reason),
+    }
+}
+
+/// # brief
+/// Get the modification time of a target program, to notice when it is rebuilt.
+fn modification_time(target: &str) -> Option<SystemTime> {
+    std::fs::metadata(target).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// # brief
+/// Get the path of the executable of a process, which keeps working after the process exited.
+fn executable_of(pid: nix::unistd::Pid) -> String {
+    let link = format!("/proc/{}/exe", pid);
+    std::fs::read_link(&link).map_or(link, |path| path.to_string_lossy().into_owned())
+}
+
+/// # brief
+/// Load the debug information of a target program.
+fn load_debug_data(target: &str) -> Result<DwarfData, String> {
+    preflight::check_target(target)?;
+    match DwarfData::from_file(target) {
+        Ok(val) => Ok(val),
+        Err(DwarfError::ErrorOpeningFile) => Err(format!("Could not open file {}", target)),
+        Err(DwarfError::DwarfFormatError(err)) => {
+            Err(format!("Could not debugging system from {}: {:?}", target, err))
+        }
+    }
+}
+
+/// # brief
+/// Apply the changes of `set environment` and `unset environment` to an environment.
+///
+/// # param
+/// - `environment` : The variables and their values, e.g. those of deet.
+/// - `changes` : The variables to set, or to remove (`None`).
+///
+/// # return
+/// * The variables of the program, sorted by name.
+fn program_environment(mut environment: Vec<(String, String)>, changes: &[(String, Option<String>)]) -> Vec<(String, String)> {
+    for (name, value) in changes {
+        environment.retain(|(set, _)| set != name);
+        if let Some(value) = value {
+            environment.push((name.clone(), value.clone()));
+        }
+    }
+    environment.sort();
+    environment
+}
+
+/// Where a new process first stops
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FirstStop {
+    /// At a breakpoint, as after `run`
+    Breakpoint,
+    /// At `main`, or the best place found instead, see `start_address`
+    Main,
+    /// At the first instruction of the program, `AT_ENTRY`
+    Entry,
+}
+
+/// What was picked from the menu of `choose`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Choice {
+    /// `0`, every match
+    All,
+    /// One match, by its index from 0
+    One(usize),
+}
+
+/// A `continue until <expr>` going on: the stops at the trap it started from are skipped while
+/// the expression is false
+struct Until {
+    addr: usize,
+    condition: String,
+    skipped: usize,
+}
 
 pub struct Debugger {
+    /// The number of the selected inferior
+    inferior_id: usize,
+    /// The inferiors that are not selected, see `InferiorState`
+    other_inferiors: Vec<InferiorState>,
     /// The path to the target program
     target: String,
+    /// The arguments passed to the target by `run` without arguments
+    args: Vec<String>,
     /// The path to the history filefor command history
     history_path: String,
     /// The readline editor for user input
-    readline: Editor<()>,
+    readline: Editor<DeetHelper>,
+    /// The addresses recently shown by `print`, `x` and `break`, most recent first
+    recent_addresses: Vec<usize>,
+    /// The source pane of `tui enable`
+    tui: SourcePane,
     /// The currently running inferior process
     inferior: Option<Inferior>,
+    /// The connection of `target remote`, used instead of `inferior` while it is open
+    remote: Option<Remote>,
+    /// The core dump of `--core`, inspected until a process is started or attached to
+    core: Option<Core>,
     /// The debug data obtained from the target program's DWARF information
     debug_data: DwarfData,
+    /// The modification time of the target when its debug data was loaded
+    target_mtime: Option<SystemTime>,
     /// The breakpoints set in the target program.
     breakpoints: HashMap<usize, u8>,
+    /// Whether the traps of `breakpoints` are in the memory of the inferior, and so their saved
+    /// bytes are its original instructions. Off from the end of a process until the traps are
+    /// installed in the next one, which reads the original bytes anew.
+    traps_installed: bool,
+    /// The records of the breakpoints, listed by `info break` and re-resolved by `file`
+    breakpoint_list: Vec<Breakpoint>,
+    /// The latest changes of the breakpoints, see `info break history` and `break undo`
+    breakpoint_history: VecDeque<Mutation>,
     /// The softirq for step over
     step_over_points: HashMap<usize, u8>,
+    /// The `set substitute-path` rules for locating source files
+    source_paths: SubstitutePaths,
+    /// The source line of the last stop, used as the current source file
+    current_line: Option<Line>,
+    /// The convenience variables (`$foo`) set by the user
+    convenience: HashMap<String, u64>,
+    /// The values printed by `print`, referenced as `$1`, `$2`, ...
+    value_history: Vec<HistoryValue>,
+    /// The pretty-printers of `print`, and whether they are used (`set print pretty`)
+    printers: pretty::Registry,
+    pretty_printing: bool,
+    /// The commands executed every time the inferior stops
+    stop_hook: Vec<String>,
+    /// The expressions of `display`, printed every time the inferior stops
+    displays: Vec<String>,
+    /// The variables the program gets on top of deet's environment (`set environment`), or
+    /// without which it runs (`unset environment`, `None`)
+    environment: Vec<(String, Option<String>)>,
+    /// Set while a command list is replayed, so that it can't trigger itself again
+    replaying: bool,
+    /// The command line being executed, recorded with the breakpoint changes it makes
+    command_line: String,
+    /// The memory snapshots taken by `snapshot`, by name
+    snapshots: HashMap<String, Snapshot>,
+    /// The heap usage measured at the last stop and at the stop before, for `info heap`
+    heap_at_stops: (Option<HeapUsage>, Option<HeapUsage>),
+    /// The function and line of the last stop reported, and how many stops in a row were there
+    last_stop: Option<(String, Line, usize)>,
+    /// The shared libraries mapped, read again when the dynamic linker changes them (see
+    /// `plant_library_hook`), to report the ones loaded or unloaded (`set print solib-events`)
+    /// and resolve the breakpoints pending on them
+    libraries: Vec<SharedLibrary>,
+    /// The symbols of the shared libraries seen, by path
+    library_symbols: HashMap<String, SymbolTable>,
+    /// The recorded file and the first and last line printed by the last `list`, cleared by a
+    /// stop so that `list` shows the stop location again
+    list_position: Option<(String, usize, usize)>,
+    /// Whether each of several stops in a row at the same line is reported in full (`set print
+    /// repeated-stops`)
+    print_repeated_stops: bool,
+    /// Whether printed pointers and `x` words are followed by the symbol they point into (`set
+    /// print symbol`)
+    print_symbol: bool,
+    /// Whether the inferiors started from now on have their output recorded (`set
+    /// capture-output`)
+    capture_output: bool,
+    /// The output recorded from the inferiors, see `info output`
+    output: Capture,
+    /// The sequence number of the first output line not counted by a stop report yet
+    output_reported: u64,
+    /// Whether stop reports say how much the inferior wrote since the last one (`set print
+    /// output-summary`)
+    print_output_summary: bool,
+    /// Whether the shared libraries loaded and unloaded are reported as the dynamic linker
+    /// changes them (`set print solib-events`)
+    print_solib_events: bool,
+    /// Whether the wall-clock time of every command is reported (`set print command-time`)
+    print_command_time: bool,
+    /// Where a crash of the program is reported, a path with `%p` and `%t` specifiers (`set
+    /// crash-report`), and whether the report is JSON rather than text
+    crash_report: Option<(String, bool)>,
+    /// The last process a crash report was written for, so its death by the signal it was
+    /// reported stopped by is not reported again
+    crash_reported: Option<nix::unistd::Pid>,
+    /// The descriptors open at `fd baseline`, with the process they were open in
+    fd_baseline: Option<(nix::unistd::Pid, Vec<FdInfo>)>,
+    /// The selected frame and the registers of the selected thread at this stop, which the
+    /// inspection commands read
+    context: Context,
+    /// The number of the thread of the last stop, to tell when another thread stops
+    stopped_thread: Option<usize>,
+    /// The most elements of an array, string or collection `print` shows, and of units `x`
+    /// shows, 0 for no limit (`set print elements`)
+    print_elements: usize,
+    /// The number of breakpoints `rbreak` sets without asking first (`set rbreak-limit`)
+    rbreak_limit: usize,
+    /// The launches of the program in this session, see `info runs`
+    runs: Vec<Run>,
+    /// Whether `step` stops in the PLT stub of a library call rather than running the call
+    /// (`set step-into-plt`)
+    step_into_plt: bool,
+    /// Whether the inferior stops after it executes a new program, to set breakpoints in it
+    /// (`set follow-exec-mode stop`), or goes on (`continue`)
+    exec_stops: bool,
+    /// The functions and files `step --into` doesn't enter, see `skip`
+    skips: Vec<Skip>,
+    frame_arguments: FrameArguments,
+    /// The commands given with `-ex`, and whether to quit after them
+    startup_commands: Vec<String>,
+    batch: bool,
+    /// Whether the session only observes the program, see `read_only_refusal`
+    read_only: bool,
+    /// The number of `assert`s that held, and the failed ones with the stop location
+    assertions_passed: usize,
+    assertion_failures: Vec<String>,
+    /// The time between two scans of `wait-attach`
+    wait_interval: Duration,
+    /// Set when the last resume was cut short by `--timeout`, to the duration given
+    timeout_stop: Option<Duration>,
+    /// Set for the time of a `continue until`
+    until: Option<Until>,
+    /// Whether the prompt hints still miss the function names, which are only known once the
+    /// debug information finished loading
+    hint_functions_pending: bool,
+    /// The exit status given to `quit`
+    quit_code: Option<i32>,
+}
+
+/// The most changed ranges `compare` prints
+const MAX_PRINTED_CHANGES: usize = 32;
+
+/// The widest the line between the program output and a stop report is
+const OUTPUT_SEPARATOR_WIDTH: usize = 40;
+
+/// Why a command is refused in a `--read-only` session
+const READ_ONLY_ERROR: &str = "read-only session";
+
+/// The number of addresses kept for the prompt hints
+const MAX_RECENT_ADDRESSES: usize = 16;
+
+/// The number of runs of `run --until-failure` without an explicit maximum
+const DEFAULT_MAX_RUNS: usize = 100;
+
+/// The number of breakpoints `rbreak` sets without asking first
+const DEFAULT_RBREAK_LIMIT: usize = 100;
+
+/// The number of source lines `list` prints
+const LIST_LINES: usize = 10;
+
+/// The breakpoints above which `install_breakpoints` shows its progress
+const INSTALL_PROGRESS_THRESHOLD: usize = 50;
+
+/// The DWARF register number of %rbp
+const DWARF_RBP: u16 = 6;
+
+/// The longest value of an argument shown in a backtrace, longer ones are cut
+const MAX_ARGUMENT_LEN: usize = 60;
+
+/// The number of value history entries `show values` lists
+const MAX_SHOWN_VALUES: usize = 10;
+
+/// Set while a command runs, when a panic is caught and ends the command only, see
+/// `in_command`
+static COMMAND_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// # brief
+/// Whether a command is running, so that a panic now is caught and the session goes on.
+pub fn in_command() -> bool {
+    COMMAND_RUNNING.load(Ordering::SeqCst)
+}
+
+
+/// Which arguments backtrace frames show (`set print frame-arguments`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameArguments {
+    None,
+    /// Scalars only, aggregates show as `...`
+    Scalars,
+    All,
 }
 
 impl Debugger {
@@ -28,68 +573,180 @@ impl Debugger {
     /// Creates a new debugger 
     ///
     /// # param
-    /// - `target` : The path to the target program.
+    /// - `options` : The target program and the startup choices from the command line.
     ///
     /// # return
-    /// * A new Debug Object
+    /// * A new Debug Object, or `Err(String)` if the process to debug can't be found or
+    /// attached to, or the target can't be loaded.
     ///
-    pub fn new(target: &str) -> Self {
-        let debug_data = match DwarfData::from_file(target) {
-            Ok(val) => val,
-            Err(DwarfError::ErrorOpeningFile) => {
-                println!("Could not open file {}", target);
-                std::process::exit(1);
+    pub fn new(options: &Options) -> Result<Self, String> {
+        let mut target = options.target.clone();
+        let mut attach_to = options.pid.map(nix::unistd::Pid::from_raw);
+        let wait_interval = options.wait_interval.unwrap_or(wait_for::DEFAULT_INTERVAL);
+        if let Some(name) = &options.wait_for {
+            match wait_for::wait_for_process(name, wait_interval, options.wait_timeout) {
+                Ok(pid) => {
+                    if target.is_empty() {
+                        target = executable_of(pid);
+                    }
+                    attach_to = Some(pid);
+                }
+                Err(err) => return Err(err.to_string()),
             }
-            Err(DwarfError::DwarfFormatError(err)) => {
-                println!("Could not debugging system from {}: {:?}", target, err);
-                std::process::exit(1);
+        }
+        // attach before loading the debug information, which takes a while
+        let inferior = match attach_to.map(|pid| Inferior::attach(pid, &target).map_err(|err| (pid, err))) {
+            Some(Ok(inferior)) => {
+                ui::announce(&format!("Attached to process {}", inferior.pid()));
+                Some(inferior)
             }
+            Some(Err((pid, err))) => return Err(format!("Could not attach to process {}: {}", pid, err)),
+            None => None,
         };
-
-        debug_data.print();
-        let history_path = format!("{}/.deet_history", std::env::var("HOME").unwrap());
-        let mut readline = Editor::<()>::new();
+        let core = options.core.as_deref().map(Core::open).transpose()?;
+        if let Some(core) = core.as_ref().filter(|_| target.is_empty()) {
+            target = core.executable().ok_or("The core dump does not name its executable, give the target program")?.to_string();
+        }
+        let debug_data = load_debug_data(&target)?;
+        let mut debugger = Debugger::with_debug_data(options, &target, inferior, debug_data);
         // Attempt to load history from ~/.deet_history if it exists
+        let _ = debugger.readline.load_history(&debugger.history_path);
+
+        debugger.update_hint_functions();
+        stats::reset();
+        ui::set_deterministic(options.deterministic);
+        inferior::set_disable_randomization(options.deterministic);
+        ui::set_interactive(!options.batch && unsafe { libc::isatty(libc::STDIN_FILENO) } == 1);
+        if options.tui {
+            // fall back to the plain mode when the TUI is not possible
+            if let Err(err) = debugger.tui.enable() {
+                println!("{}", err);
+            }
+        }
+        if let Some(core) = core {
+            debugger.inspect_core(core);
+        }
+        Ok(debugger)
+    }
 
-        let _ = readline.load_history(&history_path);
+    /// # brief
+    /// Creates a debugger of `target` from its loaded debug information, before any startup
+    /// command runs.
+    ///
+    /// # param
+    /// - `options` : The startup choices from the command line.
+    /// - `target` : The target program, found from the process waited for if not given.
+    /// - `inferior` : The process attached to, if any.
+    /// - `debug_data` : The debug information of `target`.
+    fn with_debug_data(options: &Options, target: &str, inferior: Option<Inferior>, debug_data: DwarfData) -> Self {
+        let history_path = format!("{}/.deet_history", std::env::var("HOME").unwrap_or_default());
+        let mut readline = Editor::<DeetHelper>::new();
+        readline.set_helper(Some(DeetHelper::new()));
 
         let breakpoints = HashMap::new();
         let step_over_points = HashMap::new();
         Debugger {
+            inferior_id: 1,
+            other_inferiors: Vec::new(),
             target: target.to_string(),
+            args: options.args.clone(),
             history_path,
             readline,
-            inferior: None,
+            recent_addresses: Vec::new(),
+            tui: SourcePane::new(),
+            inferior,
+            remote: None,
+            core: None,
             debug_data,
+            target_mtime: modification_time(target),
             breakpoints,
+            traps_installed: false,
+            breakpoint_list: Vec::new(),
+            breakpoint_history: VecDeque::new(),
             step_over_points,
+            source_paths: SubstitutePaths::new(),
+            current_line: None,
+            convenience: HashMap::new(),
+            value_history: Vec::new(),
+            printers: pretty::Registry::new(),
+            pretty_printing: true,
+            stop_hook: Vec::new(),
+            displays: Vec::new(),
+            environment: Vec::new(),
+            replaying: false,
+            command_line: String::new(),
+            snapshots: HashMap::new(),
+            heap_at_stops: (None, None),
+            last_stop: None,
+            libraries: Vec::new(),
+            library_symbols: HashMap::new(),
+            list_position: None,
+            print_repeated_stops: true,
+            print_symbol: false,
+            capture_output: false,
+            output: Capture::new(),
+            output_reported: 0,
+            print_output_summary: true,
+            print_solib_events: false,
+            print_command_time: false,
+            crash_report: None,
+            crash_reported: None,
+            fd_baseline: None,
+            context: Context::new(),
+            stopped_thread: None,
+            print_elements: pretty::DEFAULT_ELEMENTS,
+            rbreak_limit: DEFAULT_RBREAK_LIMIT,
+            exec_stops: true,
+            step_into_plt: false,
+            runs: Vec::new(),
+            skips: Vec::new(),
+            frame_arguments: FrameArguments::Scalars,
+            startup_commands: options
+                .session
+                .iter()
+                .map(|session| format!("restore session {}", debugger_command::quote(session)))
+                .chain(options.commands.iter().cloned())
+                .collect(),
+            batch: options.batch,
+            read_only: options.read_only,
+            assertions_passed: 0,
+            assertion_failures: Vec::new(),
+            wait_interval: options.wait_interval.unwrap_or(wait_for::DEFAULT_INTERVAL),
+            timeout_stop: None,
+            until: None,
+            hint_functions_pending: true,
+            quit_code: None,
         }
     }
 
     /// # brief
-    /// Get the next debugger command from user input.
+    /// Get the next command line from user input.
     /// The loop waits for user input and handles different situations:
     ///
     /// - If the user presses Ctrl+C, a message is printed and continues to wait for user input.
-    /// - If the user presses Ctrl+D (indicating the end of input on some systems), return a `DebuggerCommand::Quit` to exit the debugger.
+    /// - If the user presses Ctrl+D (indicating the end of input on some systems), return `quit` to exit the debugger, which asks first if a program is being debugged.
     /// - If other I/O errors occur, a panic is thrown.
-    /// - If the user input is OK, the user input is added to the history and attempts to save the history to a file.
-    /// - Next, it splits the user-entered string into words and attempts to parse it into debugger commands. If the command is successfully parsed, the command is returned; otherwise a message is printed indicating that the command was not recognized.
+    /// - If the user input is OK, the whole line is added to the history and attempts to save the history to a file.
     ///
     /// # return
-    /// Returns a `DebuggerCommand` enumeration type representing the next debugger command 
-    /// entered by the user.
-    fn get_next_command(&mut self) -> DebuggerCommand {
+    /// Returns the line entered by the user, which may hold several commands separated by `;`,
+    /// see `execute_line`.
+    fn get_next_line(&mut self) -> String {
         loop {
+            if self.hint_functions_pending {
+                self.update_hint_functions();
+            }
             // Print prompt and get next line of user input
-            match self.readline.readline("\x1b[35m(deet) \x1b[0m") {
+            let prompt = if self.read_only { "\x1b[35m(deet read-only) \x1b[0m" } else { "\x1b[35m(deet) \x1b[0m" };
+            match self.readline.readline(prompt) {
                 Err(ReadlineError::Interrupted) => {
                     // User pressed ctrl+c. We're going to ignore it
                     println!("Type\"quit\"to exit");
                 }
                 Err(ReadlineError::Eof) => {
                     // User pressd ctrl+d, which is the equivalent of "quit" for our purposes
-                    return DebuggerCommand::Quit;
+                    println!("quit");
+                    return "quit".to_string();
                 }
                 Err(err) => {
                     panic!("Unexpected I/O Error: {:?}", err);
@@ -100,214 +757,6887 @@ impl Debugger {
                     }
                     self.readline.add_history_entry(line.as_str());
                     if let Err(err) = self.readline.save_history(&self.history_path) {
-                        println!("Warning: failed to save history file at {}: {}", 
-                            self.history_path,
-                            err
-                        );
-                    }
-                    let tokens: Vec<&str> = line.split_whitespace().collect();
-                    if let Some(cmd) = DebuggerCommand::from_tokens(&tokens) {
-                        return cmd;
-                    } else {
-                        println!("Unrecognized command.");
+                        ui::notify(&format!("Warning: failed to save history file at {}: {}", self.history_path, err));
                     }
+                    return line;
+                }
+            }
+        }
+    }
+
+    /// # brief
+    /// Execute a command line from the prompt or `-ex`. It may hold several commands separated
+    /// by `;` outside quotes, e.g. `b main; run; bt`, which run in order. None of them runs if
+    /// one is not recognized, and the rest are skipped once the program is gone, as they would
+    /// have nothing to look at.
+    ///
+    /// # return
+    /// * `false` if deet should quit.
+    fn execute_line(&mut self, line: &str) -> bool {
+        let mut commands = Vec::new();
+        for segment in debugger_command::split_commands(line) {
+            let words = debugger_command::tokenize(&segment);
+            let tokens: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
+            match DebuggerCommand::from_tokens(&tokens) {
+                Some(cmd) => commands.push((segment, cmd)),
+                None => {
+                    println!("Unrecognized command \"{}\".", segment);
+                    return true;
+                }
+            }
+        }
+        let count = commands.len();
+        for (index, (segment, cmd)) in commands.into_iter().enumerate() {
+            let running = self.inferior.is_some() || matches!(cmd, DebuggerCommand::Run(_));
+            self.command_line = segment;
+            // a bug in a command ends the command, not the session
+            let outer = COMMAND_RUNNING.swap(true, Ordering::SeqCst);
+            let outcome = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                // `time` reports on its own
+                if self.print_command_time && !matches!(cmd, DebuggerCommand::Time(_)) {
+                    self.time_command(cmd, false)
+                } else {
+                    self.execute(cmd)
                 }
+            }));
+            COMMAND_RUNNING.store(outer, Ordering::SeqCst);
+            let keep = match outcome {
+                Ok(keep) => keep,
+                Err(payload) => {
+                    self.recover_from_panic(payload.as_ref());
+                    return true;
+                }
+            };
+            if !keep {
+                return false;
+            }
+            if running && self.inferior.is_none() && index + 1 < count {
+                println!("The program is not being run anymore, the rest of the line is skipped.");
+                break;
+            }
+        }
+        true
+    }
+
+    /// # brief
+    /// Go back to the prompt after a panic in a command: say what happened, and check that the
+    /// state the command may have left half changed still holds together. The traps in the
+    /// program must be those of the enabled breakpoints, and the process must still exist.
+    fn recover_from_panic(&mut self, payload: &(dyn Any + Send)) {
+        let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (None, Some(message)) => message.clone(),
+            (None, None) => "no message".to_string(),
+        };
+        println!(
+            "deet hit an internal error in `{}`: {}. Please file an issue with the message above; the session goes on.",
+            self.command_line, message
+        );
+        if inferior::take_interrupted_patch() && self.inferior.is_some() {
+            println!("Warning: the error interrupted the patching of traps in the program, whose code may be corrupted now; `run` starts it again.");
+        }
+        if let Some(pid) = self.inferior.as_ref().map(|inferior| inferior.pid()) {
+            if !std::path::Path::new(&format!("/proc/{}", pid)).exists() {
+                println!("Process {} is gone.", pid);
+                self.end_run(Outcome::Killed);
+                self.discard_traps();
+                self.inferior = None;
+            }
+        }
+        let needed: HashSet<usize> = self.breakpoint_list.iter().flat_map(|bp| bp.traps()).collect();
+        let hook = self.inferior.as_ref().and_then(|inferior| inferior.library_hook());
+        let stray: Vec<usize> = self.breakpoints.keys().filter(|addr| !needed.contains(addr) && Some(**addr) != hook).copied().collect();
+        for addr in stray {
+            self.remove_trap(addr);
+        }
+        let mut missing: Vec<usize> = needed.into_iter().filter(|addr| !self.breakpoints.contains_key(addr)).collect();
+        missing.sort();
+        for addr in missing {
+            if !self.install_trap(addr) {
+                println!("The breakpoints at {:#x} are not installed.", addr);
             }
         }
     }
 
     /// # brief
-    /// Parses a hexadecimal address string and returns the corresponding `usize` value.
+    /// Parse the address given to `x`, `snapshot` or `break *`. A bare word of hexadecimal digits
+    /// such as `401136` is read in hexadecimal, unless it is also the name of a variable or
+    /// function such as `beef`, which then wins with a note; `0xbeef` is always the number.
+    /// Anything else is evaluated as an expression, e.g. `&counter`, `main+4` or `$base+0x40`.
     ///
     /// # param
-    /// - `addr` - A hexadecimal address string, which may start with "0x" prefix.
+    /// - `addr` : The address as typed.
     ///
     /// # return
-    /// * `Some(usize)` - If the address string is successfully parsed, returns the corresponding `usize` value.
+    /// * `Ok(usize)` - the address, or `Err(String)` describing why it could not be evaluated.
+    fn parse_address(&self, addr: &str) -> Result<usize, String> {
+        let addr = addr.trim();
+        if !addr.is_empty() && addr.chars().all(|c| c.is_ascii_hexdigit()) {
+            if addr.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                if let Ok(value) = self.symbol(addr) {
+                    println!("Note: taking {} as a symbol, write 0x{} for the number", addr, addr);
+                    return Ok(value as usize);
+                }
+            }
+            return usize::from_str_radix(addr, 16).map_err(|_| format!("Invalid address {}", addr));
+        }
+        expr::eval(addr, self).map(|value| value as usize)
+    }
+
+    /// # brief
+    /// Evaluate an expression, or assign its value to a convenience variable when it has the form
+    /// `$name = <expr>`.
     ///
-    /// # Examples
+    /// # return
+    /// * `Ok(u64)` - the value of the expression, or `Err(String)` describing why it failed.
+    fn evaluate(&mut self, text: &str) -> Result<u64, String> {
+        if let Some((lhs, rhs)) = text.split_once('=') {
+            let name = lhs.trim();
+            // `==`, `!=`, `<=` and `>=` compare, they don't assign
+            let is_comparison = rhs.starts_with('=') || name.ends_with(|c| "!<>".contains(c));
+            if let (false, Some(name)) = (is_comparison, name.strip_prefix('$')) {
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!("Invalid convenience variable name \"${}\"", name));
+                }
+                if name.chars().all(|c| c.is_ascii_digit()) || self.register(name).is_some() {
+                    return Err(format!("Left operand of assignment is not a modifiable lvalue: ${}", name));
+                }
+                let value = expr::eval(rhs, self)?;
+                self.convenience.insert(name.to_string(), value);
+                return Ok(value);
+            }
+        }
+        expr::eval(text, self)
+    }
+
+    /// # brief
+    /// Get a register of the stopped inferior by name, as the selected frame sees it.
+    fn register(&self, name: &str) -> Option<u64> {
+        let regs = self.context.frame_registers(self.target_access()?, self.identity()).ok()?;
+        registers::get_register(&regs, name)
+            .or_else(|| self.inferior.as_ref()?.get_fpregs().ok()?.get_control(name))
+    }
+
+    /// # brief
+    /// Get the general purpose registers at this stop, those of frame 0 whatever frame is
+    /// selected. The inspection commands read them through here rather than from the program,
+    /// see `Context`.
+    fn registers(&self) -> Option<libc::user_regs_struct> {
+        self.context.registers(self.target_access()?, self.identity()).ok()
+    }
+
+    /// # brief
+    /// Get which inferior and thread the inspection commands look at, those of the stop the
+    /// `Context` is of.
+    fn identity(&self) -> Identity {
+        let thread = match (self.inferior.as_ref(), self.core.as_ref()) {
+            (Some(inferior), _) => Some(inferior.current_thread().map_or(inferior.pid(), |thread| thread.tid)),
+            (None, Some(core)) => Some(nix::unistd::Pid::from_raw(core.pid())),
+            (None, None) => None,
+        };
+        Identity { inferior: self.inferior_id, thread }
+    }
+
+    /// # brief
+    /// Get what reads the memory and registers of the program: the remote stub after `target
+    /// remote`, else the traced inferior, else the core dump of `--core`.
+    fn target_access(&self) -> Option<&dyn TargetAccess> {
+        match (self.remote.as_ref(), self.inferior.as_ref(), self.core.as_ref()) {
+            (Some(remote), _, _) => Some(remote),
+            (None, Some(inferior), _) => Some(inferior),
+            (None, None, Some(core)) => Some(core),
+            (None, None, None) => None,
+        }
+    }
+
+    /// # brief
+    /// Format a value for `print`, according to the `/x`-style format letter.
+    fn format_value(&self, value: u64, format: &str) -> Result<String, String> {
+        match format {
+            "" | "d" => Ok(format!("{}", value as i64)),
+            "x" => Ok(format!("{:#x}", value)),
+            "u" => Ok(format!("{}", value)),
+            "o" => Ok(format!("{:#o}", value)),
+            "t" => Ok(format!("{:b}", value)),
+            "c" => Ok(format!("{} '{}'", value as u8 as i8, (value as u8 as char).escape_default())),
+            other => Err(format!("Undefined output format \"{}\"", other)),
+        }
+    }
+
+    /// # brief
+    /// Find the variable `name` visible in the selected frame: a local of the function of the
+    /// frame, or else a global.
     ///
-    /// ```
-    /// let parser = AddressParser;
-    /// let address_str = "0x12AB";
-    /// assert_eq!(parser.parse_address(address_str), Some(4779));
-    /// ```
-    fn parse_address(&self, addr: &str) -> Option<usize> {
-        let addr_without_0x = if addr.to_lowercase().starts_with("0x") {
-            &addr[2..]
-        } else {
-            &addr
+    /// # return
+    /// * `Some((Result<Place, String>, Type))` - where the value of the variable is, or why it
+    /// can't be read, and its type. `None` if there is no such variable.
+    fn lookup_variable(&self, name: &str) -> Option<(Result<Place, String>, Type)> {
+        let regs = self.registers()?;
+        let frame = self.context.selected_frame();
+        let pc = match frame {
+            // a return address may be past the end of the function, the call is before it
+            Some(frame) if !frame.innermost => frame.rip.saturating_sub(1),
+            Some(frame) => frame.rip,
+            None => regs.rip as usize,
         };
-        usize::from_str_radix(addr_without_0x, 16).ok()
+        let variables = self.debug_data.find_variable(name);
+        let local = variables.iter().find(|(_, func, _)| {
+            func.map_or(false, |func| func.address <= pc && pc < func.address + func.text_length)
+        });
+        let global = variables.iter().find(|(_, func, _)| func.is_none());
+        let (_, func, var) = local.or(global)?;
+        let register = |regnum: u16| frame_register(&regs, frame, regnum);
+        Some((variable_place(var, *func, pc, &register), var.entity_type.clone()))
     }
 
     /// # brief
-    /// Run the debugger, processing user commands and controlling the inferior process.
+    /// Render the arguments of a backtrace frame as `name=value, ...`, as `set print
+    /// frame-arguments` asks. The outer frames only know their %rbp, so arguments held in
+    /// other registers are `<unavailable>` there.
+    fn frame_arguments(&self, frame: &Frame) -> Option<String> {
+        // the parameters of an inlined function are not where those of a call would be
+        if self.frame_arguments == FrameArguments::None || frame.inlined {
+            return None;
+        }
+        let arguments: Vec<String> = self
+            .frame_variables(frame)?
+            .into_iter()
+            .filter(|(var, _)| var.is_parameter)
+            .map(|(var, place)| format!("{}={}", var.name, self.format_argument(place, &var.entity_type)))
+            .collect();
+        Some(arguments.join(", "))
+    }
+
+    /// # brief
+    /// Find where the variables of the function of a backtrace frame are, parameters included.
     ///
-    /// This method enters a loop to continuously receive and process user commands for controlling
-    /// the debugger and the inferior process. It handles commands such as quitting the debugger,
-    /// starting or restarting the inferior process, continuing the execution, printing backtraces,
-    /// and setting breakpoints.
+    /// # return
+    /// * Each variable with its place, or why it can't be read; `None` if the frame is in no
+    /// function with debug information.
+    fn frame_variables(&self, frame: &Frame) -> Option<Vec<(&Variable, Result<Place, String>)>> {
+        let (_, func) = self.debug_data.function_containing(frame.rip)?;
+        let regs = self.registers()?;
+        let register = |regnum: u16| frame_register(&regs, Some(frame), regnum);
+        // a return address may be past the end of a location range, the call is before it
+        let pc = if frame.innermost { frame.rip } else { frame.rip.saturating_sub(1) };
+        Some(func.variables.iter().map(|var| (var, variable_place(var, Some(func), pc, &register))).collect())
+    }
+
+    /// # brief
+    /// Render the value of a frame argument: pretty-printed or as a scalar, `...` for an
+    /// aggregate unless `set print frame-arguments all`, and shortened to
+    /// `MAX_ARGUMENT_LEN` characters.
+    fn format_argument(&self, place: Result<Place, String>, ty: &Type) -> String {
+        if ty.size > 8 && self.frame_arguments != FrameArguments::All {
+            return "...".to_string();
+        }
+        let text = self.format_place(place, ty);
+        if text.chars().count() > MAX_ARGUMENT_LEN {
+            format!("{}...", text.chars().take(MAX_ARGUMENT_LEN).collect::<String>())
+        } else {
+            text
+        }
+    }
+
+    /// # brief
+    /// Render the value of a variable from where it is: pretty-printed or as a scalar, `...`
+    /// for an aggregate without a pretty-printer, `<unavailable>` when it can't be read.
+    fn format_place(&self, place: Result<Place, String>, ty: &Type) -> String {
+        let unavailable = "<unavailable>".to_string();
+        let bytes = match place {
+            Ok(Place::Memory(addr)) => match self.read_memory(addr, ty.size.max(1)) {
+                Ok(bytes) => bytes,
+                Err(_) => return unavailable,
+            },
+            Ok(Place::Register(value)) => value.to_le_bytes()[..ty.size.clamp(1, 8)].to_vec(),
+            Err(_) => return unavailable,
+        };
+        let memory = |addr: usize, len: usize| self.read_memory(addr, len).ok();
+        let members = |name: &str| self.type_members(name);
+        let pretty = if self.pretty_printing {
+            self.printers.render(&ValueReader::new(ty, &bytes, &memory, &members, self.element_limit(false)))
+        } else {
+            None
+        };
+        match pretty {
+            Some(rendered) => rendered,
+            None if ty.size <= 8 => {
+                let format = if ty.name.ends_with('*') { "x" } else { "" };
+                self.format_value(pretty::read_scalar(&bytes, ty), format).unwrap_or(unavailable)
+            }
+            None => "...".to_string(),
+        }
+    }
+
+    /// # brief
+    /// Print a source variable, with a pretty-printer for its type unless `raw` is set or
+    /// pretty printing is off.
     ///
-    /// # Examples
+    /// # return
+    /// * `false` if there is no variable `name`, so the text is an expression.
+    fn print_variable(&mut self, format: &str, name: &str, raw: bool, full: bool) -> bool {
+        if self.target_access().is_none() {
+            return self.print_static(format, name, raw, full);
+        }
+        let (place, ty) = match self.lookup_variable(name) {
+            Some((Ok(place), ty)) => (place, ty),
+            Some((Err(reason), _)) => {
+                println!("{} = <{}>", name, reason);
+                return true;
+            }
+            None => return false,
+        };
+        let bytes = match place {
+            Place::Memory(addr) => match self.read_memory(addr, ty.size.max(1)) {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    println!("Cannot access memory at address {:#x}: {}", addr, err);
+                    return true;
+                }
+            },
+            Place::Register(value) => value.to_le_bytes()[..ty.size.clamp(1, 8)].to_vec(),
+        };
+        // scalars go into the value history, aggregates by their address
+        let value = match place {
+            _ if ty.size <= 8 => pretty::read_scalar(&bytes, &ty),
+            Place::Memory(addr) => addr as u64,
+            Place::Register(value) => value,
+        };
+        let addr = match place {
+            Place::Memory(addr) => Some(addr),
+            Place::Register(_) => None,
+        };
+        let pid = self.inferior.as_ref().map(|inferior| inferior.pid());
+        self.print_typed(format, raw, full, HistoryValue { value, ty: Some(ty), bytes, addr, pid }, "");
+        true
+    }
+
+    /// # brief
+    /// Print the initial value of the global variable `name`, read from the executable while no
+    /// program runs.
     ///
-    /// ```
-    /// let mut debugger = Debugger::new();
-    /// debugger.run();
-    /// ```plaintext
-    pub fn run(&mut self) {
-        loop {
-            match self.get_next_command() {
+    /// # return
+    /// * `false` if there is no global `name` at a fixed address, so the text is an expression.
+    fn print_static(&mut self, format: &str, name: &str, raw: bool, full: bool) -> bool {
+        let (addr, ty) = match self.static_global(name) {
+            Some(global) => global,
+            None => return false,
+        };
+        let bytes = match self.debug_data.read_static(addr, ty.size.max(1)) {
+            Some(bytes) => bytes,
+            None => {
+                println!("Cannot read {} from the executable at address {:#x}", name, addr);
+                return true;
+            }
+        };
+        let value = if ty.size <= 8 { pretty::read_scalar(&bytes, &ty) } else { addr as u64 };
+        // not read from a process, so `print $N` shows it as recorded
+        let entry = HistoryValue { value, ty: Some(ty), bytes, addr: None, pid: None };
+        self.print_typed(format, raw, full, entry, " (static initial value)");
+        true
+    }
 
-                // if the inferior still alive, then kill it and set inferior into None, finally
-                // stop the loop
-                DebuggerCommand::Quit               => {
-                    if self.inferior.is_some() {
-                        self.inferior.as_mut().unwrap().kill();
-                        self.inferior = None;
-                    }
-                    return;
-                }
+    /// # brief
+    /// Find the global variable `name` at a fixed address, which can be read from the executable.
+    fn static_global(&self, name: &str) -> Option<(usize, Type)> {
+        self.debug_data.find_variable(name).into_iter().find_map(|(_, func, var)| match (func, &var.location) {
+            (None, Location::Address(addr)) => Some((*addr, var.entity_type.clone())),
+            _ => None,
+        })
+    }
 
-                // Determine whether inferior exists. If it exists, kill it and then 
-                // create a new inferior and execute it directly.
-                DebuggerCommand::Run(args)             => {
-                    if self.inferior.is_some() {
-                        // there is already a inferior running
-                        // if it has not exited, kill it first
-                        self.inferior.as_mut().unwrap().kill();
-                        self.inferior = None;
-                    }
-                    if let Some(inferior) = Inferior::new(&self.target, &args, &mut self.breakpoints) {
-                        // Crate the inferior
-                        self.inferior = Some(inferior);
+    /// # brief
+    /// Get the most elements of a value to show, `None` for all of them: with `print/full` or
+    /// `set print elements 0`.
+    fn element_limit(&self, full: bool) -> Option<usize> {
+        Some(self.print_elements).filter(|limit| !full && *limit > 0)
+    }
 
-                        match self.inferior.as_mut().unwrap().continue_run(None, &self.breakpoints, &mut self.step_over_points).unwrap() {
-                            Status::Exited(exit_code)    => {
-                                println!("Chlid exited (status {})", exit_code);
-                                self.inferior = None;
-                            }
-                            Status::Signaled(signal)     => {
-                                println!("Child exited due to signal {}", signal);
-                                self.inferior = None;
-                            }
-                            Status::Stopped(signal, rip) => {
-                                println!("Child stopped (signal {})", signal);
-                                let _line = self.debug_data.get_line_from_addr(rip);
-                                let _func = self.debug_data.get_function_from_addr(rip);
-                                if _line.is_some() && _func.is_some(){
-                                    println!("Stopped at {} ({})", _func.unwrap(), _line.unwrap());
-                                }
-                            }
-                        }
-                    } else {
-                        println!("Error starting subprocess");
-                    }
-                }
+    /// # brief
+    /// Get the members of the struct type `name`, for the pretty-printers to read a value by
+    /// the offsets of its members.
+    fn type_members(&self, name: &str) -> Option<Vec<Member>> {
+        self.debug_data.find_type(name).first().map(|def| def.members.clone())
+    }
 
-                // call continues_run from inferior ;
-                // and wait for status changing of child .
-                DebuggerCommand::Continue              => {
-                    if self.inferior.is_none() {
-                       println!("Error: you can not use continue when there is no process running!");
-                    } else {
-                        match self.inferior.as_mut().unwrap().continue_run(None, &self.breakpoints, &mut self.step_over_points).unwrap() {
-                            Status::Exited(exit_code) => {
-                                self.inferior = None;
-                                println!("Child exit (status {})", exit_code);
-                            }
-                            Status::Signaled(single) => {
-                                self.inferior = None;
-                                println!("Child exited due to signal {}", single);
-                            }
-                            Status::Stopped(single, rip) => {
-                                println!("Child stopped (signal {})", single);
-                                let _line = self.debug_data.get_line_from_addr(rip);
-                                let _func = self.debug_data.get_function_from_addr(rip);
-                                if _line.is_some() && _func.is_some(){
-                                    println!("Stopped at {} ({})", _func.unwrap(), _line.unwrap());
-                                }
-                            }
-                        }
-                    }
+    /// # brief
+    /// Format a typed value with a pretty-printer for its type unless `raw` is set or pretty
+    /// printing is off, then print it as `$N = value` followed by `note`, and record it in the
+    /// value history. Long values are cut at `set print elements` unless `full` is set.
+    fn print_typed(&mut self, format: &str, raw: bool, full: bool, entry: HistoryValue, note: &str) {
+        let ty = entry.ty.clone().unwrap_or_default();
+        let (value, bytes) = (entry.value, &entry.bytes);
+        let memory = |addr: usize, len: usize| self.read_memory(addr, len).ok();
+        let members = |name: &str| self.type_members(name);
+        let pretty = if raw || !self.pretty_printing {
+            None
+        } else {
+            self.printers.render(&ValueReader::new(&ty, bytes, &memory, &members, self.element_limit(full)))
+        };
+        let formatted = match pretty {
+            Some(rendered) => rendered,
+            None if ty.size <= 8 => match self.format_value(value, format) {
+                Ok(formatted) if ty.name.ends_with('*') => format!("{}{}", formatted, self.symbol_suffix(value)),
+                Ok(formatted) => formatted,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
                 }
-
-                // Use the ptracer::step() function to execute 
-                // one step downward from the current rip then 
-                // and observe the state changes of the child process
-                DebuggerCommand::Step                  => {
-                    if self.inferior.is_none() {
-                        println!("Error: you can not use step when there is no process running");
-                    } else {
-                        match self.inferior.as_mut().unwrap().step_over(&self.breakpoints, &mut self.step_over_points, None, &self.debug_data).unwrap() {
-                            Status::Exited(exit_code)    => {
-                                println!("Chlid exited (status {})", exit_code);
-                                self.inferior = None;
-                            }
-                            Status::Signaled(signal)     => {
-                                println!("Child exited due to signal {}", signal);
-                                self.inferior = None;
-                            }
-                            Status::Stopped(signal, rip) => {
-                                println!("Child stopped (signal {})", signal);
-                                let _line = self.debug_data.get_line_from_addr(rip);
-                                let _func = self.debug_data.get_function_from_addr(rip);
-                                if _line.is_some() && _func.is_some(){
-                                    println!("Stopped at {} ({})", _func.unwrap(), _line.unwrap());
-                                }
-                            }
-                        }
-                    }
+            },
+            None => {
+                let total = (bytes.len() + 7) / 8;
+                let shown = self.element_limit(full).map_or(total, |limit| total.min(limit));
+                let mut words: Vec<String> = bytes
+                    .chunks(8)
+                    .take(shown)
+                    .map(|chunk| format!("{:#x}", pretty::read_scalar(chunk, &Type::default())))
+                    .collect();
+                if shown < total {
+                    words.push(pretty::truncation_note(Some(total)));
                 }
+                format!("({}) {{{}}}", ty.name, words.join(", "))
+            }
+        };
+        self.value_history.push(entry);
+        println!("${} = {}{}", self.value_history.len(), formatted, note);
+    }
 
-                // print backtrace of this process , untill back to main function
-                DebuggerCommand::Backtrace             => {
-                    if self.inferior.is_none() {
-                        println!("Erro: you can not use backtrace when there is no process running");
-                    } else {
-                        self.inferior.as_mut().unwrap().print_backtrace(&self.debug_data).unwrap();
+    /// # brief
+    /// Find the value history entry of `$N`, `$` (the last one) or `$$` (the one before), given
+    /// the text after the first `$`.
+    ///
+    /// # return
+    /// * The index into the value history, `None` if there is no such entry or `name` is not a
+    /// history reference.
+    fn history_index(&self, name: &str) -> Option<usize> {
+        let len = self.value_history.len();
+        let index = match name {
+            "" => len.checked_sub(1)?,
+            "$" => len.checked_sub(2)?,
+            number => number.parse::<usize>().ok()?.checked_sub(1)?,
+        };
+        Some(index).filter(|index| *index < len)
+    }
+
+    /// # brief
+    /// Handle `print $N` for a typed entry of the value history: print it again, with memory
+    /// read again if the value lives there, so it reflects the current state. A value of an
+    /// earlier run of the program is shown as recorded, with a note.
+    fn print_history(&mut self, format: &str, raw: bool, full: bool, index: usize) {
+        let mut entry = self.value_history[index].clone();
+        let pid = self.inferior.as_ref().map(|inferior| inferior.pid());
+        let ty = entry.ty.clone().unwrap_or_default();
+        match entry.addr {
+            Some(_) if entry.pid != pid => {
+                println!("(${} was read in an earlier run of the program, showing the recorded value)", index + 1);
+            }
+            Some(addr) => match self.read_memory(addr, ty.size.max(1)) {
+                Ok(bytes) => {
+                    if ty.size <= 8 {
+                        entry.value = pretty::read_scalar(&bytes, &ty);
                     }
+                    entry.bytes = bytes;
                 }
+                Err(err) => {
+                    println!("Cannot access memory at address {:#x}: {}", addr, err);
+                    return;
+                }
+            },
+            None => {}
+        }
+        self.print_typed(format, raw, full, entry, "");
+    }
 
-                // judge if the input have'not error , then get this input and parse into address
-                // and insert HashMap ( usize(addr) - u8(ori_byte) )
-                DebuggerCommand::Breakpoint(localtion) => {
-                    let breakpoint_addr;
-                    if localtion.starts_with("*") {
-                        if let Some(address) = self.parse_address(&localtion[1..]) {
-                            breakpoint_addr = address;
-                        } else {
-                            println!("Invalid address");
-                            continue;
-                        }
-                    } else if let Some(line) = usize::from_str_radix(&localtion, 10).ok() {
-                        if let Some(address) = self.debug_data.get_addr_for_line(None, line) {
-                            breakpoint_addr = address;
-                        } else {
-                            println!("Invalid line number");
-                            continue;
+    /// # brief
+    /// Handle `show values`: list the last ten entries of the value history.
+    fn show_values(&self) {
+        let start = self.value_history.len().saturating_sub(MAX_SHOWN_VALUES);
+        for (index, entry) in self.value_history.iter().enumerate().skip(start) {
+            let what = match (&entry.ty, entry.addr) {
+                (Some(ty), Some(addr)) => format!(" ({} at {:#x})", ty.name, addr),
+                (Some(ty), None) => format!(" ({})", ty.name),
+                (None, _) => String::new(),
+            };
+            println!("${} = {:#x}{}", index + 1, entry.value, what);
+        }
+    }
+
+    /// # brief
+    /// Handle `print[/fmt] <expr>`: evaluate the expression, record it in the value history and
+    /// print it as `$N = value`. A source variable is printed with the pretty-printer of its
+    /// type; `/r` prints it raw.
+    fn print(&mut self, format: &str, text: &str) {
+        let full = format.contains("full");
+        let format = format.replace("full", "");
+        let raw = format.contains('r');
+        let format = format.replace('r', "");
+        let format = format.as_str();
+        let name = text.trim();
+        let is_identifier = name.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_identifier && self.print_variable(format, name, raw, full) {
+            return;
+        }
+        // a typed history entry is printed like the variable it came from
+        if let Some(index) = name.strip_prefix('$').and_then(|name| self.history_index(name)) {
+            if self.value_history[index].ty.is_some() {
+                self.print_history(format, raw, full, index);
+                return;
+            }
+        }
+        // vector registers don't fit the 64-bit values of expressions, show all their lanes
+        if let Some(name) = text.trim().strip_prefix('$') {
+            if let Some(fpregs) = self.inferior.as_ref().and_then(|inferior| inferior.get_fpregs().ok()) {
+                if let (true, Some(value)) = (name.starts_with("xmm"), fpregs.get_vector(name)) {
+                    println!("{}", registers::format_xmm(value));
+                    return;
+                }
+            }
+        }
+        let value = match self.evaluate(text) {
+            Ok(value) => value,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        match self.format_value(value, format) {
+            Ok(formatted) => {
+                self.remember_address(value as usize);
+                self.value_history.push(HistoryValue { value, ty: None, bytes: Vec::new(), addr: None, pid: None });
+                println!("${} = {}{}", self.value_history.len(), formatted, self.symbol_suffix(value));
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    /// # brief
+    /// Read memory of the inferior with the breakpoint traps hidden, so the original code bytes
+    /// are returned. A remote stub hides its breakpoints itself, and a core dump has none.
+    fn read_memory(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+        if let Some(remote) = self.remote.as_ref() {
+            return remote.read_bytes(addr, len);
+        }
+        if let (None, Some(core)) = (self.inferior.as_ref(), self.core.as_ref()) {
+            return core.read_bytes(addr, len);
+        }
+        let inferior = self.inferior.as_ref().ok_or(nix::Error::Sys(nix::errno::Errno::ESRCH))?;
+        let mut bytes = inferior.read_bytes(addr, len)?;
+        if !self.traps_installed {
+            return Ok(bytes);
+        }
+        for (bp_addr, orig_byte) in self.breakpoints.iter().chain(self.step_over_points.iter()) {
+            if *bp_addr >= addr && *bp_addr < addr + bytes.len() {
+                bytes[*bp_addr - addr] = *orig_byte;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// # brief
+    /// Handle `x/<count><format><size> <address>`: print `count` units of memory starting at
+    /// `address`. The format is one of `x d u o t` and the unit size one of `b h w g`, or `i` for
+    /// `count` instructions, for which a size doesn't apply. Breakpoint traps are hidden, so the
+    /// original code bytes are shown. At most `set print elements` units are shown.
+    fn examine(&mut self, format: &str, text: &str) {
+        let count_len = format.chars().take_while(|c| c.is_ascii_digit()).count();
+        let count = format[..count_len].parse::<usize>().unwrap_or(1);
+        let mut letter = 'x';
+        let mut size = 4;
+        for c in format[count_len..].chars() {
+            match c {
+                'b' => size = 1,
+                'h' => size = 2,
+                'w' => size = 4,
+                'g' => size = 8,
+                'x' | 'd' | 'u' | 'o' | 't' | 'i' => letter = c,
+                _ => {
+                    println!("Invalid format letter '{}'", c);
+                    return;
+                }
+            }
+        }
+        let addr = match self.parse_address(text) {
+            Ok(addr) => addr,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        if self.target_access().is_none() {
+            println!("Error: you can not examine memory when there is no process running");
+            return;
+        }
+        if letter == 'i' {
+            self.examine_instructions(addr, count);
+            return;
+        }
+        let total = count;
+        let count = self.element_limit(false).map_or(total, |limit| total.min(limit));
+        let (bytes, failure) = self.read_memory_partial(addr, count * size);
+        let shown = bytes.len() / size;
+        if let Some(err) = failure.as_ref().filter(|_| shown == 0) {
+            println!("Cannot access memory at address {:#x}: {}", addr + bytes.len(), err);
+            return;
+        }
+        // the units before the end of a mapping are still shown
+        let bytes = &bytes[..shown * size];
+
+        let per_line = match size {
+            8 => 2,
+            4 => 4,
+            _ => 8,
+        };
+        let mut lines: Vec<String> = Vec::new();
+        for (i, unit) in bytes.chunks(size).enumerate() {
+            if i % per_line == 0 {
+                lines.push(format!("{:#x}:", addr + i * size));
+            }
+            let line = lines.last_mut().unwrap();
+            let mut raw = [0u8; 8];
+            raw[..size].copy_from_slice(unit);
+            let value = u64::from_le_bytes(raw);
+            let bits = size * 8;
+            match letter {
+                'd' => {
+                    // sign extend the unit
+                    let shift = 64 - bits;
+                    line.push_str(&format!("\t{}", ((value << shift) as i64) >> shift));
+                }
+                'u' => line.push_str(&format!("\t{}", value)),
+                'o' => line.push_str(&format!("\t{:#o}", value)),
+                't' => line.push_str(&format!("\t{:0width$b}", value, width = bits)),
+                _ => line.push_str(&format!("\t0x{:0width$x}", value, width = size * 2)),
+            }
+            // a giant (8 byte) word may be a pointer
+            if size == 8 {
+                line.push_str(&self.symbol_suffix(value));
+            }
+        }
+        if let Some(err) = failure {
+            lines.push(format!("Cannot access memory at address {:#x}: {}", addr + shown * size, err));
+        } else if count < total {
+            lines.push(format!("... (truncated, {} total units; use `set print elements 0`)", total));
+        }
+        ui::page(lines);
+        self.remember_address(addr);
+        self.convenience.insert("_".to_string(), (addr + (shown.max(1) - 1) * size) as u64);
+    }
+
+    /// # brief
+    /// Handle `x/<count>i <address>`: print `count` instructions starting at `address`, as
+    /// `list` prints them where there is no source. Unlike that listing, the instructions are
+    /// decoded from `address` itself rather than from the start of its function, so an address in
+    /// the middle of an instruction is decoded anyway, with a note that the result may be wrong.
+    /// A byte that can't be decoded is shown as `(bad)` and decoding goes on after it.
+    ///
+    /// # param
+    /// - `addr` : The address of the first instruction.
+    /// - `count` : How many instructions to print.
+    fn examine_instructions(&mut self, addr: usize, count: usize) {
+        let (code, failure) = self.read_memory_partial(addr, count * disasm::MAX_INSTRUCTION_LEN);
+        if let Some(err) = failure.as_ref().filter(|_| code.is_empty()) {
+            println!("Cannot access memory at address {:#x}: {}", addr, err);
+            return;
+        }
+        let mut lines = Vec::new();
+        if let Some(start) = self.instruction_containing(addr) {
+            lines.push(format!(
+                "note: {:#x} is in the middle of the instruction at {:#x}; decoding from {:#x} anyway, the instructions may be wrong.",
+                addr, start, addr
+            ));
+        }
+        let pc = self.register("rip").map(|rip| rip as usize);
+        let mut pos = 0;
+        let mut last = addr;
+        for _ in 0..count {
+            if pos >= code.len() {
+                break;
+            }
+            let len = disasm::instruction_length(&code[pos..]);
+            // an instruction cut by the end of the readable memory is not shown
+            if len.is_none() && failure.is_some() && code.len() - pos < disasm::MAX_INSTRUCTION_LEN {
+                break;
+            }
+            let len = len.unwrap_or(1);
+            last = addr + pos;
+            lines.push(self.instruction_line(pc == Some(last), last, &code[pos..pos + len]));
+            pos += len;
+        }
+        if let Some(err) = failure.filter(|_| lines.len() < count) {
+            lines.push(format!("Cannot access memory at address {:#x}: {}", addr + pos, err));
+        }
+        ui::page(lines);
+        self.remember_address(addr);
+        self.convenience.insert("_".to_string(), last as u64);
+    }
+
+    /// # brief
+    /// Handle `assert <expr>`: check that an expression is true (nonzero). A failure prints the
+    /// values of both sides of a comparison and is recorded, which makes a `--batch` session exit
+    /// with status 1.
+    fn assert(&mut self, text: &str) {
+        let result = expr::parse(text).and_then(|parsed| {
+            let value = expr::evaluate(&parsed, self)?;
+            let sides = match &parsed {
+                Expr::Binary(op, lhs, rhs) if op.is_comparison() => Some((
+                    expr::evaluate(lhs, self)?,
+                    expr::evaluate(rhs, self)?,
+                )),
+                _ => None,
+            };
+            Ok((value, sides))
+        });
+        let failure = match result {
+            Ok((value, _)) if value != 0 => {
+                self.assertions_passed += 1;
+                return;
+            }
+            Ok((_, Some((lhs, rhs)))) => format!("{} (left side = {}, right side = {})", text, lhs as i64, rhs as i64),
+            Ok((value, None)) => format!("{} (value = {})", text, value as i64),
+            Err(err) => format!("{} ({})", text, err),
+        };
+        let location = match &self.current_line {
+            Some(line) if self.inferior.is_some() => format!(" at {}", line),
+            _ => String::new(),
+        };
+        println!("Assertion failed{}: {}", location, failure);
+        self.assertion_failures.push(format!("{}{}", failure, location));
+    }
+
+    /// # brief
+    /// Handle `info assertions`: how many assertions held, and which ones failed.
+    fn info_assertions(&self) {
+        println!(
+            "{} assertions passed, {} failed",
+            self.assertions_passed,
+            self.assertion_failures.len()
+        );
+        for failure in self.assertion_failures.iter() {
+            println!("  {}", failure);
+        }
+    }
+
+    /// # brief
+    /// Handle `snapshot <name> <addr> <len>`: save a copy of a memory range for `compare`.
+    fn snapshot(&mut self, args: &[String]) {
+        if args.len() != 3 {
+            println!("Usage: snapshot <name> <address> <length>");
+            return;
+        }
+        if self.target_access().is_none() {
+            println!("Error: you can not take a snapshot when there is no process running");
+            return;
+        }
+        let addr = match self.parse_address(&args[1]) {
+            Ok(addr) => addr,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let len = match self.evaluate(&args[2]) {
+            Ok(len) if len > 0 => len as usize,
+            Ok(_) => {
+                println!("The length of a snapshot must be positive");
+                return;
+            }
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        match self.read_memory(addr, len) {
+            Ok(bytes) => {
+                println!("Saved snapshot {} of {} bytes at {:#x}", args[0], len, addr);
+                self.snapshots.insert(args[0].clone(), Snapshot { addr, bytes });
+            }
+            Err(err) => println!("Cannot access memory at address {:#x}: {}", addr, err),
+        }
+    }
+
+    /// # brief
+    /// Handle `compare <name>`: read the range of a snapshot again and print the bytes that
+    /// changed, one line per run of consecutive changed bytes.
+    fn compare(&self, args: &[String]) {
+        let name = match args.get(0) {
+            Some(name) => name,
+            None => {
+                println!("Usage: compare <name>");
+                return;
+            }
+        };
+        let snapshot = match self.snapshots.get(name) {
+            Some(snapshot) => snapshot,
+            None => {
+                println!("No snapshot named {}", name);
+                return;
+            }
+        };
+        if self.target_access().is_none() {
+            println!("Error: you can not compare a snapshot when there is no process running");
+            return;
+        }
+        let current = match self.read_memory(snapshot.addr, snapshot.bytes.len()) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                println!("Cannot access memory at address {:#x}: {}", snapshot.addr, err);
+                return;
+            }
+        };
+        let changes = snapshot::diff(&snapshot.bytes, &current);
+        if changes.is_empty() {
+            println!("Snapshot {} is unchanged", name);
+            return;
+        }
+        let changed_bytes: usize = changes.iter().map(|change| change.old.len()).sum();
+        println!("Snapshot {}: {} bytes changed in {} ranges", name, changed_bytes, changes.len());
+        let hex = |bytes: &Vec<u8>| bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ");
+        for change in changes.iter().take(MAX_PRINTED_CHANGES) {
+            let start = snapshot.addr + change.offset;
+            if change.old.len() == 1 {
+                println!("  +{:#x} ({:#x}): {} -> {}", change.offset, start, hex(&change.old), hex(&change.new));
+            } else {
+                println!(
+                    "  +{:#x}..+{:#x} ({:#x}..{:#x}): {} -> {}",
+                    change.offset,
+                    change.offset + change.old.len(),
+                    start,
+                    start + change.old.len(),
+                    hex(&change.old),
+                    hex(&change.new)
+                );
+            }
+        }
+        if changes.len() > MAX_PRINTED_CHANGES {
+            println!("  ... {} more ranges", changes.len() - MAX_PRINTED_CHANGES);
+        }
+    }
+
+    /// # brief
+    /// List the snapshots taken by `snapshot`.
+    fn info_snapshots(&self) {
+        if self.snapshots.is_empty() {
+            println!("No snapshots.");
+            return;
+        }
+        let mut names: Vec<&String> = self.snapshots.keys().collect();
+        names.sort();
+        for name in names {
+            let snapshot = &self.snapshots[name];
+            println!("{}\t{:#x}\t{} bytes", name, snapshot.addr, snapshot.bytes.len());
+        }
+    }
+
+    /// # brief
+    /// Parses a `file:line` location. The line number is taken after the last colon, so that
+    /// function names containing `::` are not mistaken for a file.
+    ///
+    /// # return
+    /// * `Some((&str, usize))` - the file and the line number, or `None` if `location` is not a
+    /// `file:line` location.
+    fn parse_file_line<'a>(&self, location: &'a str) -> Option<(&'a str, usize)> {
+        let (file, line) = location.rsplit_once(':')?;
+        if file.is_empty() {
+            return None;
+        }
+        Some((file, line.parse::<usize>().ok()?))
+    }
+
+    /// # brief
+    /// Resolve a bare line number to an address. The line is looked up in the file of the current
+    /// stop location while an inferior is running, or in the file containing `main` otherwise. If
+    /// that file has no code on this line, every file is searched; when several files match, the
+    /// candidates are listed and the user has to pick one with `file:line`.
+    ///
+    /// # param
+    /// - `line` : The line number typed by the user.
+    ///
+    /// # return
+    /// * `Some(Line)` - the line table entry the line resolved to, or `None` (after printing why)
+    /// if it can't be resolved.
+    fn resolve_line(&self, line: usize) -> Option<Line> {
+        let current_file = match (&self.inferior, &self.current_line) {
+            (Some(_), Some(current)) => self.debug_data.get_target_file(&current.file),
+            _ => None,
+        };
+        let default_file = current_file
+            .or_else(|| self.debug_data.get_file_for_function("main"))
+            .or_else(|| self.debug_data.files().get(0));
+        let has_line = |file: &&File| file.lines.iter().any(|l| !l.end_sequence && l.number == line);
+
+        if let Some(file) = default_file.filter(has_line) {
+            return self.line_in_file(file, line);
+        }
+        let candidates: Vec<&File> = self.debug_data.files().iter().filter(has_line).collect();
+        match candidates.len() {
+            0 => match default_file {
+                // no file has code on exactly this line, use the next line with code
+                Some(file) => self.line_in_file(file, line),
+                None => {
+                    println!("Invalid line number");
+                    None
+                }
+            },
+            1 => self.line_in_file(candidates[0], line),
+            _ => {
+                println!("Line {} is ambiguous, it has code in several files:", line);
+                for file in candidates {
+                    println!("  {}:{}", file.name, line);
+                }
+                println!("Use break <file>:{} to choose one", line);
+                None
+            }
+        }
+    }
+
+    /// # brief
+    /// Set a breakpoint at `addr`, with no qualifiers.
+    ///
+    /// # param
+    /// - `addr` : The address of the breakpoint.
+    /// - `spec` : The location as typed, kept in the breakpoint record.
+    fn set_breakpoint(&mut self, addr: usize, spec: &str) {
+        let bp = self.new_breakpoint(addr, spec);
+        self.apply(Change::Created(bp), false);
+    }
+
+    /// # brief
+    /// Install the trap of a breakpoint at `addr`, or record it until the inferior is started.
+    ///
+    /// A temporary step trap at `addr` becomes the breakpoint: its original byte moves to the
+    /// breakpoint, so the breakpoint never records a 0xcc as the original instruction and the
+    /// byte is not lost with the step trap. A breakpoint at the current %rip does not fire right
+    /// away: resuming executes the original instruction first.
+    ///
+    /// # return
+    /// * `false` if there already is a trap at `addr` or it could not be written.
+    fn install_trap(&mut self, addr: usize) -> bool {
+        if self.read_only {
+            println!("Cannot set breakpoint at {:#x}: {}.", addr, READ_ONLY_ERROR);
+            return false;
+        }
+        if self.breakpoints.contains_key(&addr) {
+            println!("Note: breakpoint already set at {:#x}", addr);
+            return false;
+        }
+        if let Some(instruction) = self.step_over_points.remove(&addr) {
+            // the step trap already holds 0xcc in memory, keep it as the breakpoint's
+            self.breakpoints.insert(addr, instruction);
+            self.remember_address(addr);
+            return true;
+        }
+        if let Some(remote) = self.remote.as_ref() {
+            // the stub keeps the original byte itself
+            if let Err(err) = remote.set_breakpoint(addr, true) {
+                println!("Cannot set breakpoint at {:#x}: {}", addr, err);
+                return false;
+            }
+            self.breakpoints.insert(addr, 0);
+            self.remember_address(addr);
+            return true;
+        }
+        if let Some(reason) = self.system_address(addr) {
+            println!("Cannot set breakpoint at {:#x}: {}.", addr, reason);
+            return false;
+        }
+        let inferior = match self.inferior.as_mut() {
+            Some(inferior) if self.traps_installed => inferior,
+            _ => {
+                // when the inferior is initiated, these breakpoints will be installed
+                self.breakpoints.insert(addr, 0);
+                return true;
+            }
+        };
+        let _patch = PatchGuard::new();
+        let instruction = match inferior.write_byte(addr, 0xcc) {
+            Ok(instruction) => instruction,
+            Err(_) => {
+                println!("Invalid breakpoint address {:#x}", addr);
+                return false;
+            }
+        };
+        self.breakpoints.insert(addr, instruction);
+        self.remember_address(addr);
+        true
+    }
+
+    /// # brief
+    /// Explain why no trap may be written at `addr` in the running program: it is in the
+    /// `[vdso]` or `[vvar]` pages of the kernel, or in the dynamic linker, which are not where
+    /// any debug information of the program places code.
+    ///
+    /// # return
+    /// * The reason, or `None` if the address may be patched or there is no process to look at.
+    fn system_address(&self, addr: usize) -> Option<String> {
+        let pid = self.inferior.as_ref()?.pid();
+        let mappings = maps::read_maps(pid).ok()?;
+        let interpreter = auxv::read_auxv(pid).ok().and_then(|auxv| auxv.interpreter_base());
+        let mapping = maps::system_mapping(&mappings, addr, interpreter)?;
+        Some(match mapping.path.as_str() {
+            "[vdso]" => format!("{:#x} is in the [vdso], code the kernel maps into every process, deet does not patch it", addr),
+            "[vvar]" => format!("{:#x} is in [vvar], data of the kernel rather than code", addr),
+            path => format!("{:#x} is in the dynamic linker {}, which runs before the program; break on main instead", addr, path),
+        })
+    }
+
+    /// # brief
+    /// Find the entry point of the program, where `_start` is: the one the kernel reports in
+    /// the auxiliary vector of the running program, which is right for a position independent
+    /// executable too, else the one of the ELF header.
+    ///
+    /// # return
+    /// * The address and where it was found, or `None` if the executable can't be read.
+    fn entry_point(&self) -> Option<(usize, &'static str)> {
+        let runtime = self.inferior.as_ref().and_then(|inferior| auxv::read_auxv(inferior.pid()).ok()?.entry());
+        match runtime {
+            Some(entry) => Some((entry, "AT_ENTRY")),
+            None => maps::elf_entry(&self.target).ok().map(|entry| (entry, "ELF header")),
+        }
+    }
+
+    /// # brief
+    /// Handle `set {<type>} <address> = <value>`: store the value, as an integer of the size of
+    /// `type` (`char`, `short`, `int`, `long` or a pointer), in the memory of the program.
+    fn set_memory(&mut self, text: &str) {
+        const USAGE: &str = "Usage: set {char|short|int|long} <address> = <value>";
+        let parsed = text
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+            .and_then(|(ty, rest)| Some((ty.trim(), rest.split_once('=')?)));
+        let (ty, (addr, value)) = match parsed {
+            Some(parsed) => parsed,
+            None => {
+                println!("{}", USAGE);
+                return;
+            }
+        };
+        let size = match ty.trim_start_matches("unsigned ").trim_start_matches("signed ") {
+            "char" | "i8" | "u8" => 1,
+            "short" | "i16" | "u16" => 2,
+            "int" | "unsigned" | "i32" | "u32" => 4,
+            "long" | "long long" | "i64" | "u64" | "isize" | "usize" => 8,
+            pointer if pointer.ends_with('*') => 8,
+            other => {
+                println!("Unknown type \"{}\". {}", other, USAGE);
+                return;
+            }
+        };
+        let result = self
+            .parse_address(addr)
+            .and_then(|addr| Ok((addr, self.evaluate(value)?)))
+            .and_then(|(addr, value)| Ok((addr, self.write_memory(addr, &value.to_le_bytes()[..size])?)));
+        match result {
+            Ok((_, 0)) => {}
+            Ok((addr, trapped)) => println!(
+                "Note: {} of the bytes written at {} are under a breakpoint; they are kept as the \
+                 instruction put back when the breakpoint is removed.",
+                trapped,
+                self.address_format().address(addr)
+            ),
+            Err(err) => println!("Cannot write memory: {}", err),
+        }
+    }
+
+    /// # brief
+    /// Read memory like `read_memory`, keeping the bytes before the first one that can't be
+    /// read, e.g. past the end of a mapping. A remote target is read in one piece.
+    ///
+    /// # return
+    /// * The bytes read, and why the rest couldn't be if any are missing.
+    fn read_memory_partial(&self, addr: usize, len: usize) -> (Vec<u8>, Option<String>) {
+        let inferior = match (self.remote.as_ref(), self.inferior.as_ref()) {
+            (None, Some(inferior)) => inferior,
+            _ => return self.read_memory(addr, len).map_or_else(|err| (Vec::new(), Some(err.to_string())), |bytes| (bytes, None)),
+        };
+        let (mut bytes, failure) = inferior.read_bytes_partial(addr, len);
+        let traps = self.breakpoints.iter().chain(self.step_over_points.iter()).filter(|_| self.traps_installed);
+        for (bp_addr, orig_byte) in traps {
+            if *bp_addr >= addr && *bp_addr < addr + bytes.len() {
+                bytes[*bp_addr - addr] = *orig_byte;
+            }
+        }
+        (bytes, failure.map(|failure| failure.error.to_string()))
+    }
+
+    /// # brief
+    /// Write `bytes` at `addr` in the program, for the user. Every write the user asks for goes
+    /// through here, as every read goes through `read_memory`: a byte where deet keeps a trap
+    /// (a breakpoint or a step trap) replaces the original byte saved for it instead, so the
+    /// trap still fires and the new value is what the program runs once the trap is removed.
+    ///
+    /// # return
+    /// * `Ok(usize)` - how many bytes went to traps, or `Err(String)` if the memory can't be
+    /// written.
+    fn write_memory(&mut self, addr: usize, bytes: &[u8]) -> Result<usize, String> {
+        if self.read_only {
+            return Err(READ_ONLY_ERROR.to_string());
+        }
+        if self.remote.is_some() {
+            return Err("writing the memory of a remote target is not supported".to_string());
+        }
+        let inferior = self.inferior.as_mut().ok_or("The program is not being run.")?;
+        let mut trapped = 0;
+        let mut memory = bytes.to_vec();
+        // without traps in the process, every byte goes to memory
+        let traps = if self.traps_installed { memory.len() } else { 0 };
+        for (offset, byte) in memory.iter_mut().enumerate().take(traps) {
+            let target = addr + offset;
+            if let Some(orig_byte) = self.breakpoints.get_mut(&target).or_else(|| self.step_over_points.get_mut(&target)) {
+                // the trap stays in memory
+                *orig_byte = *byte;
+                *byte = 0xcc;
+                trapped += 1;
+            }
+        }
+        inferior.write_bytes(addr, &memory).map_err(|err| format!("{:#x}: {}", addr + err.done, err))?;
+        Ok(trapped)
+    }
+
+    /// # brief
+    /// Forget the traps of a process that ended, was killed or replaced its image: their saved
+    /// bytes were its instructions and mean nothing for the next process, in which
+    /// `install_breakpoints` reads the original bytes again. The addresses are kept, and the
+    /// step traps dropped.
+    fn discard_traps(&mut self) {
+        self.traps_installed = false;
+        // the hook in the dynamic linker is planted again in the next process
+        if let Some(inferior) = self.inferior.as_mut() {
+            if let Some(hook) = inferior.library_hook() {
+                self.breakpoints.remove(&hook);
+                inferior.set_library_hook(None);
+            }
+        }
+        for orig_byte in self.breakpoints.values_mut() {
+            *orig_byte = 0;
+        }
+        self.step_over_points.clear();
+    }
+
+    /// # brief
+    /// Remove the trap at `addr`, putting the original instruction back in the running program.
+    fn remove_trap(&mut self, addr: usize) {
+        let _patch = PatchGuard::new();
+        let orig_byte = match self.breakpoints.remove(&addr) {
+            Some(orig_byte) => orig_byte,
+            None => return,
+        };
+        let result = match (self.remote.as_ref(), self.inferior.as_mut()) {
+            (Some(remote), _) => remote.set_breakpoint(addr, false).map_err(|err| err.to_string()),
+            (None, Some(inferior)) if self.traps_installed => {
+                inferior.write_byte(addr, orig_byte).map(|_| ()).map_err(|err| err.to_string())
+            }
+            (None, _) => Ok(()),
+        };
+        if let Err(err) = result {
+            println!("Cannot remove the breakpoint at {:#x}: {}", addr, err);
+        }
+    }
+
+    /// # brief
+    /// Number a new breakpoint at `addr`, anchored to the function containing it. Numbers are
+    /// not reused, so `break undo` can bring a deleted breakpoint back under its own.
+    fn new_breakpoint(&self, addr: usize, spec: &str) -> Breakpoint {
+        let number = self
+            .breakpoint_list
+            .iter()
+            .map(|bp| bp.number + 1)
+            .chain(self.breakpoint_history.iter().map(|mutation| mutation.change.number() + 1))
+            .max()
+            .unwrap_or(0);
+        Breakpoint {
+            number,
+            spec: spec.to_string(),
+            resolution: Resolution::Resolved(addr),
+            anchor: self.anchor_of(addr),
+            thread: None,
+            condition: None,
+            force: false,
+            line: None,
+            dprintf: None,
+            enabled: true,
+            group: None,
+            locations: Vec::new(),
+        }
+    }
+
+    /// # brief
+    /// Install the traps of the locations of a breakpoint, all of them or none.
+    ///
+    /// # return
+    /// * `false` if one could not be installed; those installed before it are removed again.
+    fn install_traps(&mut self, addrs: &[usize]) -> bool {
+        for (index, addr) in addrs.iter().enumerate() {
+            if !self.install_trap(*addr) {
+                for installed in addrs[..index].iter() {
+                    self.remove_trap(*installed);
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    /// # brief
+    /// Make a change to the breakpoints, and record it in their history with the command line
+    /// that made it. Every change of the breakpoints by the user goes through here, so `info
+    /// break history` and `break undo` can't miss one.
+    ///
+    /// # param
+    /// - `is_undo` : Whether the change is made by `break undo`.
+    ///
+    /// # return
+    /// * `false` if the change could not be made, e.g. the trap could not be written; nothing
+    /// is recorded then.
+    fn apply(&mut self, change: Change, is_undo: bool) -> bool {
+        let change = match change {
+            Change::Created(bp) => {
+                match bp.addr() {
+                    Some(_) if !self.install_traps(&bp.traps()) => return false,
+                    Some(addr) => println!(
+                        "Set {} {} at {}{}{}",
+                        if bp.dprintf.is_some() { "dprintf" } else { "breakpoint" },
+                        bp.number,
+                        self.address_format().address(addr),
+                        if bp.locations.is_empty() { String::new() } else { format!(" ({} locations)", bp.locations.len()) },
+                        if bp.enabled { "" } else { " (disabled)" }
+                    ),
+                    None => println!("Breakpoint {} ({}) is {}, see info break", bp.number, bp.spec, bp.resolution),
+                }
+                let index = self.breakpoint_list.partition_point(|other| other.number < bp.number);
+                self.breakpoint_list.insert(index, bp.clone());
+                Change::Created(bp)
+            }
+            Change::Deleted(bp) => {
+                let bp = match self.breakpoint_list.iter().position(|other| other.number == bp.number) {
+                    Some(index) => self.breakpoint_list.remove(index),
+                    None => {
+                        println!("No breakpoint number {}.", bp.number);
+                        return false;
+                    }
+                };
+                for addr in bp.traps() {
+                    if !self.breakpoint_list.iter().any(|other| other.has_trap(addr)) {
+                        self.remove_trap(addr);
+                    }
+                }
+                println!("Deleted breakpoint {} ({})", bp.number, bp.spec);
+                Change::Deleted(bp)
+            }
+            Change::Toggled { number, enabled } => {
+                let index = match self.breakpoint_list.iter().position(|bp| bp.number == number) {
+                    Some(index) => index,
+                    None => {
+                        println!("No breakpoint number {}.", number);
+                        return false;
+                    }
+                };
+                let bp = &self.breakpoint_list[index];
+                if bp.enabled == enabled {
+                    println!("Breakpoint {} is already {}.", number, if enabled { "enabled" } else { "disabled" });
+                    return false;
+                }
+                // a trap is shared by the enabled breakpoints at its address
+                let unshared: Vec<usize> = bp
+                    .enabled_locations()
+                    .into_iter()
+                    .filter(|addr| !self.breakpoint_list.iter().any(|other| other.number != number && other.has_trap(*addr)))
+                    .collect();
+                if enabled && !self.install_traps(&unshared) {
+                    return false;
+                }
+                if !enabled {
+                    for addr in unshared {
+                        self.remove_trap(addr);
+                    }
+                }
+                let bp = &mut self.breakpoint_list[index];
+                bp.enabled = enabled;
+                println!("Breakpoint {} ({}) {}.", number, bp.spec, if enabled { "enabled" } else { "disabled" });
+                Change::Toggled { number, enabled }
+            }
+            Change::LocationToggled { number, location, enabled } => {
+                let index = match self.breakpoint_list.iter().position(|bp| bp.number == number) {
+                    Some(index) => index,
+                    None => {
+                        println!("No breakpoint number {}.", number);
+                        return false;
+                    }
+                };
+                let bp = &self.breakpoint_list[index];
+                let addr = match location.checked_sub(1).and_then(|index| bp.locations.get(index)) {
+                    Some(found) if found.enabled == enabled => {
+                        println!("Breakpoint {}.{} is already {}.", number, location, if enabled { "enabled" } else { "disabled" });
+                        return false;
+                    }
+                    Some(found) => found.addr,
+                    None => {
+                        println!("Breakpoint {} has no location {}.", number, location);
+                        return false;
+                    }
+                };
+                // the trap is only in the program while the breakpoint itself is enabled
+                let shared = self.breakpoint_list.iter().any(|other| other.number != number && other.has_trap(addr));
+                if bp.enabled && !shared {
+                    if enabled && !self.install_trap(addr) {
+                        return false;
+                    }
+                    if !enabled {
+                        self.remove_trap(addr);
+                    }
+                }
+                let bp = &mut self.breakpoint_list[index];
+                bp.locations[location - 1].enabled = enabled;
+                println!("Breakpoint {}.{} ({}) {}.", number, location, bp.spec, if enabled { "enabled" } else { "disabled" });
+                Change::LocationToggled { number, location, enabled }
+            }
+            Change::Regrouped { number, old, new } => {
+                match self.breakpoint_list.iter_mut().find(|bp| bp.number == number) {
+                    Some(bp) => bp.group = new.clone(),
+                    None => {
+                        println!("No breakpoint number {}.", number);
+                        return false;
+                    }
+                }
+                match &new {
+                    Some(group) => println!("Breakpoint {} is in group {}.", number, group),
+                    None => println!("Breakpoint {} is in no group.", number),
+                }
+                Change::Regrouped { number, old, new }
+            }
+            Change::ConditionChanged { number, old, new } => {
+                match self.breakpoint_list.iter_mut().find(|bp| bp.number == number) {
+                    Some(bp) => bp.condition = new.clone(),
+                    None => {
+                        println!("No breakpoint number {}.", number);
+                        return false;
+                    }
+                }
+                match &new {
+                    Some(condition) => println!("Breakpoint {} stops only if {}", number, condition),
+                    None => println!("Breakpoint {} now unconditional.", number),
+                }
+                Change::ConditionChanged { number, old, new }
+            }
+        };
+        self.update_hint_groups();
+        self.breakpoint_history.push_back(Mutation {
+            change,
+            time: SystemTime::now(),
+            command: self.command_line.clone(),
+            is_undo,
+            undone: false,
+        });
+        if self.breakpoint_history.len() > breakpoint::MAX_HISTORY {
+            self.breakpoint_history.pop_front();
+        }
+        true
+    }
+
+    /// # brief
+    /// Handle `break undo`: reverse the latest change of the breakpoints that was not undone
+    /// yet. A deleted breakpoint is set again from its spec, looked up in the current program.
+    /// The reversal is recorded too, but is not undone itself.
+    fn undo_breakpoint_change(&mut self) {
+        let index = match self.breakpoint_history.iter().rposition(|mutation| !mutation.undone && !mutation.is_undo) {
+            Some(index) => index,
+            None => {
+                println!("No breakpoint change to undo.");
+                return;
+            }
+        };
+        let mut inverse = self.breakpoint_history[index].change.inverse();
+        if let Change::Created(bp) = &mut inverse {
+            let (resolution, line, resolved) = self.reresolve_breakpoint(bp);
+            bp.resolution = resolution;
+            bp.line = line.or(bp.line.take());
+            bp.set_locations(&resolved);
+            if let Some(addr) = bp.addr() {
+                bp.anchor = self.anchor_of(addr);
+            }
+        }
+        // marked first, the record may move to the front of the history
+        self.breakpoint_history[index].undone = true;
+        if !self.apply(inverse, true) {
+            self.breakpoint_history[index].undone = false;
+        }
+    }
+
+    /// # brief
+    /// Get the breakpoints `enable`, `disable` and `delete` act on: the numbers given, or the
+    /// members of `group <name>`. The caller handles the case without arguments.
+    ///
+    /// # return
+    /// * The numbers, or `Err(String)` for an empty group or an argument that is not a number.
+    fn selected_breakpoints(&self, command: &str, args: &[String]) -> Result<Vec<usize>, String> {
+        if args.first().map(|arg| arg.as_str()) == Some("group") {
+            let name = match args {
+                [_, name] => name,
+                _ => return Err(format!("Usage: {} group <name>", command)),
+            };
+            let numbers: Vec<usize> =
+                self.breakpoint_list.iter().filter(|bp| bp.group.as_ref() == Some(name)).map(|bp| bp.number).collect();
+            if numbers.is_empty() {
+                return Err(format!("No breakpoints in group {}.", name));
+            }
+            return Ok(numbers);
+        }
+        args.iter()
+            .map(|arg| arg.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Usage: {} [breakpoint numbers...|group <name>]", command))
+    }
+
+    /// # brief
+    /// Handle `delete [N...|group <name>]`: delete the breakpoints numbered `N`, those of a
+    /// group, or all of them after asking.
+    fn delete_breakpoints(&mut self, args: &[String]) {
+        let numbers: Vec<usize> = if args.is_empty() {
+            if self.breakpoint_list.is_empty() {
+                println!("No breakpoints.");
+                return;
+            }
+            if !self.confirm("Delete all breakpoints?") {
+                return;
+            }
+            self.breakpoint_list.iter().map(|bp| bp.number).collect()
+        } else {
+            match self.selected_breakpoints("delete", args) {
+                Ok(numbers) => numbers,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            }
+        };
+        for number in numbers {
+            match self.breakpoint_list.iter().find(|bp| bp.number == number).cloned() {
+                Some(bp) => {
+                    self.apply(Change::Deleted(bp), false);
+                }
+                None => println!("No breakpoint number {}.", number),
+            }
+        }
+    }
+
+    /// # brief
+    /// Handle `enable [N...|N.M...|group <name>]` and `disable [N...|N.M...|group <name>]`: put
+    /// the traps of the breakpoints back, or take them out of the program while keeping the
+    /// breakpoints. `N.M` is the location M of breakpoint N only. Without arguments, all the
+    /// breakpoints are.
+    fn set_breakpoints_enabled(&mut self, args: &[String], enabled: bool) {
+        let command = if enabled { "enable" } else { "disable" };
+        let location = |arg: &String| {
+            let (number, location) = arg.split_once('.')?;
+            Some((number.parse::<usize>().ok()?, location.parse::<usize>().ok()?))
+        };
+        let (locations, args): (Vec<String>, Vec<String>) = args.iter().cloned().partition(|arg| location(arg).is_some());
+        for (number, location) in locations.iter().filter_map(location) {
+            self.apply(Change::LocationToggled { number, location, enabled }, false);
+        }
+        if args.is_empty() && !locations.is_empty() {
+            return;
+        }
+        let numbers = if args.is_empty() {
+            self.breakpoint_list.iter().filter(|bp| bp.enabled != enabled).map(|bp| bp.number).collect()
+        } else {
+            match self.selected_breakpoints(command, &args) {
+                Ok(numbers) => numbers,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            }
+        };
+        for number in numbers {
+            self.apply(Change::Toggled { number, enabled }, false);
+        }
+    }
+
+    /// # brief
+    /// Handle `group add <name> <N...>` and `group remove <N...>`: move breakpoints to a group,
+    /// leaving the one they were in, or take them out of their group.
+    fn group(&mut self, args: &[String]) {
+        const USAGE: &str = "Usage: group add <name> <breakpoint numbers...> | group remove <breakpoint numbers...>";
+        let (group, numbers) = match args {
+            [command, name, numbers @ ..] if command == "add" && !numbers.is_empty() => {
+                if let Err(err) = breakpoint::check_group_name(name) {
+                    println!("{}", err);
+                    return;
+                }
+                (Some(name.clone()), numbers)
+            }
+            [command, numbers @ ..] if command == "remove" && !numbers.is_empty() => (None, numbers),
+            _ => {
+                println!("{}", USAGE);
+                return;
+            }
+        };
+        let numbers = match numbers.iter().map(|arg| arg.parse::<usize>()).collect::<Result<Vec<_>, _>>() {
+            Ok(numbers) => numbers,
+            Err(_) => {
+                println!("{}", USAGE);
+                return;
+            }
+        };
+        for number in numbers {
+            let old = match self.breakpoint_list.iter().find(|bp| bp.number == number) {
+                Some(bp) => bp.group.clone(),
+                None => {
+                    println!("No breakpoint number {}.", number);
+                    continue;
+                }
+            };
+            if old == group {
+                continue;
+            }
+            self.apply(Change::Regrouped { number, old, new: group.clone() }, false);
+        }
+    }
+
+    /// # brief
+    /// Handle `info groups`: list the breakpoint groups with how many breakpoints each has, and
+    /// how many of them are enabled.
+    fn info_groups(&self) {
+        let mut groups: Vec<(&String, usize, usize)> = Vec::new();
+        for bp in self.breakpoint_list.iter() {
+            let group = match &bp.group {
+                Some(group) => group,
+                None => continue,
+            };
+            match groups.iter_mut().find(|(name, _, _)| *name == group) {
+                Some((_, count, enabled)) => {
+                    *count += 1;
+                    *enabled += bp.enabled as usize;
+                }
+                None => groups.push((group, 1, bp.enabled as usize)),
+            }
+        }
+        if groups.is_empty() {
+            println!("No breakpoint groups; set one with break <location> group=<name>.");
+            return;
+        }
+        groups.sort();
+        let mut table = Table::new(&["Group", "Breakpoints", "Enabled"]);
+        for (name, count, enabled) in groups {
+            table.row(vec![name.clone(), count.to_string(), enabled.to_string()]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `condition N [expr]`: make breakpoint `N` stop only where `expr` is non-zero, or
+    /// always without an expression.
+    fn condition(&mut self, text: &str) {
+        let text = text.trim();
+        let (number, condition) = match text.split_once(char::is_whitespace) {
+            Some((number, condition)) => (number, Some(condition.trim().to_string())),
+            None => (text, None),
+        };
+        let number = match number.parse::<usize>() {
+            Ok(number) => number,
+            Err(_) => {
+                println!("Usage: condition <breakpoint number> [expression]");
+                return;
+            }
+        };
+        if let Some(condition) = &condition {
+            if let Err(err) = expr::parse(condition) {
+                println!("Invalid condition \"{}\": {}", condition, err);
+                return;
+            }
+        }
+        let old = match self.breakpoint_list.iter().find(|bp| bp.number == number) {
+            Some(bp) => bp.condition.clone(),
+            None => {
+                println!("No breakpoint number {}.", number);
+                return;
+            }
+        };
+        self.apply(Change::ConditionChanged { number, old, new: condition }, false);
+    }
+
+    /// # brief
+    /// Handle `break <location> [force] [thread N] [group=<name>] [disabled] [if <cond>]`. An
+    /// address typed by hand that is in the middle of an instruction is refused unless `force`
+    /// is given.
+    fn breakpoint(&mut self, text: &str) {
+        if text.trim() == "undo" {
+            self.undo_breakpoint_change();
+            return;
+        }
+        self.create_breakpoint(text, None);
+    }
+
+    /// # brief
+    /// Handle `dprintf <location>, "format", args... [thread N] [if <cond>]`: set a breakpoint
+    /// that prints the message each time it is hit, and lets the program go on.
+    fn dprintf(&mut self, words: &[String]) {
+        let (text, dprintf) = match Dprintf::parse(words) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        if let Some((arg, err)) = dprintf.args.iter().find_map(|arg| expr::parse(arg).err().map(|err| (arg, err))) {
+            println!("Invalid argument \"{}\": {}", arg, err);
+            return;
+        }
+        self.create_breakpoint(&text, Some(dprintf));
+    }
+
+    /// # brief
+    /// Set a breakpoint from the arguments of `break`, printing a message instead of stopping if
+    /// `dprintf` is given.
+    fn create_breakpoint(&mut self, text: &str, dprintf: Option<Dprintf>) {
+        let (location, Qualifiers { thread, condition, force, group, disabled }) = match breakpoint::parse_qualifiers(text) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        // threads are numbered in the order they are created, a thread of the next run is
+        // taken on trust
+        let running = self.inferior.as_ref().filter(|inferior| !inferior.threads().is_empty());
+        if let (Some(thread), Some(inferior)) = (thread, running) {
+            if !inferior.threads().iter().any(|known| known.number == thread) {
+                println!("Unknown thread {}.", thread);
+                return;
+            }
+        }
+        if let Some(condition) = &condition {
+            if let Err(err) = expr::parse(condition) {
+                println!("Invalid condition \"{}\": {}", condition, err);
+                return;
+            }
+        }
+        // a function of a library the program loads later, e.g. with dlopen, is found then
+        if breakpoint::is_function_name(&location) && !self.knows_function(&location) {
+            if !self.confirm(&format!("Function \"{}\" not defined. Make breakpoint pending on future shared library load?", location)) {
+                return;
+            }
+            let pending = self.new_breakpoint(0, &location);
+            let bp = Breakpoint { thread, condition, force, dprintf, enabled: !disabled, group, resolution: Resolution::Pending, anchor: None, ..pending };
+            self.apply(Change::Created(bp), false);
+            return;
+        }
+        let resolved = match self.resolve_locations(&location) {
+            Some(resolved) => resolved,
+            None => return,
+        };
+        let (location, resolved) = match self.pick_function(location, resolved) {
+            Some(picked) => picked,
+            None => return,
+        };
+        let (addr, line) = resolved[0].clone();
+        // a relative location is kept as the line it resolved to, e.g. for `save session`
+        let (location, requested) = match self.anchored_line(&location) {
+            Some(Ok((file, number))) => (format!("{}:{}", self.source_paths.to_local(&file), number), Some(number)),
+            _ => {
+                let requested = location.rsplit(':').next().and_then(|number| number.parse::<usize>().ok());
+                (location, requested)
+            }
+        };
+        if let Some(line) = &line {
+            if let Some(requested) = requested.filter(|requested| *requested != line.number) {
+                let file = self.source_paths.to_local(&line.file);
+                println!("No code at {}:{}, breakpoint set at {}:{} ({:#x})", file, requested, file, line.number, addr);
+            }
+        }
+        // lines and functions come from the line table, which only has instruction starts; an
+        // address typed by hand is checked once the code can be read
+        if location.starts_with('*') && !force && self.inferior.is_some() {
+            if let Some(start) = self.instruction_containing(addr) {
+                self.warn_inside_instruction(addr, start);
+                println!("Use `break {} force` to set it anyway.", location);
+                return;
+            }
+        }
+        let mut bp = Breakpoint { thread, condition, force, line, dprintf, enabled: !disabled, group, ..self.new_breakpoint(addr, &location) };
+        bp.set_locations(&resolved);
+        self.apply(Change::Created(bp), false);
+    }
+
+    /// # brief
+    /// Check whether the function `name` is in the program or a shared library it loaded.
+    fn knows_function(&self, name: &str) -> bool {
+        name == "_start"
+            || !self.debug_data.functions_named(name).is_empty()
+            || self.debug_data.get_addr_for_function(None, name).is_some()
+            || self.library_function(name).is_some()
+    }
+
+    /// # brief
+    /// Ask which function a new breakpoint is for when its location names several, with the
+    /// menu of `choose`.
+    ///
+    /// # return
+    /// * The spec of the breakpoint and its locations: the name with every function, or the
+    /// `*address` of the function picked, which no other function shares. `None` if the menu was
+    /// cancelled.
+    fn pick_function(
+        &mut self,
+        location: String,
+        resolved: Vec<(usize, Option<Line>)>,
+    ) -> Option<(String, Vec<(usize, Option<Line>)>)> {
+        // each function as the menu shows it, with its address
+        let functions: Vec<(String, usize)> = self
+            .debug_data
+            .functions_named(&location)
+            .iter()
+            .map(|(file, func)| {
+                let choice = format!("{} at {}:{}", func.name, self.source_paths.to_local(&file.name), func.line_number);
+                (choice, func.address)
+            })
+            .collect();
+        if functions.len() < 2 {
+            return Some((location, resolved));
+        }
+        let choices: Vec<String> = functions.iter().map(|(choice, _)| choice.clone()).collect();
+        match self.choose(&format!("Function \"{}\" matches {} functions:", location, choices.len()), &choices)? {
+            Choice::All => Some((location, resolved)),
+            Choice::One(index) => {
+                let addr = functions[index].1;
+                Some((format!("*{:#x}", addr), vec![(addr, self.debug_data.line_at(addr as u64))]))
+            }
+        }
+    }
+
+    /// # brief
+    /// Check that `addr` begins an instruction, by decoding the function containing it from its
+    /// entry. Addresses outside any function, or behind an instruction that can't be decoded,
+    /// can't be checked.
+    ///
+    /// # return
+    /// * The start of the instruction `addr` is in the middle of, `None` if it begins one or
+    /// can't be checked.
+    fn instruction_containing(&self, addr: usize) -> Option<usize> {
+        let (_, func) = self.debug_data.function_containing(addr)?;
+        let code = self.read_memory(func.address, addr + 1 - func.address).ok()?;
+        match disasm::boundary(&code, func.address, addr) {
+            Boundary::Inside(start) => Some(start),
+            Boundary::Start | Boundary::Unknown => None,
+        }
+    }
+
+    /// # brief
+    /// Warn that a breakpoint at `addr` would overwrite the middle of the instruction at `start`.
+    fn warn_inside_instruction(&self, addr: usize, start: usize) {
+        let anchor = match self.anchor_of(start) {
+            Some((name, offset)) => format!(" ({}+{:#x})", name, offset),
+            None => String::new(),
+        };
+        println!(
+            "warning: {:#x} is in the middle of the instruction at {:#x}{}; a trap there would corrupt it.",
+            addr, start, anchor
+        );
+    }
+
+    /// # brief
+    /// Install the breakpoints in the inferior just started or attached to, and report them in
+    /// one line. A breakpoint whose trap could not be written is marked broken; `file` places it
+    /// again. The progress is shown for more than `INSTALL_PROGRESS_THRESHOLD` breakpoints, and
+    /// Ctrl+C leaves the rest out, marked broken as well.
+    fn install_breakpoints(&mut self) {
+        // no trap is ever written, so detaching leaves the process as it was
+        if self.read_only {
+            return;
+        }
+        self.relocate_entry_breakpoints();
+        let refused: Vec<(usize, String)> = self
+            .breakpoints
+            .keys()
+            .filter_map(|addr| Some((*addr, self.system_address(*addr)?)))
+            .collect();
+        for (addr, _) in refused.iter() {
+            self.breakpoints.remove(addr);
+        }
+        let total = self.breakpoints.len();
+        // the progress depends on timing, so it is left out of deterministic output
+        let show_progress = total > INSTALL_PROGRESS_THRESHOLD && !ui::quiet() && !ui::deterministic();
+        let progress = |done: usize, total: usize| {
+            if show_progress {
+                eprint!("\rInstalling {} breakpoints... {}%", total, done * 100 / total);
+                std::io::Write::flush(&mut std::io::stderr()).ok();
+            }
+        };
+        let cancel = CancelToken::new();
+        let (installed, interrupted) =
+            self.inferior.as_mut().unwrap().install_breakpoints(&mut self.breakpoints, &cancel, &progress);
+        drop(cancel);
+        if show_progress {
+            ui::notify(&format!("\rInstalling {} breakpoints... {}.", total, if interrupted { "interrupted" } else { "done" }));
+        }
+        // the breakpoints left out by Ctrl+C are not in the program, as if their trap failed
+        let left_out: Vec<usize> =
+            self.breakpoints.keys().filter(|addr| !installed.iter().any(|(done, _)| done == *addr)).copied().collect();
+        let mut results: Vec<(usize, Result<(), String>)> = installed
+            .into_iter()
+            .map(|(addr, result)| (addr, result.map_err(|err| err.to_string())))
+            .chain(refused.into_iter().map(|(addr, reason)| (addr, Err(reason))))
+            .collect();
+        self.traps_installed = true;
+        if results.is_empty() && left_out.is_empty() {
+            return;
+        }
+        results.sort_by_key(|(addr, _)| *addr);
+        let installed = results.iter().filter(|(_, result)| result.is_ok()).count();
+        let mut report = format!("Installed {} breakpoint{}", installed, if installed == 1 { "" } else { "s" });
+        if !left_out.is_empty() {
+            report.push_str(&format!("; interrupted, {} not installed and marked broken, `file` places them again", left_out.len()));
+        }
+        for addr in left_out {
+            self.breakpoints.remove(&addr);
+            for bp in self.breakpoint_list.iter_mut().filter(|bp| bp.enabled_locations().contains(&addr)) {
+                bp.resolution = Resolution::Broken("not installed, the installation was interrupted".to_string());
+            }
+        }
+        for (addr, result) in results {
+            let err = match result {
+                Ok(()) => continue,
+                Err(err) => err,
+            };
+            self.breakpoints.remove(&addr);
+            for bp in self.breakpoint_list.iter_mut().filter(|bp| bp.enabled_locations().contains(&addr)) {
+                report.push_str(&format!("; breakpoint {} ({}) could not be installed: {}", bp.number, bp.spec, err));
+                bp.resolution = Resolution::Broken(format!("could not be installed: {}", err));
+            }
+        }
+    }
+
+    /// # brief
+    /// Move the breakpoints on `_start` set before the program ran to the entry point the kernel
+    /// reports, which differs from the one of the ELF header in a position independent executable.
+    fn relocate_entry_breakpoints(&mut self) {
+        let entry = match self.entry_point() {
+            Some((entry, "AT_ENTRY")) => entry,
+            _ => return,
+        };
+        let moved: Vec<usize> = self
+            .breakpoint_list
+            .iter()
+            .filter(|bp| bp.spec == "_start")
+            .flat_map(|bp| bp.traps())
+            .filter(|addr| *addr != entry)
+            .collect();
+        for bp in self.breakpoint_list.iter_mut().filter(|bp| bp.spec == "_start") {
+            bp.resolution = Resolution::Resolved(entry);
+        }
+        for addr in moved {
+            if !self.breakpoint_list.iter().any(|bp| bp.has_trap(addr)) {
+                self.breakpoints.remove(&addr);
+            }
+            self.breakpoints.insert(entry, 0);
+        }
+    }
+
+    /// # brief
+    /// Check the address breakpoints set before the inferior existed, now that its code can be
+    /// read. One in the middle of an instruction is removed from the code and marked broken,
+    /// unless it was set with `force`.
+    fn check_pending_breakpoints(&mut self) {
+        for index in 0..self.breakpoint_list.len() {
+            let bp = &self.breakpoint_list[index];
+            let addr = match bp.addr() {
+                Some(addr) if bp.is_address() && !bp.force => addr,
+                _ => continue,
+            };
+            let start = match self.instruction_containing(addr) {
+                Some(start) => start,
+                None => continue,
+            };
+            self.warn_inside_instruction(addr, start);
+            if let Some(orig_byte) = self.breakpoints.remove(&addr) {
+                let _ = self.inferior.as_mut().unwrap().write_byte(addr, orig_byte);
+            }
+            let bp = &mut self.breakpoint_list[index];
+            bp.resolution = Resolution::Broken(format!("inside the instruction at {:#x}", start));
+            println!("Breakpoint {} ({}) is broken, see info break; set it again with force to keep it.", bp.number, bp.spec);
+        }
+    }
+
+    /// # brief
+    /// Check whether a stop at the trap of a breakpoint is to be reported: the thread of one of
+    /// the breakpoints there matches and its condition holds. A `dprintf` prints its message
+    /// then, and doesn't stop. Other stops are always reported. A condition or a message that
+    /// can't be evaluated stops, with the error printed.
+    fn breakpoint_should_stop(&mut self, rip: usize) -> bool {
+        let hit: Vec<Breakpoint> = self.breakpoint_list.iter().filter(|bp| bp.has_trap(rip)).cloned().collect();
+        if hit.is_empty() {
+            return true;
+        }
+        // a stop resumed past is a stop of its own, maybe of another thread
+        self.context.on_stop(self.identity());
+        let current = self.inferior.as_ref().and_then(|inferior| inferior.current_thread()).map(|thread| thread.number);
+        let mut stop = false;
+        for bp in hit {
+            stats::record(|stats| *stats.breakpoint_hits.entry(bp.number).or_insert(0) += 1);
+            if bp.thread.is_some() && bp.thread != current {
+                continue;
+            }
+            if let Some(condition) = &bp.condition {
+                match expr::eval(condition, self) {
+                    Ok(0) => continue,
+                    Ok(_) => {}
+                    Err(err) => {
+                        println!("Error in the condition of breakpoint {}: {}", bp.number, err);
+                        stop = true;
+                        continue;
+                    }
+                }
+            }
+            match &bp.dprintf {
+                Some(dprintf) => stop |= !self.print_dprintf(bp.number, dprintf),
+                None => stop = true,
+            }
+        }
+        // the condition of `continue until` holds for this resume only, on top of the others
+        let condition = match &self.until {
+            Some(until) if stop && until.addr == rip => until.condition.clone(),
+            _ => return stop,
+        };
+        match expr::eval(&condition, self) {
+            Ok(0) => {
+                self.until.as_mut().unwrap().skipped += 1;
+                false
+            }
+            Ok(_) => true,
+            Err(err) => {
+                println!("Error in the condition of continue until: {}", err);
+                true
+            }
+        }
+    }
+
+    /// # brief
+    /// Handle `continue until <expr>`: resume from a breakpoint, and at each later stop at the
+    /// same place resume again while the expression is false. The breakpoint keeps its own
+    /// condition. Any other stop, Ctrl+C included (it stops the inferior with SIGINT), ends the
+    /// command.
+    fn continue_until(&mut self, condition: &str) {
+        if condition.is_empty() {
+            println!("Usage: continue until <expression>");
+            return;
+        }
+        if let Err(err) = expr::parse(condition) {
+            println!("{}", err);
+            return;
+        }
+        if self.remote.is_some() {
+            println!("Error: continue until is not supported on a remote target");
+            return;
+        }
+        if self.inferior.is_none() {
+            println!("Error: you can not use continue when there is no process running!");
+            return;
+        }
+        let rip = self.register("rip").map(|rip| rip as usize);
+        let (number, addr) = match self.breakpoint_list.iter().find(|bp| rip.map_or(false, |rip| bp.has_trap(rip))) {
+            Some(bp) => (bp.number, rip.unwrap()),
+            None => {
+                println!("Not stopped at a breakpoint: continue until needs one to stop at again.");
+                return;
+            }
+        };
+        self.until = Some(Until { addr, condition: condition.to_string(), skipped: 0 });
+        let result = self.resume(None, None);
+        let until = self.until.take().unwrap();
+        match result {
+            Ok((status, _)) => {
+                let held = matches!(status, Status::Stopped(nix::sys::signal::Signal::SIGTRAP, rip) if rip == addr);
+                self.handle_status(status);
+                if held {
+                    println!("{} held after skipping {} stops at breakpoint {}.", until.condition, until.skipped, number);
+                } else {
+                    println!("Stopped before {} held, after skipping {} stops at breakpoint {}.", until.condition, until.skipped, number);
+                }
+            }
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+
+    /// # brief
+    /// Print the message of the `dprintf` breakpoint `number`, with its arguments evaluated at
+    /// the current stop. The message goes to the output of deet, not to the program's.
+    ///
+    /// # return
+    /// * `false` if an argument could not be evaluated, after printing why.
+    fn print_dprintf(&mut self, number: usize, dprintf: &Dprintf) -> bool {
+        let mut values = Vec::new();
+        for arg in dprintf.args.iter() {
+            match expr::eval(arg, self) {
+                Ok(value) => values.push(value),
+                Err(err) => {
+                    println!("Error in the arguments of dprintf {}: {}", number, err);
+                    return false;
+                }
+            }
+        }
+        let memory = |addr: usize, len: usize| self.read_memory(addr, len).ok();
+        let ty = Type::default();
+        let reader = ValueReader::new(&ty, &[], &memory, &|_| None, self.element_limit(false));
+        let message = dprintf.render(&values, |addr| match reader.c_string(addr as usize) {
+            Some((text, false)) => text,
+            Some((text, true)) => format!("{}...", text),
+            None => format!("<error reading {:#x}>", addr),
+        });
+        print!("{}", message);
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+        true
+    }
+
+    /// # brief
+    /// Resume the inferior until a stop to report. Stops at a breakpoint for another thread or
+    /// whose condition is false resume it again, without returning to the prompt; the trap
+    /// stays armed.
+    ///
+    /// # param
+    /// - `signal` : The signal to deliver as the inferior resumes, none if `None`.
+    ///
+    /// # return
+    /// * The status, and whether the inferior was stopped by the deadline, or the request on the
+    /// inferior that failed.
+    fn resume(&mut self, signal: Option<nix::sys::signal::Signal>, deadline: Option<Instant>) -> Result<(Status, bool), TraceError> {
+        let mut signal = signal;
+        loop {
+            let (status, timed_out) = self
+                .inferior
+                .as_mut()
+                .unwrap()
+                .continue_run(signal.take(), &self.breakpoints, &mut self.step_over_points, deadline)?;
+            let hook = self.inferior.as_ref().and_then(|inferior| inferior.library_hook());
+            match status {
+                // the dynamic linker changed the libraries
+                Status::Stopped(nix::sys::signal::Signal::SIGTRAP, rip) if !timed_out && Some(rip) == hook => self.library_event(),
+                Status::Stopped(nix::sys::signal::Signal::SIGTRAP, rip) if !timed_out && !self.breakpoint_should_stop(rip) => {}
+                _ => return Ok((status, timed_out)),
+            }
+        }
+    }
+
+    /// # brief
+    /// Describe the functions the code at `addr` was inlined into, as the end of a stop report:
+    /// ` (inlined from middle() at a.c:10, from outer() at b.c:30)`, empty when nothing was
+    /// inlined there.
+    fn inlined_from(&self, addr: usize) -> String {
+        let frames = self.debug_data.inlined_frames(addr as u64);
+        let callers: Vec<String> = frames
+            .iter()
+            .skip(1)
+            .map(|frame| match &frame.line {
+                Some(line) => format!("{}() at {}:{}", frame.function, self.source_paths.to_local(&line.file), line.number),
+                None => format!("{}()", frame.function),
+            })
+            .collect();
+        if callers.is_empty() {
+            return String::new();
+        }
+        format!(" (inlined from {})", callers.join(", from "))
+    }
+
+    /// # brief
+    /// Get the function containing `addr` and the offset of `addr` into it.
+    fn anchor_of(&self, addr: usize) -> Option<(String, usize)> {
+        let (_, func) = self.debug_data.function_containing(addr)?;
+        Some((func.name.clone(), addr - func.address))
+    }
+
+    /// # brief
+    /// Find the code of `line` in `file`, or of the next line with code when it has none.
+    ///
+    /// # return
+    /// * The line table entry, or `None` (after printing why) if `line` is past the last line
+    /// with code of the file.
+    fn line_in_file(&self, file: &File, line: usize) -> Option<Line> {
+        let found = self.debug_data.get_addr_for_line(Some(&file.name), line);
+        if found.is_none() {
+            let last = file.lines.iter().filter(|l| !l.end_sequence).map(|l| l.number).max().unwrap_or(0);
+            println!(
+                "line {} is past the end of {} (last line with code: {})",
+                line,
+                self.source_paths.to_local(&file.name),
+                last
+            );
+        }
+        found
+    }
+
+    /// # brief
+    /// Resolve a location relative to the current line, the line of the last stop: `.` for the
+    /// line itself, `+N` and `-N` for the lines after and before it.
+    ///
+    /// # return
+    /// * `None` if `location` is not relative, else the recorded file and the line number, or
+    /// why there is none.
+    fn anchored_line(&self, location: &str) -> Option<Result<(String, usize), String>> {
+        let offset = match location.chars().next()? {
+            '.' if location.len() == 1 => 0,
+            '+' | '-' => location.parse::<i64>().ok()?,
+            _ => return None,
+        };
+        let current = match &self.current_line {
+            Some(current) => current,
+            None => return Some(Err("No current location: the program has not stopped yet.".to_string())),
+        };
+        let number = current.number as i64 + offset;
+        if number < 1 {
+            return Some(Err(format!("Line {} is before the start of {}.", number, self.source_paths.to_local(&current.file))));
+        }
+        Some(Ok((current.file.clone(), number as usize)))
+    }
+
+    /// # brief
+    /// Resolve a location in the last listing: `list:N` for its Nth line, so a line on screen
+    /// can be picked without counting from the current one.
+    ///
+    /// # return
+    /// * `None` if `location` is not one, else the recorded file and the line number, or why
+    /// there is none.
+    fn listed_line(&self, location: &str) -> Option<Result<(String, usize), String>> {
+        let index = location.strip_prefix("list:")?.parse::<usize>().ok()?;
+        let (file, first, last) = match &self.list_position {
+            Some(position) => position,
+            None => return Some(Err("No listing yet: list:N needs a list first.".to_string())),
+        };
+        if index == 0 || first + index - 1 > *last {
+            return Some(Err(format!("The last listing has {} lines.", last + 1 - first)));
+        }
+        Some(Ok((file.clone(), first + index - 1)))
+    }
+
+    /// # brief
+    /// Resolve a breakpoint location: `*address`, `line`, `file:line`, `func`, `.`, `+N` and
+    /// `-N` relative to the current line, or `list:N` for a line of the last listing. Errors are
+    /// printed.
+    ///
+    /// # return
+    /// * The address, and for a line location the line table entry it resolved to, which is
+    /// a later line when the one asked for has no code.
+    fn resolve_location(&self, location: &str) -> Option<(usize, Option<Line>)> {
+        let line = if let Some(anchored) = self.anchored_line(location).or_else(|| self.listed_line(location)) {
+            let (file, number) = match anchored {
+                Ok(anchored) => anchored,
+                Err(err) => {
+                    println!("{}", err);
+                    return None;
+                }
+            };
+            match self.debug_data.get_target_file(&file) {
+                Some(file) => self.line_in_file(file, number),
+                None => {
+                    println!("No source file named {}.", self.source_paths.to_local(&file));
+                    None
+                }
+            }
+        } else if location.starts_with("*") {
+            return match self.parse_address(&location[1..]) {
+                Ok(address) => Some((address, None)),
+                Err(err) => {
+                    println!("{}", err);
+                    None
+                }
+            };
+        } else if let Some(line) = usize::from_str_radix(location, 10).ok() {
+            self.resolve_line(line)
+        } else if let Some((file, line)) = self.parse_file_line(location) {
+            // the user types a local path, the debug data knows the recorded one
+            let recorded = self.source_paths.to_recorded(file);
+            match self.debug_data.get_target_file(&recorded) {
+                Some(file) => self.line_in_file(file, line),
+                None => {
+                    println!("No source file named {}.", file);
+                    None
+                }
+            }
+        } else if location == "_start" {
+            return match self.entry_point() {
+                Some((entry, _)) => Some((entry, None)),
+                None => {
+                    println!("Cannot read the entry point of {}.", self.target);
+                    None
+                }
+            };
+        } else if let Some(address) = self.debug_data.get_addr_for_function(None, location) {
+            return Some((address, None));
+        } else if let Some((address, _)) = self.library_function(location) {
+            return Some((address, None));
+        } else {
+            println!("Usage b|break|breakpoint *address|line|file:line|func|.|+offset|-offset");
+            return None;
+        };
+        line.map(|line| (line.address, Some(line)))
+    }
+
+    /// # brief
+    /// Resolve a breakpoint location that may have code at several addresses: a line of a
+    /// header has a copy in each compilation unit including it, and so do the inline functions
+    /// and macros it defines; a function name may stand for several functions, see
+    /// `DwarfData::functions_named`. Other locations are resolved by `resolve_location`.
+    ///
+    /// # return
+    /// * The addresses sorted, with the line table entry of each, at least one; `None` if the
+    /// location could not be resolved, the error printed.
+    fn resolve_locations(&self, location: &str) -> Option<Vec<(usize, Option<Line>)>> {
+        let spec = match self.anchored_line(location).or_else(|| self.listed_line(location)) {
+            Some(Ok(anchored)) => Some(anchored),
+            Some(Err(_)) => None,
+            None => self.parse_file_line(location).map(|(file, line)| (self.source_paths.to_recorded(file), line)),
+        };
+        if let Some((file, number)) = spec {
+            let lines = self.debug_data.line_locations(&file, number);
+            // a header is no compilation unit, `resolve_location` would not find it
+            if lines.len() > 1 || (lines.len() == 1 && self.debug_data.get_target_file(&file).is_none()) {
+                return Some(lines.into_iter().map(|line| (line.address, Some(line))).collect());
+            }
+        } else if !location.starts_with('*') && location.parse::<usize>().is_err() {
+            // overloads, statics of the same name and the instances of a generic
+            let functions = self.debug_data.functions_named(location);
+            if !functions.is_empty() {
+                return Some(functions.iter().map(|(_, func)| (func.address, None)).collect());
+            }
+        }
+        self.resolve_location(location).map(|resolved| vec![resolved])
+    }
+
+    /// # brief
+    /// Get the address past the prologue of a function: the first statement of a line after
+    /// the one the function starts at.
+    fn post_prologue_address(&self, addr: usize) -> usize {
+        let func = match self.debug_data.function_containing(addr) {
+            Some((_, func)) => func,
+            None => return addr,
+        };
+        let rows = self.debug_data.get_line_entries_in_range(func.address, func.address + func.text_length);
+        let first_line = rows.first().map(|row| row.number);
+        rows.iter()
+            .find(|row| row.is_stmt && !row.end_sequence && Some(row.number) != first_line)
+            .map_or(addr, |row| row.address)
+    }
+
+    /// # brief
+    /// Handle `step --into [function]`: step the current line like `step`, but enter the named
+    /// function when the line calls it, or the first function called that is not skipped. The
+    /// step ends past the prologue of the function entered.
+    fn step_into(&mut self, name: Option<&str>) {
+        if let Some(name) = name {
+            if self.debug_data.find_function(name).is_empty() {
+                println!("No function named {}.", name);
+                return;
+            }
+        }
+        if !self.leave_plt() {
+            return;
+        }
+        let (debug_data, skips, into_plt) = (&self.debug_data, &self.skips, self.step_into_plt);
+        // with `set step-into-plt on`, the stub of a library call counts as a function to enter
+        let enter = |addr: usize| {
+            enters_function(debug_data, skips, addr, name) || (into_plt && name.is_none() && debug_data.plt_stub(addr) == Some(true))
+        };
+        let inferior = self.inferior.as_mut().unwrap();
+        let mut status = match inferior.step_over(&self.breakpoints, &mut self.step_over_points, None, &self.debug_data, Some(&enter)) {
+            Ok(status) => status,
+            Err(err) => {
+                println!("Error: {}", err);
+                return;
+            }
+        };
+        if let Status::Stopped(_, rip) = status {
+            if enters_function(&self.debug_data, &self.skips, rip, name) {
+                let addr = self.post_prologue_address(rip);
+                status = match self.inferior.as_mut().unwrap().run_to(addr, &self.breakpoints, &mut self.step_over_points) {
+                    Ok(status) => status,
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        return;
+                    }
+                };
+            } else if let Some(name) = name {
+                println!("The line finished without calling {}.", name);
+            }
+        }
+        self.handle_status(status);
+    }
+
+    /// # brief
+    /// Get out of a PLT stub before a step, e.g. after `stepi` into a call to `printf`: unless
+    /// `set step-into-plt` is on, the library function the stub leads to is run back to its
+    /// caller, so that the step goes on as if the call was stepped over.
+    ///
+    /// # return
+    /// * `true` if the step goes on, `false` if it ended at another stop, reported already.
+    fn leave_plt(&mut self) -> bool {
+        let rip = match self.register("rip") {
+            Some(rip) => rip as usize,
+            None => return true,
+        };
+        // further in a stub the return address is no longer on top of the stack
+        if self.step_into_plt || self.debug_data.plt_stub(rip) != Some(true) {
+            return true;
+        }
+        println!("Run till the library call of the PLT stub at {} returns", self.address_format().address(rip));
+        match self.inferior.as_mut().unwrap().finish(rip, &self.breakpoints, &mut self.step_over_points) {
+            Ok(Status::Stopped(nix::sys::signal::Signal::SIGTRAP, addr)) if !self.breakpoints.contains_key(&addr) => true,
+            Ok(status) => {
+                self.handle_status(status);
+                false
+            }
+            Err(err) => {
+                println!("Error: {}", err);
+                false
+            }
+        }
+    }
+
+    /// # brief
+    /// Handle `finish`: run until the current function returns to its caller. In a recursive
+    /// function, the stop is at the return of the current invocation.
+    fn finish(&mut self) {
+        let rip = match self.register("rip") {
+            Some(rip) => rip as usize,
+            None => {
+                println!("Error: you can not use finish when there is no process running");
+                return;
+            }
+        };
+        let func_addr = match self.debug_data.function_containing(rip) {
+            Some((_, func)) if func.name == "main" => {
+                println!("\"finish\" not meaningful in the outermost frame.");
+                return;
+            }
+            Some((_, func)) => {
+                println!("Run till exit from {}", func.name);
+                func.address
+            }
+            None => {
+                println!("Cannot finish: no function contains {:#x}", rip);
+                return;
+            }
+        };
+        match self.inferior.as_mut().unwrap().finish(func_addr, &self.breakpoints, &mut self.step_over_points) {
+            Ok(status) => self.handle_status(status),
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+
+    /// # brief
+    /// Handle `skip`: list the skipped functions and files, `skip [function] <name>` and `skip
+    /// file <name>` add one, `skip delete [n]` removes one or all.
+    fn skip(&mut self, args: &[String]) {
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let skip = match args.as_slice() {
+            [] => {
+                if self.skips.is_empty() {
+                    println!("Not skipping any files or functions.");
+                }
+                for (index, skip) in self.skips.iter().enumerate() {
+                    println!("{:<5}{}", index + 1, skip);
+                }
+                return;
+            }
+            ["delete"] => {
+                if !self.skips.is_empty() && self.confirm("Delete all skips?") {
+                    self.skips.clear();
+                }
+                return;
+            }
+            ["delete", number] => {
+                match number.parse::<usize>() {
+                    Ok(number) if number >= 1 && number <= self.skips.len() => {
+                        self.skips.remove(number - 1);
+                    }
+                    _ => println!("No skip number {}.", number),
+                }
+                return;
+            }
+            ["file", name] => Skip::File(name.to_string()),
+            ["function", name] | [name] => Skip::Function(name.to_string()),
+            _ => {
+                println!("Usage: skip [function] <name> | skip file <name> | skip delete [n]");
+                return;
+            }
+        };
+        println!("Step into will skip {}.", skip);
+        self.skips.push(skip);
+    }
+
+    /// # brief
+    /// Handle `rbreak <regex>`: set a breakpoint past the prologue of every function whose name
+    /// matches. More than `rbreak_limit` breakpoints are only set once the user confirms.
+    fn regex_breakpoint(&mut self, pattern: &str) {
+        if pattern.is_empty() {
+            println!("Usage: rbreak <regex>");
+            return;
+        }
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                println!("Invalid regex \"{}\": {}", pattern, err);
+                return;
+            }
+        };
+        let cancel = CancelToken::new();
+        let (functions, interrupted) = self.debug_data.functions_matching(|name| regex.is_match(name), &cancel);
+        if interrupted {
+            println!("(interrupted) {} functions matched \"{}\" so far, no breakpoint set.", functions.len(), pattern);
+            return;
+        }
+        let mut matches: Vec<(String, usize, String, usize)> = functions
+            .into_iter()
+            // declarations and abstract instances of inlined functions have no code
+            .filter(|(_, func)| func.text_length > 0)
+            // past the prologue, so the arguments can be read at the stop
+            .map(|(file, func)| (func.name.clone(), self.post_prologue_address(func.address), file.name.clone(), func.line_number))
+            .collect();
+        matches.sort_by_key(|(_, address, _, _)| *address);
+        matches.dedup_by_key(|(_, address, _, _)| *address);
+        if matches.is_empty() {
+            println!("No function matches \"{}\".", pattern);
+            return;
+        }
+        if matches.len() > self.rbreak_limit {
+            let question = format!(
+                "{} functions match \"{}\", more than the limit of {} (set rbreak-limit). Set them all?",
+                matches.len(), pattern, self.rbreak_limit
+            );
+            if !self.confirm(&question) {
+                println!("Not confirmed, no breakpoint set.");
+                return;
+            }
+        }
+        let before = self.breakpoint_list.len();
+        for (name, address, file, line) in &matches {
+            self.set_breakpoint(*address, name);
+            println!("  {} ({}:{})", name, self.source_paths.to_local(file), line);
+        }
+        println!("Set {} breakpoints on functions matching \"{}\".", self.breakpoint_list.len() - before, pattern);
+    }
+
+    /// # brief
+    /// Find where a breakpoint belongs in freshly loaded debug data. Lines and functions are
+    /// looked up again by name; an address breakpoint keeps its offset into its function, which
+    /// is only a guess once the code of the function changed.
+    ///
+    /// # return
+    /// * The new resolution, the line for a line location, and all the addresses with their
+    /// line when a line location has several (see `Breakpoint::set_locations`).
+    fn reresolve_breakpoint(&self, bp: &Breakpoint) -> (Resolution, Option<Line>, Vec<(usize, Option<Line>)>) {
+        if !bp.is_address() {
+            // a function of a library waits for the library to be loaded again
+            if breakpoint::is_function_name(&bp.spec) && !self.knows_function(&bp.spec) {
+                return (Resolution::Pending, None, Vec::new());
+            }
+            return match self.resolve_locations(&bp.spec) {
+                Some(resolved) => (Resolution::Resolved(resolved[0].0), resolved[0].1.clone(), resolved),
+                None => (Resolution::Pending, None, Vec::new()),
+            };
+        }
+        let (name, offset) = match bp.anchor.as_ref() {
+            Some(anchor) => anchor,
+            None => return (Resolution::Broken("the address is in no function".to_string()), None, Vec::new()),
+        };
+        let functions: Vec<&Function> = self.debug_data.find_function(name).into_iter().map(|(_, func)| func).collect();
+        let resolution = reanchor(name, *offset, &functions);
+        if let Resolution::Resolved(addr) = resolution {
+            if bp.addr() != Some(addr) {
+                println!(
+                    "Warning: breakpoint {} moved to {}+{:#x} ({:#x}); this is a guess, the code may have changed",
+                    bp.number, name, offset, addr
+                );
+            }
+        }
+        (resolution, None, Vec::new())
+    }
+
+    /// # brief
+    /// Load the debug data of `target` again and move the breakpoints to their new addresses.
+    /// The inferior must not be running, its traps would be at the old addresses.
+    fn reload_target(&mut self, target: &str) -> bool {
+        let debug_data = match load_debug_data(target) {
+            Ok(debug_data) => debug_data,
+            Err(err) => {
+                println!("{}", err);
+                return false;
+            }
+        };
+        println!("Reading symbols from {}...", target);
+        self.replace_debug_data(target, debug_data);
+        true
+    }
+
+    /// # brief
+    /// Use the debug data of `target` loaded again, and move the breakpoints to their
+    /// addresses in it; those not found are kept pending.
+    fn replace_debug_data(&mut self, target: &str, debug_data: DwarfData) {
+        self.target = target.to_string();
+        self.debug_data = debug_data;
+        self.target_mtime = modification_time(target);
+        self.current_line = None;
+        self.list_position = None;
+        self.update_hint_functions();
+
+        self.breakpoints.clear();
+        let mut list = std::mem::take(&mut self.breakpoint_list);
+        for bp in list.iter_mut() {
+            let (resolution, line, resolved) = self.reresolve_breakpoint(bp);
+            bp.resolution = resolution;
+            bp.line = line;
+            bp.set_locations(&resolved);
+            match bp.addr() {
+                Some(addr) => {
+                    bp.anchor = self.anchor_of(addr);
+                    for trap in bp.traps() {
+                        self.breakpoints.insert(trap, 0);
+                    }
+                }
+                // the anchor is kept, to look the breakpoint up again at the next reload
+                None => println!("Breakpoint {} ({}) is {}, see info break", bp.number, bp.spec, bp.resolution),
+            }
+        }
+        self.breakpoint_list = list;
+    }
+
+    /// # brief
+    /// Handle `file [path]`: load the target again, e.g. after a rebuild, or load another one.
+    fn file(&mut self, args: &[String]) {
+        let target = args.get(0).cloned().unwrap_or_else(|| self.target.clone());
+        if self.inferior.is_some() {
+            println!("Killing the running program to load {}", target);
+            self.end_inferior();
+        }
+        self.reload_target(&target);
+    }
+
+    /// # brief
+    /// Handle `wait-attach <name> [timeout]`: wait for a new process running `name` and attach
+    /// to it. The debug data of its executable is loaded and the breakpoints are placed in it
+    /// again, then installed in the process.
+    fn wait_attach(&mut self, args: &[String]) {
+        let name = match args.get(0) {
+            Some(name) => name,
+            None => {
+                println!("Usage: wait-attach <executable name> [timeout in seconds]");
+                return;
+            }
+        };
+        let timeout = match args.get(1).map(|secs| secs.parse::<u64>()) {
+            Some(Ok(secs)) => Some(Duration::from_secs(secs)),
+            Some(Err(_)) => {
+                println!("Invalid timeout \"{}\"", args[1]);
+                return;
+            }
+            None => None,
+        };
+        self.end_inferior();
+        let pid = match wait_for::wait_for_process(name, self.wait_interval, timeout) {
+            Ok(pid) => pid,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let target = executable_of(pid);
+        // stop the process first, the debug data can be loaded while it waits
+        let inferior = match Inferior::attach(pid, &target) {
+            Ok(inferior) => inferior,
+            Err(err) => {
+                println!("Could not attach to process {}: {}", pid, err);
+                return;
+            }
+        };
+        println!("Attached to process {}", pid);
+        if target != self.target || modification_time(&target) != self.target_mtime {
+            self.reload_target(&target);
+        }
+        self.inferior = Some(inferior);
+        // a stop of another process, whatever the number of its inferior
+        self.context.on_stop(self.identity());
+        let rip = self.registers().map_or(0, |regs| regs.rip as usize);
+        self.forget_all_libraries();
+        self.install_breakpoints();
+        self.plant_library_hook();
+        self.check_pending_breakpoints();
+        self.handle_status(Status::Stopped(nix::sys::signal::Signal::SIGSTOP, rip));
+    }
+
+    /// # brief
+    /// Handle `info stats`: show what deet did since the session started or `stats reset`.
+    fn info_stats(&self) {
+        let stats = stats::current();
+        if ui::deterministic() {
+            println!("Statistics:");
+        } else {
+            println!("Statistics of the last {:.1?}:", stats.since.elapsed());
+        }
+        println!("  Commands: {} run, {} continue, {} step", stats.runs, stats.continues, stats.steps);
+        println!("  Single-steps: {}", stats.single_steps);
+        println!("  Memory: {} bytes read, {} bytes written", stats.bytes_read, stats.bytes_written);
+        if !ui::deterministic() {
+            println!("  Time in waitpid: {:.3?}", stats.wait_time);
+        }
+        if !stats.breakpoint_hits.is_empty() {
+            let mut table = Table::new(&["Breakpoint", "Spec", "Hits"]);
+            for (number, hits) in stats.breakpoint_hits.iter() {
+                let spec = self.breakpoint_list.iter().find(|bp| bp.number == *number).map(|bp| bp.spec.clone());
+                table.row(vec![number.to_string(), spec.unwrap_or_else(|| "(deleted)".to_string()), hits.to_string()]);
+            }
+            table.print();
+        }
+        if !stats.requests.is_empty() {
+            let mut requests: Vec<_> = stats.requests.iter().collect();
+            requests.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.to_string().cmp(&b.0.to_string())));
+            let mut table = Table::new(&["Request", "Calls"]);
+            for (op, calls) in requests {
+                table.row(vec![op.to_string(), calls.to_string()]);
+            }
+            table.print();
+        }
+    }
+
+    /// # brief
+    /// Plant the trap deet keeps in the dynamic linker of a new process, at `_dl_debug_state`:
+    /// the function whose address `_r_debug.r_brk` gives debuggers, called after each change
+    /// to the list of libraries. Its stops read the libraries again, see `library_event`.
+    /// There is none in a static executable or a read-only session; the libraries are then
+    /// read at stops.
+    fn plant_library_hook(&mut self) {
+        let pid = match self.inferior.as_ref() {
+            Some(inferior) if !self.read_only => inferior.pid(),
+            _ => return,
+        };
+        let base = match auxv::read_auxv(pid).ok().and_then(|auxv| auxv.interpreter_base()) {
+            Some(base) => base,
+            None => return,
+        };
+        let path = match maps::read_maps(pid).ok().and_then(|mappings| mappings.into_iter().find(|mapping| mapping.start == base)) {
+            Some(mapping) => mapping.path,
+            None => return,
+        };
+        let addr = match self.library_symbols(&path).function_address("_dl_debug_state") {
+            Some(offset) => base + offset,
+            None => return,
+        };
+        let inferior = self.inferior.as_mut().unwrap();
+        let _patch = PatchGuard::new();
+        if let Ok(orig_byte) = inferior.write_byte(addr, 0xcc) {
+            self.breakpoints.insert(addr, orig_byte);
+            inferior.set_library_hook(Some(addr));
+        }
+        // an attached process has its libraries already
+        self.library_event();
+    }
+
+    /// # brief
+    /// Get the symbols of the shared library at `path`, read once.
+    fn library_symbols(&mut self, path: &str) -> &SymbolTable {
+        self.library_symbols
+            .entry(path.to_string())
+            .or_insert_with(|| SymbolTable::from_file(path).unwrap_or_default())
+    }
+
+    /// # brief
+    /// Read the shared libraries again once the dynamic linker changed them: report the ones
+    /// loaded and unloaded (`set print solib-events`), read the symbols of the new ones and
+    /// resolve the breakpoints pending on them. The breakpoints in a library unloaded are
+    /// pending again.
+    fn library_event(&mut self) {
+        let pid = match self.inferior.as_ref() {
+            Some(inferior) => inferior.pid(),
+            None => return,
+        };
+        let libraries = match maps::read_shared_libraries(pid) {
+            Ok(libraries) => libraries,
+            Err(_) => return,
+        };
+        let loaded: Vec<SharedLibrary> = libraries.iter().filter(|library| !self.libraries.contains(library)).cloned().collect();
+        let unloaded: Vec<SharedLibrary> = self.libraries.iter().filter(|library| !libraries.contains(library)).cloned().collect();
+        if self.print_solib_events {
+            for library in loaded.iter() {
+                println!("Loaded {} at {}", library.path, self.address_format().address(library.base));
+            }
+            for library in unloaded.iter() {
+                println!("Unloaded {}", library.path);
+            }
+        }
+        self.libraries = libraries;
+        self.forget_libraries(&unloaded);
+        for library in loaded.iter() {
+            self.library_symbols(&library.path);
+        }
+        if !loaded.is_empty() {
+            self.resolve_library_breakpoints();
+        }
+    }
+
+    /// # brief
+    /// Find the function `name` in the shared libraries mapped.
+    ///
+    /// # return
+    /// * Its address, and the path of the library defining it.
+    fn library_function(&self, name: &str) -> Option<(usize, String)> {
+        self.libraries.iter().find_map(|library| {
+            let offset = self.library_symbols.get(&library.path)?.function_address(name)?;
+            Some((library.base + offset, library.path.clone()))
+        })
+    }
+
+    /// # brief
+    /// Resolve the breakpoints pending on a function of a shared library that is now loaded,
+    /// and install them.
+    fn resolve_library_breakpoints(&mut self) {
+        for index in 0..self.breakpoint_list.len() {
+            let bp = &self.breakpoint_list[index];
+            if bp.resolution != Resolution::Pending || !breakpoint::is_function_name(&bp.spec) {
+                continue;
+            }
+            let (addr, path) = match self.library_function(&bp.spec) {
+                Some(found) => found,
+                None => continue,
+            };
+            let bp = &mut self.breakpoint_list[index];
+            bp.resolution = Resolution::Resolved(addr);
+            bp.set_locations(&[(addr, None)]);
+            let (number, spec, traps) = (bp.number, bp.spec.clone(), bp.traps());
+            if traps.into_iter().all(|trap| self.install_trap(trap)) {
+                println!("Breakpoint {} ({}) resolved to {} in {}", number, spec, self.address_format().address(addr), path);
+            }
+        }
+    }
+
+    /// # brief
+    /// Make the breakpoints in `libraries`, unloaded or of a process that is gone, pending again
+    /// until a library defining their function is loaded. Their traps went with the code.
+    fn forget_libraries(&mut self, libraries: &[SharedLibrary]) {
+        let gone = |addr: usize| libraries.iter().any(|library| library.base <= addr && addr < library.end);
+        for bp in self.breakpoint_list.iter_mut().filter(|bp| bp.addr().map_or(false, gone)) {
+            for trap in bp.traps() {
+                self.breakpoints.remove(&trap);
+            }
+            bp.resolution = Resolution::Pending;
+            bp.set_locations(&[]);
+        }
+    }
+
+    /// # brief
+    /// Forget the libraries of a process that is gone, or replaced its image, before the
+    /// breakpoints are installed in the next one: see `forget_libraries`.
+    fn forget_all_libraries(&mut self) {
+        let libraries = std::mem::take(&mut self.libraries);
+        self.forget_libraries(&libraries);
+    }
+
+    /// # brief
+    /// Handle `info sharedlibrary`: list the shared objects mapped in the inferior, with where
+    /// they are loaded and whether deet has their symbols.
+    fn info_shared_libraries(&mut self) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => {
+                println!("The program is not being run.");
+                return;
+            }
+        };
+        // without the hook in the dynamic linker, nothing noticed the changes since the last stop
+        if inferior.library_hook().is_none() {
+            self.library_event();
+        }
+        if self.libraries.is_empty() {
+            println!("No shared libraries loaded at this time.");
+            return;
+        }
+        let format = self.address_format();
+        let mut table = Table::new(&["From", "To", "Symbols", "Shared Object Library"]);
+        for library in self.libraries.iter() {
+            let symbols = self.library_symbols.get(&library.path).map_or(false, |symbols| !symbols.is_empty());
+            table.row(vec![
+                format.address(library.base),
+                format.address(library.end),
+                if symbols { "Yes" } else { "No" }.to_string(),
+                library.path.clone(),
+            ]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info dwarf-errors`: list the compilation units the loader left out, with what
+    /// could not be decoded in each.
+    fn info_dwarf_errors(&self) {
+        let errors = self.debug_data.unit_errors();
+        if errors.is_empty() {
+            println!("All the compilation units of {} were parsed.", self.target);
+            return;
+        }
+        let mut table = Table::new(&["Compilation unit", "Error"]);
+        for (name, err) in errors.iter() {
+            table.row(vec![self.source_paths.to_local(name), format!("{:?}", err)]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info dwarf-cache`: show how well the caches of the addr2line lookups work.
+    fn info_dwarf_cache(&self) {
+        let mut table = Table::new(&["Cache", "Hits", "Misses", "Hit %", "Entries"]);
+        for (name, stats, len, capacity) in self.debug_data.cache_stats() {
+            let lookups = stats.hits + stats.misses;
+            let rate = if lookups > 0 { stats.hits * 100 / lookups } else { 0 };
+            table.row(vec![
+                name.to_string(),
+                stats.hits.to_string(),
+                stats.misses.to_string(),
+                rate.to_string(),
+                format!("{}/{}", len, capacity),
+            ]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info break history`: list the latest changes of the breakpoints, oldest first.
+    fn info_breakpoint_history(&self) {
+        if self.breakpoint_history.is_empty() {
+            println!("No breakpoint changes.");
+            return;
+        }
+        let mut table = Table::new(&["When", "Change", "Command"]);
+        for mutation in self.breakpoint_history.iter() {
+            let ago = mutation.time.elapsed().map_or(0, |elapsed| elapsed.as_secs());
+            let when = if ui::deterministic() { "-".to_string() } else { format!("{}s ago", ago) };
+            let mut change = mutation.change.to_string();
+            if mutation.undone {
+                change.push_str(" (undone)");
+            }
+            table.row(vec![when, change, mutation.command.clone()]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info break`: list the breakpoints with their anchors and the broken ones.
+    fn info_breakpoints(&self) {
+        if self.breakpoint_list.is_empty() {
+            println!("No breakpoints.");
+            return;
+        }
+        self.breakpoint_table().print();
+    }
+
+    /// # brief
+    /// Make the table of `info break`: each breakpoint with the spec as typed, its resolution
+    /// and where it is, then its condition, message and locations.
+    fn breakpoint_table(&self) -> Table {
+        let mut table = Table::new(&["Num", "Enb", "Spec", "Status", "Address", "Group", "What"]);
+        // the rows under a breakpoint only fill the last column
+        let detail = |text: String| vec![String::new(), String::new(), String::new(), String::new(), String::new(), String::new(), text];
+        for bp in self.breakpoint_list.iter() {
+            let address = match &bp.resolution {
+                Resolution::Resolved(addr) => format!("{:#x}", addr),
+                Resolution::Pending => "<pending>".to_string(),
+                Resolution::Broken(_) => "<broken>".to_string(),
+            };
+            let mut what = match &bp.anchor {
+                Some((name, 0)) => format!("in {}", name),
+                Some((name, offset)) => format!("in {}+{:#x}", name, offset),
+                None => String::new(),
+            };
+            // the line a line location snapped to, rather than the one typed
+            let line = bp.line.clone().or_else(|| bp.addr().and_then(|addr| self.debug_data.line_at(addr as u64)));
+            if let Some(line) = line {
+                what.push_str(&format!(" at {}:{}", self.source_paths.to_local(&line.file), line.number));
+            }
+            if let Some(thread) = bp.thread {
+                what.push_str(&format!(" thread {}", thread));
+            }
+            table.row(vec![
+                bp.number.to_string(),
+                if bp.enabled { "y" } else { "n" }.to_string(),
+                bp.spec.clone(),
+                bp.resolution.to_string(),
+                address,
+                bp.group.clone().unwrap_or_default(),
+                what.trim_start().to_string(),
+            ]);
+            if let Some(condition) = &bp.condition {
+                table.row(detail(format!("stop only if {}", condition)));
+            }
+            if let Some(dprintf) = &bp.dprintf {
+                let args: String = dprintf.args.iter().map(|arg| format!(", {}", arg)).collect();
+                table.row(detail(format!("printf {}{}", dprintf.quoted_format(), args)));
+            }
+            for (index, location) in bp.locations.iter().enumerate() {
+                let what = match &location.line {
+                    Some(line) => format!("at {}:{}", self.source_paths.to_local(&line.file), line.number),
+                    None => String::new(),
+                };
+                let what = match self.anchor_of(location.addr) {
+                    Some((name, 0)) => format!("in {} {}", name, what),
+                    Some((name, offset)) => format!("in {}+{:#x} {}", name, offset, what),
+                    None => what,
+                };
+                table.row(vec![
+                    format!("{}.{}", bp.number, index + 1),
+                    if location.enabled { "y" } else { "n" }.to_string(),
+                    String::new(),
+                    String::new(),
+                    format!("{:#x}", location.addr),
+                    String::new(),
+                    what.trim_end().to_string(),
+                ]);
+            }
+        }
+        table
+    }
+
+    /// # brief
+    /// Give the function names of the selected inferior to the prompt hints. They are given
+    /// before a later prompt if the debug information is still loading.
+    fn update_hint_functions(&mut self) {
+        let functions = match self.debug_data.loaded_files() {
+            Some(files) => files
+                .iter()
+                .flat_map(|file| file.functions.iter().map(|func| func.name.clone()))
+                .collect(),
+            None => {
+                self.hint_functions_pending = true;
+                return;
+            }
+        };
+        self.hint_functions_pending = false;
+        if let Some(helper) = self.readline.helper_mut() {
+            helper.set_functions(functions);
+        }
+    }
+
+    /// # brief
+    /// Record an address shown to the user, for the prompt hints.
+    fn remember_address(&mut self, addr: usize) {
+        self.recent_addresses.retain(|recent| *recent != addr);
+        self.recent_addresses.insert(0, addr);
+        self.recent_addresses.truncate(MAX_RECENT_ADDRESSES);
+        let addresses = self.recent_addresses.clone();
+        if let Some(helper) = self.readline.helper_mut() {
+            helper.set_addresses(addresses);
+        }
+    }
+
+    /// # brief
+    /// Give the names of the breakpoint groups to the prompt hints.
+    fn update_hint_groups(&mut self) {
+        let groups: Vec<String> = self.breakpoint_list.iter().filter_map(|bp| bp.group.clone()).collect();
+        if let Some(helper) = self.readline.helper_mut() {
+            helper.set_groups(groups);
+        }
+    }
+
+    /// # brief
+    /// Handle `tui enable|disable`.
+    fn tui_command(&mut self, args: &[String]) {
+        match args.get(0).map(|s| s.as_str()) {
+            Some("enable") => match self.tui.enable() {
+                Ok(()) => self.draw_source_pane(),
+                Err(err) => println!("{}", err),
+            },
+            Some("disable") => self.tui.disable(),
+            _ => println!("Usage: tui enable|disable"),
+        }
+    }
+
+    /// # brief
+    /// Read the lines of the source file recorded as `file` in the debug information.
+    fn source_text(&self, file: &str) -> Option<Vec<String>> {
+        let comp_dir = self
+            .debug_data
+            .get_target_file(file)
+            .and_then(|file| file.comp_dir.clone());
+        self
+            .source_paths
+            .locate(file, comp_dir.as_deref())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(|l| l.to_string()).collect::<Vec<String>>())
+    }
+
+    /// # brief
+    /// Handle `list[/s|/a] [location|+|-]`: print `LIST_LINES` lines of source around a location,
+    /// the lines after the last listing (`list +`, and `list` again) or before it (`list -`).
+    /// Without a listing yet, `list` shows the current line. Where there is no source, because
+    /// the location has no line information or its file can't be found, the instructions around
+    /// it are listed instead; `/s` lists the source only, `/a` the instructions only. The
+    /// format may also be the first word, `list /a main`.
+    fn list(&mut self, format: &str, arg: &str) {
+        match self.listing(format, arg) {
+            Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    /// # brief
+    /// Make the output of `list`, see there, and remember the lines of source listed.
+    ///
+    /// # return
+    /// * The lines to print, none if the location could not be resolved (which was reported),
+    /// or `Err(String)` saying why nothing can be listed.
+    fn listing(&mut self, format: &str, arg: &str) -> Result<Vec<String>, String> {
+        let (format, arg) = match arg.strip_prefix('/').map(|rest| rest.split_once(char::is_whitespace).unwrap_or((rest, ""))) {
+            // a format is letters, an absolute path such as /src/main.c:12 is a location
+            Some((word, rest)) if format.is_empty() && word.chars().all(|c| c.is_ascii_alphabetic()) => (word, rest),
+            _ => (format, arg),
+        };
+        let arg = arg.trim();
+        let mode = match format {
+            "" => None,
+            "s" | "a" => format.chars().next(),
+            other => return Err(format!("Invalid list format \"/{}\": use /s for the source or /a for the instructions.", other)),
+        };
+        // where the instructions are listed from when there is no source
+        let pc = self.register("rip").map(|rip| rip as usize);
+        if mode == Some('a') {
+            let addr = match arg {
+                "" | "+" | "-" => pc,
+                location => self.resolve_location(location).map(|(addr, _)| addr),
+            };
+            return match addr {
+                Some(addr) => self.instruction_listing(addr, None),
+                None if arg.is_empty() => Err("No current location: the program has not stopped yet; use list /a <location>.".to_string()),
+                None => Ok(Vec::new()),
+            };
+        }
+        let fallback = mode.is_none();
+        let mut addr = None;
+        let (file, first) = match (arg, &self.list_position) {
+            ("", Some((file, _, last))) | ("+", Some((file, _, last))) => (file.clone(), last + 1),
+            ("-", Some((file, first, _))) => {
+                if *first == 1 {
+                    return Err(format!("Already at the start of {}.", self.source_paths.to_local(file)));
+                }
+                (file.clone(), first.saturating_sub(LIST_LINES).max(1))
+            }
+            ("", None) | ("+", None) | ("-", None) => match (&self.frame_line(), pc) {
+                (Some(line), _) => {
+                    addr = pc;
+                    (line.file.clone(), line.number.saturating_sub(LIST_LINES / 2).max(1))
+                }
+                (None, Some(pc)) if fallback => {
+                    return self.instruction_listing(pc, Some("No line number information at the current location"));
+                }
+                (None, _) => return Err("No current location: the program has not stopped yet; use list <location>.".to_string()),
+            },
+            (location, _) => {
+                let line = match self.resolve_location(location) {
+                    Some((resolved, Some(line))) => {
+                        addr = Some(resolved);
+                        line
+                    }
+                    Some((resolved, None)) => match self.debug_data.line_at(resolved as u64) {
+                        Some(line) => {
+                            addr = Some(resolved);
+                            line
+                        }
+                        None if fallback => {
+                            let reason = format!("No line number information for {:#x}", resolved);
+                            return self.instruction_listing(resolved, Some(&reason));
+                        }
+                        None => return Err(format!("No line number information for {:#x}.", resolved)),
+                    },
+                    None => return Ok(Vec::new()),
+                };
+                (line.file.clone(), line.number.saturating_sub(LIST_LINES / 2).max(1))
+            }
+        };
+        let source = match (self.source_text(&file), addr) {
+            (Some(source), _) => source,
+            (None, Some(addr)) if fallback => {
+                let reason = format!("Source file {} not found", self.source_paths.to_local(&file));
+                return self.instruction_listing(addr, Some(&reason));
+            }
+            (None, _) => return Err(format!("Source file {} not found.", self.source_paths.to_local(&file))),
+        };
+        if first > source.len() {
+            let local = self.source_paths.to_local(&file);
+            return Err(format!("Line number {} out of range; {} has {} lines.", first, local, source.len()));
+        }
+        let last = (first + LIST_LINES - 1).min(source.len());
+        let breakpoint_lines = self.breakpoint_lines(&file);
+        let current = self.current_line.as_ref().filter(|line| line.file == file).map(|line| line.number);
+        let lines = (first..=last)
+            .map(|number| source::gutter_line(number, &source[number - 1], breakpoint_lines.contains(&number), current == Some(number)))
+            .collect();
+        self.list_position = Some((file, first, last));
+        Ok(lines)
+    }
+
+    /// # brief
+    /// List the instructions around `addr`, for `list` where there is no source: their address,
+    /// with the function and offset, and their bytes, with the original bytes under the traps.
+    /// The instruction at the pc is marked `=>`. Instructions are decoded from the start of the
+    /// function containing `addr`, so that they are split at the right places; outside a known
+    /// function, from `addr` itself.
+    ///
+    /// # param
+    /// - `addr` : The address to list around.
+    /// - `reason` : Why the source is not listed, the title of the listing.
+    ///
+    /// # return
+    /// * The lines of the listing, or `Err(String)` if the memory can't be read or decoded.
+    fn instruction_listing(&self, addr: usize, reason: Option<&str>) -> Result<Vec<String>, String> {
+        let (base, len) = match self.debug_data.function_containing(addr) {
+            Some((_, func)) if func.text_length > 0 => (func.address, func.text_length),
+            _ => (addr, LIST_LINES * disasm::MAX_INSTRUCTION_LEN),
+        };
+        let code = self.read_memory(base, len).map_err(|err| format!("Cannot access memory at address {:#x}: {}", base, err))?;
+        let instructions = disasm::instructions(&code, base);
+        let index = instructions
+            .iter()
+            .position(|(start, len)| addr < start + len)
+            .ok_or(format!("Cannot decode the instructions at {:#x}.", addr))?;
+        let mut lines = Vec::new();
+        if let Some(reason) = reason {
+            lines.push(format!("{}; instructions around {}:", reason, self.address_format().address(addr)));
+        }
+        let pc = self.register("rip").map(|rip| rip as usize);
+        let first = index.saturating_sub(LIST_LINES / 2);
+        for (start, len) in instructions.iter().skip(first).take(LIST_LINES) {
+            lines.push(self.instruction_line(pc == Some(*start), *start, &code[start - base..start - base + len]));
+        }
+        Ok(lines)
+    }
+
+    /// # brief
+    /// Handle `disassemble [expr]`: list the instructions of the function containing the address,
+    /// by default that of the selected frame, as `x/i` shows them.
+    fn disassemble(&mut self, arg: &str) {
+        match self.disassembly(arg) {
+            Ok(lines) => lines.iter().for_each(|line| println!("{}", line)),
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    /// # brief
+    /// Make the output of `disassemble`, see there. Outside of the known functions, the
+    /// instructions around the address are listed instead, as `list` does.
+    ///
+    /// # return
+    /// * The lines to print, or `Err(String)` saying why nothing can be disassembled.
+    fn disassembly(&mut self, arg: &str) -> Result<Vec<String>, String> {
+        let addr = match arg.trim() {
+            "" => self.register("rip").ok_or("No frame selected.")? as usize,
+            text => self.evaluate(text)? as usize,
+        };
+        let (name, base, len) = match self.debug_data.function_containing(addr) {
+            Some((_, func)) if func.text_length > 0 => (func.name.clone(), func.address, func.text_length),
+            _ => return self.instruction_listing(addr, Some("No function contains this address")),
+        };
+        let code = self.read_memory(base, len).map_err(|err| format!("Cannot access memory at address {:#x}: {}", base, err))?;
+        let pc = self.register("rip").map(|rip| rip as usize);
+        let mut lines = vec![format!("Dump of assembler code for function {}:", name)];
+        let mut pos = 0;
+        while pos < code.len() {
+            // a byte that can't be decoded is shown as `(bad)`, and decoding goes on after it
+            let len = disasm::instruction_length(&code[pos..]).unwrap_or(1);
+            lines.push(self.instruction_line(pc == Some(base + pos), base + pos, &code[pos..pos + len]));
+            pos += len;
+        }
+        lines.push("End of assembler dump.".to_string());
+        Ok(lines)
+    }
+
+    /// # brief
+    /// Format an instruction as the instruction listings show it, `list` without source and
+    /// `x/i` and `disassemble`: the address, with the function and offset, the bytes and the
+    /// instruction. Bytes that are not one instruction are `(bad)`, an instruction whose name is
+    /// not known `(unknown)`.
+    ///
+    /// # param
+    /// - `at_pc` : Whether the instruction is the one at the pc, marked `=>`.
+    /// - `start` : The address of the instruction.
+    /// - `bytes` : Its bytes, with the original bytes under the traps.
+    fn instruction_line(&self, at_pc: bool, start: usize, bytes: &[u8]) -> String {
+        let marker = if at_pc { "=>" } else { "  " };
+        let symbol = match self.debug_data.symbol_at(start) {
+            Some((name, 0)) => format!(" <{}>", name),
+            Some((name, offset)) => format!(" <{}+{:#x}>", name, offset),
+            None => String::new(),
+        };
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let instruction = match disasm::instruction_length(bytes) {
+            Some(len) if len == bytes.len() => disasm::disassemble(bytes, start).unwrap_or_else(|| "(unknown)".to_string()),
+            _ => "(bad)".to_string(),
+        };
+        // the bytes of most instructions fit 7 columns
+        format!("{} {}{}:\t{:<20}\t{}", marker, self.address_format().address(start), symbol, hex.join(" "), instruction)
+    }
+
+    /// # brief
+    /// Redraw the source pane around the current line, if the TUI is enabled.
+    fn draw_source_pane(&self) {
+        if !self.tui.is_enabled() {
+            return;
+        }
+        let line = match &self.current_line {
+            Some(line) => line,
+            None => {
+                self.tui.draw("No source", None, 0, &HashSet::new());
+                return;
+            }
+        };
+        let source = self.source_text(&line.file);
+        let breakpoint_lines = self.breakpoint_lines(&line.file);
+        let title = self.source_paths.to_local(&line.file);
+        self.tui.draw(&title, source.as_deref(), line.number, &breakpoint_lines);
+    }
+
+    /// # brief
+    /// Get the lines of the recorded `file` that hold an enabled breakpoint, for the markers
+    /// of `list` and the source pane.
+    fn breakpoint_lines(&self, file: &str) -> HashSet<usize> {
+        self.breakpoint_list
+            .iter()
+            .flat_map(|bp| bp.traps().into_iter().map(move |addr| (bp, addr)))
+            .filter_map(|(bp, addr)| {
+                // the line a line location resolved to, else the line of the address
+                let line = match bp.location_number(addr) {
+                    Some(number) => bp.locations[number - 1].line.clone(),
+                    None => bp.line.clone(),
+                };
+                line.or_else(|| self.debug_data.line_at(addr as u64))
+            })
+            .filter(|bp_line| bp_line.file == file)
+            .map(|bp_line| bp_line.number)
+            .collect()
+    }
+
+    /// # brief
+    /// Handle `target remote <host:port>`: debug the program run by a gdbserver-style stub
+    /// instead of a traced process. The breakpoints set so far are inserted by the stub.
+    fn target(&mut self, args: &[String]) {
+        let address = match (args.get(0).map(|arg| arg.as_str()), args.get(1), args.len()) {
+            (Some("remote"), Some(address), 2) => address,
+            _ => {
+                println!("Usage: target remote <host:port>");
+                return;
+            }
+        };
+        if self.inferior.is_some() || self.remote.is_some() {
+            println!("Error: a program is already being debugged, kill or disconnect from it first");
+            return;
+        }
+        let (remote, status) = match Remote::connect(address) {
+            Ok(connection) => connection,
+            Err(err) => {
+                println!("{}: {}", address, err);
+                return;
+            }
+        };
+        println!("Remote debugging using {}", address);
+        if remote.text_offset != 0 {
+            // deet takes the addresses of the debug information as they are
+            ui::notify(&format!("Warning: the stub reports the program text relocated by {:#x}, addresses will be wrong", remote.text_offset));
+        }
+        let mut pending: Vec<usize> = self.breakpoints.keys().copied().collect();
+        pending.sort();
+        for addr in pending {
+            if let Err(err) = remote.set_breakpoint(addr, true) {
+                println!("Cannot insert breakpoint at {:#x}: {}", addr, err);
+            }
+        }
+        self.remote = Some(remote);
+        self.handle_status(status);
+    }
+
+    /// # brief
+    /// Handle `disconnect`: detach from the program of the remote stub, which keeps running.
+    fn disconnect(&mut self) {
+        match self.remote.take() {
+            Some(remote) => match remote.detach() {
+                Ok(()) => println!("Ending remote debugging."),
+                Err(err) => println!("Error detaching from the remote target: {}", err),
+            },
+            None => println!("Error: not connected to a remote target"),
+        }
+    }
+
+    /// # brief
+    /// Resume the program of the remote stub, for one instruction if `step` is set, and report
+    /// how it stopped. The connection is dropped if it failed.
+    fn remote_resume(&mut self, step: bool) {
+        match self.remote.as_ref().unwrap().resume(step) {
+            Ok(status) => self.handle_status(status),
+            Err(err) => {
+                println!("{}", err);
+                if let remote::Error::Io(_) = err {
+                    self.remote = None;
+                }
+            }
+        }
+    }
+
+    /// # brief
+    /// Write the report of `set crash-report` when `status` is a crash of the selected
+    /// inferior, before the status is handled and the process reaped. A death by a signal the
+    /// process was reported stopped by is not reported twice.
+    fn write_crash_report(&mut self, status: &Status) {
+        let (pattern, json) = match &self.crash_report {
+            Some(setting) if status.is_crash() => setting.clone(),
+            _ => return,
+        };
+        // a remote stub has no pid, nor captured output
+        let pid = match (self.inferior.as_ref(), self.remote.as_ref()) {
+            (Some(inferior), None) => inferior.pid(),
+            _ => return,
+        };
+        if matches!(status, Status::Signaled(..)) && self.crash_reported == Some(pid) {
+            return;
+        }
+        self.crash_reported = Some(pid);
+        let path = match crash_report::report_path(&pattern, pid, &self.target) {
+            Ok(path) => path,
+            Err(err) => {
+                println!("Cannot write the crash report: {}", err);
+                return;
+            }
+        };
+        let report = self.crash_report(status, pid);
+        let text = if json { report.to_json() } else { report.to_text() };
+        match std::fs::write(&path, text) {
+            Ok(()) => println!("Crash report written to {}", path),
+            Err(err) => println!("Cannot write the crash report to {}: {}", path, err),
+        }
+    }
+
+    /// # brief
+    /// Gather what a crash report holds. After a stop by a signal, the stack, the variables
+    /// of the innermost frames and the registers are read from the process; after an exit
+    /// only how it ended and its output are known.
+    fn crash_report(&self, status: &Status, pid: nix::unistd::Pid) -> CrashReport {
+        let args = self.runs.iter().rev().find(|run| run.pid == Some(pid.as_raw())).map_or(&self.args, |run| &run.args);
+        self.output.settle();
+        let mut report = CrashReport {
+            argv: std::iter::once(self.target.clone()).chain(args.iter().cloned()).collect(),
+            pid,
+            status: status.describe(&self.debug_data),
+            signal: None,
+            fault_address: None,
+            backtrace: Vec::new(),
+            frames: Vec::new(),
+            output: self.output.last_lines(crash_report::OUTPUT_LINES),
+            registers: Vec::new(),
+        };
+        let signal = match status {
+            Status::Signaled(signal, _) => {
+                report.signal = Some(format!("{:?}", signal));
+                return report;
+            }
+            Status::Stopped(signal, _) => signal,
+            _ => return report,
+        };
+        report.signal = Some(format!("{:?}", signal));
+        let inferior = self.inferior.as_ref().unwrap();
+        report.fault_address = inferior.fault_address().ok();
+        let arguments = |frame: &Frame| self.frame_arguments(frame);
+        report.backtrace = match inferior.backtrace(&self.debug_data, false, &arguments, &self.address_format()) {
+            Ok(lines) => lines,
+            Err(err) => vec![format!("(cannot walk the stack: {})", err)],
+        };
+        if let Ok(stack) = inferior.stack(&self.debug_data, false) {
+            report.frames = stack
+                .frames
+                .iter()
+                .take(crash_report::FRAMES_WITH_LOCALS)
+                .map(|frame| {
+                    let line = frame.line.clone().or_else(|| self.debug_data.line_at(frame.rip as u64));
+                    let function = frame.function.clone().unwrap_or_else(|| "unknown func".to_string());
+                    let description = match line {
+                        Some(line) => format!("{} ({})", function, line),
+                        None => format!("{} at {}", function, self.address_format().address(frame.rip)),
+                    };
+                    // the variables of an inlined function are not those of the frame it is in
+                    let variables = match self.frame_variables(frame) {
+                        Some(variables) if !frame.inlined => variables
+                            .into_iter()
+                            .map(|(var, place)| (var.name.clone(), self.format_place(place, &var.entity_type)))
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    ReportFrame { description, variables }
+                })
+                .collect();
+        }
+        if let Some(regs) = self.registers() {
+            report.registers = registers::GENERAL_REGISTERS
+                .iter()
+                .filter_map(|reg| Some((*reg, registers::get_register(&regs, reg)?)))
+                .collect();
+        }
+        report
+    }
+
+    /// # brief
+    /// Kill the selected inferior, or detach from it if deet attached to it. A core dump being
+    /// inspected is put aside.
+    fn end_inferior(&mut self) {
+        self.core = None;
+        // the program of a remote stub keeps running, as after `disconnect`
+        if let Some(remote) = self.remote.take() {
+            if let Err(err) = remote.detach() {
+                println!("Error detaching from the remote target: {}", err);
+            }
+        }
+        if let Some(inferior) = self.inferior.as_mut() {
+            // a process we attached to keeps running without us
+            if inferior.is_attached() {
+                match inferior.detach(&self.breakpoints, &mut self.step_over_points) {
+                    Ok(()) => println!("Detaching from process {}", inferior.pid()),
+                    Err(err) => println!("Error detaching from process: {}", err),
+                }
+            } else {
+                inferior.kill();
+                println!("killing running inferior (pid{})", inferior.pid());
+                self.end_run(Outcome::Killed);
+            }
+            self.discard_traps();
+            self.inferior = None;
+        }
+    }
+
+    /// # brief
+    /// End the session: put the terminal back, save the history, and kill every inferior deet
+    /// started or detach from the ones it attached to. `quit` and the unwinding of a panic both
+    /// go through here, see the `Drop` of `Debugger`.
+    fn shutdown(&mut self) {
+        self.tui.disable();
+        if let Err(err) = self.readline.save_history(&self.history_path) {
+            ui::notify(&format!("Warning: failed to save history file at {}: {}", self.history_path, err));
+        }
+        self.end_inferior();
+        let ids: Vec<usize> = self.other_inferiors.iter().map(|state| state.id).collect();
+        for id in ids {
+            self.switch_inferior(id);
+            self.end_inferior();
+        }
+    }
+
+    /// # brief
+    /// Get the record of the launch of the selected inferior, `None` if it was attached to.
+    fn current_run(&mut self) -> Option<&mut Run> {
+        let pid = self.inferior.as_ref()?.pid().as_raw();
+        self.runs.iter_mut().rev().find(|run| run.pid == Some(pid))
+    }
+
+    /// # brief
+    /// Record how the run of the selected inferior ended, see `info runs`.
+    fn end_run(&mut self, outcome: Outcome) {
+        if let Some(run) = self.current_run() {
+            run.end(outcome);
+        }
+    }
+
+    /// # brief
+    /// Handle `info runs`: list the launches of the program in this session, with their
+    /// arguments and how they ended.
+    fn info_runs(&self) {
+        if self.runs.is_empty() {
+            println!("The program has not been run.");
+            return;
+        }
+        let mut table = Table::new(&["Num", "Outcome", "Time", "Stops", "Arguments"]);
+        for (index, run) in self.runs.iter().enumerate() {
+            let time = match run.duration.or_else(|| run.started.map(|started| started.elapsed())) {
+                _ if ui::deterministic() => "-".to_string(),
+                Some(duration) => format!("{:.1?}", duration),
+                None => "-".to_string(),
+            };
+            let args: Vec<String> = run.args.iter().map(|arg| debugger_command::quote(arg)).collect();
+            table.row(vec![(index + 1).to_string(), run.outcome.to_string(), time, run.stops.to_string(), args.join(" ")]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Exchange the per-process fields of the debugger with an inferior state.
+    fn swap_inferior(&mut self, state: &mut InferiorState) {
+        std::mem::swap(&mut self.inferior_id, &mut state.id);
+        std::mem::swap(&mut self.target, &mut state.target);
+        std::mem::swap(&mut self.args, &mut state.args);
+        std::mem::swap(&mut self.inferior, &mut state.inferior);
+        std::mem::swap(&mut self.remote, &mut state.remote);
+        std::mem::swap(&mut self.core, &mut state.core);
+        std::mem::swap(&mut self.debug_data, &mut state.debug_data);
+        std::mem::swap(&mut self.target_mtime, &mut state.target_mtime);
+        std::mem::swap(&mut self.breakpoints, &mut state.breakpoints);
+        std::mem::swap(&mut self.traps_installed, &mut state.traps_installed);
+        std::mem::swap(&mut self.breakpoint_list, &mut state.breakpoint_list);
+        std::mem::swap(&mut self.breakpoint_history, &mut state.breakpoint_history);
+        std::mem::swap(&mut self.step_over_points, &mut state.step_over_points);
+        std::mem::swap(&mut self.current_line, &mut state.current_line);
+        std::mem::swap(&mut self.snapshots, &mut state.snapshots);
+        std::mem::swap(&mut self.heap_at_stops, &mut state.heap_at_stops);
+        std::mem::swap(&mut self.last_stop, &mut state.last_stop);
+        std::mem::swap(&mut self.libraries, &mut state.libraries);
+    }
+
+    /// # brief
+    /// Select inferior `id`. The previously selected inferior keeps its process, breakpoints
+    /// and stop location.
+    ///
+    /// # return
+    /// * `false` if there is no inferior `id`.
+    fn switch_inferior(&mut self, id: usize) -> bool {
+        if id == self.inferior_id {
+            return true;
+        }
+        match self.other_inferiors.iter().position(|state| state.id == id) {
+            Some(index) => {
+                let mut state = self.other_inferiors.remove(index);
+                self.swap_inferior(&mut state);
+                self.other_inferiors.push(state);
+                self.other_inferiors.sort_by_key(|state| state.id);
+                self.context.on_stop(self.identity());
+                self.update_hint_functions();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// # brief
+    /// Handle `add-inferior <path>`: load another program to debug, without selecting it.
+    fn add_inferior(&mut self, args: &[String]) {
+        let target = match args.get(0) {
+            Some(target) => target,
+            None => {
+                println!("Usage: add-inferior <path> [arguments...]");
+                return;
+            }
+        };
+        let debug_data = match load_debug_data(target) {
+            Ok(debug_data) => debug_data,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let id = self
+            .other_inferiors
+            .iter()
+            .map(|state| state.id)
+            .chain(std::iter::once(self.inferior_id))
+            .max()
+            .unwrap()
+            + 1;
+        self.other_inferiors.push(InferiorState {
+            id,
+            target: target.clone(),
+            args: args[1..].to_vec(),
+            inferior: None,
+            remote: None,
+            core: None,
+            debug_data,
+            target_mtime: modification_time(target),
+            breakpoints: HashMap::new(),
+            traps_installed: false,
+            breakpoint_list: Vec::new(),
+            breakpoint_history: VecDeque::new(),
+            step_over_points: HashMap::new(),
+            current_line: None,
+            snapshots: HashMap::new(),
+            heap_at_stops: (None, None),
+            last_stop: None,
+            libraries: Vec::new(),
+        });
+        println!("Added inferior {} ({})", id, target);
+    }
+
+    /// # brief
+    /// Handle `inferior [n]`: select inferior `n`, or show the selected one.
+    fn select_inferior(&mut self, args: &[String]) {
+        if let Some(arg) = args.get(0) {
+            match arg.parse::<usize>() {
+                Ok(id) if self.switch_inferior(id) => {}
+                _ => {
+                    println!("Inferior ID {} not known.", arg);
+                    return;
+                }
+            }
+        }
+        match &self.inferior {
+            Some(inferior) => println!("[Current inferior is {} [process {}] ({})]", self.inferior_id, inferior.pid(), self.target),
+            None => println!("[Current inferior is {} [<not running>] ({})]", self.inferior_id, self.target),
+        }
+    }
+
+    /// # brief
+    /// Handle `info inferiors`: list the inferiors with their process ids and breakpoints.
+    fn info_inferiors(&self) {
+        let mut table = Table::new(&["", "Num", "Process", "Breakpoints", "Executable"]);
+        let current = (self.inferior_id, &self.inferior, &self.breakpoints, &self.target);
+        let others = self
+            .other_inferiors
+            .iter()
+            .map(|state| (state.id, &state.inferior, &state.breakpoints, &state.target));
+        let mut all: Vec<_> = std::iter::once(current).chain(others).collect();
+        all.sort_by_key(|(id, _, _, _)| *id);
+        for (id, inferior, breakpoints, target) in all {
+            let process = match inferior {
+                Some(inferior) => format!("process {}", inferior.pid()),
+                None => "<not running>".to_string(),
+            };
+            let marker = if id == self.inferior_id { "*" } else { "" };
+            table.row(vec![marker.to_string(), id.to_string(), process, breakpoints.len().to_string(), target.clone()]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info threads`: list the threads of the program with the function each is in,
+    /// `*` marking the selected one.
+    fn info_threads(&self) {
+        let inferior = match self.inferior.as_ref().filter(|inferior| !inferior.threads().is_empty()) {
+            Some(inferior) => inferior,
+            None => {
+                println!("No threads.");
+                return;
+            }
+        };
+        let current = inferior.current_thread().map(|thread| thread.number);
+        let mut table = Table::new(&["", "Id", "Target Id", "Frame"]);
+        for thread in inferior.threads() {
+            let marker = if Some(thread.number) == current { "*" } else { "" };
+            // the selected thread is seen from the selected frame, see `Context`
+            let frame = match Some(thread.number) == current {
+                true => self.top_frame(),
+                false => match inferior.thread_registers(thread.tid) {
+                    Ok(regs) => self.frame_of(regs.rip),
+                    Err(err) => format!("<{}>", err),
+                },
+            };
+            table.row(vec![marker.to_string(), thread.number.to_string(), format!("Thread {}", thread.tid), frame]);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `thread`, `thread <n>` and `thread apply all|<n>... <command>`. The selected
+    /// thread is the one the inspection commands look at until the program runs again.
+    fn thread(&mut self, args: &[String]) {
+        let (numbers, selected) = match self.inferior.as_ref() {
+            Some(inferior) => (
+                inferior.threads().iter().map(|thread| thread.number).collect::<Vec<usize>>(),
+                inferior.current_thread().map(|thread| (thread.number, thread.tid)),
+            ),
+            None => {
+                println!("No thread selected: the program is not being run.");
+                return;
+            }
+        };
+        let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let (ids, command): (Vec<usize>, Vec<&str>) = match args.as_slice() {
+            [] => {
+                if let Some((number, tid)) = selected {
+                    println!("[Current thread is {} (Thread {})]", number, tid);
+                }
+                return;
+            }
+            ["apply", "all", command @ ..] if !command.is_empty() => (numbers, command.to_vec()),
+            ["apply", rest @ ..] => {
+                let count = rest.iter().take_while(|arg| arg.parse::<usize>().is_ok()).count();
+                if count == 0 || count == rest.len() {
+                    println!("Usage: thread apply all|<id>... <command>");
+                    return;
+                }
+                let ids = rest[..count].iter().map(|id| id.parse::<usize>().unwrap()).collect();
+                (ids, rest[count..].to_vec())
+            }
+            [id] => {
+                match id.parse::<usize>().ok().filter(|id| self.switch_thread(*id)) {
+                    Some(id) => {
+                        let tid = self.inferior.as_ref().and_then(|inferior| inferior.current_thread()).map(|thread| thread.tid).unwrap();
+                        println!("[Switching to thread {} (Thread {})]", id, tid);
+                        println!("#0  {}", self.top_frame());
+                    }
+                    None => println!("Unknown thread {}.", id),
+                }
+                return;
+            }
+            _ => {
+                println!("Usage: thread [<id>] | thread apply all|<id>... <command>");
+                return;
+            }
+        };
+        // the selected thread and frame are put back after the command, which must not run the
+        // program
+        match DebuggerCommand::from_tokens(&command) {
+            Some(cmd) if cmd.is_inspection() => {}
+            Some(_) => {
+                println!("Error: {} is not allowed in thread apply, only commands that inspect the program are", command[0]);
+                return;
+            }
+            None => {
+                println!("Undefined command: \"{}\"", command[0]);
+                return;
+            }
+        }
+        let frame = self.context.selected_frame().cloned().map(|frame| (self.context.frame(), frame));
+        for id in ids {
+            if !self.switch_thread(id) {
+                println!("Unknown thread {}.", id);
+                continue;
+            }
+            let tid = self.inferior.as_ref().and_then(|inferior| inferior.current_thread()).map(|thread| thread.tid).unwrap();
+            println!("\nThread {} (Thread {}) {}:", id, tid, self.top_frame());
+            if let Some(cmd) = DebuggerCommand::from_tokens(&command) {
+                self.command_line = command.join(" ");
+                self.execute(cmd);
+            }
+        }
+        if let Some((number, _)) = selected {
+            self.switch_thread(number);
+        }
+        if let Some((number, frame)) = frame {
+            self.context.select_frame(number, frame);
+        }
+    }
+
+    /// # brief
+    /// Select the thread numbered `number` for the inspection commands, on frame 0.
+    ///
+    /// # return
+    /// * Whether the program has such a thread.
+    fn switch_thread(&mut self, number: usize) -> bool {
+        let selected = self.inferior.as_mut().map_or(false, |inferior| inferior.select_thread(number).is_some());
+        if selected {
+            self.context.on_stop(self.identity());
+        }
+        selected
+    }
+
+    /// # brief
+    /// Say which thread stopped when it is not the one of the previous stop, as gdb does, once
+    /// the program has more than one thread.
+    fn report_thread_switch(&mut self) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => return,
+        };
+        let thread = match inferior.current_thread() {
+            Some(thread) => thread,
+            None => return,
+        };
+        let previous = self.stopped_thread.replace(thread.number);
+        if inferior.threads().len() > 1 && previous.map_or(false, |previous| previous != thread.number) {
+            println!("[Switching to thread {} (Thread {})]", thread.number, thread.tid);
+        }
+    }
+
+    /// # brief
+    /// Handle `info program`: say whether the program runs and where it is stopped. An end of
+    /// the program while it was stopped was already found by the poll of `execute`.
+    fn info_program(&self) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => {
+                println!("The program being debugged is not being run.");
+                return;
+            }
+        };
+        let kind = if inferior.is_attached() { "attached" } else { "child" };
+        println!("\tUsing the running image of {} process {}.", kind, inferior.pid());
+        if let Some(regs) = self.registers() {
+            println!("Program stopped at {}, in {}.", self.address_format().address(regs.rip as usize), self.top_frame());
+        }
+        self.print_syscall_state(inferior);
+    }
+
+    /// # brief
+    /// Print the system call the inferior is in, where it sleeps in the kernel and its process
+    /// state, for `info program`: the usual answer to why a program hangs.
+    fn print_syscall_state(&self, inferior: &Inferior) {
+        let state = match inferior.syscall_state() {
+            Ok(state) => state,
+            Err(err) => {
+                println!("System call state unknown: {}", err);
+                return;
+            }
+        };
+        match state.syscall {
+            Some((number, args)) => {
+                let args: Vec<String> = args.iter().map(|arg| format!("{:#x}", arg)).collect();
+                println!("In system call {} ({}), arguments {}.", syscalls::name(number).unwrap_or("unknown"), number, args.join(", "));
+            }
+            None => println!("Not in a system call."),
+        }
+        // orig_rax holds the number of the system call the stop interrupted or ended, -1 for a
+        // stop by a trap, and rax its result
+        if let (Some(number), Some(value)) = (self.register("orig_rax"), self.register("rax")) {
+            if (number as i64) >= 0 {
+                let name = syscalls::name(number).unwrap_or("unknown");
+                println!("Last system call {} returned {}.", name, syscalls::describe_return(value as i64));
+            }
+        }
+        if let Some(wchan) = state.wchan {
+            println!("Sleeping in kernel function {}.", wchan);
+        }
+        if let Some(letter) = state.state {
+            let meaning = match letter {
+                'R' => "running",
+                'S' => "sleeping",
+                'D' => "waiting for I/O",
+                'T' => "stopped by a signal",
+                't' => "stopped by the debugger",
+                'Z' => "zombie",
+                _ => "see proc(5)",
+            };
+            println!("Process state: {} ({}).", letter, meaning);
+        }
+    }
+
+    /// # brief
+    /// Handle `info start`: print the entry point of the program, the address of `main` and
+    /// the load bias, where deet expects the program to start.
+    fn info_start(&self) {
+        match self.entry_point() {
+            Some((entry, source)) => println!("Entry point: {:#x} (from the {})", entry, source),
+            None => println!("Entry point: unknown, {} can't be read", self.target),
+        }
+        let bias = self.inferior.as_ref().map(|inferior| inferior.load_bias());
+        match (self.debug_data.get_addr_for_function(None, "main"), bias) {
+            (Some(main), Some(bias)) if bias != 0 => {
+                println!("main: {:#x} in the debug information, {:#x} in memory", main, main.wrapping_add(bias))
+            }
+            (Some(main), _) => println!("main: {:#x}", main),
+            (None, _) => println!("main: not found in the debug information"),
+        }
+        match bias {
+            Some(bias) => println!("Load bias: {:#x}", bias),
+            None => println!("Load bias: unknown until the program runs"),
+        }
+        match self.start_address() {
+            Some((addr, reason)) => println!("start stops at: {:#x}, {}", addr, reason),
+            None => println!("start stops at: unknown"),
+        }
+        let pid = self.inferior.as_ref().map(|inferior| inferior.pid());
+        if let Some(base) = pid.and_then(|pid| auxv::read_auxv(pid).ok()?.interpreter_base()) {
+            // the first stop after exec is in the dynamic linker, before the libraries are mapped
+            println!("Dynamic linker: loaded at {:#x}, runs before the entry point", base);
+        }
+    }
+
+    /// # brief
+    /// Get how addresses are shown in messages: under `--deterministic`, relative to the load
+    /// base of the executable while a program runs, else absolute.
+    fn address_format(&self) -> ui::AddressFormat {
+        let pid = match self.inferior.as_ref() {
+            Some(inferior) if ui::deterministic() => inferior.pid(),
+            _ => return ui::AddressFormat::default(),
+        };
+        let mappings = maps::executable_mappings(pid).unwrap_or_default();
+        let base = mappings.iter().map(|mapping| mapping.start - mapping.offset).min();
+        let end = mappings.iter().map(|mapping| mapping.end).max();
+        let name = std::path::Path::new(&self.target)
+            .file_name()
+            .map_or(self.target.clone(), |name| name.to_string_lossy().into_owned());
+        match (base, end) {
+            (Some(base), Some(end)) => ui::AddressFormat::relative(&name, base, end),
+            _ => ui::AddressFormat::default(),
+        }
+    }
+
+    /// # brief
+    /// Describe the top frame of the stopped inferior, e.g. `func (file.c:12)`.
+    fn top_frame(&self) -> String {
+        match self.registers() {
+            Some(regs) => self.frame_of(regs.rip),
+            None => "<not running>".to_string(),
+        }
+    }
+
+    /// # brief
+    /// Describe where a thread at `rip` is, e.g. `func (file.c:12)`.
+    fn frame_of(&self, rip: u64) -> String {
+        let func = self.debug_data.function_at(rip).unwrap_or_else(|| format!("{:#x}", rip));
+        match self.debug_data.line_at(rip) {
+            Some(line) => format!("{} ({})", func, Line { file: self.source_paths.to_local(&line.file), ..line }),
+            None => func,
+        }
+    }
+
+    /// # brief
+    /// Get the line the selected frame is at: the line of the stop for frame 0, the line of
+    /// the call for an outer frame.
+    fn frame_line(&self) -> Option<Line> {
+        match self.context.selected_frame() {
+            Some(frame) => {
+                let pc = if frame.innermost { frame.rip } else { frame.rip.saturating_sub(1) };
+                frame.line.clone().or_else(|| self.debug_data.line_at(pc as u64))
+            }
+            None => self.current_line.clone(),
+        }
+    }
+
+    /// # brief
+    /// Handle `frame [N]`, `up [N]` and `down [N]`: select the frame that `print`, `list`, `x`
+    /// and the registers in expressions look at until the program resumes, and print it.
+    ///
+    /// # param
+    /// - `command` : `frame`, `up` or `down`.
+    /// - `args` : The number of the frame for `frame`, the number of frames to move for `up`
+    ///   and `down`, 1 by default.
+    fn select_frame(&mut self, command: &str, args: &[String]) {
+        if self.remote.is_some() {
+            println!("Error: {} is not supported on a remote target", command);
+            return;
+        }
+        if self.inferior.is_none() && self.core.is_none() {
+            println!("No stack.");
+            return;
+        }
+        let count = match args {
+            [] => None,
+            [count] => match count.parse::<usize>() {
+                Ok(count) => Some(count),
+                Err(_) => {
+                    println!("Invalid number \"{}\".", count);
+                    return;
+                }
+            },
+            _ => {
+                println!("Usage: {} [N]", command);
+                return;
+            }
+        };
+        let frames = match self.stack(false) {
+            Ok(stack) => stack.frames,
+            Err(err) => {
+                println!("Error: {}", err);
+                return;
+            }
+        };
+        let current = self.context.frame();
+        let number = match command {
+            "up" => current + count.unwrap_or(1),
+            "down" => match current.checked_sub(count.unwrap_or(1)) {
+                Some(number) => number,
+                None => {
+                    println!("Bottom (innermost) frame selected; you cannot go down.");
+                    return;
+                }
+            },
+            _ => count.unwrap_or(current),
+        };
+        let frame = match frames.get(number) {
+            Some(frame) => frame.clone(),
+            None if command == "up" => {
+                println!("Initial frame selected; you cannot go up.");
+                return;
+            }
+            None => {
+                println!("No frame at level {}.", number);
+                return;
+            }
+        };
+        self.context.select_frame(number, frame);
+        self.list_position = None;
+        let frame = &frames[number];
+        let func = frame.function.clone().unwrap_or_else(|| format!("{:#x}", frame.rip));
+        match self.frame_line() {
+            Some(line) => println!("#{}  {} ({})", number, func, Line { file: self.source_paths.to_local(&line.file), ..line }),
+            None => println!("#{}  {}", number, func),
+        }
+    }
+
+    /// # brief
+    /// Inspect a core dump instead of a process, as given by `--core`: say which process
+    /// dumped it and why, and show the frame it crashed in.
+    fn inspect_core(&mut self, core: Core) {
+        ui::announce(&format!("Core was generated by process {} of `{}'.", core.pid(), self.target));
+        if let Some(signal) = core.signal() {
+            println!("Program terminated with signal {:?}.", signal);
+        }
+        self.core = Some(core);
+        self.context.on_stop(self.identity());
+        self.select_frame("frame", &[]);
+    }
+
+    /// # brief
+    /// Walk the frames of the stopped inferior, or of the program that dumped the core being
+    /// inspected.
+    ///
+    /// # param
+    /// - `force` : Follow frame pointers outside the `[stack]` mapping.
+    fn stack(&self, force: bool) -> Result<Stack, String> {
+        match (self.inferior.as_ref(), self.core.as_ref()) {
+            (Some(inferior), _) => inferior.stack(&self.debug_data, force).map_err(|err| err.to_string()),
+            (None, Some(core)) => {
+                let regs = self.registers().ok_or("The program has no registers now.")?;
+                let peek = |addr| core.read_bytes(addr, 8).map(|bytes| u64::from_ne_bytes(bytes[..].try_into().unwrap()));
+                inferior::walk_stack(regs, &core.mappings(), &self.debug_data, force, &peek).map_err(|err| err.to_string())
+            }
+            (None, None) => Err("No stack.".to_string()),
+        }
+    }
+
+    /// # brief
+    /// Set the program output apart from the stop report about to be printed. Recorded output
+    /// is summarized in one line, if there is any since the last report; output to the terminal
+    /// can't be counted, so a separator line is printed instead.
+    fn report_output(&mut self) {
+        let captured = match &self.inferior {
+            Some(inferior) => inferior.is_captured(),
+            None => return,
+        };
+        if !self.print_output_summary {
+            return;
+        }
+        if !captured {
+            println!("{}", "─".repeat(ui::output_width().min(OUTPUT_SEPARATOR_WIDTH)));
+            return;
+        }
+        self.output.settle();
+        let (stdout, stderr) = self.output.counts_since(self.output_reported);
+        self.output_reported = self.output.next_seq();
+        let lines = |count: usize| if count == 1 { "line" } else { "lines" };
+        match (stdout, stderr) {
+            (0, 0) => {}
+            (stdout, 0) => println!("(program wrote {} {} to stdout — see `info output`)", stdout, lines(stdout)),
+            (0, stderr) => println!("(program wrote {} {} to stderr — see `info output`)", stderr, lines(stderr)),
+            (stdout, stderr) => println!(
+                "(program wrote {} {} to stdout, {} to stderr — see `info output`)",
+                stdout,
+                lines(stdout),
+                stderr
+            ),
+        }
+    }
+
+    /// # brief
+    /// Handle `info output [count]`: print the last lines the inferiors wrote while their output
+    /// was captured, all the lines kept by default. Lines to stderr are marked.
+    fn info_output(&self, count: Option<&String>) {
+        let count = match count.map(|count| count.parse::<usize>()) {
+            None => usize::MAX,
+            Some(Ok(count)) => count,
+            Some(Err(_)) => {
+                println!("Usage: info output [number of lines]");
+                return;
+            }
+        };
+        let lines = self.output.last_lines(count);
+        if lines.is_empty() {
+            if self.capture_output {
+                println!("No output captured.");
+            } else {
+                println!("No output captured; use `set capture-output on` before `run`.");
+            }
+        }
+        ui::page(lines.iter().map(|line| match line.stream {
+            Stream::Stdout => format!("{:>6}  {}", line.seq, line.text),
+            Stream::Stderr => format!("{:>6}! {}", line.seq, line.text),
+        }));
+    }
+
+    /// # brief
+    /// Read the open file descriptors of the selected inferior, the error printed.
+    fn open_fds(&self) -> Option<(nix::unistd::Pid, Vec<FdInfo>)> {
+        let inferior = match (self.inferior.as_ref(), self.remote.as_ref()) {
+            (Some(inferior), None) => inferior,
+            (_, Some(_)) => {
+                println!("The descriptors of a remote target can't be read.");
+                return None;
+            }
+            (None, None) => {
+                println!("The program is not being run.");
+                return None;
+            }
+        };
+        match inferior.open_fds() {
+            Ok(fds) => Some((inferior.pid(), fds)),
+            Err(err) => {
+                println!("Cannot read the descriptors of process {}: {}", inferior.pid(), err);
+                None
+            }
+        }
+    }
+
+    /// # brief
+    /// Print file descriptors as a table, with a first column `marks` (e.g. `+` and `-`) when
+    /// it is given.
+    fn print_fds(&self, fds: &[&FdInfo], marks: Option<&[&str]>) {
+        let headers = ["", "Fd", "Type", "Flags", "Offset", "Target"];
+        let mut table = Table::new(if marks.is_some() { &headers } else { &headers[1..] });
+        for (index, fd) in fds.iter().enumerate() {
+            let mut row: Vec<String> = marks.map(|marks| marks[index].to_string()).into_iter().collect();
+            row.extend(vec![
+                fd.fd.to_string(),
+                fd.kind(),
+                fd.describe_flags(),
+                fd.pos.map_or(String::new(), |pos| pos.to_string()),
+                fd.target.clone(),
+            ]);
+            table.row(row);
+        }
+        table.print();
+    }
+
+    /// # brief
+    /// Handle `info fds`: list the open file descriptors of the program, from `/proc`.
+    fn info_fds(&self) {
+        if let Some((_, fds)) = self.open_fds() {
+            self.print_fds(&fds.iter().collect::<Vec<_>>(), None);
+        }
+    }
+
+    /// # brief
+    /// Handle `fd baseline` and `fd diff`: record the open descriptors at one stop, then list
+    /// those opened (`+`) and closed (`-`) since, e.g. between two breakpoints around a
+    /// suspected leak.
+    fn fd(&mut self, args: &[String]) {
+        match args.first().map(|arg| arg.as_str()) {
+            Some("baseline") => {
+                if let Some((pid, fds)) = self.open_fds() {
+                    println!("Recorded {} open descriptors of process {}.", fds.len(), pid);
+                    self.fd_baseline = Some((pid, fds));
+                }
+            }
+            Some("diff") => {
+                let (pid, now) = match self.open_fds() {
+                    Some(open) => open,
+                    None => return,
+                };
+                let before = match &self.fd_baseline {
+                    Some((baseline_pid, before)) if *baseline_pid == pid => before,
+                    Some((baseline_pid, _)) => {
+                        println!("The baseline is of process {}, not {}; record one with `fd baseline`.", baseline_pid, pid);
+                        return;
+                    }
+                    None => {
+                        println!("No baseline: record one with `fd baseline` first.");
+                        return;
+                    }
+                };
+                let changes = fd_changes(before, &now);
+                if changes.is_empty() {
+                    println!("No descriptor opened or closed since the baseline.");
+                    return;
+                }
+                let marks: Vec<&str> = changes.iter().map(|(mark, _)| *mark).collect();
+                let fds: Vec<&FdInfo> = changes.iter().map(|(_, fd)| *fd).collect();
+                self.print_fds(&fds, Some(&marks));
+            }
+            _ => println!("Usage: fd baseline|diff"),
+        }
+    }
+
+    /// # brief
+    /// Handle `display [expr]`: print the expression now and every time the program stops;
+    /// without one, print them all.
+    fn display(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            self.print_displays();
+            return;
+        }
+        self.displays.push(text.to_string());
+        if self.target_access().is_some() {
+            self.print_display(self.displays.len() - 1);
+        }
+    }
+
+    /// # brief
+    /// Handle `undisplay [N...]`: stop printing the expressions of `display` numbered `N`, or
+    /// all of them.
+    fn undisplay(&mut self, args: &[String]) {
+        if args.is_empty() {
+            self.displays.clear();
+            return;
+        }
+        let mut numbers = Vec::new();
+        for arg in args {
+            match arg.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= self.displays.len() => numbers.push(number),
+                _ => {
+                    println!("No display number {}.", arg);
+                    return;
+                }
+            }
+        }
+        numbers.sort_unstable();
+        numbers.dedup();
+        for number in numbers.into_iter().rev() {
+            self.displays.remove(number - 1);
+        }
+    }
+
+    /// # brief
+    /// Handle `info display`: list the expressions of `display` by number.
+    fn info_display(&self) {
+        if self.displays.is_empty() {
+            println!("There are no auto-display expressions now.");
+            return;
+        }
+        println!("Auto-display expressions now in effect:");
+        for (index, text) in self.displays.iter().enumerate() {
+            println!("{}: {}", index + 1, text);
+        }
+    }
+
+    /// # brief
+    /// Print the expressions of `display`, as at a stop.
+    fn print_displays(&mut self) {
+        for index in 0..self.displays.len() {
+            self.print_display(index);
+        }
+    }
+
+    /// # brief
+    /// Print an expression of `display` as `N: expr = value`, or why it can't be evaluated.
+    fn print_display(&mut self, index: usize) {
+        let text = self.displays[index].clone();
+        match self.evaluate(&text).and_then(|value| self.format_value(value, "")) {
+            Ok(value) => println!("{}: {} = {}", index + 1, text, value),
+            Err(err) => println!("{}: {}: {}", index + 1, text, err),
+        }
+    }
+
+    /// # brief
+    /// Set a variable of the environment of the program for the next runs, or remove it from
+    /// that environment (`None`).
+    fn set_environment(&mut self, name: &str, value: Option<String>) {
+        match self.environment.iter_mut().find(|(set, _)| set == name) {
+            Some(entry) => entry.1 = value,
+            None => self.environment.push((name.to_string(), value)),
+        }
+    }
+
+    /// # brief
+    /// Handle `unset environment <name>`: run the program without the variable.
+    fn unset(&mut self, args: &[String]) {
+        match args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().as_slice() {
+            ["environment", name] => self.set_environment(name, None),
+            _ => println!("Usage: unset environment <name>"),
+        }
+    }
+
+    /// # brief
+    /// Handle `show environment [name]`: the environment the program gets, deet's with the
+    /// changes of `set environment` and `unset environment`.
+    fn show_environment(&self, name: Option<&String>) {
+        let environment = program_environment(std::env::vars().collect(), &self.environment);
+        match name {
+            Some(name) => match environment.iter().find(|(set, _)| set == name) {
+                Some((_, value)) => println!("{} = {}", name, value),
+                None => println!("Environment variable \"{}\" not defined.", name),
+            },
+            None => {
+                for (name, value) in environment.iter() {
+                    println!("{}={}", name, value);
+                }
+            }
+        }
+    }
+
+    /// # brief
+    /// Handle `maint panic [patch]`: panic on purpose, to check that a bug in a command ends
+    /// the command only; with `patch`, in the middle of a patch of the code of the program.
+    fn maintenance(&mut self, args: &[String]) {
+        match args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().as_slice() {
+            ["panic"] => panic!("maint panic"),
+            ["panic", "patch"] => {
+                let _patch = PatchGuard::new();
+                panic!("maint panic patch");
+            }
+            _ => println!("Usage: maint panic [patch]"),
+        }
+    }
+
+    /// # brief
+    /// Gather what brings a fresh deet back to the current investigation: the target, its
+    /// arguments and environment, the settings, skips, convenience variables, displays and
+    /// breakpoints, with the address of each breakpoint so a restore can tell it moved.
+    fn session(&self) -> Session {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let mut settings: Vec<(&str, Vec<String>)> = Vec::new();
+        if !self.stop_hook.is_empty() {
+            settings.push(("stop-hook", vec![self.stop_hook.join("; ")]));
+        }
+        if let Some((path, json)) = &self.crash_report {
+            let mut words = vec![path.clone()];
+            if *json {
+                words.push("json".to_string());
+            }
+            settings.push(("crash-report", words));
+        }
+        let frame_arguments = match self.frame_arguments {
+            FrameArguments::None => "none",
+            FrameArguments::Scalars => "scalars",
+            FrameArguments::All => "all",
+        };
+        let wait_interval = self.wait_interval.as_millis().to_string();
+        let print_elements = self.print_elements.to_string();
+        let rbreak_limit = self.rbreak_limit.to_string();
+        for (name, value) in [
+            ("print pretty", on_off(self.pretty_printing)),
+            ("print repeated-stops", on_off(self.print_repeated_stops)),
+            ("print symbol", on_off(self.print_symbol)),
+            ("print output-summary", on_off(self.print_output_summary)),
+            ("print solib-events", on_off(self.print_solib_events)),
+            ("print command-time", on_off(self.print_command_time)),
+            ("print elements", print_elements.as_str()),
+            ("pagination", on_off(ui::pagination())),
+            ("capture-output", on_off(self.capture_output)),
+            ("sanitize-output", on_off(self.output.sanitize())),
+            ("coredump", on_off(inferior::dump_core())),
+            ("print frame-arguments", frame_arguments),
+            ("wait-interval", wait_interval.as_str()),
+            ("rbreak-limit", rbreak_limit.as_str()),
+            ("follow-exec-mode", if self.exec_stops { "stop" } else { "continue" }),
+            ("step-into-plt", on_off(self.step_into_plt)),
+        ] {
+            settings.push((name, vec![value.to_string()]));
+        }
+        let mut convenience: Vec<(String, u64)> =
+            self.convenience.iter().map(|(name, value)| (name.clone(), *value)).collect();
+        convenience.sort();
+        Session {
+            target: self.target.clone(),
+            args: self.args.clone(),
+            environment: self.environment.clone(),
+            substitute_paths: self.source_paths.rules().to_vec(),
+            settings: settings.into_iter().map(|(name, words)| (name.to_string(), words)).collect(),
+            skips: self.skips.iter().map(|skip| skip.to_string()).collect(),
+            convenience,
+            displays: self.displays.clone(),
+            breakpoints: self
+                .breakpoint_list
+                .iter()
+                .map(|bp| SavedBreakpoint { command: bp.command(), addr: bp.addr() })
+                .collect(),
+            runs: self.runs.clone(),
+        }
+    }
+
+    /// # brief
+    /// Handle `save session <file>`: write the session, see `session` and `Session::to_text`.
+    fn save_session(&self, path: &str) {
+        match std::fs::write(path, self.session().to_text()) {
+            Ok(()) => println!("Saved the session to {} ({} breakpoints).", path, self.breakpoint_list.len()),
+            Err(err) => println!("Cannot write {}: {}", path, err),
+        }
+    }
+
+    /// # brief
+    /// Handle `restore session <file>` and `deet --session`: bring back a saved session. The
+    /// settings, skips and breakpoints are set by their commands, the breakpoints resolved
+    /// against the current target, with a warning for a breakpoint whose address differs from
+    /// the one saved.
+    fn restore_session(&mut self, path: &str) {
+        let session = match Session::read(path) {
+            Ok(session) => session,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        // the target is loaded already when deet was started with it
+        if session.target != self.target || modification_time(&session.target) != self.target_mtime {
+            self.restore_command(path, vec!["file".to_string(), session.target.clone()]);
+        }
+        self.args = session.args;
+        self.environment = session.environment;
+        for (from, to) in session.substitute_paths.iter() {
+            self.source_paths.add(from, to);
+        }
+        for (name, words) in session.settings {
+            let mut tokens = vec!["set".to_string()];
+            tokens.extend(name.split_whitespace().map(|word| word.to_string()));
+            tokens.extend(words);
+            self.restore_command(path, tokens);
+        }
+        for skip in session.skips.iter() {
+            let mut tokens = vec!["skip".to_string()];
+            tokens.extend(debugger_command::tokenize(skip));
+            self.restore_command(path, tokens);
+        }
+        self.convenience.extend(session.convenience);
+        self.displays = session.displays;
+        for saved in session.breakpoints.iter() {
+            let count = self.breakpoint_list.len();
+            self.restore_command(path, debugger_command::tokenize(&saved.command));
+            if let (Some(bp), Some(saved)) = (self.breakpoint_list.get(count), saved.addr) {
+                match bp.addr() {
+                    Some(addr) if addr != saved => println!(
+                        "Warning: breakpoint {} ({}) moved from {:#x} to {:#x}",
+                        bp.number, bp.spec, saved, addr
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        self.runs.extend(session.runs);
+    }
+
+    /// # brief
+    /// Execute a command of `restore_session`, which can't quit deet.
+    fn restore_command(&mut self, path: &str, words: Vec<String>) {
+        if words.is_empty() {
+            return;
+        }
+        let line = words.iter().map(|word| debugger_command::quote(word)).collect::<Vec<_>>().join(" ");
+        let tokens: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
+        match DebuggerCommand::from_tokens(&tokens) {
+            Some(DebuggerCommand::Quit(_)) => println!("Error in session {}: quit is not allowed here", path),
+            Some(cmd) => {
+                self.command_line = format!("{} (session {})", line, path);
+                self.execute(cmd);
+            }
+            None => println!("Error in session {}: unrecognized command \"{}\"", path, line),
+        }
+    }
+
+    /// # brief
+    /// Start a new inferior with the current arguments, killing the old one, and run it until it
+    /// stops or terminates.
+    ///
+    /// # param
+    /// - `timeout` : Stop the inferior if it runs longer than this.
+    ///
+    /// # return
+    /// * `Some(Status)` - the status of the new inferior, or `None` if it could not be started.
+    fn start_inferior(&mut self, timeout: Option<Duration>, first_stop: FirstStop) -> Option<Status> {
+        if self.inferior.is_some() {
+            // there is already a inferior running
+            // if it has not exited, kill it first
+            self.end_run(Outcome::Killed);
+            let inferior = self.inferior.as_mut().unwrap();
+            inferior.kill();
+            println!("killing running inferior (pid{})", inferior.pid());
+            self.discard_traps();
+            self.inferior = None;
+        }
+        if !self.snapshots.is_empty() {
+            println!("Discarding {} snapshots: addresses may differ in the new process", self.snapshots.len());
+            self.snapshots.clear();
+        }
+        // step traps, heap measurements, the last stop and its libraries belong to the old process
+        self.step_over_points.clear();
+        self.heap_at_stops = (None, None);
+        self.last_stop = None;
+        self.forget_all_libraries();
+        self.core = None;
+        let target = self.target.clone();
+        if modification_time(&target) != self.target_mtime {
+            println!("`{}' has changed; re-reading symbols.", target);
+            self.reload_target(&target);
+        }
+        if let Some(warning) = preflight::setuid_warning(&target) {
+            println!("{}", warning);
+        }
+        let capture = if self.capture_output { Some(&self.output) } else { None };
+        self.output_reported = self.output.next_seq();
+        match Inferior::new(&self.target, &self.args, &self.environment, capture) {
+            Ok(inferior) => {
+                // Crate the inferior
+                self.runs.push(Run::new(self.args.clone(), inferior.pid().as_raw()));
+                self.inferior = Some(inferior);
+                self.install_breakpoints();
+                self.plant_library_hook();
+                self.check_pending_breakpoints();
+                if first_stop != FirstStop::Breakpoint {
+                    return self.run_to_start(first_stop);
+                }
+                let deadline = timeout.map(|timeout| Instant::now() + timeout);
+                let (status, timed_out) = match self.resume(None, deadline) {
+                    Ok(stop) => stop,
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        return None;
+                    }
+                };
+                self.timeout_stop = if timed_out { timeout } else { None };
+                Some(status)
+            }
+            Err(err) => {
+                println!("Error starting subprocess: {}", err);
+                if let Some(explanation) = preflight::explain_spawn_failure() {
+                    println!("{}", explanation);
+                }
+                None
+            }
+        }
+    }
+
+    /// # brief
+    /// Run the new process to where `start` or `starti` stops it, through a temporary trap,
+    /// after saying where that is. A breakpoint on the way stops it first.
+    fn run_to_start(&mut self, first_stop: FirstStop) -> Option<Status> {
+        let target = match first_stop {
+            FirstStop::Entry => self.entry_point().map(|(entry, source)| (entry, format!("the entry point, from the {}", source))),
+            _ => self.start_address(),
+        };
+        let (addr, reason) = match target {
+            Some(target) => target,
+            None => {
+                println!("Cannot find main or the entry point of {}.", self.target);
+                return match self.resume(None, None) {
+                    Ok((status, _)) => Some(status),
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        None
+                    }
+                };
+            }
+        };
+        println!("Temporary stop at {}: {}", self.address_format().address(addr), reason);
+        let inferior = self.inferior.as_mut().unwrap();
+        match inferior.run_to(addr, &self.breakpoints, &mut self.step_over_points) {
+            Ok(status) => Some(status),
+            Err(err) => {
+                println!("Error: {}", err);
+                None
+            }
+        }
+    }
+
+    /// # brief
+    /// Find where `start` stops, the first of: `main` past its prologue when the debug
+    /// information has lines for it, the `main` symbol, and the entry point of the program. A
+    /// Rust release build or a stripped program may have no line of `main`, the entry point
+    /// is then all that is left.
+    ///
+    /// # return
+    /// * The address and which of them it is, with why the better ones could not be used, or
+    /// `None` if the executable can't be read.
+    fn start_address(&self) -> Option<(usize, String)> {
+        let debug_main = self.debug_data.get_addr_for_function(None, "main");
+        if let Some(main) = debug_main.filter(|main| self.debug_data.line_at(*main as u64).is_some()) {
+            return Some((self.post_prologue_address(main), "main, past its prologue".to_string()));
+        }
+        let why = if debug_main.is_some() { "main has no line information" } else { "main is not in the debug information" };
+        if let Some(main) = self.debug_data.function_symbol("main").or(debug_main) {
+            return Some((main, format!("the main symbol ({})", why)));
+        }
+        let (entry, source) = self.entry_point()?;
+        Some((entry, format!("the entry point, from the {} ({}, and there is no main symbol)", source, why)))
+    }
+
+    /// # brief
+    /// Handle `start` and `starti`: run the program with `args`, or the last arguments if there
+    /// are none, stopping at `main` or at its first instruction.
+    fn start(&mut self, args: Vec<String>, first_stop: FirstStop) {
+        if self.remote.is_some() {
+            println!("Error: start is not supported on a remote target, disconnect first");
+            return;
+        }
+        if !args.is_empty() {
+            self.args = args;
+        }
+        if let Some(status) = self.start_inferior(None, first_stop) {
+            self.handle_status(status);
+        }
+    }
+
+    /// # brief
+    /// Handle `run --until-failure [max]`: run the inferior again and again while it exits with
+    /// status 0. The loop ends at the first run that exits nonzero, is killed by a signal or
+    /// stops (at a breakpoint, or by Ctrl+C which is delivered to the inferior), and control
+    /// returns to the prompt at that point. With `set crash-report`, the crashes are written to
+    /// their reports and the loop goes on; only the other stops end it.
+    ///
+    /// # param
+    /// - `max` : The maximum number of runs.
+    fn run_until_failure(&mut self, max: usize) {
+        let mut outcomes: Vec<String> = Vec::new();
+        for run in 1..=max {
+            let status = match self.start_inferior(None, FirstStop::Breakpoint) {
+                Some(status) => status,
+                None => break,
+            };
+            if let Status::Exited(0) = status {
+                self.end_run(Outcome::Exited(0));
+                self.discard_traps();
+                self.inferior = None;
+                outcomes.push(format!("{}", status));
+                continue;
+            }
+            outcomes.push(status.describe(&self.debug_data));
+            if self.crash_report.is_some() && status.is_crash() {
+                // reported to a file, the loop goes on to the next run
+                println!("Run {} of {} crashed:", run, max);
+                let stopped = match status {
+                    Status::Stopped(signal, _) => Some(signal),
+                    _ => None,
+                };
+                self.handle_status(status);
+                if let Some(signal) = stopped.filter(|_| self.inferior.is_some()) {
+                    self.end_run(Outcome::Signaled(format!("{:?}", signal)));
+                    self.end_inferior();
+                }
+                continue;
+            }
+            println!("Run {} of {} did not exit cleanly:", run, max);
+            self.handle_status(status);
+            break;
+        }
+        // summarize the statuses, most frequent first
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for outcome in outcomes.iter() {
+            match counts.iter_mut().find(|(status, _)| status == outcome) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((outcome.clone(), 1)),
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        println!("{} runs:", outcomes.len());
+        for (status, count) in counts {
+            println!("  {} x {}", count, status);
+        }
+    }
+
+    /// # brief
+    /// Report the status of the inferior after it was resumed. The inferior is dropped if it has
+    /// terminated, otherwise the location it stopped at is printed.
+    ///
+    /// # param
+    /// - `status` : The status returned by the inferior.
+    fn handle_status(&mut self, status: Status) {
+        self.context.on_stop(self.identity());
+        self.report_output();
+        self.write_crash_report(&status);
+        if !self.other_inferiors.is_empty() {
+            print!("[Inferior {}] ", self.inferior_id);
+        }
+        match status {
+            Status::Exited(exit_code)            => {
+                self.last_stop = None;
+                println!("Child exited (status {})", exit_code);
+                self.end_run(Outcome::Exited(exit_code));
+                self.convenience.insert("_exitcode".to_string(), exit_code as u64);
+                self.discard_traps();
+                self.inferior = None;
+                self.remote = None;
+            }
+            Status::Signaled(signal, core_dumped) => {
+                self.last_stop = None;
+                self.end_run(Outcome::Signaled(format!("{:?}", signal)));
+                let pid = self.inferior.as_ref().map(|inferior| inferior.pid());
+                if core_dumped {
+                    println!("Child terminated with {:?} (signal {}, core dumped)", signal, signal as i32);
+                    match pid.map(|pid| core_pattern::core_destination(pid, &self.target)) {
+                        Some(CoreDestination::File(core)) => println!("The core file is probably {}", core),
+                        Some(CoreDestination::Piped(program)) => println!("The core was handled by {}", program),
+                        Some(CoreDestination::Unknown(reason)) => println!("The core file can't be found: {}", reason),
+                        None => {}
+                    }
+                } else {
+                    println!("Child terminated with {:?} (signal {})", signal, signal as i32);
+                    if let Some(pid) = pid.filter(|_| core_pattern::dumps_core(signal)) {
+                        if inferior::dump_core() {
+                            println!("No core was dumped: {}", core_pattern::explain_missing_core(pid, &self.target));
+                        } else {
+                            println!("No core was dumped; `set coredump on` allows it for the next runs.");
+                        }
+                    }
+                }
+                self.discard_traps();
+                self.inferior = None;
+                self.remote = None;
+            }
+            Status::Exec(rip)                    => self.handle_exec(rip),
+            Status::Stopped(signal, rip)         => {
+                if signal == nix::sys::signal::Signal::SIGTRAP && self.breakpoints.contains_key(&rip) {
+                    if let Some(run) = self.current_run() {
+                        run.stops += 1;
+                    }
+                }
+                let _line = self.debug_data.line_at(rip as u64);
+                let _func = self.debug_data.function_at(rip as u64);
+                self.last_stop = count_stop(self.last_stop.take(), _line.as_ref(), _func.as_deref());
+                let repeats = self.last_stop.as_ref().map_or(1, |(_, _, repeats)| *repeats);
+                let timeout_stop = self.timeout_stop.take();
+                if repeats > 1 && !self.print_repeated_stops && timeout_stop.is_none()
+                    && signal == nix::sys::signal::Signal::SIGTRAP
+                {
+                    // the stop is the same as the previous one, say so in one line
+                    let (func, line, _) = self.last_stop.as_ref().unwrap();
+                    let local = Line { file: self.source_paths.to_local(&line.file), ..line.clone() };
+                    println!("Stopped at {} ({}) (same line, {} stops)", func, local, repeats);
+                } else {
+                    self.report_thread_switch();
+                    match timeout_stop {
+                        Some(timeout) => println!("Child stopped by timeout after {:?}", timeout),
+                        None => println!("Child stopped by {}", inferior::describe_signal(signal)),
+                    }
+                    if signal == nix::sys::signal::Signal::SIGSEGV {
+                        self.report_stack_overflow();
+                        self.report_bad_jump(rip);
+                    }
+                    // which copy of a line with several locations was hit
+                    let location = self.breakpoint_list.iter().find(|bp| bp.has_trap(rip)).and_then(|bp| {
+                        Some((bp.number, bp.location_number(rip)?))
+                    });
+                    if let (Some((number, location)), nix::sys::signal::Signal::SIGTRAP) = (location, signal) {
+                        println!("Breakpoint {}.{}", number, location);
+                    }
+                    match (&_line, &_func) {
+                        (Some(line), Some(func)) => {
+                            let local = Line { file: self.source_paths.to_local(&line.file), ..line.clone() };
+                            println!("Stopped at {} ({}){}", func, local, self.inlined_from(rip));
+                        }
+                        (None, Some(func)) => println!("Stopped at {} in {}", self.address_format().address(rip), func),
+                        _ if self.debug_data.plt_stub(rip).is_some() => println!(
+                            "Stopped at {} in the PLT, on the way to a shared library",
+                            self.address_format().address(rip)
+                        ),
+                        _ => println!("Stopped at {}", self.address_format().address(rip)),
+                    }
+                }
+                if _line.is_some() {
+                    self.current_line = _line;
+                    self.list_position = None;
+                }
+                self.draw_source_pane();
+                let heap = self.measure_heap();
+                self.heap_at_stops = (heap, self.heap_at_stops.0);
+                // without the hook in the dynamic linker, the changes are found at stops
+                let changed = self.inferior.as_mut().map_or(false, |inferior| {
+                    inferior.take_library_change() || (inferior.library_hook().is_none() && self.print_solib_events)
+                });
+                if changed {
+                    self.library_event();
+                }
+                self.print_displays();
+                let hook = self.stop_hook.clone();
+                self.run_commands(&hook, "stop hook");
+            }
+        }
+    }
+
+    /// # brief
+    /// Follow the inferior into the program it executed: load the debug data of the new
+    /// executable, place the breakpoints in it again and install them, then stop there or go
+    /// on, see `set follow-exec-mode`.
+    ///
+    /// # param
+    /// - `rip` : The first instruction of the new program.
+    fn handle_exec(&mut self, rip: usize) {
+        let pid = self.inferior.as_ref().unwrap().pid();
+        self.last_stop = None;
+        let path = executable_of(pid);
+        println!("process {} is executing new program: {}", pid, path);
+        // the old image is gone, and its traps and libraries with it
+        self.discard_traps();
+        self.forget_all_libraries();
+        if !self.reload_target(&path) {
+            println!("The breakpoints stay at their addresses in the previous program.");
+        }
+        self.inferior.as_mut().unwrap().refresh_load_bias(&path);
+        self.install_breakpoints();
+        self.plant_library_hook();
+        if !self.exec_stops {
+            match self.resume(None, None) {
+                Ok((status, _)) => self.handle_status(status),
+                Err(err) => println!("Error: {}", err),
+            }
+            return;
+        }
+        println!("Stopped at {}, the start of the new program", self.address_format().address(rip));
+        self.draw_source_pane();
+    }
+
+    /// # brief
+    /// Diagnose a fault at an address outside of any executable mapping, which is what a call
+    /// through a null or invalid function pointer produces.
+    fn report_bad_jump(&self, rip: usize) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => return,
+        };
+        match maps::read_maps(inferior.pid()) {
+            Ok(mappings) if !maps::is_executable(&mappings, rip) => {}
+            _ => return,
+        }
+        println!("Fault at {:#x}: this looks like a call through a null or invalid function pointer", rip);
+        // the call pushed the return address before jumping
+        let caller = self
+            .registers()
+            .and_then(|regs| inferior.read_bytes(regs.rsp as usize, 8).ok())
+            .map(|bytes| usize::from_le_bytes(bytes[..8].try_into().unwrap()));
+        if let Some(caller) = caller {
+            let func = self.debug_data.function_at(caller as u64).unwrap_or_else(|| "??".to_string());
+            match self.debug_data.line_at(caller as u64) {
+                Some(line) => println!("Called from {} ({})", func, Line { file: self.source_paths.to_local(&line.file), ..line }),
+                None => println!("Called from {} ({:#x})", func, caller),
+            }
+        }
+    }
+
+    /// # brief
+    /// Tell a stack overflow apart from other segmentation faults, by where the fault address
+    /// lies relative to the stack mappings.
+    fn report_stack_overflow(&self) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => return,
+        };
+        let (addr, mappings) = match (inferior.fault_address(), maps::read_maps(inferior.pid())) {
+            (Ok(addr), Ok(mappings)) => (addr, mappings),
+            _ => return,
+        };
+        if let Some(below) = maps::stack_overflow(&mappings, addr) {
+            println!("Probable stack overflow (fault address {:#x} is {} bytes below the stack limit)", addr, below);
+        }
+    }
+
+    /// # brief
+    /// Replay a list of commands, e.g. the stop hook. Errors are reported and the remaining
+    /// commands still run. A command list that resumes the inferior doesn't replay command
+    /// lists again from the resulting stop, so a hook containing `step` can't recurse.
+    ///
+    /// # param
+    /// - `commands` : The command lines to execute.
+    /// - `origin` : What the commands come from, for error messages.
+    fn run_commands(&mut self, commands: &[String], origin: &str) {
+        if self.replaying {
+            return;
+        }
+        self.replaying = true;
+        for line in commands {
+            let words = debugger_command::tokenize(line);
+            let tokens: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            match DebuggerCommand::from_tokens(&tokens) {
+                Some(DebuggerCommand::Quit(_)) => println!("Error in {}: quit is not allowed here", origin),
+                Some(cmd) => {
+                    self.command_line = format!("{} ({})", line, origin);
+                    self.execute(cmd);
+                }
+                None => println!("Error in {}: unrecognized command \"{}\"", origin, line),
+            }
+        }
+        self.replaying = false;
+    }
+
+    /// # brief
+    /// Handle `set <setting> <args...>`.
+    fn set(&mut self, args: &[String]) {
+        if args.get(0).map_or(false, |arg| arg.starts_with('$')) {
+            if let Err(err) = self.evaluate(&args.join(" ")) {
+                println!("{}", err);
+            }
+            return;
+        }
+        if args.get(0).map_or(false, |arg| arg.starts_with('{')) {
+            self.set_memory(&args.join(" "));
+            return;
+        }
+        match args.get(0).map(|s| s.as_str()) {
+            Some("substitute-path") => {
+                if args.len() != 3 {
+                    println!("Usage: set substitute-path <from> <to>");
+                    return;
+                }
+                self.source_paths.add(&args[1], &args[2]);
+            }
+            Some("stop-hook") => {
+                // an empty definition clears the hook
+                self.stop_hook = debugger_command::split_commands(&args[1..].join(" "));
+            }
+            Some("args") => self.args = args[1..].to_vec(),
+            // `set environment NAME=VALUE` or `set environment NAME VALUE`
+            Some("environment") => {
+                let text = args[1..].join(" ");
+                let (name, value) = text.split_once('=').unwrap_or_else(|| text.split_once(' ').unwrap_or((&text, "")));
+                match name.trim() {
+                    "" => println!("Usage: set environment <name>=<value>"),
+                    name => self.set_environment(name, Some(value.trim().to_string())),
+                }
+            }
+            Some("wait-interval") => match args.get(1).map(|ms| ms.parse::<u64>()) {
+                Some(Ok(ms)) => self.wait_interval = Duration::from_millis(ms),
+                _ => println!("Usage: set wait-interval <milliseconds>"),
+            },
+            Some("rbreak-limit") => match args.get(1).map(|limit| limit.parse::<usize>()) {
+                Some(Ok(limit)) => self.rbreak_limit = limit,
+                _ => println!("Usage: set rbreak-limit <number of breakpoints>"),
+            },
+            Some("step-into-plt") => {
+                if let Some(on) = parse_on_off(args.get(1)) {
+                    self.step_into_plt = on;
+                }
+            }
+            Some("follow-exec-mode") => match args.get(1).map(|s| s.as_str()) {
+                Some("stop") => self.exec_stops = true,
+                Some("continue") => self.exec_stops = false,
+                _ => println!("Usage: set follow-exec-mode stop|continue"),
+            },
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("elements") => {
+                match args.get(2).map(|limit| limit.parse::<usize>()) {
+                    Some(Ok(limit)) => self.print_elements = limit,
+                    _ => println!("Usage: set print elements <number of elements, 0 for no limit>"),
+                }
+            }
+            Some("pagination") => {
+                if let Some(on) = parse_on_off(args.get(1)) {
+                    ui::set_pagination(on);
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("pretty") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.pretty_printing = on;
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("repeated-stops") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.print_repeated_stops = on;
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("symbol") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.print_symbol = on;
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("output-summary") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.print_output_summary = on;
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("solib-events") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.print_solib_events = on;
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("command-time") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    self.print_command_time = on;
+                }
+            }
+            Some("sanitize-output") => {
+                if let Some(on) = parse_on_off(args.get(1)) {
+                    self.output.set_sanitize(on);
+                }
+            }
+            Some("crash-report") => match &args[1..] {
+                [off] if off == "off" => self.crash_report = None,
+                [path] => self.set_crash_report(path, false),
+                [path, format] if format == "json" => self.set_crash_report(path, true),
+                _ => println!("Usage: set crash-report <path> [json] | off"),
+            },
+            Some("capture-output") => {
+                if let Some(on) = parse_on_off(args.get(1)) {
+                    self.capture_output = on;
+                }
+                if self.inferior.is_some() {
+                    println!("The output of the running program is unchanged; the setting applies from the next run.");
+                }
+            }
+            Some("debug") if args.get(1).map(|s| s.as_str()) == Some("ptrace") => {
+                if let Some(on) = parse_on_off(args.get(2)) {
+                    inferior::set_trace_requests(on);
+                }
+            }
+            Some("coredump") => {
+                if let Some(on) = parse_on_off(args.get(1)) {
+                    inferior::set_dump_core(on);
+                }
+                if self.inferior.is_some() {
+                    println!("The core size limit of the running program is unchanged; the setting applies from the next run.");
+                }
+            }
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("frame-arguments") => {
+                match args.get(2).map(|s| s.as_str()) {
+                    Some("none") => self.frame_arguments = FrameArguments::None,
+                    Some("scalars") => self.frame_arguments = FrameArguments::Scalars,
+                    Some("all") => self.frame_arguments = FrameArguments::All,
+                    _ => println!("Usage: set print frame-arguments none|scalars|all"),
+                }
+            }
+            Some(other) => println!("Undefined set command: \"{}\"", other),
+            None => println!("Usage: set args|capture-output|sanitize-output|coredump|debug ptrace|pagination|print pretty|print repeated-stops|print frame-arguments|print symbol|print output-summary|print solib-events|print command-time|print elements|rbreak-limit|follow-exec-mode|step-into-plt|substitute-path|stop-hook|wait-interval|crash-report ..."),
+        }
+    }
+
+    /// # brief
+    /// Handle `show <setting>`.
+    fn show(&self, args: &[String]) {
+        match args.get(0).map(|s| s.as_str()) {
+            Some("environment") => self.show_environment(args.get(1)),
+            Some("substitute-path") => {
+                if self.source_paths.rules().is_empty() {
+                    println!("No source path substitution rules.");
+                }
+                for (from, to) in self.source_paths.rules() {
+                    println!("  `{}` -> `{}`", from, to);
+                }
+            }
+            Some("convenience") | Some("conv") => {
+                if self.convenience.is_empty() {
+                    println!("No debugger convenience values now defined.");
+                }
+                let mut names: Vec<&String> = self.convenience.keys().collect();
+                names.sort();
+                for name in names {
+                    println!("${} = {}", name, self.convenience[name]);
+                }
+            }
+            Some("stop-hook") => {
+                if self.stop_hook.is_empty() {
+                    println!("No stop hook is defined.");
+                } else {
+                    println!("Stop hook: {}", self.stop_hook.join("; "));
+                }
+            }
+            Some("values") => self.show_values(),
+            Some("args") => println!(
+                "Argument list to give program being debugged when it is started is \"{}\".",
+                self.args.join(" ")
+            ),
+            Some("wait-interval") => println!(
+                "wait-attach scans for the process every {} ms.",
+                self.wait_interval.as_millis()
+            ),
+            Some("rbreak-limit") => println!(
+                "rbreak asks before setting more than {} breakpoints.",
+                self.rbreak_limit
+            ),
+            Some("step-into-plt") => println!(
+                "Stepping into PLT stubs is {}.",
+                if self.step_into_plt { "on" } else { "off" }
+            ),
+            Some("follow-exec-mode") => println!(
+                "After an exec, the program {}.",
+                if self.exec_stops { "stops in the new program" } else { "continues" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("elements") => match self.print_elements {
+                0 => println!("Limit on string chars or array elements to print is unlimited."),
+                limit => println!("Limit on string chars or array elements to print is {}.", limit),
+            },
+            Some("pagination") => println!("State of pagination is {}.", if ui::pagination() { "on" } else { "off" }),
+            Some("debug") if args.get(1).map(|s| s.as_str()) == Some("ptrace") => println!(
+                "Logging of ptrace requests is {}.",
+                if inferior::trace_requests() { "on" } else { "off" }
+            ),
+            Some("coredump") => println!(
+                "Core dumps of the programs run are {}.",
+                if inferior::dump_core() { "allowed" } else { "left to the inherited limit" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("pretty") => println!(
+                "Pretty printing of known types is {}.",
+                if self.pretty_printing { "on" } else { "off" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("frame-arguments") => println!(
+                "Backtraces show {} arguments.",
+                match self.frame_arguments {
+                    FrameArguments::None => "no",
+                    FrameArguments::Scalars => "scalar",
+                    FrameArguments::All => "all",
+                }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("symbol") => println!(
+                "Printing of symbol names when printing pointers is {}.",
+                if self.print_symbol { "on" } else { "off" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("output-summary") => println!(
+                "Summaries of the program output at stops are {}.",
+                if self.print_output_summary { "on" } else { "off" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("solib-events") => println!(
+                "Reports of the shared libraries loaded and unloaded are {}.",
+                if self.print_solib_events { "on" } else { "off" }
+            ),
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("command-time") => println!(
+                "Reporting the time every command takes is {}.",
+                if self.print_command_time { "on" } else { "off" }
+            ),
+            Some("capture-output") => println!(
+                "Capturing of the program output is {}.",
+                if self.capture_output { "on" } else { "off" }
+            ),
+            Some("sanitize-output") => println!(
+                "Clearing the screen in the captured program output is {}.",
+                if self.output.sanitize() { "replaced by a line saying so" } else { "passed to the terminal" }
+            ),
+            Some("crash-report") => match &self.crash_report {
+                Some((path, json)) => println!("Crashes are reported to {}{}.", path, if *json { " as JSON" } else { "" }),
+                None => println!("Crashes are not reported to a file."),
+            },
+            Some("print") if args.get(1).map(|s| s.as_str()) == Some("repeated-stops") => println!(
+                "Repeated stops at the same line are {}.",
+                if self.print_repeated_stops { "reported in full" } else { "collapsed" }
+            ),
+            Some(other) => println!("Undefined show command: \"{}\"", other),
+            None => println!("Usage: show args|capture-output|sanitize-output|coredump|debug ptrace|pagination|print pretty|print repeated-stops|print frame-arguments|print symbol|print output-summary|print solib-events|print command-time|print elements|rbreak-limit|follow-exec-mode|step-into-plt|substitute-path|convenience|values|stop-hook|wait-interval|crash-report"),
+        }
+    }
+
+    /// # brief
+    /// Handle `set crash-report <path> [json]`, checking the specifiers of the path right away
+    /// rather than at the first crash.
+    fn set_crash_report(&mut self, path: &str, json: bool) {
+        if let Err(err) = crash_report::report_path(path, nix::unistd::Pid::this(), &self.target) {
+            println!("{}", err);
+            return;
+        }
+        self.crash_report = Some((path.to_string(), json));
+    }
+
+    /// # brief
+    /// Handle `info <subcommand>`.
+    fn info(&mut self, args: &[String]) {
+        match args.get(0).map(|s| s.as_str()) {
+            Some("source") => self.info_source(),
+            Some("snapshots") => self.info_snapshots(),
+            Some("heap") => self.info_heap(),
+            Some("assertions") => self.info_assertions(),
+            Some("inferiors") => self.info_inferiors(),
+            Some("program") => self.info_program(),
+            Some("start") => self.info_start(),
+            Some("runs") => self.info_runs(),
+            Some("threads") => self.info_threads(),
+            Some("output") => self.info_output(args.get(1)),
+            Some("dwarf-cache") => self.info_dwarf_cache(),
+            Some("stats") => self.info_stats(),
+            Some("dwarf-stats") => println!("{}", self.debug_data.load_stats()),
+            Some("dwarf-errors") => self.info_dwarf_errors(),
+            Some("groups") => self.info_groups(),
+            Some("display") => self.info_display(),
+            Some("sharedlibrary") | Some("shared") => self.info_shared_libraries(),
+            Some("break") | Some("breakpoints") | Some("b") if args.get(1).map(|s| s.as_str()) == Some("history") => {
+                self.info_breakpoint_history()
+            }
+            Some("break") | Some("breakpoints") | Some("b") => self.info_breakpoints(),
+            Some("all-registers") => self.info_all_registers(args.get(1).map(|name| name.trim_start_matches('$'))),
+            Some("scope") => match args.get(1) {
+                Some(location) => self.info_scope(location),
+                None => println!("Usage: info scope *address|line|file:line|func"),
+            },
+            Some("address") => match args.get(1) {
+                Some(name) => self.info_address(name),
+                None => println!("Argument required (symbol name)."),
+            },
+            Some("symbol") if args.len() > 1 => self.info_symbol(&args[1..].join(" ")),
+            Some("symbol") => println!("Argument required (address)."),
+            Some("functions") => self.info_functions(&args[1..].join(" ")),
+            Some("types") => self.info_types(&args[1..]),
+            Some("fds") => self.info_fds(),
+            Some(other) => println!("Undefined info command: \"{}\"", other),
+            None => println!("Usage: info break|source|address|symbol|scope|snapshots|heap|assertions|inferiors|program|threads|output|all-registers|dwarf-cache|dwarf-stats|dwarf-errors|sharedlibrary|groups|stats|functions|types|start|runs|fds"),
+        }
+    }
+
+    /// # brief
+    /// Measure the heap usage of the inferior from its memory mappings.
+    fn measure_heap(&self) -> Option<HeapUsage> {
+        let pid = self.inferior.as_ref()?.pid();
+        Some(maps::heap_usage(&maps::read_maps(pid).ok()?))
+    }
+
+    /// # brief
+    /// Handle `info heap`: print the size of the heap segment and of the anonymous mappings, and
+    /// how much they grew since the previous stop.
+    fn info_heap(&self) {
+        if self.inferior.is_none() {
+            println!("The program is not being run.");
+            return;
+        }
+        let current = match self.measure_heap() {
+            Some(usage) => usage,
+            None => {
+                println!("Cannot read the memory mappings of the process");
+                return;
+            }
+        };
+        let delta = |now: usize, before: Option<usize>| match before {
+            Some(before) => format!(" ({:+} bytes since the previous stop)", now as i64 - before as i64),
+            None => String::new(),
+        };
+        let previous = self.heap_at_stops.1;
+        println!("[heap] segment:       {} bytes{}", current.heap, delta(current.heap, previous.map(|usage| usage.heap)));
+        println!(
+            "Anonymous mappings:   {} bytes in {} mappings{}",
+            current.anonymous,
+            current.anonymous_count,
+            delta(current.anonymous, previous.map(|usage| usage.anonymous))
+        );
+        // reading malloc's arenas needs the symbols of the C library, which are not loaded
+        println!("Allocator internals unavailable (no debug symbols for the C library)");
+    }
+
+    /// # brief
+    /// Handle `info all-registers [name]`: print the general purpose, x87 and SSE registers, or
+    /// only the register `name`.
+    fn info_all_registers(&self, name: Option<&str>) {
+        let inferior = match self.inferior.as_ref() {
+            Some(inferior) => inferior,
+            None => {
+                println!("The program has no registers now.");
+                return;
+            }
+        };
+        let regs = self.context.registers(inferior, self.identity()).map_err(|err| err.to_string());
+        let (regs, fpregs) = match (regs, inferior.get_fpregs().map_err(|err| err.to_string())) {
+            (Ok(regs), Ok(fpregs)) => (regs, fpregs),
+            (Err(err), _) | (_, Err(err)) => {
+                println!("Cannot read the registers: {}", err);
+                return;
+            }
+        };
+        let selected = |reg: &str| name.map_or(true, |name| name == reg);
+        let mut found = false;
+        for reg in registers::GENERAL_REGISTERS.iter().filter(|reg| selected(reg)) {
+            let value = registers::get_register(&regs, reg).unwrap();
+            println!("{:<10}{:<20}{}", reg, format!("{:#x}", value), value as i64);
+            found = true;
+        }
+        for i in (0..8).filter(|i| selected(&format!("st{}", i))) {
+            let raw = fpregs.st[i];
+            println!("{:<10}{:<24}(raw {:#022x})", format!("st{}", i), registers::f80_to_f64(raw), raw & ((1u128 << 80) - 1));
+            found = true;
+        }
+        for reg in ["fctrl", "fstat", "ftag", "mxcsr"].iter().filter(|reg| selected(reg)) {
+            println!("{:<10}{:#x}", reg, fpregs.get_control(reg).unwrap());
+            found = true;
+        }
+        for i in (0..16).filter(|i| selected(&format!("xmm{}", i))) {
+            println!("{:<10}{}", format!("xmm{}", i), registers::format_xmm(fpregs.xmm[i]));
+            found = true;
+        }
+        if !found {
+            println!("Invalid register `{}'", name.unwrap_or(""));
+        }
+    }
+
+    /// # brief
+    /// Handle `info symbol <addr>`: name the symbol an address points into, with the offset
+    /// into it and the section, e.g. `main + 0x1f in section .text`.
+    fn info_symbol(&mut self, text: &str) {
+        let addr = match self.evaluate(text) {
+            Ok(addr) => addr as usize,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        let (name, offset) = match self.debug_data.symbol_at(addr) {
+            Some(symbol) => symbol,
+            None => {
+                println!("No symbol matches {}.", text);
+                return;
+            }
+        };
+        let symbol = if offset == 0 { name } else { format!("{} + {:#x}", name, offset) };
+        match self.debug_data.section_at(addr) {
+            Some(section) => println!("{} in section {}", symbol, section),
+            None => println!("{}", symbol),
+        }
+    }
+
+    /// # brief
+    /// Handle `info functions [regex]`: list the functions defined in the debug information, or
+    /// those whose name matches the regex, by file. Ctrl+C stops the listing.
+    fn info_functions(&self, pattern: &str) {
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                println!("Invalid regex \"{}\": {}", pattern, err);
+                return;
+            }
+        };
+        let cancel = CancelToken::new();
+        let (mut functions, _) = self
+            .debug_data
+            // declarations and abstract instances of inlined functions have no code
+            .functions_matching(|name| regex.is_match(name), &cancel);
+        functions.retain(|(_, func)| func.text_length > 0);
+        functions.sort_by(|(a_file, a), (b_file, b)| (&a_file.name, &a.name).cmp(&(&b_file.name, &b.name)));
+        functions.dedup_by_key(|(_, func)| func.address);
+        let mut pager = Pager::with_cancel(&cancel);
+        let mut printed = if pattern.is_empty() {
+            pager.line("All defined functions:")
+        } else {
+            pager.line(&format!("All functions matching \"{}\":", pattern))
+        };
+        let mut file_name = None;
+        for (file, func) in functions.iter() {
+            if !printed {
+                break;
+            }
+            if file_name != Some(&file.name) {
+                file_name = Some(&file.name);
+                printed = pager.line("") && pager.line(&format!("File {}:", self.source_paths.to_local(&file.name)));
+            }
+            printed = printed && pager.line(&format!("{}:\t{}", func.line_number, func.name));
+        }
+        if cancel.is_cancelled() {
+            println!("(interrupted)");
+        }
+    }
+
+    /// # brief
+    /// Handle `info types [-a] [regex]`: list the structs, classes, unions, enums and typedefs
+    /// whose name matches, by the file declaring them. Anonymous and compiler-generated types
+    /// are only listed with `-a`.
+    fn info_types(&self, args: &[String]) {
+        let all = args.first().map(|arg| arg.as_str()) == Some("-a");
+        let pattern = args[all as usize..].join(" ");
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(err) => {
+                println!("Invalid regex \"{}\": {}", pattern, err);
+                return;
+            }
+        };
+        let mut types: Vec<&TypeDefinition> = self
+            .debug_data
+            .types()
+            .iter()
+            .filter(|def| all || !def.is_hidden())
+            .filter(|def| regex.is_match(def.name.as_deref().unwrap_or("")))
+            .collect();
+        // a type of a header is defined again in every unit including it
+        types.sort_by(|a, b| (&a.file, &a.name, a.line_number, a.kind).cmp(&(&b.file, &b.name, b.line_number, b.kind)));
+        types.dedup_by(|a, b| (&a.file, &a.name, a.line_number, a.kind) == (&b.file, &b.name, b.line_number, b.kind));
+        let cancel = CancelToken::new();
+        let mut pager = Pager::with_cancel(&cancel);
+        let mut printed = if pattern.is_empty() {
+            pager.line("All defined types:")
+        } else {
+            pager.line(&format!("All types matching \"{}\":", pattern))
+        };
+        let mut file_name = None;
+        for def in types.iter() {
+            if !printed {
+                break;
+            }
+            if file_name != Some(&def.file) {
+                file_name = Some(&def.file);
+                printed = pager.line("") && pager.line(&format!("File {}:", self.source_paths.to_local(&def.file)));
+            }
+            let name = def.name.as_deref().unwrap_or("<anonymous>");
+            let description = match &def.target {
+                Some(target) => format!("typedef {} {}", target.name, name),
+                None => format!("{} {}", def.kind, name),
+            };
+            printed = printed && pager.line(&format!("{}:\t{} ({} bytes)", def.line_number, description, def.size));
+        }
+        if cancel.is_cancelled() {
+            println!("(interrupted)");
+        }
+    }
+
+    /// # brief
+    /// Handle `ptype <type name|expression>`: print the layout of a type, its members with their
+    /// offsets and sizes or its enumerators with their values. An expression is not evaluated,
+    /// only its type is found, so no memory is read and no process is needed.
+    fn ptype(&self, arg: &str) {
+        let arg = arg.trim();
+        if arg.is_empty() {
+            println!("Usage: ptype <type name|expression>");
+            return;
+        }
+        let mut definitions = self.debug_data.find_type(arg);
+        // `typedef struct point point` names both
+        if definitions.iter().any(|def| def.kind != TypeKind::Typedef) {
+            definitions.retain(|def| def.kind != TypeKind::Typedef);
+        }
+        if !definitions.is_empty() {
+            for def in definitions {
+                self.print_type_layout(def, "", "");
+            }
+            return;
+        }
+        let dtype = match expr::parse(arg).and_then(|expr| self.static_type(&expr)) {
+            Ok(dtype) => dtype,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            }
+        };
+        // the layout of a pointed-to or const struct is shown too
+        let (qualifier, rest) = match dtype.name.strip_prefix("const ") {
+            Some(rest) => ("const ", rest),
+            None => ("", dtype.name.as_str()),
+        };
+        let (name, pointer) = match rest.strip_suffix(" *") {
+            Some(name) => (name, " *"),
+            None => (rest, ""),
+        };
+        match self.preferred_type(name) {
+            Some(def) => self.print_type_layout(def, qualifier, pointer),
+            None => println!("type = {}", dtype.name),
+        }
+    }
+
+    /// # brief
+    /// Print the layout of a type definition for `ptype`, following typedefs to the type they
+    /// name.
+    ///
+    /// # param
+    /// - `def` : The definition to print.
+    /// - `prefix` : The qualifiers before the type, e.g. `const `.
+    /// - `suffix` : What follows the type, ` *` for a pointer to it.
+    fn print_type_layout(&self, def: &TypeDefinition, prefix: &str, suffix: &str) {
+        let mut def = def;
+        // typedefs of typedefs, up to a few levels
+        for _ in 0..4 {
+            if def.kind != TypeKind::Typedef {
+                break;
+            }
+            let target = def.target.as_ref().map_or("void", |target| target.name.as_str());
+            match self.preferred_type(target) {
+                Some(target) => def = target,
+                None => {
+                    println!("type = {}{}{}", prefix, target, suffix);
+                    return;
+                }
+            }
+        }
+        let name = def.name.as_deref().unwrap_or("<anonymous>");
+        let location = format!("{}:{}", self.source_paths.to_local(&def.file), def.line_number);
+        if def.kind == TypeKind::Typedef {
+            // a loop of typedefs
+            println!("type = {}{}{}", prefix, name, suffix);
+            return;
+        }
+        println!("type = {}{} {} {{", prefix, def.kind, name);
+        if def.kind == TypeKind::Enum {
+            for (name, value) in def.enumerators.iter() {
+                println!("    {} = {},", name, value);
+            }
+        } else {
+            if !def.members.is_empty() {
+                println!("    /* offset |  size */");
+            }
+            for member in def.members.iter() {
+                let name = member.name.as_deref().unwrap_or("<anonymous>");
+                match member.bit_size {
+                    Some(bits) => println!(
+                        "    /* {:>6} |     ? */  {} {} : {};  /* bitfield, not supported */",
+                        member.offset, member.member_type.name, name, bits
+                    ),
+                    None => println!(
+                        "    /* {:>6} | {:>5} */  {} {};",
+                        member.offset, member.member_type.size, member.member_type.name, name
+                    ),
+                }
+            }
+            if def.has_variants {
+                println!("    /* variants, not supported */");
+            }
+        }
+        println!("}}{} /* {} bytes, {} */", suffix, def.size, location);
+    }
+
+    /// # brief
+    /// Find the definition of the type `name`, a struct rather than a typedef of the same name.
+    fn preferred_type(&self, name: &str) -> Option<&TypeDefinition> {
+        let definitions = self.debug_data.find_type(name);
+        definitions
+            .iter()
+            .find(|def| def.kind != TypeKind::Typedef)
+            .or(definitions.first())
+            .copied()
+    }
+
+    /// # brief
+    /// Find the type of an expression without evaluating it. Arithmetic and comparisons yield
+    /// the unsigned 64-bit integers the evaluator computes with.
+    ///
+    /// # return
+    /// * The type, or why the expression has none (an unknown symbol).
+    fn static_type(&self, expr: &Expr) -> Result<Type, String> {
+        match expr {
+            Expr::Symbol(name) => {
+                let rip = self.register("rip").map(|rip| rip as usize);
+                let variables = self.debug_data.find_variable(name);
+                let local = variables.iter().find(|(_, func, _)| match (func, rip) {
+                    (Some(func), Some(rip)) => func.address <= rip && rip < func.address + func.text_length,
+                    _ => false,
+                });
+                let global = variables.iter().find(|(_, func, _)| func.is_none());
+                match local.or(global) {
+                    Some((_, _, var)) => Ok(var.entity_type.clone()),
+                    None if !self.debug_data.find_function(name).is_empty() => Ok(Type::new("<function>".to_string(), 0)),
+                    None => Err(format!("No symbol \"{}\" in current context.", name)),
+                }
+            }
+            Expr::AddressOf(name) => {
+                let target = self.static_type(&Expr::Symbol(name.clone()))?;
+                Ok(Type::new(format!("{} *", target.name), 8))
+            }
+            Expr::Negate(inner) => self.static_type(inner).map(|_| Type::new("unsigned long".to_string(), 8)),
+            Expr::Binary(_, lhs, rhs) => {
+                self.static_type(lhs)?;
+                self.static_type(rhs)?;
+                Ok(Type::new("unsigned long".to_string(), 8))
+            }
+            Expr::Number(_) | Expr::Variable(_) => Ok(Type::new("unsigned long".to_string(), 8)),
+        }
+    }
+
+    /// # brief
+    /// Get the ` <symbol+offset>` annotation of a pointer value when `set print symbol` is on,
+    /// an empty string otherwise or when it points into no symbol.
+    fn symbol_suffix(&self, value: u64) -> String {
+        if !self.print_symbol {
+            return String::new();
+        }
+        match self.debug_data.symbol_at(value as usize) {
+            Some((name, 0)) => format!(" <{}>", name),
+            Some((name, offset)) => format!(" <{}+{:#x}>", name, offset),
+            None => String::new(),
+        }
+    }
+
+    /// # brief
+    /// Print where the functions and variables named `name` live. Every match is listed, since
+    /// static symbols may share a name across files.
+    fn info_address(&self, name: &str) {
+        let functions = self.debug_data.find_function(name);
+        let variables = self.debug_data.find_variable(name);
+        if functions.is_empty() && variables.is_empty() {
+            println!("No symbol \"{}\" in current context.", name);
+            return;
+        }
+        for (file, func) in functions {
+            println!(
+                "Symbol \"{}\" is a function at address {:#x}, {} bytes long, declared at {}:{}.",
+                name, func.address, func.text_length, file.name, func.line_number
+            );
+        }
+        for (file, func, var) in variables {
+            match (&var.location, func) {
+                (Location::Address(addr), None) => println!(
+                    "Symbol \"{}\" is static storage at address {:#x}, declared at {}:{}.",
+                    name, addr, file.name, var.line_number
+                ),
+                (Location::Address(addr), Some(func)) => println!(
+                    "Symbol \"{}\" is static storage at address {:#x} in function {}, declared at {}:{}.",
+                    name, addr, func.name, file.name, var.line_number
+                ),
+                (Location::FramePointerOffset(offset), Some(func)) => println!(
+                    "Symbol \"{}\" is a variable at frame base offset {} in function {}, declared at {}:{}; it only exists while {} is running.",
+                    name, offset, func.name, file.name, var.line_number, func.name
+                ),
+                (Location::FramePointerOffset(offset), None) => println!(
+                    "Symbol \"{}\" is a variable at frame base offset {}, declared at {}:{}.",
+                    name, offset, file.name, var.line_number
+                ),
+                (Location::Unavailable(reason), _) => println!(
+                    "Symbol \"{}\" is a variable with no readable location ({}), declared at {}:{}.",
+                    name, reason, file.name, var.line_number
+                ),
+                (location, func) => println!(
+                    "Symbol \"{}\" is a variable {}{}, declared at {}:{}.",
+                    name,
+                    describe_location(location),
+                    func.map_or(String::new(), |func| format!(" in function {}", func.name)),
+                    file.name,
+                    var.line_number
+                ),
+            }
+        }
+    }
+
+    /// # brief
+    /// Handle `info scope <location>`: list the variables of the function containing the
+    /// location, with where each of them is stored.
+    fn info_scope(&self, location: &str) {
+        let addr = match self.resolve_location(location) {
+            Some((addr, _)) => addr,
+            None => return,
+        };
+        let (file, func) = match self.debug_data.function_containing(addr) {
+            Some(found) => found,
+            None => {
+                println!("No function contains address {:#x}.", addr);
+                return;
+            }
+        };
+        println!("Scope for {} (function {} in {}):", location, func.name, file.name);
+        if func.variables.is_empty() {
+            println!("Symbol table contains no locals.");
+        }
+        for var in func.variables.iter() {
+            let storage = describe_location(&var.location);
+            println!("Symbol {} is {}, {} bytes ({}).", var.name, storage, var.entity_type.size, var.entity_type.name);
+        }
+    }
+
+    /// # brief
+    /// Print the recorded path, compilation directory, and local path of the current source
+    /// file, i.e. the file of the last stop location.
+    fn info_source(&self) {
+        let line = match &self.current_line {
+            Some(line) => line,
+            None => {
+                println!("No current source file.");
+                return;
+            }
+        };
+        let comp_dir = self
+            .debug_data
+            .get_target_file(&line.file)
+            .and_then(|file| file.comp_dir.clone());
+        println!("Current source file is {}", line.file);
+        if let Some(dir) = &comp_dir {
+            println!("Compilation directory is {}", dir);
+        }
+        match self.source_paths.locate(&line.file, comp_dir.as_deref()) {
+            Some(path) => println!("Located in {}", path.display()),
+            None => println!(
+                "Source file not found on disk (looked for {})",
+                self.source_paths.to_local(&line.file)
+            ),
+        }
+    }
+
+    /// # brief
+    /// Run the debugger, processing user commands and controlling the inferior process.
+    ///
+    /// This method enters a loop to continuously receive and process user commands for controlling
+    /// the debugger and the inferior process. It handles commands such as quitting the debugger,
+    /// starting or restarting the inferior process, continuing the execution, printing backtraces,
+    /// and setting breakpoints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut debugger = Debugger::new();
+    /// debugger.run();
+    /// ```plaintext
+    pub fn run(&mut self) -> i32 {
+        for line in self.startup_commands.clone() {
+            if !self.execute_line(&line) {
+                return self.exit_code();
+            }
+        }
+        if self.batch {
+            self.execute(DebuggerCommand::Quit(Vec::new()));
+            return self.exit_code();
+        }
+        loop {
+            let line = self.get_next_line();
+            if !self.execute_line(&line) {
+                return self.exit_code();
+            }
+        }
+    }
+
+    /// # brief
+    /// The exit status of deet: the one given to `quit`, else 1 if an assertion failed in a batch
+    /// session, 0 otherwise.
+    fn exit_code(&self) -> i32 {
+        if let Some(code) = self.quit_code {
+            return code;
+        }
+        if self.batch && !self.assertion_failures.is_empty() { 1 } else { 0 }
+    }
+
+    /// # brief
+    /// Ask a yes or no question before a destructive command. The answer is yes without asking
+    /// in batch mode and when the input is not a terminal, as nobody could answer; an end of
+    /// input at the question is a yes too, so a second Ctrl+D quits.
+    ///
+    /// # param
+    /// - `question` : The question, without the `(y or n)` suffix.
+    ///
+    /// # return
+    /// * Whether the command is confirmed.
+    fn confirm(&mut self, question: &str) -> bool {
+        if self.batch || unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            return true;
+        }
+        loop {
+            match self.readline.readline(&format!("{} (y or n) ", question)) {
+                Ok(answer) => match answer.trim() {
+                    "y" | "yes" => return true,
+                    "n" | "no" => return false,
+                    _ => println!("Please answer y or n."),
+                },
+                Err(ReadlineError::Eof) => {
+                    println!("EOF [answered Y; input not from terminal]");
+                    return true;
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// # brief
+    /// Ask which of several matches a command is for, e.g. the functions of the same name
+    /// `break` is given: a numbered menu, `[0] all`, the matches from 1 and `[q] cancel`, read
+    /// through the line editor. In batch mode and when the input is not a terminal nobody can
+    /// answer, so all of them are taken, with a warning.
+    ///
+    /// # param
+    /// - `title` : The line above the menu, saying what is ambiguous.
+    /// - `choices` : A line for each match.
+    ///
+    /// # return
+    /// * What was picked, `None` if cancelled.
+    fn choose(&mut self, title: &str, choices: &[String]) -> Option<Choice> {
+        if self.batch || unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            println!("Warning: {}; taking all of them.", title.trim_end_matches(':'));
+            return Some(Choice::All);
+        }
+        println!("{}", title);
+        println!("[0] all");
+        for (index, choice) in choices.iter().enumerate() {
+            println!("[{}] {}", index + 1, choice);
+        }
+        println!("[q] cancel");
+        loop {
+            match self.readline.readline("> ") {
+                Ok(answer) => match answer.trim() {
+                    "q" | "cancel" => return None,
+                    "0" | "all" => return Some(Choice::All),
+                    number => match number.parse::<usize>() {
+                        Ok(number) if number >= 1 && number <= choices.len() => return Some(Choice::One(number - 1)),
+                        _ => println!("Please answer a number from 0 to {}, or q.", choices.len()),
+                    },
+                },
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// # brief
+    /// Describe what quitting does to each program being debugged, for the question of `quit`.
+    fn quit_consequences(&self) -> Vec<String> {
+        let describe = |id: usize, inferior: &Option<Inferior>, remote: &Option<Remote>| match (inferior, remote) {
+            (_, Some(_)) => Some(format!("Inferior {} [remote target] will be detached.", id)),
+            (Some(inferior), None) if inferior.is_attached() => {
+                Some(format!("Inferior {} [process {}] will be detached.", id, inferior.pid()))
+            }
+            (Some(inferior), None) => Some(format!("Inferior {} [process {}] will be killed.", id, inferior.pid())),
+            (None, None) => None,
+        };
+        std::iter::once(describe(self.inferior_id, &self.inferior, &self.remote))
+            .chain(self.other_inferiors.iter().map(|state| describe(state.id, &state.inferior, &state.remote)))
+            .flatten()
+            .collect()
+    }
+
+    /// # brief
+    /// Execute a command and report the wall-clock time it took. With `full`, as the `time`
+    /// prefix asks, also report the CPU time the inferior used meanwhile and the traps of
+    /// breakpoints it reached, the ones resumed from without a stop report included.
+    ///
+    /// # return
+    /// * `false` if the debugger should quit, as `execute`.
+    fn time_command(&mut self, cmd: DebuggerCommand, full: bool) -> bool {
+        let pid = self.inferior.as_ref().map(|inferior| inferior.pid());
+        let cpu_before = self.inferior.as_ref().and_then(|inferior| inferior.cpu_times());
+        let hits_before: u64 = stats::current().breakpoint_hits.values().sum();
+        let started = Instant::now();
+        let keep = self.execute(cmd);
+        let elapsed = if ui::deterministic() { "-".to_string() } else { format!("{:.1?}", started.elapsed()) };
+        if !full {
+            println!("Command time: {}", elapsed);
+            return keep;
+        }
+        let hits: u64 = stats::current().breakpoint_hits.values().sum::<u64>() - hits_before;
+        // the same process, not one started by the command
+        let cpu_after = match self.inferior.as_ref() {
+            Some(inferior) if Some(inferior.pid()) == pid => inferior.cpu_times(),
+            _ => None,
+        };
+        let cpu = match (cpu_before, cpu_after) {
+            _ if ui::deterministic() => "-".to_string(),
+            (Some((user_before, system_before)), Some((user, system))) => format!(
+                "{:.2?} user, {:.2?} system",
+                user.saturating_sub(user_before),
+                system.saturating_sub(system_before)
+            ),
+            (Some(_), None) => "unknown, the process ended".to_string(),
+            (None, _) => "none, no process was running".to_string(),
+        };
+        println!("Wall time: {}, inferior CPU time: {}, breakpoint stops: {}", elapsed, cpu, hits);
+        keep
+    }
+
+    /// # brief
+    /// Tell whether a command is refused in a read-only session: it writes traps or memory,
+    /// delivers a signal, or ends the process observed. Stepping by lines and `finish` plant
+    /// temporary traps; `stepi` and a plain `continue` don't change the program.
+    ///
+    /// # return
+    /// * What the command would do, for the error, or `None` if it may run.
+    fn read_only_refusal(&self, cmd: &DebuggerCommand) -> Option<&'static str> {
+        if !self.read_only {
+            return None;
+        }
+        match cmd {
+            DebuggerCommand::Breakpoint(_) | DebuggerCommand::Dprintf(_) | DebuggerCommand::RegexBreakpoint(_) => {
+                Some("set breakpoints")
+            }
+            DebuggerCommand::Step(_) | DebuggerCommand::Finish => Some("step with temporary breakpoints"),
+            DebuggerCommand::Continue(args) if args.first().map(|arg| arg.as_str()) == Some("until") => {
+                Some("continue until")
+            }
+            // `continue [--timeout <duration>]` without a signal only resumes
+            DebuggerCommand::Continue(args)
+                if debugger_command::take_timeout(args).map_or(false, |(_, rest)| !rest.is_empty()) =>
+            {
+                Some("deliver a signal")
+            }
+            DebuggerCommand::Set(args) if args.first().map_or(false, |arg| arg.starts_with('{')) => Some("write memory"),
+            DebuggerCommand::Run(_) | DebuggerCommand::Start(_) | DebuggerCommand::StartInstruction(_) => {
+                Some("start the program again")
+            }
+            _ => None,
+        }
+    }
+
+    /// # brief
+    /// Execute one debugger command. This is used for commands typed at the prompt as well as
+    /// for replayed command lists like the stop hook.
+    ///
+    /// # param
+    /// - `cmd` : The command to execute.
+    ///
+    /// # return
+    /// * `false` if the debugger should quit, `true` otherwise.
+    fn execute(&mut self, cmd: DebuggerCommand) -> bool {
+        // the inferior may have died since it last stopped, report that once instead of
+        // failing ptrace requests on it
+        if let Some(status) = self.inferior.as_mut().and_then(|inferior| inferior.poll_exit()) {
+            println!("The program is no longer running.");
+            self.handle_status(status);
+        }
+        if let Some(refused) = self.read_only_refusal(&cmd) {
+            println!("Cannot {}: {}.", refused, READ_ONLY_ERROR);
+            return true;
+        }
+        // anything but an inspection may run or change the program, and starts from frame 0
+        if !cmd.is_inspection() {
+            self.context.on_resume();
+        }
+        match &cmd {
+            DebuggerCommand::Run(_) => stats::record(|stats| stats.runs += 1),
+            DebuggerCommand::Continue(_) => stats::record(|stats| stats.continues += 1),
+            DebuggerCommand::Step(_) | DebuggerCommand::StepInstruction | DebuggerCommand::Finish => {
+                stats::record(|stats| stats.steps += 1)
+            }
+            _ => {}
+        }
+        match cmd {
+            DebuggerCommand::Time(inner) => return self.time_command(*inner, true),
+
+            // if the inferior still alive, then kill it and set inferior into None, finally
+            // stop the loop
+            DebuggerCommand::Quit(args)         => {
+                let code = match args.get(0).map(|code| code.parse::<i32>()) {
+                    None => None,
+                    Some(Ok(code)) => Some(code),
+                    Some(Err(_)) => {
+                        println!("Usage: quit [exit code]");
+                        return true;
+                    }
+                };
+                let consequences = self.quit_consequences();
+                if !consequences.is_empty() {
+                    let question = format!("A debugging session is active.\n\n\t{}\n\nQuit anyway?", consequences.join("\n\t"));
+                    if !self.confirm(&question) {
+                        println!("Not confirmed.");
+                        return true;
+                    }
+                }
+                self.quit_code = code;
+                self.shutdown();
+                return false;
+            }
+
+            // Determine whether inferior exists. If it exists, kill it and then 
+            // create a new inferior and execute it directly.
+            DebuggerCommand::Run(args)             => {
+                let (timeout, args) = match debugger_command::take_timeout(&args) {
+                    Ok(split) => split,
+                    Err(err) => {
+                        println!("{}", err);
+                        return true;
+                    }
+                };
+                if self.remote.is_some() {
+                    println!("Error: run is not supported on a remote target, disconnect first");
+                } else if args.first().map(|arg| arg.as_str()) == Some("--until-failure") {
+                    match args.get(1).map(|max| max.parse::<usize>()) {
+                        None => self.run_until_failure(DEFAULT_MAX_RUNS),
+                        Some(Ok(max)) if max > 0 => self.run_until_failure(max),
+                        Some(_) => println!("Usage: run --until-failure [max runs]"),
+                    }
+                } else if let Some(number) = args.first().and_then(|arg| arg.strip_prefix('!')) {
+                    // `run !N` replays the arguments of run N of `info runs`
+                    match number.parse::<usize>().ok().and_then(|number| self.runs.get(number.wrapping_sub(1))) {
+                        Some(run) if args.len() == 1 => {
+                            self.args = run.args.clone();
+                            if let Some(status) = self.start_inferior(timeout, FirstStop::Breakpoint) {
+                                self.handle_status(status);
+                            }
                         }
-                    } else if let Some(address) = self.debug_data.get_addr_for_function(None, &localtion) {
-                        breakpoint_addr = address;
-                    } else {
-                        println!("Usage b|break|breakpoint *address|line|func");
-                        continue;
+                        Some(_) => println!("Usage: run !<run number>"),
+                        None => println!("No run number {}, see info runs.", number),
+                    }
+                } else {
+                    // `run` without arguments reuses the last ones
+                    if !args.is_empty() {
+                        self.args = args;
                     }
+                    if let Some(status) = self.start_inferior(timeout, FirstStop::Breakpoint) {
+                        self.handle_status(status);
+                    }
+                }
+            }
+            DebuggerCommand::Start(args)           => self.start(args, FirstStop::Main),
+            DebuggerCommand::StartInstruction(args) => self.start(args, FirstStop::Entry),
 
-                    if self.inferior.is_some() {
-                        if let Some(instruction) = self.inferior.as_mut().unwrap().write_byte(breakpoint_addr, 0xcc).ok() {
-                            println!("Set breakpoint {} at {:#x}", self.breakpoints.len(), breakpoint_addr);
-                            self.breakpoints.insert(breakpoint_addr, instruction);
-                        } else {
-                            println!("Invalid breakpoint address {:#x}", breakpoint_addr);
+            // call continues_run from inferior ;
+            // and wait for status changing of child .
+            DebuggerCommand::Continue(args) if args.first().map(|arg| arg.as_str()) == Some("until") => {
+                self.continue_until(args[1..].join(" ").trim())
+            }
+            DebuggerCommand::Continue(args)        => {
+                let (timeout, signal) = match debugger_command::take_timeout(&args) {
+                    Ok((timeout, rest)) if rest.len() <= 1 => match rest.first().map(|text| inferior::parse_signal(text)) {
+                        None => (timeout, None),
+                        Some(Ok(signal)) => (timeout, Some(signal)),
+                        Some(Err(err)) => {
+                            println!("{}", err);
+                            return true;
+                        }
+                    },
+                    Ok(_) => {
+                        println!("Usage: continue [--timeout <duration>] [signal], continue until <expression>");
+                        return true;
+                    }
+                    Err(err) => {
+                        println!("{}", err);
+                        return true;
+                    }
+                };
+                if self.remote.is_some() {
+                    if timeout.is_some() {
+                        println!("Note: --timeout is ignored on a remote target");
+                    }
+                    if signal.is_some() {
+                        println!("Note: the signal is not delivered on a remote target");
+                    }
+                    self.remote_resume(false);
+                } else if self.inferior.is_none() {
+                   println!("Error: you can not use continue when there is no process running!");
+                } else {
+                    // `continue SIGNAL` delivers that signal as the inferior resumes, `continue 0` none
+                    match signal {
+                        Some(Some(signal)) => {
+                            let rip = self.register("rip").map(|rip| rip as usize);
+                            if let Some(bp) = self.breakpoint_list.iter().find(|bp| rip.map_or(false, |rip| bp.has_trap(rip))) {
+                                println!(
+                                    "Warning: stopped at breakpoint {}, {:?} is delivered once its instruction has run.",
+                                    bp.number, signal
+                                );
+                            }
+                            println!("Continuing with signal {}.", inferior::describe_signal(signal));
+                        }
+                        Some(None) => println!("Continuing without a signal."),
+                        None => {}
+                    }
+                    let signal = signal.flatten();
+                    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+                    match self.resume(signal, deadline) {
+                        Ok((status, timed_out)) => {
+                            self.timeout_stop = if timed_out { timeout } else { None };
+                            self.handle_status(status);
+                        }
+                        Err(err) => println!("Error: {}", err),
+                    }
+                }
+            }
+
+            // Use the ptracer::step() function to execute 
+            // one step downward from the current rip then 
+            // and observe the state changes of the child process
+            DebuggerCommand::Step(args)            => {
+                if self.remote.is_some() {
+                    println!("Error: step is not supported on a remote target, use stepi");
+                } else if self.inferior.is_none() {
+                    println!("Error: you can not use step when there is no process running");
+                } else if args.is_empty() {
+                    if self.leave_plt() {
+                        match self.inferior.as_mut().unwrap().step_over(&self.breakpoints, &mut self.step_over_points, None, &self.debug_data, None) {
+                            Ok(status) => self.handle_status(status),
+                            Err(err) => println!("Error: {}", err),
+                        }
+                    }
+                } else if args[0] == "--into" && args.len() <= 2 {
+                    self.step_into(args.get(1).map(|name| name.as_str()));
+                } else {
+                    println!("Usage: step [--into [function]]");
+                }
+            }
+
+            // execute exactly one machine instruction
+            DebuggerCommand::StepInstruction       => {
+                if self.remote.is_some() {
+                    self.remote_resume(true);
+                } else if self.inferior.is_none() {
+                    println!("Error: you can not use stepi when there is no process running");
+                } else {
+                    match self.inferior.as_mut().unwrap().step_instruction(&self.breakpoints, &mut self.step_over_points) {
+                        Ok(status) => self.handle_status(status),
+                        Err(err) => println!("Error: {}", err),
+                    }
+                }
+            }
+
+            DebuggerCommand::Finish                => {
+                if self.remote.is_some() {
+                    println!("Error: finish is not supported on a remote target");
+                } else {
+                    self.finish();
+                }
+            }
+
+            // print backtrace of this process , untill back to main function
+            DebuggerCommand::Backtrace(force)      => {
+                if self.remote.is_some() {
+                    println!("Error: backtrace is not supported on a remote target");
+                } else if self.inferior.is_none() && self.core.is_none() {
+                    println!("Erro: you can not use backtrace when there is no process running");
+                } else {
+                    match self.stack(force) {
+                        Ok(stack) => {
+                            let arguments = |frame: &Frame| self.frame_arguments(frame);
+                            ui::page(inferior::render_backtrace(stack, &self.debug_data, &arguments, &self.address_format()))
                         }
-                    } else {
-                        // when the inferior is initiated, these breakpoints will be installed
-                        println!("Set breakpoint {} at {:#x}", self.breakpoints.len(), breakpoint_addr);
-                        self.breakpoints.insert(breakpoint_addr, 0);
+                        Err(err) => println!("Error: {}", err),
                     }
                 }
             }
+
+            // judge if the input have'not error , then get this input and parse into address
+            // and insert HashMap ( usize(addr) - u8(ori_byte) )
+            DebuggerCommand::Breakpoint(text)      => self.breakpoint(&text),
+            DebuggerCommand::Dprintf(words)        => self.dprintf(&words),
+            DebuggerCommand::Delete(args)          => self.delete_breakpoints(&args),
+            DebuggerCommand::Enable(args)          => self.set_breakpoints_enabled(&args, true),
+            DebuggerCommand::Disable(args)         => self.set_breakpoints_enabled(&args, false),
+            DebuggerCommand::Group(args)           => self.group(&args),
+            DebuggerCommand::Condition(text)       => self.condition(&text),
+            DebuggerCommand::List(format, arg)     => self.list(&format, &arg),
+
+            DebuggerCommand::Set(args)             => self.set(&args),
+            DebuggerCommand::Show(args)            => self.show(&args),
+            DebuggerCommand::Info(args)            => self.info(&args),
+            DebuggerCommand::Print(format, text)   => self.print(&format, &text),
+            DebuggerCommand::Examine(format, text) => self.examine(&format, &text),
+            DebuggerCommand::Snapshot(args)        => self.snapshot(&args),
+            DebuggerCommand::Fd(args)              => self.fd(&args),
+            DebuggerCommand::Maintenance(args)     => self.maintenance(&args),
+            DebuggerCommand::Compare(args)         => self.compare(&args),
+            DebuggerCommand::Assert(text)          => self.assert(&text),
+            DebuggerCommand::AddInferior(args)     => self.add_inferior(&args),
+            DebuggerCommand::Inferior(args)        => self.select_inferior(&args),
+            DebuggerCommand::Tui(args)             => self.tui_command(&args),
+            DebuggerCommand::File(args)            => self.file(&args),
+            DebuggerCommand::WaitAttach(args)      => self.wait_attach(&args),
+            DebuggerCommand::RegexBreakpoint(text) => self.regex_breakpoint(&text),
+            DebuggerCommand::Ptype(text) => self.ptype(&text),
+            DebuggerCommand::Skip(args)            => self.skip(&args),
+            DebuggerCommand::Unset(args)           => self.unset(&args),
+            DebuggerCommand::Display(text)         => self.display(&text),
+            DebuggerCommand::Undisplay(args)       => self.undisplay(&args),
+            DebuggerCommand::Disassemble(text)     => self.disassemble(&text),
+            DebuggerCommand::Target(args)          => self.target(&args),
+            DebuggerCommand::Disconnect            => self.disconnect(),
+            DebuggerCommand::Stats(args)           => match args.first().map(|s| s.as_str()) {
+                Some("reset") => {
+                    stats::reset();
+                    println!("Statistics reset.");
+                }
+                _ => println!("Usage: stats reset (see info stats)"),
+            },
+            DebuggerCommand::Thread(args)          => self.thread(&args),
+            DebuggerCommand::Frame(args)           => self.select_frame("frame", &args),
+            DebuggerCommand::Up(args)              => self.select_frame("up", &args),
+            DebuggerCommand::Down(args)            => self.select_frame("down", &args),
+            DebuggerCommand::SaveSession(args)     => match args.as_slice() {
+                [path] => self.save_session(path),
+                _ => println!("Usage: save session <file>"),
+            },
+            DebuggerCommand::RestoreSession(args)  => match args.as_slice() {
+                [path] => self.restore_session(path),
+                _ => println!("Usage: restore session <file>"),
+            },
+        }
+        true
+    }
+}
+
+impl Drop for Debugger {
+    /// A panic unwinding out of `run` leaves the inferiors stopped with their traps in them and
+    /// the terminal as the TUI set it; clean up as `quit` does. The exit code of deet after a
+    /// panic is set in `main`.
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.shutdown();
+        }
+    }
+}
+
+impl Scope for Debugger {
+    /// Resolves `$N` / `$` / `$$` from the value history, then registers of the stopped inferior,
+    /// then convenience variables.
+    fn variable(&self, name: &str) -> Option<u64> {
+        if name.is_empty() || name == "$" || name.chars().all(|c| c.is_ascii_digit()) {
+            return self.history_index(name).map(|index| self.value_history[index].value);
+        }
+        self.register(name).or_else(|| self.convenience.get(name).copied())
+    }
+
+    /// A variable visible at the current stop (or a global read from the executable while no
+    /// program runs) gives its value, aggregates their address as in the value history, and a
+    /// function gives its address.
+    fn symbol(&self, name: &str) -> Result<u64, String> {
+        let read = |addr: usize, ty: &Type, bytes: Option<Vec<u8>>| match bytes {
+            _ if ty.size > 8 => Ok(addr as u64),
+            Some(bytes) => Ok(pretty::read_scalar(&bytes, ty)),
+            None => Err(format!("Cannot access memory at address {:#x}", addr)),
+        };
+        match self.lookup_variable(name) {
+            Some((Ok(Place::Memory(addr)), ty)) => read(addr, &ty, self.read_memory(addr, ty.size.max(1)).ok()),
+            Some((Ok(Place::Register(value)), _)) => Ok(value),
+            Some((Err(reason), _)) => Err(format!("{} is <{}>", name, reason)),
+            None if self.target_access().is_none() && self.static_global(name).is_some() => {
+                let (addr, ty) = self.static_global(name).unwrap();
+                read(addr, &ty, self.debug_data.read_static(addr, ty.size.max(1)))
+            }
+            None => self.address_of(name),
         }
     }
+
+    /// A variable must live in memory; a function gives its address.
+    fn address_of(&self, name: &str) -> Result<u64, String> {
+        let variable = match self.lookup_variable(name) {
+            Some((Ok(Place::Memory(addr)), _)) => Some(addr),
+            Some((Ok(Place::Register(_)), _)) => {
+                return Err(format!("Address requested for identifier \"{}\" which is in a register", name))
+            }
+            Some((Err(reason), _)) => return Err(format!("{} is <{}>", name, reason)),
+            None if self.target_access().is_none() => self.static_global(name).map(|(addr, _)| addr),
+            None => None,
+        };
+        variable
+            .or_else(|| self.debug_data.get_addr_for_function(None, name))
+            .map(|addr| addr as u64)
+            .ok_or(format!("No symbol \"{}\" in current context.", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::symtab::Symbol;
+
+    #[test]
+    fn on_off_defaults_to_on() {
+        let arg = |s: &str| Some(s.to_string());
+        assert_eq!(parse_on_off(None), Some(true));
+        assert_eq!(parse_on_off(arg("on").as_ref()), Some(true));
+        assert_eq!(parse_on_off(arg("off").as_ref()), Some(false));
+        assert_eq!(parse_on_off(arg("yes").as_ref()), None);
+    }
+
+    /// # brief
+    /// A function of the program loaded again.
+    fn function(address: usize, text_length: usize) -> Function {
+        Function { name: "parse".to_string(), address, text_length, ..Function::default() }
+    }
+
+    #[test]
+    fn anchor_follows_the_function() {
+        let moved = function(0x2000, 0x40);
+        assert_eq!(reanchor("parse", 0x10, &[&moved]), Resolution::Resolved(0x2010));
+        assert_eq!(reanchor("parse", 0, &[&moved]), Resolution::Resolved(0x2000));
+    }
+
+    #[test]
+    fn anchor_past_a_shorter_function_is_broken() {
+        let shrunk = function(0x2000, 0x10);
+        assert_eq!(
+            reanchor("parse", 0x10, &[&shrunk]),
+            Resolution::Broken("parse is now shorter than offset 0x10 (16 bytes)".to_string())
+        );
+    }
+
+    #[test]
+    fn anchor_in_a_missing_or_ambiguous_function() {
+        assert_eq!(reanchor("parse", 0x10, &[]), Resolution::Pending);
+        let (first, second) = (function(0x2000, 0x40), function(0x3000, 0x40));
+        assert_eq!(
+            reanchor("parse", 0x10, &[&first, &second]),
+            Resolution::Broken("function parse is ambiguous".to_string())
+        );
+    }
+
+    /// # brief
+    /// A debugger of `target` described by `debug_data`, with no process yet. It keeps its
+    /// history in the temporary directory, away from the one of the user.
+    fn test_debugger(target: &str, debug_data: DwarfData) -> Debugger {
+        let mut debugger = Debugger::with_debug_data(&Options::default(), target, None, debug_data);
+        let name = format!("deet-history-{}-{:?}", std::process::id(), std::thread::current().id());
+        debugger.history_path = std::env::temp_dir().join(name).to_string_lossy().into_owned();
+        debugger
+    }
+
+    /// # brief
+    /// A debugger of the program described by `files`.
+    fn debugger(files: Vec<File>) -> Debugger {
+        test_debugger("prog", DwarfData::from_files(files))
+    }
+
+    /// # brief
+    /// A file with code on the given lines, 0x10 bytes each from `address` on, and the one
+    /// function `function` covering them.
+    fn source_file(name: &str, function: &str, address: usize, lines: &[usize]) -> File {
+        let row = |number: usize, address: usize, end_sequence: bool| Line {
+            file: name.to_string(),
+            number,
+            address,
+            is_stmt: true,
+            column: 0,
+            end_sequence,
+        };
+        let mut rows: Vec<Line> = lines.iter().enumerate().map(|(i, number)| row(*number, address + i * 0x10, false)).collect();
+        rows.push(row(lines[lines.len() - 1], address + lines.len() * 0x10, true));
+        let function = Function {
+            name: function.to_string(),
+            address,
+            text_length: lines.len() * 0x10,
+            line_number: lines[0],
+            ..Function::default()
+        };
+        File { name: name.to_string(), functions: vec![function], lines: rows, ..File::default() }
+    }
+
+    #[test]
+    fn bare_line_is_in_the_file_of_main() {
+        // main is not in the first file, which has code on the same line
+        let debugger = debugger(vec![
+            source_file("util.c", "parse_line", 0x2000, &[10, 12, 14]),
+            source_file("main.c", "main", 0x1000, &[11, 12, 13]),
+        ]);
+        let line = debugger.resolve_line(12).unwrap();
+        assert_eq!((line.file.as_str(), line.number, line.address), ("main.c", 12, 0x1010));
+    }
+
+    #[test]
+    fn bare_line_missing_from_the_file_of_main_is_searched_for() {
+        let debugger = debugger(vec![
+            source_file("main.c", "main", 0x1000, &[3, 4]),
+            source_file("util.c", "parse_line", 0x2000, &[10, 12, 14]),
+            source_file("args.c", "parse_args", 0x3000, &[20, 21]),
+        ]);
+        let line = debugger.resolve_line(12).unwrap();
+        assert_eq!((line.file.as_str(), line.address), ("util.c", 0x2010));
+        // a line no file has code on is the next line with code in the file of main
+        assert!(debugger.resolve_line(30).is_none());
+        assert_eq!(debugger.resolve_line(1).unwrap().address, 0x1000);
+    }
+
+    #[test]
+    fn bare_line_of_several_other_files_is_ambiguous() {
+        let debugger = debugger(vec![
+            source_file("main.c", "main", 0x1000, &[3, 4]),
+            source_file("util.c", "parse_line", 0x2000, &[10, 12]),
+            source_file("args.c", "parse_args", 0x3000, &[12, 13]),
+        ]);
+        assert!(debugger.resolve_line(12).is_none());
+    }
+
+    #[test]
+    fn relative_lines_need_a_stop() {
+        let debugger = debugger(vec![source_file("main.c", "main", 0x1000, &[40, 41, 42, 43])]);
+        assert_eq!(debugger.anchored_line("+3"), Some(Err("No current location: the program has not stopped yet.".to_string())));
+        assert_eq!(debugger.anchored_line("main"), None);
+        assert_eq!(debugger.anchored_line("42"), None);
+    }
+
+    #[test]
+    fn relative_lines_are_from_the_stop_line() {
+        let mut debugger = debugger(vec![source_file("main.c", "main", 0x1000, &[40, 41, 42, 43])]);
+        debugger.current_line = debugger.debug_data.get_addr_for_line(Some("main.c"), 41);
+        assert_eq!(debugger.anchored_line("."), Some(Ok(("main.c".to_string(), 41))));
+        assert_eq!(debugger.anchored_line("+2"), Some(Ok(("main.c".to_string(), 43))));
+        assert_eq!(debugger.anchored_line("-1"), Some(Ok(("main.c".to_string(), 40))));
+        assert_eq!(debugger.anchored_line("-41"), Some(Err("Line 0 is before the start of main.c.".to_string())));
+        // `+` alone and `..` are not line offsets
+        assert_eq!(debugger.anchored_line("+"), None);
+        assert_eq!(debugger.anchored_line(".."), None);
+
+        let (addr, line) = debugger.resolve_location("+2").unwrap();
+        assert_eq!((addr, line.map(|line| line.number)), (0x1030, Some(43)));
+    }
+
+    #[test]
+    fn breakpoints_keep_their_spec_across_a_reload() {
+        let mut debugger = debugger(vec![
+            source_file("main.c", "main", 0x1000, &[10, 11, 12, 13]),
+            source_file("util.c", "helper", 0x2000, &[20, 21]),
+        ]);
+        assert!(debugger.execute_line("break main; break main.c:12; break helper; break *0x2004"));
+        let specs: Vec<(&str, Option<usize>)> = debugger.breakpoint_list.iter().map(|bp| (bp.spec.as_str(), bp.addr())).collect();
+        assert_eq!(specs, [("main", Some(0x1000)), ("main.c:12", Some(0x1020)), ("helper", Some(0x2000)), ("*0x2004", Some(0x2004))]);
+
+        // rebuilt: main moved and helper is gone
+        debugger.replace_debug_data("prog", DwarfData::from_files(vec![source_file("main.c", "main", 0x3000, &[10, 11, 12, 13])]));
+        let resolutions: Vec<&Resolution> = debugger.breakpoint_list.iter().map(|bp| &bp.resolution).collect();
+        assert_eq!(
+            resolutions,
+            [&Resolution::Resolved(0x3000), &Resolution::Resolved(0x3020), &Resolution::Pending, &Resolution::Pending]
+        );
+        let table = debugger.breakpoint_table().render(200);
+        let rows: Vec<Vec<&str>> = table.lines().map(|line| line.split_whitespace().collect()).collect();
+        assert_eq!(rows[0], ["Num", "Enb", "Spec", "Status", "Address", "Group", "What"]);
+        assert_eq!(rows[2], ["1", "y", "main.c:12", "resolved", "0x3020", "in", "main+0x20", "at", "main.c:12"]);
+        assert_eq!(rows[4], ["3", "y", "*0x2004", "pending", "<pending>", "in", "helper+0x4"]);
+    }
+
+    /// # brief
+    /// A debugger of a program assembled from `code`, with no process yet. Its debug data has
+    /// one function, `_start`, with a line per 0x10 bytes of `lines` lines.
+    fn assembled(name: &str, code: &str, lines: usize) -> Debugger {
+        let program = fixture::assemble(name, code);
+        let numbers: Vec<usize> = (1..=lines).collect();
+        let debug_data = DwarfData::from_files(vec![source_file("main.s", "_start", fixture::TEXT, &numbers)]);
+        test_debugger(&program, debug_data)
+    }
+
+    /// # brief
+    /// Read memory of the program as it is, traps included.
+    fn raw_bytes(debugger: &Debugger, addr: usize, len: usize) -> Vec<u8> {
+        debugger.inferior.as_ref().unwrap().read_bytes(addr, len).unwrap()
+    }
+
+    #[test]
+    fn user_writes_under_a_trap_go_to_the_saved_byte() {
+        let nops = "    nop\n".repeat(8);
+        let mut debugger = assembled("user_writes", &format!("{}{}", nops, fixture::EXIT_7), 1);
+        let trap = fixture::TEXT + 2;
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", trap)));
+        assert_eq!(debugger.registers().unwrap().rip as usize, trap);
+
+        // xchg %eax, %ecx, a one-byte instruction too
+        assert_eq!(debugger.write_memory(fixture::TEXT + 1, &[0x91, 0x91, 0x91]), Ok(1));
+        assert_eq!(raw_bytes(&debugger, fixture::TEXT, 4), [0x90, 0x91, 0xcc, 0x91]);
+        assert_eq!(debugger.read_memory(fixture::TEXT, 4).unwrap(), [0x90, 0x91, 0x91, 0x91]);
+        assert_eq!(debugger.breakpoints[&trap], 0x91);
+        // the byte written is the one put back
+        assert!(debugger.execute_line("delete 0"));
+        assert_eq!(raw_bytes(&debugger, fixture::TEXT, 4), [0x90, 0x91, 0x91, 0x91]);
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+    }
+
+    #[test]
+    fn a_panic_ends_the_session_as_quit_does() {
+        let mut debugger = assembled("panic_shutdown", &format!("    nop\n    nop\n{}", fixture::EXIT_7), 1);
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", fixture::TEXT + 1)));
+        debugger.readline.add_history_entry("run");
+        let pid = debugger.inferior.as_ref().unwrap().pid();
+        let history_path = debugger.history_path.clone();
+
+        let unwound = panic::catch_unwind(panic::AssertUnwindSafe(move || {
+            let _session = debugger;
+            panic!("a bug in deet");
+        }));
+        assert!(unwound.is_err());
+        // killed and reaped, the history saved
+        assert_eq!(nix::sys::signal::kill(pid, None), Err(nix::Error::Sys(nix::errno::Errno::ESRCH)));
+        let history = std::fs::read_to_string(&history_path).unwrap();
+        assert!(history.lines().any(|line| line == "run"), "{:?}", history);
+        std::fs::remove_file(&history_path).unwrap();
+    }
+
+    #[test]
+    fn list_locations_are_lines_of_the_last_listing() {
+        let mut debugger = debugger(vec![source_file("main.c", "main", 0x1000, &[40, 41, 42, 43])]);
+        assert_eq!(debugger.listed_line("list:1"), Some(Err("No listing yet: list:N needs a list first.".to_string())));
+        debugger.list_position = Some(("main.c".to_string(), 36, 45));
+        assert_eq!(debugger.listed_line("list:1"), Some(Ok(("main.c".to_string(), 36))));
+        assert_eq!(debugger.listed_line("list:7"), Some(Ok(("main.c".to_string(), 42))));
+        assert_eq!(debugger.listed_line("list:11"), Some(Err("The last listing has 10 lines.".to_string())));
+        assert_eq!(debugger.listed_line("list:0"), Some(Err("The last listing has 10 lines.".to_string())));
+        assert_eq!(debugger.listed_line("main"), None);
+        assert_eq!(debugger.resolve_location("list:7").map(|(addr, _)| addr), Some(0x1020));
+    }
+
+    #[test]
+    fn listing_marks_breakpoints_and_the_current_line() {
+        let path = std::env::temp_dir().join(format!("deet-listing-{}.c", std::process::id()));
+        let text: String = (1..=12).map(|number| format!("line {}\n", number)).collect();
+        std::fs::write(&path, text).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let mut debugger = debugger(vec![source_file(&path, "main", 0x1000, &[3, 4, 5, 6])]);
+        assert!(debugger.execute_line(&format!("break {}:4; break {}:6", path, path)));
+        debugger.current_line = debugger.debug_data.get_addr_for_line(Some(&path), 5);
+
+        let listing = debugger.listing("", &format!("{}:5", path)).unwrap();
+        assert_eq!(listing.len(), LIST_LINES);
+        assert_eq!(&listing[2..7], ["  3     line 3", "B 4     line 4", " >5     line 5", "B 6     line 6", "  7     line 7"]);
+        assert_eq!(debugger.list_position, Some((path.clone(), 1, 10)));
+        // then the next lines, to the end of the file
+        assert_eq!(debugger.listing("", "").unwrap(), ["  11    line 11", "  12    line 12"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn listing_without_source_falls_back_to_the_instructions() {
+        let nops = "    nop\n".repeat(4);
+        let mut debugger = assembled("listing_fallback", &format!("{}{}", nops, fixture::EXIT_7), 2);
+        let trap = fixture::TEXT + 2;
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", trap)));
+
+        // the debug data has no line at an address, only addresses of lines
+        let listing = debugger.listing("", "").unwrap();
+        assert_eq!(listing[0], "No line number information at the current location; instructions around 0x401002:");
+        let at_pc: Vec<&String> = listing.iter().filter(|line| line.starts_with("=>")).collect();
+        assert_eq!(at_pc.len(), 1);
+        // the original byte, not the trap
+        assert!(at_pc[0].starts_with("=> 0x401002") && at_pc[0].contains("\t90 "), "{:?}", at_pc[0]);
+        // main.s is not on disk
+        let listing = debugger.listing("", "main.s:2").unwrap();
+        assert_eq!(listing[0], "Source file main.s not found; instructions around 0x401010:");
+        assert_eq!(debugger.listing("s", "main.s:2").unwrap_err(), "Source file main.s not found.");
+        let forced = debugger.listing("a", "").unwrap();
+        assert!(forced[0].starts_with("   0x401000"), "{:?}", forced[0]);
+        assert_eq!(debugger.listing("x", "").unwrap_err(), "Invalid list format \"/x\": use /s for the source or /a for the instructions.");
+        assert!(debugger.execute_line("kill"));
+    }
+
+    #[test]
+    fn disassemble_shows_the_function_with_the_original_bytes() {
+        // 32 bytes, the text of the function of the two lines
+        let code = format!("{}    mov $0x2a, %eax\n{}{}", "    nop\n".repeat(3), fixture::EXIT_7, "    nop\n".repeat(12));
+        let mut debugger = assembled("disassemble", &code, 2);
+        let trap = fixture::TEXT + 3;
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", trap)));
+        assert_eq!(raw_bytes(&debugger, trap, 1), [0xcc]);
+
+        let lines = debugger.disassembly("").unwrap();
+        assert_eq!(lines.len(), 21, "{:#?}", lines);
+        assert_eq!(lines[0], "Dump of assembler code for function _start:");
+        assert_eq!(lines[20], "End of assembler dump.");
+        let at_pc: Vec<&String> = lines.iter().filter(|line| line.starts_with("=>")).collect();
+        assert_eq!(at_pc, [&lines[4]]);
+        assert!(lines[4].starts_with("=> 0x401003") && lines[4].ends_with("\tb8 2a 00 00 00      \tmov    $0x2a,%eax"), "{:?}", lines[4]);
+        assert!(lines[5].ends_with("\tmov    $0x3c,%eax"), "{:?}", lines[5]);
+        assert!(lines[7].starts_with("   0x401012") && lines[7].ends_with("\tsyscall"), "{:?}", lines[7]);
+        // any address in the function shows it all
+        assert_eq!(debugger.disassembly(&format!("{:#x}", fixture::TEXT + 0x1f)).unwrap(), lines);
+        assert!(debugger.execute_line("kill"));
+    }
+
+    #[test]
+    fn run_while_stopped_at_a_breakpoint_installs_it_again() {
+        let nops = "    nop\n".repeat(4);
+        let mut debugger = assembled("run_again", &format!("{}{}", nops, fixture::EXIT_7), 1);
+        let trap = fixture::TEXT + 2;
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", trap)));
+        let first = debugger.inferior.as_ref().unwrap().pid();
+
+        assert!(debugger.execute_line("run"));
+        assert_ne!(debugger.inferior.as_ref().unwrap().pid(), first);
+        assert_eq!(debugger.registers().unwrap().rip as usize, trap);
+        // the original byte read from the new process, not the trap of the old one
+        assert_eq!(debugger.breakpoints[&trap], 0x90);
+        assert_eq!(raw_bytes(&debugger, trap, 1), [0xcc]);
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+        let outcomes: Vec<&Outcome> = debugger.runs.iter().map(|run| &run.outcome).collect();
+        assert!(matches!(outcomes.as_slice(), [Outcome::Killed, Outcome::Exited(7)]), "{:?}", outcomes);
+    }
+
+    #[test]
+    fn thread_selects_what_the_inspection_commands_read() {
+        let mut debugger = assembled("thread_select", fixture::THREADS, 1);
+        let trap = fixture::TEXT + 0x20;
+        assert!(debugger.execute_line(&format!("break *{:#x}; run", trap)));
+        let stopped = debugger.stopped_thread.unwrap();
+        assert!(stopped == 2 || stopped == 3, "{}", stopped);
+        assert_eq!(debugger.evaluate("$rip"), Ok(trap as u64));
+
+        // the main thread waits in its loop, past the code of the threads
+        assert!(debugger.execute_line("thread 1; info threads"));
+        assert!(debugger.evaluate("$rip").unwrap() > trap as u64);
+        assert!(debugger.execute_line("thread 9"));
+        assert_eq!(debugger.inferior.as_ref().unwrap().current_thread().unwrap().number, 1);
+
+        // the selection comes back after thread apply, which only inspects
+        assert!(debugger.execute_line(&format!("thread {}; thread apply all print $rip; thread apply all continue", stopped)));
+        assert_eq!(debugger.inferior.as_ref().unwrap().current_thread().unwrap().number, stopped);
+        assert_eq!(debugger.evaluate("$rip"), Ok(trap as u64));
+
+        // the other thread stops at the breakpoint next, with no selection left over
+        assert!(debugger.execute_line("thread 1; continue"));
+        assert_eq!(debugger.stopped_thread, Some(5 - stopped));
+        assert_eq!(debugger.evaluate("$rip"), Ok(trap as u64));
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+        let outcomes: Vec<&Outcome> = debugger.runs.iter().map(|run| &run.outcome).collect();
+        assert!(matches!(outcomes.as_slice(), [Outcome::Exited(7)]), "{:?}", outcomes);
+    }
+
+    #[test]
+    fn a_thread_breakpoint_only_stops_its_thread() {
+        let mut debugger = assembled("thread_breakpoint", fixture::THREADS, 1);
+        let trap = fixture::TEXT + 0x20;
+        assert!(debugger.execute_line(&format!("break *{:#x} thread 3; run", trap)));
+        assert_eq!(debugger.stopped_thread, Some(3));
+        assert_eq!(debugger.evaluate("$rip"), Ok(trap as u64));
+        assert_eq!(debugger.breakpoint_list[0].command_args(), format!("*{:#x} thread 3", trap));
+
+        // the threads of the program are known now
+        assert!(debugger.execute_line(&format!("break *{:#x} thread 9", fixture::TEXT + 0x21)));
+        assert_eq!(debugger.breakpoint_list.len(), 1);
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+        let outcomes: Vec<&Outcome> = debugger.runs.iter().map(|run| &run.outcome).collect();
+        assert!(matches!(outcomes.as_slice(), [Outcome::Exited(7)]), "{:?}", outcomes);
+    }
+
+    #[test]
+    fn read_only_sessions_leave_the_attached_process_as_it_was() {
+        // sleep for half a second, then say ok and exit
+        let code = format!(
+            "    lea delay(%rip), %rdi\n    xor %esi, %esi\n    mov $35, %eax\n    syscall\n    \
+             mov $1, %eax\n    mov $1, %edi\n    lea message(%rip), %rsi\n    mov $3, %edx\n    syscall\n\
+             {}delay:\n    .quad 0, 500000000\nmessage:\n    .ascii \"ok\\n\"\n",
+            fixture::EXIT_7
+        );
+        let mut debugger = assembled("read_only", &code, 2);
+        debugger.read_only = true;
+        let child = std::process::Command::new(&debugger.target).stdout(std::process::Stdio::piped()).spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        debugger.inferior = Some(Inferior::attach(nix::unistd::Pid::from_raw(child.id() as i32), &debugger.target).unwrap());
+        let text = raw_bytes(&debugger, fixture::TEXT, 0x40);
+
+        assert!(debugger.execute_line(&format!("break *{:#x}; set {{char}}{:#x} = 0x90", fixture::TEXT + 4, fixture::TEXT)));
+        assert!(debugger.breakpoints.is_empty());
+        assert_eq!(debugger.evaluate("$rip = 0"), Err("Left operand of assignment is not a modifiable lvalue: $rip".to_string()));
+        assert_eq!(raw_bytes(&debugger, fixture::TEXT, 0x40), text);
+
+        debugger.end_inferior();
+        let output = child.wait_with_output().unwrap();
+        assert_eq!((output.status.code(), output.stdout.as_slice()), (Some(7), b"ok\n".as_slice()));
+    }
+
+    #[test]
+    fn a_crash_is_reported_to_the_file_of_crash_report() {
+        // _start calls fault, which writes to address 0x10
+        let code = "    call fault\n    .org 0x10\nfault:\n    push %rbp\n    mov %rsp, %rbp\n    movl $1, 0x10\n";
+        let program = fixture::assemble("crash_report", code);
+        // the names come from the ELF symbols, the lines of the files are not looked up by address
+        let symbol = |name: &str, address: usize| Symbol { name: name.to_string(), address, size: 0x10, is_function: true };
+        let debug_data = DwarfData::from_files(Vec::new())
+            .with_symbols(vec![symbol("_start", fixture::TEXT), symbol("fault", fixture::TEXT + 0x10)]);
+        let mut debugger = test_debugger(&program, debug_data);
+        let dir = std::env::temp_dir().join(format!("deet-crash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pattern = dir.join("%p.txt").to_string_lossy().into_owned();
+        assert!(debugger.execute_line(&format!("set crash-report {}; run", pattern)));
+
+        let pid = debugger.inferior.as_ref().unwrap().pid();
+        let report = std::fs::read_to_string(dir.join(format!("{}.txt", pid))).unwrap();
+        assert!(report.contains("signal: SIGSEGV\nfault address: 0x10\n"), "{}", report);
+        assert!(report.contains("\nbacktrace:\n  fault (source file not found)\n  _start (source file not found)\n"), "{}", report);
+        assert!(report.contains("\nlocals of frame #1, _start at 0x401005:\n"), "{}", report);
+    }
+
+    #[test]
+    fn fd_changes_are_by_number_with_reused_numbers_closed_then_opened() {
+        let fd = |fd: i32, target: &str| FdInfo { fd, target: target.to_string(), flags: None, pos: None };
+        let before = [fd(0, "/dev/pts/0"), fd(3, "/tmp/a"), fd(4, "pipe:[812]")];
+        let now = [fd(0, "/dev/pts/0"), fd(3, "/tmp/b"), fd(5, "socket:[4711]")];
+        let changes: Vec<(&str, i32, &str)> =
+            fd_changes(&before, &now).into_iter().map(|(mark, fd)| (mark, fd.fd, fd.target.as_str())).collect();
+        assert_eq!(changes, [("-", 3, "/tmp/a"), ("+", 3, "/tmp/b"), ("-", 4, "pipe:[812]"), ("+", 5, "socket:[4711]")]);
+        assert!(fd_changes(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_same_line_are_counted() {
+        let line = |number: usize, address: usize| Line {
+            file: "main.c".to_string(),
+            number,
+            address,
+            is_stmt: true,
+            column: 0,
+            end_sequence: false,
+        };
+        let count = |stop: &Option<(String, Line, usize)>| stop.as_ref().map(|(_, _, repeats)| *repeats);
+        let first = count_stop(None, Some(&line(12, 0x1000)), Some("loop"));
+        assert_eq!(count(&first), Some(1));
+        // the address within the line does not matter
+        let second = count_stop(first, Some(&line(12, 0x1008)), Some("loop"));
+        assert_eq!(count(&second), Some(2));
+        let third = count_stop(second.clone(), Some(&line(12, 0x1000)), Some("loop"));
+        assert_eq!(count(&third), Some(3));
+
+        assert_eq!(count(&count_stop(second.clone(), Some(&line(13, 0x1010)), Some("loop"))), Some(1));
+        assert_eq!(count(&count_stop(second.clone(), Some(&line(12, 0x2000)), Some("other"))), Some(1));
+        let column = Line { column: 5, ..line(12, 0x1000) };
+        assert_eq!(count(&count_stop(second.clone(), Some(&column), Some("loop"))), Some(1));
+        // a stop without a line ends the series
+        assert!(count_stop(second.clone(), None, Some("loop")).is_none());
+        assert!(count_stop(second, Some(&line(12, 0x1000)), None).is_none());
+    }
+
+    #[test]
+    fn a_saved_session_is_restored_as_it_was() {
+        let files = || vec![source_file("main.c", "main", 0x1000, &[10, 11, 12]), source_file("parse.c", "parse", 0x2000, &[20])];
+        let mut saved = debugger(files());
+        assert!(saved.execute_line("set args -v 'input file.txt'; set environment LANG=C; unset environment HOME"));
+        assert!(saved.execute_line("set print pretty off; set stop-hook 'bt; print $rax'; set substitute-path /build /src"));
+        assert!(saved.execute_line("skip function drop; set $count = 3; display $count + 1; break main.c:12; break parse"));
+        let session = saved.session();
+        assert_eq!(session.args, ["-v", "input file.txt"]);
+        assert_eq!(session.environment, [("LANG".to_string(), Some("C".to_string())), ("HOME".to_string(), None)]);
+        assert_eq!(session.displays, ["$count + 1"]);
+        assert_eq!(session.breakpoints[0], SavedBreakpoint { command: "break main.c:12".to_string(), addr: Some(0x1020) });
+
+        let path = std::env::temp_dir().join(format!("deet-session-{}", std::process::id()));
+        let path = path.to_string_lossy();
+        saved.save_session(&path);
+        let mut restored = debugger(files());
+        restored.restore_session(&path);
+        std::fs::remove_file(path.as_ref()).unwrap();
+        assert_eq!(restored.session(), session);
+    }
+
+    #[test]
+    fn environment_changes_apply_in_order() {
+        let deet = vec![("HOME".to_string(), "/root".to_string()), ("TERM".to_string(), "xterm".to_string())];
+        let changes = [
+            ("TERM".to_string(), None),
+            ("LANG".to_string(), Some("C".to_string())),
+            ("HOME".to_string(), Some("/tmp".to_string())),
+        ];
+        assert_eq!(
+            program_environment(deet.clone(), &changes),
+            [("HOME".to_string(), "/tmp".to_string()), ("LANG".to_string(), "C".to_string())]
+        );
+        assert_eq!(program_environment(deet.clone(), &[]), deet);
+
+        let mut debugger = debugger(vec![]);
+        assert!(debugger.execute_line("set environment TERM dumb; unset environment TERM; set environment LANG = C.UTF-8"));
+        assert_eq!(debugger.environment, [("TERM".to_string(), None), ("LANG".to_string(), Some("C.UTF-8".to_string()))]);
+    }
+
+    #[test]
+    fn displays_are_numbered_in_order() {
+        let mut debugger = debugger(vec![]);
+        assert!(debugger.execute_line("display $a; display $b; display $c; display $d"));
+        assert!(debugger.execute_line("undisplay 3 1"));
+        assert_eq!(debugger.displays, ["$b", "$d"]);
+        assert!(debugger.execute_line("undisplay 3"));
+        assert_eq!(debugger.displays, ["$b", "$d"]);
+        assert!(debugger.execute_line("undisplay"));
+        assert!(debugger.displays.is_empty());
+    }
+
+    /// # brief
+    /// Whether each breakpoint of `debugger` is enabled, with its group.
+    fn groups(debugger: &Debugger) -> Vec<(usize, bool, Option<&str>)> {
+        debugger.breakpoint_list.iter().map(|bp| (bp.number, bp.enabled, bp.group.as_deref())).collect()
+    }
+
+    #[test]
+    fn groups_are_enabled_disabled_and_deleted_together() {
+        let mut debugger = debugger(vec![
+            source_file("main.c", "main", 0x1000, &[10, 11, 12, 13]),
+            source_file("parse.c", "parse", 0x2000, &[20, 21]),
+        ]);
+        assert!(debugger.execute_line("break main group=parsing; break parse group=parsing; break main.c:12"));
+        assert_eq!(groups(&debugger), [(0, true, Some("parsing")), (1, true, Some("parsing")), (2, true, None)]);
+
+        assert!(debugger.execute_line("disable group parsing"));
+        assert_eq!(groups(&debugger), [(0, false, Some("parsing")), (1, false, Some("parsing")), (2, true, None)]);
+        assert!(debugger.execute_line("enable group parsing"));
+        assert_eq!(groups(&debugger), [(0, true, Some("parsing")), (1, true, Some("parsing")), (2, true, None)]);
+
+        // moving to another group leaves the first one
+        assert!(debugger.execute_line("group add io 1 2; group remove 0"));
+        assert_eq!(groups(&debugger), [(0, true, None), (1, true, Some("io")), (2, true, Some("io"))]);
+        assert!(debugger.execute_line("disable group parsing; delete group io"));
+        assert_eq!(groups(&debugger), [(0, true, None)]);
+    }
+
+    #[test]
+    fn a_disabled_group_has_no_traps() {
+        let nops = "    nop\n".repeat(4);
+        let mut debugger = assembled("disabled_group", &format!("{}{}", nops, fixture::EXIT_7), 1);
+        let (grouped, other) = (fixture::TEXT + 1, fixture::TEXT + 2);
+        assert!(debugger.execute_line(&format!("break *{:#x} group=g; break *{:#x}; disable group g; run", grouped, other)));
+        assert_eq!(debugger.registers().unwrap().rip as usize, other);
+        assert_eq!(raw_bytes(&debugger, grouped, 2), [0x90, 0xcc]);
+
+        assert!(debugger.execute_line("enable group g"));
+        assert_eq!(raw_bytes(&debugger, grouped, 2), [0xcc, 0xcc]);
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+    }
+
+    #[test]
+    fn start_stops_at_the_best_known_start_of_main() {
+        let program = fixture::assemble("start_address", &format!("    nop\n{}", fixture::EXIT_7));
+        let main = || source_file("main.c", "main", 0x1000, &[10, 11, 12]);
+        let symbol = || Symbol { name: "main".to_string(), address: 0x1100, size: 0x10, is_function: true };
+        let start = |debug_data: DwarfData| test_debugger(&program, debug_data).start_address().unwrap();
+
+        let with_lines = DwarfData::from_files(vec![main()]).with_rows_cached().with_symbols(vec![symbol()]);
+        assert_eq!(start(with_lines), (0x1010, "main, past its prologue".to_string()));
+        let without_lines = DwarfData::from_files(vec![main()]).with_symbols(vec![symbol()]);
+        assert_eq!(start(without_lines), (0x1100, "the main symbol (main has no line information)".to_string()));
+        let debug_only = DwarfData::from_files(vec![main()]);
+        assert_eq!(start(debug_only), (0x1000, "the main symbol (main has no line information)".to_string()));
+        let symbol_only = DwarfData::from_files(Vec::new()).with_symbols(vec![symbol()]);
+        assert_eq!(start(symbol_only), (0x1100, "the main symbol (main is not in the debug information)".to_string()));
+        assert_eq!(
+            start(DwarfData::from_files(Vec::new())),
+            (
+                fixture::TEXT,
+                "the entry point, from the ELF header (main is not in the debug information, and there is no main symbol)"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn a_panic_in_a_command_ends_the_command_only() {
+        let nops = "    nop\n".repeat(4);
+        let mut debugger = assembled("maint_panic", &format!("{}{}", nops, fixture::EXIT_7), 1);
+        let (first, second) = (fixture::TEXT + 1, fixture::TEXT + 2);
+        assert!(debugger.execute_line(&format!("break *{:#x}; break *{:#x}; run", first, second)));
+        let pid = debugger.inferior.as_ref().unwrap().pid();
+
+        // the rest of the line is not run either
+        assert!(debugger.execute_line("maint panic patch; delete 1"));
+        assert!(!inferior::take_interrupted_patch());
+        assert_eq!(debugger.inferior.as_ref().unwrap().pid(), pid);
+        assert_eq!(debugger.breakpoint_list.len(), 2);
+        assert!(debugger.execute_line("maint panic"));
+        // a trap the command left out is put back, a stray one removed
+        debugger.remove_trap(second);
+        debugger.install_trap(fixture::TEXT + 3);
+        assert!(debugger.execute_line("maint panic"));
+        assert_eq!(raw_bytes(&debugger, first, 3), [0xcc, 0xcc, 0x90]);
+
+        assert!(debugger.execute_line("continue"));
+        assert_eq!(debugger.registers().unwrap().rip as usize, second);
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+    }
+
+    #[test]
+    fn a_pending_breakpoint_stops_in_the_library_dlopen_loads() {
+        let plugin = fixture::build("dlopen_plugin.so", &[("plugin.c", "int plugin_answer(void) { return 42; }\n")], &["-shared", "-fPIC", "plugin.c"]);
+        let main = "#include <dlfcn.h>
+int main(int argc, char **argv) {
+    void *plugin = dlopen(argv[1], RTLD_NOW);
+    if (!plugin)
+        return 1;
+    int (*answer)(void) = (int (*)(void)) dlsym(plugin, \"plugin_answer\");
+    return answer();
+}
+";
+        let program = fixture::build("dlopen", &[("main.c", main)], &["main.c", "-ldl"]);
+        let mut debugger = test_debugger(&program, DwarfData::from_files(Vec::new()));
+        debugger.batch = true;
+        debugger.args = vec![plugin.clone()];
+        assert!(debugger.execute_line("break plugin_answer"));
+        assert_eq!(debugger.breakpoint_list[0].resolution, Resolution::Pending);
+
+        assert!(debugger.execute_line("run"));
+        let hook = debugger.inferior.as_ref().unwrap().library_hook();
+        assert!(hook.is_some());
+        let library = debugger.libraries.iter().find(|library| library.path == plugin).expect("the plugin is loaded").clone();
+        let (addr, path) = debugger.library_function("plugin_answer").unwrap();
+        assert_eq!(path, plugin);
+        assert!(library.base <= addr && addr < library.end);
+        assert_eq!(debugger.breakpoint_list[0].resolution, Resolution::Resolved(addr));
+        assert_eq!(debugger.registers().unwrap().rip as usize, addr);
+        // the hook is no byte of the program to the user
+        assert_eq!(raw_bytes(&debugger, hook.unwrap(), 1), [0xcc]);
+        assert_ne!(debugger.read_memory(hook.unwrap(), 1).unwrap(), [0xcc]);
+        assert!(!debugger.library_symbols[&plugin].is_empty());
+
+        assert!(debugger.execute_line("continue"));
+        assert!(debugger.inferior.is_none());
+        let outcomes: Vec<&Outcome> = debugger.runs.iter().map(|run| &run.outcome).collect();
+        assert!(matches!(outcomes.as_slice(), [Outcome::Exited(42)]), "{:?}", outcomes);
+        // the hook went with the process
+        assert!(!debugger.breakpoints.contains_key(&hook.unwrap()));
+    }
+
+    #[test]
+    fn a_core_is_inspected_as_the_program_stopped_in_its_crash() {
+        let flags = ["-g", "-O0", "-no-pie", "-fno-omit-frame-pointer", "crash.c"];
+        let program = fixture::build("crash_inspected", &[("crash.c", fixture::CRASH)], &flags);
+        let path = match fixture::dump_core(&program) {
+            Some(path) => path,
+            // the kernel pipes the cores to a program, nothing to inspect here
+            None => return,
+        };
+        let core = Core::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let rip = core.registers().unwrap().rip as usize;
+        let mut debugger = test_debugger(&program, DwarfData::from_file(&program).unwrap());
+        debugger.inspect_core(core);
+        let frames = debugger.stack(false).unwrap().frames;
+        assert_eq!((frames[0].function.as_deref(), frames[0].rip), (Some("main"), rip));
+        assert_eq!(debugger.evaluate("$rip").unwrap(), rip as u64);
+        assert!(debugger.execute_line("bt"));
+
+        // running the program puts the core aside
+        assert!(debugger.execute_line("run"));
+        assert!(debugger.core.is_none());
+        assert!(debugger.inferior.is_some());
+    }
+
+    #[test]
+    fn continue_from_an_outer_frame_shows_frame_zero_of_the_next_stop() {
+        // _start calls outer, which calls inner, which stops twice
+        let code = "    call outer\n".to_string() + fixture::EXIT_7 + "    .type outer, @function
+    .org 0x18, 0x90
+outer:
+    push %rbp
+    mov %rsp, %rbp
+    call inner
+    pop %rbp
+    ret
+    .type inner, @function
+    .org 0x28, 0x90
+inner:
+    push %rbp
+    mov %rsp, %rbp
+    nop
+    nop
+    pop %rbp
+    ret
+";
+        let program = fixture::assemble("frame_continue", &code);
+        let mut debugger = test_debugger(&program, DwarfData::from_file(&program).unwrap());
+        let (first, second) = (fixture::TEXT + 0x2c, fixture::TEXT + 0x2d);
+        assert!(debugger.execute_line(&format!("break *{:#x}; break *{:#x}; run", first, second)));
+        assert_eq!(debugger.registers().unwrap().rip as usize, first);
+        // the call of outer returns right after it
+        assert!(debugger.execute_line("frame 2; print $rip"));
+        assert_eq!(debugger.value_history.last().unwrap().value as usize, fixture::TEXT + 5);
+
+        assert!(debugger.execute_line("frame 2; continue; print $rip"));
+        assert_eq!(debugger.context.frame(), 0);
+        assert_eq!(debugger.value_history.last().unwrap().value as usize, second);
+    }
 }
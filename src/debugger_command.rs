@@ -1,20 +1,311 @@
+use std::time::Duration;
+
 pub enum DebuggerCommand {
-    Quit,
-    Step,
+    /// `quit [exit code]`
+    Quit(Vec<String>),
+    /// `step [--into [function]]`
+    Step(Vec<String>),
+    StepInstruction,
+    Finish,
     Run(Vec<String>),
-    Continue,
-    Backtrace,
+    /// `start [args...]`: run to `main`
+    Start(Vec<String>),
+    /// `starti [args...]`: run to the first instruction of the program
+    StartInstruction(Vec<String>),
+    /// `continue [--timeout <duration>] [signal]`, `continue until <expr>`
+    Continue(Vec<String>),
+    /// `bt -force` follows frame pointers outside the stack mapping
+    Backtrace(bool),
+    /// `frame [N]`
+    Frame(Vec<String>),
+    /// `up [N]`
+    Up(Vec<String>),
+    /// `down [N]`
+    Down(Vec<String>),
     Breakpoint(String),
+    /// `dprintf <location>, "format", args... [if <cond>]`, split by `tokenize`
+    Dprintf(Vec<String>),
+    /// `delete [N...|group <name>]`
+    Delete(Vec<String>),
+    /// `enable [N...|N.M...|group <name>]`
+    Enable(Vec<String>),
+    /// `disable [N...|N.M...|group <name>]`
+    Disable(Vec<String>),
+    /// `group add <name> <N...>`, `group remove <N...>`
+    Group(Vec<String>),
+    /// `condition N [expr]`
+    Condition(String),
+    /// `list[/s|/a] [location|+|-]`
+    List(String, String),
+    Set(Vec<String>),
+    /// `unset environment <name>`
+    Unset(Vec<String>),
+    Show(Vec<String>),
+    Info(Vec<String>),
+    Print(String, String),
+    /// `display [expr]`: print the expression at every stop
+    Display(String),
+    /// `undisplay [N...]`
+    Undisplay(Vec<String>),
+    Examine(String, String),
+    /// `disassemble [expr]`
+    Disassemble(String),
+    Snapshot(Vec<String>),
+    /// `fd baseline`, `fd diff`
+    Fd(Vec<String>),
+    /// `maint panic [patch]`, to check the recovery from a bug in a command
+    Maintenance(Vec<String>),
+    Compare(Vec<String>),
+    Assert(String),
+    AddInferior(Vec<String>),
+    Inferior(Vec<String>),
+    Tui(Vec<String>),
+    File(Vec<String>),
+    WaitAttach(Vec<String>),
+    /// `rbreak <regex>`
+    RegexBreakpoint(String),
+    /// `ptype <type name|expression>`
+    Ptype(String),
+    Skip(Vec<String>),
+    /// `target remote <host:port>`
+    Target(Vec<String>),
+    Disconnect,
+    /// `thread [<id>]`, `thread apply all|<id>... <command>`
+    Thread(Vec<String>),
+    /// `save session <file>`, `restore session <file>`
+    SaveSession(Vec<String>),
+    RestoreSession(Vec<String>),
+    /// `stats reset`
+    Stats(Vec<String>),
+    /// `time <command>`: run the command and report how long it took
+    Time(Box<DebuggerCommand>),
+}
+
+/// # brief
+/// Split a command line into words. Single or double quotes group words containing whitespace,
+/// and are removed from the result.
+///
+/// # example
+/// ```
+/// assert_eq!(tokenize("set stop-hook \"bt; print $rax\""), vec!["set", "stop-hook", "bt; print $rax"]);
+/// ```
+pub fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_token = true;
+            }
+            None if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// # brief
+/// Quote a word for `tokenize` if it contains whitespace or quotes, or is empty.
+pub fn quote(word: &str) -> String {
+    if !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || c == '"' || c == '\'') {
+        word.to_string()
+    } else if word.contains('"') {
+        format!("'{}'", word)
+    } else {
+        format!("\"{}\"", word)
+    }
+}
+
+/// # brief
+/// Split a line into `;`-separated commands. Semicolons inside quotes don't separate commands.
+/// Empty commands are dropped.
+pub fn split_commands(line: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ';' => {
+                commands.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    commands.push(current);
+    commands
+        .into_iter()
+        .map(|command| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+        .collect()
+}
+
+/// What the argument of a command is, for the prompt hints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgumentKind {
+    /// A breakpoint location: `*address`, line, `file:line` or function
+    Location,
+    /// An expression
+    Expression,
+}
+
+/// # brief
+/// Parse a duration like `5s`, `500ms` or `2m`. A plain number is in seconds.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => text.split_at(index),
+        None => (text, "s"),
+    };
+    let number: f64 = number.parse().ok()?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" | "min" => number * 60.0,
+        _ => return None,
+    };
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// # brief
+/// Split a `--timeout <duration>` option off the front of a command's arguments.
+///
+/// # return
+/// * `Ok((Option<Duration>, rest))`, or `Err(String)` if the duration is missing or invalid.
+pub fn take_timeout(args: &[String]) -> Result<(Option<Duration>, Vec<String>), String> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--timeout") => {
+            let text = args.get(1).ok_or("--timeout requires a duration, e.g. 5s")?;
+            let duration = parse_duration(text).ok_or(format!("Invalid duration \"{}\"", text))?;
+            Ok((Some(duration), args[2..].to_vec()))
+        }
+        _ => Ok((None, args.to_vec())),
+    }
+}
+
+/// # brief
+/// Get the kind of argument a command takes, by the command names `from_tokens` accepts.
+pub fn argument_kind(name: &str) -> Option<ArgumentKind> {
+    match name {
+        "b"  | "break"| "breakpoint" | "dprintf" | "l" | "list" => Some(ArgumentKind::Location),
+        "p"  | "print" | "x" | "assert" | "ptype" => Some(ArgumentKind::Expression),
+        _ => None,
+    }
+}
+
+/// # brief
+/// Whether the word after `command` and `word` is the name of a breakpoint group, as in
+/// `disable group <name>` and `group add <name>`.
+pub fn takes_group(command: &str, word: &str) -> bool {
+    match command {
+        "en" | "enable" | "dis" | "disable" | "d" | "delete" => word == "group",
+        "group" => word == "add",
+        _ => false,
+    }
 }
 
 impl DebuggerCommand {
+    /// # brief
+    /// Check whether the command only looks at the stopped program, so that the selected thread
+    /// and frame and the registers read at this stop stay valid after it. `thread` is not, it
+    /// selects another thread, and neither is `time`: the command it runs is checked when it is
+    /// executed.
+    pub fn is_inspection(&self) -> bool {
+        match self {
+            DebuggerCommand::Backtrace(_)
+            | DebuggerCommand::Frame(_)
+            | DebuggerCommand::Up(_)
+            | DebuggerCommand::Down(_)
+            | DebuggerCommand::List(..)
+            | DebuggerCommand::Show(_)
+            | DebuggerCommand::Info(_)
+            | DebuggerCommand::Print(..)
+            | DebuggerCommand::Display(_)
+            | DebuggerCommand::Examine(..)
+            | DebuggerCommand::Disassemble(_)
+            | DebuggerCommand::Ptype(_)
+            | DebuggerCommand::Assert(_)
+            | DebuggerCommand::Compare(_)
+            | DebuggerCommand::Fd(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn from_tokens(tokens: &Vec<&str>) -> Option<Self> {
-        match tokens[0] {
-            "q"  | "quit" | "exit"   => Some(DebuggerCommand::Quit),
-            "s"  | "step" | "next"   => Some(DebuggerCommand::Step),
-            "c"  | "cont" | "continue"   => Some(DebuggerCommand::Continue),
-            "bt" | "back" | "backtrace"  => Some(DebuggerCommand::Backtrace),
-            "b"  | "break"| "breakpoint" => Some(DebuggerCommand::Breakpoint(tokens[1].to_string())),
+        // `print/x` and `x/8xg` carry a format after the command name
+        let (name, format) = tokens[0].split_once('/').unwrap_or((tokens[0], ""));
+        match name {
+            "q"  | "quit" | "exit"   => Some(DebuggerCommand::Quit(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "s"  | "step" | "next"   => Some(DebuggerCommand::Step(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "si" | "stepi"           => Some(DebuggerCommand::StepInstruction),
+            "fin" | "finish"         => Some(DebuggerCommand::Finish),
+            "c"  | "cont" | "continue"   => Some(DebuggerCommand::Continue(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "bt" | "back" | "backtrace"  => Some(DebuggerCommand::Backtrace(tokens.get(1) == Some(&"-force"))),
+            "f"  | "frame" => Some(DebuggerCommand::Frame(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "up"           => Some(DebuggerCommand::Up(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "down"         => Some(DebuggerCommand::Down(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "b"  | "break"| "breakpoint" => Some(DebuggerCommand::Breakpoint(tokens[1..].join(" "))),
+            "dprintf"      => Some(DebuggerCommand::Dprintf(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "l"  | "list"  => Some(DebuggerCommand::List(format.to_string(), tokens[1..].join(" "))),
+            "d"  | "delete" => Some(DebuggerCommand::Delete(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "condition"    => Some(DebuggerCommand::Condition(tokens[1..].join(" "))),
+            "en" | "enable"  => Some(DebuggerCommand::Enable(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "dis" | "disable" => Some(DebuggerCommand::Disable(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "group"        => Some(DebuggerCommand::Group(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "set"  => Some(DebuggerCommand::Set(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "unset" => Some(DebuggerCommand::Unset(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "show" => Some(DebuggerCommand::Show(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "i"  | "info"  => Some(DebuggerCommand::Info(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "p"  | "print" => Some(DebuggerCommand::Print(format.to_string(), tokens[1..].join(" "))),
+            "display"      => Some(DebuggerCommand::Display(tokens[1..].join(" "))),
+            "undisplay"    => Some(DebuggerCommand::Undisplay(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "disas" | "disassemble" => Some(DebuggerCommand::Disassemble(tokens[1..].join(" "))),
+            "x"            => Some(DebuggerCommand::Examine(format.to_string(), tokens[1..].join(" "))),
+            "snapshot"     => Some(DebuggerCommand::Snapshot(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "fd"           => Some(DebuggerCommand::Fd(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "maint" | "maintenance" => Some(DebuggerCommand::Maintenance(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "add-inferior" => Some(DebuggerCommand::AddInferior(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "inferior"     => Some(DebuggerCommand::Inferior(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "tui"          => Some(DebuggerCommand::Tui(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "file"         => Some(DebuggerCommand::File(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "wait-attach"  => Some(DebuggerCommand::WaitAttach(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "rbreak"       => Some(DebuggerCommand::RegexBreakpoint(tokens[1..].join(" "))),
+            "ptype"        => Some(DebuggerCommand::Ptype(tokens[1..].join(" "))),
+            "skip"         => Some(DebuggerCommand::Skip(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "assert"       => Some(DebuggerCommand::Assert(tokens[1..].join(" "))),
+            "compare"      => Some(DebuggerCommand::Compare(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "target"       => Some(DebuggerCommand::Target(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "disconnect"   => Some(DebuggerCommand::Disconnect),
+            "stats"        => Some(DebuggerCommand::Stats(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "time" if tokens.len() > 1 => Some(DebuggerCommand::Time(Box::new(DebuggerCommand::from_tokens(&tokens[1..].to_vec())?))),
+            "thread"       => Some(DebuggerCommand::Thread(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "save-session" => Some(DebuggerCommand::SaveSession(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "save" if tokens.get(1) == Some(&"session") => Some(DebuggerCommand::SaveSession(tokens[2..].iter().map(|s| s.to_string()).collect())),
+            "restore-session" => Some(DebuggerCommand::RestoreSession(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "restore" if tokens.get(1) == Some(&"session") => Some(DebuggerCommand::RestoreSession(tokens[2..].iter().map(|s| s.to_string()).collect())),
+            "start"        => Some(DebuggerCommand::Start(tokens[1..].iter().map(|s| s.to_string()).collect())),
+            "starti"       => Some(DebuggerCommand::StartInstruction(tokens[1..].iter().map(|s| s.to_string()).collect())),
             "r"  | "run"   => {
                 let args = tokens[1..].to_vec();
                 Some(DebuggerCommand::Run(
@@ -26,3 +317,101 @@ impl DebuggerCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        assert_eq!(tokenize("break  main.c:12\tif x"), ["break", "main.c:12", "if", "x"]);
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("   ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_groups_quoted_words() {
+        assert_eq!(tokenize("set stop-hook \"bt; print $rax\""), ["set", "stop-hook", "bt; print $rax"]);
+        assert_eq!(tokenize("run 'a b' \"it's\""), ["run", "a b", "it's"]);
+        // quotes inside a word join it with the quoted part
+        assert_eq!(tokenize("x=\"y z\""), ["x=y z"]);
+        // an empty quoted word is a word
+        assert_eq!(tokenize("run \"\" x"), ["run", "", "x"]);
+        // an unterminated quote runs to the end of the line
+        assert_eq!(tokenize("echo \"a b"), ["echo", "a b"]);
+    }
+
+    #[test]
+    fn split_commands_at_semicolons() {
+        assert_eq!(split_commands("b main; run; bt"), ["b main", "run", "bt"]);
+        assert_eq!(split_commands(";; bt ;"), ["bt"]);
+        assert!(split_commands("").is_empty());
+    }
+
+    #[test]
+    fn split_commands_keeps_quoted_semicolons() {
+        assert_eq!(split_commands("set stop-hook \"bt; info locals\"; c"), ["set stop-hook \"bt; info locals\"", "c"]);
+        assert_eq!(split_commands("print 'a;b'"), ["print 'a;b'"]);
+        // the other quote doesn't end the quoted part
+        assert_eq!(split_commands("echo \"it's; here\""), ["echo \"it's; here\""]);
+    }
+
+    #[test]
+    fn durations() {
+        let cases = [
+            ("5", Some(Duration::from_secs(5))),
+            ("5s", Some(Duration::from_secs(5))),
+            ("500ms", Some(Duration::from_millis(500))),
+            ("1.5s", Some(Duration::from_millis(1500))),
+            ("2m", Some(Duration::from_secs(120))),
+            ("2min", Some(Duration::from_secs(120))),
+            ("0", Some(Duration::from_secs(0))),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(parse_duration(text), *expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for text in ["", "s", "5h", "5 s", "-1s", "1.2.3s", "ms5"].iter() {
+            assert_eq!(parse_duration(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn timeout_option_comes_first() {
+        let args = |words: &[&str]| words.iter().map(|word| word.to_string()).collect::<Vec<String>>();
+        assert_eq!(take_timeout(&args(&["--timeout", "2s", "SIGINT"])), Ok((Some(Duration::from_secs(2)), args(&["SIGINT"]))));
+        assert_eq!(take_timeout(&args(&["SIGINT"])), Ok((None, args(&["SIGINT"]))));
+        assert_eq!(take_timeout(&args(&[])), Ok((None, args(&[]))));
+        // after another argument it is an argument of the program
+        assert_eq!(take_timeout(&args(&["-v", "--timeout", "2s"])), Ok((None, args(&["-v", "--timeout", "2s"]))));
+        assert_eq!(take_timeout(&args(&["--timeout"])), Err("--timeout requires a duration, e.g. 5s".to_string()));
+        assert_eq!(take_timeout(&args(&["--timeout", "soon"])), Err("Invalid duration \"soon\"".to_string()));
+    }
+
+    #[test]
+    fn quote_only_what_needs_it() {
+        assert_eq!(quote("input.txt"), "input.txt");
+        assert_eq!(quote("a b"), "\"a b\"");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("it's"), "\"it's\"");
+        assert_eq!(quote("say \"hi\""), "'say \"hi\"'");
+    }
+
+    #[test]
+    fn quoted_words_tokenize_back() {
+        let words = ["plain", "two words", "", "it's", "\"quoted\"", "tab\there"];
+        let line: Vec<String> = words.iter().map(|word| quote(word)).collect();
+        assert_eq!(tokenize(&line.join(" ")), words);
+    }
+
+    #[test]
+    fn backtrace_may_be_forced() {
+        let parse = |line: &str| DebuggerCommand::from_tokens(&line.split_whitespace().collect());
+        assert!(matches!(parse("bt"), Some(DebuggerCommand::Backtrace(false))));
+        assert!(matches!(parse("backtrace -force"), Some(DebuggerCommand::Backtrace(true))));
+        assert!(matches!(parse("bt force"), Some(DebuggerCommand::Backtrace(false))));
+    }
+}
@@ -0,0 +1,840 @@
+/// The longest x86-64 instruction, in bytes
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// Where an address falls in the instruction stream of a function, as found by `boundary`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary {
+    /// The address begins an instruction
+    Start,
+    /// The address is inside the instruction beginning at the given address
+    Inside(usize),
+    /// An instruction before the address could not be decoded
+    Unknown,
+}
+
+/// # brief
+/// Get the length of a ModRM byte and what follows it (SIB byte and displacement), in 64-bit
+/// mode.
+fn modrm_len(bytes: &[u8]) -> Option<usize> {
+    let modrm = *bytes.first()?;
+    let (md, rm) = (modrm >> 6, modrm & 7);
+    if md == 3 {
+        return Some(1);
+    }
+    let mut len = 1;
+    if rm == 4 {
+        let sib = *bytes.get(1)?;
+        len += 1;
+        if md == 0 && sib & 7 == 5 {
+            len += 4;
+        }
+    } else if md == 0 && rm == 5 {
+        // rip-relative
+        len += 4;
+    }
+    match md {
+        1 => len += 1,
+        2 => len += 4,
+        _ => {}
+    }
+    Some(len)
+}
+
+/// # brief
+/// Describe a one-byte opcode.
+///
+/// # return
+/// * `Some((has_modrm, immediate_len))`, or `None` for an opcode invalid in 64-bit mode.
+fn one_byte(opcode: u8, modrm: Option<u8>, imm_z: usize, rex_w: bool, addr32: bool) -> Option<(bool, usize)> {
+    let reg = modrm.map(|modrm| (modrm >> 3) & 7);
+    Some(match opcode {
+        0x00..=0x3f => match opcode & 7 {
+            0..=3 => (true, 0),
+            4 => (false, 1),
+            5 => (false, imm_z),
+            // push/pop of segment registers, daa, das, aaa, aas
+            _ => return None,
+        },
+        0x50..=0x5f => (false, 0),
+        0x63 => (true, 0),
+        0x68 => (false, imm_z),
+        0x69 => (true, imm_z),
+        0x6a => (false, 1),
+        0x6b => (true, 1),
+        0x6c..=0x6f => (false, 0),
+        0x70..=0x7f => (false, 1),
+        0x80 | 0x83 => (true, 1),
+        0x81 => (true, imm_z),
+        // XOP, rather than pop r/m, when the reg field is not 0
+        0x8f if reg.map_or(false, |reg| reg != 0) => return None,
+        0x84..=0x8f => (true, 0),
+        0x90..=0x99 | 0x9b..=0x9f => (false, 0),
+        0xa0..=0xa3 => (false, if addr32 { 4 } else { 8 }),
+        0xa4..=0xa7 | 0xaa..=0xaf => (false, 0),
+        0xa8 => (false, 1),
+        0xa9 => (false, imm_z),
+        0xb0..=0xb7 => (false, 1),
+        0xb8..=0xbf => (false, if rex_w { 8 } else { imm_z }),
+        0xc0 | 0xc1 | 0xc6 => (true, 1),
+        0xc2 | 0xca => (false, 2),
+        0xc3 | 0xc9 | 0xcb | 0xcc | 0xcf => (false, 0),
+        0xc7 => (true, imm_z),
+        0xc8 => (false, 3),
+        0xcd => (false, 1),
+        0xd0..=0xd3 | 0xd8..=0xdf => (true, 0),
+        0xd7 => (false, 0),
+        0xe0..=0xe7 | 0xeb => (false, 1),
+        // the operand size prefix is ignored for near branches in 64-bit mode
+        0xe8 | 0xe9 => (false, 4),
+        0xec..=0xef | 0xf1 | 0xf4 | 0xf5 | 0xf8..=0xfd => (false, 0),
+        0xf6 => (true, if reg? <= 1 { 1 } else { 0 }),
+        0xf7 => (true, if reg? <= 1 { imm_z } else { 0 }),
+        0xfe | 0xff => (true, 0),
+        _ => return None,
+    })
+}
+
+/// # brief
+/// Describe an opcode of the `0F` map.
+///
+/// # return
+/// * `Some((has_modrm, immediate_len))`, or `None` for an invalid opcode.
+fn two_byte(opcode: u8) -> Option<(bool, usize)> {
+    Some(match opcode {
+        0x00..=0x03 | 0x0d => (true, 0),
+        0x05..=0x09 | 0x0b | 0x0e => (false, 0),
+        // 3DNow!, the opcode is in the immediate byte
+        0x0f => (true, 1),
+        0x10..=0x23 | 0x28..=0x2f => (true, 0),
+        0x30..=0x35 | 0x37 => (false, 0),
+        0x40..=0x6f | 0x74..=0x76 | 0x78 | 0x79 | 0x7c..=0x7f => (true, 0),
+        0x70..=0x73 => (true, 1),
+        0x77 => (false, 0),
+        0x80..=0x8f => (false, 4),
+        0x90..=0x9f => (true, 0),
+        0xa0..=0xa2 | 0xa8..=0xaa => (false, 0),
+        0xa3 | 0xa5 | 0xab | 0xad..=0xb9 | 0xbb..=0xc1 | 0xc3 | 0xc7 => (true, 0),
+        0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6 => (true, 1),
+        0xc8..=0xcf => (false, 0),
+        0xd0..=0xff => (true, 0),
+        _ => return None,
+    })
+}
+
+/// # brief
+/// Get the immediate length of an instruction of a VEX or EVEX encoded map, which always has a
+/// ModRM byte.
+fn vex_imm_len(map: u8, opcode: u8) -> Option<usize> {
+    match map {
+        1 => Some(match opcode {
+            0x70..=0x73 | 0xc2 | 0xc4..=0xc6 => 1,
+            _ => 0,
+        }),
+        2 | 5 | 6 => Some(0),
+        3 => Some(1),
+        _ => None,
+    }
+}
+
+/// # brief
+/// Decode the length of the x86-64 instruction at the start of `bytes`. Only the length is
+/// decoded, from the prefixes, opcode, ModRM, SIB, displacement and immediate; what the
+/// instruction does is not.
+///
+/// # return
+/// * The length in bytes, or `None` if the bytes are not a valid or known instruction.
+pub fn instruction_length(bytes: &[u8]) -> Option<usize> {
+    let bytes = &bytes[..bytes.len().min(MAX_INSTRUCTION_LEN)];
+    let mut pos = 0;
+    let mut operand16 = false;
+    let mut addr32 = false;
+    loop {
+        match *bytes.get(pos)? {
+            0x66 => operand16 = true,
+            0x67 => addr32 = true,
+            0xf0 | 0xf2 | 0xf3 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => {}
+            _ => break,
+        }
+        pos += 1;
+    }
+    // a REX prefix only counts right before the opcode
+    let mut rex_w = false;
+    while let Some(rex @ 0x40..=0x4f) = bytes.get(pos) {
+        rex_w = rex & 8 != 0;
+        pos += 1;
+    }
+    let imm_z = if operand16 && !rex_w { 2 } else { 4 };
+    let opcode = *bytes.get(pos)?;
+    pos += 1;
+    let (has_modrm, imm_len) = match opcode {
+        0x0f => {
+            let opcode = *bytes.get(pos)?;
+            pos += 1;
+            match opcode {
+                0x38 => {
+                    pos += 1;
+                    (true, 0)
+                }
+                0x3a => {
+                    pos += 1;
+                    (true, 1)
+                }
+                opcode => two_byte(opcode)?,
+            }
+        }
+        0xc4 | 0xc5 | 0x62 => {
+            // VEX with 2 or 3 payload bytes, EVEX with 3
+            let (payload, map) = match opcode {
+                0xc5 => (1, 1),
+                0xc4 => (2, *bytes.get(pos)? & 0x1f),
+                _ => (3, *bytes.get(pos)? & 0x07),
+            };
+            pos += payload;
+            let opcode = *bytes.get(pos)?;
+            pos += 1;
+            (true, vex_imm_len(map, opcode)?)
+        }
+        opcode => one_byte(opcode, bytes.get(pos).copied(), imm_z, rex_w, addr32)?,
+    };
+    if has_modrm {
+        pos += modrm_len(bytes.get(pos..)?)?;
+    }
+    pos += imm_len;
+    if pos > bytes.len() {
+        return None;
+    }
+    Some(pos)
+}
+
+/// # brief
+/// Split `code`, loaded at `base` and starting on an instruction, into its instructions, up to
+/// its end or the first one that can't be decoded.
+///
+/// # return
+/// * The address and length of each instruction.
+pub fn instructions(code: &[u8], base: usize) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(len) = code.get(pos..).and_then(instruction_length) {
+        found.push((base + pos, len));
+        pos += len;
+    }
+    found
+}
+
+/// # brief
+/// Find whether `addr` begins an instruction, decoding the instructions of `code` from its start
+/// onwards. `code` must start on an instruction, e.g. at the entry of a function, and is
+/// loaded at `base`.
+pub fn boundary(code: &[u8], base: usize, addr: usize) -> Boundary {
+    let mut pos = base;
+    while pos < addr {
+        let len = match code.get(pos - base..).and_then(instruction_length) {
+            Some(len) => len,
+            None => return Boundary::Unknown,
+        };
+        if pos + len > addr {
+            return Boundary::Inside(pos);
+        }
+        pos += len;
+    }
+    Boundary::Start
+}
+
+/// The mnemonics of the arithmetic group, `00`-`3F` and `80`-`83`, by operation
+const ARITHMETIC: [&str; 8] = ["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"];
+/// The mnemonics of the shift group, `C0`, `C1` and `D0`-`D3`, by the reg field of ModRM
+const SHIFTS: [&str; 8] = ["rol", "ror", "rcl", "rcr", "shl", "shr", "sal", "sar"];
+/// The conditions of `jcc`, `setcc` and `cmovcc`, by the low nibble of the opcode
+const CONDITIONS: [&str; 16] = ["o", "no", "b", "ae", "e", "ne", "be", "a", "s", "ns", "p", "np", "l", "ge", "le", "g"];
+
+/// # brief
+/// Name the x86-64 instruction at the start of `bytes`. Like `instruction_length`, this is not
+/// a full decoder: only the opcode (and the reg field of ModRM for the group opcodes) is looked
+/// at, the operands are left to `disassemble`, and the less common opcodes, SSE and VEX ones
+/// among them, are not known.
+///
+/// # return
+/// * The mnemonic, e.g. `mov` or `jne`, or `None` if the instruction is not known.
+pub fn mnemonic(bytes: &[u8]) -> Option<String> {
+    let mut pos = 0;
+    let mut rep = false;
+    let mut lock = false;
+    while let Some(prefix @ (0x66 | 0x67 | 0xf0 | 0xf2 | 0xf3 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65)) = bytes.get(pos) {
+        rep |= *prefix == 0xf3;
+        lock |= *prefix == 0xf0;
+        pos += 1;
+    }
+    while let Some(0x40..=0x4f) = bytes.get(pos) {
+        pos += 1;
+    }
+    let opcode = *bytes.get(pos)?;
+    let reg = bytes.get(pos + 1).map(|modrm| ((modrm >> 3) & 7) as usize);
+    let name = match opcode {
+        0x0f => return two_byte_mnemonic(bytes.get(pos + 1..)?, rep),
+        0x00..=0x3f if opcode & 7 <= 5 => ARITHMETIC[(opcode >> 3) as usize].to_string(),
+        0x50..=0x57 => "push".to_string(),
+        0x58..=0x5f => "pop".to_string(),
+        0x63 => "movsxd".to_string(),
+        0x68 | 0x6a => "push".to_string(),
+        0x69 | 0x6b => "imul".to_string(),
+        0x70..=0x7f => format!("j{}", CONDITIONS[(opcode & 0xf) as usize]),
+        0x80..=0x83 => ARITHMETIC[reg?].to_string(),
+        0x84 | 0x85 | 0xa8 | 0xa9 => "test".to_string(),
+        0x86 | 0x87 => "xchg".to_string(),
+        0x88..=0x8c | 0x8e | 0xa0..=0xa3 | 0xb0..=0xbf | 0xc6 | 0xc7 => "mov".to_string(),
+        0x8d => "lea".to_string(),
+        0x8f => "pop".to_string(),
+        0x90 if rep => "pause".to_string(),
+        0x90 => "nop".to_string(),
+        0x91..=0x97 => "xchg".to_string(),
+        0x98 => "cltq".to_string(),
+        0x99 => "cqto".to_string(),
+        0xa4 | 0xa5 => "movs".to_string(),
+        0xaa | 0xab => "stos".to_string(),
+        0xc0 | 0xc1 | 0xd0..=0xd3 => SHIFTS[reg?].to_string(),
+        0xc2 | 0xc3 if rep => "repz ret".to_string(),
+        0xc2 | 0xc3 => "ret".to_string(),
+        0xc9 => "leave".to_string(),
+        0xcc => "int3".to_string(),
+        0xcd => "int".to_string(),
+        0xe8 => "call".to_string(),
+        0xe9 | 0xeb => "jmp".to_string(),
+        0xf4 => "hlt".to_string(),
+        0xf6 | 0xf7 => ["test", "test", "not", "neg", "mul", "imul", "div", "idiv"][reg?].to_string(),
+        0xfe => ["inc", "dec"].get(reg?)?.to_string(),
+        0xff => ["inc", "dec", "call", "call", "jmp", "jmp", "push"].get(reg?)?.to_string(),
+        _ => return None,
+    };
+    // a rep prefix is part of the name of a string instruction only
+    let name = match opcode {
+        0xa4 | 0xa5 | 0xaa | 0xab if rep => format!("rep {}", name),
+        _ if lock => format!("lock {}", name),
+        _ => name,
+    };
+    Some(name)
+}
+
+/// # brief
+/// Name an instruction of the `0F` map, given the bytes after `0F`.
+fn two_byte_mnemonic(bytes: &[u8], rep: bool) -> Option<String> {
+    let opcode = *bytes.first()?;
+    let reg = bytes.get(1).map(|modrm| (modrm >> 3) & 7);
+    Some(match opcode {
+        0x05 => "syscall".to_string(),
+        0x0b => "ud2".to_string(),
+        0x1e if rep && bytes.get(1) == Some(&0xfa) => "endbr64".to_string(),
+        0x1f => "nop".to_string(),
+        0x40..=0x4f => format!("cmov{}", CONDITIONS[(opcode & 0xf) as usize]),
+        0x80..=0x8f => format!("j{}", CONDITIONS[(opcode & 0xf) as usize]),
+        0x90..=0x9f => format!("set{}", CONDITIONS[(opcode & 0xf) as usize]),
+        0xa2 => "cpuid".to_string(),
+        0xaf => "imul".to_string(),
+        0xb6 | 0xb7 => "movzx".to_string(),
+        0xbe | 0xbf => "movsx".to_string(),
+        0x18 if reg? <= 3 => "prefetch".to_string(),
+        _ => return None,
+    })
+}
+
+/// The general purpose registers by number, for operands of 8, 4, 2 and 1 bytes
+const REGISTERS_64: [&str; 16] =
+    ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+const REGISTERS_32: [&str; 16] =
+    ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+const REGISTERS_16: [&str; 16] =
+    ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w"];
+const REGISTERS_8: [&str; 16] =
+    ["al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil", "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+/// The byte registers 4 to 7 without a REX prefix
+const HIGH_BYTES: [&str; 4] = ["ah", "ch", "dh", "bh"];
+
+/// # brief
+/// Name a general purpose register in AT&T syntax, e.g. `%eax`.
+///
+/// # param
+/// - `number` : The register, 0 to 15.
+/// - `size` : The size of the operand in bytes.
+/// - `rex` : Whether the instruction has a REX prefix, which makes 4 to 7 the low bytes of
+/// `rsp`, `rbp`, `rsi` and `rdi` rather than `ah` to `bh`.
+fn register_name(number: usize, size: usize, rex: bool) -> String {
+    let name = match size {
+        8 => REGISTERS_64[number],
+        4 => REGISTERS_32[number],
+        2 => REGISTERS_16[number],
+        _ if !rex && (4..8).contains(&number) => HIGH_BYTES[number - 4],
+        _ => REGISTERS_8[number],
+    };
+    format!("%{}", name)
+}
+
+/// # brief
+/// Write a displacement as objdump does, e.g. `-0x8`.
+fn displacement(value: i64) -> String {
+    if value < 0 {
+        format!("-{:#x}", value.unsigned_abs())
+    } else {
+        format!("{:#x}", value)
+    }
+}
+
+/// # brief
+/// Read a little-endian signed value of 1, 2, 4 or 8 bytes, sign-extended.
+fn signed(bytes: &[u8]) -> i64 {
+    let mut value = 0i64;
+    for (i, byte) in bytes.iter().enumerate() {
+        value |= (*byte as i64) << (8 * i);
+    }
+    let bits = 8 * bytes.len() as u32;
+    if bits > 0 && bits < 64 {
+        value = (value << (64 - bits)) >> (64 - bits);
+    }
+    value
+}
+
+/// # brief
+/// Write an immediate operand as objdump does: the value sign-extended to the operand size, in
+/// hexadecimal, e.g. `$0xfffffff0` for -16 in a 4-byte operation.
+fn immediate(value: i64, size: usize) -> String {
+    let mask = if size >= 8 { u64::MAX } else { (1u64 << (8 * size)) - 1 };
+    format!("${:#x}", value as u64 & mask)
+}
+
+/// The ModRM byte of an instruction and the SIB byte and displacement after it, decoded
+#[derive(Debug, Clone, PartialEq)]
+struct ModRm {
+    /// The reg field, extended by REX.R
+    reg: usize,
+    /// The r/m operand: a register, extended by REX.B, or memory written in AT&T syntax
+    rm: Result<usize, String>,
+}
+
+impl ModRm {
+    /// # brief
+    /// Write the r/m operand, a register of `size` bytes or the memory.
+    fn rm(&self, size: usize, rex: bool) -> String {
+        match &self.rm {
+            Ok(number) => register_name(*number, size, rex),
+            Err(memory) => memory.clone(),
+        }
+    }
+}
+
+/// # brief
+/// Decode a ModRM byte, with its SIB byte and displacement, in 64-bit mode.
+///
+/// # param
+/// - `bytes` : The instruction from its ModRM byte on.
+/// - `rex` : The REX prefix, 0 without one.
+/// - `addr32` : Whether the address size prefix makes the address registers 32-bit.
+fn decode_modrm(bytes: &[u8], rex: u8, addr32: bool) -> Option<ModRm> {
+    let modrm = *bytes.first()?;
+    let (md, rm) = (modrm >> 6, (modrm & 7) as usize);
+    let reg = ((modrm >> 3) & 7) as usize | if rex & 4 != 0 { 8 } else { 0 };
+    let b = if rex & 1 != 0 { 8 } else { 0 };
+    if md == 3 {
+        return Some(ModRm { reg, rm: Ok(rm | b) });
+    }
+    let address_size = if addr32 { 4 } else { 8 };
+    let mut pos = 1;
+    let (mut base, mut index) = (None, None);
+    let mut rip_relative = false;
+    if rm == 4 {
+        let sib = *bytes.get(1)?;
+        pos += 1;
+        let number = ((sib >> 3) & 7) as usize | if rex & 2 != 0 { 8 } else { 0 };
+        // an index of 4 is none, unless REX.X makes it r12
+        if number != 4 {
+            index = Some((number, 1 << (sib >> 6)));
+        }
+        if !(md == 0 && sib & 7 == 5) {
+            base = Some((sib & 7) as usize | b);
+        }
+    } else if md == 0 && rm == 5 {
+        rip_relative = true;
+    } else {
+        base = Some(rm | b);
+    }
+    let disp_len = match md {
+        1 => 1,
+        2 => 4,
+        _ if base.is_none() => 4,
+        _ => 0,
+    };
+    let disp = signed(bytes.get(pos..pos + disp_len)?);
+    let mut memory = if disp_len > 0 { displacement(disp) } else { String::new() };
+    if rip_relative {
+        memory.push_str(if addr32 { "(%eip)" } else { "(%rip)" });
+    } else if base.is_some() || index.is_some() {
+        memory.push('(');
+        if let Some(base) = base {
+            memory.push_str(&register_name(base, address_size, true));
+        }
+        if let Some((index, scale)) = index {
+            memory.push_str(&format!(",{},{}", register_name(index, address_size, true), scale));
+        }
+        memory.push(')');
+    }
+    Some(ModRm { reg, rm: Err(memory) })
+}
+
+/// How the AT&T name of an instruction differs from its `mnemonic`
+#[derive(Debug, Clone, PartialEq)]
+enum Name {
+    Mnemonic,
+    /// With the suffix of an operand size of this many bytes, e.g. `movl` for 4
+    Sized(usize),
+    /// Another name, e.g. `movzbl` for `movzx` from a byte to 4 bytes
+    Renamed(String),
+}
+
+/// # brief
+/// Get the suffix of an operand size in AT&T syntax, e.g. `l` for 4 bytes.
+fn size_suffix(size: usize) -> char {
+    match size {
+        1 => 'b',
+        2 => 'w',
+        4 => 'l',
+        _ => 'q',
+    }
+}
+
+/// # brief
+/// Write the operands of the x86-64 instruction at the start of `bytes` in AT&T syntax, the
+/// source first, for the instructions `mnemonic` knows; the targets of relative branches are
+/// absolute addresses.
+///
+/// # param
+/// - `addr` : The address of the instruction, which relative branches are from.
+///
+/// # return
+/// * The operands, empty for an instruction without any, and how the name of the instruction
+/// differs from its mnemonic, or `None` if the operands are not known.
+fn operands(bytes: &[u8], addr: usize) -> Option<(String, Name)> {
+    let len = instruction_length(bytes)?;
+    let bytes = &bytes[..len];
+    let mut pos = 0;
+    let (mut operand16, mut addr32) = (false, false);
+    while let Some(prefix @ (0x66 | 0x67 | 0xf0 | 0xf2 | 0xf3 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65)) = bytes.get(pos) {
+        operand16 |= *prefix == 0x66;
+        addr32 |= *prefix == 0x67;
+        pos += 1;
+    }
+    let mut rex = 0;
+    while let Some(byte @ 0x40..=0x4f) = bytes.get(pos) {
+        rex = *byte;
+        pos += 1;
+    }
+    let has_rex = rex != 0;
+    let size = if rex & 8 != 0 { 8 } else if operand16 { 2 } else { 4 };
+    let b = if rex & 1 != 0 { 8 } else { 0 };
+    let opcode = bytes[pos];
+    pos += 1;
+    // the immediate or the relative offset ends the instruction of each form below
+    let imm = |n: usize| signed(&bytes[len - n..]);
+    let imm_z = if size == 2 { 2 } else { 4 };
+    let target = |n: usize| format!("{:#x}", (addr + len) as i64 + imm(n));
+    let register = |number: usize, size: usize| register_name(number, size, has_rex);
+    let modrm = || decode_modrm(&bytes[pos..], rex, addr32);
+    let operands = |list: &[String]| list.join(",");
+    // only an operation on memory without a register operand needs the size in its name
+    let sized = |m: &ModRm, size: usize| if m.rm.is_err() { Name::Sized(size) } else { Name::Mnemonic };
+    Some(match opcode {
+        0x0f => {
+            let opcode = bytes[pos];
+            pos += 1;
+            let modrm = || decode_modrm(&bytes[pos..], rex, addr32);
+            match opcode {
+                0x05 | 0x0b | 0xa2 => (String::new(), Name::Mnemonic),
+                0x1e if bytes.get(pos) == Some(&0xfa) => (String::new(), Name::Mnemonic),
+                0x1f => {
+                    let m = modrm()?;
+                    (m.rm(size, has_rex), sized(&m, size))
+                }
+                0x18 => (modrm()?.rm(8, has_rex), Name::Mnemonic),
+                0x40..=0x4f | 0xaf => {
+                    let m = modrm()?;
+                    (operands(&[m.rm(size, has_rex), register(m.reg, size)]), Name::Mnemonic)
+                }
+                0x80..=0x8f => (target(4), Name::Mnemonic),
+                0x90..=0x9f => {
+                    let m = modrm()?;
+                    (m.rm(1, has_rex), sized(&m, 1))
+                }
+                0xb6 | 0xb7 | 0xbe | 0xbf => {
+                    let m = modrm()?;
+                    let from = if opcode & 1 == 0 { 1 } else { 2 };
+                    let extension = if opcode >= 0xbe { 's' } else { 'z' };
+                    let name = format!("mov{}{}{}", extension, size_suffix(from), size_suffix(size));
+                    (operands(&[m.rm(from, has_rex), register(m.reg, size)]), Name::Renamed(name))
+                }
+                _ => return None,
+            }
+        }
+        0x00..=0x3f if opcode & 7 <= 5 => match opcode & 7 {
+            0 | 1 => {
+                let (m, size) = (modrm()?, if opcode & 1 == 0 { 1 } else { size });
+                (operands(&[register(m.reg, size), m.rm(size, has_rex)]), Name::Mnemonic)
+            }
+            2 | 3 => {
+                let (m, size) = (modrm()?, if opcode & 1 == 0 { 1 } else { size });
+                (operands(&[m.rm(size, has_rex), register(m.reg, size)]), Name::Mnemonic)
+            }
+            4 => (operands(&[immediate(imm(1), 1), register(0, 1)]), Name::Mnemonic),
+            _ => (operands(&[immediate(imm(imm_z), size), register(0, size)]), Name::Mnemonic),
+        },
+        0x50..=0x5f => (register((opcode & 7) as usize | b, 8), Name::Mnemonic),
+        0x63 => {
+            let m = modrm()?;
+            let name = if size == 8 { Name::Renamed("movslq".to_string()) } else { Name::Mnemonic };
+            (operands(&[m.rm(4, has_rex), register(m.reg, size)]), name)
+        }
+        0x68 => (immediate(imm(imm_z), 8), Name::Mnemonic),
+        0x6a => (immediate(imm(1), 8), Name::Mnemonic),
+        0x69 | 0x6b => {
+            let m = modrm()?;
+            let value = if opcode == 0x69 { imm(imm_z) } else { imm(1) };
+            (operands(&[immediate(value, size), m.rm(size, has_rex), register(m.reg, size)]), Name::Mnemonic)
+        }
+        0x70..=0x7f | 0xeb => (target(1), Name::Mnemonic),
+        0x80..=0x83 => {
+            let m = modrm()?;
+            let (size, value) = match opcode {
+                0x80 => (1, imm(1)),
+                0x81 => (size, imm(imm_z)),
+                _ => (size, imm(1)),
+            };
+            (operands(&[immediate(value, size), m.rm(size, has_rex)]), sized(&m, size))
+        }
+        0x84..=0x89 => {
+            let (m, size) = (modrm()?, if opcode & 1 == 0 { 1 } else { size });
+            (operands(&[register(m.reg, size), m.rm(size, has_rex)]), Name::Mnemonic)
+        }
+        0x8a | 0x8b | 0x8d => {
+            let (m, size) = (modrm()?, if opcode == 0x8a { 1 } else { size });
+            (operands(&[m.rm(size, has_rex), register(m.reg, size)]), Name::Mnemonic)
+        }
+        0x8f => {
+            let m = modrm()?;
+            (m.rm(8, has_rex), sized(&m, 8))
+        }
+        0x90 if b == 0 => (String::new(), Name::Mnemonic),
+        0x90..=0x97 => (operands(&[register(0, size), register((opcode & 7) as usize | b, size)]), Name::Mnemonic),
+        0x98 | 0x99 | 0xa4 | 0xa5 | 0xaa | 0xab | 0xc3 | 0xc9 | 0xcc | 0xf4 => (String::new(), Name::Mnemonic),
+        0xa8 => (operands(&[immediate(imm(1), 1), register(0, 1)]), Name::Mnemonic),
+        0xa9 => (operands(&[immediate(imm(imm_z), size), register(0, size)]), Name::Mnemonic),
+        0xb0..=0xb7 => (operands(&[immediate(imm(1), 1), register((opcode & 7) as usize | b, 1)]), Name::Mnemonic),
+        0xb8..=0xbf if size == 8 => {
+            let operands = operands(&[immediate(imm(8), 8), register((opcode & 7) as usize | b, 8)]);
+            (operands, Name::Renamed("movabs".to_string()))
+        }
+        0xb8..=0xbf => (operands(&[immediate(imm(imm_z), size), register((opcode & 7) as usize | b, size)]), Name::Mnemonic),
+        0xc0 | 0xc1 | 0xd0..=0xd3 => {
+            let m = modrm()?;
+            let size = if opcode & 1 == 0 { 1 } else { size };
+            let operand = m.rm(size, has_rex);
+            match opcode {
+                0xc0 | 0xc1 => (operands(&[immediate(imm(1), 1), operand]), sized(&m, size)),
+                0xd2 | 0xd3 => (operands(&[register(1, 1), operand]), sized(&m, size)),
+                _ => (operand, sized(&m, size)),
+            }
+        }
+        0xc2 => (immediate(imm(2), 2), Name::Mnemonic),
+        0xc6 | 0xc7 => {
+            let m = modrm()?;
+            let (size, value) = if opcode == 0xc6 { (1, imm(1)) } else { (size, imm(imm_z)) };
+            (operands(&[immediate(value, size), m.rm(size, has_rex)]), sized(&m, size))
+        }
+        0xcd => (immediate(imm(1), 1), Name::Mnemonic),
+        0xe8 | 0xe9 => (target(4), Name::Mnemonic),
+        0xf6 | 0xf7 => {
+            let m = modrm()?;
+            let size = if opcode == 0xf6 { 1 } else { size };
+            match m.reg & 7 {
+                0 | 1 => {
+                    let value = if opcode == 0xf6 { imm(1) } else { imm(imm_z) };
+                    (operands(&[immediate(value, size), m.rm(size, has_rex)]), sized(&m, size))
+                }
+                _ => (m.rm(size, has_rex), sized(&m, size)),
+            }
+        }
+        0xfe | 0xff => {
+            let m = modrm()?;
+            let size = if opcode == 0xfe { 1 } else { size };
+            match m.reg & 7 {
+                0 | 1 => (m.rm(size, has_rex), sized(&m, size)),
+                // indirect branches
+                2 | 4 if opcode == 0xff => (format!("*{}", m.rm(8, has_rex)), Name::Mnemonic),
+                6 if opcode == 0xff => (m.rm(8, has_rex), sized(&m, 8)),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// # brief
+/// Disassemble the x86-64 instruction at the start of `bytes` in AT&T syntax, as objdump shows
+/// it, e.g. `mov    %rsp,%rbp`. An instruction whose operands are not known is shown by its
+/// mnemonic only. See `mnemonic` for the instructions known.
+///
+/// # param
+/// - `addr` : The address of the instruction, which relative branches are from.
+///
+/// # return
+/// * The instruction, or `None` if it is not known.
+pub fn disassemble(bytes: &[u8], addr: usize) -> Option<String> {
+    let mnemonic = mnemonic(bytes)?;
+    let (operands, name) = match operands(bytes, addr) {
+        Some(operands) => operands,
+        None => return Some(mnemonic),
+    };
+    let name = match name {
+        Name::Mnemonic => mnemonic,
+        Name::Sized(size) => format!("{}{}", mnemonic, size_suffix(size)),
+        Name::Renamed(name) => name,
+    };
+    Some(if operands.is_empty() { name } else { format!("{:<6} {}", name, operands) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An instruction and what it decodes to, as objdump shows it
+    const KNOWN: &[(&[u8], usize, &str)] = &[
+        (&[0x55], 1, "push"),
+        (&[0x41, 0x57], 2, "push"),
+        (&[0x48, 0x89, 0xe5], 3, "mov"),
+        (&[0x48, 0x83, 0xec, 0x10], 4, "sub"),
+        (&[0x48, 0x8d, 0x05, 0x10, 0x00, 0x00, 0x00], 7, "lea"),
+        (&[0xb8, 0x2a, 0x00, 0x00, 0x00], 5, "mov"),
+        (&[0x48, 0xb8, 1, 2, 3, 4, 5, 6, 7, 8], 10, "mov"),
+        (&[0xe8, 0x00, 0x00, 0x00, 0x00], 5, "call"),
+        (&[0xff, 0xd0], 2, "call"),
+        (&[0x75, 0xf0], 2, "jne"),
+        (&[0x0f, 0x84, 0x10, 0x00, 0x00, 0x00], 6, "je"),
+        (&[0xeb, 0xfe], 2, "jmp"),
+        (&[0xc3], 1, "ret"),
+        (&[0xf3, 0xc3], 2, "repz ret"),
+        (&[0xc9], 1, "leave"),
+        (&[0xcc], 1, "int3"),
+        (&[0x0f, 0x05], 2, "syscall"),
+        (&[0xf3, 0x0f, 0x1e, 0xfa], 4, "endbr64"),
+        (&[0x0f, 0x1f, 0x44, 0x00, 0x00], 5, "nop"),
+        (&[0x0f, 0xb6, 0xc0], 3, "movzx"),
+        (&[0x0f, 0x94, 0xc0], 3, "sete"),
+        (&[0x48, 0x0f, 0x4f, 0xc1], 4, "cmovg"),
+        (&[0xf7, 0xd8], 2, "neg"),
+        (&[0xd1, 0xe0], 2, "shl"),
+        (&[0xf0, 0x48, 0x0f, 0xb1, 0x0a], 5, "(unknown)"),
+        (&[0xf3, 0x48, 0xab], 3, "rep stos"),
+    ];
+
+    /// Instructions assembled at 0 and how objdump disassembles them, without the comment of
+    /// the target of an address relative to `%rip`
+    const OBJDUMP: &[(usize, &[u8], &str)] = &[
+        (0x0, &[0x55], "push   %rbp"),
+        (0x1, &[0x48, 0x89, 0xe5], "mov    %rsp,%rbp"),
+        (0x4, &[0x48, 0x83, 0xec, 0x10], "sub    $0x10,%rsp"),
+        (0x8, &[0x48, 0x83, 0xe4, 0xf0], "and    $0xfffffffffffffff0,%rsp"),
+        (0xc, &[0xc7, 0x45, 0xfc, 0x00, 0x00, 0x00, 0x00], "movl   $0x0,-0x4(%rbp)"),
+        (0x13, &[0x8b, 0x45, 0xfc], "mov    -0x4(%rbp),%eax"),
+        (0x16, &[0x48, 0x8d, 0x05, 0x10, 0x00, 0x00, 0x00], "lea    0x10(%rip),%rax"),
+        (0x1d, &[0xb8, 0x2a, 0x00, 0x00, 0x00], "mov    $0x2a,%eax"),
+        (0x22, &[0x48, 0xb8, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08], "movabs $0x807060504030201,%rax"),
+        (0x2c, &[0xe8, 0x00, 0x00, 0x00, 0x00], "call   0x31"),
+        (0x31, &[0xff, 0xd0], "call   *%rax"),
+        (0x33, &[0xff, 0x50, 0x08], "call   *0x8(%rax)"),
+        (0x36, &[0x75, 0xf0], "jne    0x28"),
+        (0x38, &[0x74, 0x14], "je     0x4e"),
+        (0x3a, &[0xeb, 0xfe], "jmp    0x3a"),
+        (0x3c, &[0xc3], "ret"),
+        (0x3d, &[0xc9], "leave"),
+        (0x3e, &[0xcc], "int3"),
+        (0x3f, &[0x0f, 0x05], "syscall"),
+        (0x41, &[0x0f, 0x1f, 0x04, 0x00], "nopl   (%rax,%rax,1)"),
+        (0x45, &[0x66, 0x0f, 0x1f, 0x04, 0x00], "nopw   (%rax,%rax,1)"),
+        (0x4a, &[0x0f, 0xb6, 0xc0], "movzbl %al,%eax"),
+        (0x4d, &[0x0f, 0xb6, 0x07], "movzbl (%rdi),%eax"),
+        (0x50, &[0x0f, 0x94, 0xc0], "sete   %al"),
+        (0x53, &[0x48, 0x0f, 0x4f, 0xc1], "cmovg  %rcx,%rax"),
+        (0x57, &[0xf7, 0xd8], "neg    %eax"),
+        (0x59, &[0xd1, 0xe0], "shl    %eax"),
+        (0x5b, &[0x48, 0xc1, 0xe0, 0x03], "shl    $0x3,%rax"),
+        (0x5f, &[0xd3, 0xea], "shr    %cl,%edx"),
+        (0x61, &[0xf3, 0x48, 0xab], "rep stos"),
+        (0x64, &[0x4c, 0x89, 0x24, 0x24], "mov    %r12,(%rsp)"),
+        (0x68, &[0x44, 0x8b, 0x44, 0x24, 0x08], "mov    0x8(%rsp),%r8d"),
+        (0x6d, &[0x8b, 0x0c, 0x98], "mov    (%rax,%rbx,4),%ecx"),
+        (0x70, &[0x48, 0x8b, 0x14, 0xc5, 0x00, 0x00, 0x00, 0x00], "mov    0x0(,%rax,8),%rdx"),
+        (0x78, &[0x40, 0x88, 0x37], "mov    %sil,(%rdi)"),
+        (0x7b, &[0x88, 0xe3], "mov    %ah,%bl"),
+        (0x7d, &[0x31, 0xc0], "xor    %eax,%eax"),
+        (0x7f, &[0x04, 0x01], "add    $0x1,%al"),
+        (0x81, &[0x85, 0xff], "test   %edi,%edi"),
+        (0x83, &[0xa8, 0x01], "test   $0x1,%al"),
+        (0x85, &[0xf6, 0x07, 0x01], "testb  $0x1,(%rdi)"),
+        (0x88, &[0x6b, 0xc0, 0x64], "imul   $0x64,%eax,%eax"),
+        (0x8b, &[0x48, 0x0f, 0xaf, 0xfe], "imul   %rsi,%rdi"),
+        (0x8f, &[0x41, 0x5f], "pop    %r15"),
+        (0x91, &[0x6a, 0x01], "push   $0x1"),
+        (0x93, &[0x68, 0x45, 0x23, 0x01, 0x00], "push   $0x12345"),
+        (0x98, &[0x91], "xchg   %eax,%ecx"),
+        (0x99, &[0x48, 0x63, 0xc7], "movslq %edi,%rax"),
+        (0x9c, &[0xff, 0x00], "incl   (%rax)"),
+        (0x9e, &[0x49, 0xff, 0xc9], "dec    %r9"),
+        (0xa1, &[0xcd, 0x80], "int    $0x80"),
+        (0xa3, &[0xc6, 0x00, 0x05], "movb   $0x5,(%rax)"),
+        (0xa6, &[0x48, 0xc7, 0x43, 0x08, 0xff, 0xff, 0xff, 0xff], "movq   $0xffffffffffffffff,0x8(%rbx)"),
+        (0xae, &[0x48, 0x83, 0x38, 0x00], "cmpq   $0x0,(%rax)"),
+        (0xb2, &[0x41, 0x89, 0xc2], "mov    %eax,%r10d"),
+        (0xba, &[0xf3, 0x0f, 0x1e, 0xfa], "endbr64"),
+        (0xbe, &[0xf4], "hlt"),
+    ];
+
+    #[test]
+    fn disassembles_as_objdump_does() {
+        for (addr, bytes, text) in OBJDUMP {
+            assert_eq!(disassemble(bytes, *addr).as_deref(), Some(*text), "{:02x?} at {:#x}", bytes, addr);
+        }
+        // mov %ds,%eax, whose operands are not known
+        assert_eq!(disassemble(&[0x8c, 0xd8], 0).as_deref(), Some("mov"));
+        assert_eq!(disassemble(&[0x06], 0), None);
+    }
+
+    #[test]
+    fn decodes_known_instructions() {
+        for (bytes, len, name) in KNOWN {
+            assert_eq!(instruction_length(bytes), Some(*len), "length of {:02x?}", bytes);
+            let mnemonic = mnemonic(bytes).unwrap_or_else(|| "(unknown)".to_string());
+            assert_eq!(mnemonic, *name, "mnemonic of {:02x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn truncated_instruction_has_no_length() {
+        // a call cut after two bytes of its displacement
+        assert_eq!(instruction_length(&[0xe8, 0x00, 0x00]), None);
+        assert_eq!(instruction_length(&[0x48, 0x89]), None);
+        assert_eq!(instruction_length(&[]), None);
+        assert_eq!(mnemonic(&[]), None);
+    }
+
+    #[test]
+    fn invalid_opcodes_are_not_decoded() {
+        // push es and aaa don't exist in 64-bit mode
+        assert_eq!(instruction_length(&[0x06]), None);
+        assert_eq!(instruction_length(&[0x37]), None);
+    }
+
+    #[test]
+    fn splits_a_function_into_instructions() {
+        // push %rbp; mov %rsp,%rbp; pop %rbp; ret
+        let code = [0x55, 0x48, 0x89, 0xe5, 0x5d, 0xc3];
+        assert_eq!(instructions(&code, 0x1000), vec![(0x1000, 1), (0x1001, 3), (0x1004, 1), (0x1005, 1)]);
+        assert_eq!(boundary(&code, 0x1000, 0x1004), Boundary::Start);
+        assert_eq!(boundary(&code, 0x1000, 0x1002), Boundary::Inside(0x1001));
+        assert_eq!(boundary(&[0x06, 0x55], 0x1000, 0x1001), Boundary::Unknown);
+    }
+}
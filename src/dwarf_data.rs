@@ -1,9 +1,19 @@
 use addr2line::Context;
 use object::Object;
-use std::convert::TryInto;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{fmt, fs};
 
+use crate::cancel::CancelToken;
 use crate::gimli_wrapper;
+use crate::lru::{Lru, Stats};
+use crate::symtab::{Symbol, SymbolTable};
+use crate::ui::{self, Table};
 
 #[derive(Debug)]
 pub enum Error {
@@ -20,7 +30,34 @@ impl From<gimli_wrapper::Error> for Error {
 #[derive(Clone)]
 pub enum Location {
     Address(usize),
+    /// An offset from the frame base of the function, see `FrameBase`
     FramePointerOffset(isize),
+    /// The value is held in a register, by DWARF register number
+    Register(u16),
+    /// The value is in memory at a register plus an offset
+    RegisterOffset(u16, i64),
+    /// A location list: the location depends on the pc
+    List(Vec<(Range<usize>, Location)>),
+    /// The variable exists but deet can't read it, e.g. it was optimized out
+    Unavailable(String),
+}
+
+impl Location {
+    /// # brief
+    /// Pick the location of a location list that applies at `pc`.
+    ///
+    /// # return
+    /// * The location at `pc`, which is never a `List`.
+    pub fn at(&self, pc: usize) -> Location {
+        match self {
+            Location::List(entries) => entries
+                .iter()
+                .find(|(range, _)| range.contains(&pc))
+                .map(|(_, location)| location.clone())
+                .unwrap_or_else(|| Location::Unavailable("optimized out at this pc".to_string())),
+            location => location.clone(),
+        }
+    }
 }
 
 impl fmt::Display for Location {
@@ -28,6 +65,10 @@ impl fmt::Display for Location {
         match *self {
             Location::Address(addr) => write!(f, "Address({:#x})", addr),
             Location::FramePointerOffset(offset) => write!(f, "FramePointerOffset({})", offset),
+            Location::Register(regnum) => write!(f, "Register({})", regnum),
+            Location::RegisterOffset(regnum, offset) => write!(f, "RegisterOffset({}, {})", regnum, offset),
+            Location::List(ref entries) => write!(f, "List({} entries)", entries.len()),
+            Location::Unavailable(ref reason) => write!(f, "Unavailable({})", reason),
         }
     }
 }
@@ -38,6 +79,23 @@ impl fmt::Debug for Location {
     }
 }
 
+/// What `Location::FramePointerOffset` is relative to, from the `DW_AT_frame_base` of the
+/// function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameBase {
+    /// `DW_OP_call_frame_cfa`: the canonical frame address, 16 bytes above the saved %rbp once
+    /// the prologue ran
+    Cfa,
+    /// `DW_OP_regN` / `DW_OP_bregN`: a register plus an offset, by DWARF register number
+    Register(u16, i64),
+}
+
+impl Default for FrameBase {
+    fn default() -> Self {
+        FrameBase::Cfa
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Type {
     pub name: String,
@@ -50,6 +108,71 @@ impl Type {
     }
 }
 
+/// The kind of a type definition listed by `info types`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeKind {
+    Struct,
+    Class,
+    Union,
+    Enum,
+    Typedef,
+}
+
+impl fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeKind::Struct => write!(f, "struct"),
+            TypeKind::Class => write!(f, "class"),
+            TypeKind::Union => write!(f, "union"),
+            TypeKind::Enum => write!(f, "enum"),
+            TypeKind::Typedef => write!(f, "typedef"),
+        }
+    }
+}
+
+/// A member of a struct, class or union
+#[derive(Debug, Clone)]
+pub struct Member {
+    /// `None` for an anonymous struct or union member
+    pub name: Option<String>,
+    /// The offset in bytes from the start of the type, for a bitfield the byte holding its
+    /// first bit
+    pub offset: usize,
+    pub member_type: Type,
+    /// The width of a bitfield in bits, `None` for other members
+    pub bit_size: Option<u64>,
+}
+
+/// A struct, class, union, enum or typedef found in the debug information, as listed by
+/// `info types` and printed by `ptype`
+#[derive(Debug, Clone)]
+pub struct TypeDefinition {
+    pub kind: TypeKind,
+    /// `None` for an anonymous type
+    pub name: Option<String>,
+    pub size: usize,
+    /// Where the type is declared, the compilation unit if the debug information doesn't say
+    pub file: String,
+    pub line_number: usize,
+    /// Made up by the compiler, such as the state of a closure or a vtable
+    pub artificial: bool,
+    pub members: Vec<Member>,
+    /// The variants of an enum, with their values
+    pub enumerators: Vec<(String, i64)>,
+    /// For a typedef, the type it names
+    pub target: Option<Type>,
+    /// Rust enums are structs with a variant part, which deet doesn't decode
+    pub has_variants: bool,
+}
+
+impl TypeDefinition {
+    /// Tells whether `info types` leaves the type out unless `-a` is passed: anonymous types
+    /// and those the compiler generated, whose Rust names are in braces (`{closure_env#0}`).
+    pub fn is_hidden(&self) -> bool {
+        self.artificial || self.name.as_ref().map_or(true, |name| name.starts_with('{') || name.starts_with('<'))
+    }
+}
+
 // For variables and formal parameters
 #[derive(Debug, Clone)]
 pub struct Variable {
@@ -57,6 +180,8 @@ pub struct Variable {
     pub entity_type: Type,
     pub location: Location,
     pub line_number: usize, // Line number in source file
+    /// Whether this is a formal parameter of the function owning it
+    pub is_parameter: bool,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -65,32 +190,205 @@ pub struct Function {
     pub address: usize,
     pub text_length: usize,
     pub line_number: usize,
+    pub frame_base: FrameBase,
     pub variables: Vec<Variable>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Line {
     pub file: String,
     pub number: usize,
     pub address: usize,
+    /// Whether the address is a recommended breakpoint location, i.e. the beginning of a
+    /// statement rather than one of the other rows a line may be split into
+    pub is_stmt: bool,
+    /// Column of the row, 0 when unknown
+    pub column: usize,
+    /// Marks the first address past the end of a sequence of instructions. These rows belong to
+    /// no line, they only bound the previous one.
+    pub end_sequence: bool,
+}
+
+/// One of the functions the code at an address belongs to when functions were inlined into
+/// others: the innermost inlined function, or one of the functions it was inlined into.
+#[derive(Debug, Clone)]
+pub struct InlinedFrame {
+    pub function: String,
+    /// Where the code is in the function: the line of the address for the innermost function,
+    /// the line of the inlined call for the functions it was inlined into
+    pub line: Option<Line>,
 }
 
 impl fmt::Display for Line {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}:{}", self.file, self.number)
+        if self.column != 0 {
+            write!(f, "{}:{}:{}", self.file, self.number, self.column)
+        } else {
+            write!(f, "{}:{}", self.file, self.number)
+        }
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct File {
     pub name: String,
+    pub comp_dir: Option<String>,
     pub global_variables: Vec<Variable>,
     pub functions: Vec<Function>,
     pub lines: Vec<Line>,
+    /// The rows of the line program of the unit for other files, mostly headers: each unit
+    /// including a header has its own copy of the code of its inline functions and macros
+    pub included_lines: Vec<Line>,
 }
 
-pub struct DwarfData {
+/// The time spent in each phase of loading the debug information, and what was found
+#[derive(Debug, Default, Clone)]
+pub struct LoadStats {
+    pub mmap: Duration,
+    pub object_parse: Duration,
+    pub addr2line_context: Duration,
+    /// Collecting the types of all units, before the main walk
+    pub type_pass: Duration,
+    /// Walking the DIEs of all units for functions and variables
+    pub die_walk: Duration,
+    /// Decoding and sorting the line programs
+    pub line_programs: Duration,
+    /// The compilation unit taking the longest to load, and how long it took
+    pub slowest_unit: Option<(String, Duration)>,
+    /// The whole work of the loader thread, from the start of `from_file`
+    pub total: Duration,
+    pub units: usize,
+    pub functions: usize,
+    pub variables: usize,
+    /// Distinct addresses of the rows of all line programs
+    pub line_rows: usize,
+}
+
+impl fmt::Display for LoadStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<20}{:>12?}", "mmap", self.mmap)?;
+        writeln!(f, "{:<20}{:>12?}", "object parse", self.object_parse)?;
+        writeln!(f, "{:<20}{:>12?}", "addr2line context", self.addr2line_context)?;
+        writeln!(f, "{:<20}{:>12?}", "type pass", self.type_pass)?;
+        writeln!(f, "{:<20}{:>12?}", "DIE walk", self.die_walk)?;
+        writeln!(f, "{:<20}{:>12?}", "line programs", self.line_programs)?;
+        writeln!(f, "{:<20}{:>12?}", "total", self.total)?;
+        if let Some((name, time)) = &self.slowest_unit {
+            writeln!(f, "slowest unit: {} ({:?})", name, time)?;
+        }
+        write!(
+            f,
+            "{} units, {} functions, {} variables, {} line rows",
+            self.units, self.functions, self.variables, self.line_rows
+        )
+    }
+}
+
+/// How often the progress of the loading is shown while a command waits for it
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The number of compilation units loaded so far, out of `total`
+#[derive(Default)]
+struct Progress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// What the loader extracts from the debug information
+#[derive(Default)]
+struct Symbols {
     files: Vec<File>,
+    /// The sorted addresses of the rows of all line programs. The pcs from one row to the next
+    /// all have the same line.
+    row_addresses: Vec<usize>,
+    /// The global variables with a fixed address, for the symbol lookups of data addresses
+    globals: SymbolTable,
+    /// The phases of the loader thread
+    stats: LoadStats,
+    /// The compilation units left out because they could not be decoded, by name
+    unit_errors: Vec<(String, gimli_wrapper::Error)>,
+}
+
+/// The background thread extracting the functions, variables and lines of the compilation units
+struct Loader {
+    handle: thread::JoinHandle<Result<Symbols, Error>>,
+    progress: Arc<Progress>,
+}
+
+/// The number of entries of each lookup cache
+const CACHE_CAPACITY: usize = 1024;
+
+/// The results of addr2line recently looked up. A `DwarfData` is replaced as a whole when the
+/// target is reloaded, and its caches with it.
+struct LookupCache {
+    /// The line at a pc
+    lines: Lru<u64, Option<Line>>,
+    /// The line of every pc from a row of the line programs to the next, by row address
+    line_ranges: Lru<usize, Option<Line>>,
+    /// The function at a pc
+    functions: Lru<u64, Option<String>>,
+}
+
+/// # Brief
+///
+/// Extract the files of a mapped executable, reporting the progress in `progress`. This runs
+/// on the loader thread.
+fn load_files(mmap: memmap::Mmap, progress: &Progress) -> Result<Symbols, Error> {
+    let object = object::File::parse(&*mmap)
+        .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
+    let mut stats = LoadStats::default();
+    let (files, row_addresses, unit_errors) = gimli_wrapper::load_file(&object, endian_of(&object), &|done, total| {
+        progress.total.store(total, Ordering::Relaxed);
+        progress.done.store(done, Ordering::Relaxed);
+    }, &mut stats)?;
+    stats.functions = files.iter().map(|file| file.functions.len()).sum();
+    stats.variables = files
+        .iter()
+        .map(|file| file.global_variables.len() + file.functions.iter().map(|func| func.variables.len()).sum::<usize>())
+        .sum();
+    let globals = SymbolTable::from_symbols(
+        files
+            .iter()
+            .flat_map(|file| file.global_variables.iter())
+            .filter_map(|var| match var.location {
+                Location::Address(address) => Some(Symbol {
+                    name: var.name.clone(),
+                    address,
+                    size: var.entity_type.size,
+                    is_function: false,
+                }),
+                _ => None,
+            })
+            .collect(),
+    );
+    Ok(Symbols {files, row_addresses, globals, stats, unit_errors})
+}
+
+fn endian_of(object: &object::File) -> gimli::RunTimeEndian {
+    if object.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    }
+}
+
+pub struct DwarfData {
+    /// The symbols, set once the loader finished
+    symbols: OnceCell<Symbols>,
+    /// The loader, until its symbols are taken
+    loader: RefCell<Option<Loader>>,
+    /// The type definitions, read from the executable again by the first `types`
+    types: OnceCell<Vec<TypeDefinition>>,
+    cache: RefCell<LookupCache>,
+    /// The ELF symbols, for the addresses the debug information doesn't cover
+    symbol_table: SymbolTable,
+    /// The executable, opened again by `read_static`
+    path: String,
+    /// When `from_file` started, and the phases it ran before starting the loader
+    started: Instant,
+    stats: LoadStats,
+    /// Set once an error decoding the debug information was reported
+    reported_error: Cell<bool>,
     addr2line: Context<addr2line::gimli::EndianRcSlice<addr2line::gimli::RunTimeEndian>>,
 }
 
@@ -112,31 +410,303 @@ impl DwarfData {
     /// * `Result<DwarfData, Error>` - A `Result` indicating success (`Ok`) with the created `DwarfData` object,
     ///   or an error (`Err`) if there was a problem opening the file or parsing the debug information.
     ///
+    /// # Note
+    ///
+    /// Only the addr2line context, which reporting a stop needs, is built before returning. The
+    /// functions, variables and lines of every file are extracted by a background thread, and
+    /// the first use of them waits for it (see `files`).
+    ///
     pub fn from_file(path: &str) -> Result<Self, Error> {
+        let started = Instant::now();
+        let mut stats = LoadStats::default();
         let file = fs::File::open(path).or(Err(Error::ErrorOpeningFile))?;
         let mmap = unsafe { 
             memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile))?
         };
-        let object = object::File::parse(&*mmap)
-            .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
-        let endian = if object.is_little_endian() {
-            gimli::RunTimeEndian::Little
-        } else {
-            gimli::RunTimeEndian::Big
+        stats.mmap = started.elapsed();
+        let (addr2line, symbol_table) = {
+            let parse_start = Instant::now();
+            let object = object::File::parse(&*mmap)
+                .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
+            let symbol_table = SymbolTable::from_object(&object);
+            let context_start = Instant::now();
+            stats.object_parse = context_start - parse_start;
+            let context = Context::new(&object).or_else(|e| Err(gimli_wrapper::Error::from(e)))?;
+            stats.addr2line_context = context_start.elapsed();
+            (context, symbol_table)
+        };
+        let progress = Arc::new(Progress::default());
+        let handle = {
+            let progress = Arc::clone(&progress);
+            thread::spawn(move || {
+                let mut symbols = load_files(mmap, &progress)?;
+                symbols.stats.total = started.elapsed();
+                Ok(symbols)
+            })
         };
         Ok(DwarfData {
-            files: gimli_wrapper::load_file(&object, endian)?,
-            addr2line: Context::new(&object).or_else(|e| Err(gimli_wrapper::Error::from(e)))?,
+            symbols: OnceCell::new(),
+            loader: RefCell::new(Some(Loader {handle, progress})),
+            types: OnceCell::new(),
+            cache: RefCell::new(LookupCache {
+                lines: Lru::new(CACHE_CAPACITY),
+                line_ranges: Lru::new(CACHE_CAPACITY),
+                functions: Lru::new(CACHE_CAPACITY),
+            }),
+            symbol_table,
+            path: path.to_string(),
+            started,
+            stats,
+            reported_error: Cell::new(false),
+            addr2line,
+        })
+    }
+
+    /// Returns the files (compilation units) found in the debug information, waiting for the
+    /// loader to finish if needed. The progress is shown while waiting.
+    pub fn files(&self) -> &Vec<File> {
+        &self.symbols().files
+    }
+
+    /// Waits for the symbols of the loader, see `files`.
+    fn symbols(&self) -> &Symbols {
+        self.symbols.get_or_init(|| {
+            let loader = self.loader.borrow_mut().take().expect("the symbols are taken only once");
+            // the progress depends on timing, so it is left out of deterministic output
+            if !ui::deterministic() && !ui::quiet() && !loader.handle.is_finished() {
+                while !loader.handle.is_finished() {
+                    let done = loader.progress.done.load(Ordering::Relaxed);
+                    let total = loader.progress.total.load(Ordering::Relaxed);
+                    if total > 0 {
+                        eprint!("\rReading symbols... {}%", done * 100 / total);
+                    } else {
+                        eprint!("\rReading symbols...");
+                    }
+                    io::stderr().flush().ok();
+                    thread::sleep(PROGRESS_INTERVAL);
+                }
+                ui::notify("\rReading symbols... done.");
+            }
+            match loader.handle.join() {
+                Ok(Ok(symbols)) => {
+                    if !symbols.unit_errors.is_empty() {
+                        ui::notify(&format!(
+                            "warning: {} of {} compilation units could not be parsed; 'info dwarf-errors' for details",
+                            symbols.unit_errors.len(),
+                            symbols.stats.units
+                        ));
+                    }
+                    symbols
+                }
+                Ok(Err(err)) => self.report_error(err).unwrap_or_default(),
+                Err(_) => {
+                    ui::notify("warning: reading the debug information failed, no symbols are available");
+                    Symbols::default()
+                }
+            }
         })
     }
 
+    /// Returns the struct, class, union, enum and typedef definitions of the debug information,
+    /// in the order of the compilation units. They are only needed by `info types` and `ptype`,
+    /// so the loader doesn't extract them: the first call reads them from the executable.
+    pub fn types(&self) -> &Vec<TypeDefinition> {
+        self.types.get_or_init(|| {
+            let load = || -> Result<Vec<TypeDefinition>, Error> {
+                let file = fs::File::open(&self.path).or(Err(Error::ErrorOpeningFile))?;
+                let mmap = unsafe { memmap::Mmap::map(&file).or(Err(Error::ErrorOpeningFile))? };
+                let object = object::File::parse(&*mmap)
+                    .or_else(|e| Err(gimli_wrapper::Error::ObjectError(e.to_string())))?;
+                Ok(gimli_wrapper::load_types(&object, endian_of(&object))?)
+            };
+            load().unwrap_or_else(|err| self.report_error(err).unwrap_or_default())
+        })
+    }
+
+    /// Looks up the definitions of the type `name`, e.g. `point` or `struct point`. A type
+    /// defined in a header is found once per compilation unit including it, the duplicates
+    /// are left out.
+    pub fn find_type(&self, name: &str) -> Vec<&TypeDefinition> {
+        let (kind, name) = match name.split_once(' ') {
+            Some(("struct", name)) => (Some(TypeKind::Struct), name),
+            Some(("class", name)) => (Some(TypeKind::Class), name),
+            Some(("union", name)) => (Some(TypeKind::Union), name),
+            Some(("enum", name)) => (Some(TypeKind::Enum), name),
+            _ => (None, name),
+        };
+        let mut found: Vec<&TypeDefinition> = self
+            .types()
+            .iter()
+            .filter(|def| def.name.as_deref() == Some(name.trim()) && kind.map_or(true, |kind| def.kind == kind))
+            .collect();
+        found.sort_by(|a, b| (&a.file, a.line_number, a.kind).cmp(&(&b.file, b.line_number, b.kind)));
+        found.dedup_by(|a, b| (&a.file, a.line_number, a.kind) == (&b.file, b.line_number, b.kind));
+        found
+    }
+
+    /// Returns the compilation units the loader left out because they could not be decoded,
+    /// with the errors, waiting for the loader to finish if needed.
+    pub fn unit_errors(&self) -> &[(String, gimli_wrapper::Error)] {
+        &self.symbols().unit_errors
+    }
+
+    /// Like `files`, but without waiting: `None` while the loader is still running.
+    pub fn loaded_files(&self) -> Option<&Vec<File>> {
+        let finished = self
+            .loader
+            .borrow()
+            .as_ref()
+            .map_or(true, |loader| loader.handle.is_finished());
+        if finished {
+            Some(self.files())
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves the file that defines a function.
+    ///
+    /// # Param
+    ///
+    /// * `func_name`: The name of the function.
+    ///
+    /// # Returns
+    ///
+    /// The first file containing a function named `func_name`, or `None` if there is no such function.
+    pub fn get_file_for_function(&self, func_name: &str) -> Option<&File> {
+        self.files()
+            .iter()
+            .find(|file| file.functions.iter().any(|func| func.name == func_name))
+    }
+
+    /// Looks up all functions with a given name.
+    ///
+    /// # Param
+    ///
+    /// * `name`: The name of the function.
+    ///
+    /// # Returns
+    ///
+    /// Every matching function together with the file defining it. Static functions may share a
+    /// name across files, so there can be more than one match.
+    pub fn find_function(&self, name: &str) -> Vec<(&File, &Function)> {
+        self.files()
+            .iter()
+            .flat_map(|file| file.functions.iter().map(move |func| (file, func)))
+            .filter(|(_, func)| func.name == name)
+            .collect()
+    }
+
+    /// Looks up the functions a name given to `break` may mean: the functions of that name in
+    /// every file, such as statics of the same name and C++ overloads, and the instances of a
+    /// generic function or template, e.g. `process<i32>` and `process<&str>` for `process`.
+    ///
+    /// # Param
+    ///
+    /// * `name`: The name, without generic arguments to match all the instances.
+    ///
+    /// # Returns
+    ///
+    /// The functions with the file defining each, in the order of the files. A function emitted
+    /// in several units, such as an inline one the linker merged, counts once.
+    pub fn functions_named(&self, name: &str) -> Vec<(&File, &Function)> {
+        let matches = |func: &Function| {
+            func.name.strip_prefix(name).map_or(false, |rest| rest.is_empty() || rest.starts_with('<'))
+        };
+        let mut addresses = Vec::new();
+        self.files()
+            .iter()
+            .flat_map(|file| file.functions.iter().map(move |func| (file, func)))
+            // a copy discarded by the linker is left at address 0
+            .filter(|(_, func)| func.address != 0 && matches(func))
+            .filter(|(_, func)| {
+                let first = !addresses.contains(&func.address);
+                addresses.push(func.address);
+                first
+            })
+            .collect()
+    }
+
+    /// Looks up the functions whose name passes a filter, such as a regex, for the commands that
+    /// go through all of them. The scan checks `cancel` before each file, so Ctrl+C stops it.
+    ///
+    /// # Param
+    ///
+    /// * `matches`: The filter, given the name of each function.
+    /// * `cancel`: The token of the command.
+    ///
+    /// # Returns
+    ///
+    /// The matching functions with the file defining each, in the order of the files, and whether
+    /// the scan was cancelled before the last file, in which case the matches are partial.
+    pub fn functions_matching(
+        &self,
+        matches: impl Fn(&str) -> bool,
+        cancel: &CancelToken,
+    ) -> (Vec<(&File, &Function)>, bool) {
+        let mut found = Vec::new();
+        for file in self.files() {
+            if cancel.is_cancelled() {
+                return (found, true);
+            }
+            found.extend(file.functions.iter().filter(|func| matches(&func.name)).map(|func| (file, func)));
+        }
+        (found, false)
+    }
+
+    /// Looks up the function whose code contains an address.
+    ///
+    /// # Param
+    ///
+    /// * `addr`: The address, in the debug information's address space.
+    ///
+    /// # Returns
+    ///
+    /// The function together with the file defining it, or `None` outside of any function.
+    pub fn function_containing(&self, addr: usize) -> Option<(&File, &Function)> {
+        self.files()
+            .iter()
+            .flat_map(|file| file.functions.iter().map(move |func| (file, func)))
+            .find(|(_, func)| func.address <= addr && addr < func.address + func.text_length)
+    }
+
+    /// Looks up all variables with a given name, globals first.
+    ///
+    /// # Param
+    ///
+    /// * `name`: The name of the variable.
+    ///
+    /// # Returns
+    ///
+    /// Every matching variable with the file declaring it, and the function owning it for locals
+    /// and parameters (`None` for globals).
+    pub fn find_variable(&self, name: &str) -> Vec<(&File, Option<&Function>, &Variable)> {
+        let globals = self.files().iter().flat_map(|file| {
+            file.global_variables
+                .iter()
+                .map(move |var| (file, None, var))
+        });
+        let locals = self.files().iter().flat_map(|file| {
+            file.functions.iter().flat_map(move |func| {
+                func.variables
+                    .iter()
+                    .map(move |var| (file, Some(func), var))
+            })
+        });
+        globals
+            .chain(locals)
+            .filter(|(_, _, var)| var.name == name)
+            .collect()
+    }
+
     /// # Brief
     ///
     /// Find the target file in the list of files.
     ///
     /// This function searches for a file in the list of files based on the given `file` parameter.
     /// It checks if the file name matches exactly with `file`, or if `file` does not contain a slash '/'
-    /// and the file name ends with `file` preceded by a slash '/'.
+    /// and the file name ends with `file` preceded by a slash '/'. A relative file name also matches
+    /// when it is joined with its compilation directory, which is how addr2line reports paths.
     ///
     /// # Arguments
     ///
@@ -146,14 +716,15 @@ impl DwarfData {
     ///
     /// An optional reference to the target `File` if found, or `None` if not found.
     ///
-    #[allow(dead_code)]
-    fn get_target_file(&self, file: &str) -> Option<&File> {
-        self.files.iter().find(|f| {
-            (f.name == file) || (!file.contains("/") && f.name.ends_with(&format!("/{}", file)))
+    pub fn get_target_file(&self, file: &str) -> Option<&File> {
+        self.files().iter().find(|f| {
+            (f.name == file)
+                || (!file.contains("/") && f.name.ends_with(&format!("/{}", file)))
+                || f.comp_dir.as_ref().map_or(false, |dir| format!("{}/{}", dir, f.name) == file)
         })
     }
 
-    /// Retrieves the memory address corresponding to a specified file and line number.
+    /// Retrieves the line table entry of the code of a specified file and line number.
     /// 
     /// # Param
     /// 
@@ -162,19 +733,60 @@ impl DwarfData {
     /// 
     /// # Returns
     /// 
-    /// If the corresponding line is found, the memory address of that line is returned. Otherwise, `None` is returned.
-    pub fn get_addr_for_line(&self, file: Option<&str>, line_number: usize) -> Option<usize> {
+    /// The first entry of the line, or of the next line with code when the line has none (a
+    /// comment or a blank line); its `number` tells which. `None` if the file is not found or
+    /// the line is past its last line with code.
+    pub fn get_addr_for_line(&self, file: Option<&str>, line_number: usize) -> Option<Line> {
         let target_file = match file {
             Some(filename) => self.get_target_file(filename)?,
-            None => self.files.get(0)?,
+            None => self.files().get(0)?,
         };
-        Some(
-            target_file
-                .lines
-                .iter()
-                .find(|line| line.number >= line_number)?
-                .address,
-            )
+        target_file
+            .lines
+            .iter()
+            .filter(|line| !line.end_sequence && line.number >= line_number)
+            // line programs are sorted by address, not by line
+            .min_by_key(|line| (line.number, line.address))
+            .cloned()
+    }
+
+    /// Retrieves every location of the code of a line, across the compilation units: a line of
+    /// a header has code in each unit including it, at different addresses.
+    ///
+    /// # Param
+    ///
+    /// * `file`: The file name, as recorded or a bare name such as `util.h`.
+    /// * `line_number`: The line number in the source code.
+    ///
+    /// # Returns
+    ///
+    /// The first entry of the line in each unit, sorted by address, or of the next line with
+    /// code as `get_addr_for_line` does. Empty if no unit has code for the file past the line.
+    pub fn line_locations(&self, file: &str, line_number: usize) -> Vec<Line> {
+        let matches = |name: &str| name == file || (!file.contains('/') && name.ends_with(&format!("/{}", file)));
+        let per_unit: Vec<Vec<&Line>> = self
+            .files()
+            .iter()
+            .map(|unit| {
+                let own = unit.lines.iter().filter(|_| matches(&unit.name));
+                own.chain(unit.included_lines.iter().filter(|line| matches(&line.file)))
+                    // a copy discarded by the linker is left at address 0
+                    .filter(|line| !line.end_sequence && line.number >= line_number && line.address != 0)
+                    .collect()
+            })
+            .collect();
+        let number = match per_unit.iter().flatten().map(|line| line.number).min() {
+            Some(number) => number,
+            None => return Vec::new(),
+        };
+        let mut found: Vec<Line> = per_unit
+            .iter()
+            .filter_map(|rows| rows.iter().filter(|line| line.number == number).min_by_key(|line| line.address))
+            .map(|line| (*line).clone())
+            .collect();
+        found.sort_by_key(|line| line.address);
+        found.dedup_by_key(|line| line.address);
+        found
     }
 
     /// 
@@ -199,7 +811,7 @@ impl DwarfData {
                     .address,
             ),
             None => {
-                for file in &self.files {
+                for file in self.files() {
                     if let Some(func) = file.functions.iter().find(|func| func.name == func_name) {
                         return Some(func.address);
                     }
@@ -213,41 +825,293 @@ impl DwarfData {
     /// 
     /// # Param
     /// 
-    /// * `curr_addr`: The memory address.
+    /// * `curr_addr`: The memory address. Any value is accepted, including corrupted ones.
     /// 
     /// # Returns
     /// 
-    /// If the corresponding source code line is found, the information of that line is returned. Otherwise, `None` is returned.
-    pub fn get_line_from_addr(&self, curr_addr: usize) -> Option<Line> {
-        let location = self
-            .addr2line
-            .find_location(curr_addr.try_into().unwrap())
-            .ok()??;
-        Some( Line{
-            file: location.file?.to_string(),
-            number: location.line?.try_into().unwrap(),
-            address: curr_addr,
-        })
+    /// If the corresponding source code line is found, the information of that line is returned,
+    /// `None` if there is no line information for the address, or an `Error` if the debug
+    /// information could not be decoded.
+    pub fn get_line_from_addr(&self, curr_addr: u64) -> Result<Option<Line>, Error> {
+        if let Some(line) = self.cache.borrow_mut().lines.get(&curr_addr) {
+            return Ok(line);
+        }
+        let line = self.resolve_line(curr_addr)?;
+        self.cache.borrow_mut().lines.insert(curr_addr, line.clone());
+        Ok(line)
+    }
+
+    /// Looks up the line at a pc missing from the cache of `get_line_from_addr`, in the cache of
+    /// line ranges first.
+    fn resolve_line(&self, curr_addr: u64) -> Result<Option<Line>, Error> {
+        let addr = curr_addr as usize;
+        if self.is_past_end_sequence(addr) {
+            return Ok(None);
+        }
+        let row = self.row_containing(addr);
+        let cached = row.and_then(|row| self.cache.borrow_mut().line_ranges.get(&row));
+        if let Some(line) = cached {
+            return Ok(line.map(|line| Line {
+                address: addr,
+                is_stmt: self.get_statement_at(addr).is_some(),
+                ..line
+            }));
+        }
+        let line = self.find_line(curr_addr)?;
+        if let Some(row) = row {
+            self.cache.borrow_mut().line_ranges.insert(row, line.clone());
+        }
+        Ok(line)
+    }
+
+    /// The address of the last row of the line programs at or before `addr`.
+    fn row_containing(&self, addr: usize) -> Option<usize> {
+        let rows = &self.symbols().row_addresses;
+        let end = rows.partition_point(|row| *row <= addr);
+        rows[..end].last().copied()
+    }
+
+    /// Asks addr2line for the line at a pc.
+    fn find_line(&self, curr_addr: u64) -> Result<Option<Line>, Error> {
+        let addr = curr_addr as usize;
+        let location = match self.addr2line.find_location(curr_addr).map_err(gimli_wrapper::Error::from)? {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        let (file, number) = match (location.file, location.line) {
+            (Some(file), Some(number)) => (file, number),
+            _ => return Ok(None),
+        };
+        Ok(Some( Line{
+            file: file.to_string(),
+            number: number as usize,
+            address: addr,
+            is_stmt: self.get_statement_at(addr).is_some(),
+            column: location.column.unwrap_or(0) as usize,
+            end_sequence: false,
+        }))
+    }
+
+    /// Like `get_line_from_addr`, for display purposes: a damaged debug information is reported
+    /// once per session, and treated as no line information.
+    pub fn line_at(&self, curr_addr: u64) -> Option<Line> {
+        self.get_line_from_addr(curr_addr)
+            .unwrap_or_else(|err| self.report_error(err))
+    }
+
+    /// Retrieves the line-table row starting a statement at exactly a memory address.
+    ///
+    /// # Param
+    ///
+    /// * `addr`: The memory address.
+    ///
+    /// # Returns
+    ///
+    /// The row with `is_stmt` set at `addr`, or `None` if `addr` is not the beginning of a
+    /// statement. When several rows share the address the last one wins, as in the line program.
+    pub fn get_statement_at(&self, addr: usize) -> Option<&Line> {
+        self.get_line_entries_in_range(addr, addr.saturating_add(1))
+            .into_iter()
+            .filter(|line| line.is_stmt && !line.end_sequence)
+            .last()
+    }
+
+    /// Retrieves the line-table rows whose addresses lie in a range, in address order.
+    ///
+    /// # Param
+    ///
+    /// * `lo`: The first address of the range.
+    /// * `hi`: The end of the range, exclusive.
+    ///
+    /// # Returns
+    ///
+    /// The rows of all files with `lo <= address < hi`, end-of-sequence rows included.
+    pub fn get_line_entries_in_range(&self, lo: usize, hi: usize) -> Vec<&Line> {
+        let mut entries: Vec<&Line> = self
+            .files()
+            .iter()
+            .flat_map(|file| {
+                // the rows of every file are sorted by address
+                let start = file.lines.partition_point(|line| line.address < lo);
+                let end = file.lines.partition_point(|line| line.address < hi);
+                file.lines[start..end].iter()
+            })
+            .collect();
+        entries.sort_by_key(|line| (line.address, !line.end_sequence));
+        entries
+    }
+
+    /// Check whether an address lies past the end of a sequence (e.g. in the padding after a
+    /// function's last instruction), where no line may be attributed to it.
+    fn is_past_end_sequence(&self, addr: usize) -> bool {
+        let last_row = self
+            .files()
+            .iter()
+            .filter_map(|file| {
+                let end = file.lines.partition_point(|line| line.address <= addr);
+                file.lines[..end].last()
+            })
+            // a sequence may start right where the previous one ends, the new row wins then
+            .max_by_key(|line| (line.address, !line.end_sequence));
+        last_row.map_or(false, |line| line.end_sequence)
     }
 
     /// Retrieves the function name corresponding to a memory address.
     /// 
     /// # Parameters
     /// 
-    /// * `curr_addr`: The memory address.
+    /// * `curr_addr`: The memory address. Any value is accepted, including corrupted ones.
     /// 
     /// # Returns
     /// 
-    /// If the corresponding function is found, the name of that function is returned. Otherwise, `None` is returned.
-    #[allow(dead_code)]
-    pub fn get_function_from_addr(&self, curr_addr: usize) -> Option<String> {
-        let frame = self
-            .addr2line
-            .find_frames(curr_addr.try_into().unwrap())
-            .ok()?
-            .next()
-            .ok()??;
-        Some( frame.function?.raw_name().ok()?.to_string() )
+    /// If the corresponding function is found, the name of that function is returned, `None` if
+    /// there is no function at the address, or an `Error` if the debug information could not be
+    /// decoded.
+    pub fn get_function_from_addr(&self, curr_addr: u64) -> Result<Option<String>, Error> {
+        if let Some(function) = self.cache.borrow_mut().functions.get(&curr_addr) {
+            return Ok(function);
+        }
+        let function = self.find_function_name(curr_addr)?;
+        self.cache.borrow_mut().functions.insert(curr_addr, function.clone());
+        Ok(function)
+    }
+
+    /// Asks addr2line for the function at a pc.
+    fn find_function_name(&self, curr_addr: u64) -> Result<Option<String>, Error> {
+        let mut frames = self.addr2line.find_frames(curr_addr).map_err(gimli_wrapper::Error::from)?;
+        let function = match frames.next().map_err(gimli_wrapper::Error::from)? {
+            Some(frame) => frame.function,
+            None => return Ok(None),
+        };
+        match function {
+            Some(function) => Ok(Some(function.raw_name().map_err(gimli_wrapper::Error::from)?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieves the chain of functions inlined at a memory address.
+    ///
+    /// # Returns
+    ///
+    /// The innermost inlined function first and the function the code was compiled into last,
+    /// a single frame where nothing was inlined and none where there is no function, or an
+    /// `Error` if the debug information could not be decoded.
+    pub fn get_inlined_frames(&self, curr_addr: u64) -> Result<Vec<InlinedFrame>, Error> {
+        let mut frames = self.addr2line.find_frames(curr_addr).map_err(gimli_wrapper::Error::from)?;
+        let mut inlined = Vec::new();
+        while let Some(frame) = frames.next().map_err(gimli_wrapper::Error::from)? {
+            let function = match &frame.function {
+                Some(function) => function.raw_name().map_err(gimli_wrapper::Error::from)?.to_string(),
+                None => "??".to_string(),
+            };
+            let line = frame.location.and_then(|location| {
+                Some(Line {
+                    file: location.file?.to_string(),
+                    number: location.line? as usize,
+                    address: curr_addr as usize,
+                    is_stmt: false,
+                    column: location.column.unwrap_or(0) as usize,
+                    end_sequence: false,
+                })
+            });
+            inlined.push(InlinedFrame { function, line });
+        }
+        Ok(inlined)
+    }
+
+    /// Like `get_inlined_frames`, for display purposes: a damaged debug information is reported
+    /// once per session, and treated as no function.
+    pub fn inlined_frames(&self, curr_addr: u64) -> Vec<InlinedFrame> {
+        match self.get_inlined_frames(curr_addr) {
+            Ok(frames) => frames,
+            Err(err) => self.report_error(err).unwrap_or_default(),
+        }
+    }
+
+    /// Like `get_function_from_addr`, for display purposes: a damaged debug information is
+    /// reported once per session, and treated as no function. Code the debug information
+    /// doesn't cover, e.g. a binary whose DWARF was stripped, is named after its ELF symbol.
+    pub fn function_at(&self, curr_addr: u64) -> Option<String> {
+        self.get_function_from_addr(curr_addr)
+            .unwrap_or_else(|err| self.report_error(err))
+            .or_else(|| {
+                let (symbol, _) = self.symbol_table.lookup(curr_addr as usize)?;
+                Some(symbol.name.clone()).filter(|_| symbol.is_function)
+            })
+    }
+
+    /// Returns the symbol `addr` points into and the offset into it: a function or variable
+    /// of the ELF symbol table, or else a global variable of the debug information. Waits for
+    /// the loader when the ELF symbols don't cover `addr`.
+    pub fn symbol_at(&self, addr: usize) -> Option<(String, usize)> {
+        self.symbol_table
+            .lookup(addr)
+            .or_else(|| self.symbols().globals.lookup(addr))
+            .map(|(symbol, offset)| (symbol.name.clone(), offset))
+    }
+
+    /// Reads the initial contents of `len` bytes at `addr` from the executable, e.g. the value
+    /// of a global variable before the program runs. A section without contents in the file
+    /// (`.bss`) reads as zeros.
+    pub fn read_static(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        let offset = match self.symbol_table.file_offset(addr, len)? {
+            Some(offset) => offset,
+            None => return Some(vec![0; len]),
+        };
+        let mut file = fs::File::open(&self.path).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut bytes = vec![0; len];
+        file.read_exact(&mut bytes).ok()?;
+        Some(bytes)
+    }
+
+    /// Returns the name of the ELF section containing `addr`.
+    pub fn section_at(&self, addr: usize) -> Option<&str> {
+        self.symbol_table.section_of(addr)
+    }
+
+    /// Tells whether `addr` is in a PLT stub: `Some(true)` at the first instruction of one,
+    /// `Some(false)` further in, `None` outside of the PLT.
+    pub fn plt_stub(&self, addr: usize) -> Option<bool> {
+        self.symbol_table.plt_stub(addr)
+    }
+
+    /// Returns the address of the function `name` in the ELF symbol table, for code the debug
+    /// information doesn't describe.
+    pub fn function_symbol(&self, name: &str) -> Option<usize> {
+        self.symbol_table.function_address(name)
+    }
+
+    /// Returns the time spent loading the debug information, waiting for the loader to finish.
+    pub fn load_stats(&self) -> LoadStats {
+        let loader = &self.symbols().stats;
+        LoadStats {
+            mmap: self.stats.mmap,
+            object_parse: self.stats.object_parse,
+            addr2line_context: self.stats.addr2line_context,
+            // a failed load has no total, the time until now is all there is
+            total: if loader.total.is_zero() { self.started.elapsed() } else { loader.total },
+            ..loader.clone()
+        }
+    }
+
+    /// Returns the name, the statistics, the number of entries and the capacity of each lookup
+    /// cache, for `info dwarf-cache`.
+    pub fn cache_stats(&self) -> Vec<(&'static str, Stats, usize, usize)> {
+        let cache = self.cache.borrow();
+        vec![
+            ("line by pc", cache.lines.stats(), cache.lines.len(), cache.lines.capacity()),
+            ("line by range", cache.line_ranges.stats(), cache.line_ranges.len(), cache.line_ranges.capacity()),
+            ("function by pc", cache.functions.stats(), cache.functions.len(), cache.functions.capacity()),
+        ]
+    }
+
+    /// Warn about the first error decoding the debug information, later ones are silent.
+    fn report_error<T>(&self, err: Error) -> Option<T> {
+        if !self.reported_error.replace(true) {
+            ui::notify(&format!("warning: the debug information seems damaged, some locations can't be shown ({:?})", err));
+        }
+        None
     }
 
     /// Prints the details of the DWARF data.
@@ -277,47 +1141,348 @@ impl DwarfData {
     /// This function is primarily used for debugging and understanding the structure of the DWARF data.
     #[allow(dead_code)]
     pub fn print(&self) {
-        for file in &self.files {
+        for file in self.files() {
             println!("------");
             println!("{}", file.name);
             println!("------");
 
             println!("\x1b[34m| - - - - Global variables- - - - |\x1b[0m");
+            let mut globals = Table::new(&["Variable", "Type", "Location", "Line"]);
             for var in &file.global_variables {
-                println!(
-                    "| Variable: {:<20} | Type: {:<8} | Location: {:<10} | Line: {:<5} |",
-                    var.name, var.entity_type.name, var.location, var.line_number
-                );
+                globals.row(vec![
+                    var.name.clone(),
+                    var.entity_type.name.clone(),
+                    var.location.to_string(),
+                    var.line_number.to_string(),
+                ]);
             }
+            globals.print();
 
             println!("\x1b[34m|- - - - - Functions - - - - -| \x1b[0m");
+            let mut functions = Table::new(&["Function / variable", "Line", "Address / type", "Length / location"]);
             for func in &file.functions {
-                println!(
-                    "| Function: {:<17} | Line: {:<8} | Address: {:<24x} | Length: {:<6} |",
-                    func.name, func.line_number, func.address, func.text_length,
-                );
+                functions.row(vec![
+                    func.name.clone(),
+                    func.line_number.to_string(),
+                    format!("{:#x}", func.address),
+                    func.text_length.to_string(),
+                ]);
                 for var in &func.variables {
-                    println!(
-                    "| Variable: {:<17} | Type: {:<8} | Location: {:<20} | Line: {:<8} |",
-                        var.name, var.entity_type.name, var.location, var.line_number
-                    );
+                    functions.row(vec![
+                        format!("  {}", var.name),
+                        var.line_number.to_string(),
+                        var.entity_type.name.clone(),
+                        var.location.to_string(),
+                    ]);
                 }
             }
+            functions.print();
 
             println!("\x1b[34m| - - - - Line numbers - - - - |\x1b[0m");
+            let mut lines = Table::new(&["Line", "Address"]);
             for line in &file.lines {
-                println!(
-                    "| Line: {:<4} | Address: {:<5x} |",
-                    line.number, line.address
-                );
+                lines.row(vec![line.number.to_string(), format!("{:#x}", line.address)]);
             }
+            lines.print();
         }
     }
 }
 
 impl fmt::Debug for DwarfData {
     fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "DwarfData {{files: {:?}}}", self.files)
+        write!(f, "DwarfData {{files: {:?}}}", self.files())
+    }
+}
+
+
+/// An x86-64 ELF header without any section, for an addr2line context that knows nothing
+#[cfg(test)]
+const EMPTY_ELF: [u8; 64] = {
+    let mut header = [0u8; 64];
+    header[0] = 0x7f;
+    header[1] = b'E';
+    header[2] = b'L';
+    header[3] = b'F';
+    header[4] = 2;
+    header[5] = 1;
+    header[6] = 1;
+    header[16] = 2;
+    header[18] = 62;
+    header[20] = 1;
+    header[52] = 64;
+    header[54] = 56;
+    header[58] = 64;
+    header
+};
+
+#[cfg(test)]
+impl DwarfData {
+    /// # brief
+    /// Make the debug information of an executable from its files, as the loader would have
+    /// extracted them.
+    pub fn from_files(files: Vec<File>) -> DwarfData {
+        let object = object::File::parse(&EMPTY_ELF[..]).unwrap();
+        let addr2line = Context::new(&object).unwrap();
+        let mut row_addresses: Vec<usize> = files.iter().flat_map(|file| file.lines.iter().map(|line| line.address)).collect();
+        row_addresses.sort_unstable();
+        row_addresses.dedup();
+        let symbols = Symbols { files, row_addresses, ..Symbols::default() };
+        DwarfData {
+            symbols: OnceCell::from(symbols),
+            loader: RefCell::new(None),
+            types: OnceCell::new(),
+            cache: RefCell::new(LookupCache {
+                lines: Lru::new(CACHE_CAPACITY),
+                line_ranges: Lru::new(CACHE_CAPACITY),
+                functions: Lru::new(CACHE_CAPACITY),
+            }),
+            symbol_table: SymbolTable::default(),
+            path: String::new(),
+            started: Instant::now(),
+            stats: LoadStats::default(),
+            reported_error: Cell::new(false),
+            addr2line,
+        }
+    }
+
+    /// # brief
+    /// Make `line_at` find the rows of the files at their addresses, as addr2line would: the
+    /// line programs behind `from_files` are empty.
+    pub fn with_rows_cached(self) -> DwarfData {
+        for line in self.symbols().files.iter().flat_map(|file| file.lines.iter()).filter(|line| !line.end_sequence) {
+            self.cache.borrow_mut().lines.insert(line.address as u64, Some(line.clone()));
+        }
+        self
+    }
+
+    /// # brief
+    /// Give the executable the ELF symbols `symbols`, for the code the files don't describe.
+    pub fn with_symbols(mut self, symbols: Vec<Symbol>) -> DwarfData {
+        self.symbol_table = SymbolTable::from_symbols(symbols);
+        self
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(name: &str, address: usize) -> Function {
+        Function { name: name.to_string(), address, text_length: 0x20, line_number: 1, ..Function::default() }
+    }
+
+    fn file(name: &str, functions: Vec<Function>) -> File {
+        File { name: name.to_string(), functions, ..File::default() }
+    }
+
+    /// Two files, each with its own functions
+    fn two_files() -> DwarfData {
+        DwarfData::from_files(vec![
+            file("main.c", vec![function("main", 0x1000), function("parse_args", 0x1020)]),
+            file("util.c", vec![function("parse_line", 0x2000)]),
+        ])
+    }
+
+    fn line(number: usize, address: usize, end_sequence: bool) -> Line {
+        Line { file: "main.c".to_string(), number, address, is_stmt: true, column: 0, end_sequence }
+    }
+
+    /// A file with line rows, one of its sequences ending at the top of the address space
+    fn with_lines() -> DwarfData {
+        let mut main = file("main.c", vec![function("main", 0x1000)]);
+        main.lines = vec![
+            line(1, 0x1000, false),
+            line(2, 0x1010, false),
+            line(3, 0x1020, true),
+            line(10, usize::MAX - 0x10, false),
+            line(10, usize::MAX, true),
+        ];
+        DwarfData::from_files(vec![main])
+    }
+
+    /// # brief
+    /// Generate pseudo-random numbers (xorshift64), the same ones at every run.
+    fn random_addresses(count: usize) -> impl Iterator<Item = u64> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..count).map(move |_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+    }
+
+    #[test]
+    fn any_address_can_be_looked_up() {
+        let data = with_lines();
+        let edges = [0, 1, 0x1000, 0x1020, 1 << 63, (1 << 63) + 0x1000, u64::MAX - 0x10, u64::MAX - 1, u64::MAX];
+        let addresses: Vec<u64> = edges.iter().copied().chain(random_addresses(10_000)).collect();
+        assert!(addresses.iter().filter(|addr| *addr >> 63 == 1).count() > 1000);
+        // twice, the second time from the caches
+        for addr in addresses.iter().chain(addresses.iter()) {
+            assert!(data.get_line_from_addr(*addr).is_ok(), "line at {:#x}", addr);
+            assert!(data.get_function_from_addr(*addr).is_ok(), "function at {:#x}", addr);
+        }
+    }
+
+    #[test]
+    fn no_line_past_the_end_of_a_sequence() {
+        let data = with_lines();
+        assert_eq!(data.get_line_from_addr(0x1020).unwrap(), None);
+        assert_eq!(data.get_line_from_addr(u64::MAX).unwrap(), None);
+        assert_eq!(data.get_line_from_addr(0x0fff).unwrap(), None);
+    }
+
+    fn names(found: &[(&File, &Function)]) -> Vec<String> {
+        found.iter().map(|(file, func)| format!("{}:{}", file.name, func.name)).collect()
+    }
+
+    fn variable(name: &str, location: Location, is_parameter: bool) -> Variable {
+        Variable { name: name.to_string(), entity_type: Type::new("int".to_string(), 4), location, line_number: 3, is_parameter }
+    }
+
+    /// Two files each with a static `helper` and a `count`, global in one and local in the other
+    fn ambiguous() -> DwarfData {
+        let mut main = file("main.c", vec![function("main", 0x1000), function("helper", 0x1040)]);
+        main.global_variables = vec![variable("count", Location::Address(0x4010), false)];
+        main.functions[0].variables = vec![variable("argc", Location::FramePointerOffset(-20), true)];
+        let mut util = file("util.c", vec![function("helper", 0x2000)]);
+        util.functions[0].variables = vec![variable("count", Location::FramePointerOffset(-4), false)];
+        DwarfData::from_files(vec![main, util])
+    }
+
+    #[test]
+    fn find_function_returns_the_record() {
+        let data = ambiguous();
+        let found = data.find_function("main");
+        assert_eq!(names(&found), ["main.c:main"]);
+        let (_, main) = found[0];
+        assert_eq!((main.address, main.text_length, main.line_number), (0x1000, 0x20, 1));
+        assert_eq!(main.variables[0].name, "argc");
+    }
+
+    #[test]
+    fn find_function_lists_a_name_of_two_files() {
+        let data = ambiguous();
+        let found = data.find_function("helper");
+        assert_eq!(names(&found), ["main.c:helper", "util.c:helper"]);
+        assert_eq!(found.iter().map(|(_, func)| func.address).collect::<Vec<_>>(), [0x1040, 0x2000]);
+    }
+
+    #[test]
+    fn find_function_of_an_unknown_name_is_empty() {
+        let data = ambiguous();
+        assert!(data.find_function("nothing").is_empty());
+        assert!(data.find_function("help").is_empty());
+    }
+
+    #[test]
+    fn find_variable_lists_globals_before_locals() {
+        let data = ambiguous();
+        let found = data.find_variable("count");
+        let found: Vec<(&str, Option<&str>, String)> = found
+            .iter()
+            .map(|(file, func, var)| (file.name.as_str(), func.map(|func| func.name.as_str()), var.location.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [("main.c", None, "Address(0x4010)".to_string()), ("util.c", Some("helper"), "FramePointerOffset(-4)".to_string())]
+        );
+    }
+
+    #[test]
+    fn find_variable_finds_a_parameter() {
+        let data = ambiguous();
+        let found = data.find_variable("argc");
+        assert_eq!(found.len(), 1);
+        let (file, func, var) = found[0];
+        assert_eq!((file.name.as_str(), func.map(|func| func.name.as_str())), ("main.c", Some("main")));
+        assert!(var.is_parameter);
+        assert!(data.find_variable("argv").is_empty());
+    }
+
+    #[test]
+    fn functions_matching_scans_every_file() {
+        let data = two_files();
+        let cancel = CancelToken::new();
+        let (found, interrupted) = data.functions_matching(|name| name.starts_with("parse"), &cancel);
+        assert_eq!(names(&found), ["main.c:parse_args", "util.c:parse_line"]);
+        assert!(!interrupted);
+    }
+
+    #[test]
+    fn functions_matching_stops_at_a_tripped_token() {
+        let data = two_files();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let (found, interrupted) = data.functions_matching(|_| true, &cancel);
+        assert!(found.is_empty());
+        assert!(interrupted);
+    }
+
+    #[test]
+    fn functions_matching_keeps_the_matches_found_before_the_cancel() {
+        let data = two_files();
+        let cancel = CancelToken::new();
+        // cancelled while the first file is scanned, as by Ctrl+C
+        let (found, interrupted) = data.functions_matching(
+            |name| {
+                cancel.cancel();
+                name.starts_with("parse")
+            },
+            &cancel,
+        );
+        assert_eq!(names(&found), ["main.c:parse_args"]);
+        assert!(interrupted);
+    }
+
+    /// Rust generics, C++ overloads and the copies the linker made of them
+    fn instances() -> DwarfData {
+        DwarfData::from_files(vec![
+            file("lib.rs", vec![function("process<i32>", 0x1000), function("process<&str>", 0x1040), function("processor", 0x1080)]),
+            file("shape.cpp", vec![function("area", 0x2000), function("area", 0x2040), function("inline_max<int>", 0x2080)]),
+            // the same inline instance emitted in another unit, and a copy the linker discarded
+            file("other.cpp", vec![function("inline_max<int>", 0x2080), function("area", 0)]),
+        ])
+    }
+
+    #[test]
+    fn functions_named_lists_every_generic_instance() {
+        let data = instances();
+        assert_eq!(names(&data.functions_named("process")), ["lib.rs:process<i32>", "lib.rs:process<&str>"]);
+        // generic arguments select one instance
+        assert_eq!(names(&data.functions_named("process<&str>")), ["lib.rs:process<&str>"]);
+        assert_eq!(names(&data.functions_named("processor")), ["lib.rs:processor"]);
+    }
+
+    #[test]
+    fn functions_named_lists_every_overload() {
+        let data = instances();
+        let found = data.functions_named("area");
+        assert_eq!(names(&found), ["shape.cpp:area", "shape.cpp:area"]);
+        assert_eq!(found.iter().map(|(_, func)| func.address).collect::<Vec<_>>(), [0x2000, 0x2040]);
+    }
+
+    #[test]
+    fn functions_named_counts_a_merged_copy_once() {
+        let data = instances();
+        assert_eq!(names(&data.functions_named("inline_max")), ["shape.cpp:inline_max<int>"]);
+        assert!(data.functions_named("proc").is_empty());
+    }
+
+    #[test]
+    fn read_static_reads_the_file_contents_of_the_section() {
+        let path = std::env::temp_dir().join(format!("deet-read-static-{}", std::process::id()));
+        let mut contents = vec![0xffu8; 0x20];
+        contents[0x14..0x18].copy_from_slice(&42u32.to_le_bytes());
+        fs::write(&path, &contents).unwrap();
+        let mut data = DwarfData::from_files(Vec::new());
+        data.path = path.to_string_lossy().into_owned();
+        data.symbol_table = SymbolTable::from_sections(vec![(".data", 0x4000..0x4010, Some(0x10)), (".bss", 0x4010..0x4100, None)]);
+
+        assert_eq!(data.read_static(0x4004, 4), Some(42u32.to_le_bytes().to_vec()));
+        assert_eq!(data.read_static(0x4020, 4), Some(vec![0; 4]));
+        assert_eq!(data.read_static(0x5000, 4), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
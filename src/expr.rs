@@ -0,0 +1,459 @@
+/// An expression of the command language, as used by `print`, `x` and `set $var = ...`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(u64),
+    /// `$name`: a register, a convenience variable or a value history entry
+    Variable(String),
+    /// `name`: the value of a source variable, or the address of a function
+    Symbol(String),
+    /// `&name`: the address of a source variable or function
+    AddressOf(String),
+    Negate(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    /// Whether the operator compares its operands, yielding 1 or 0
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge)
+    }
+}
+
+/// Provides the values of the names used in an expression.
+pub trait Scope {
+    /// # brief
+    /// Look up the value of a `$name` variable.
+    ///
+    /// # param
+    /// - `name` : The variable name without the leading `$`.
+    ///
+    /// # return
+    /// * `Some(u64)` - the value, or `None` if the variable is not defined.
+    fn variable(&self, name: &str) -> Option<u64>;
+
+    /// # brief
+    /// Look up the value of a source variable, or the address of a function.
+    ///
+    /// # param
+    /// - `name` : The name as written in the expression.
+    ///
+    /// # return
+    /// * `Ok(u64)` - the value, or `Err(String)` if there is no such symbol or it can't be read.
+    fn symbol(&self, name: &str) -> Result<u64, String> {
+        Err(format!("No symbol \"{}\" in current context.", name))
+    }
+
+    /// # brief
+    /// Look up the address of a source variable or a function, for `&name`.
+    fn address_of(&self, name: &str) -> Result<u64, String> {
+        Err(format!("No symbol \"{}\" in current context.", name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(u64),
+    Variable(String),
+    Ident(String),
+    Op(char),
+    Compare(BinOp),
+    LParen,
+    RParen,
+}
+
+/// # brief
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+pub fn parse_number(text: &str) -> Result<u64, String> {
+    let lower = text.to_lowercase();
+    let value = match lower.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => lower.parse::<u64>(),
+    };
+    value.or(Err(format!("Invalid number \"{}\"", text)))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Number(parse_number(&text)?));
+        } else if c == '$' {
+            i += 1;
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            // `$` alone and `$$` refer to the value history
+            if i == start && i < chars.len() && chars[i] == '$' {
+                i += 1;
+            }
+            tokens.push(Token::Variable(chars[start..i].iter().collect()));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "=!<>".contains(c) {
+            let next = chars.get(i + 1).copied();
+            let (op, len) = match (c, next) {
+                ('=', Some('=')) => (BinOp::Eq, 2),
+                ('!', Some('=')) => (BinOp::Ne, 2),
+                ('<', Some('=')) => (BinOp::Le, 2),
+                ('>', Some('=')) => (BinOp::Ge, 2),
+                ('<', _) => (BinOp::Lt, 1),
+                ('>', _) => (BinOp::Gt, 1),
+                _ => return Err(format!("Invalid character '{}' in expression", c)),
+            };
+            tokens.push(Token::Compare(op));
+            i += len;
+        } else if "+-*/%&".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else {
+            return Err(format!("Invalid character '{}' in expression", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens of one expression.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // comparison := additive (('==' | '!=' | '<' | '<=' | '>' | '>=') additive)?
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.additive()?;
+        if let Some(Token::Compare(op)) = self.peek().cloned() {
+            self.next();
+            let rhs = self.additive()?;
+            return Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    // additive := term (('+' | '-') term)*
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.term()?;
+            let op = if op == '+' { BinOp::Add } else { BinOp::Sub };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.unary()?;
+            let op = match op {
+                '*' => BinOp::Mul,
+                '/' => BinOp::Div,
+                _ => BinOp::Rem,
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // unary := '-' unary | '&' identifier | primary
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.next();
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some(Token::Op('&')) => {
+                self.next();
+                match self.next() {
+                    Some(Token::Ident(name)) => Ok(Expr::AddressOf(name)),
+                    _ => Err("Attempt to take address of value not located in memory.".to_string()),
+                }
+            }
+            _ => self.primary(),
+        }
+    }
+
+    // primary := number | variable | identifier | '(' comparison ')'
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Variable(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Ident(name)) => Ok(Expr::Symbol(name)),
+            Some(Token::LParen) => {
+                let expr = self.comparison()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err("Missing ')' in expression".to_string()),
+                }
+            }
+            Some(token) => Err(format!("Syntax error in expression near {:?}", token)),
+            None => Err("Incomplete expression".to_string()),
+        }
+    }
+}
+
+/// # brief
+/// Parses an expression.
+///
+/// # return
+/// * `Ok(Expr)` - the parsed expression, or `Err(String)` describing the syntax error.
+pub fn parse(input: &str) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err("Argument required (expression to compute).".to_string());
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.comparison()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("Syntax error in expression near {:?}", token));
+    }
+    Ok(expr)
+}
+
+/// # brief
+/// Evaluates an expression. Arithmetic wraps around like unsigned 64-bit integers, and
+/// comparisons are unsigned, yielding 1 when true and 0 when false.
+///
+/// # param
+/// - `expr` : The expression to evaluate.
+/// - `scope` : Provides the values of the variables used in the expression.
+pub fn evaluate(expr: &Expr, scope: &dyn Scope) -> Result<u64, String> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        Expr::Variable(name) => scope
+            .variable(name)
+            .ok_or(format!("Convenience variable ${} is void", name)),
+        Expr::Symbol(name) => scope.symbol(name),
+        Expr::AddressOf(name) => scope.address_of(name),
+        Expr::Negate(inner) => Ok(evaluate(inner, scope)?.wrapping_neg()),
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = evaluate(lhs, scope)?;
+            let rhs = evaluate(rhs, scope)?;
+            match op {
+                BinOp::Add => Ok(lhs.wrapping_add(rhs)),
+                BinOp::Sub => Ok(lhs.wrapping_sub(rhs)),
+                BinOp::Mul => Ok(lhs.wrapping_mul(rhs)),
+                BinOp::Div => lhs.checked_div(rhs).ok_or("Division by zero".to_string()),
+                BinOp::Rem => lhs.checked_rem(rhs).ok_or("Division by zero".to_string()),
+                BinOp::Eq => Ok((lhs == rhs) as u64),
+                BinOp::Ne => Ok((lhs != rhs) as u64),
+                BinOp::Lt => Ok((lhs < rhs) as u64),
+                BinOp::Le => Ok((lhs <= rhs) as u64),
+                BinOp::Gt => Ok((lhs > rhs) as u64),
+                BinOp::Ge => Ok((lhs >= rhs) as u64),
+            }
+        }
+    }
+}
+
+/// # brief
+/// Parses and evaluates an expression.
+pub fn eval(input: &str, scope: &dyn Scope) -> Result<u64, String> {
+    evaluate(&parse(input)?, scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A program stopped with a few registers, convenience variables and source symbols
+    struct Stopped {
+        variables: HashMap<&'static str, u64>,
+        symbols: HashMap<&'static str, (u64, u64)>,
+    }
+
+    impl Scope for Stopped {
+        fn variable(&self, name: &str) -> Option<u64> {
+            self.variables.get(name).copied()
+        }
+
+        fn symbol(&self, name: &str) -> Result<u64, String> {
+            match self.symbols.get(name) {
+                Some((value, _)) => Ok(*value),
+                None => Err(format!("No symbol \"{}\" in current context.", name)),
+            }
+        }
+
+        fn address_of(&self, name: &str) -> Result<u64, String> {
+            match self.symbols.get(name) {
+                Some((_, addr)) => Ok(*addr),
+                None => Err(format!("No symbol \"{}\" in current context.", name)),
+            }
+        }
+    }
+
+    fn stopped() -> Stopped {
+        let variables = [("rip", 0x401136), ("rsp", 0x7ffe_0000), ("count", 3), ("1", 42), ("", 42)];
+        let symbols = [("argc", (2, 0x7ffe_0010)), ("main", (0x401126, 0x401126))];
+        Stopped { variables: variables.iter().copied().collect(), symbols: symbols.iter().copied().collect() }
+    }
+
+    /// A scope without any name, where symbols fall back to the defaults of `Scope`
+    struct Empty;
+
+    impl Scope for Empty {
+        fn variable(&self, _: &str) -> Option<u64> {
+            None
+        }
+    }
+
+    /// # brief
+    /// Evaluate `input` in the stopped program, failing the test on an error.
+    fn value(input: &str) -> u64 {
+        eval(input, &stopped()).unwrap_or_else(|err| panic!("{}: {}", input, err))
+    }
+
+    /// # brief
+    /// Get the error of evaluating `input` in the stopped program.
+    fn error(input: &str) -> String {
+        eval(input, &stopped()).unwrap_err()
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(value("42"), 42);
+        assert_eq!(value("0x2A"), 42);
+        assert_eq!(value("0"), 0);
+        assert_eq!(value("18446744073709551615"), u64::MAX);
+        assert_eq!(parse_number("0xffffffffffffffff"), Ok(u64::MAX));
+        assert_eq!(parse_number("12ab"), Err("Invalid number \"12ab\"".to_string()));
+    }
+
+    #[test]
+    fn symbols_and_addresses() {
+        assert_eq!(value("argc"), 2);
+        assert_eq!(value("&argc"), 0x7ffe_0010);
+        assert_eq!(value("main + 4"), 0x40112a);
+        assert_eq!(parse("&main").unwrap(), Expr::AddressOf("main".to_string()));
+    }
+
+    #[test]
+    fn registers_and_convenience_variables() {
+        assert_eq!(value("$rip"), 0x401136);
+        assert_eq!(value("$rsp - 8"), 0x7ffd_fff8);
+        assert_eq!(value("$count * 2"), 6);
+        // the value history
+        assert_eq!(value("$1"), 42);
+        assert_eq!(value("$"), 42);
+        assert_eq!(parse("$$").unwrap(), Expr::Variable("$".to_string()));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3"), 7);
+        assert_eq!(value("(1 + 2) * 3"), 9);
+        assert_eq!(value("10 - 4 - 3"), 3);
+        assert_eq!(value("20 / 2 / 5"), 2);
+        assert_eq!(value("7 % 4 * 2"), 6);
+        assert_eq!(value("-2 * 3 + 7"), 1);
+        assert_eq!(value("1 + 2 == 3"), 1);
+        assert_eq!(value("2 * 3 < 5"), 0);
+    }
+
+    #[test]
+    fn parse_tree() {
+        let number = |value| Box::new(Expr::Number(value));
+        assert_eq!(
+            parse("1 + 2 * 3").unwrap(),
+            Expr::Binary(BinOp::Add, number(1), Box::new(Expr::Binary(BinOp::Mul, number(2), number(3))))
+        );
+        assert_eq!(parse("-x").unwrap(), Expr::Negate(Box::new(Expr::Symbol("x".to_string()))));
+    }
+
+    #[test]
+    fn arithmetic_wraps_and_compares_unsigned() {
+        assert_eq!(value("0 - 1"), u64::MAX);
+        assert_eq!(value("-1"), u64::MAX);
+        assert_eq!(value("0xffffffffffffffff + 2"), 1);
+        assert_eq!(value("-1 > 0"), 1);
+    }
+
+    #[test]
+    fn comparisons() {
+        let cases = [("3 == 3", 1), ("3 != 3", 0), ("2 < 3", 1), ("3 <= 3", 1), ("4 > 3", 1), ("3 >= 4", 0)];
+        for (input, expected) in cases.iter() {
+            assert_eq!(value(input), *expected, "{}", input);
+        }
+        assert!(BinOp::Le.is_comparison());
+        assert!(!BinOp::Rem.is_comparison());
+    }
+
+    #[test]
+    fn error_messages() {
+        let cases = [
+            ("", "Argument required (expression to compute)."),
+            ("1 +", "Incomplete expression"),
+            ("(1 + 2", "Missing ')' in expression"),
+            ("1 2", "Syntax error in expression near Number(2)"),
+            ("1 + )", "Syntax error in expression near RParen"),
+            ("1 = 2", "Invalid character '=' in expression"),
+            ("a.b", "Invalid character '.' in expression"),
+            ("&3", "Attempt to take address of value not located in memory."),
+            ("0x", "Invalid number \"0x\""),
+            ("1 / 0", "Division by zero"),
+            ("1 % (2 - 2)", "Division by zero"),
+            ("$nothing", "Convenience variable $nothing is void"),
+            ("nothing", "No symbol \"nothing\" in current context."),
+        ];
+        for (input, message) in cases.iter() {
+            assert_eq!(error(input), *message, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn scope_without_symbols() {
+        assert_eq!(eval("argc", &Empty), Err("No symbol \"argc\" in current context.".to_string()));
+        assert_eq!(eval("&argc", &Empty), Err("No symbol \"argc\" in current context.".to_string()));
+        assert_eq!(eval("1 + 1", &Empty), Ok(2));
+    }
+}
@@ -0,0 +1,148 @@
+//! Programs for the tests that trace a real process, built with the C compiler of the system
+//! (the linker of rustc) when a test first needs them.
+
+use crate::core_pattern::{self, CoreDestination};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the `.text` section of the programs built by `assemble` starts, alone in its page
+pub const TEXT: usize = 0x401000;
+
+/// The linker script of `assemble`: only `.text`, at a known address and with nothing mapped
+/// after its page
+const LINKER_SCRIPT: &str = "ENTRY(_start)
+SECTIONS
+{
+  . = 0x401000;
+  .text : { *(.text) }
+}
+";
+
+/// # brief
+/// Build a program from its sources, in a directory of its own under the temporary directory.
+///
+/// # param
+/// - `name` : The name of the program, unique among the fixtures.
+/// - `sources` : The name of each file to write next to the program and its text.
+/// - `flags` : The arguments of `cc`, which runs in that directory.
+///
+/// # return
+/// * The path of the program.
+pub fn build(name: &str, sources: &[(&str, &str)], flags: &[&str]) -> String {
+    let dir: PathBuf = std::env::temp_dir().join(format!("deet-fixtures-{}", std::process::id())).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, text) in sources {
+        std::fs::write(dir.join(file), text).unwrap();
+    }
+    let output = Command::new("cc")
+        .current_dir(&dir)
+        .args(flags)
+        .args(["-o", name])
+        .output()
+        .expect("cc is needed to build the fixtures");
+    assert!(output.status.success(), "could not build {}: {}", name, String::from_utf8_lossy(&output.stderr));
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+/// # brief
+/// Build a static program without libc from `code`, AT&T assembly whose `.text` section
+/// starts at `TEXT` with `_start`, so the addresses of its instructions are known.
+///
+/// # return
+/// * The path of the program.
+pub fn assemble(name: &str, code: &str) -> String {
+    let code = format!("    .text\n    .globl _start\n_start:\n{}", code);
+    build(
+        name,
+        &[("main.s", &code), ("link.ld", LINKER_SCRIPT)],
+        &["-nostdlib", "-static", "-no-pie", "-Wl,-T,link.ld", "-Wl,--build-id=none", "main.s"],
+    )
+}
+
+/// # brief
+/// Run a program that crashes, with the core size limit raised, in its own directory.
+///
+/// # return
+/// * The path of its core dump, `None` if the kernel kept it elsewhere, e.g. piped to
+///   systemd-coredump, which the tests can't count on.
+pub fn dump_core(program: &str) -> Option<String> {
+    let dir = Path::new(program).parent().unwrap().to_path_buf();
+    let mut command = Command::new(program);
+    command.current_dir(&dir);
+    unsafe {
+        command.pre_exec(|| {
+            let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+            libc::getrlimit(libc::RLIMIT_CORE, &mut limit);
+            limit.rlim_cur = limit.rlim_max;
+            libc::setrlimit(libc::RLIMIT_CORE, &limit);
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    let pid = nix::unistd::Pid::from_raw(child.id() as i32);
+    let status = child.wait().unwrap();
+    assert!(status.signal().is_some(), "{} did not crash", program);
+    match core_pattern::core_destination(pid, program) {
+        CoreDestination::File(path) if status.core_dumped() => {
+            let path = dir.join(path);
+            path.exists().then(|| path.to_string_lossy().into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// A program that keeps a marker word in its frame and writes to a null pointer
+pub const CRASH: &str = "int main(void) {
+    volatile unsigned long marker = 0x1122334455667788;
+    *(volatile int *)0 = 0;
+    return marker != 0;
+}
+";
+
+/// Exit with status 7, in 12 bytes
+pub const EXIT_7: &str = "    mov $60, %eax\n    mov $7, %edi\n    syscall\n";
+
+/// Two threads created with `clone` that wait for the main thread to let them go, then run
+/// the `nop` at `TEXT + 0x20`, count themselves and exit. The main thread exits
+/// with status 7 once both are done.
+pub const THREADS: &str = "    jmp main
+    .org 0x10, 0x90
+worker:
+    cmpq $0, (%rbx)
+    je worker
+    .org 0x20, 0x90
+    nop
+    lock incq 8(%rbx)
+    mov $60, %eax
+    xor %edi, %edi
+    syscall
+main:
+    sub $0x100, %rsp
+    mov %rsp, %rbx
+    movq $0, (%rbx)
+    movq $0, 8(%rbx)
+    lea -0x1000(%rsp), %r12
+    call spawn
+    lea -0x2000(%rsp), %r12
+    call spawn
+    movq $1, (%rbx)
+wait:
+    cmpq $2, 8(%rbx)
+    jne wait
+    mov $231, %eax
+    mov $7, %edi
+    syscall
+spawn:
+    mov $56, %eax
+    # CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD | CLONE_SYSVSEM
+    mov $0x50f00, %edi
+    mov %r12, %rsi
+    xor %edx, %edx
+    xor %r10d, %r10d
+    xor %r8d, %r8d
+    syscall
+    test %rax, %rax
+    jz worker
+    ret
+";
@@ -13,23 +13,30 @@ use std::borrow;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::Write;
+use std::time::Instant;
 use std::{io, path};
 
-use crate::dwarf_data::{File, Function, Line, Location, Type, Variable};
+use crate::dwarf_data::{
+    File, FrameBase, Function, Line, LoadStats, Location, Member, Type, TypeDefinition, TypeKind, Variable,
+};
 
-pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<Vec<File>, Error> {
-    // Load a section and return as `Cow<[u8]>`.
-    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>, gimli::Error> {
-        Ok(object
-            .section_data_by_name(id.name())
-            .unwrap_or(borrow::Cow::Borrowed(&[][..])))
-    };
-    // Load a supplementary section. We don't have a supplementary object file,
-    // so always return an empty slice.
-    let load_section_sup = |_| Ok(borrow::Cow::Borrowed(&[][..]));
+/// The files loaded, the sorted addresses of their rows, and the units left out with their
+/// names and errors
+type Units = (Vec<File>, Vec<usize>, Vec<(String, Error)>);
 
-    // Load all of the sections.
-    let dwarf_cow = gimli::Dwarf::load(&load_section, &load_section_sup)?;
+/// Extract the files of `object`, calling `progress` with the number of compilation units done
+/// and their total after each unit. The addresses of all rows of the line programs, including
+/// the rows of files other than the compilation units, are returned sorted along with the files.
+/// The compilation units that could not be decoded are left out, and returned with their names
+/// and errors; only sections that can't be read at all fail the whole load. The time spent in
+/// each phase is recorded in `stats`.
+pub fn load_file(
+    object: &object::File,
+    endian: gimli::RunTimeEndian,
+    progress: &dyn Fn(usize, usize),
+    stats: &mut LoadStats,
+) -> Result<Units, Error> {
+    let dwarf_cow = load_dwarf(object)?;
 
     // Borrow a `Cow<[u8]>` to create an `EndianSlice`.
     let borrow_section: &dyn for<'a> Fn(
@@ -40,42 +47,133 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
     // Create `EndianSlice`s for all of the sections.
     let dwarf = dwarf_cow.borrow(&borrow_section);
 
-    // Define a mapping from type offsets to type structs
-    let mut offset_to_type: HashMap<usize, Type> = HashMap::new();
+    // Collect the types first, so that variables can refer to types defined after them
+    let types_start = Instant::now();
+    let (offset_to_type, type_wrappers, unit_count) = collect_types(&dwarf);
+    stats.type_pass = types_start.elapsed();
 
-    let mut compilation_units: Vec<File> = Vec::new();
-
-    // Iterate over the compilation units.
+    // Iterate over the compilation units
     let mut iter = dwarf.units();
-    while let Some(header) = iter.next()? {
+    let next = || iter.next().map(|header| header.map(|header| (header.offset().0, header))).map_err(Error::from);
+    let load = |header, compilation_units: &mut Vec<File>, row_addresses: &mut Vec<usize>, stats: &mut LoadStats| {
         let unit = dwarf.unit(header)?;
+        load_unit(&dwarf, &unit, &offset_to_type, &type_wrappers, compilation_units, row_addresses, stats)
+    };
+    let (mut compilation_units, mut row_addresses, unit_errors) = load_units(next, load, &|done| progress(done, unit_count), stats);
 
-        // Iterate over the Debugging Information Entries (DIEs) in the unit.
-        let mut depth = 0;
+    // Sequences are not emitted in address order, sort the rows for range queries. A sequence
+    // starting where another one ends is sorted after that end-of-sequence row.
+    let sort_start = Instant::now();
+    for file in compilation_units.iter_mut() {
+        file.lines.sort_by_key(|line| (line.address, !line.end_sequence));
+        file.included_lines.sort_by_key(|line| (line.address, !line.end_sequence));
+    }
+    row_addresses.sort_unstable();
+    row_addresses.dedup();
+    stats.line_programs += sort_start.elapsed();
+    stats.line_rows = row_addresses.len();
+    Ok((compilation_units, row_addresses, unit_errors))
+}
+
+/// # brief
+/// Load the compilation units one after the other. A unit that can't be decoded is left out
+/// along with what it added, and the others are still loaded; a unit whose header can't be
+/// read ends the walk, since the units after it can't be found.
+///
+/// # param
+/// - `next` : The offset in `.debug_info` and the header of the next unit, `None` after the
+/// last one.
+/// - `load` : Decode a unit, adding its files and the addresses of its rows.
+/// - `progress` : Called with the number of units done after each one.
+///
+/// # return
+/// * The files and rows of the units loaded, and the name and error of each unit left out.
+fn load_units<H>(
+    mut next: impl FnMut() -> Result<Option<(usize, H)>, Error>,
+    mut load: impl FnMut(H, &mut Vec<File>, &mut Vec<usize>, &mut LoadStats) -> Result<(), Error>,
+    progress: &dyn Fn(usize),
+    stats: &mut LoadStats,
+) -> Units {
+    let mut compilation_units: Vec<File> = Vec::new();
+    let mut row_addresses: Vec<usize> = Vec::new();
+    let mut units_done = 0;
+    let mut unit_errors = Vec::new();
+    loop {
+        let (offset, header) = match next() {
+            Ok(Some(unit)) => unit,
+            Ok(None) => break,
+            // the length of the unit is unknown, so are the units after it
+            Err(err) => {
+                unit_errors.push(("<the units after the last one read>".to_string(), err));
+                break;
+            }
+        };
+        let unit_start = Instant::now();
+        let (files_before, rows_before) = (compilation_units.len(), row_addresses.len());
+        if let Err(err) = load(header, &mut compilation_units, &mut row_addresses, stats) {
+            let name = compilation_units
+                .get(files_before)
+                .map_or(format!("<unit at .debug_info+{:#x}>", offset), |file| file.name.clone());
+            compilation_units.truncate(files_before);
+            row_addresses.truncate(rows_before);
+            unit_errors.push((name, err));
+        }
+        let unit_time = unit_start.elapsed();
+        if stats.slowest_unit.as_ref().map_or(true, |(_, slowest)| unit_time > *slowest) {
+            let name = compilation_units.last().map_or("<unknown>".to_string(), |file| file.name.clone());
+            stats.slowest_unit = Some((name, unit_time));
+        }
+        units_done += 1;
+        progress(units_done);
+    }
+    stats.units = units_done;
+    (compilation_units, row_addresses, unit_errors)
+}
+
+/// The sections of the executable, as read by `load_file`
+type Slice<'a> = gimli::EndianSlice<'a, gimli::RunTimeEndian>;
+
+/// Load the DWARF sections of `object`, missing sections as empty ones.
+fn load_dwarf<'data>(object: &object::File<'data>) -> Result<gimli::Dwarf<borrow::Cow<'data, [u8]>>, gimli::Error> {
+    // Load a section and return as `Cow<[u8]>`.
+    let load_section = |id: gimli::SectionId| -> Result<borrow::Cow<[u8]>, gimli::Error> {
+        Ok(object
+            .section_data_by_name(id.name())
+            .unwrap_or(borrow::Cow::Borrowed(&[][..])))
+    };
+    // Load a supplementary section. We don't have a supplementary object file,
+    // so always return an empty slice.
+    let load_section_sup = |_| Ok(borrow::Cow::Borrowed(&[][..]));
+
+    // Load all of the sections.
+    gimli::Dwarf::load(&load_section, &load_section_sup)
+}
+
+/// Map the offsets of the type DIEs to the types they describe, with the pointer, const and
+/// typedef types apart, named after the type they refer to once it is known. The number of
+/// compilation units is returned along with them.
+fn collect_types(dwarf: &gimli::Dwarf<Slice>) -> (HashMap<usize, Type>, HashMap<usize, TypeWrapper>, usize) {
+    // Define a mapping from type offsets to type structs
+    let mut offset_to_type: HashMap<usize, Type> = HashMap::new();
+    // Pointer, const and typedef types, named after the type they refer to once it is known
+    let mut type_wrappers: HashMap<usize, TypeWrapper> = HashMap::new();
+    let mut unit_count = 0;
+    let mut iter = dwarf.units();
+    // a unit that can't be decoded is skipped here, the second pass reports it
+    while let Ok(Some(header)) = iter.next() {
+        unit_count += 1;
+        let unit = match dwarf.unit(header) {
+            Ok(unit) => unit,
+            Err(_) => continue,
+        };
         let mut entries = unit.entries();
-        while let Some((delta_depth, entry)) = entries.next_dfs()? {
-            depth += delta_depth;
-            // Update the offset_to_type mapping for types
-            // Update the variable list for formal params/variables
+        while let Ok(Some((_, entry))) = entries.next_dfs() {
             match entry.tag() {
-                gimli::DW_TAG_compile_unit => {
-                    let name = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_name) {
-                        if let Ok(DebugValue::Str(name)) = get_attr_value(&attr, &unit, &dwarf) {
-                            name
-                        } else {
-                            "<unknown>".to_string()
-                        }
-                    } else {
-                        "<unknown>".to_string()
-                    };
-                    compilation_units.push(File {
-                        name,
-                        global_variables: Vec::new(),
-                        functions: Vec::new(),
-                        lines: Vec::new(),
-                    });
-                }
-                gimli::DW_TAG_base_type => {
+                gimli::DW_TAG_base_type
+                | gimli::DW_TAG_structure_type
+                | gimli::DW_TAG_class_type
+                | gimli::DW_TAG_union_type
+                | gimli::DW_TAG_enumeration_type => {
                     let name = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_name) {
                         if let Ok(DebugValue::Str(name)) = get_attr_value(&attr, &unit, &dwarf) {
                             name
@@ -98,150 +196,464 @@ pub fn load_file(object: &object::File, endian: gimli::RunTimeEndian) -> Result<
                         // TODO: report error?
                         0
                     };
-                    let type_offset = entry.offset().0;
+                    let type_offset = section_offset(entry.offset(), &unit);
                     offset_to_type
                         .insert(type_offset, Type::new(name, byte_size.try_into().unwrap()));
                 }
-                gimli::DW_TAG_subprogram => {
-                    let mut func: Function = Default::default();
-                    let mut attrs = entry.attrs();
-                    while let Some(attr) = attrs.next()? {
-                        let val = get_attr_value(&attr, &unit, &dwarf);
-                        //println!("   {}: {:?}", attr.name(), val);
-                        match attr.name() {
-                            gimli::DW_AT_name => {
-                                if let Ok(DebugValue::Str(name)) = val {
-                                    func.name = name;
-                                }
+                gimli::DW_TAG_pointer_type | gimli::DW_TAG_const_type | gimli::DW_TAG_typedef => {
+                    let target = match entry.attr(gimli::DW_AT_type) {
+                        Ok(Some(attr)) => match get_attr_value(&attr, &unit, &dwarf) {
+                            Ok(DebugValue::Size(offset)) => Some(offset),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    let name = match entry.attr(gimli::DW_AT_name) {
+                        Ok(Some(attr)) => match get_attr_value(&attr, &unit, &dwarf) {
+                            Ok(DebugValue::Str(name)) => Some(name),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    let wrapper = TypeWrapper { tag: entry.tag(), name, target };
+                    type_wrappers.insert(section_offset(entry.offset(), &unit), wrapper);
+                }
+                _ => {}
+            }
+        }
+    }
+    (offset_to_type, type_wrappers, unit_count)
+}
+
+/// Extract the struct, class, union, enum and typedef definitions of `object`, for `info types`
+/// and `ptype`. Declarations without a definition are left out, so are the units that can't be
+/// decoded. Only the direct members of a type are recorded, a nested type is a definition of
+/// its own.
+pub fn load_types(object: &object::File, endian: gimli::RunTimeEndian) -> Result<Vec<TypeDefinition>, Error> {
+    let dwarf_cow = load_dwarf(object)?;
+    let borrow_section: &dyn for<'a> Fn(
+        &'a borrow::Cow<[u8]>,
+    ) -> gimli::EndianSlice<'a, gimli::RunTimeEndian> =
+        &|section| gimli::EndianSlice::new(&*section, endian);
+    let dwarf = dwarf_cow.borrow(&borrow_section);
+    let (offset_to_type, type_wrappers, _) = collect_types(&dwarf);
+    let resolve = |offset: Option<usize>| {
+        offset
+            .and_then(|offset| resolve_type(offset, &offset_to_type, &type_wrappers, 0))
+            .unwrap_or_else(|| Type::new("<unknown>".to_string(), 0))
+    };
+
+    let mut definitions: Vec<TypeDefinition> = Vec::new();
+    let mut iter = dwarf.units();
+    while let Ok(Some(header)) = iter.next() {
+        let unit = match dwarf.unit(header) {
+            Ok(unit) => unit,
+            Err(_) => continue,
+        };
+        let unit_name = match unit.name {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => "<unknown>".to_string(),
+        };
+        // the definitions enclosing the current DIE, by depth
+        let mut parents: Vec<(isize, usize)> = Vec::new();
+        let mut depth = 0;
+        let mut entries = unit.entries();
+        while let Ok(Some((delta, entry))) = entries.next_dfs() {
+            depth += delta;
+            while parents.last().map_or(false, |(parent_depth, _)| *parent_depth >= depth) {
+                parents.pop();
+            }
+            // the member of a type is a child of its DIE
+            let parent = parents.last().filter(|(parent_depth, _)| *parent_depth + 1 == depth).map(|(_, index)| *index);
+            let kind = match entry.tag() {
+                gimli::DW_TAG_structure_type => TypeKind::Struct,
+                gimli::DW_TAG_class_type => TypeKind::Class,
+                gimli::DW_TAG_union_type => TypeKind::Union,
+                gimli::DW_TAG_enumeration_type => TypeKind::Enum,
+                gimli::DW_TAG_typedef => TypeKind::Typedef,
+                gimli::DW_TAG_member => {
+                    if let Some(index) = parent {
+                        let bit_offset = attr_udata(entry, gimli::DW_AT_data_bit_offset);
+                        let offset = attr_udata(entry, gimli::DW_AT_data_member_location)
+                            .or(bit_offset.map(|bits| bits / 8))
+                            .unwrap_or(0);
+                        definitions[index].members.push(Member {
+                            name: attr_string(entry, gimli::DW_AT_name, &unit, &dwarf),
+                            offset: offset as usize,
+                            member_type: resolve(attr_reference(entry, gimli::DW_AT_type, &unit, &dwarf)),
+                            bit_size: attr_udata(entry, gimli::DW_AT_bit_size),
+                        });
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_enumerator => {
+                    if let (Some(index), Some(name)) = (parent, attr_string(entry, gimli::DW_AT_name, &unit, &dwarf)) {
+                        let value = match entry.attr(gimli::DW_AT_const_value) {
+                            Ok(Some(attr)) => attr.value().sdata_value().or(attr.value().udata_value().map(|value| value as i64)),
+                            _ => None,
+                        };
+                        definitions[index].enumerators.push((name, value.unwrap_or(0)));
+                    }
+                    continue;
+                }
+                gimli::DW_TAG_variant_part => {
+                    if let Some(index) = parent {
+                        definitions[index].has_variants = true;
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Ok(Some(_)) = entry.attr(gimli::DW_AT_declaration) {
+                continue;
+            }
+            let file = match entry.attr(gimli::DW_AT_decl_file) {
+                Ok(Some(attr)) => match attr.value() {
+                    gimli::AttributeValue::FileIndex(index) => {
+                        let mut path = String::new();
+                        dump_file_index(&mut path, index, &unit, &dwarf)?;
+                        Some(path.trim().to_string()).filter(|path| !path.is_empty())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            let target = match kind {
+                TypeKind::Typedef => Some(resolve(attr_reference(entry, gimli::DW_AT_type, &unit, &dwarf))),
+                _ => None,
+            };
+            definitions.push(TypeDefinition {
+                kind,
+                name: attr_string(entry, gimli::DW_AT_name, &unit, &dwarf),
+                size: match &target {
+                    Some(target) => target.size,
+                    None => attr_udata(entry, gimli::DW_AT_byte_size).unwrap_or(0) as usize,
+                },
+                file: file.unwrap_or_else(|| unit_name.clone()),
+                line_number: attr_udata(entry, gimli::DW_AT_decl_line).unwrap_or(0) as usize,
+                artificial: matches!(entry.attr(gimli::DW_AT_artificial), Ok(Some(_))),
+                members: Vec::new(),
+                enumerators: Vec::new(),
+                target,
+                has_variants: false,
+            });
+            if kind != TypeKind::Typedef {
+                parents.push((depth, definitions.len() - 1));
+            }
+        }
+    }
+    Ok(definitions)
+}
+
+/// # brief
+/// Get a constant attribute of a DIE, `None` if it is absent or not a constant.
+fn attr_udata<R: Reader>(entry: &gimli::DebuggingInformationEntry<R>, name: gimli::DwAt) -> Option<u64> {
+    entry.attr(name).ok()??.value().udata_value()
+}
+
+/// # brief
+/// Get a string attribute of a DIE, such as its name.
+fn attr_string<R: Reader>(
+    entry: &gimli::DebuggingInformationEntry<R>,
+    name: gimli::DwAt,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Option<String> {
+    match get_attr_value(&entry.attr(name).ok()??, unit, dwarf) {
+        Ok(DebugValue::Str(value)) => Some(value),
+        _ => None,
+    }
+}
+
+/// # brief
+/// Get the section offset of the DIE an attribute refers to, such as the type of a member.
+fn attr_reference<R: Reader>(
+    entry: &gimli::DebuggingInformationEntry<R>,
+    name: gimli::DwAt,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Option<usize> {
+    match get_attr_value(&entry.attr(name).ok()??, unit, dwarf) {
+        Ok(DebugValue::Size(offset)) => Some(offset),
+        _ => None,
+    }
+}
+
+/// Extract the file of one compilation unit into `compilation_units`: its functions, variables
+/// and lines, with the addresses of the rows of its line program added to `row_addresses`.
+/// On error, what was added for the unit so far is left for the caller to remove.
+fn load_unit(
+    dwarf: &gimli::Dwarf<Slice>,
+    unit: &gimli::Unit<Slice>,
+    offset_to_type: &HashMap<usize, Type>,
+    type_wrappers: &HashMap<usize, TypeWrapper>,
+    compilation_units: &mut Vec<File>,
+    row_addresses: &mut Vec<usize>,
+    stats: &mut LoadStats,
+) -> Result<(), Error> {
+    let unit_start = Instant::now();
+    // Iterate over the Debugging Information Entries (DIEs) in the unit.
+    let mut depth = 0;
+    // the depth of the last function, whose direct formal parameters are its arguments
+    let mut function_depth = None;
+    let mut entries = unit.entries();
+    while let Some((delta_depth, entry)) = entries.next_dfs()? {
+        depth += delta_depth;
+        // Update the variable list for formal params/variables
+        match entry.tag() {
+            gimli::DW_TAG_compile_unit => {
+                let name = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_name) {
+                    if let Ok(DebugValue::Str(name)) = get_attr_value(&attr, &unit, &dwarf) {
+                        name
+                    } else {
+                        "<unknown>".to_string()
+                    }
+                } else {
+                    "<unknown>".to_string()
+                };
+                let comp_dir = if let Ok(Some(attr)) = entry.attr(gimli::DW_AT_comp_dir) {
+                    if let Ok(DebugValue::Str(dir)) = get_attr_value(&attr, &unit, &dwarf) {
+                        Some(dir)
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                };
+                compilation_units.push(File {
+                    name,
+                    comp_dir,
+                    global_variables: Vec::new(),
+                    functions: Vec::new(),
+                    lines: Vec::new(),
+                    included_lines: Vec::new(),
+                });
+            }
+            gimli::DW_TAG_subprogram => {
+                function_depth = Some(depth);
+                let mut func: Function = Default::default();
+                let mut attrs = entry.attrs();
+                while let Some(attr) = attrs.next()? {
+                    let val = get_attr_value(&attr, &unit, &dwarf);
+                    //println!("   {}: {:?}", attr.name(), val);
+                    match attr.name() {
+                        gimli::DW_AT_name => {
+                            if let Ok(DebugValue::Str(name)) = val {
+                                func.name = name;
                             }
-                            gimli::DW_AT_high_pc => {
-                                if let Ok(DebugValue::Uint(high_pc)) = val {
-                                    func.text_length = high_pc.try_into().unwrap();
-                                }
+                        }
+                        gimli::DW_AT_high_pc => {
+                            if let Ok(DebugValue::Uint(high_pc)) = val {
+                                func.text_length = high_pc.try_into().unwrap();
                             }
-                            gimli::DW_AT_low_pc => {
-                                //println!("low pc {:?}", attr.value());
-                                if let Ok(DebugValue::Uint(low_pc)) = val {
-                                    func.address = low_pc.try_into().unwrap();
-                                }
+                        }
+                        gimli::DW_AT_low_pc => {
+                            //println!("low pc {:?}", attr.value());
+                            if let Ok(DebugValue::Uint(low_pc)) = val {
+                                func.address = low_pc.try_into().unwrap();
                             }
-                            gimli::DW_AT_decl_line => {
-                                if let Ok(DebugValue::Uint(line_number)) = val {
-                                    func.line_number = line_number.try_into().unwrap();
-                                }
+                        }
+                        gimli::DW_AT_decl_line => {
+                            if let Ok(DebugValue::Uint(line_number)) = val {
+                                func.line_number = line_number.try_into().unwrap();
+                            }
+                        }
+                        gimli::DW_AT_frame_base => {
+                            if let Some(frame_base) = get_frame_base(&attr, &unit) {
+                                func.frame_base = frame_base;
                             }
-                            _ => {}
                         }
+                        _ => {}
                     }
-                    compilation_units.last_mut().unwrap().functions.push(func);
                 }
-                gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
-                    let mut name = String::new();
-                    let mut entity_type: Option<Type> = None;
-                    let mut location: Option<Location> = None;
-                    let mut line_number = 0;
-                    let mut attrs = entry.attrs();
-                    while let Some(attr) = attrs.next()? {
-                        let val = get_attr_value(&attr, &unit, &dwarf);
-                        //println!("   {}: {:?}", attr.name(), val);
-                        match attr.name() {
-                            gimli::DW_AT_name => {
-                                if let Ok(DebugValue::Str(attr_name)) = val {
-                                    name = attr_name;
-                                }
-                            }
-                            gimli::DW_AT_type => {
-                                if let Ok(DebugValue::Size(offset)) = val {
-                                    if let Some(dtype) = offset_to_type.get(&offset).clone() {
-                                        entity_type = Some(dtype.clone());
-                                    }
-                                }
+                compilation_units.last_mut().unwrap().functions.push(func);
+            }
+            gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable => {
+                let mut name = String::new();
+                let mut entity_type: Option<Type> = None;
+                let mut location: Option<Location> = None;
+                let mut line_number = 0;
+                let mut attrs = entry.attrs();
+                while let Some(attr) = attrs.next()? {
+                    let val = get_attr_value(&attr, &unit, &dwarf);
+                    //println!("   {}: {:?}", attr.name(), val);
+                    match attr.name() {
+                        gimli::DW_AT_name => {
+                            if let Ok(DebugValue::Str(attr_name)) = val {
+                                name = attr_name;
                             }
-                            gimli::DW_AT_location => {
-                                if let Some(loc) = get_location(&attr, &unit) {
-                                    location = Some(loc);
-                                }
+                        }
+                        gimli::DW_AT_type => {
+                            if let Ok(DebugValue::Size(offset)) = val {
+                                entity_type = resolve_type(offset, &offset_to_type, &type_wrappers, 0);
                             }
-                            gimli::DW_AT_decl_line => {
-                                if let Ok(DebugValue::Uint(num)) = val {
-                                    line_number = num;
-                                }
+                        }
+                        gimli::DW_AT_location => {
+                            location = Some(get_location(&attr, &unit, &dwarf));
+                        }
+                        gimli::DW_AT_decl_line => {
+                            if let Ok(DebugValue::Uint(num)) = val {
+                                line_number = num;
                             }
-                            _ => {}
                         }
+                        _ => {}
                     }
-                    if entity_type.is_some() && location.is_some() {
-                        let var = Variable {
-                            name,
-                            entity_type: entity_type.unwrap(),
-                            location: location.unwrap(),
-                            line_number: line_number.try_into().unwrap(),
-                        };
-                        if depth == 1 {
-                            compilation_units
-                                .last_mut()
-                                .unwrap()
-                                .global_variables
-                                .push(var);
-                        } else if depth > 1 {
-                            compilation_units
-                                .last_mut()
-                                .unwrap()
-                                .functions
-                                .last_mut()
-                                .unwrap()
-                                .variables
-                                .push(var);
+                }
+                if entity_type.is_some() {
+                    // a variable without a location was optimized out, it is still listed
+                    let location = location
+                        .unwrap_or_else(|| Location::Unavailable("optimized out".to_string()));
+                    let var = Variable {
+                        name,
+                        entity_type: entity_type.unwrap(),
+                        location,
+                        line_number: line_number.try_into().unwrap(),
+                        is_parameter: entry.tag() == gimli::DW_TAG_formal_parameter
+                            && function_depth == Some(depth - 1),
+                    };
+                    if depth == 1 {
+                        compilation_units
+                            .last_mut()
+                            .unwrap()
+                            .global_variables
+                            .push(var);
+                    } else if depth > 1 {
+                        // statics nested in namespaces come before any function
+                        if let Some(func) =
+                            compilation_units.last_mut().unwrap().functions.last_mut()
+                        {
+                            func.variables.push(var);
                         }
                     }
                 }
-                // NOTE: :You may consider supporting other types by extending this
-                // match statement
-                _ => {}
             }
+            // NOTE: :You may consider supporting other types by extending this
+            // match statement
+            _ => {}
         }
+    }
 
-        // Get line numbers
-        if let Some(program) = unit.line_program.clone() {
-            // Iterate over the line program rows.
-            let mut rows = program.rows();
-            while let Some((header, row)) = rows.next_row()? {
-                if !row.end_sequence() {
-                    // Determine the path. Real applications should cache this for performance.
-                    let mut path = path::PathBuf::new();
-                    if let Some(file) = row.file(header) {
-                        if let Some(dir) = file.directory(header) {
-                            path.push(dwarf.attr_string(&unit, dir)?.to_string_lossy().as_ref());
-                        }
-                        path.push(
-                            dwarf
-                                .attr_string(&unit, file.path_name())?
-                                .to_string_lossy()
-                                .as_ref(),
-                        );
-                    }
+    let lines_start = Instant::now();
+    stats.die_walk += lines_start - unit_start;
+    // Get line numbers
+    if let Some(program) = unit.line_program.clone() {
+        // Iterate over the line program rows.
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            row_addresses.push(row.address().try_into().unwrap());
+            // Determine the path. Real applications should cache this for performance.
+            let mut path = path::PathBuf::new();
+            if let Some(file) = row.file(header) {
+                if let Some(dir) = file.directory(header) {
+                    path.push(dwarf.attr_string(&unit, dir)?.to_string_lossy().as_ref());
+                }
+                path.push(
+                    dwarf
+                        .attr_string(&unit, file.path_name())?
+                        .to_string_lossy()
+                        .as_ref(),
+                );
+            }
 
-                    // Get the File
-                    let file = compilation_units
-                        .iter_mut()
-                        .find(|f| f.name == path.as_os_str().to_str().unwrap());
+            // Get the File
+            let file = compilation_units
+                .iter_mut()
+                .find(|f| f.name == path.as_os_str().to_str().unwrap());
 
-                    // Determine line/column. DWARF line/column is never 0, so we use that
-                    // but other applications may want to display this differently.
-                    let line = row.line().unwrap_or(0);
+            // Determine line/column. DWARF line/column is never 0, so we use that
+            // but other applications may want to display this differently.
+            let line = row.line().unwrap_or(0);
+            let column = match row.column() {
+                gimli::ColumnType::LeftEdge => 0,
+                gimli::ColumnType::Column(column) => column,
+            };
 
-                    if let Some(file) = file {
-                        file.lines.push(Line {
-                            file: file.name.clone(),
-                            number: line.try_into().unwrap(),
-                            address: row.address().try_into().unwrap(),
-                        });
+            // End-of-sequence rows are kept: they mark where the previous row's range ends
+            let row_line = |file: String| Line {
+                file,
+                number: line.try_into().unwrap(),
+                address: row.address().try_into().unwrap(),
+                is_stmt: row.is_stmt(),
+                column: column.try_into().unwrap(),
+                end_sequence: row.end_sequence(),
+            };
+            match file {
+                Some(file) => file.lines.push(row_line(file.name.clone())),
+                // the code of a header, kept with the unit including it
+                None => {
+                    if let Some(unit_file) = compilation_units.last_mut() {
+                        unit_file.included_lines.push(row_line(path.to_string_lossy().into_owned()));
                     }
                 }
             }
         }
     }
-    Ok(compilation_units)
+    stats.line_programs += lines_start.elapsed();
+    Ok(())
+}
+
+/// The most pointer, const and typedef levels `resolve_type` follows, against cycles
+const MAX_TYPE_DEPTH: usize = 8;
+
+/// A type DIE that only refers to another type: a pointer, a const qualifier or a typedef.
+struct TypeWrapper {
+    tag: gimli::DwTag,
+    name: Option<String>,
+    target: Option<usize>,
+}
+
+/// # brief
+/// Get the offset of a DIE in its section, which is how type references are resolved.
+fn section_offset<R: Reader>(offset: UnitOffset, unit: &gimli::Unit<R>) -> usize {
+    match offset.to_unit_section_offset(unit) {
+        UnitSectionOffset::DebugInfoOffset(goff) => goff.0,
+        UnitSectionOffset::DebugTypesOffset(goff) => goff.0,
+    }
+}
+
+/// # brief
+/// Find the type at `offset`, naming pointer and const types after the type they refer to,
+/// e.g. `const char *`. A pointer to a type deet doesn't record (arrays, functions) is named
+/// `<unknown> *`, other variables of such types are skipped.
+fn resolve_type(
+    offset: usize,
+    types: &HashMap<usize, Type>,
+    wrappers: &HashMap<usize, TypeWrapper>,
+    depth: usize,
+) -> Option<Type> {
+    if let Some(dtype) = types.get(&offset) {
+        return Some(dtype.clone());
+    }
+    let wrapper = wrappers.get(&offset)?;
+    if depth >= MAX_TYPE_DEPTH {
+        return None;
+    }
+    let target = wrapper.target.map(|target| resolve_type(target, types, wrappers, depth + 1));
+    match wrapper.tag {
+        gimli::DW_TAG_pointer_type => {
+            // Rust names its references and raw pointers, C leaves them unnamed
+            let name = wrapper.name.clone().unwrap_or_else(|| match &target {
+                Some(Some(target)) => format!("{} *", target.name),
+                Some(None) => "<unknown> *".to_string(),
+                None => "void *".to_string(),
+            });
+            Some(Type::new(name, 8))
+        }
+        gimli::DW_TAG_const_type => {
+            let target = match target {
+                Some(target) => target?,
+                None => Type::new("void".to_string(), 0),
+            };
+            Some(Type::new(format!("const {}", target.name), target.size))
+        }
+        _ => {
+            let size = match target {
+                Some(target) => target?.size,
+                None => 0,
+            };
+            Some(Type::new(wrapper.name.clone().unwrap_or_default(), size))
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -292,25 +704,84 @@ impl<'input, Endian> Reader for gimli::EndianSlice<'input, Endian> where
 
 trait Reader: gimli::Reader<Offset = usize> + Send + Sync {}
 
-fn get_location<R: Reader>(attr: &gimli::Attribute<R>, unit: &gimli::Unit<R>) -> Option<Location> {
-    if let gimli::AttributeValue::Exprloc(ref data) = attr.value() {
-        let encoding = unit.encoding();
-        let mut pc = data.0.clone();
-        if pc.len() > 0 {
-            if let Ok(op) = gimli::Operation::parse(&mut pc, encoding) {
-                match op {
-                    gimli::Operation::FrameOffset { offset } => {
-                        return Some(Location::FramePointerOffset(offset.try_into().unwrap()));
-                    }
-                    gimli::Operation::Address { address } => {
-                        return Some(Location::Address(address.try_into().unwrap()));
+/// # brief
+/// Simplify a location expression to a `Location`: a single `DW_OP_fbreg`, `DW_OP_addr`,
+/// `DW_OP_regN` or `DW_OP_bregN`. Anything else is recorded as unavailable, with the reason.
+fn parse_location<R: Reader>(data: gimli::Expression<R>, encoding: gimli::Encoding) -> Location {
+    let mut pc = data.0;
+    if pc.len() == 0 {
+        return Location::Unavailable("optimized out".to_string());
+    }
+    let location = match gimli::Operation::parse(&mut pc, encoding) {
+        Ok(gimli::Operation::FrameOffset { offset }) => {
+            Location::FramePointerOffset(offset.try_into().unwrap())
+        }
+        Ok(gimli::Operation::Address { address }) => Location::Address(address.try_into().unwrap()),
+        Ok(gimli::Operation::Register { register }) => Location::Register(register.0),
+        Ok(gimli::Operation::RegisterOffset { register, offset, .. }) => {
+            Location::RegisterOffset(register.0, offset)
+        }
+        Ok(_) => return Location::Unavailable("unsupported location expression".to_string()),
+        Err(_) => return Location::Unavailable("malformed location expression".to_string()),
+    };
+    if pc.len() > 0 {
+        return Location::Unavailable("composite location expression".to_string());
+    }
+    location
+}
+
+/// # brief
+/// Simplify a location attribute to a `Location`, reading the location list it refers to if
+/// it is not a single expression.
+fn get_location<R: Reader>(
+    attr: &gimli::Attribute<R>,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Location {
+    match attr.value() {
+        gimli::AttributeValue::Exprloc(data) => parse_location(data, unit.encoding()),
+        gimli::AttributeValue::LocationListsRef(offset) => {
+            let mut entries = Vec::new();
+            let mut iter = match dwarf.locations(unit, offset) {
+                Ok(iter) => iter,
+                Err(_) => return Location::Unavailable("malformed location list".to_string()),
+            };
+            loop {
+                match iter.next() {
+                    Ok(Some(entry)) => {
+                        let range = entry.range.begin as usize..entry.range.end as usize;
+                        entries.push((range, parse_location(entry.data, unit.encoding())));
                     }
-                    _ => {}
+                    Ok(None) => break,
+                    Err(_) => return Location::Unavailable("malformed location list".to_string()),
                 }
             }
+            Location::List(entries)
         }
+        _ => Location::Unavailable("unknown location form".to_string()),
+    }
+}
+
+/// # brief
+/// Read the `DW_AT_frame_base` of a function.
+///
+/// # return
+/// * `Some(FrameBase)`, or `None` for a frame base deet doesn't understand, which is taken to
+/// be the CFA like before.
+fn get_frame_base<R: Reader>(attr: &gimli::Attribute<R>, unit: &gimli::Unit<R>) -> Option<FrameBase> {
+    let data = match attr.value() {
+        gimli::AttributeValue::Exprloc(data) => data,
+        _ => return None,
+    };
+    let mut pc = data.0;
+    match gimli::Operation::parse(&mut pc, unit.encoding()).ok()? {
+        gimli::Operation::CallFrameCFA => Some(FrameBase::Cfa),
+        gimli::Operation::Register { register } => Some(FrameBase::Register(register.0, 0)),
+        gimli::Operation::RegisterOffset { register, offset, .. } => {
+            Some(FrameBase::Register(register.0, offset))
+        }
+        _ => None,
     }
-    None
 }
 
 // based on dwarf_dump.rs
@@ -620,3 +1091,124 @@ fn dump_op<R: Reader, W: Write>(
     };
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// A unit as `load_units` sees it: the file it adds, the addresses of its rows, and the
+    /// error it fails with after adding them.
+    type FakeUnit = (Option<&'static str>, Vec<usize>, Option<Error>);
+
+    /// # brief
+    /// Load fake units at offsets 0, 0x100, 0x200..., with a header that can't be read after
+    /// them if `header_error` is given.
+    fn load(units: Vec<FakeUnit>, mut header_error: Option<Error>) -> (Units, usize) {
+        let mut units = units.into_iter().enumerate();
+        let next = || match units.next() {
+            Some((index, unit)) => Ok(Some((index * 0x100, unit))),
+            None => header_error.take().map_or(Ok(None), Err),
+        };
+        let load = |(name, rows, error): FakeUnit, files: &mut Vec<File>, row_addresses: &mut Vec<usize>, _: &mut LoadStats| {
+            if let Some(name) = name {
+                files.push(File { name: name.to_string(), ..File::default() });
+            }
+            row_addresses.extend(rows);
+            error.map_or(Ok(()), Err)
+        };
+        let done = Cell::new(0);
+        let mut stats = LoadStats::default();
+        let units = load_units(next, load, &|units| done.set(units), &mut stats);
+        assert_eq!(stats.units, done.get());
+        (units, done.get())
+    }
+
+    /// # brief
+    /// The names of the files loaded.
+    fn names(files: &[File]) -> Vec<&str> {
+        files.iter().map(|file| file.name.as_str()).collect()
+    }
+
+    #[test]
+    fn units_after_a_corrupted_one_still_load() {
+        let corrupted = Error::GimliError(gimli::Error::UnexpectedEof(gimli::ReaderOffsetId(0x1234)));
+        let ((files, rows, errors), done) = load(
+            vec![
+                (Some("a.c"), vec![0x1000, 0x1010], None),
+                (Some("b.c"), vec![0x2000], Some(corrupted.clone())),
+                (Some("c.c"), vec![0x3000], None),
+            ],
+            None,
+        );
+        assert_eq!(names(&files), ["a.c", "c.c"]);
+        // what the corrupted unit added before it failed is left out with it
+        assert_eq!(rows, [0x1000, 0x1010, 0x3000]);
+        assert_eq!(errors, [("b.c".to_string(), corrupted)]);
+        assert_eq!(done, 3);
+    }
+
+    #[test]
+    fn a_unit_failing_before_its_name_is_named_by_its_offset() {
+        let ((files, _, errors), _) = load(
+            vec![(Some("a.c"), vec![], None), (None, vec![], Some(Error::GimliError(gimli::Error::Other)))],
+            None,
+        );
+        assert_eq!(names(&files), ["a.c"]);
+        assert_eq!(errors, [("<unit at .debug_info+0x100>".to_string(), Error::GimliError(gimli::Error::Other))]);
+    }
+
+    #[test]
+    fn an_unreadable_header_ends_the_walk() {
+        let truncated = Error::GimliError(gimli::Error::UnexpectedEof(gimli::ReaderOffsetId(0x200)));
+        let ((files, _, errors), done) = load(vec![(Some("a.c"), vec![0x1000], None)], Some(truncated.clone()));
+        assert_eq!(names(&files), ["a.c"]);
+        assert_eq!(errors, [("<the units after the last one read>".to_string(), truncated)]);
+        assert_eq!(done, 1);
+    }
+
+    /// # brief
+    /// The type DIEs gcc writes for
+    /// `typedef const char *name_t; struct point { int x, y; }; struct point *p; const void *v;`
+    /// and a Rust reference `&u8`, by offset, with a typedef loop that can't be resolved.
+    fn c_types() -> (HashMap<usize, Type>, HashMap<usize, TypeWrapper>) {
+        let types = [(0x30, "char", 1), (0x40, "int", 4), (0x50, "point", 8), (0x60, "u8", 1)];
+        let wrapper = |tag, name: Option<&str>, target| TypeWrapper { tag, name: name.map(|name| name.to_string()), target };
+        let wrappers = vec![
+            (0x100, wrapper(gimli::DW_TAG_const_type, None, Some(0x30))),
+            (0x110, wrapper(gimli::DW_TAG_pointer_type, None, Some(0x100))),
+            (0x120, wrapper(gimli::DW_TAG_typedef, Some("name_t"), Some(0x110))),
+            (0x130, wrapper(gimli::DW_TAG_pointer_type, None, Some(0x50))),
+            (0x140, wrapper(gimli::DW_TAG_const_type, None, None)),
+            (0x150, wrapper(gimli::DW_TAG_pointer_type, None, Some(0x140))),
+            (0x160, wrapper(gimli::DW_TAG_pointer_type, None, None)),
+            (0x170, wrapper(gimli::DW_TAG_pointer_type, Some("&u8"), Some(0x60))),
+            (0x180, wrapper(gimli::DW_TAG_pointer_type, None, Some(0x999))),
+            (0x190, wrapper(gimli::DW_TAG_typedef, Some("loop_t"), Some(0x1a0))),
+            (0x1a0, wrapper(gimli::DW_TAG_typedef, Some("loop2_t"), Some(0x190))),
+        ];
+        (
+            types.iter().map(|(offset, name, size)| (*offset, Type::new(name.to_string(), *size))).collect(),
+            wrappers.into_iter().collect(),
+        )
+    }
+
+    #[test]
+    fn type_references_resolve_to_names_and_sizes() {
+        let (types, wrappers) = c_types();
+        let resolve = |offset| resolve_type(offset, &types, &wrappers, 0).map(|ty| (ty.name, ty.size));
+        let named = |name: &str, size| Some((name.to_string(), size));
+        assert_eq!(resolve(0x40), named("int", 4));
+        assert_eq!(resolve(0x100), named("const char", 1));
+        assert_eq!(resolve(0x110), named("const char *", 8));
+        // a typedef has the size of its target, and its own name
+        assert_eq!(resolve(0x120), named("name_t", 8));
+        assert_eq!(resolve(0x130), named("point *", 8));
+        assert_eq!(resolve(0x150), named("const void *", 8));
+        assert_eq!(resolve(0x160), named("void *", 8));
+        assert_eq!(resolve(0x170), named("&u8", 8));
+        assert_eq!(resolve(0x180), named("<unknown> *", 8));
+        assert_eq!(resolve(0x190), None);
+        assert_eq!(resolve(0x999), None);
+    }
+}
@@ -0,0 +1,107 @@
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+
+use crate::debugger_command::{self, ArgumentKind};
+
+/// The readline helper of the prompt. It shows the rest of a likely input as a grey hint,
+/// taken from the function names of the target, the addresses recently shown, the breakpoint
+/// groups and the command history.
+#[derive(Debug, Default)]
+pub struct DeetHelper {
+    /// The function names of the selected inferior, sorted
+    functions: Vec<String>,
+    /// The addresses recently shown, most recent first
+    addresses: Vec<usize>,
+    /// The names of the breakpoint groups, sorted
+    groups: Vec<String>,
+}
+
+impl DeetHelper {
+    pub fn new() -> Self {
+        DeetHelper { functions: Vec::new(), addresses: Vec::new(), groups: Vec::new() }
+    }
+
+    pub fn set_functions(&mut self, mut functions: Vec<String>) {
+        functions.sort();
+        functions.dedup();
+        self.functions = functions;
+    }
+
+    pub fn set_addresses(&mut self, addresses: Vec<usize>) {
+        self.addresses = addresses;
+    }
+
+    pub fn set_groups(&mut self, mut groups: Vec<String>) {
+        groups.sort();
+        groups.dedup();
+        self.groups = groups;
+    }
+
+    /// # brief
+    /// Complete an argument of a command, according to what the command expects there.
+    fn hint_argument(&self, kind: ArgumentKind, word: &str) -> Option<String> {
+        let addresses = self.addresses.iter().map(|addr| format!("{:#x}", addr));
+        let candidates: Vec<String> = match kind {
+            ArgumentKind::Location => match word.strip_prefix('*') {
+                Some(_) => addresses.map(|addr| format!("*{}", addr)).collect(),
+                None => self.functions.clone(),
+            },
+            ArgumentKind::Expression => addresses.collect(),
+        };
+        candidates
+            .into_iter()
+            .find(|candidate| candidate.len() > word.len() && candidate.starts_with(word))
+            .map(|candidate| candidate[word.len()..].to_string())
+    }
+}
+
+impl Hinter for DeetHelper {
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
+        // only hint at the end of the line, where the hint is drawn
+        if line.is_empty() || pos < line.len() {
+            return None;
+        }
+        let words = debugger_command::tokenize(line);
+        if let (Some(command), true) = (words.first(), line.ends_with(|c: char| !c.is_whitespace())) {
+            if words.len() == 2 {
+                let name = command.split('/').next().unwrap_or("");
+                if let Some(kind) = debugger_command::argument_kind(name) {
+                    if let Some(hint) = self.hint_argument(kind, &words[1]) {
+                        return Some(hint);
+                    }
+                }
+            }
+            // `enable group <name>`, `group add <name>`
+            if words.len() == 3 && debugger_command::takes_group(command, &words[1]) {
+                let word = &words[2];
+                if let Some(group) = self.groups.iter().find(|group| group.len() > word.len() && group.starts_with(word.as_str())) {
+                    return Some(group[word.len()..].to_string());
+                }
+            }
+        }
+        // the most recent history entry starting with the line
+        ctx.history()
+            .iter()
+            .rev()
+            .find(|entry| entry.len() > line.len() && entry.starts_with(line))
+            .map(|entry| entry[line.len()..].to_string())
+    }
+}
+
+impl Highlighter for DeetHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[90m{}\x1b[0m", hint))
+    }
+}
+
+impl Completer for DeetHelper {
+    type Candidate = String;
+}
+
+impl Validator for DeetHelper {}
+
+impl Helper for DeetHelper {}
@@ -1,16 +1,31 @@
+use nix::errno::Errno;
 use nix::sys::ptrace;
 use nix::sys::signal;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
-use std::process::Child;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::os::unix::process::CommandExt;
 use std::mem::size_of;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fmt;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
+use crate::cancel::CancelToken;
+use crate::context;
+use crate::core_pattern;
 use crate::dwarf_data::DwarfData;
 use crate::dwarf_data::Line;
+use crate::maps;
+use crate::output::Capture;
+use crate::preflight;
+use crate::registers::FpRegisters;
+use crate::stats;
+use crate::target::TargetAccess;
+use crate::ui::{self, AddressFormat};
 
 /// # brief 
 /// Align the given address to the nearest word boundary, Pointer size depends on current platform.
@@ -31,7 +46,27 @@ fn align_addr_to_word(addr: usize) -> usize {
     addr & (-(size_of::<usize>() as isize) as usize)
 }
 
+/// # brief
+/// Group byte patches by the aligned word they are in, so that each word is read and written
+/// once however many of its bytes change.
+///
+/// # return
+/// * The words in address order, each with its patches in address order.
+fn group_by_word(patches: impl IntoIterator<Item = (usize, u8)>) -> Vec<(usize, Vec<(usize, u8)>)> {
+    let mut patches: Vec<(usize, u8)> = patches.into_iter().collect();
+    patches.sort_by_key(|(addr, _)| *addr);
+    let mut words: Vec<(usize, Vec<(usize, u8)>)> = Vec::new();
+    for (addr, byte) in patches {
+        match words.last_mut() {
+            Some((word_addr, word)) if *word_addr == align_addr_to_word(addr) => word.push((addr, byte)),
+            _ => words.push((align_addr_to_word(addr), vec![(addr, byte)])),
+        }
+    }
+    words
+}
+
 // Status of the Child Process 
+#[derive(Debug)]
 pub enum Status {
     /// Indicates inferior stopped. Contains the signal that stopped the process, as well as the
     /// current instruction pointer that it is stopped at.
@@ -40,16 +75,95 @@ pub enum Status {
     /// Indicates inferior exited normally. Contains the exit status code.
     Exited(i32),
 
-    /// Indicates the inferior exited due to signal. Contains the signal that killed the process
-    Signaled(signal::Signal),
+    /// Indicates the inferior exited due to signal. Contains the signal that killed the process,
+    /// and whether it dumped core.
+    Signaled(signal::Signal, bool),
+
+    /// Indicates the inferior called execve: it runs a new program, and is stopped at the first
+    /// instruction of it. Contains that instruction pointer.
+    Exec(usize),
+}
+
+/// # brief
+/// Check the first stop of a spawned child, which must be the SIGTRAP after exec.
+///
+/// # return
+/// * `Err(Error::UnexpectedStop)` for a stop by another signal, `Err(Error::ExitedBeforeStart)`
+/// if the child terminated instead.
+fn exec_stop(status: Status) -> Result<(), Error> {
+    match status {
+        Status::Stopped(signal::Signal::SIGTRAP, _) => Ok(()),
+        Status::Stopped(signal, _) => Err(Error::UnexpectedStop(signal)),
+        status => Err(Error::ExitedBeforeStart(status)),
+    }
+}
+
+/// # brief
+/// Describe `signal` with its number and what it means, e.g. `SIGSEGV (signal 11,
+/// Segmentation fault)`.
+pub fn describe_signal(signal: signal::Signal) -> String {
+    let description = unsafe { std::ffi::CStr::from_ptr(libc::strsignal(signal as libc::c_int)) };
+    format!("{:?} (signal {}, {})", signal, signal as i32, description.to_string_lossy())
+}
+
+/// # brief
+/// Parse a signal given to a command, by name with or without the `SIG` prefix, in any case,
+/// or by number: `SIGUSR1`, `usr1` and `10` are the same signal. `0` is no signal.
+///
+/// # return
+/// * `Ok(Some(Signal))`, `Ok(None)` for `0`, or `Err(String)` if it is not a signal.
+pub fn parse_signal(text: &str) -> Result<Option<signal::Signal>, String> {
+    let invalid = || format!("Unknown signal \"{}\", expected a name like SIGUSR1 or USR1, or a number", text);
+    if let Ok(number) = text.parse::<i32>() {
+        if number == 0 {
+            return Ok(None);
+        }
+        return signal::Signal::from_c_int(number).map(Some).map_err(|_| invalid());
+    }
+    let name = text.to_uppercase();
+    let name = if name.starts_with("SIG") { name } else { format!("SIG{}", name) };
+    name.parse::<signal::Signal>().map(Some).map_err(|_| invalid())
+}
+
+impl Status {
+    /// # brief
+    /// Describe the status like `Display`, with the function and line a stop is at.
+    pub fn describe(&self, debug_data: &DwarfData) -> String {
+        match self {
+            Status::Stopped(signal, ip) => {
+                let func = debug_data.function_at(*ip as u64);
+                match (func, debug_data.line_at(*ip as u64)) {
+                    (Some(func), Some(line)) => format!("Stopped by {:?} (signal {}) in {} ({})", signal, *signal as i32, func, line),
+                    (Some(func), None) => format!("Stopped by {:?} (signal {}) in {} at {:#x}", signal, *signal as i32, func, ip),
+                    _ => self.to_string(),
+                }
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// # brief
+    /// Whether the status is a crash, as `set crash-report` reports them: a stop by a signal
+    /// whose default action dumps a core (SIGTRAP aside, breakpoints raise it), an exit with a
+    /// nonzero status, or the death of the process by a signal.
+    pub fn is_crash(&self) -> bool {
+        match self {
+            Status::Stopped(signal, _) => *signal != signal::Signal::SIGTRAP && core_pattern::dumps_core(*signal),
+            Status::Exited(code) => *code != 0,
+            Status::Signaled(..) => true,
+            Status::Exec(_) => false,
+        }
+    }
 }
 
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Status::Stopped(signal, ip) => write!(f, "Stopped: Signal {:?}, Instruction Pointer: 0x{:X}", signal, ip),
+            Status::Stopped(signal, ip) => write!(f, "Stopped by {:?} (signal {}) at {:#x}", signal, *signal as i32, ip),
             Status::Exited(exit_code) => write!(f, "Exited with status code: {}", exit_code),
-            Status::Signaled(signal) => write!(f, "Signaled: Signal {:?}", signal),
+            Status::Signaled(signal, true) => write!(f, "Terminated by {:?} (signal {}, core dumped)", signal, *signal as i32),
+            Status::Signaled(signal, false) => write!(f, "Terminated by {:?} (signal {})", signal, *signal as i32),
+            Status::Exec(ip) => write!(f, "Executed a new program, stopped at {:#x}", ip),
         }
     }
 }
@@ -68,131 +182,1234 @@ fn child_traceme() -> Result<(), std::io::Error> {
     )))
 }
 
+/// # brief
+/// Turn off address space randomization for the program about to be executed, so that it is
+/// loaded at the same addresses every run. nix does not wrap `personality` in all versions.
+fn disable_randomization() -> Result<(), std::io::Error> {
+    let persona = unsafe { libc::personality(0xffffffff) };
+    if persona == -1 || unsafe { libc::personality((persona | libc::ADDR_NO_RANDOMIZE) as libc::c_ulong) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// # brief
+/// Raise the core size limit of the program about to be executed as far as the hard limit
+/// allows, so that a crash dumps a core whatever the limit of deet's shell.
+fn allow_core_dumps() -> Result<(), std::io::Error> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    limit.rlim_cur = limit.rlim_max;
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Errors starting an inferior
+#[derive(Debug)]
+pub enum Error {
+    /// The target could not be spawned
+    SpawnFailed(std::io::Error),
+    /// The child terminated before it stopped after exec
+    ExitedBeforeStart(Status),
+    /// The first stop of the child was not the SIGTRAP after exec
+    UnexpectedStop(signal::Signal),
+    /// A ptrace request on the new child failed
+    PtraceFailed(TraceError),
+    /// PTRACE_ATTACH was refused, with the reason found by the preflight checks
+    AttachDenied(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SpawnFailed(err) => write!(f, "could not execute the target: {}", err),
+            Error::ExitedBeforeStart(status) => write!(f, "the child terminated before it started ({})", status),
+            Error::UnexpectedStop(signal) => write!(f, "the child stopped with {:?} instead of SIGTRAP after exec", signal),
+            Error::PtraceFailed(err) => write!(f, "{}", err),
+            Error::AttachDenied(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Whether every ptrace request is logged, see `set debug ptrace`
+static TRACE_REQUESTS: AtomicBool = AtomicBool::new(false);
+
+/// Whether new inferiors run without address space randomization, see `--deterministic`
+static NO_RANDOMIZATION: AtomicBool = AtomicBool::new(false);
+
+/// Whether new inferiors may dump a core, see `set coredump`
+static DUMP_CORE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The patches of the code of an inferior going on, see `PatchGuard`. They are counted per
+    /// thread, as the panic interrupting one is caught on the thread that made it.
+    static PATCHES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks a patch of the code of an inferior going on: traps written, or lifted to execute the
+/// original instruction and put back. A panic in the middle leaves the mark when it drops the
+/// guard, so that the debugger recovering from it knows the code may be half patched.
+pub struct PatchGuard;
+
+impl PatchGuard {
+    pub fn new() -> Self {
+        PATCHES.with(|patches| patches.set(patches.get() + 1));
+        PatchGuard
+    }
+}
+
+impl Drop for PatchGuard {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            PATCHES.with(|patches| patches.set(patches.get() - 1));
+        }
+    }
+}
+
+/// # brief
+/// Check whether a panic interrupted a patch of the code of an inferior, and clear the marks.
+pub fn take_interrupted_patch() -> bool {
+    PATCHES.with(|patches| patches.replace(0)) > 0
+}
+
+/// # brief
+/// Let the inferiors spawned from now on dump a core when they crash, or keep the core size
+/// limit they inherit.
+pub fn set_dump_core(on: bool) {
+    DUMP_CORE.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Whether the inferiors spawned from now on may dump a core.
+pub fn dump_core() -> bool {
+    DUMP_CORE.load(Ordering::Relaxed)
+}
+
+/// # brief
+/// Start the inferiors spawned from now on at fixed addresses, or at random ones again.
+pub fn set_disable_randomization(on: bool) {
+    NO_RANDOMIZATION.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Turn the logging of every ptrace request, and its outcome, on or off.
+pub fn set_trace_requests(on: bool) {
+    TRACE_REQUESTS.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Whether every ptrace request is logged.
+pub fn trace_requests() -> bool {
+    TRACE_REQUESTS.load(Ordering::Relaxed)
+}
+
+/// A request deet makes to the kernel about the inferior: a ptrace request, or the wait and
+/// the signals that go with them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraceOp {
+    Attach,
+    SetOptions,
+    PeekData,
+    PokeData,
+    GetRegs,
+    SetRegs,
+    GetFpRegs,
+    GetSigInfo,
+    Cont,
+    Step,
+    Detach,
+    Wait,
+    Kill,
+    GetEventMsg,
+}
+
+impl fmt::Display for TraceOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TraceOp::Attach => "PTRACE_ATTACH",
+            TraceOp::SetOptions => "PTRACE_SETOPTIONS",
+            TraceOp::PeekData => "PTRACE_PEEKDATA",
+            TraceOp::PokeData => "PTRACE_POKEDATA",
+            TraceOp::GetRegs => "PTRACE_GETREGS",
+            TraceOp::SetRegs => "PTRACE_SETREGS",
+            TraceOp::GetFpRegs => "PTRACE_GETFPREGS",
+            TraceOp::GetSigInfo => "PTRACE_GETSIGINFO",
+            TraceOp::Cont => "PTRACE_CONT",
+            TraceOp::Step => "PTRACE_SINGLESTEP",
+            TraceOp::Detach => "PTRACE_DETACH",
+            TraceOp::Wait => "waitpid",
+            TraceOp::Kill => "kill",
+            TraceOp::GetEventMsg => "PTRACE_GETEVENTMSG",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A failed request on the inferior, with what was asked of which process
+#[derive(Debug, Clone, Copy)]
+pub struct TraceError {
+    pub op: TraceOp,
+    pub pid: Pid,
+    /// The address the request was about, for the memory requests
+    pub addr: Option<usize>,
+    pub source: nix::Error,
+}
+
+impl TraceError {
+    /// # brief
+    /// Suggest what the error of the request usually means.
+    fn hint(&self) -> Option<&'static str> {
+        let errno = match self.source {
+            nix::Error::Sys(errno) => errno,
+            _ => return None,
+        };
+        match (self.op, errno) {
+            (TraceOp::PokeData, Errno::EIO) | (TraceOp::PokeData, Errno::EFAULT) => {
+                Some("is the address in a writable mapping?")
+            }
+            (TraceOp::PeekData, Errno::EIO) | (TraceOp::PeekData, Errno::EFAULT) => Some("is the address mapped?"),
+            (TraceOp::Wait, Errno::ECHILD) => Some("was the process already reaped?"),
+            (_, Errno::ESRCH) => Some("is the process stopped, and still alive?"),
+            (_, Errno::EPERM) => Some("is the process traced by deet?"),
+            _ => None,
+        }
+    }
+}
+
+/// # brief
+/// Describe a request: the operation, its address if any and the process.
+fn describe_request(op: TraceOp, pid: Pid, addr: Option<usize>) -> String {
+    match addr {
+        Some(addr) => format!("{} at {:#x} on pid {}", op, addr, pid),
+        None => format!("{} on pid {}", op, pid),
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} failed: ", describe_request(self.op, self.pid, self.addr))?;
+        match self.source {
+            nix::Error::Sys(errno) => write!(f, "{}", errno.desc())?,
+            other => write!(f, "{}", other)?,
+        }
+        match self.hint() {
+            Some(hint) => write!(f, " ({})", hint),
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<TraceError> for nix::Error {
+    fn from(err: TraceError) -> Self {
+        err.source
+    }
+}
+
+/// # brief
+/// Make a request on `pid` through `call`, logging it when `set debug ptrace` is on, and give
+/// its error the context of the request. All the requests on the inferior go through here, and
+/// are counted for `info stats`.
+fn request<T>(
+    op: TraceOp,
+    pid: Pid,
+    addr: Option<usize>,
+    call: impl FnOnce() -> nix::Result<T>
+) -> Result<T, TraceError> {
+    let start = Instant::now();
+    let result = call().map_err(|source| TraceError { op, pid, addr, source });
+    stats::record_request(op, start.elapsed());
+    if trace_requests() {
+        match &result {
+            Ok(_) => ui::notify(&format!("[ptrace] {}", describe_request(op, pid, addr))),
+            Err(err) => ui::notify(&format!("[ptrace] {}", err)),
+        }
+    }
+    result
+}
+
+/// A read or write of the inferior's memory that stopped at a word it could not access
+#[derive(Debug)]
+pub struct PartialTransfer {
+    /// How many bytes were transferred before, the offset of the first failing byte
+    pub done: usize,
+    pub error: TraceError,
+}
+
+impl fmt::Display for PartialTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (after {} byte{})", self.error, self.done, if self.done == 1 { "" } else { "s" })
+    }
+}
+
+/// What a stopped process was doing in the kernel, read from `/proc/<pid>/syscall`, `wchan`
+/// and `stat`
+#[derive(Debug, Clone, Default)]
+pub struct SyscallState {
+    /// The number of the system call it is in and its six raw arguments, `None` if it is not in
+    /// one
+    pub syscall: Option<(u64, [usize; 6])>,
+    /// The kernel function it sleeps in, `None` if it is not sleeping
+    pub wchan: Option<String>,
+    /// The state letter of `stat`, e.g. `t` for stopped by a tracer
+    pub state: Option<char>,
+}
+
+impl SyscallState {
+    /// # brief
+    /// Parse the contents of the `syscall`, `wchan` and `stat` files of a process. A field that
+    /// can't be understood is left out.
+    pub fn parse(syscall: &str, wchan: &str, stat: &str) -> Self {
+        let fields: Vec<&str> = syscall.split_whitespace().collect();
+        // "running", or "-1 sp pc" outside of a system call
+        let syscall = fields.get(0).and_then(|number| number.parse::<u64>().ok()).and_then(|number| {
+            let mut args = [0; 6];
+            for (arg, field) in args.iter_mut().zip(fields.get(1..7)?) {
+                *arg = usize::from_str_radix(field.trim_start_matches("0x"), 16).ok()?;
+            }
+            Some((number, args))
+        });
+        let wchan = Some(wchan.trim()).filter(|wchan| !wchan.is_empty() && *wchan != "0").map(String::from);
+        // the name in parentheses may hold spaces, the state follows the last parenthesis
+        let state = stat.rfind(')').and_then(|end| stat[end + 1..].split_whitespace().next()).and_then(|state| state.chars().next());
+        SyscallState { syscall, wchan, state }
+    }
+}
+
+/// An open file descriptor of a process, from `/proc/<pid>/fd` and `/proc/<pid>/fdinfo`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FdInfo {
+    pub fd: i32,
+    /// Where the descriptor leads: a path, or e.g. `socket:[4711]`, `pipe:[812]` or
+    /// `anon_inode:[eventfd]`
+    pub target: String,
+    /// The `O_*` flags of the open file, `None` if `fdinfo` could not be read
+    pub flags: Option<i32>,
+    /// The file offset
+    pub pos: Option<u64>,
+}
+
+impl FdInfo {
+    /// # brief
+    /// Get what the descriptor is: `socket`, `pipe`, `eventfd`, another anonymous inode by
+    /// its name (e.g. `eventpoll`), or `file` for everything with a path.
+    pub fn kind(&self) -> String {
+        if self.target.starts_with("socket:") {
+            return "socket".to_string();
+        }
+        if self.target.starts_with("pipe:") {
+            return "pipe".to_string();
+        }
+        match self.target.strip_prefix("anon_inode:") {
+            Some(name) => name.trim_start_matches('[').trim_end_matches(']').to_string(),
+            None => "file".to_string(),
+        }
+    }
+
+    /// # brief
+    /// Render the flags as the access mode and the flags worth knowing about a leak, e.g.
+    /// `rw,cloexec`.
+    pub fn describe_flags(&self) -> String {
+        let flags = match self.flags {
+            Some(flags) => flags,
+            None => return "?".to_string(),
+        };
+        let mut names = vec![match flags & libc::O_ACCMODE {
+            libc::O_WRONLY => "w",
+            libc::O_RDWR => "rw",
+            _ => "r",
+        }];
+        for (flag, name) in [(libc::O_CLOEXEC, "cloexec"), (libc::O_APPEND, "append"), (libc::O_NONBLOCK, "nonblock")] {
+            if flags & flag != 0 {
+                names.push(name);
+            }
+        }
+        names.join(",")
+    }
+}
+
+/// A stop a thread reported while deet was stopping it, after another thread stopped first. It
+/// is reported at the next resume, instead of resuming the threads.
+struct PendingStop {
+    status: Status,
+    /// The address of the trap the thread executed, if it stopped right after one
+    trap: Option<usize>,
+}
+
+/// A thread of the inferior, traced from its creation with `PTRACE_O_TRACECLONE`, or since deet
+/// attached to the process
+pub struct Thread {
+    /// The number of the thread in deet, 1 for the main thread, then in the order of creation
+    pub number: usize,
+    pub tid: Pid,
+    /// Whether the thread was resumed and did not report a stop since
+    running: bool,
+    /// Whether a SIGSTOP not to report is on its way to the thread: one deet sent to stop it
+    /// along with another thread, or the first stop of a new thread
+    stop_expected: bool,
+    pending: Option<PendingStop>,
+    /// The signal to deliver when the thread is resumed, kept while a pending stop of another
+    /// thread was reported instead
+    signal: Option<signal::Signal>,
+}
+
+impl Thread {
+    fn new(number: usize, tid: Pid) -> Self {
+        Thread { number, tid, running: true, stop_expected: false, pending: None, signal: None }
+    }
+}
+
 pub struct Inferior {
-    child: Child,
+    pid: Pid,
+    /// Whether deet attached to an existing process rather than spawning it
+    attached: bool,
+    /// Whether the stdout and stderr of the process are read by a `Capture`
+    captured: bool,
+    /// The difference between the addresses in the debug information and in memory, non-zero
+    /// for position independent executables
+    load_bias: usize,
+    /// The threads of the process, in the order of creation
+    threads: Vec<Thread>,
+    /// The thread the registers are read from and the single steps execute: the one that
+    /// stopped last, unless `select_thread` chose another
+    current: Pid,
+    /// Whether the threads were all resumed, by a continue, rather than the current one alone
+    /// for a single step. New threads run then; during a step, they wait for the next continue.
+    all_running: bool,
+    /// The number of the next new thread
+    next_thread: usize,
+    /// The trap the debugger keeps in the dynamic linker, see `set_library_hook`
+    library_hook: Option<usize>,
+    /// Whether a stop at the library hook was passed without being reported
+    library_changed: bool,
 }
 
 impl Inferior {
     /// # brief
-    /// Attempts to start a new inferior process. Returns Some(Inferior) if successful, or None if
-    /// an error is encountered.
+    /// Attempts to start a new inferior process. The new process is stopped at the SIGTRAP it
+    /// receives after exec, ready for `install_breakpoints`.
+    ///
+    /// # param
+    /// - `target` : The path of the executable.
+    /// - `args` : The arguments of the program.
+    /// - `environment` : The variables to set in the environment of deet for the program, or to
+    /// remove from it (`None`).
+    /// - `capture` : Where to record the stdout and stderr of the program, which are then pipes,
+    /// or `None` to let it share deet's terminal.
+    ///
+    /// # return
+    /// * Returns the new Inferior, or an `Error` if the child could not be started and stopped.
     ///
-    pub fn new(target: &str, args: &Vec<String>, breakpoints: &mut HashMap<usize, u8>) -> Option<Self> {
+    pub fn new(
+        target: &str,
+        args: &[String],
+        environment: &[(String, Option<String>)],
+        capture: Option<&Capture>,
+    ) -> Result<Self, Error> {
         let mut cmd = Command::new(target);
         cmd.args(args);
+        for (name, value) in environment {
+            match value {
+                Some(value) => cmd.env(name, value),
+                None => cmd.env_remove(name),
+            };
+        }
+        if capture.is_some() {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
         unsafe {
             // Allow father Process trace chlid ; before execute Child
-            cmd.pre_exec(child_traceme);
+            let fixed_addresses = NO_RANDOMIZATION.load(Ordering::Relaxed);
+            let core_dumps = DUMP_CORE.load(Ordering::Relaxed);
+            cmd.pre_exec(move || {
+                if fixed_addresses {
+                    disable_randomization()?;
+                }
+                if core_dumps {
+                    allow_core_dumps()?;
+                }
+                child_traceme()
+            });
         }
         // When a process that has PTRACE_TRACEME enabled calls exec,
         // the operating system will local the specified program into process,
         // and then (before the new program starts running) it will pause the process using 
-        // SIGTRAP . So at the time when inferior is returnd, chlid process is paused.
-        let child_cmd = cmd.spawn().ok()?;
-        let mut inferior = Inferior {child: child_cmd};
-        // install breakpoints
-        let bps = breakpoints.clone();
-        for bp in bps.keys() {
-            // a set containing all keys. 
-            // Traversing this set can obtain the memory address of each breakpoint.
-            match inferior.write_byte(*bp, 0xcc) {
-                Ok(ori_instr) => {breakpoints.insert(*bp, ori_instr);},
-                Err(_) => println!("Invalid breakpoint address {:#x}", bp),
+        // SIGTRAP. Wait for that stop before touching the new image.
+        let mut child_cmd = cmd.spawn().map_err(Error::SpawnFailed)?;
+        let pid = nix::unistd::Pid::from_raw(child_cmd.id() as i32);
+        if let Some(capture) = capture {
+            capture.attach(child_cmd.stdout.take().unwrap(), child_cmd.stderr.take().unwrap());
+        }
+        let mut inferior = Inferior::traced(pid, false, capture.is_some());
+        if let Err(err) = exec_stop(inferior.wait().map_err(Error::PtraceFailed)?) {
+            // a child stopped otherwise is still there
+            if let Error::UnexpectedStop(_) = err {
+                inferior.kill();
             }
+            return Err(err);
         }
-        Some(inferior)
+        // kill the child if deet dies, report later execs as ptrace events, and trace the threads
+        // it creates
+        inferior.prepare(
+            target,
+            ptrace::Options::PTRACE_O_EXITKILL | ptrace::Options::PTRACE_O_TRACEEXEC | ptrace::Options::PTRACE_O_TRACECLONE,
+        )?;
+        Ok(inferior)
     }
 
     /// # brief
-    /// get pid from io and return it
+    /// Attach to a running process and to each of its threads. The process is stopped, ready for
+    /// `install_breakpoints`.
     ///
-    /// # return
-    /// * Return the pid of the inferior
+    /// # param
+    /// - `pid` : The process to attach to.
+    /// - `target` : The path of the executable of the process.
     ///
-    /// # example
-    /// ```
-    /// inferior.pid();
-    /// ```
-    pub fn pid(&self) -> Pid {
-        nix::unistd::Pid::from_raw(self.child.id() as i32)
+    /// # return
+    /// * Returns the attached Inferior, or an `Error` if the process could not be traced.
+    pub fn attach(pid: Pid, target: &str) -> Result<Self, Error> {
+        request(TraceOp::Attach, pid, None, || ptrace::attach(pid)).map_err(|err| match err.source {
+            nix::Error::Sys(Errno::EPERM) => Error::AttachDenied(preflight::explain_attach_denied(pid)),
+            _ => Error::PtraceFailed(err),
+        })?;
+        let mut inferior = Inferior::traced(pid, true, false);
+        // PTRACE_ATTACH sends SIGSTOP, wait for the process to stop with it
+        match inferior.wait().map_err(Error::PtraceFailed)? {
+            Status::Stopped(_, _) => {}
+            status => return Err(Error::ExitedBeforeStart(status)),
+        }
+        inferior.attach_threads().map_err(Error::PtraceFailed)?;
+        // the process outlives deet, so no PTRACE_O_EXITKILL here
+        inferior.prepare(target, ptrace::Options::PTRACE_O_TRACEEXEC | ptrace::Options::PTRACE_O_TRACECLONE)?;
+        Ok(inferior)
+    }
+
+    /// # brief
+    /// Make the inferior of a process with one thread, traced and about to stop.
+    fn traced(pid: Pid, attached: bool, captured: bool) -> Self {
+        Inferior {
+            pid,
+            attached,
+            captured,
+            load_bias: 0,
+            threads: vec![Thread::new(1, pid)],
+            current: pid,
+            all_running: true,
+            next_thread: 2,
+            library_hook: None,
+            library_changed: false,
+        }
+    }
+
+    /// # brief
+    /// Attach to the threads of the process other than the main one, as `/proc` lists them,
+    /// until no new one shows up: a thread not attached yet may create another. A thread that
+    /// ends in between is left out.
+    fn attach_threads(&mut self) -> Result<(), TraceError> {
+        loop {
+            let tids: Vec<Pid> = fs::read_dir(format!("/proc/{}/task", self.pid))
+                .map(|dir| dir.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok()).map(Pid::from_raw).collect())
+                .unwrap_or_default();
+            let new: Vec<Pid> = tids.into_iter().filter(|tid| self.thread(*tid).is_none()).collect();
+            if new.is_empty() {
+                return Ok(());
+            }
+            for tid in new {
+                if request(TraceOp::Attach, tid, None, || ptrace::attach(tid)).is_ok() {
+                    // PTRACE_ATTACH sent it SIGSTOP
+                    self.add_thread(tid);
+                }
+            }
+            self.stop_others()?;
+        }
+    }
+
+    /// # brief
+    /// Set the ptrace options of the threads and compute the load bias of a newly stopped
+    /// inferior. The threads it creates later inherit the options.
+    fn prepare(&mut self, target: &str, options: ptrace::Options) -> Result<(), Error> {
+        for thread in &self.threads {
+            request(TraceOp::SetOptions, thread.tid, None, || ptrace::setoptions(thread.tid, options))
+                .map_err(Error::PtraceFailed)?;
+        }
+        self.refresh_load_bias(target);
+        Ok(())
+    }
+
+    /// # brief
+    /// Compute the load bias again, after the inferior executed `target`.
+    pub fn refresh_load_bias(&mut self, target: &str) {
+        self.load_bias = maps::load_bias(self.pid(), target).unwrap_or(0);
     }
 
     /// # brief
-    /// Kill the process and wait for it to end.
+    /// Write the traps of `breakpoints` into the stopped inferior, recording the original bytes.
+    /// Only the addresses are used: the saved bytes, which may be left from another process,
+    /// are replaced by the ones read from this one.
     ///
-    /// # example
-    /// ```
-    /// inferior.kill();
-    /// ```
+    /// The traps sharing a word are written with one read and one write of it, which matters with
+    /// the hundreds of breakpoints of an `rbreak` or a session.
     ///
-    pub fn kill(&mut self) {
-        self.child.kill().unwrap();
-        self.wait(None).unwrap();
-        println!("killing running inferior (pid{})", self.pid());
+    /// # param
+    /// - `breakpoints` : The addresses of the breakpoints, whose original bytes are recorded.
+    /// - `cancel` : Stops the installation before the next word once cancelled, by Ctrl+C.
+    /// - `progress` : Called after each word with the breakpoints done so far and their total.
+    ///
+    /// # return
+    /// * The address of each breakpoint installed or tried, sorted, with the error of writing its
+    /// trap if that failed, and whether the installation was interrupted before the others. A
+    /// failed or left out breakpoint keeps its entry in `breakpoints` untouched.
+    pub fn install_breakpoints(
+        &mut self,
+        breakpoints: &mut HashMap<usize, u8>,
+        cancel: &CancelToken,
+        progress: &dyn Fn(usize, usize),
+    ) -> (Vec<(usize, Result<(), TraceError>)>, bool) {
+        let _patch = PatchGuard::new();
+        let total = breakpoints.len();
+        let mut results = Vec::new();
+        for (word_addr, patches) in group_by_word(breakpoints.keys().map(|addr| (*addr, 0xcc))) {
+            if cancel.is_cancelled() {
+                return (results, true);
+            }
+            match self.patch_word(word_addr, &patches) {
+                Ok(orig_bytes) => {
+                    for ((addr, _), orig_byte) in patches.iter().zip(orig_bytes) {
+                        breakpoints.insert(*addr, orig_byte);
+                        results.push((*addr, Ok(())));
+                    }
+                }
+                Err(err) => results.extend(patches.iter().map(|(addr, _)| (*addr, Err(err)))),
+            }
+            progress(results.len(), total);
+        }
+        (results, false)
+    }
+
+    /// # brief
+    /// Whether the inferior was attached to rather than spawned by deet.
+    pub fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    /// # brief
+    /// Whether the output of the inferior is recorded by a `Capture`.
+    pub fn is_captured(&self) -> bool {
+        self.captured
     }
 
     /// # brief
-    /// Waits for the status of the process and returns the corresponding status value.
+    /// Remove all traps from the inferior and let all its threads run on untraced.
     ///
     /// # param
-    ///  - `option`: Option<WaitPidFlag> - used to specify the behavior of the waiting process. 
-    ///  If `None`, the default options are used.
+    /// - `breakpoints` - The addresses of the breakpoints and their original bytes.
+    /// - `step_points` - The addresses of the temporary step traps and their original bytes.
+    pub fn detach(
+        &mut self,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>
+    ) -> Result<(), TraceError> {
+        // a stop at a trap was rewound already, see `settle_trap`
+        for (word_addr, patches) in group_by_word(breakpoints.iter().chain(step_points.iter()).map(|(addr, byte)| (*addr, *byte))) {
+            self.patch_word(word_addr, &patches)?;
+        }
+        step_points.clear();
+        self.settle_threads()?;
+        for thread in &self.threads {
+            request(TraceOp::Detach, thread.tid, None, || ptrace::detach(thread.tid))?;
+        }
+        Ok(())
+    }
+
+    /// # brief
+    /// Put the threads as they would be without deet, before it lets them go: a thread with a
+    /// pending stop right after a trap, removed now, goes back to the instruction, and the
+    /// SIGSTOP deet sent a thread is taken before it stops the untraced process. The signals of
+    /// the other pending stops are not delivered.
+    fn settle_threads(&mut self) -> Result<(), TraceError> {
+        let mut gone = Vec::new();
+        for i in 0..self.threads.len() {
+            let tid = self.threads[i].tid;
+            if let Some(PendingStop { trap: Some(trap), .. }) = self.threads[i].pending.take() {
+                let mut regs = self.thread_registers(tid)?;
+                regs.rip = trap as u64;
+                self.set_thread_registers(tid, regs)?;
+            }
+            // the stop comes once the thread runs
+            while self.threads[i].stop_expected {
+                request(TraceOp::Cont, tid, None, || ptrace::cont(tid, None))?;
+                match self.wait_status(tid, None)? {
+                    WaitStatus::Stopped(_, signal::Signal::SIGSTOP) => self.threads[i].stop_expected = false,
+                    WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                        gone.push(tid);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        gone.into_iter().for_each(|tid| self.remove_thread(tid));
+        Ok(())
+    }
+
+    /// # brief
+    /// Get the load bias of the executable, which is 0 unless it is position independent.
+    pub fn load_bias(&self) -> usize {
+        self.load_bias
+    }
+
+    /// # brief
+    /// get pid from io and return it
     ///
     /// # return
-    /// * If the wait is successful, the process's status value is returned, 
-    /// otherwise a `nix::Error` is returned.
+    /// * Return the pid of the inferior
     ///
     /// # example
     /// ```
-    /// let status = match process.wait(Some(WaitPidFlag::WNOHANG)) {
-    /// Ok(s) => s,
-    /// Err(e) => return Err(e),
-    /// };
+    /// inferior.pid();
     /// ```
-    pub fn wait(&self, option: Option<WaitPidFlag>) -> Result<Status, nix::Error> {
-        Ok(match waitpid(self.pid(), option)? {
-            WaitStatus::Exited(_pid, exit_code) => Status::Exited(exit_code),
-            WaitStatus::Signaled(_pid, signal, _core_dumped) => Status::Signaled(signal),
-            WaitStatus::Stopped(_pid, signal) => {
-                let regs = ptrace::getregs(self.pid())?;
-                Status::Stopped(signal, regs.rip as usize)
-            },
-            other => panic!("waited returned unexpected status: {:?}", other),
-        })
+    pub fn pid(&self) -> Pid {
+        self.pid
     }
 
     /// # brief
-    /// Wake up the paused inferior process, there are two possibilities:
-    /// paused by breakpoints
-    /// (1) inferior process paused by breakpoints
-    /// (2) inferior process paused by other signals (e.g. ctrl + c)//   
-    ///
-    /// # param
-    /// - `signal` - Optional signal to deliver to the process upon resuming execution.
-    /// - `breakpoints` - A hashmap containing the addresses of breakpoints set in the process.
-    ///   
-    /// # return
-    /// * Returns a `Result` indicating the status of the process after resuming execution. Possible
-    /// return values are:
-    ///
-    /// * `Ok(Status::Exited(exit_code))` - If the process has exited with a specific exit code.
-    /// * `Ok(Status::Signaled(signal))` - If the process has been terminated by a signal.
-    /// * `Ok(Status::Stopped(signal, status))` - If the process has been stopped by a signal, with
-    ///   information about the signal and the status.
-    /// * `Err(nix::Error)` - If an error occurs during the execution of the function. 
-    ///
+    /// Get the threads of the process, in the order of creation, the main thread first while it
+    /// lives. They are all stopped when the process is.
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    /// # brief
+    /// Record the address of the trap the debugger keeps in the dynamic linker, at the function
+    /// it calls after each change to the list of libraries. The trap is one of the breakpoints
+    /// passed to the resuming functions. `continue_run` reports its stops, for the debugger to
+    /// read the libraries again; `run_to`, `finish` and the steps go on past it, and
+    /// `take_library_change` tells they did.
+    pub fn set_library_hook(&mut self, addr: Option<usize>) {
+        self.library_hook = addr;
+    }
+
+    /// # brief
+    /// Get the address of the library hook, see `set_library_hook`.
+    pub fn library_hook(&self) -> Option<usize> {
+        self.library_hook
+    }
+
+    /// # brief
+    /// Check whether a stop at the library hook was passed since the last call.
+    pub fn take_library_change(&mut self) -> bool {
+        std::mem::take(&mut self.library_changed)
+    }
+
+    /// # brief
+    /// Check whether `status` is a stop at the library hook, for the resuming functions that go
+    /// on past it. The stop is recorded for `take_library_change`.
+    ///
+    /// # return
+    /// * The address of the hook and its original byte, to pass it with `pass_trap`, or `None`
+    /// for any other stop.
+    fn at_library_hook(&mut self, status: &Status, breakpoints: &HashMap<usize, u8>) -> Option<(usize, u8)> {
+        match status {
+            Status::Stopped(signal::Signal::SIGTRAP, rip) if Some(*rip) == self.library_hook => {
+                let ori_instr = *breakpoints.get(rip)?;
+                self.library_changed = true;
+                Some((*rip, ori_instr))
+            }
+            _ => None,
+        }
+    }
+
+    /// # brief
+    /// Get the thread the registers are read from and the single steps execute, the one that
+    /// stopped last unless another was selected.
+    pub fn current_thread(&self) -> Option<&Thread> {
+        self.thread(self.current)
+    }
+
+    /// # brief
+    /// Make the thread numbered `number` the current one, for `thread` and `thread apply`.
+    ///
+    /// # return
+    /// * The thread, or `None` if there is none with that number.
+    pub fn select_thread(&mut self, number: usize) -> Option<&Thread> {
+        let tid = self.threads.iter().find(|thread| thread.number == number)?.tid;
+        self.current = tid;
+        self.thread(tid)
+    }
+
+    /// # brief
+    /// Get the general purpose registers of one of the stopped threads.
+    pub fn thread_registers(&self, tid: Pid) -> Result<libc::user_regs_struct, TraceError> {
+        request(TraceOp::GetRegs, tid, None, || ptrace::getregs(tid))
+    }
+
+    fn set_thread_registers(&self, tid: Pid, regs: libc::user_regs_struct) -> Result<(), TraceError> {
+        request(TraceOp::SetRegs, tid, None, || ptrace::setregs(tid, regs))
+    }
+
+    fn thread(&self, tid: Pid) -> Option<&Thread> {
+        self.threads.iter().find(|thread| thread.tid == tid)
+    }
+
+    fn thread_mut(&mut self, tid: Pid) -> Option<&mut Thread> {
+        self.threads.iter_mut().find(|thread| thread.tid == tid)
+    }
+
+    /// # brief
+    /// Record a thread just traced, which is yet to report the SIGSTOP it starts with.
+    fn add_thread(&mut self, tid: Pid) {
+        let mut thread = Thread::new(self.next_thread, tid);
+        thread.stop_expected = true;
+        self.threads.push(thread);
+        self.next_thread += 1;
+    }
+
+    /// # brief
+    /// Forget a thread that ended. If it was the current one, the oldest thread left is.
+    fn remove_thread(&mut self, tid: Pid) {
+        self.threads.retain(|thread| thread.tid != tid);
+        if self.current == tid {
+            self.current = self.threads.first().map_or(self.pid, |thread| thread.tid);
+        }
+    }
+
+    /// # brief
+    /// Kill the process and wait for it to end. A process that already ended, and was reaped,
+    /// is left alone: the signal fails at once instead of the wait blocking or failing.
+    ///
+    /// # example
+    /// ```
+    /// inferior.kill();
+    /// ```
+    ///
+    pub fn kill(&mut self) {
+        if self.signal(signal::Signal::SIGKILL).is_err() {
+            return;
+        }
+        // stops queued before the kill come first; every thread reports its end, the main
+        // thread last
+        loop {
+            for thread in self.threads.iter_mut() {
+                thread.running = true;
+                thread.pending = None;
+            }
+            self.all_running = true;
+            match self.wait() {
+                Ok(Status::Exited(_)) | Ok(Status::Signaled(_, _)) | Err(_) => return,
+                Ok(Status::Stopped(_, _)) | Ok(Status::Exec(_)) => {}
+            }
+        }
+    }
+
+    /// # brief
+    /// Check, without blocking, whether the inferior terminated while deet was not waiting for
+    /// it, e.g. killed by a signal from another process while stopped. The termination status
+    /// is reaped by this call, so it must be reported by the caller.
+    ///
+    /// # return
+    /// * `Some(Status)` - the exit status if the inferior has terminated, `None` if it is alive.
+    pub fn poll_exit(&mut self) -> Option<Status> {
+        // the main thread reports the end of the process once the other threads are reaped
+        let tids: Vec<Pid> = self.threads.iter().rev().map(|thread| thread.tid).collect();
+        for tid in tids {
+            if let Ok(wait_status @ (WaitStatus::Exited(..) | WaitStatus::Signaled(..))) = self.wait_status(tid, Some(WaitPidFlag::WNOHANG)) {
+                if let Ok(Some(status)) = self.thread_event(tid, wait_status) {
+                    return Some(status);
+                }
+            }
+        }
+        None
+    }
+
+    /// # brief
+    /// Wait for a thread to stop, and the others with it, or for the process to terminate.
+    ///
+    /// # return
+    /// * If the wait is successful, the process's status value is returned, 
+    /// otherwise a `TraceError` is returned.
+    ///
+    /// # example
+    /// ```
+    /// let status = match process.wait() {
+    /// Ok(s) => s,
+    /// Err(e) => return Err(e),
+    /// };
+    /// ```
+    pub fn wait(&mut self) -> Result<Status, TraceError> {
+        loop {
+            // a blocking wait only reports changes of state, but a resumption is not a stop
+            if let Some(status) = self.wait_event(true)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// # brief
+    /// Check, without blocking, whether a thread stopped or the process terminated, with
+    /// `WNOHANG`. A status found is reaped by this call, so it must be handled by the caller.
+    ///
+    /// # return
+    /// * `Ok(Some(Status))` - the new status, `Ok(None)` if the process is still running or
+    /// nothing changed since the last wait, or the `TraceError` of `waitpid`, e.g. `ECHILD` for a
+    /// process already reaped.
+    pub fn poll_status(&mut self) -> Result<Option<Status>, TraceError> {
+        self.wait_event(false)
+    }
+
+    /// # brief
+    /// Wait for the next stop to report, handling the events of the threads that are not one on
+    /// the way: a new thread, the end of a thread, the SIGSTOP deet sent to stop one. A stop
+    /// kept pending while the threads were stopped comes first, once they are resumed.
+    ///
+    /// All-stop: the thread that stops becomes the current one, and the threads still running
+    /// are stopped with it.
+    ///
+    /// # param
+    /// - `block` : Whether to wait for the stop, or only check with `WNOHANG`.
+    ///
+    /// # return
+    /// * The stop or the end of the process, `None` without one when not blocking.
+    fn wait_event(&mut self, block: bool) -> Result<Option<Status>, TraceError> {
+        loop {
+            if self.all_running {
+                if let Some(thread) = self.threads.iter_mut().find(|thread| thread.pending.is_some()) {
+                    let pending = thread.pending.take().unwrap();
+                    self.current = thread.tid;
+                    self.stop_others()?;
+                    return Ok(Some(pending.status));
+                }
+            }
+            let (tid, wait_status) = match self.next_wait_status(block)? {
+                Some(event) => event,
+                None => return Ok(None),
+            };
+            if let Some(status) = self.thread_event(tid, wait_status)? {
+                return Ok(Some(status));
+            }
+        }
+    }
+
+    /// # brief
+    /// Wait for a change of state of a running thread. With a single thread running the wait
+    /// blocks in `waitpid`; several are polled in turn, every `THREAD_POLL`, as a wait for any
+    /// child could take the children of other inferiors.
+    ///
+    /// # return
+    /// * The thread and its change of state, `None` if none changed when not blocking.
+    fn next_wait_status(&mut self, block: bool) -> Result<Option<(Pid, WaitStatus)>, TraceError> {
+        loop {
+            let running: Vec<Pid> = self.threads.iter().filter(|thread| thread.running).map(|thread| thread.tid).collect();
+            match running.as_slice() {
+                [tid] if block => return Ok(Some((*tid, self.wait_status(*tid, None)?))),
+                [] if !block && !self.threads.is_empty() => return Ok(None),
+                // nothing could ever change
+                [] => {
+                    let source = nix::Error::Sys(Errno::ECHILD);
+                    return Err(TraceError { op: TraceOp::Wait, pid: self.pid, addr: None, source });
+                }
+                _ => {}
+            }
+            for tid in running {
+                match self.wait_status(tid, Some(WaitPidFlag::WNOHANG)) {
+                    Ok(WaitStatus::StillAlive) => {}
+                    Ok(wait_status) => return Ok(Some((tid, wait_status))),
+                    // a thread that ran execve does not exist under its own id anymore
+                    Err(err) if tid != self.pid && err.source == nix::Error::Sys(Errno::ECHILD) => self.remove_thread(tid),
+                    Err(err) => return Err(err),
+                }
+            }
+            if !block {
+                return Ok(None);
+            }
+            std::thread::sleep(THREAD_POLL);
+        }
+    }
+
+    /// # brief
+    /// Handle a change of state of a thread: track the threads it creates and end, and turn a
+    /// stop to report into a `Status`, stopping the other threads.
+    ///
+    /// # return
+    /// * The status to report, or `None` for an event deet handled by itself.
+    fn thread_event(&mut self, tid: Pid, wait_status: WaitStatus) -> Result<Option<Status>, TraceError> {
+        match wait_status {
+            // the main thread reports the end of the process once the other threads are gone
+            WaitStatus::Exited(..) | WaitStatus::Signaled(..) if tid == self.pid => {
+                self.threads.clear();
+                return self.status_from(tid, wait_status);
+            }
+            WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                self.remove_thread(tid);
+                // a step of the thread ended with it: the others go on as after a continue
+                if !self.all_running && !self.threads.iter().any(|thread| thread.running) {
+                    self.all_running = true;
+                    for thread in self.threads.iter_mut().filter(|thread| thread.pending.is_none()) {
+                        request(TraceOp::Cont, thread.tid, None, || ptrace::cont(thread.tid, thread.signal.take()))?;
+                        thread.running = true;
+                    }
+                }
+                return Ok(None);
+            }
+            WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE) => {
+                let new = request(TraceOp::GetEventMsg, tid, None, || ptrace::getevent(tid))?;
+                self.add_thread(Pid::from_raw(new as i32));
+                // the thread goes on as it was resumed
+                if self.all_running {
+                    request(TraceOp::Cont, tid, None, || ptrace::cont(tid, None))?;
+                } else {
+                    request(TraceOp::Step, tid, None, || ptrace::step(tid, None))?;
+                }
+                return Ok(None);
+            }
+            WaitStatus::Stopped(_, signal::Signal::SIGSTOP) if self.thread(tid).map_or(false, |thread| thread.stop_expected) => {
+                let (all_running, stepping) = (self.all_running, tid == self.current);
+                let thread = self.thread_mut(tid).unwrap();
+                thread.stop_expected = false;
+                // a thread resumed with the SIGSTOP of a pending stop still queued goes on as
+                // it was resumed; a new thread waits for a continue
+                if all_running {
+                    request(TraceOp::Cont, tid, None, || ptrace::cont(tid, None))?;
+                } else if stepping {
+                    request(TraceOp::Step, tid, None, || ptrace::step(tid, None))?;
+                } else {
+                    thread.running = false;
+                }
+                return Ok(None);
+            }
+            _ => {}
+        }
+        let status = match self.status_from(tid, wait_status)? {
+            Some(status) => status,
+            None => return Ok(None),
+        };
+        if let Some(thread) = self.thread_mut(tid) {
+            thread.running = false;
+        }
+        self.current = tid;
+        if let Status::Exec(_) = status {
+            // the other threads are gone with the old image, their ends were reported
+            self.threads.retain(|thread| thread.tid == tid);
+        }
+        self.stop_others()?;
+        Ok(Some(status))
+    }
+
+    /// # brief
+    /// Stop the threads still running after one stopped, as all-stop debugging wants: each is
+    /// sent SIGSTOP, and the wait for it takes that stop. A thread that reports another stop
+    /// first keeps it pending, see `PendingStop`, and its SIGSTOP comes once it runs again. A
+    /// main thread that ended while the others run will only report the end of the process.
+    fn stop_others(&mut self) -> Result<(), TraceError> {
+        let pid = self.pid;
+        for thread in self.threads.iter_mut().filter(|thread| thread.running && !thread.stop_expected) {
+            if thread.tid == pid && is_zombie(pid) {
+                continue;
+            }
+            // a thread that ends in between reports it below
+            if request(TraceOp::Kill, thread.tid, None, || tgkill(pid, thread.tid, signal::Signal::SIGSTOP)).is_ok() {
+                thread.stop_expected = true;
+            }
+        }
+        while let Some(tid) = self.threads.iter().find(|thread| thread.running && thread.stop_expected).map(|thread| thread.tid) {
+            let wait_status = self.wait_status(tid, None)?;
+            match wait_status {
+                WaitStatus::Stopped(_, signal::Signal::SIGSTOP) => {
+                    let thread = self.thread_mut(tid).unwrap();
+                    thread.stop_expected = false;
+                    thread.running = false;
+                }
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE) => {
+                    let new = request(TraceOp::GetEventMsg, tid, None, || ptrace::getevent(tid))?;
+                    self.add_thread(Pid::from_raw(new as i32));
+                    // the SIGSTOP reaches the thread once it goes on
+                    request(TraceOp::Cont, tid, None, || ptrace::cont(tid, None))?;
+                }
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) if tid != pid => self.remove_thread(tid),
+                _ => {
+                    let status = match self.status_from(tid, wait_status)? {
+                        Some(status) => status,
+                        None => continue,
+                    };
+                    let trap = match status {
+                        Status::Stopped(signal::Signal::SIGTRAP, rip) => trap_address(rip as u64)
+                            .filter(|trap| self.read_bytes(*trap, 1).map_or(false, |byte| byte == [0xcc])),
+                        _ => None,
+                    };
+                    let thread = self.thread_mut(tid).unwrap();
+                    thread.running = false;
+                    thread.pending = Some(PendingStop { status, trap });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// # brief
+    /// Resume all the threads for a continue, the current one with `signal`. A pending stop of
+    /// a thread is reported instead, and no thread resumed, unless the trap it stopped after
+    /// was removed since: the thread goes back to the instruction then. A thread other than
+    /// the current one that is stopped at a trap executes the original instruction first,
+    /// alone, or it would report the same breakpoint again.
+    fn resume_all(
+        &mut self,
+        signal: Option<signal::Signal>,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>,
+    ) -> Result<(), TraceError> {
+        for i in 0..self.threads.len() {
+            let trap = match &self.threads[i].pending {
+                Some(PendingStop { trap: Some(trap), .. }) => *trap,
+                _ => continue,
+            };
+            if self.read_bytes(trap, 1)? != [0xcc] {
+                let tid = self.threads[i].tid;
+                let mut regs = self.thread_registers(tid)?;
+                regs.rip = trap as u64;
+                self.set_thread_registers(tid, regs)?;
+                self.threads[i].pending = None;
+            }
+        }
+        let current = self.current;
+        if self.threads.iter().any(|thread| thread.pending.is_some()) {
+            if let Some(thread) = self.thread_mut(current) {
+                thread.signal = signal.or(thread.signal);
+            }
+            self.all_running = true;
+            return Ok(());
+        }
+        let others: Vec<Pid> = self.threads.iter().map(|thread| thread.tid).filter(|tid| *tid != current).collect();
+        for tid in others {
+            let rip = self.thread_registers(tid)?.rip as usize;
+            if !breakpoints.contains_key(&rip) && !step_points.contains_key(&rip) {
+                continue;
+            }
+            self.current = tid;
+            let stepped = self.step_over_trap(breakpoints, step_points);
+            self.current = current;
+            match stepped? {
+                Some(Status::Stopped(signal::Signal::SIGTRAP, _)) | None => {}
+                // reported at once, as if it came while stopping the thread
+                Some(status) => {
+                    let pending = matches!(status, Status::Stopped(..));
+                    if let (true, Some(thread)) = (pending, self.thread_mut(tid)) {
+                        thread.pending = Some(PendingStop { status, trap: None });
+                    }
+                    if let Some(thread) = self.thread_mut(current) {
+                        thread.signal = signal.or(thread.signal);
+                    }
+                    self.all_running = true;
+                    return Ok(());
+                }
+            }
+        }
+        self.all_running = true;
+        for thread in self.threads.iter_mut() {
+            let signal = if thread.tid == current { signal.or(thread.signal.take()) } else { thread.signal.take() };
+            match request(TraceOp::Cont, thread.tid, None, || ptrace::cont(thread.tid, signal)) {
+                Ok(()) => {}
+                // a main thread that ended while the others run
+                Err(_) if thread.tid != current && is_zombie(thread.tid) => {}
+                Err(err) => return Err(err),
+            }
+            thread.running = true;
+        }
+        Ok(())
+    }
+
+    /// # brief
+    /// Turn the result of `waitpid` for a thread into a `Status`, reading %rip of a stopped
+    /// thread.
+    ///
+    /// # return
+    /// * `Ok(None)` when the thread did not stop: it is still running (`WNOHANG`) or was
+    /// resumed by SIGCONT.
+    fn status_from(&self, tid: Pid, wait_status: WaitStatus) -> Result<Option<Status>, TraceError> {
+        let rip = || -> Result<usize, TraceError> { Ok(self.thread_registers(tid)?.rip as usize) };
+        Ok(Some(match wait_status {
+            WaitStatus::Exited(_pid, exit_code) => Status::Exited(exit_code),
+            WaitStatus::Signaled(_pid, signal, core_dumped) => Status::Signaled(signal, core_dumped),
+            WaitStatus::Stopped(_pid, signal) => Status::Stopped(signal, rip()?),
+            // with PTRACE_O_TRACEEXEC, an exec stops the inferior with a ptrace event
+            WaitStatus::PtraceEvent(_pid, _, libc::PTRACE_EVENT_EXEC) => Status::Exec(rip()?),
+            WaitStatus::PtraceEvent(_pid, signal, _event) => Status::Stopped(signal, rip()?),
+            WaitStatus::StillAlive | WaitStatus::Continued(_) => return Ok(None),
+            other => panic!("waited returned unexpected status: {:?}", other),
+        }))
+    }
+
+    /// # brief
+    /// Wait for the inferior to stop or terminate, stopping it with SIGSTOP if it is still
+    /// running at `deadline`. The wait polls with `WNOHANG`, so the deadline is kept without a
+    /// timer thread.
+    ///
+    /// # param
+    /// - `deadline` : When to stop the inferior, or `None` to wait without a time limit.
+    ///
+    /// # return
+    /// * `Ok((Status, bool))` - the status, and whether the inferior was stopped by the deadline.
+    pub fn wait_until(&mut self, deadline: Option<Instant>) -> Result<(Status, bool), TraceError> {
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => return Ok((self.wait()?, false)),
+        };
+        loop {
+            // a genuine stop came first, the deadline is dropped
+            if let Some(status) = self.poll_status()? {
+                return Ok((status, false));
+            }
+            if Instant::now() >= deadline {
+                // one thread, the current one if it runs; the others stop with it
+                let tid = match self.threads.iter().find(|thread| thread.running && thread.tid == self.current) {
+                    Some(thread) => thread.tid,
+                    None => self.threads.iter().find(|thread| thread.running).map_or(self.pid, |thread| thread.tid),
+                };
+                request(TraceOp::Kill, tid, None, || tgkill(self.pid, tid, signal::Signal::SIGSTOP))?;
+                // a stop racing the SIGSTOP is reported as it is
+                let status = self.wait()?;
+                let timed_out = matches!(status, Status::Stopped(signal::Signal::SIGSTOP, _));
+                return Ok((status, timed_out));
+            }
+            std::thread::sleep(TIMED_WAIT_POLL.min(deadline.saturating_duration_since(Instant::now())));
+        }
+    }
+
+    /// # brief
+    /// Wake up the paused inferior process, there are two possibilities:
+    /// paused by breakpoints
+    /// (1) inferior process paused by breakpoints
+    /// (2) inferior process paused by other signals (e.g. ctrl + c)//   
+    ///
+    /// # param
+    /// - `signal` - Optional signal to deliver to the process upon resuming execution.
+    /// - `breakpoints` - A hashmap containing the addresses of breakpoints set in the process.
+    /// - `deadline` - Stop the inferior with SIGSTOP if it is still running then.
+    ///   
+    /// # return
+    /// * Returns a `Result` indicating the status of the process after resuming execution, and
+    /// whether the deadline stopped it. Possible statuses are:
+    ///
+    /// * `Ok(Status::Exited(exit_code))` - If the process has exited with a specific exit code.
+    /// * `Ok(Status::Signaled(signal, core_dumped))` - If the process has been terminated by a
+    ///   signal.
+    /// * `Ok(Status::Stopped(signal, status))` - If the process has been stopped by a signal, with
+    ///   information about the signal and the status.
+    /// * `Err(TraceError)` - If an error occurs during the execution of the function. 
+    ///
     /// # Examples
     ///
     /// ```
     /// let mut debugger = Debugger::new();
     /// let breakpoints = HashMap::new();
-    /// match debugger.continue_run(Some(signal::Signal::SIGCONT), &breakpoints) {
-    ///     Ok(status) => {
+    /// match debugger.continue_run(Some(signal::Signal::SIGCONT), &breakpoints, &mut step_points, None) {
+    ///     Ok((status, _)) => {
     ///         match status {
     ///             Status::Exited(exit_code) => {
     ///                 println!("Process exited with code: {}", exit_code);
     ///             }
-    ///             Status::Signaled(signal) => {
+    ///             Status::Signaled(signal, _) => {
     ///                 println!("Process terminated by signal: {:?}", signal);
     ///             }
     ///             Status::Stopped(signal, status) => {
@@ -209,197 +1426,1528 @@ impl Inferior {
         &mut self, 
         signal: Option<signal::Signal>, 
         breakpoints: &HashMap<usize, u8>,
-        step_points: &mut HashMap<usize, u8>
-    ) -> Result<Status, nix::Error> {
-        let mut regs = ptrace::getregs(self.pid())?;
-        let rip = regs.rip as usize;
+        step_points: &mut HashMap<usize, u8>,
+        deadline: Option<Instant>,
+    ) -> Result<(Status, bool), TraceError> {
+        self.continue_to(signal, breakpoints, step_points, deadline, None)
+    }
 
-        // check if inferior stopped at a breakpoint
-        println!("\x1b[33mbreakpoints: {:?} \n rip: {}\x1b[0m", breakpoints, rip); // Delete TOOD
-        if let Some(ori_instr) = breakpoints.get(&(rip - 1)) {
-            println!("stopped at a breakpoints");
-            // restore the first byte of the instruction we replaced
-            self.write_byte(rip - 1, *ori_instr).unwrap();
-            // set %rip = %rip - 1 to rewind the instruction pointer
-            regs.rip = (rip - 1) as u64;
-            ptrace::setregs(self.pid(), regs).unwrap();
-            // go to the next instruction
-            println!("\x1b[31mExecute ptrace::step\x1b[0m"); // Delete TOOD
-            ptrace::step(self.pid(), None).unwrap();
-            // wait for inferior to stop due to SIGTRAP, just return if the inferior terminates here
-
-            match self.wait(None).unwrap() {
-                Status::Exited(exit_code) => return Ok(Status::Exited(exit_code)),
-                Status::Signaled(signal) => return Ok(Status::Signaled(signal)),
-                Status::Stopped(_, _) => {
-                    // restore 0xcc in the breakpoint localtion
-                    self.write_byte(rip - 1, 0xcc).unwrap();
+    /// # brief
+    /// Resume the inferior like `continue_run`. A step trap hit on the way is not a stop to
+    /// report, it is left over from a step command that ended elsewhere: it is removed and the
+    /// inferior resumed again. Only the step trap at `wanted`, planted by the caller, stops it;
+    /// with `wanted`, the library hook is passed as well.
+    fn continue_to(
+        &mut self,
+        signal: Option<signal::Signal>,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>,
+        deadline: Option<Instant>,
+        wanted: Option<usize>,
+    ) -> Result<(Status, bool), TraceError> {
+        // if inferior stopped at a breakpoint, execute the original instruction first
+        match self.step_over_trap(breakpoints, step_points)? {
+            Some(Status::Stopped(signal::Signal::SIGTRAP, _)) | None => {}
+            // a signal arrived while stepping off the trap, report it rather than lose it
+            Some(status) => return Ok((status, false)),
+        }
+        let mut signal = signal;
+        let stepping = self.current;
+        loop {
+            // resume normal execution
+            self.resume_all(signal.take(), breakpoints, step_points)?;
+            // wait for inferior to stop or terminate
+            let (status, timed_out) = self.wait_until(deadline)?;
+            let status = self.settle_trap(status, breakpoints, step_points)?;
+            // a plain continue reports the stop, for the debugger to read the libraries at once
+            let hook = if wanted.is_some() { self.at_library_hook(&status, breakpoints) } else { None };
+            if let Some((hook, ori_instr)) = hook {
+                // the libraries changed on the way, the debugger reads them at the stop
+                if let Some(status) = self.pass_trap(hook, ori_instr)? {
+                    return Ok((status, timed_out));
                 }
+                self.current = stepping;
+                continue;
             }
-
-        } else if let Some(ori_instr) = step_points.get(&(rip - 1)) {
-            println!("\x1b[32mstopped at a step_points\x1b[0m");// Delete TOOD
-            // restore the first byte of the instruction we replaced
-            self.write_byte(rip - 1, *ori_instr).unwrap();
-            // set %rip = %rip - 1 to rewind the instruction pointer
-            regs.rip = (rip - 1) as u64;
-            ptrace::setregs(self.pid(), regs).unwrap();
-            // go to the next instruction
-            ptrace::step(self.pid(), None).unwrap();
-            match self.wait(None).unwrap() {
-                Status::Exited(exit_code) => return Ok(Status::Exited(exit_code)),
-                Status::Signaled(signal) => return Ok(Status::Signaled(signal)),
-                Status::Stopped(_, _) => {
-                    step_points.remove(&(rip - 1));
+            let rip = match status {
+                // the trap is for the thread that asked, another one goes on
+                Status::Stopped(signal::Signal::SIGTRAP, rip) if Some(rip) == wanted && self.current != stepping && step_points.contains_key(&rip) => {
+                    if let Some(status) = self.pass_trap(rip, step_points[&rip])? {
+                        return Ok((status, timed_out));
+                    }
+                    self.current = stepping;
+                    continue;
                 }
+                Status::Stopped(signal::Signal::SIGTRAP, rip) if Some(rip) != wanted && !breakpoints.contains_key(&rip) => rip,
+                _ => return Ok((status, timed_out)),
+            };
+            match step_points.remove(&rip) {
+                // %rip is at the trap already, put the instruction back and go on
+                Some(ori_instr) => {
+                    self.write_byte(rip, ori_instr)?;
+                }
+                None => return Ok((status, timed_out)),
             }
         }
-        println!("\x1b[32mExecute ptrace::cont\x1b[0m"); // Delete TOOD
-        // resume normal execution
-        ptrace::cont(self.pid(), signal)?;
-        // wait for inferior to stop or terminate
-        self.wait(None)
     }
 
-    /// Executes a single step in the debugging process.
-    ///
-    /// # param
-    /// - `breakpoints` - A reference to a `HashMap` containing the addresses of breakpoints.
+    /// # brief
+    /// Execute the original instruction under the trap at `addr` in the current thread alone,
+    /// and arm the trap again: it is for another thread, or another frame.
     ///
     /// # return
-    /// A `Result` indicating the status of the operation or an error from the `nix` library.
-    ///
-    pub fn step_over(
-        &mut self, 
-        breakpoints: &HashMap<usize, u8>, 
-        step_points: &mut HashMap<usize, u8>,
-        signal: Option<signal::Signal>, 
-        dwarf_data: &DwarfData
-    ) -> Result<Status, nix::Error> {
-        let mut regs = ptrace::getregs(self.pid())?;
-        let rip = regs.rip as usize;
-        // check if inferior stopped at a breakpoint
-        let line_object: Line = dwarf_data.get_line_from_addr(rip).unwrap();
-        if let Some(ori_instr) = breakpoints.get(&(rip - 1)) {
-            println!("\x1b[31mstopped at a breakpoints\x1b[0m");// Delete TOOD
-            // restore the first byte of the instruction we replaced
-            self.write_byte(rip - 1, *ori_instr).unwrap();
-            // set %rip = %rip - 1 to rewind the instruction pointer
-            regs.rip = (rip - 1) as u64;
-            ptrace::setregs(self.pid(), regs).unwrap();
-            // go to the next instruction
-            ptrace::step(self.pid(), None).unwrap();
-            match self.wait(None).unwrap() {
-                Status::Exited(exit_code) => return Ok(Status::Exited(exit_code)),
-                Status::Signaled(signal) => return Ok(Status::Signaled(signal)),
-                Status::Stopped(_, _) => {
-                    // restore 0xcc in the breakpoint localtion
-                    self.write_byte(rip - 1, 0xcc).unwrap();
-                }
-            }
-        } else if let Some(ori_instr) = step_points.get(&(rip - 1)) {
-            println!("\x1b[32mstopped at a step_points\x1b[0m");// Delete TOOD
-            // restore the first byte of the instruction we replaced
-            self.write_byte(rip - 1, *ori_instr).unwrap();
-            // set %rip = %rip - 1 to rewind the instruction pointer
-            regs.rip = (rip - 1) as u64;
-            ptrace::setregs(self.pid(), regs).unwrap();
-            // go to the next instruction
-            ptrace::step(self.pid(), None).unwrap();
-            match self.wait(None).unwrap() {
-                Status::Exited(exit_code) => return Ok(Status::Exited(exit_code)),
-                Status::Signaled(signal) => return Ok(Status::Signaled(signal)),
-                Status::Stopped(_, _) => {
-                    step_points.remove(&(rip - 1));
-                }
+    /// * `Ok(None)` once past the instruction, or `Ok(Some(Status))` if the thread stopped
+    /// otherwise, by a signal, or the process ended.
+    fn pass_trap(&mut self, addr: usize, ori_instr: u8) -> Result<Option<Status>, TraceError> {
+        self.write_byte(addr, ori_instr)?;
+        self.step(None)?;
+        let status = self.wait()?;
+        if let Status::Stopped(signal, _) = status {
+            self.write_byte(addr, 0xcc)?;
+            if signal == signal::Signal::SIGTRAP {
+                return Ok(None);
             }
-        } 
-        println!("\x1b[35mLine: {:?} \n\x1b[30mAddr: {:?} \nSet Line_number: {}\x1b[0m", &line_object, dwarf_data.get_addr_for_line(None, line_object.number + 1), line_object.number + 1);
-        let next_addr = dwarf_data.get_addr_for_line(None, line_object.number + 1).unwrap();
-        // exist Bug TODO
-        println!("\x1b[37mFind the addr: {:?}\x1b[0m", next_addr); // TODO Delete
-        let ori_instr = self.write_byte(next_addr, 0xcc).unwrap();
-        step_points.insert(next_addr, ori_instr);
-        println!("\x1b[36mbreakpoints: {:?} \nrip: {} \nstep_points: {:?}\x1b[0m", 
-            breakpoints, 
-            rip,
-            step_points,
-        ); // Delete TOOD
+        }
+        Ok(Some(status))
+    }
 
-        // resume normal execution
-        ptrace::cont(self.pid(), signal)?;
-        // wait for inferior to stop due to SIGTRAP, just return if the inferior terminates here
-        self.wait(None)
+    /// # brief
+    /// Turn the stop of a trap that was just executed into a stop at the trap: %rip, one byte
+    /// past it, is rewound to the trap address, so the status, the registers and the
+    /// instruction to execute next all agree. Call it only after resuming with `ptrace::cont`,
+    /// where a SIGTRAP right after a trap comes from it; after a single step, %rip may follow a
+    /// trap address without having executed the trap.
+    ///
+    /// # return
+    /// * The status with the rewound %rip, or `status` as it is if the stop is not at a trap.
+    fn settle_trap(
+        &mut self,
+        status: Status,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &HashMap<usize, u8>
+    ) -> Result<Status, TraceError> {
+        let trap_addr = match status {
+            Status::Stopped(signal::Signal::SIGTRAP, rip) => match trap_address(rip as u64) {
+                Some(trap_addr) if breakpoints.contains_key(&trap_addr) || step_points.contains_key(&trap_addr) => trap_addr,
+                _ => return Ok(status),
+            },
+            _ => return Ok(status),
+        };
+        let mut regs = self.getregs()?;
+        regs.rip = trap_addr as u64;
+        self.setregs(regs)?;
+        // the report comes from the registers as they are now
+        Ok(Status::Stopped(signal::Signal::SIGTRAP, self.getregs()?.rip as usize))
     }
 
     /// # brief
-    /// This function uses the `ptrace` library to retrieve the register state of the current process
-    /// and then loops through the function call stack, printing the source code line and 
-    /// function name at each step.
-    /// 
+    /// If the inferior is stopped at a breakpoint or a step trap, restore the original
+    /// instruction and execute it. Breakpoints are armed again afterwards, step traps are
+    /// removed.
+    ///
     /// # param
-    /// - `debug_data` - A reference to the `DwarfData` containing the debugging information for the
-    ///   current process.
-    ///                                      
+    /// - `breakpoints` - The addresses of the breakpoints and their original bytes.
+    /// - `step_points` - The addresses of the temporary step traps and their original bytes.
     ///
     /// # return
-    /// A `Result` indicating success or an error from the `nix` library.
-    ///
-    pub fn print_backtrace(&self, debug_data: &DwarfData) -> Result<(), nix::Error> {
-        let regs = ptrace::getregs(self.pid())?;
-        let mut rip = regs.rip as usize;
-        let mut rbp = regs.rbp as usize;
-
-        loop {
-            let _line = debug_data.get_line_from_addr(rip);
-            let _func = debug_data.get_function_from_addr(rip);
-
-            match (&_line, &_func) {
-                (None, None) => println!("unknown func (source file not found)"),
-                (Some(line), None) => println!("unknown func ({})", line),
-                (None, Some(func)) => println!("{} (source file not found)", func),
-                (Some(line), Some(func)) => println!("{} ({})", func, line),
+    /// * `Ok(Some(Status))` - the status after executing the original instruction, or `Ok(None)`
+    /// if the inferior is not stopped at a trap.
+    fn step_over_trap(
+        &mut self,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>
+    ) -> Result<Option<Status>, TraceError> {
+        let _patch = PatchGuard::new();
+        // a stop at a trap was rewound by `settle_trap`, %rip is the trap address
+        let trap_addr = self.getregs()?.rip as usize;
+        let (ori_instr, is_breakpoint) = match (breakpoints.get(&trap_addr), step_points.get(&trap_addr)) {
+            (Some(ori_instr), _) => (*ori_instr, true),
+            (None, Some(ori_instr)) => (*ori_instr, false),
+            (None, None) => return Ok(None),
+        };
+        // restore the first byte of the instruction we replaced
+        self.write_byte(trap_addr, ori_instr)?;
+        // go to the next instruction, and wait for inferior to stop due to SIGTRAP, just return
+        // if the inferior terminates here
+        let status = match self.step(None).and_then(|_| self.wait()) {
+            Ok(status) => status,
+            Err(err) => {
+                // the breakpoint stays armed if the process is still there, as the debugger
+                // believes it is
+                if is_breakpoint {
+                    let _ = self.write_byte(trap_addr, 0xcc);
+                }
+                return Err(err);
             }
-
-            if let Some(func) = _func {
-                if func == "main" {
-                    break;
-                } 
+        };
+        if let Status::Stopped(_, _) = status {
+            if is_breakpoint {
+                // restore 0xcc in the breakpoint localtion
+                self.write_byte(trap_addr, 0xcc)?;
             } else {
-                break;
+                step_points.remove(&trap_addr);
             }
-            rip = ptrace::read(self.pid(), ( rbp + 8 ) as ptrace::AddressType)? as usize;
-            rbp = ptrace::read(self.pid(), ( rbp     ) as ptrace::AddressType)? as usize;
         }
-        Ok(())
+        Ok(Some(status))
     }
 
     /// # brief
-    /// Writes a single byte of data to another process's memory and 
-    /// returns the original byte of data at that memory address before writing.
+    /// Execute exactly one machine instruction. If the inferior is stopped at a trap, that is the
+    /// original instruction the trap replaced.
+    ///
+    /// # return
+    /// A `Result` indicating the status of the inferior after the instruction.
+    pub fn step_instruction(
+        &mut self,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>
+    ) -> Result<Status, TraceError> {
+        if let Some(status) = self.step_over_trap(breakpoints, step_points)? {
+            return Ok(status);
+        }
+        self.step(None)?;
+        self.wait()
+    }
+
+    /// # brief
+    /// Step to the beginning of the next source line, stepping over function calls.
+    ///
+    /// A single line may span several line-table rows, so the inferior is single-stepped until it
+    /// reaches a statement row (`is_stmt`) of a different file or line than the starting one.
+    /// A call is detected right after it is executed, by the return address it pushed; the callee
+    /// is then run to a temporary trap at that return address, unless `enter` accepts the
+    /// callee: stepping ends at its first instruction then. Stepping also ends when the
+    /// function returns, when a breakpoint is hit, or when the inferior terminates.
     ///
     /// # param
-    /// - `addr`: usize - memory address to write to
-    /// - `val`: u8 - the byte value to write
+    /// - `breakpoints` - The addresses of the breakpoints and their original bytes.
+    /// - `step_points` - The addresses of the temporary step traps and their original bytes.
+    /// - `signal` - The signal to deliver when the inferior is first resumed over a call.
+    /// - `dwarf_data` - The debug information providing the line table.
+    /// - `enter` - Decides by the address of a callee whether to step into it.
     ///
     /// # return
-    /// Returns a Result<u8, nix::Error> containing the raw bytes at this memory 
-    /// address before writing, or an error object
-    ///
-    pub fn write_byte(&mut self, addr: usize, val: u8) -> Result<u8, nix::Error> {
-        let aligned_addr = align_addr_to_word(addr);
-        let byte_offset = addr - aligned_addr;
-        let word = ptrace::read(self.pid(), aligned_addr as ptrace::AddressType)? as u64;
-
-        let orig_byte = (word >> 8 * byte_offset) & 0xff;
-        let masked_word = word & !(0xff << 8 * byte_offset);
+    /// A `Result` indicating the status of the inferior when stepping ended.
+    pub fn step_over(
+        &mut self, 
+        breakpoints: &HashMap<usize, u8>, 
+        step_points: &mut HashMap<usize, u8>,
+        signal: Option<signal::Signal>, 
+        dwarf_data: &DwarfData,
+        enter: Option<&dyn Fn(usize) -> bool>,
+    ) -> Result<Status, TraceError> {
+        let _patch = PatchGuard::new();
+        // if inferior stopped at a trap, the original instruction is the first one stepped: it
+        // may be a call to step over. A breakpoint there is handled by the loop below.
+        let start = self.getregs()?.rip as usize;
+        if let Some(ori_instr) = step_points.remove(&start) {
+            self.write_byte(start, ori_instr)?;
+        }
+        let mut status = Status::Stopped(signal::Signal::SIGTRAP, start);
+        let mut start_line: Option<Line> = dwarf_data.line_at(start as u64);
+        let mut first = true;
+        let mut signal = signal;
+        loop {
+            let regs = self.getregs()?;
+            let rip = regs.rip as usize;
+            if let Some(line) = dwarf_data.get_statement_at(rip) {
+                let same_line = start_line
+                    .as_ref()
+                    .map_or(false, |start| start.file == line.file && start.number == line.number);
+                if !same_line {
+                    return Ok(status);
+                }
+            }
+            // the value on top of the stack is where a `ret` jumps to
+            let stack_top = self.peek(regs.rsp as usize)? as u64;
+
+            match breakpoints.get(&rip) {
+                // about to execute a breakpoint: stop at it, as `continue` would
+                Some(_) if !first => return Ok(Status::Stopped(signal::Signal::SIGTRAP, rip)),
+                // a breakpoint at the starting point, execute the original instruction
+                Some(ori_instr) => {
+                    self.write_byte(rip, *ori_instr)?;
+                    self.step(None)?;
+                    status = self.wait()?;
+                    if let Status::Stopped(_, _) = status {
+                        self.write_byte(rip, 0xcc)?;
+                    }
+                }
+                None => {
+                    self.step(None)?;
+                    status = self.wait()?;
+                }
+            }
+            first = false;
+            match status {
+                Status::Stopped(signal::Signal::SIGTRAP, _) => {}
+                // a terminating or a real signal ends the step
+                _ => return Ok(status),
+            }
+
+            let new_regs = self.getregs()?;
+            if new_regs.rsp == regs.rsp + 8 && new_regs.rip == stack_top {
+                // returned into the middle of the caller's line, finish that line unless the
+                // caller has no line information
+                start_line = dwarf_data.line_at(new_regs.rip);
+                if start_line.is_none() {
+                    return Ok(status);
+                }
+                continue;
+            }
+            let return_addr = if new_regs.rsp + 8 == regs.rsp {
+                self.peek(new_regs.rsp as usize)? as u64
+            } else {
+                0
+            };
+            // a call pushes the address of the instruction following it
+            let called = return_addr > regs.rip && return_addr <= regs.rip + 15
+                && (new_regs.rip < regs.rip || new_regs.rip > regs.rip + 15);
+            if !called {
+                continue;
+            }
+            if enter.map_or(false, |enter| enter(new_regs.rip as usize)) {
+                return Ok(Status::Stopped(signal::Signal::SIGTRAP, new_regs.rip as usize));
+            }
+            // the call returns when %rsp is back where it was before the call
+            if let Some(status) = self.run_to_return(return_addr as usize, regs.rsp, breakpoints, step_points, signal.take())? {
+                return Ok(status);
+            }
+            status = Status::Stopped(signal::Signal::SIGTRAP, self.getregs()?.rip as usize);
+        }
+    }
+
+    /// # brief
+    /// Resume the inferior until a frame returns to `return_addr`, through a temporary trap
+    /// there. The frame is told apart by its depth: a recursive invocation of the same function
+    /// returns to the same address from a deeper frame, with %rsp still below `frame_rsp`. Such
+    /// a return does not stop; its original instruction is executed and the trap armed again.
+    ///
+    /// # param
+    /// - `return_addr` - The address the frame returns to.
+    /// - `frame_rsp` - %rsp once the frame returned, the address right above its return address.
+    /// - `breakpoints` - The addresses of the breakpoints and their original bytes.
+    /// - `step_points` - The addresses of the temporary step traps and their original bytes.
+    /// - `signal` - The signal to deliver when the inferior is first resumed.
+    ///
+    /// # return
+    /// * `Ok(None)` - the frame returned, the inferior is at `return_addr` with the trap removed,
+    /// or `Ok(Some(Status))` for any other stop, e.g. a breakpoint in a callee.
+    fn run_to_return(
+        &mut self,
+        return_addr: usize,
+        frame_rsp: u64,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>,
+        signal: Option<signal::Signal>,
+    ) -> Result<Option<Status>, TraceError> {
+        let _patch = PatchGuard::new();
+        let planted = !breakpoints.contains_key(&return_addr) && !step_points.contains_key(&return_addr);
+        if planted {
+            let ori_instr = self.write_byte(return_addr, 0xcc)?;
+            step_points.insert(return_addr, ori_instr);
+        }
+        let mut signal = signal;
+        let stepping = self.current;
+        loop {
+            self.resume_all(signal.take(), breakpoints, step_points)?;
+            let status = self.wait()?;
+            let status = self.settle_trap(status, breakpoints, step_points)?;
+            let stopped_rip = match status {
+                Status::Stopped(_, rip) => rip,
+                _ => return Ok(Some(status)),
+            };
+            let passed = if let Some((hook, ori_instr)) = self.at_library_hook(&status, breakpoints) {
+                // the libraries changed during the call, the debugger reads them at the stop
+                self.pass_trap(hook, ori_instr)?
+            } else if stopped_rip != return_addr || !planted {
+                // stopped inside the callee, or at a breakpoint that sits at the return address
+                Some(status)
+            } else if self.current != stepping {
+                // another thread runs the same code: it goes on, and so does the frame
+                self.pass_trap(return_addr, step_points[&return_addr])?
+            } else if self.getregs()?.rsp >= frame_rsp {
+                // the same depth or shallower, e.g. after a longjmp out of the frame
+                break;
+            } else {
+                // a recursive call returned to the same address in a deeper frame: execute the
+                // original instruction there, arm the trap again and keep going
+                self.pass_trap(return_addr, step_points[&return_addr])?
+            };
+            if let Some(status) = passed {
+                // the trap at the return address is not needed
+                if planted {
+                    if let Some(ori_instr) = step_points.remove(&return_addr) {
+                        self.write_byte(return_addr, ori_instr)?;
+                    }
+                }
+                return Ok(Some(status));
+            }
+            self.current = stepping;
+        }
+        // back from the frame: restore the instruction, %rip is at it already
+        let ori_instr = step_points.remove(&return_addr).unwrap();
+        self.write_byte(return_addr, ori_instr)?;
+        Ok(None)
+    }
+
+    /// # brief
+    /// Run until the function the inferior is stopped in returns to its caller, for `finish`.
+    /// Inside recursion this is the return of the current invocation, not of a deeper one.
+    ///
+    /// The return address is found by where %rip is in the frame pointer prologue: on top of
+    /// the stack at the entry of the function, right above it after `push %rbp`, and at
+    /// 8(%rbp) once the frame pointer is set up.
+    ///
+    /// # param
+    /// - `func_addr` - The entry address of the function the inferior is stopped in.
+    /// - `breakpoints` - The addresses of the breakpoints and their original bytes.
+    /// - `step_points` - The addresses of the temporary step traps and their original bytes.
+    ///
+    /// # return
+    /// A `Result` indicating the status of the inferior, stopped at the return address or
+    /// elsewhere.
+    pub fn finish(
+        &mut self,
+        func_addr: usize,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>
+    ) -> Result<Status, TraceError> {
+        let regs = self.getregs()?;
+        // the prologue follows an `endbr64` in code built with control flow protection
+        let mut code = self.read_bytes(func_addr, 4).unwrap_or_default();
+        if let (Some(first), Some(ori_instr)) = (code.first_mut(), breakpoints.get(&func_addr).or(step_points.get(&func_addr))) {
+            *first = *ori_instr;
+        }
+        let entry = if code == [0xf3, 0x0f, 0x1e, 0xfa] { func_addr + 4 } else { func_addr };
+        let rip = regs.rip as usize;
+        let slot = if rip <= entry {
+            regs.rsp
+        } else if rip == entry + 1 {
+            regs.rsp + 8
+        } else {
+            regs.rbp + 8
+        };
+        let return_addr = self.peek(slot as usize)? as usize;
+        let frame_rsp = slot + 8;
+        // the instruction at a trap here runs first, it may be the `ret` itself
+        match self.step_over_trap(breakpoints, step_points)? {
+            Some(Status::Stopped(signal::Signal::SIGTRAP, _)) | None => {}
+            Some(status) => return Ok(status),
+        }
+        let regs = self.getregs()?;
+        if regs.rip as usize == return_addr && regs.rsp >= frame_rsp {
+            return Ok(Status::Stopped(signal::Signal::SIGTRAP, return_addr));
+        }
+        match self.run_to_return(return_addr, frame_rsp, breakpoints, step_points, None)? {
+            Some(status) => Ok(status),
+            None => Ok(Status::Stopped(signal::Signal::SIGTRAP, return_addr)),
+        }
+    }
+
+    /// # brief
+    /// Resume the inferior until it reaches `addr`, through a temporary trap there, e.g. to run
+    /// the prologue of a function just entered. The trap is removed when it is hit.
+    ///
+    /// # return
+    /// A `Result` indicating the status of the inferior when it stopped, at `addr` or elsewhere.
+    pub fn run_to(
+        &mut self,
+        addr: usize,
+        breakpoints: &HashMap<usize, u8>,
+        step_points: &mut HashMap<usize, u8>
+    ) -> Result<Status, TraceError> {
+        let _patch = PatchGuard::new();
+        if self.getregs()?.rip as usize == addr {
+            return Ok(Status::Stopped(signal::Signal::SIGTRAP, addr));
+        }
+        if breakpoints.contains_key(&addr) || step_points.contains_key(&addr) {
+            // the trap already there stops the inferior
+            return Ok(self.continue_to(None, breakpoints, step_points, None, Some(addr))?.0);
+        }
+        let ori_instr = self.write_byte(addr, 0xcc)?;
+        step_points.insert(addr, ori_instr);
+        let (status, _) = self.continue_to(None, breakpoints, step_points, None, Some(addr))?;
+        let stopped_rip = match status {
+            Status::Stopped(_, rip) => rip,
+            // the process is gone, and the trap with it
+            _ => {
+                step_points.remove(&addr);
+                return Ok(status);
+            }
+        };
+        step_points.remove(&addr);
+        self.write_byte(addr, ori_instr)?;
+        if stopped_rip != addr {
+            return Ok(status);
+        }
+        Ok(Status::Stopped(signal::Signal::SIGTRAP, self.getregs()?.rip as usize))
+    }
+
+    /// # brief
+    /// This function uses the `ptrace` library to retrieve the register state of the current process
+    /// and then loops through the function call stack, rendering the source code line and 
+    /// function name at each step.
+    /// 
+    /// # param
+    /// - `debug_data` - A reference to the `DwarfData` containing the debugging information for the
+    ///   current process.
+    /// - `force` - Follow frame pointers outside the `[stack]` mapping, e.g. for heap-allocated
+    ///   coroutine stacks.
+    /// - `arguments` - Renders the arguments of a frame, e.g. `path=0x4020 "/etc/app.conf"`, or
+    ///   `None` to print the frame without them.
+    /// - `format` - How the addresses of frames without a function are shown.
+    ///                                      
+    ///
+    /// # return
+    /// The lines of the backtrace, innermost frame first, or an error from the `nix` library.
+    ///
+    pub fn backtrace(
+        &self,
+        debug_data: &DwarfData,
+        force: bool,
+        arguments: &dyn Fn(&Frame) -> Option<String>,
+        format: &AddressFormat,
+    ) -> Result<Vec<String>, TraceError> {
+        Ok(render_backtrace(self.stack(debug_data, force)?, debug_data, arguments, format))
+    }
+
+    /// # brief
+    /// Walk the frames of the stopped inferior by their frame pointers, up to `main`, for
+    /// `backtrace` and for the users of the frames themselves, see `walk_stack`.
+    ///
+    /// # param
+    /// - `force` - Follow frame pointers outside the `[stack]` mapping.
+    ///
+    /// # return
+    /// * The frames, innermost first, or an error from the `nix` library.
+    pub fn stack(&self, debug_data: &DwarfData, force: bool) -> Result<Stack, TraceError> {
+        let mappings = maps::read_maps(self.pid()).unwrap_or_default();
+        walk_stack(self.getregs()?, &mappings, debug_data, force, &|addr| self.peek(addr).map(|word| word as u64))
+    }
+
+    /// # brief
+    /// Get the address that caused the fault the inferior is stopped by, from the siginfo of
+    /// the signal.
+    pub fn fault_address(&self) -> Result<usize, TraceError> {
+        let info = self.getsiginfo()?;
+        Ok(unsafe { info.si_addr() } as usize)
+    }
+
+    /// # brief
+    /// Read which system call the stopped inferior is in, where in the kernel it sleeps and its
+    /// process state. The files are in `/proc`, so a remote target has none.
+    pub fn syscall_state(&self) -> io::Result<SyscallState> {
+        let read = |name: &str| fs::read_to_string(format!("/proc/{}/{}", self.pid, name));
+        Ok(SyscallState::parse(&read("syscall")?, &read("wchan").unwrap_or_default(), &read("stat")?))
+    }
+
+    /// # brief
+    /// List the open file descriptors of the inferior, sorted by number, with their flags and
+    /// offset from `fdinfo`. A descriptor closed while the directory is read is left out.
+    pub fn open_fds(&self) -> io::Result<Vec<FdInfo>> {
+        let mut fds = Vec::new();
+        for entry in fs::read_dir(format!("/proc/{}/fd", self.pid))? {
+            let fd = match entry.ok().and_then(|entry| entry.file_name().to_str()?.parse::<i32>().ok()) {
+                Some(fd) => fd,
+                None => continue,
+            };
+            let target = match fs::read_link(format!("/proc/{}/fd/{}", self.pid, fd)) {
+                Ok(target) => target.to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            let info = fs::read_to_string(format!("/proc/{}/fdinfo/{}", self.pid, fd)).unwrap_or_default();
+            let field = |name: &str| info.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(':')).map(str::trim);
+            fds.push(FdInfo {
+                fd,
+                target,
+                // in octal
+                flags: field("flags").and_then(|flags| i32::from_str_radix(flags, 8).ok()),
+                pos: field("pos").and_then(|pos| pos.parse().ok()),
+            });
+        }
+        fds.sort_by_key(|fd| fd.fd);
+        Ok(fds)
+    }
+
+    /// # brief
+    /// Read the CPU time the inferior used so far, from the `utime` and `stime` fields of
+    /// `/proc/<pid>/stat`.
+    ///
+    /// # return
+    /// * The time in user mode and in system mode, or `None` once the process has ended and
+    /// was reaped: its file is gone then.
+    pub fn cpu_times(&self) -> Option<(Duration, Duration)> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", self.pid)).ok()?;
+        // the name in parentheses may hold spaces; the state, field 3, follows the last one
+        let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks_per_second <= 0 {
+            return None;
+        }
+        let time = |field: usize| -> Option<Duration> {
+            let ticks: u64 = fields.get(field - 3)?.parse().ok()?;
+            Some(Duration::from_secs_f64(ticks as f64 / ticks_per_second as f64))
+        };
+        Some((time(14)?, time(15)?))
+    }
+
+    /// # brief
+    /// Get the general purpose registers of the stopped inferior, for the `Context` of the
+    /// stop that the inspection commands read them from. Resuming reads them with `getregs`.
+    pub fn registers(&self) -> Result<libc::user_regs_struct, TraceError> {
+        debug_assert!(context::reading(), "the registers of a stop are read through its Context");
+        self.getregs()
+    }
+
+    /// # brief
+    /// Read the x87 and SSE registers of the stopped inferior. nix does not wrap
+    /// `PTRACE_GETFPREGS`, so the request is issued directly.
+    pub fn get_fpregs(&self) -> Result<FpRegisters, TraceError> {
+        // the kernel fills the whole struct, its size is fixed by the x86_64 ABI
+        let mut raw: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+        request(TraceOp::GetFpRegs, self.current, None, || {
+            let res = unsafe {
+                libc::ptrace(
+                    libc::PTRACE_GETFPREGS,
+                    self.current.as_raw(),
+                    std::ptr::null_mut::<libc::c_void>(),
+                    &mut raw as *mut libc::user_fpregs_struct as *mut libc::c_void,
+                )
+            };
+            if res == -1 {
+                return Err(nix::Error::last());
+            }
+            Ok(())
+        })?;
+        Ok(FpRegisters::from_raw(&raw))
+    }
+
+    /// # brief
+    /// Reads `len` bytes of the inferior's memory, one word at a time.
+    ///
+    /// # param
+    /// - `addr`: usize - memory address to start reading at
+    /// - `len`: usize - number of bytes to read
+    ///
+    /// # return
+    /// Returns a Result<Vec<u8>, TraceError> containing the bytes read, or an error object if
+    /// any word of the range can't be read
+    ///
+    pub fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, TraceError> {
+        match self.read_bytes_partial(addr, len) {
+            (bytes, None) => Ok(bytes),
+            (_, Some(err)) => Err(err.error),
+        }
+    }
+
+    /// # brief
+    /// Reads `len` bytes of the inferior's memory like `read_bytes`, keeping the bytes read
+    /// before a word that can't be, e.g. past the end of a mapping. Aligned words never cross a
+    /// page, so every readable byte of the range is returned.
+    ///
+    /// # return
+    /// * The bytes read, and the failure if fewer than `len` could be.
+    pub fn read_bytes_partial(&self, addr: usize, len: usize) -> (Vec<u8>, Option<PartialTransfer>) {
+        let mut bytes = Vec::with_capacity(len);
+        let mut word_addr = align_addr_to_word(addr);
+        while bytes.len() < len {
+            let word = match self.peek(word_addr as usize) {
+                Ok(word) => word as u64,
+                Err(error) => {
+                    let done = bytes.len();
+                    return (bytes, Some(PartialTransfer { done, error }));
+                }
+            };
+            for (i, byte) in word.to_le_bytes().iter().enumerate() {
+                if word_addr + i >= addr && bytes.len() < len {
+                    bytes.push(*byte);
+                }
+            }
+            word_addr += size_of::<usize>();
+        }
+        (bytes, None)
+    }
+
+    /// # brief
+    /// Writes `bytes` to the inferior's memory, one word at a time: the words at both ends are
+    /// read first to keep their bytes outside of the range. Stops at the first word that can't
+    /// be read or written, the words before it stay written.
+    ///
+    /// # return
+    /// * `Ok(())`, or the failure with how many bytes were written before it.
+    pub fn write_bytes(&mut self, addr: usize, bytes: &[u8]) -> Result<(), PartialTransfer> {
+        let word_size = size_of::<usize>();
+        let end = addr + bytes.len();
+        let mut word_addr = align_addr_to_word(addr);
+        while word_addr < end {
+            let done = word_addr.max(addr) - addr;
+            let (first, last) = (word_addr.max(addr), (word_addr + word_size).min(end));
+            let result = if first == word_addr && last == word_addr + word_size {
+                Ok(u64::from_le_bytes(bytes[done..done + word_size].try_into().unwrap()))
+            } else {
+                self.peek(word_addr).map(|word| {
+                    let mut word = (word as u64).to_le_bytes();
+                    word[first - word_addr..last - word_addr].copy_from_slice(&bytes[done..last - addr]);
+                    u64::from_le_bytes(word)
+                })
+            };
+            result.and_then(|word| self.poke(word_addr, word)).map_err(|error| PartialTransfer { done, error })?;
+            word_addr += word_size;
+        }
+        Ok(())
+    }
+
+    /// # brief
+    /// Writes a single byte of data to another process's memory and 
+    /// returns the original byte of data at that memory address before writing.
+    ///
+    /// # param
+    /// - `addr`: usize - memory address to write to
+    /// - `val`: u8 - the byte value to write
+    ///
+    /// # return
+    /// Returns a Result<u8, TraceError> containing the raw bytes at this memory 
+    /// address before writing, or an error object
+    ///
+    pub fn write_byte(&mut self, addr: usize, val: u8) -> Result<u8, TraceError> {
+        let aligned_addr = align_addr_to_word(addr);
+        let byte_offset = addr - aligned_addr;
+        let word = self.peek(aligned_addr as usize)? as u64;
+
+        let orig_byte = (word >> 8 * byte_offset) & 0xff;
+        let masked_word = word & !(0xff << 8 * byte_offset);
         let updated_word = masked_word | ((val as u64) << 8 * byte_offset);
 
-        ptrace::write(
-          self.pid(),
-          aligned_addr as ptrace::AddressType,
-          updated_word as *mut std::ffi::c_void,
-        )?;
+        self.poke(aligned_addr, updated_word)?;
         Ok(orig_byte as u8)
     }
+
+    /// # brief
+    /// Write bytes into one word with a single read and write of it, see `group_by_word`.
+    ///
+    /// # param
+    /// - `word_addr` : The aligned address of the word.
+    /// - `patches` : The address of each byte to write, all in the word, and its new value.
+    ///
+    /// # return
+    /// * The bytes the patches replaced, in the order of `patches`.
+    fn patch_word(&mut self, word_addr: usize, patches: &[(usize, u8)]) -> Result<Vec<u8>, TraceError> {
+        let mut word = (self.peek(word_addr)? as u64).to_le_bytes();
+        let orig_bytes = patches
+            .iter()
+            .map(|(addr, byte)| std::mem::replace(&mut word[addr - word_addr], *byte))
+            .collect();
+        self.poke(word_addr, u64::from_le_bytes(word))?;
+        Ok(orig_bytes)
+    }
+
+    /// # brief
+    /// Read the word at `addr` with PTRACE_PEEKDATA.
+    fn peek(&self, addr: usize) -> Result<libc::c_long, TraceError> {
+        request(TraceOp::PeekData, self.current, Some(addr), || {
+            ptrace::read(self.current, addr as ptrace::AddressType)
+        })
+    }
+
+    /// # brief
+    /// Write `word` at `addr` with PTRACE_POKEDATA.
+    fn poke(&self, addr: usize, word: u64) -> Result<(), TraceError> {
+        request(TraceOp::PokeData, self.current, Some(addr), || {
+            ptrace::write(self.current, addr as ptrace::AddressType, word as *mut std::ffi::c_void)
+        })
+    }
+
+    fn getregs(&self) -> Result<libc::user_regs_struct, TraceError> {
+        self.thread_registers(self.current)
+    }
+
+    fn setregs(&self, regs: libc::user_regs_struct) -> Result<(), TraceError> {
+        self.set_thread_registers(self.current, regs)
+    }
+
+    fn getsiginfo(&self) -> Result<libc::siginfo_t, TraceError> {
+        request(TraceOp::GetSigInfo, self.current, None, || ptrace::getsiginfo(self.current))
+    }
+
+    /// # brief
+    /// Execute one instruction of the current thread, delivering `signal` if any. The other
+    /// threads stay stopped.
+    fn step(&mut self, signal: Option<signal::Signal>) -> Result<(), TraceError> {
+        let tid = self.current;
+        request(TraceOp::Step, tid, None, || ptrace::step(tid, signal))?;
+        self.all_running = false;
+        if let Some(thread) = self.thread_mut(tid) {
+            thread.running = true;
+        }
+        Ok(())
+    }
+
+    /// # brief
+    /// Wait for a change of state of a thread with `waitpid`.
+    fn wait_status(&self, tid: Pid, option: Option<WaitPidFlag>) -> Result<WaitStatus, TraceError> {
+        // the threads other than the main one are only reported with __WALL
+        let option = option.map_or(WaitPidFlag::__WALL, |option| option | WaitPidFlag::__WALL);
+        request(TraceOp::Wait, tid, None, || waitpid(tid, Some(option)))
+    }
+
+    /// # brief
+    /// Send `signal` to the inferior.
+    fn signal(&self, signal: signal::Signal) -> Result<(), TraceError> {
+        request(TraceOp::Kill, self.pid(), None, || signal::kill(self.pid(), signal))
+    }
+}
+
+impl TargetAccess for Inferior {
+    fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+        Ok(Inferior::read_bytes(self, addr, len)?)
+    }
+
+    fn registers(&self) -> Result<libc::user_regs_struct, nix::Error> {
+        Ok(Inferior::registers(self)?)
+    }
+}
+
+/// # brief
+/// Render the frames of a stack as the lines of `backtrace`: the function with its arguments
+/// and the line of each frame, innermost first, with the repeated frames of a recursion folded.
+///
+/// # param
+/// - `arguments` - Renders the arguments of a frame, or `None` to print the frame without them.
+/// - `format` - How the addresses of frames without a function are shown.
+pub fn render_backtrace(
+    stack: Stack,
+    debug_data: &DwarfData,
+    arguments: &dyn Fn(&Frame) -> Option<String>,
+    format: &AddressFormat,
+) -> Vec<String> {
+    let Stack { frames, bad_pc, incomplete } = stack;
+    let mut lines = Vec::new();
+    if let Some(pc) = bad_pc {
+        lines.push(format!("{} (not executable code, called from the frame below)", format.address(pc)));
+    }
+    let mut i = 0;
+    while i < frames.len() {
+        let (period, repeats) = repeated_frames(&frames[i..]);
+        for frame in frames[i..i + period].iter() {
+            let func = frame.function.as_ref().map(|func| match arguments(frame) {
+                Some(arguments) => format!("{}({})", func, arguments),
+                None => func.clone(),
+            });
+            let line = frame.line.clone().or_else(|| debug_data.line_at(frame.rip as u64));
+            let entry = match (line, func) {
+                (None, None) => format!("unknown func at {} (source file not found)", format.address(frame.rip)),
+                (Some(line), None) => format!("unknown func ({})", line),
+                (None, Some(func)) => format!("{} (source file not found)", func),
+                (Some(line), Some(func)) => format!("{} ({})", func, line),
+            };
+            lines.push(if frame.inlined { format!("{} (inlined)", entry) } else { entry });
+        }
+        if repeats > 0 {
+            let frames_word = if period == 1 { "frame" } else { "frames" };
+            lines.push(format!("... previous {} {} repeated {} times ...", period, frames_word, repeats));
+        }
+        i += period * (repeats + 1);
+    }
+    lines.extend(incomplete);
+    lines
+}
+
+/// # brief
+/// Walk the frames of a stopped program by their frame pointers, from the registers of its
+/// innermost frame up to `main`: of a traced process, or of a core dump.
+///
+/// # param
+/// - `mappings` - The mappings of the program, to tell a call through a bad function pointer
+///   and to keep to the `[stack]` mapping; empty if they are unknown.
+/// - `force` - Follow frame pointers outside the `[stack]` mapping.
+/// - `peek` - Reads the word at an address of the program.
+///
+/// # return
+/// * The frames, innermost first, or the error of the first word that could not be read.
+pub fn walk_stack<E: fmt::Display>(
+    regs: libc::user_regs_struct,
+    mappings: &[maps::Mapping],
+    debug_data: &DwarfData,
+    force: bool,
+    peek: &dyn Fn(usize) -> Result<u64, E>,
+) -> Result<Stack, E> {
+    let mut rip = regs.rip as usize;
+    let mut rbp = regs.rbp as usize;
+    // a corrupted rbp would read unrelated memory and show bogus frames, so only follow
+    // frame pointers into the stack mapping unless forced
+    let stack = if force {
+        None
+    } else {
+        mappings.iter().find(|mapping| mapping.path == "[stack]").cloned()
+    };
+    let mut innermost = true;
+    let mut bad_pc = None;
+    if !mappings.is_empty() && !maps::is_executable(mappings, rip) {
+        // a call through a bad function pointer: the call pushed the return address, but
+        // the callee never ran its prologue, so %rbp still belongs to the caller
+        bad_pc = Some(rip);
+        rip = peek(regs.rsp as usize)? as usize;
+        innermost = false;
+    }
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut incomplete = None;
+    loop {
+        // the functions inlined at the pc come first, as frames of their own
+        let mut inlined = debug_data.inlined_frames(rip as u64);
+        let physical = inlined.pop();
+        for frame in inlined {
+            frames.push(Frame {rip, rbp, function: Some(frame.function), line: frame.line, innermost, inlined: true});
+        }
+        let (_func, line) = match physical {
+            Some(frame) => (Some(frame.function), frame.line),
+            None => (debug_data.function_at(rip as u64), None),
+        };
+        frames.push(Frame {rip, rbp, function: _func.clone(), line, innermost, inlined: false});
+        innermost = false;
+
+        if let Some(func) = _func {
+            if func == "main" {
+                break;
+            } 
+        } else {
+            break;
+        }
+        if let Some(stack) = &stack {
+            if !maps::holds_frame(stack, rbp) {
+                incomplete = Some(format!("(frame pointer {:#x} outside stack, backtrace may be incomplete)", rbp));
+                break;
+            }
+        }
+        let frame = peek(rbp + 8 as usize)
+            .and_then(|ret| Ok((ret, peek(rbp as usize)?)));
+        match frame {
+            Ok((ret, saved_rbp)) => {
+                rip = ret as usize;
+                rbp = saved_rbp as usize;
+            }
+            Err(err) => {
+                incomplete = Some(format!("(cannot read frame at {:#x}: {}, backtrace may be incomplete)", rbp, err));
+                break;
+            }
+        }
+    }
+    Ok(Stack { frames, bad_pc, incomplete })
+}
+
+/// The longest cycle of frames `repeated_frames` looks for, e.g. mutual recursion of 4 functions
+const MAX_REPEATED_PERIOD: usize = 4;
+
+/// The fewest repetitions of a cycle worth folding in a backtrace
+const MIN_FOLDED_REPEATS: usize = 3;
+
+/// The frames of a stopped program, as `walk_stack` walked them
+pub struct Stack {
+    /// Innermost first
+    pub frames: Vec<Frame>,
+    /// The pc when it is outside executable code, after a call through a bad function pointer:
+    /// the frames start at the caller then
+    pub bad_pc: Option<usize>,
+    /// Why the walk ended before `main`, when it did
+    pub incomplete: Option<String>,
+}
+
+/// One frame of a backtrace
+#[derive(Clone)]
+pub struct Frame {
+    /// The pc, a return address for all frames but the innermost
+    pub rip: usize,
+    /// The frame pointer of the frame
+    pub rbp: usize,
+    pub function: Option<String>,
+    /// Where the frame is in its function: for a function inlined into another, the line of
+    /// the inlined call in the frame below
+    pub line: Option<Line>,
+    /// Whether this is the frame the inferior is stopped in, where all registers are known.
+    /// The outer frames only know their %rbp.
+    pub innermost: bool,
+    /// Whether the function was inlined into the frame below it, which has the same pc and
+    /// stack frame
+    pub inlined: bool,
+}
+
+impl Frame {
+    /// Whether two frames run the same code, whatever their stack.
+    fn same_code(&self, other: &Frame) -> bool {
+        self.rip == other.rip && self.function == other.function && self.inlined == other.inlined
+    }
+}
+
+/// # brief
+/// Find a cycle of frames repeated right at the start of `frames`, comparing the (pc, function)
+/// pairs. A recursive call returns to the same pc in every frame, so deep recursion shows up as
+/// the same few pairs over and over.
+///
+/// # return
+/// * `(period, repeats)` - the length of the cycle and how many times it repeats after its
+/// first occurrence, or `(1, 0)` if nothing repeats often enough to be folded.
+fn repeated_frames(frames: &[Frame]) -> (usize, usize) {
+    let same = |a: &[Frame], b: &[Frame]| a.iter().zip(b).all(|(a, b)| a.same_code(b));
+    let mut best = (1, 0);
+    for period in 1..=MAX_REPEATED_PERIOD.min(frames.len()) {
+        let mut repeats = 0;
+        while (repeats + 2) * period <= frames.len()
+            && same(&frames[(repeats + 1) * period..(repeats + 2) * period], &frames[..period])
+        {
+            repeats += 1;
+        }
+        // prefer the cycle covering the most frames
+        if repeats >= MIN_FOLDED_REPEATS && period * repeats > best.0 * best.1 {
+            best = (period, repeats);
+        }
+    }
+    best
+}
+
+/// How often `wait_until` checks whether the inferior stopped
+const TIMED_WAIT_POLL: Duration = Duration::from_millis(10);
+
+/// How often the wait checks the threads of the inferior when several of them run
+const THREAD_POLL: Duration = Duration::from_millis(1);
+
+/// # brief
+/// Send `signal` to the thread `tid` of the process `pid` with `tgkill`, which nix does not
+/// wrap: `kill` would let any thread of the process take it.
+fn tgkill(pid: Pid, tid: Pid, signal: signal::Signal) -> nix::Result<()> {
+    let res = unsafe { libc::syscall(libc::SYS_tgkill, pid.as_raw(), tid.as_raw(), signal as libc::c_int) };
+    if res == -1 {
+        return Err(nix::Error::last());
+    }
+    Ok(())
+}
+
+/// # brief
+/// Check whether a thread ended and waits to be reaped, as the main thread does when it exits
+/// before the others: it reports nothing until the end of the process.
+fn is_zombie(tid: Pid) -> bool {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", tid)).unwrap_or_default();
+    // the name in parentheses may hold spaces; the state follows the last one
+    stat.rfind(')').map_or(false, |end| stat[end + 1..].trim_start().starts_with('Z'))
+}
+
+/// # brief
+/// Get the address of the trap an inferior stopped by, one byte before %rip.
+///
+/// # return
+/// * `None` if %rip is 0, e.g. after a jump through a null function pointer, in which case the
+/// stop can't come from a trap.
+fn trap_address(rip: u64) -> Option<usize> {
+    (rip as usize).checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use nix::sys::signal::Signal;
+
+    #[test]
+    fn parses_signal_names_and_numbers() {
+        let cases = [
+            ("SIGUSR1", Some(Signal::SIGUSR1)),
+            ("USR1", Some(Signal::SIGUSR1)),
+            ("usr1", Some(Signal::SIGUSR1)),
+            ("SigSegv", Some(Signal::SIGSEGV)),
+            ("10", Some(Signal::SIGUSR1)),
+            ("9", Some(Signal::SIGKILL)),
+            ("0", None),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(parse_signal(text), Ok(*expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_signal() {
+        for text in ["", "junk", "SIG", "SIGFOO", "-1", "1000", "USR1 "].iter() {
+            let err = parse_signal(text).unwrap_err();
+            assert!(err.contains(&format!("\"{}\"", text)), "{}: {}", text, err);
+        }
+    }
+
+    #[test]
+    fn spawning_a_missing_program_fails() {
+        match Inferior::new("/nonexistent/deet-test", &[], &[], None) {
+            Err(Error::SpawnFailed(err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+            Err(err) => panic!("expected SpawnFailed, got {}", err),
+            Ok(mut inferior) => {
+                inferior.kill();
+                panic!("a missing program was started");
+            }
+        }
+    }
+
+    #[test]
+    fn spawned_program_stops_at_exec() {
+        let mut inferior = Inferior::new("/bin/false", &[], &[], None).unwrap();
+        // stopped at the SIGTRAP after exec, before the first instruction of the program
+        assert!(inferior.poll_status().unwrap().is_none());
+        let (status, timed_out) = inferior.continue_run(None, &HashMap::new(), &mut HashMap::new(), None).unwrap();
+        assert!(matches!(status, Status::Exited(1)), "{:?}", status);
+        assert!(!timed_out);
+        inferior.kill();
+    }
+
+    #[test]
+    fn environment_changes_are_made_for_the_program() {
+        std::env::set_var("DEET_TEST_REMOVED", "1");
+        let environment = [("DEET_TEST_ADDED".to_string(), Some("yes".to_string())), ("DEET_TEST_REMOVED".to_string(), None)];
+        let check = ["-c".to_string(), "test \"$DEET_TEST_ADDED\" = yes && test -z \"${DEET_TEST_REMOVED+set}\"".to_string()];
+        let mut inferior = Inferior::new("/bin/sh", &check, &environment, None).unwrap();
+        let (status, _) = inferior.continue_run(None, &HashMap::new(), &mut HashMap::new(), None).unwrap();
+        assert!(matches!(status, Status::Exited(0)), "{:?}", status);
+        inferior.kill();
+    }
+
+    #[test]
+    fn first_stop_must_be_the_exec_trap() {
+        assert!(exec_stop(Status::Stopped(Signal::SIGTRAP, 0x401000)).is_ok());
+        assert!(matches!(exec_stop(Status::Stopped(Signal::SIGSTOP, 0)), Err(Error::UnexpectedStop(Signal::SIGSTOP))));
+        assert!(matches!(exec_stop(Status::Exited(127)), Err(Error::ExitedBeforeStart(Status::Exited(127)))));
+        let killed = exec_stop(Status::Signaled(Signal::SIGKILL, false)).unwrap_err();
+        assert!(matches!(killed, Error::ExitedBeforeStart(Status::Signaled(Signal::SIGKILL, false))));
+        assert!(killed.to_string().starts_with("the child terminated before it started ("), "{}", killed);
+    }
+
+    /// # brief
+    /// Start `program` and let it run, as after `run`.
+    fn running(program: &str, args: &[&str]) -> Inferior {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut inferior = Inferior::new(program, &args, &[], None).unwrap();
+        inferior.resume_all(None, &HashMap::new(), &mut HashMap::new()).unwrap();
+        inferior
+    }
+
+    /// # brief
+    /// Poll the inferior until its status changes, for at most five seconds.
+    fn poll_until_changed(inferior: &mut Inferior) -> Status {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(status) = inferior.poll_status().unwrap() {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the status of the inferior did not change");
+    }
+
+    #[test]
+    fn poll_of_a_sleeping_program_reports_no_change() {
+        let mut inferior = running("/bin/sleep", &["5"]);
+        assert!(inferior.poll_status().unwrap().is_none());
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(inferior.poll_status().unwrap().is_none());
+        inferior.kill();
+    }
+
+    #[test]
+    fn poll_reaps_an_exit() {
+        let mut inferior = running("/bin/sh", &["-c", "exit 3"]);
+        assert!(matches!(poll_until_changed(&mut inferior), Status::Exited(3)));
+        // reaped: there is nothing left to wait for
+        let err = inferior.poll_status().unwrap_err();
+        assert_eq!(err.source, nix::Error::Sys(Errno::ECHILD));
+    }
+
+    #[test]
+    fn poll_exit_notices_a_program_killed_while_stopped() {
+        // stopped at exec
+        let mut inferior = Inferior::new("/bin/sleep", &["5".to_string()], &[], None).unwrap();
+        assert!(inferior.poll_exit().is_none());
+        // killed by another process between two commands
+        nix::sys::signal::kill(inferior.pid(), Signal::SIGKILL).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let status = loop {
+            if let Some(status) = inferior.poll_exit() {
+                break status;
+            }
+            assert!(Instant::now() < deadline, "the kill was not noticed");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert!(matches!(status, Status::Signaled(Signal::SIGKILL, false)), "{:?}", status);
+        inferior.kill();
+    }
+
+    /// Four one-byte instructions at `fixture::TEXT`, then an exit with status 7
+    const NOPS: &str = "    nop\n    nop\n    nop\n    nop\n";
+
+    #[test]
+    fn breakpoint_stop_is_reported_at_the_trap() {
+        let program = fixture::assemble("breakpoint_stop", &format!("{}{}", NOPS, fixture::EXIT_7));
+        let mut inferior = Inferior::new(&program, &[], &[], None).unwrap();
+        let trap = fixture::TEXT + 2;
+        let mut breakpoints = HashMap::from([(trap, 0)]);
+        let (results, _) = inferior.install_breakpoints(&mut breakpoints, &CancelToken::new(), &|_, _| {});
+        assert!(results[0].1.is_ok());
+        assert_eq!(breakpoints[&trap], 0x90);
+
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut HashMap::new(), None).unwrap();
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+        // rewound already: the registers agree with the report
+        assert_eq!(inferior.getregs().unwrap().rip as usize, trap);
+        // resuming executes the original instruction rather than the trap again
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut HashMap::new(), None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
+    #[test]
+    fn leftover_step_trap_is_resumed_through() {
+        let program = fixture::assemble("leftover_step_trap", &format!("{}{}", NOPS, fixture::EXIT_7));
+        let mut inferior = Inferior::new(&program, &[], &[], None).unwrap();
+        let (step_trap, trap) = (fixture::TEXT + 1, fixture::TEXT + 3);
+        let mut step_points = HashMap::from([(step_trap, inferior.write_byte(step_trap, 0xcc).unwrap())]);
+        let mut breakpoints = HashMap::from([(trap, 0)]);
+        inferior.install_breakpoints(&mut breakpoints, &CancelToken::new(), &|_, _| {});
+
+        // the step trap is not a stop, the breakpoint after it is
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+        assert!(step_points.is_empty());
+        assert_eq!(inferior.read_bytes(step_trap, 1).unwrap(), [0x90]);
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
+    #[test]
+    fn deterministic_programs_run_without_address_randomization() {
+        set_disable_randomization(true);
+        let spawned = Inferior::new("/bin/sleep", &["5".to_string()], &[], None);
+        set_disable_randomization(false);
+        let mut inferior = spawned.unwrap();
+        let persona = std::fs::read_to_string(format!("/proc/{}/personality", inferior.pid())).unwrap();
+        let persona = i32::from_str_radix(persona.trim(), 16).unwrap();
+        assert_ne!(persona & libc::ADDR_NO_RANDOMIZE, 0, "personality {:#x}", persona);
+        inferior.kill();
+    }
+
+    /// # brief
+    /// A descriptor without an `fdinfo` reading, only its number and target.
+    fn fd(fd: i32, target: &str) -> FdInfo {
+        FdInfo { fd, target: target.to_string(), flags: None, pos: None }
+    }
+
+    #[test]
+    fn fd_kinds_come_from_the_target() {
+        let kinds: Vec<String> = ["/dev/null", "socket:[4711]", "pipe:[812]", "anon_inode:[eventfd]", "anon_inode:[eventpoll]"]
+            .iter()
+            .map(|target| fd(3, target).kind())
+            .collect();
+        assert_eq!(kinds, ["file", "socket", "pipe", "eventfd", "eventpoll"]);
+    }
+
+    #[test]
+    fn fd_flags_are_the_access_mode_and_the_flags_of_leaks() {
+        let flags = |flags: Option<i32>| FdInfo { flags, ..fd(3, "/dev/null") }.describe_flags();
+        // O_RDONLY
+        assert_eq!(flags(Some(0)), "r");
+        assert_eq!(flags(Some(libc::O_WRONLY | libc::O_APPEND)), "w,append");
+        // O_LARGEFILE, always set on x86_64, is left out
+        assert_eq!(flags(Some(libc::O_RDWR | libc::O_CLOEXEC | libc::O_NONBLOCK | 0o100000)), "rw,cloexec,nonblock");
+        assert_eq!(flags(None), "?");
+    }
+
+    #[test]
+    fn open_fds_lists_the_descriptors_opened_since_a_stop() {
+        // open /dev/null with O_CLOEXEC, then make a pipe, an eventfd and a socket
+        let code = "    nop\n    nop\n    \
+                    mov $2, %eax\n    lea path(%rip), %rdi\n    mov $0x80000, %esi\n    syscall\n    \
+                    sub $16, %rsp\n    mov %rsp, %rdi\n    xor %esi, %esi\n    mov $293, %eax\n    syscall\n    \
+                    mov $290, %eax\n    xor %edi, %edi\n    xor %esi, %esi\n    syscall\n    \
+                    mov $41, %eax\n    mov $1, %edi\n    mov $1, %esi\n    xor %edx, %edx\n    syscall\n    \
+                    .org 0x60, 0x90\n    nop\n";
+        let program = fixture::assemble("open_fds", &format!("{}{}path:\n    .asciz \"/dev/null\"\n", code, fixture::EXIT_7));
+        let mut inferior = Inferior::new(&program, &[], &[], None).unwrap();
+        let (before, after) = (fixture::TEXT + 1, fixture::TEXT + 0x60);
+        let mut breakpoints = HashMap::from([(before, 0), (after, 0)]);
+        inferior.install_breakpoints(&mut breakpoints, &CancelToken::new(), &|_, _| {});
+        let mut step_points = HashMap::new();
+
+        inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        let open: Vec<i32> = inferior.open_fds().unwrap().iter().map(|fd| fd.fd).collect();
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == after), "{:?}", status);
+        let opened: Vec<FdInfo> = inferior.open_fds().unwrap().into_iter().filter(|fd| !open.contains(&fd.fd)).collect();
+        let kinds: Vec<(String, String)> = opened.iter().map(|fd| (fd.kind(), fd.describe_flags())).collect();
+        assert_eq!(
+            kinds,
+            [("file", "r,cloexec"), ("pipe", "r"), ("pipe", "w"), ("eventfd", "rw"), ("socket", "rw")]
+                .map(|(kind, flags)| (kind.to_string(), flags.to_string()))
+        );
+        assert_eq!((opened[0].target.as_str(), opened[0].pos), ("/dev/null", Some(0)));
+        inferior.kill();
+    }
+
+    #[test]
+    fn syscall_state_parses_the_proc_files() {
+        let stat = "4711 (a (b) c) t 1 4711";
+        let state = SyscallState::parse("35 0x40101c 0x0 0x0 0x0 0x0 0x1f 0x7ffd7b888b30 0x401010\n", "hrtimer_nanosleep", stat);
+        assert_eq!(state.syscall, Some((35, [0x40101c, 0, 0, 0, 0, 0x1f])));
+        assert_eq!((state.wchan.as_deref(), state.state), (Some("hrtimer_nanosleep"), Some('t')));
+
+        let cases = [("running\n", "0"), ("-1 0x7ffd7b888b30 0x401010\n", ""), ("35 0x1 0x2\n", "\n"), ("35 0xg 0 0 0 0 0 0 0\n", "0")];
+        for (syscall, wchan) in cases {
+            let state = SyscallState::parse(syscall, wchan, "1 (init) S 0");
+            assert_eq!((state.syscall, state.wchan, state.state), (None, None, Some('S')), "{:?}", syscall);
+        }
+        assert_eq!(SyscallState::parse("running", "", "garbled").state, None);
+    }
+
+    #[test]
+    fn syscall_state_of_a_process_blocked_in_nanosleep() {
+        let code = format!("    lea delay(%rip), %rdi\n    xor %esi, %esi\n    mov $35, %eax\n    syscall\n{}delay:\n    .quad 5, 0\n", fixture::EXIT_7);
+        let program = fixture::assemble("nanosleep", &code);
+        let mut child = std::process::Command::new(&program).spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let inferior = Inferior::attach(Pid::from_raw(child.id() as i32), &program).unwrap();
+
+        let state = inferior.syscall_state().unwrap();
+        // nanosleep(&delay, NULL), stopped by the tracer in it: delay follows the 12 bytes of exit
+        assert_eq!(state.syscall, Some((35, [fixture::TEXT + 0x1c, 0, 0, 0, 0, 0])));
+        assert_eq!((state.wchan.as_deref(), state.state), (Some("ptrace_stop"), Some('t')));
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn trace_errors_say_what_failed_and_hint_at_why() {
+        let pid = Pid::from_raw(12345);
+        let error = |op: TraceOp, addr: Option<usize>, errno: Errno| TraceError { op, pid, addr, source: nix::Error::Sys(errno) };
+        let cases = [
+            (
+                error(TraceOp::PokeData, Some(0x401a30), Errno::EIO),
+                "PTRACE_POKEDATA at 0x401a30 on pid 12345 failed: I/O error (is the address in a writable mapping?)",
+            ),
+            (
+                error(TraceOp::PeekData, Some(0x10), Errno::EFAULT),
+                "PTRACE_PEEKDATA at 0x10 on pid 12345 failed: Bad address (is the address mapped?)",
+            ),
+            (
+                error(TraceOp::Wait, None, Errno::ECHILD),
+                "waitpid on pid 12345 failed: No child processes (was the process already reaped?)",
+            ),
+            (
+                error(TraceOp::GetRegs, None, Errno::ESRCH),
+                "PTRACE_GETREGS on pid 12345 failed: No such process (is the process stopped, and still alive?)",
+            ),
+            (
+                error(TraceOp::Cont, None, Errno::EPERM),
+                "PTRACE_CONT on pid 12345 failed: Operation not permitted (is the process traced by deet?)",
+            ),
+            (error(TraceOp::Kill, None, Errno::EINVAL), "kill on pid 12345 failed: Invalid argument"),
+            // ECHILD is only explained for a wait
+            (error(TraceOp::SetRegs, None, Errno::ECHILD), "PTRACE_SETREGS on pid 12345 failed: No child processes"),
+        ];
+        for (err, message) in cases {
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn writing_an_unmapped_address_fails_with_the_request() {
+        let program = fixture::assemble("unmapped_write", &format!("{}{}", NOPS, fixture::EXIT_7));
+        let mut inferior = Inferior::new(&program, &[], &[], None).unwrap();
+        // nothing is mapped after the page of the text
+        let err = inferior.write_byte(fixture::TEXT + 0x1000, 0xcc).unwrap_err();
+        assert_eq!((err.op, err.addr, err.pid), (TraceOp::PeekData, Some(fixture::TEXT + 0x1000), inferior.pid()));
+        assert_eq!(
+            err.to_string(),
+            format!("PTRACE_PEEKDATA at 0x402000 on pid {} failed: I/O error (is the address mapped?)", inferior.pid())
+        );
+        inferior.kill();
+    }
+
+    #[test]
+    fn a_panic_during_a_patch_leaves_it_marked() {
+        {
+            let _patch = PatchGuard::new();
+        }
+        assert!(!take_interrupted_patch());
+        let unwound = std::panic::catch_unwind(|| {
+            let _patch = PatchGuard::new();
+            panic!("in the middle of a patch");
+        });
+        assert!(unwound.is_err());
+        assert!(take_interrupted_patch());
+        // taking it clears it
+        assert!(!take_interrupted_patch());
+    }
+
+    /// # brief
+    /// A stopped program whose last function, `EXIT_7`, ends its text section at the end of
+    /// the page, with nothing mapped after it.
+    fn ends_at_a_page_end(name: &str) -> Inferior {
+        let code = format!("    jmp last\n    .org 0x1000 - 12, 0x90\nlast:\n{}", fixture::EXIT_7);
+        Inferior::new(&fixture::assemble(name, &code), &[], &[], None).unwrap()
+    }
+
+    /// The machine code of `fixture::EXIT_7`
+    const EXIT_7_CODE: [u8; 12] = [0xb8, 0x3c, 0, 0, 0, 0xbf, 7, 0, 0, 0, 0x0f, 0x05];
+
+    #[test]
+    fn reads_stop_at_the_end_of_the_mapping() {
+        let mut inferior = ends_at_a_page_end("partial_read");
+        let end = fixture::TEXT + 0x1000;
+        assert_eq!(inferior.read_bytes(end - 12, 12).unwrap(), EXIT_7_CODE);
+
+        let (bytes, err) = inferior.read_bytes_partial(end - 8, 16);
+        let err = err.unwrap();
+        assert_eq!((bytes.as_slice(), err.done), (&EXIT_7_CODE[4..], 8));
+        assert_eq!((err.error.op, err.error.addr), (TraceOp::PeekData, Some(end)));
+        // from the middle of the last word
+        let (bytes, err) = inferior.read_bytes_partial(end - 3, 8);
+        assert_eq!((bytes.as_slice(), err.map(|err| err.done)), (&EXIT_7_CODE[9..], Some(3)));
+        assert!(inferior.read_bytes(end - 3, 8).is_err());
+        inferior.kill();
+    }
+
+    #[test]
+    fn writes_stop_at_the_end_of_the_mapping() {
+        let mut inferior = ends_at_a_page_end("partial_write");
+        let end = fixture::TEXT + 0x1000;
+        // the bytes in the mapping are the code as it is, the function still runs
+        let mut bytes = EXIT_7_CODE[8..].to_vec();
+        bytes.extend([0xcc; 4]);
+        let err = inferior.write_bytes(end - 4, &bytes).unwrap_err();
+        assert_eq!((err.done, err.error.op, err.error.addr), (4, TraceOp::PeekData, Some(end)));
+        assert_eq!(err.to_string(), format!("{} (after 4 bytes)", err.error));
+        assert_eq!(inferior.read_bytes(end - 12, 12).unwrap(), EXIT_7_CODE);
+
+        assert_eq!(inferior.write_bytes(end - 4, &[0, 0]).map_err(|err| err.done), Ok(()));
+        assert_eq!(inferior.read_bytes(end - 12, 12).unwrap(), EXIT_7_CODE);
+        let (status, _) = inferior.continue_run(None, &HashMap::new(), &mut HashMap::new(), None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
+    #[test]
+    fn patches_are_grouped_by_word() {
+        let words = group_by_word([(0x1009, 1), (0x1001, 2), (0x1007, 3), (0x1010, 4), (0x1000, 5)]);
+        assert_eq!(
+            words,
+            [(0x1000, vec![(0x1000, 5), (0x1001, 2), (0x1007, 3)]), (0x1008, vec![(0x1009, 1)]), (0x1010, vec![(0x1010, 4)])]
+        );
+        assert!(group_by_word(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn a_word_is_patched_at_once() {
+        let mut inferior = Inferior::new(&fixture::assemble("patch_word", &format!("{}{}", NOPS, fixture::EXIT_7)), &[], &[], None).unwrap();
+        let word = fixture::TEXT;
+        let original = inferior.read_bytes(word, 8).unwrap();
+        assert_eq!(inferior.patch_word(word, &[(word + 1, 0xcc), (word + 6, 0xcc)]).unwrap(), [original[1], original[6]]);
+        let mut patched = original.clone();
+        patched[1] = 0xcc;
+        patched[6] = 0xcc;
+        assert_eq!(inferior.read_bytes(word, 8).unwrap(), patched);
+        assert_eq!(inferior.patch_word(word, &[(word + 1, original[1]), (word + 6, original[6])]).unwrap(), [0xcc, 0xcc]);
+        assert_eq!(inferior.read_bytes(word, 8).unwrap(), original);
+        inferior.kill();
+    }
+
+    #[test]
+    fn breakpoints_sharing_a_word_all_fire() {
+        let program = fixture::assemble("shared_word", &format!("{}{}", NOPS, fixture::EXIT_7));
+        let mut inferior = Inferior::new(&program, &[], &[], None).unwrap();
+        let traps = [fixture::TEXT + 1, fixture::TEXT + 2, fixture::TEXT + 3];
+        let mut breakpoints: HashMap<usize, u8> = traps.iter().map(|addr| (*addr, 0)).collect();
+        let (results, cancelled) = inferior.install_breakpoints(&mut breakpoints, &CancelToken::new(), &|_, _| {});
+        assert!(!cancelled);
+        assert_eq!(results.iter().map(|(addr, result)| (*addr, result.is_ok())).collect::<Vec<_>>(), traps.map(|addr| (addr, true)));
+        assert!(traps.iter().all(|addr| breakpoints[addr] == 0x90));
+        assert_eq!(inferior.read_bytes(fixture::TEXT, 4).unwrap(), [0x90, 0xcc, 0xcc, 0xcc]);
+
+        let mut step_points = HashMap::new();
+        for trap in traps {
+            let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+            assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+        }
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
+    /// # brief
+    /// Start the program of `fixture::THREADS` with a breakpoint at the `nop` of its threads.
+    ///
+    /// # return
+    /// * The inferior, the breakpoints and the address of the trap.
+    fn threads(name: &str) -> (Inferior, HashMap<usize, u8>, usize) {
+        let mut inferior = Inferior::new(&fixture::assemble(name, fixture::THREADS), &[], &[], None).unwrap();
+        let trap = fixture::TEXT + 0x20;
+        let mut breakpoints = HashMap::from([(trap, 0)]);
+        inferior.install_breakpoints(&mut breakpoints, &CancelToken::new(), &|_, _| {});
+        (inferior, breakpoints, trap)
+    }
+
+    #[test]
+    fn threads_are_traced_and_stop_together() {
+        let (mut inferior, breakpoints, trap) = threads("threads_stop");
+        let mut step_points = HashMap::new();
+        let mut hits = Vec::new();
+        for hit in 0..2 {
+            let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+            assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+            let current = inferior.current_thread().unwrap();
+            assert_ne!(current.tid, inferior.pid());
+            hits.push(current.number);
+            if hit == 0 {
+                // the threads are let go once both exist
+                let numbers: Vec<usize> = inferior.threads().iter().map(|thread| thread.number).collect();
+                assert_eq!(numbers, [1, 2, 3]);
+                let tasks = std::fs::read_dir(format!("/proc/{}/task", inferior.pid())).unwrap().count();
+                assert_eq!(tasks, 3);
+            }
+            // all-stop: no thread runs at a stop
+            assert!(inferior.threads().iter().all(|thread| !thread.running));
+            assert_eq!(inferior.thread_registers(inferior.current).unwrap().rip as usize, trap);
+            // the main thread waits in its loop, past the code of the threads
+            assert!(inferior.thread_registers(inferior.pid()).unwrap().rip as usize > trap);
+        }
+        hits.sort();
+        assert_eq!(hits, [2, 3]);
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+        assert!(inferior.threads().is_empty());
+    }
+
+    #[test]
+    fn a_removed_breakpoint_does_not_stop_the_other_thread() {
+        let (mut inferior, mut breakpoints, trap) = threads("threads_removed");
+        let mut step_points = HashMap::new();
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+        // the other thread may have hit the trap already, its pending stop goes with the trap
+        inferior.write_byte(trap, breakpoints.remove(&trap).unwrap()).unwrap();
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
+    #[test]
+    fn a_step_runs_the_current_thread_alone() {
+        let (mut inferior, breakpoints, trap) = threads("threads_step");
+        let mut step_points = HashMap::new();
+        inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        let others: Vec<(Pid, u64)> = inferior
+            .threads()
+            .iter()
+            .filter(|thread| thread.tid != inferior.current)
+            .map(|thread| (thread.tid, inferior.thread_registers(thread.tid).unwrap().rip))
+            .collect();
+        let status = inferior.step_instruction(&breakpoints, &mut step_points).unwrap();
+        // past the nop, alone
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap + 1), "{:?}", status);
+        for (tid, rip) in others {
+            assert_eq!(inferior.thread_registers(tid).unwrap().rip, rip);
+        }
+        // the other thread still stops at the trap
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Stopped(Signal::SIGTRAP, rip) if rip == trap), "{:?}", status);
+        let (status, _) = inferior.continue_run(None, &breakpoints, &mut step_points, None).unwrap();
+        assert!(matches!(status, Status::Exited(7)), "{:?}", status);
+    }
+
 }
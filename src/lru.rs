@@ -0,0 +1,162 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Hits and misses of a cache
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+/// A small cache dropping the least recently used entry when it is full.
+///
+/// Each use of an entry gives it the next generation and queues its key with it, rather than
+/// moving the key within the queue: a use costs no walk of the entries. The queue entry of an
+/// older use is stale, and skipped when the oldest entry is dropped.
+pub struct Lru<K, V> {
+    capacity: usize,
+    /// The values, with the generation of their last use
+    entries: HashMap<K, (V, u64)>,
+    /// The keys with the generation of each use, least recent first
+    order: VecDeque<(K, u64)>,
+    /// The generation of the next use
+    generation: u64,
+    stats: Stats,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Lru<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            generation: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// # brief
+    /// Look up `key`, counting a hit or a miss.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        match self.entries.get_mut(key) {
+            Some((value, used)) => {
+                let value = value.clone();
+                self.stats.hits += 1;
+                *used = self.generation;
+                self.queue(key.clone());
+                Some(value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// # brief
+    /// Add or replace the entry of `key`, dropping the least recently used one if the cache is
+    /// full.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.insert(key.clone(), (value, self.generation));
+        self.queue(key);
+        if self.entries.len() > self.capacity {
+            while let Some((oldest, used)) = self.order.pop_front() {
+                if matches!(self.entries.get(&oldest), Some((_, last)) if *last == used) {
+                    self.entries.remove(&oldest);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Queue the use of `key` that was just given the current generation, and drop the stale
+    /// uses once they outnumber the entries, which keeps the queue within twice the capacity.
+    fn queue(&mut self, key: K) {
+        self.order.push_back((key, self.generation));
+        self.generation += 1;
+        if self.order.len() > 2 * self.capacity.max(1) {
+            let entries = &self.entries;
+            self.order.retain(|(key, used)| matches!(entries.get(key), Some((_, last)) if last == used));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// The keys of `cache`, least recently used first.
+    fn keys(cache: &Lru<u64, &str>) -> Vec<u64> {
+        cache
+            .order
+            .iter()
+            .filter(|(key, used)| cache.entries[key].1 == *used)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    #[test]
+    fn the_least_recently_used_entry_is_dropped() {
+        let mut cache = Lru::new(3);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(3, "c");
+        assert_eq!(cache.get(&1), Some("a"));
+        cache.insert(4, "d");
+        assert_eq!(keys(&cache), [3, 1, 4]);
+        assert_eq!(cache.get(&2), None);
+        // replacing an entry is a use of it too
+        cache.insert(3, "C");
+        cache.insert(5, "e");
+        assert_eq!(keys(&cache), [4, 3, 5]);
+        assert_eq!((cache.get(&3), cache.get(&1)), (Some("C"), None));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn stale_uses_are_dropped_from_the_queue() {
+        let mut cache = Lru::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        for _ in 0..10 {
+            cache.get(&1);
+        }
+        assert!(cache.order.len() <= 4, "{} uses queued", cache.order.len());
+        assert_eq!(keys(&cache), [2, 1]);
+        cache.insert(3, "c");
+        assert_eq!((cache.get(&2), cache.get(&1), cache.get(&3)), (None, Some("a"), Some("c")));
+    }
+
+    #[test]
+    fn hits_and_misses_are_counted() {
+        let mut cache = Lru::new(2);
+        cache.insert(1, "a");
+        cache.get(&1);
+        cache.get(&1);
+        cache.get(&2);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+    }
+
+    #[test]
+    fn a_cache_of_one_keeps_the_last_entry() {
+        let mut cache = Lru::new(1);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!((cache.get(&1), cache.get(&2)), (None, Some("b")));
+        assert_eq!(keys(&cache), [2]);
+    }
+}
@@ -3,22 +3,284 @@ mod debugger_command;
 mod debugger;
 mod dwarf_data;
 mod gimli_wrapper;
+mod source;
+mod expr;
+mod registers;
+mod maps;
+mod snapshot;
+mod hint;
+mod tui;
+mod breakpoint;
+mod pretty;
+mod wait_for;
+mod preflight;
+mod lru;
+mod regex;
+mod skip;
+mod disasm;
+mod symtab;
+mod target;
+mod remote;
+mod ui;
+mod output;
+mod stats;
+mod cargo;
+mod core_pattern;
+mod cancel;
+mod auxv;
+mod runs;
+mod syscalls;
+mod crash_report;
+mod context;
+mod core_file;
+mod session;
+#[cfg(test)]
+mod fixture;
 
-use crate::debugger::Debugger;
+use crate::debugger::{Debugger, Options};
+use crate::dwarf_data::DwarfData;
 use nix::sys::signal::{signal, SigHandler, Signal};
 use std::env;
+use std::panic;
+use std::time::Duration;
+
+/// The exit status of deet when it panics, EX_SOFTWARE of sysexits.h
+const PANIC_EXIT_CODE: i32 = 70;
+
+const USAGE: &str = "Usage: deet [options] <target program> [arguments...]
+       deet [options] --args <target program> [arguments...]
+       deet [options] --pid <pid> [target program]
+       deet [options] --core <file> [target program]
+       deet [options] --wait-for <name> [target program]
+       deet [options] --session <file> [target program]
+       deet [options] --cargo test [filter] [cargo options...]
+       deet [options] --cargo run [cargo options...] [-- arguments...]
+       deet --profile-load <target program>
+
+Options:
+  --args          Everything after the target program is passed to it
+  --cargo test|run ...
+                  Build with cargo and debug the test executable holding the tests
+                  matching the filter, or the binary or example built (e.g. with
+                  --example foo); everything after it is for cargo
+  --pid <pid>     Attach to a running process
+  --core <file>   Inspect the core dump of a crashed program, by default of the executable
+                  that dumped it
+  --wait-for <name>
+                  Wait for a new process running <name> and attach to it
+  --wait-interval <ms>
+                  Time between two scans for the process (default 1)
+  --wait-timeout <seconds>
+                  Give up waiting for the process after this long
+  --read-only     Only observe the program: refuse breakpoints, memory writes, signals
+                  and anything else that would change it
+  --session <file>
+                  Restore a session saved by `save session`, before the -ex commands
+  --profile-load <file>
+                  Time loading the debug information of <file>, then exit
+  -ex <command>   Execute a command before the prompt, may be repeated
+  --tui           Show a source pane above the command line
+  --batch         Quit after the -ex commands, with status 1 if an assertion failed
+  -q, --quiet     Leave out the startup messages and the progress of loading symbols;
+                  only the responses to commands are printed on stdout in any case
+  --deterministic Make the output the same every run: no address randomization, no
+                  times, addresses shown relative to the executable (prog+0x1a32)
+  -h, --help      Print this help
+  -V, --version   Print the version";
+
+/// # brief
+/// Parse the deet command line. The first argument that is not an option is the target, and
+/// everything after it belongs to the target, so its own `--flags` are not taken by deet.
+///
+/// # return
+/// * `Ok(Some(Options))` - the startup options, `Ok(None)` if deet should exit right away (after
+/// `--help` or `--version`), or `Err(String)` describing the bad argument.
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut target = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(None);
+            }
+            "-V" | "--version" => {
+                println!("deet {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            }
+            "--pid" => {
+                let pid = iter.next().ok_or("--pid requires a process id")?;
+                options.pid = Some(pid.parse().or(Err(format!("Invalid process id \"{}\"", pid)))?);
+            }
+            "--core" => {
+                options.core = Some(iter.next().ok_or("--core requires a core file")?.clone());
+            }
+            "--wait-for" => {
+                options.wait_for = Some(iter.next().ok_or("--wait-for requires an executable name")?.clone());
+            }
+            "--wait-interval" => {
+                let ms = iter.next().ok_or("--wait-interval requires a number of milliseconds")?;
+                let ms = ms.parse().or(Err(format!("Invalid interval \"{}\"", ms)))?;
+                options.wait_interval = Some(Duration::from_millis(ms));
+            }
+            "--wait-timeout" => {
+                let secs = iter.next().ok_or("--wait-timeout requires a number of seconds")?;
+                let secs = secs.parse().or(Err(format!("Invalid timeout \"{}\"", secs)))?;
+                options.wait_timeout = Some(Duration::from_secs(secs));
+            }
+            "--profile-load" => {
+                let target = iter.next().ok_or("--profile-load requires a target program")?;
+                std::process::exit(profile_load(target));
+            }
+            "--session" => {
+                options.session = Some(iter.next().ok_or("--session requires a session file")?.clone());
+            }
+            "-ex" | "--ex" => {
+                options.commands.push(iter.next().ok_or("-ex requires a command")?.clone());
+            }
+            "--batch" => options.batch = true,
+            "--tui" => options.tui = true,
+            "-q" | "--quiet" => options.quiet = true,
+            "--deterministic" => options.deterministic = true,
+            "--read-only" => options.read_only = true,
+            "--cargo" => {
+                let rest: Vec<String> = iter.cloned().collect();
+                let launch = cargo::launch(&rest)?;
+                if !options.quiet {
+                    ui::notify(format!("Debugging {} {}", launch.executable, launch.args.join(" ")).trim_end());
+                }
+                options.target = launch.executable;
+                options.args = launch.args;
+                return Ok(Some(options));
+            }
+            "--args" => {
+                target = Some(iter.next().ok_or("--args requires a target program")?.clone());
+                break;
+            }
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            _ => {
+                target = Some(arg.clone());
+                break;
+            }
+        }
+    }
+    if options.core.is_some() && (options.pid.is_some() || options.wait_for.is_some()) {
+        return Err("--core debugs a dump, not a process: it can't go with --pid or --wait-for".to_string());
+    }
+    options.args = iter.cloned().collect();
+    options.target = match (target, options.pid) {
+        (Some(target), _) => target,
+        // the executable of the process to attach to
+        (None, Some(pid)) => format!("/proc/{}/exe", pid),
+        // found once the process appears, or in the core
+        (None, None) if options.wait_for.is_some() || options.core.is_some() => String::new(),
+        // the target the session was saved with
+        (None, None) if options.session.is_some() => session::Session::read(options.session.as_ref().unwrap())?.target,
+        (None, None) => return Err("No target program given".to_string()),
+    };
+    Ok(Some(options))
+}
+
+/// # brief
+/// Handle `--profile-load`: load the debug information of `target` and print the time each
+/// phase took, what was found and the peak memory use.
+///
+/// # return
+/// * The exit status, 1 if the debug information could not be loaded.
+fn profile_load(target: &str) -> i32 {
+    let debug_data = match DwarfData::from_file(target) {
+        Ok(debug_data) => debug_data,
+        Err(err) => {
+            println!("Could not load the debug information of {}: {:?}", target, err);
+            return 1;
+        }
+    };
+    println!("{}", debug_data.load_stats());
+    // VmHWM is the peak resident set size
+    let peak = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| status.lines().find(|line| line.starts_with("VmHWM:")).map(|line| line[6..].trim().to_string()));
+    if let Some(peak) = peak {
+        println!("peak RSS: {}", peak);
+    }
+    0
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: {} <target program>", args[0]);
-        std::process::exit(1);
-    }
-    let target = &args[1];
+    let options = match parse_args(&args[1..]) {
+        Ok(Some(options)) => options,
+        Ok(None) => return,
+        Err(err) => {
+            println!("{}\n\n{}", err, USAGE);
+            std::process::exit(1);
+        }
+    };
 
     // Disable handling of ctrl+c in this process (so that ctrl+c only gets delivered to child
     // processes)
     unsafe { signal(Signal::SIGINT, SigHandler::SigIgn) }.expect("Error disabling SIGINT handling");
 
-    Debugger::new(target).run();
+    ui::set_quiet(options.quiet);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // a panic in a command is caught, the debugger says so itself
+        if !debugger::in_command() {
+            ui::notify("deet crashed; the programs it debugs are killed or detached. Please file an issue with the message above.");
+        }
+    }));
+    // the debugger is dropped while the panic unwinds, which ends the session as `quit` does
+    let session = panic::catch_unwind(|| match Debugger::new(&options) {
+        Ok(mut debugger) => debugger.run(),
+        Err(err) => {
+            println!("{}", err);
+            1
+        }
+    });
+    std::process::exit(session.unwrap_or(PANIC_EXIT_CODE));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// Parse a deet command line given without the program name.
+    fn parse(line: &str) -> Result<Option<Options>, String> {
+        let args: Vec<String> = line.split_whitespace().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn quiet_is_an_option_of_deet_only() {
+        for line in ["-q prog", "--quiet prog"] {
+            let options = parse(line).unwrap().unwrap();
+            assert!(options.quiet, "{}", line);
+            assert_eq!(options.target, "prog");
+        }
+        // after the target, it is an argument of the target
+        let options = parse("prog -q --quiet").unwrap().unwrap();
+        assert!(!options.quiet);
+        assert_eq!(options.args, ["-q", "--quiet"]);
+    }
+
+    #[test]
+    fn options_before_the_target_are_checked() {
+        assert_eq!(parse("-x prog").unwrap_err(), "Unknown option -x");
+        assert_eq!(parse("-q").unwrap_err(), "No target program given");
+        let options = parse("--batch -ex run --args prog --batch").unwrap().unwrap();
+        assert!(options.batch);
+        assert_eq!((options.commands, options.args), (vec!["run".to_string()], vec!["--batch".to_string()]));
+    }
+
+    #[test]
+    fn a_core_names_its_executable_unless_a_target_is_given() {
+        let options = parse("--core core.1234").unwrap().unwrap();
+        assert_eq!((options.core.as_deref(), options.target.as_str()), (Some("core.1234"), ""));
+        let options = parse("--core core.1234 ./prog").unwrap().unwrap();
+        assert_eq!(options.target, "./prog");
+        assert_eq!(parse("--core").unwrap_err(), "--core requires a core file");
+    }
 }
@@ -0,0 +1,291 @@
+use crate::auxv;
+use nix::unistd::Pid;
+use std::fs;
+use std::io::{self, Read};
+
+/// One line of `/proc/<pid>/maps`.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Mapping {
+    pub start: usize,
+    pub end: usize,
+    /// Permissions, e.g. `r-xp`
+    pub perms: String,
+    /// Offset of the mapping in the mapped file
+    pub offset: usize,
+    /// The mapped file, a pseudo path like `[stack]`, or empty for anonymous mappings
+    pub path: String,
+}
+
+/// # brief
+/// Parses the content of a `/proc/<pid>/maps` file. Malformed lines are skipped.
+pub fn parse_maps(content: &str) -> Vec<Mapping> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (start, end) = fields.next()?.split_once('-')?;
+            let perms = fields.next()?.to_string();
+            let offset = usize::from_str_radix(fields.next()?, 16).ok()?;
+            // skip the device and the inode, the rest is the path (which may contain spaces)
+            fields.next()?;
+            fields.next()?;
+            Some(Mapping {
+                start: usize::from_str_radix(start, 16).ok()?,
+                end: usize::from_str_radix(end, 16).ok()?,
+                perms,
+                offset,
+                path: fields.collect::<Vec<&str>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// # brief
+/// Reads the memory mappings of a process.
+pub fn read_maps(pid: Pid) -> io::Result<Vec<Mapping>> {
+    Ok(parse_maps(&fs::read_to_string(format!("/proc/{}/maps", pid))?))
+}
+
+/// # brief
+/// Read the start of the ELF header of a file, up to `e_entry`.
+fn read_elf_header(path: &str) -> io::Result<[u8; 32]> {
+    let mut header = [0u8; 32];
+    fs::File::open(path)?.read_exact(&mut header)?;
+    if &header[..4] != b"\x7fELF" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not an ELF file"));
+    }
+    Ok(header)
+}
+
+/// # brief
+/// Read an integer field of an ELF header, in the byte order given by EI_DATA.
+fn header_field(header: &[u8; 32], offset: usize, size: usize) -> usize {
+    let mut bytes = [0u8; 8];
+    bytes[..size].copy_from_slice(&header[offset..offset + size]);
+    if header[5] == 2 {
+        bytes[..size].reverse();
+    }
+    u64::from_le_bytes(bytes) as usize
+}
+
+/// # brief
+/// Read the machine an ELF file is built for, its `e_machine`, e.g. 62 for x86-64.
+pub fn elf_machine(path: &str) -> io::Result<usize> {
+    Ok(header_field(&read_elf_header(path)?, 18, 2))
+}
+
+/// # brief
+/// Check whether an ELF file is position independent (`ET_DYN`), i.e. loaded at a random base.
+fn is_position_independent(path: &str) -> io::Result<bool> {
+    Ok(header_field(&read_elf_header(path)?, 16, 2) == 3)
+}
+
+/// # brief
+/// Read the entry point recorded in the ELF header of a file, which is where the program
+/// starts unless it is position independent.
+pub fn elf_entry(path: &str) -> io::Result<usize> {
+    let header = read_elf_header(path)?;
+    // e_entry is a word of the class given by EI_CLASS
+    let size = if header[4] == 2 { 8 } else { 4 };
+    Ok(header_field(&header, 24, size))
+}
+
+/// # brief
+/// Check whether an address lies in an executable mapping.
+pub fn is_executable(mappings: &[Mapping], addr: usize) -> bool {
+    mappings
+        .iter()
+        .any(|mapping| mapping.perms.contains('x') && mapping.start <= addr && addr < mapping.end)
+}
+
+/// # brief
+/// Compute the load bias of the main executable of a process: the difference between the
+/// addresses in the debug information and the addresses in memory. It is 0 unless the
+/// executable is position independent.
+///
+/// # param
+/// - `pid` : The process, stopped after exec.
+/// - `target` : The path of the executable.
+pub fn load_bias(pid: Pid, target: &str) -> io::Result<usize> {
+    if !is_position_independent(target)? {
+        return Ok(0);
+    }
+    // the kernel tells where it put the entry point, else the lowest mapping of the file is used
+    if let Some(entry) = auxv::read_auxv(pid).ok().and_then(|auxv| auxv.entry()) {
+        return Ok(entry.wrapping_sub(elf_entry(target)?));
+    }
+    executable_mappings(pid)?
+        .iter()
+        .map(|mapping| mapping.start - mapping.offset)
+        .min()
+        .ok_or(io::Error::new(io::ErrorKind::NotFound, "executable is not mapped"))
+}
+
+/// # brief
+/// Get the mappings of the main executable of a process, in address order.
+pub fn executable_mappings(pid: Pid) -> io::Result<Vec<Mapping>> {
+    let exe = fs::read_link(format!("/proc/{}/exe", pid))?;
+    let exe = exe.to_string_lossy();
+    Ok(read_maps(pid)?.into_iter().filter(|mapping| mapping.path == exe).collect())
+}
+
+/// A shared object mapped in a process, e.g. the C library or the dynamic linker
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedLibrary {
+    pub path: String,
+    /// The address its file is loaded at, where file offset 0 would be
+    pub base: usize,
+    /// The end of its last mapping
+    pub end: usize,
+}
+
+/// # brief
+/// List the shared objects in the mappings of a process: the files other than the executable
+/// with code mapped, once each, in address order. Files mapped as data only, such as locale
+/// archives, are left out.
+///
+/// # param
+/// - `mappings` : The mappings of the process.
+/// - `exe` : The path of its executable.
+pub fn shared_libraries(mappings: &[Mapping], exe: &str) -> Vec<SharedLibrary> {
+    let mut libraries: Vec<SharedLibrary> = Vec::new();
+    for mapping in mappings.iter().filter(|mapping| mapping.path.starts_with('/') && mapping.path != exe) {
+        let base = mapping.start.wrapping_sub(mapping.offset);
+        match libraries.iter_mut().find(|library| library.path == mapping.path) {
+            Some(library) => {
+                library.base = library.base.min(base);
+                library.end = library.end.max(mapping.end);
+            }
+            None => libraries.push(SharedLibrary { path: mapping.path.clone(), base, end: mapping.end }),
+        }
+    }
+    libraries.retain(|library| mappings.iter().any(|mapping| mapping.path == library.path && mapping.perms.contains('x')));
+    libraries.sort_by_key(|library| library.base);
+    libraries
+}
+
+/// # brief
+/// Read the shared objects mapped in a process, see `shared_libraries`.
+pub fn read_shared_libraries(pid: Pid) -> io::Result<Vec<SharedLibrary>> {
+    let exe = fs::read_link(format!("/proc/{}/exe", pid))?;
+    Ok(shared_libraries(&read_maps(pid)?, &exe.to_string_lossy()))
+}
+
+/// # brief
+/// Find the mapping holding `addr` if it belongs to the system rather than to the program: the
+/// `[vdso]` and `[vvar]` pages of the kernel, or the dynamic linker.
+///
+/// # param
+/// - `mappings` : The mappings of the process.
+/// - `addr` : The address.
+/// - `interpreter_base` : Where the dynamic linker was loaded (`AT_BASE`), `None` for a static
+/// executable.
+pub fn system_mapping(mappings: &[Mapping], addr: usize, interpreter_base: Option<usize>) -> Option<&Mapping> {
+    let interpreter = interpreter_base
+        .and_then(|base| mappings.iter().find(|mapping| mapping.start == base))
+        .map(|mapping| mapping.path.as_str())
+        .filter(|path| !path.is_empty());
+    mappings.iter().find(|mapping| {
+        let kernel = mapping.path == "[vdso]" || mapping.path == "[vvar]";
+        (kernel || Some(mapping.path.as_str()) == interpreter) && mapping.start <= addr && addr < mapping.end
+    })
+}
+
+/// The memory a process uses for dynamic allocations, as visible in its mappings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapUsage {
+    /// Size of the `[heap]` segment grown by `brk`
+    pub heap: usize,
+    /// Total size of the anonymous writable mappings, where `mmap`ed allocations, malloc arenas
+    /// of other threads and thread stacks live
+    pub anonymous: usize,
+    /// Number of anonymous writable mappings
+    pub anonymous_count: usize,
+}
+
+/// # brief
+/// Measure the heap segment and the anonymous writable mappings of a process.
+pub fn heap_usage(mappings: &[Mapping]) -> HeapUsage {
+    let mut usage = HeapUsage { heap: 0, anonymous: 0, anonymous_count: 0 };
+    for mapping in mappings {
+        let size = mapping.end - mapping.start;
+        if mapping.path == "[heap]" {
+            usage.heap += size;
+        } else if mapping.path.is_empty() && mapping.perms.contains('w') {
+            usage.anonymous += size;
+            usage.anonymous_count += 1;
+        }
+    }
+    usage
+}
+
+/// The size of a page, which is also the size of a thread stack's guard page
+const PAGE_SIZE: usize = 4096;
+
+/// # brief
+/// Check whether a fault address is just below a stack: within one page below the `[stack]`
+/// mapping, or inside the guard page (an inaccessible anonymous mapping) right below a thread
+/// stack.
+///
+/// # return
+/// * `Some(usize)` - how many bytes below the stack limit the address is, or `None` if the fault
+/// doesn't look like a stack overflow.
+pub fn stack_overflow(mappings: &[Mapping], addr: usize) -> Option<usize> {
+    for (index, mapping) in mappings.iter().enumerate() {
+        if mapping.path == "[stack]" && addr < mapping.start && mapping.start - addr <= PAGE_SIZE {
+            return Some(mapping.start - addr);
+        }
+        let guard = mapping.path.is_empty() && mapping.perms.starts_with("---");
+        if guard && mapping.start <= addr && addr < mapping.end {
+            let stack = mappings.get(index + 1)?;
+            if stack.start == mapping.end && stack.path.is_empty() && stack.perms.contains('w') {
+                return Some(stack.start - addr);
+            }
+        }
+    }
+    None
+}
+
+/// # brief
+/// Check whether a frame pointer may be followed in a backtrace: it is 8-byte aligned and the
+/// two words at it, the saved %rbp and the return address, are inside the stack mapping.
+pub fn holds_frame(stack: &Mapping, rbp: usize) -> bool {
+    rbp & 7 == 0 && rbp >= stack.start && rbp <= stack.end.saturating_sub(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `[stack]` mapping of 64 KiB
+    fn stack() -> Mapping {
+        Mapping { start: 0x7ffd_0000, end: 0x7ffe_0000, perms: "rw-p".to_string(), offset: 0, path: "[stack]".to_string() }
+    }
+
+    #[test]
+    fn frame_inside_the_stack_is_followed() {
+        let stack = stack();
+        assert!(holds_frame(&stack, 0x7ffd_0000));
+        assert!(holds_frame(&stack, 0x7ffd_8a40));
+        // the two words end right at the top of the stack
+        assert!(holds_frame(&stack, 0x7ffe_0000 - 16));
+    }
+
+    #[test]
+    fn frame_outside_the_stack_is_not() {
+        let stack = stack();
+        assert!(!holds_frame(&stack, 0));
+        assert!(!holds_frame(&stack, 0x7ffd_0000 - 8));
+        assert!(!holds_frame(&stack, 0x7ffe_0000 - 8));
+        assert!(!holds_frame(&stack, 0x7ffe_0000));
+        assert!(!holds_frame(&stack, usize::MAX - 7));
+    }
+
+    #[test]
+    fn misaligned_frame_is_not_followed() {
+        let stack = stack();
+        assert!(!holds_frame(&stack, 0x7ffd_8a44));
+        assert!(!holds_frame(&stack, 0x7ffd_8a41));
+    }
+}
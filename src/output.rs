@@ -0,0 +1,328 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::process::{ChildStderr, ChildStdout};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The number of lines of output kept for `info output`
+const MAX_LINES: usize = 1000;
+
+/// The longest time a stop report waits for the output the inferior wrote before it stopped
+const SETTLE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Written instead of the escape sequences that would wipe what deet printed
+const SCREEN_PLACEHOLDER: &str = "[inferior cleared the screen / entered alternate screen]";
+
+/// The longest escape sequence followed, a longer one is passed on as it is
+const MAX_SEQUENCE_LEN: usize = 32;
+
+/// The stream of the inferior a line was written to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stream::Stdout => write!(f, "stdout"),
+            Stream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// A line written by the inferior, numbered in the order the lines arrived
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub seq: u64,
+    pub stream: Stream,
+    pub text: String,
+}
+
+#[derive(Default)]
+struct Buffer {
+    /// The sequence number of the next line
+    next_seq: u64,
+    lines: VecDeque<Line>,
+    /// The read end of each pipe still open
+    open: Vec<(Stream, RawFd)>,
+    /// Whether the escape sequences clearing the screen are replaced, `set sanitize-output`
+    sanitize: bool,
+}
+
+impl Buffer {
+    fn push(&mut self, stream: Stream, text: String) {
+        self.lines.push_back(Line { seq: self.next_seq, stream, text });
+        self.next_seq += 1;
+        if self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// Replaces the escape sequences clearing the terminal in the output of an inferior by a line
+/// saying so, so that a curses program doesn't wipe deet's stop reports: `ESC c`, `ESC [ 2 J`,
+/// `ESC [ 3 J` and the switches to the alternate screen (`ESC [ ? 1049 h`, `?1047h`, `?47h`).
+/// The switches back are dropped, the other sequences are passed on. A sequence split
+/// between two reads is held until its end arrives.
+#[derive(Default)]
+struct Sanitizer {
+    /// The start of an escape sequence not ended yet
+    sequence: Vec<u8>,
+    /// Whether the last byte passed on left a line unfinished
+    midline: bool,
+}
+
+impl Sanitizer {
+    /// # brief
+    /// Filter the next bytes of the stream.
+    fn filter(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(input.len());
+        for &byte in input {
+            if self.sequence.is_empty() && byte != 0x1b {
+                output.push(byte);
+                self.midline = byte != b'\n';
+                continue;
+            }
+            self.sequence.push(byte);
+            let ended = match self.sequence.as_slice() {
+                [0x1b] => false,
+                // a CSI sequence ends with a byte from @ to ~
+                [0x1b, b'[', .., last] if self.sequence.len() > 2 => (0x40..=0x7e).contains(last),
+                [0x1b, b'['] => false,
+                _ => true,
+            };
+            if !ended && self.sequence.len() < MAX_SEQUENCE_LEN {
+                continue;
+            }
+            let sequence = std::mem::take(&mut self.sequence);
+            match sequence.as_slice() {
+                b"\x1bc" | b"\x1b[2J" | b"\x1b[3J" | b"\x1b[?1049h" | b"\x1b[?1047h" | b"\x1b[?47h" => {
+                    if self.midline {
+                        output.push(b'\n');
+                    }
+                    output.extend_from_slice(SCREEN_PLACEHOLDER.as_bytes());
+                    output.push(b'\n');
+                    self.midline = false;
+                }
+                b"\x1b[?1049l" | b"\x1b[?1047l" | b"\x1b[?47l" => {}
+                _ => output.extend_from_slice(&sequence),
+            }
+        }
+        output
+    }
+
+    /// # brief
+    /// Get the start of a sequence the stream ended in, passed on as it is.
+    fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.sequence)
+    }
+}
+
+/// The output of inferiors started with `set capture-output on`: their stdout and stderr are
+/// pipes, read by one thread each, which passes the output on to deet's own streams and
+/// records it line by line.
+#[derive(Clone, Default)]
+pub struct Capture {
+    buffer: Arc<Mutex<Buffer>>,
+}
+
+/// # brief
+/// Get the number of bytes waiting to be read from the pipe `fd`.
+fn unread_bytes(fd: RawFd) -> usize {
+    let mut count: libc::c_int = 0;
+    let res = unsafe { libc::ioctl(fd, libc::FIONREAD, &mut count as *mut libc::c_int) };
+    if res == -1 { 0 } else { count as usize }
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        let capture = Capture::default();
+        capture.set_sanitize(true);
+        capture
+    }
+
+    /// # brief
+    /// Choose whether the escape sequences clearing the screen are replaced by a line saying
+    /// so, for the output read from now on.
+    pub fn set_sanitize(&self, sanitize: bool) {
+        self.buffer.lock().unwrap().sanitize = sanitize;
+    }
+
+    pub fn sanitize(&self) -> bool {
+        self.buffer.lock().unwrap().sanitize
+    }
+
+    /// # brief
+    /// Start reading the stdout and stderr pipes of a new inferior.
+    pub fn attach(&self, stdout: ChildStdout, stderr: ChildStderr) {
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+            buffer.open.push((Stream::Stdout, stdout.as_raw_fd()));
+            buffer.open.push((Stream::Stderr, stderr.as_raw_fd()));
+        }
+        self.spawn_reader(Stream::Stdout, stdout);
+        self.spawn_reader(Stream::Stderr, stderr);
+    }
+
+    /// # brief
+    /// Read `pipe` until the inferior closes it, writing everything read to deet's stream of
+    /// the same kind right away, including a line not ended yet such as a prompt.
+    fn spawn_reader(&self, stream: Stream, mut pipe: impl Read + AsRawFd + Send + 'static) {
+        let buffer = Arc::clone(&self.buffer);
+        std::thread::spawn(move || {
+            let fd = pipe.as_raw_fd();
+            let mut pending = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let mut sanitizer = Sanitizer::default();
+            let pass_on = |text: &[u8]| {
+                let _ = match stream {
+                    Stream::Stdout => std::io::stdout().write_all(text).and_then(|_| std::io::stdout().flush()),
+                    Stream::Stderr => std::io::stderr().write_all(text),
+                };
+            };
+            loop {
+                let count = match pipe.read(&mut chunk) {
+                    Ok(0) | Err(_) => break,
+                    Ok(count) => count,
+                };
+                let sanitize = buffer.lock().unwrap().sanitize;
+                let text = if sanitize { sanitizer.filter(&chunk[..count]) } else { chunk[..count].to_vec() };
+                pass_on(&text);
+                pending.extend_from_slice(&text);
+                let mut buffer = buffer.lock().unwrap();
+                while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    buffer.push(stream, String::from_utf8_lossy(&line[..end]).into_owned());
+                }
+            }
+            let rest = sanitizer.finish();
+            pass_on(&rest);
+            pending.extend_from_slice(&rest);
+            let mut buffer = buffer.lock().unwrap();
+            if !pending.is_empty() {
+                buffer.push(stream, String::from_utf8_lossy(&pending).into_owned());
+            }
+            // the pipe is closed when the thread ends, after it stops being looked at
+            buffer.open.retain(|(_, open_fd)| *open_fd != fd);
+        });
+    }
+
+    /// # brief
+    /// Give the reader threads a moment to record what the inferior wrote before it stopped:
+    /// wait until the pipes are empty, for at most `SETTLE_TIMEOUT`.
+    pub fn settle(&self) {
+        let deadline = Instant::now() + SETTLE_TIMEOUT;
+        while Instant::now() < deadline {
+            let unread: usize = self.buffer.lock().unwrap().open.iter().map(|(_, fd)| unread_bytes(*fd)).sum();
+            if unread == 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // the bytes just read may still be on their way into the buffer
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    /// # brief
+    /// Get the sequence number the next line will have, to find the lines that arrive later.
+    pub fn next_seq(&self) -> u64 {
+        self.buffer.lock().unwrap().next_seq
+    }
+
+    /// # brief
+    /// Count the lines written since the line numbered `seq`.
+    ///
+    /// # return
+    /// * The number of lines written to stdout and to stderr.
+    pub fn counts_since(&self, seq: u64) -> (usize, usize) {
+        let buffer = self.buffer.lock().unwrap();
+        let new_lines = buffer.lines.iter().filter(|line| line.seq >= seq);
+        new_lines.fold((0, 0), |(stdout, stderr), line| match line.stream {
+            Stream::Stdout => (stdout + 1, stderr),
+            Stream::Stderr => (stdout, stderr + 1),
+        })
+    }
+
+    /// # brief
+    /// Get the last `count` lines kept, oldest first.
+    pub fn last_lines(&self, count: usize) -> Vec<Line> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer.lines.iter().skip(buffer.lines.len().saturating_sub(count)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// Filter `chunks` as consecutive reads of one stream, then its end.
+    fn sanitize(chunks: &[&[u8]]) -> String {
+        let mut sanitizer = Sanitizer::default();
+        let mut output: Vec<u8> = chunks.iter().flat_map(|chunk| sanitizer.filter(chunk)).collect();
+        output.extend(sanitizer.finish());
+        String::from_utf8(output).unwrap()
+    }
+
+    fn placeholder() -> String {
+        format!("{}\n", SCREEN_PLACEHOLDER)
+    }
+
+    #[test]
+    fn plain_output_is_unchanged() {
+        assert_eq!(sanitize(&[b"hello\n", b"world"]), "hello\nworld");
+    }
+
+    #[test]
+    fn clears_are_replaced() {
+        for clear in [&b"\x1bc"[..], b"\x1b[2J", b"\x1b[3J", b"\x1b[?1049h", b"\x1b[?1047h", b"\x1b[?47h"] {
+            assert_eq!(sanitize(&[clear]), placeholder(), "{:?}", clear);
+        }
+    }
+
+    #[test]
+    fn clear_in_the_middle_of_a_line_starts_a_new_one() {
+        assert_eq!(sanitize(&[b"before\x1b[2Jafter\n"]), format!("before\n{}after\n", placeholder()));
+    }
+
+    #[test]
+    fn switches_back_are_dropped() {
+        assert_eq!(sanitize(&[b"a\x1b[?1049lb"]), "ab");
+    }
+
+    #[test]
+    fn other_sequences_pass() {
+        assert_eq!(sanitize(&[b"\x1b[31mred\x1b[0m\n"]), "\x1b[31mred\x1b[0m\n");
+        assert_eq!(sanitize(&[b"\x1b7saved\x1b8"]), "\x1b7saved\x1b8");
+    }
+
+    #[test]
+    fn sequence_split_between_reads_is_held() {
+        let whole = sanitize(&[b"x\x1b[?1049hy"]);
+        assert_eq!(whole, format!("x\n{}y", placeholder()));
+        // every split of the bytes gives the same output
+        let bytes = b"x\x1b[?1049hy";
+        for split in 1..bytes.len() {
+            assert_eq!(sanitize(&[&bytes[..split], &bytes[split..]]), whole, "split at {}", split);
+        }
+        assert_eq!(sanitize(&[b"\x1b", b"[", b"2", b"J"]), placeholder());
+        assert_eq!(sanitize(&[b"\x1b[3", b"1mred"]), "\x1b[31mred");
+    }
+
+    #[test]
+    fn unfinished_sequence_is_passed_at_the_end() {
+        assert_eq!(sanitize(&[b"text\x1b[2"]), "text\x1b[2");
+    }
+
+    #[test]
+    fn overlong_sequence_is_passed_on() {
+        let mut long = b"\x1b[".to_vec();
+        long.extend(vec![b'1'; MAX_SEQUENCE_LEN]);
+        long.push(b'J');
+        assert_eq!(sanitize(&[&long]).into_bytes(), long);
+    }
+}
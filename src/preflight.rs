@@ -0,0 +1,307 @@
+use crate::maps;
+use nix::unistd::Pid;
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+
+const SCOPE_PATH: &str = "/proc/sys/kernel/yama/ptrace_scope";
+
+/// The `e_machine` of the programs deet can debug, x86-64
+const HOST_MACHINE: usize = 62;
+
+/// # brief
+/// Name the architecture of an ELF `e_machine`.
+fn machine_name(machine: usize) -> String {
+    match machine {
+        3 => "i386 (32-bit x86)".to_string(),
+        8 => "MIPS".to_string(),
+        20 => "PowerPC".to_string(),
+        21 => "PowerPC64".to_string(),
+        22 => "s390".to_string(),
+        40 => "ARM (32-bit)".to_string(),
+        62 => "x86-64".to_string(),
+        183 => "AArch64".to_string(),
+        243 => "RISC-V".to_string(),
+        other => format!("machine type {}", other),
+    }
+}
+
+/// # brief
+/// Check that the target is a program deet can run and trace: not a script, an ELF file built
+/// for x86-64, and executable.
+///
+/// # return
+/// * `Err(String)` saying what the file is instead and what to do about it; `Ok(())` as well
+/// when the file can't be read, which loading it reports.
+pub fn check_target(target: &str) -> Result<(), String> {
+    let mut start = Vec::new();
+    match fs::File::open(target) {
+        Ok(file) => file.take(256).read_to_end(&mut start).ok(),
+        Err(_) => return Ok(()),
+    };
+    if let Some(line) = start.strip_prefix(b"#!") {
+        let line = String::from_utf8_lossy(line.split(|b| *b == b'\n').next().unwrap_or_default());
+        let interpreter = line.split_whitespace().next().unwrap_or("<interpreter>");
+        return Err(format!(
+            "{} is a script for {}, which can't be traced by itself; debug the interpreter with the script as an argument: deet --args {} {}",
+            target,
+            line.trim(),
+            interpreter,
+            target
+        ));
+    }
+    let machine = match maps::elf_machine(target) {
+        Ok(machine) => machine,
+        Err(_) => return Err(format!("{} is not an executable: it is not an ELF file", target)),
+    };
+    if machine != HOST_MACHINE {
+        return Err(format!(
+            "{} is built for {}, deet debugs {} programs only",
+            target,
+            machine_name(machine),
+            machine_name(HOST_MACHINE)
+        ));
+    }
+    let mode = fs::metadata(target).map(|metadata| metadata.permissions().mode()).unwrap_or(0o755);
+    if mode & 0o111 == 0 {
+        return Err(format!("{} is not executable (mode {:o}); `chmod +x {}` first", target, mode & 0o7777, target));
+    }
+    Ok(())
+}
+
+/// # brief
+/// Check the target for the setuid and setgid bits, which the kernel ignores for a traced
+/// process.
+///
+/// # return
+/// * A one-line warning if the target is setuid or setgid, `None` otherwise.
+pub fn setuid_warning(target: &str) -> Option<String> {
+    let bits = setuid_bits(fs::metadata(target).ok()?.permissions().mode())?;
+    Some(format!(
+        "warning: {} is {}; the bits are dropped under ptrace, so it runs with your own privileges",
+        target, bits
+    ))
+}
+
+/// # brief
+/// Name the setuid and setgid bits set in a file mode.
+///
+/// # return
+/// * `setuid`, `setgid` or both, `None` if neither is set.
+fn setuid_bits(mode: u32) -> Option<&'static str> {
+    match (mode & libc::S_ISUID != 0, mode & libc::S_ISGID != 0) {
+        (true, true) => Some("setuid and setgid"),
+        (true, false) => Some("setuid"),
+        (false, true) => Some("setgid"),
+        (false, false) => None,
+    }
+}
+
+/// # brief
+/// Read the Yama ptrace scope, `None` if Yama is not enabled.
+fn ptrace_scope() -> Option<u32> {
+    parse_scope(&fs::read_to_string(SCOPE_PATH).ok()?)
+}
+
+/// # brief
+/// Parse the content of the `ptrace_scope` file.
+fn parse_scope(content: &str) -> Option<u32> {
+    content.trim().parse().ok()
+}
+
+/// # brief
+/// Explain what a Yama ptrace scope forbids, and how to lift the restriction.
+fn explain_scope(scope: u32) -> Option<String> {
+    match scope {
+        1 => Some(format!(
+            "{} is 1: only descendants of deet can be traced; run deet as root, or `echo 0 | sudo tee {}`",
+            SCOPE_PATH, SCOPE_PATH
+        )),
+        2 => Some(format!(
+            "{} is 2: only processes with CAP_SYS_PTRACE can attach; run deet as root",
+            SCOPE_PATH
+        )),
+        3 => Some(format!(
+            "{} is 3: ptrace is disabled until the next reboot",
+            SCOPE_PATH
+        )),
+        _ => None,
+    }
+}
+
+/// # brief
+/// Get the `TracerPid` field of the content of a `/proc/<pid>/status` file.
+///
+/// # return
+/// * The pid of the process tracing it, `None` if it is not traced (a `TracerPid` of 0).
+fn parse_tracer_pid(status: &str) -> Option<i32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|pid| pid.trim().parse().ok())
+        .filter(|pid| *pid != 0)
+}
+
+/// # brief
+/// Find the process already tracing `pid`, with its name when it can be read.
+fn tracer_of(pid: Pid) -> Option<String> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let tracer = parse_tracer_pid(&status)?;
+    Some(match fs::read_to_string(format!("/proc/{}/comm", tracer)) {
+        Ok(comm) => format!("{} ({})", tracer, comm.trim_end()),
+        Err(_) => tracer.to_string(),
+    })
+}
+
+/// # brief
+/// Explain why attaching to `pid` was refused with EPERM: another tracer, the Yama ptrace
+/// scope, or (when neither applies) missing permissions on the process.
+///
+/// # return
+/// * A one-line message saying what to do about it.
+pub fn explain_attach_denied(pid: Pid) -> String {
+    explain_denial(pid, tracer_of(pid), ptrace_scope())
+}
+
+/// # brief
+/// Explain a refused attach from what was found, see `explain_attach_denied`.
+///
+/// # param
+/// - `tracer` : The process already tracing `pid`, if any.
+/// - `scope` : The Yama ptrace scope, `None` without Yama.
+fn explain_denial(pid: Pid, tracer: Option<String>, scope: Option<u32>) -> String {
+    if let Some(tracer) = tracer {
+        return format!("process {} is already traced by process {}; detach that debugger first", pid, tracer);
+    }
+    if let Some(explanation) = scope.and_then(explain_scope) {
+        return explanation;
+    }
+    format!("not permitted to trace process {}; it may belong to another user", pid)
+}
+
+/// # brief
+/// Explain a failure to spawn the target when ptrace itself is disabled, which makes the
+/// PTRACE_TRACEME of the child fail.
+pub fn explain_spawn_failure() -> Option<String> {
+    ptrace_scope().filter(|scope| *scope == 3).and_then(explain_scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The start of `/proc/<pid>/status` of a process traced by process 4321
+    const TRACED_STATUS: &str = "Name:\tsleep\nUmask:\t0022\nState:\tt (tracing stop)\nTgid:\t1234\nNgid:\t0\nPid:\t1234\nPPid:\t1000\nTracerPid:\t4321\nUid:\t1000\t1000\t1000\t1000\n";
+
+    #[test]
+    fn finds_the_tracer_pid() {
+        assert_eq!(parse_tracer_pid(TRACED_STATUS), Some(4321));
+        assert_eq!(parse_tracer_pid(&TRACED_STATUS.replace("4321", "0")), None);
+        assert_eq!(parse_tracer_pid("Name:\tsleep\nPid:\t1234\n"), None);
+        assert_eq!(parse_tracer_pid("TracerPid:\tgarbage\n"), None);
+    }
+
+    #[test]
+    fn interprets_the_ptrace_scope() {
+        assert_eq!(parse_scope("1\n"), Some(1));
+        assert_eq!(parse_scope(""), None);
+        assert_eq!(explain_scope(0), None);
+        assert!(explain_scope(1).unwrap().contains("only descendants of deet"));
+        assert!(explain_scope(2).unwrap().contains("CAP_SYS_PTRACE"));
+        assert!(explain_scope(3).unwrap().contains("disabled until the next reboot"));
+    }
+
+    #[test]
+    fn explains_a_denied_attach() {
+        let pid = Pid::from_raw(1234);
+        let traced = explain_denial(pid, Some("4321 (gdb)".to_string()), Some(1));
+        assert_eq!(traced, "process 1234 is already traced by process 4321 (gdb); detach that debugger first");
+        assert_eq!(explain_denial(pid, None, Some(2)), explain_scope(2).unwrap());
+        assert_eq!(explain_denial(pid, None, Some(0)), "not permitted to trace process 1234; it may belong to another user");
+        assert_eq!(explain_denial(pid, None, None), "not permitted to trace process 1234; it may belong to another user");
+    }
+
+    /// A file crafted for a test in the temporary directory, removed when dropped
+    struct Crafted(std::path::PathBuf);
+
+    impl Crafted {
+        fn new(name: &str, content: &[u8], mode: u32) -> Self {
+            let path = std::env::temp_dir().join(format!("deet-preflight-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            Crafted(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for Crafted {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// # brief
+    /// Make the start of a little-endian 64-bit ELF header for `machine`.
+    fn elf_header(machine: u16) -> Vec<u8> {
+        let mut header = vec![0u8; 64];
+        header[..4].copy_from_slice(b"\x7fELF");
+        header[4] = 2;
+        header[5] = 1;
+        header[6] = 1;
+        // ET_EXEC
+        header[16] = 2;
+        header[18..20].copy_from_slice(&machine.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn accepts_an_executable_for_the_host() {
+        let file = Crafted::new("host", &elf_header(HOST_MACHINE as u16), 0o755);
+        assert_eq!(check_target(file.path()), Ok(()));
+    }
+
+    #[test]
+    fn refuses_a_script() {
+        let file = Crafted::new("script", b"#!/usr/bin/env python3 -u\nprint('hi')\n", 0o755);
+        let err = check_target(file.path()).unwrap_err();
+        assert!(err.contains("is a script for /usr/bin/env python3 -u"), "{}", err);
+        assert!(err.ends_with(&format!("deet --args /usr/bin/env {}", file.path())), "{}", err);
+    }
+
+    #[test]
+    fn refuses_a_foreign_machine() {
+        let file = Crafted::new("aarch64", &elf_header(183), 0o755);
+        let err = check_target(file.path()).unwrap_err();
+        assert!(err.contains("is built for AArch64, deet debugs x86-64 programs only"), "{}", err);
+    }
+
+    #[test]
+    fn refuses_what_is_not_elf() {
+        let file = Crafted::new("text", b"just some text, long enough to hold an ELF header if it were one\n", 0o755);
+        let err = check_target(file.path()).unwrap_err();
+        assert!(err.ends_with("is not an executable: it is not an ELF file"), "{}", err);
+    }
+
+    #[test]
+    fn refuses_a_file_without_execute_permission() {
+        let file = Crafted::new("noexec", &elf_header(HOST_MACHINE as u16), 0o644);
+        let err = check_target(file.path()).unwrap_err();
+        assert!(err.contains("is not executable (mode 644)"), "{}", err);
+    }
+
+    #[test]
+    fn leaves_a_missing_file_to_the_loader() {
+        assert_eq!(check_target("/nonexistent/deet-preflight-target"), Ok(()));
+    }
+
+    #[test]
+    fn names_the_setuid_bits() {
+        assert_eq!(setuid_bits(0o4755), Some("setuid"));
+        assert_eq!(setuid_bits(0o2755), Some("setgid"));
+        assert_eq!(setuid_bits(0o6755), Some("setuid and setgid"));
+        assert_eq!(setuid_bits(0o755), None);
+    }
+}
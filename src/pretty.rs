@@ -0,0 +1,456 @@
+//! Pretty-printers for values of well-known types, e.g. C strings and Rust `String`s, which
+//! would otherwise print as a pointer or a bag of words.
+
+use crate::dwarf_data::{Member, Type};
+
+/// The elements of an array, a collection or a string shown by default, `set print elements`
+pub const DEFAULT_ELEMENTS: usize = 200;
+
+/// # brief
+/// Get what ends a value cut at the element limit.
+///
+/// # param
+/// - `total` : The number of elements of the whole value, `None` if unknown such as for a C
+/// string longer than the limit.
+pub fn truncation_note(total: Option<usize>) -> String {
+    match total {
+        Some(total) => format!(
+            "... (truncated, {} total elements; use `print/full` or `set print elements 0`)",
+            total
+        ),
+        None => "... (truncated; use `print/full` or `set print elements 0`)".to_string(),
+    }
+}
+
+/// A value of the inferior as seen by a pretty-printer: its type, its bytes, and access to the
+/// inferior memory for what it points to.
+pub struct ValueReader<'a> {
+    pub ty: &'a Type,
+    /// The value itself, read from memory or from a register
+    pub bytes: &'a [u8],
+    memory: &'a dyn Fn(usize, usize) -> Option<Vec<u8>>,
+    members: &'a dyn Fn(&str) -> Option<Vec<Member>>,
+    /// The most elements shown, `None` for all of them
+    pub limit: Option<usize>,
+}
+
+impl<'a> ValueReader<'a> {
+    /// # brief
+    /// Create a reader for a value of type `ty`.
+    ///
+    /// # param
+    /// - `memory` : Reads `len` bytes of the inferior at an address, `None` if it can't.
+    /// - `members` : Looks up the members of a struct type by name in the debug information,
+    /// `None` if it's not there.
+    /// - `limit` : The most elements of a string or collection to show, `None` for all.
+    pub fn new(
+        ty: &'a Type,
+        bytes: &'a [u8],
+        memory: &'a dyn Fn(usize, usize) -> Option<Vec<u8>>,
+        members: &'a dyn Fn(&str) -> Option<Vec<Member>>,
+        limit: Option<usize>,
+    ) -> Self {
+        ValueReader { ty, bytes, memory, members, limit }
+    }
+
+    /// # brief
+    /// Get how many of `len` elements to show.
+    fn shown(&self, len: usize) -> usize {
+        self.limit.map_or(len, |limit| len.min(limit))
+    }
+
+    /// # brief
+    /// Read `len` bytes of the inferior at `addr`.
+    pub fn read(&self, addr: usize, len: usize) -> Option<Vec<u8>> {
+        (self.memory)(addr, len)
+    }
+
+    /// # brief
+    /// Get the 8-byte word at `offset` into the value.
+    pub fn word(&self, offset: usize) -> Option<usize> {
+        let bytes = self.bytes.get(offset..offset + 8)?;
+        Some(usize::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    /// # brief
+    /// Find the offset into the value of a member nested in its type, e.g. `["buf", "cap"]`
+    /// for the capacity of a Rust `Vec`, from the members of each type on the way.
+    ///
+    /// # return
+    /// * The offset, or `None` if a type or a member isn't in the debug information.
+    pub fn member_offset(&self, path: &[&str]) -> Option<usize> {
+        let mut type_name = self.ty.name.clone();
+        let mut offset = 0;
+        for name in path {
+            let member = (self.members)(&type_name)?.into_iter().find(|member| member.name.as_deref() == Some(*name))?;
+            offset += member.offset;
+            type_name = member.member_type.name;
+        }
+        Some(offset)
+    }
+
+    /// # brief
+    /// Read a NUL-terminated string at `addr`, at most `limit` bytes of it.
+    ///
+    /// # return
+    /// * `Some((String, bool))` - the string, and whether it was cut at the limit.
+    pub fn c_string(&self, addr: usize) -> Option<(String, bool)> {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let mut bytes = Vec::new();
+        // read in small chunks, the string may end right before unmapped memory
+        while bytes.len() < limit {
+            let chunk = self.read(addr + bytes.len(), 8)?;
+            match chunk.iter().position(|b| *b == 0) {
+                Some(end) => {
+                    bytes.extend_from_slice(&chunk[..end]);
+                    return Some((String::from_utf8_lossy(&bytes).into_owned(), false));
+                }
+                None => bytes.extend_from_slice(&chunk),
+            }
+        }
+        bytes.truncate(limit);
+        Some((String::from_utf8_lossy(&bytes).into_owned(), true))
+    }
+}
+
+/// A printer for the values of some types, picked by the type name.
+pub trait PrettyPrinter {
+    fn matches(&self, ty: &Type) -> bool;
+    /// # brief
+    /// Render the value, or `None` to fall back to the raw rendering, e.g. when the memory it
+    /// points to can't be read.
+    fn render(&self, val: &ValueReader) -> Option<String>;
+}
+
+/// The pretty-printers `print` tries, the last one first. Only the built-in ones: deet has no
+/// library target through which other code could register its own.
+pub struct Registry {
+    printers: Vec<Box<dyn PrettyPrinter>>,
+}
+
+impl Registry {
+    /// # brief
+    /// Create a registry holding the built-in printers for C strings and the Rust `&str`,
+    /// slices, `String`, `Vec` and `Option` of references.
+    pub fn new() -> Self {
+        Registry {
+            printers: vec![
+                Box::new(CString),
+                Box::new(RustStr),
+                Box::new(RustSlice),
+                Box::new(RustString),
+                Box::new(RustVec),
+                Box::new(RustOption),
+            ],
+        }
+    }
+
+    /// # brief
+    /// Render a value with the first printer matching its type.
+    ///
+    /// # return
+    /// * `Some(String)`, or `None` if no printer renders it.
+    pub fn render(&self, val: &ValueReader) -> Option<String> {
+        self.printers
+            .iter()
+            .rev()
+            .filter(|printer| printer.matches(val.ty))
+            .find_map(|printer| printer.render(val))
+    }
+}
+
+/// # brief
+/// Whether an integer type is signed, by its C or Rust name.
+pub fn is_signed(name: &str) -> bool {
+    let name = name.trim_start_matches("const ");
+    let rust_signed = name.len() > 1 && name.starts_with('i') && name[1..].chars().all(|c| c.is_ascii_digit());
+    if rust_signed || name == "isize" {
+        return true;
+    }
+    !name.contains("unsigned")
+        && ["char", "short", "int", "long"].iter().any(|c_type| name.contains(c_type))
+}
+
+/// # brief
+/// Read an integer of `bytes.len()` bytes (at most 8), sign-extending it for signed types.
+pub fn read_scalar(bytes: &[u8], ty: &Type) -> u64 {
+    let mut buf = [0u8; 8];
+    let len = bytes.len().min(8);
+    buf[..len].copy_from_slice(&bytes[..len]);
+    let value = u64::from_le_bytes(buf);
+    if len == 0 || len == 8 || !is_signed(&ty.name) {
+        return value;
+    }
+    let shift = 64 - 8 * len as u32;
+    (((value << shift) as i64) >> shift) as u64
+}
+
+/// # brief
+/// Format one element of a slice or vector of a primitive Rust type.
+///
+/// # return
+/// * `Some(String)`, or `None` if the element type is not a primitive one.
+fn format_element(bytes: &[u8], name: &str) -> Option<String> {
+    let raw = |len: usize| -> Option<u64> {
+        let mut buf = [0u8; 8];
+        buf[..len].copy_from_slice(bytes.get(..len)?);
+        Some(u64::from_le_bytes(buf))
+    };
+    Some(match name {
+        "u8" => format!("{}", raw(1)?),
+        "u16" => format!("{}", raw(2)?),
+        "u32" => format!("{}", raw(4)?),
+        "u64" | "usize" => format!("{}", raw(8)?),
+        "i8" => format!("{}", raw(1)? as u8 as i8),
+        "i16" => format!("{}", raw(2)? as u16 as i16),
+        "i32" => format!("{}", raw(4)? as u32 as i32),
+        "i64" | "isize" => format!("{}", raw(8)? as i64),
+        "f32" => format!("{}", f32::from_bits(raw(4)? as u32)),
+        "f64" => format!("{}", f64::from_bits(raw(8)?)),
+        "bool" => format!("{}", raw(1)? != 0),
+        "char" => format!("{:?}", std::char::from_u32(raw(4)? as u32)?),
+        _ => return None,
+    })
+}
+
+/// The size of a primitive Rust type, by name.
+fn element_size(name: &str) -> Option<usize> {
+    match name {
+        "u8" | "i8" | "bool" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" | "f32" | "char" => Some(4),
+        "u64" | "i64" | "usize" | "isize" | "f64" => Some(8),
+        _ => None,
+    }
+}
+
+/// # brief
+/// Render `len` elements of type `element` at `ptr` as `[a, b, c]`, up to the element limit.
+fn render_elements(val: &ValueReader, ptr: usize, len: usize, element: &str) -> Option<String> {
+    let size = element_size(element)?;
+    let shown = val.shown(len);
+    let bytes = if shown == 0 { Vec::new() } else { val.read(ptr, shown * size)? };
+    let mut items: Vec<String> = Vec::new();
+    for chunk in bytes.chunks(size).take(shown) {
+        items.push(format_element(chunk, element)?);
+    }
+    if len > shown {
+        items.push(truncation_note(Some(len)));
+    }
+    Some(format!("[{}]", items.join(", ")))
+}
+
+/// # brief
+/// Read `len` bytes of text at `ptr`, up to the element limit, as a quoted string.
+fn render_text(val: &ValueReader, ptr: usize, len: usize) -> Option<String> {
+    let shown = val.shown(len);
+    let bytes = if shown == 0 { Vec::new() } else { val.read(ptr, shown)? };
+    let text = String::from_utf8_lossy(&bytes);
+    Some(format!("{:?}{}", text, if len > shown { truncation_note(Some(len)) } else { String::new() }))
+}
+
+/// # brief
+/// Read the data pointer, the length and the capacity of a Rust `Vec`, or of the `Vec` a
+/// `String` holds in `vec`, at the offsets of its `buf.ptr`, `len` and `buf.cap` members: the
+/// field order of these types is not fixed. Since Rust 1.80 the pointer and the capacity are
+/// one level further, in `buf.inner`.
+///
+/// # param
+/// - `vec` : The path of the `Vec` in the value, empty for a `Vec` itself.
+fn vec_parts(val: &ValueReader, vec: &[&str]) -> Option<(usize, usize, usize)> {
+    let word = |path: &[&str]| val.word(val.member_offset(&[vec, path].concat())?);
+    let ptr = word(&["buf", "ptr"]).or_else(|| word(&["buf", "inner", "ptr"]))?;
+    let cap = word(&["buf", "cap"]).or_else(|| word(&["buf", "inner", "cap"]))?;
+    let len = word(&["len"])?;
+    if ptr == 0 || len > cap {
+        return None;
+    }
+    Some((ptr, len, cap))
+}
+
+/// The generic arguments of a type name, e.g. `u8` and `alloc::alloc::Global` for
+/// `Vec<u8, alloc::alloc::Global>`.
+fn generic_arguments(name: &str) -> Vec<String> {
+    let inner = match (name.find('<'), name.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &name[start + 1..end],
+        _ => return Vec::new(),
+    };
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in inner.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                arguments.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    arguments.push(current.trim().to_string());
+    arguments
+}
+
+/// C strings: `char *`, `const char *` and the signed/unsigned variants.
+struct CString;
+
+impl PrettyPrinter for CString {
+    fn matches(&self, ty: &Type) -> bool {
+        ty.name.ends_with("char *")
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        let ptr = val.word(0)?;
+        if ptr == 0 {
+            return Some("0x0".to_string());
+        }
+        let (text, cut) = val.c_string(ptr)?;
+        Some(format!("{:#x} {:?}{}", ptr, text, if cut { truncation_note(None) } else { String::new() }))
+    }
+}
+
+/// Rust string slices, a pointer and a length.
+struct RustStr;
+
+impl PrettyPrinter for RustStr {
+    fn matches(&self, ty: &Type) -> bool {
+        ty.name == "&str" || ty.name == "&mut str"
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        render_text(val, val.word(0)?, val.word(8)?)
+    }
+}
+
+/// Rust slices of primitive types, a pointer and a length.
+struct RustSlice;
+
+impl PrettyPrinter for RustSlice {
+    fn matches(&self, ty: &Type) -> bool {
+        (ty.name.starts_with("&[") || ty.name.starts_with("&mut [")) && ty.name.ends_with(']')
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        let start = val.ty.name.find('[')?;
+        let element = &val.ty.name[start + 1..val.ty.name.len() - 1];
+        render_elements(val, val.word(0)?, val.word(8)?, element)
+    }
+}
+
+/// Rust `String`s, a `Vec<u8>` holding UTF-8.
+struct RustString;
+
+impl PrettyPrinter for RustString {
+    fn matches(&self, ty: &Type) -> bool {
+        (ty.name == "String" || ty.name.ends_with("::String")) && ty.size == 24
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        let (ptr, len, _) = vec_parts(val, &["vec"])?;
+        render_text(val, ptr, len)
+    }
+}
+
+/// Rust `Vec`s of primitive types.
+struct RustVec;
+
+impl PrettyPrinter for RustVec {
+    fn matches(&self, ty: &Type) -> bool {
+        let base = ty.name.split('<').next().unwrap_or("");
+        (base == "Vec" || base.ends_with("::Vec")) && ty.size == 24
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        let element = generic_arguments(&val.ty.name).into_iter().next()?;
+        let (ptr, len, cap) = vec_parts(val, &[])?;
+        Some(format!("Vec(len={}, cap={}) {}", len, cap, render_elements(val, ptr, len, &element)?))
+    }
+}
+
+/// Rust `Option`s of references and boxes, where `None` is the null pointer.
+struct RustOption;
+
+impl PrettyPrinter for RustOption {
+    fn matches(&self, ty: &Type) -> bool {
+        let base = ty.name.split('<').next().unwrap_or("");
+        (base == "Option" || base.ends_with("::Option")) && ty.size == 8
+    }
+
+    fn render(&self, val: &ValueReader) -> Option<String> {
+        let inner = generic_arguments(&val.ty.name).into_iter().next()?;
+        let is_pointer = inner.starts_with('&') || inner.contains("Box<") || inner.contains("NonNull<");
+        if !is_pointer {
+            return None;
+        }
+        match val.word(0)? {
+            0 => Some("None".to_string()),
+            ptr => Some(format!("Some({:#x})", ptr)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// A member `name` of type `type_name` at `offset`.
+    fn member(name: &str, offset: usize, type_name: &str) -> Member {
+        Member { name: Some(name.to_string()), offset, member_type: Type::new(type_name.to_string(), 8), bit_size: None }
+    }
+
+    /// # brief
+    /// The members of a `Vec<u8>` that puts `len` first and the capacity before the pointer,
+    /// and of the `String` holding it, in the layout of the standard library before Rust 1.80.
+    fn std_members(name: &str) -> Option<Vec<Member>> {
+        match name {
+            "String" => Some(vec![member("vec", 0, "Vec<u8, alloc::alloc::Global>")]),
+            "Vec<u8, alloc::alloc::Global>" => {
+                Some(vec![member("len", 0, "usize"), member("buf", 8, "RawVec<u8, alloc::alloc::Global>")])
+            }
+            "RawVec<u8, alloc::alloc::Global>" => Some(vec![member("cap", 0, "usize"), member("ptr", 8, "Unique<u8>")]),
+            _ => None,
+        }
+    }
+
+    /// # brief
+    /// Render `words` as a value of type `name` with the members of `members`, over a heap
+    /// of "hello" at 0x1000.
+    fn render(name: &str, words: [usize; 3], members: &dyn Fn(&str) -> Option<Vec<Member>>) -> Option<String> {
+        let ty = Type::new(name.to_string(), 24);
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        let memory = |addr: usize, len: usize| b"hello".get(addr.checked_sub(0x1000)?..addr - 0x1000 + len).map(|bytes| bytes.to_vec());
+        Registry::new().render(&ValueReader::new(&ty, &bytes, &memory, members, None))
+    }
+
+    #[test]
+    fn vec_parts_are_read_at_their_member_offsets() {
+        // len, cap, ptr: the capacity is not the middle word
+        assert_eq!(render("Vec<u8, alloc::alloc::Global>", [3, 0x10, 0x1000], &std_members).as_deref(), Some("Vec(len=3, cap=16) [104, 101, 108]"));
+        assert_eq!(render("String", [5, 5, 0x1000], &std_members).as_deref(), Some("\"hello\""));
+    }
+
+    #[test]
+    fn vec_parts_follow_the_inner_raw_vec_of_newer_std() {
+        let members = |name: &str| match name {
+            "Vec<u8, alloc::alloc::Global>" => {
+                Some(vec![member("buf", 0, "RawVec<u8, alloc::alloc::Global>"), member("len", 16, "usize")])
+            }
+            "RawVec<u8, alloc::alloc::Global>" => Some(vec![member("inner", 0, "RawVecInner<alloc::alloc::Global>")]),
+            "RawVecInner<alloc::alloc::Global>" => Some(vec![member("ptr", 0, "Unique<u8>"), member("cap", 8, "Cap")]),
+            _ => None,
+        };
+        assert_eq!(render("Vec<u8, alloc::alloc::Global>", [0x1000, 8, 2], &members).as_deref(), Some("Vec(len=2, cap=8) [104, 101]"));
+    }
+
+    #[test]
+    fn a_vec_without_its_members_is_left_raw() {
+        assert_eq!(render("Vec<u8, alloc::alloc::Global>", [3, 0x10, 0x1000], &|_| None), None);
+        // a length past the capacity is no Vec
+        assert_eq!(render("Vec<u8, alloc::alloc::Global>", [17, 0x10, 0x1000], &std_members), None);
+    }
+}
@@ -0,0 +1,250 @@
+/// A small regular expression matcher for `rbreak`, supporting what is useful on symbol names:
+/// literals, `.`, `^`, `$`, `*`, `+`, `?`, classes like `[a-z_]` and `[^0-9]`, the escapes
+/// `\d`, `\w`, `\s` and alternation with `|`. There are no groups.
+pub struct Regex {
+    branches: Vec<Branch>,
+}
+
+/// One alternative of a pattern
+struct Branch {
+    /// `^`: the match must start at the beginning of the text
+    at_start: bool,
+    /// `$`: the match must reach the end of the text
+    at_end: bool,
+    pieces: Vec<Piece>,
+}
+
+struct Piece {
+    atom: Atom,
+    /// The minimum and maximum number of repetitions
+    min: usize,
+    max: usize,
+}
+
+enum Atom {
+    Any,
+    Char(char),
+    /// The items, and whether the class is negated
+    Class(Vec<ClassItem>, bool),
+}
+
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(lo, hi) => *lo <= c && c <= *hi,
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace(),
+        }
+    }
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => *expected == c,
+            Atom::Class(items, negated) => items.iter().any(|item| item.matches(c)) != *negated,
+        }
+    }
+}
+
+/// # brief
+/// Get the class item of an escape like `\d`, or of an escaped literal like `\.`.
+fn escape_item(c: char) -> ClassItem {
+    match c {
+        'd' => ClassItem::Digit,
+        'w' => ClassItem::Word,
+        's' => ClassItem::Space,
+        c => ClassItem::Range(c, c),
+    }
+}
+
+/// # brief
+/// Parse the inside of a `[...]` class, after the opening bracket.
+fn parse_class(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Atom, String> {
+    let negated = chars.peek() == Some(&'^');
+    if negated {
+        chars.next();
+    }
+    let mut items = Vec::new();
+    let mut first = true;
+    loop {
+        let c = chars.next().ok_or("unterminated [")?;
+        // a `]` right after the bracket is a literal
+        if c == ']' && !first {
+            return Ok(Atom::Class(items, negated));
+        }
+        first = false;
+        let item = if c == '\\' {
+            escape_item(chars.next().ok_or("trailing backslash")?)
+        } else {
+            ClassItem::Range(c, c)
+        };
+        match (item, chars.peek()) {
+            (ClassItem::Range(lo, _), Some('-')) => {
+                chars.next();
+                match chars.next().ok_or("unterminated [")? {
+                    // a trailing `-` is a literal
+                    ']' => {
+                        items.push(ClassItem::Range(lo, lo));
+                        items.push(ClassItem::Range('-', '-'));
+                        return Ok(Atom::Class(items, negated));
+                    }
+                    hi if hi < lo => return Err(format!("invalid range {}-{}", lo, hi)),
+                    hi => items.push(ClassItem::Range(lo, hi)),
+                }
+            }
+            (item, _) => items.push(item),
+        }
+    }
+}
+
+/// # brief
+/// Try to match `pieces` at the start of `text`, preferring longer repetitions.
+fn match_here(pieces: &[Piece], text: &[char], at_end: bool) -> bool {
+    let (piece, rest) = match pieces.split_first() {
+        Some(split) => split,
+        None => return !at_end || text.is_empty(),
+    };
+    let mut count = 0;
+    while count < piece.max && count < text.len() && piece.atom.matches(text[count]) {
+        count += 1;
+    }
+    count >= piece.min && (piece.min..=count).rev().any(|n| match_here(rest, &text[n..], at_end))
+}
+
+impl Regex {
+    /// # brief
+    /// Compile a pattern.
+    ///
+    /// # return
+    /// * `Ok(Regex)`, or `Err(String)` describing what is wrong with the pattern.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let mut branches = Vec::new();
+        let mut branch = Branch { at_start: false, at_end: false, pieces: Vec::new() };
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '^' if branch.pieces.is_empty() && !branch.at_start => {
+                    branch.at_start = true;
+                    continue;
+                }
+                '$' if chars.peek().map_or(true, |next| *next == '|') => {
+                    branch.at_end = true;
+                    continue;
+                }
+                '|' => {
+                    branches.push(std::mem::replace(
+                        &mut branch,
+                        Branch { at_start: false, at_end: false, pieces: Vec::new() },
+                    ));
+                    continue;
+                }
+                '*' | '+' | '?' => {
+                    let piece = branch.pieces.last_mut().ok_or(format!("nothing to repeat before {}", c))?;
+                    if piece.min != 1 || piece.max != 1 {
+                        return Err(format!("nothing to repeat before {}", c));
+                    }
+                    match c {
+                        '*' => (piece.min, piece.max) = (0, usize::MAX),
+                        '+' => piece.max = usize::MAX,
+                        _ => piece.min = 0,
+                    }
+                    continue;
+                }
+                '.' => Atom::Any,
+                '[' => parse_class(&mut chars)?,
+                '\\' => match escape_item(chars.next().ok_or("trailing backslash")?) {
+                    ClassItem::Range(c, _) => Atom::Char(c),
+                    item => Atom::Class(vec![item], false),
+                },
+                c => Atom::Char(c),
+            };
+            branch.pieces.push(Piece { atom, min: 1, max: 1 });
+        }
+        branches.push(branch);
+        Ok(Regex { branches })
+    }
+
+    /// # brief
+    /// Check whether the pattern matches somewhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.branches.iter().any(|branch| {
+            if branch.at_start {
+                match_here(&branch.pieces, &text, branch.at_end)
+            } else {
+                (0..=text.len()).any(|start| match_here(&branch.pieces, &text[start..], branch.at_end))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_symbol_names() {
+        let cases = [
+            ("parse", "parse_line", true),
+            ("^parse", "do_parse", false),
+            ("line$", "parse_line", true),
+            ("line$", "parse_lines", false),
+            ("^main$", "main", true),
+            ("^main$", "main2", false),
+            ("^p.rse", "parse", true),
+            ("^_*start", "__start", true),
+            ("^_+start", "start", false),
+            ("^handlers?$", "handler", true),
+            ("^handlers?$", "handlerss", false),
+            ("^[a-c_]+$", "cab_", true),
+            ("^[a-c_]+$", "cabd", false),
+            ("^[^0-9]", "9lives", false),
+            ("^[^0-9]", "lives9", true),
+            ("_v\\d$", "parse_v2", true),
+            ("^\\w+$", "parse line", false),
+            ("\\s", "operator new", true),
+            ("std::.*::new$|^alloc_", "std::vec::Vec<T>::new", true),
+            ("std::.*::new$|^alloc_", "alloc_page", true),
+            ("std::.*::new$|^alloc_", "my_alloc_page", false),
+            ("a\\.b", "a.b", true),
+            ("a\\.b", "axb", false),
+            // a `]` first and a `-` last are literals
+            ("^[]x]", "]", true),
+            ("[a-]$", "x-", true),
+            // `^` elsewhere and `$` before more are literals
+            ("a^b", "a^b", true),
+            ("a$b", "a$b", true),
+            ("", "anything", true),
+        ];
+        for (pattern, text, matches) in cases {
+            assert_eq!(Regex::new(pattern).unwrap().is_match(text), matches, "{} on {}", pattern, text);
+        }
+    }
+
+    #[test]
+    fn invalid_patterns_are_explained() {
+        let cases = [
+            ("*main", "nothing to repeat before *"),
+            ("a+*", "nothing to repeat before *"),
+            ("a|?", "nothing to repeat before ?"),
+            ("[a-z", "unterminated ["),
+            ("[a-", "unterminated ["),
+            ("main\\", "trailing backslash"),
+            ("[\\", "trailing backslash"),
+            ("[z-a]", "invalid range z-a"),
+        ];
+        for (pattern, message) in cases {
+            assert_eq!(Regex::new(pattern).err().as_deref(), Some(message), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use libc::user_regs_struct;
+
+/// # brief
+/// Get a general purpose register by name. `pc`, `sp` and `fp` are accepted as aliases for
+/// `rip`, `rsp` and `rbp`.
+///
+/// # param
+/// - `regs` : The registers of the stopped inferior.
+/// - `name` : The register name, without the `$`.
+///
+/// # return
+/// * `Some(u64)` - the register value, or `None` if there is no such register.
+pub fn get_register(regs: &user_regs_struct, name: &str) -> Option<u64> {
+    Some(match name {
+        "rax" => regs.rax,
+        "rbx" => regs.rbx,
+        "rcx" => regs.rcx,
+        "rdx" => regs.rdx,
+        "rsi" => regs.rsi,
+        "rdi" => regs.rdi,
+        "rbp" | "fp" => regs.rbp,
+        "rsp" | "sp" => regs.rsp,
+        "r8" => regs.r8,
+        "r9" => regs.r9,
+        "r10" => regs.r10,
+        "r11" => regs.r11,
+        "r12" => regs.r12,
+        "r13" => regs.r13,
+        "r14" => regs.r14,
+        "r15" => regs.r15,
+        "rip" | "pc" => regs.rip,
+        "eflags" => regs.eflags,
+        "cs" => regs.cs,
+        "ss" => regs.ss,
+        "ds" => regs.ds,
+        "es" => regs.es,
+        "fs" => regs.fs,
+        "gs" => regs.gs,
+        "fs_base" => regs.fs_base,
+        "gs_base" => regs.gs_base,
+        "orig_rax" => regs.orig_rax,
+        _ => return None,
+    })
+}
+
+/// The general purpose registers, in the order `info all-registers` prints them
+pub const GENERAL_REGISTERS: [&str; 27] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+    "r13", "r14", "r15", "rip", "eflags", "cs", "ss", "ds", "es", "fs", "gs", "fs_base",
+    "gs_base", "orig_rax",
+];
+
+/// The general purpose registers by DWARF register number on x86_64, as assigned by the
+/// System V psABI (rdx comes before rcx there)
+const DWARF_REGISTERS: [&str; 17] = [
+    "rax", "rdx", "rcx", "rbx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+    "r13", "r14", "r15", "rip",
+];
+
+/// # brief
+/// Get the name of a general purpose register by its DWARF register number.
+pub fn dwarf_register_name(regnum: u16) -> Option<&'static str> {
+    DWARF_REGISTERS.get(regnum as usize).copied()
+}
+
+/// # brief
+/// Get a general purpose register by its DWARF register number, as used in location
+/// expressions.
+pub fn get_dwarf_register(regs: &user_regs_struct, regnum: u16) -> Option<u64> {
+    get_register(regs, dwarf_register_name(regnum)?)
+}
+
+/// The x87 and SSE state of the inferior, as read by `PTRACE_GETFPREGS`.
+#[derive(Debug, Clone)]
+pub struct FpRegisters {
+    /// x87 control word
+    pub fcw: u16,
+    /// x87 status word
+    pub fsw: u16,
+    /// x87 tag word (abridged)
+    pub ftw: u16,
+    pub mxcsr: u32,
+    /// The x87 stack, 80-bit values in the low bits
+    pub st: [u128; 8],
+    pub xmm: [u128; 16],
+}
+
+/// Assemble 128-bit registers from the little-endian words of the kernel's layout.
+fn to_u128s<const N: usize>(words: &[u32]) -> [u128; N] {
+    let mut regs = [0u128; N];
+    for (i, reg) in regs.iter_mut().enumerate() {
+        *reg = words[i * 4..i * 4 + 4]
+            .iter()
+            .rev()
+            .fold(0u128, |acc, word| (acc << 32) | *word as u128);
+    }
+    regs
+}
+
+impl FpRegisters {
+    pub fn from_raw(raw: &libc::user_fpregs_struct) -> Self {
+        FpRegisters {
+            fcw: raw.cwd,
+            fsw: raw.swd,
+            ftw: raw.ftw,
+            mxcsr: raw.mxcsr,
+            st: to_u128s(&raw.st_space),
+            xmm: to_u128s(&raw.xmm_space),
+        }
+    }
+
+    /// # brief
+    /// Get a 128-bit register (`xmm0`..`xmm15`, `st0`..`st7`) by name.
+    pub fn get_vector(&self, name: &str) -> Option<u128> {
+        let (regs, index): (&[u128], &str) = if let Some(index) = name.strip_prefix("xmm") {
+            (&self.xmm, index)
+        } else {
+            (&self.st, name.strip_prefix("st")?)
+        };
+        regs.get(index.parse::<usize>().ok()?).copied()
+    }
+
+    /// # brief
+    /// Get a control or status register (`fctrl`, `fstat`, `ftag`, `mxcsr`) by name.
+    pub fn get_control(&self, name: &str) -> Option<u64> {
+        Some(match name {
+            "fctrl" => self.fcw as u64,
+            "fstat" => self.fsw as u64,
+            "ftag" => self.ftw as u64,
+            "mxcsr" => self.mxcsr as u64,
+            _ => return None,
+        })
+    }
+}
+
+/// # brief
+/// Convert an x87 80-bit extended precision value to the nearest `f64`.
+pub fn f80_to_f64(raw: u128) -> f64 {
+    let mantissa = raw as u64;
+    let exponent = ((raw >> 64) & 0x7fff) as i32;
+    let sign = if (raw >> 79) & 1 == 1 { -1.0 } else { 1.0 };
+    let value = match exponent {
+        0 if mantissa == 0 => 0.0,
+        0x7fff if mantissa << 1 == 0 => f64::INFINITY,
+        0x7fff => f64::NAN,
+        // the mantissa has an explicit integer bit, so it is scaled by 2^-63
+        _ => mantissa as f64 * 2f64.powi(exponent - 16383 - 63),
+    };
+    sign * value
+}
+
+/// # brief
+/// Format an xmm register as raw hex and as its two `f64` and four `f32` lanes, lowest lane
+/// first.
+pub fn format_xmm(value: u128) -> String {
+    let doubles: Vec<String> = (0..2)
+        .map(|i| format!("{}", f64::from_bits((value >> (i * 64)) as u64)))
+        .collect();
+    let floats: Vec<String> = (0..4)
+        .map(|i| format!("{}", f32::from_bits((value >> (i * 32)) as u32)))
+        .collect();
+    format!(
+        "{:#034x}  v2_double {{{}}}  v4_float {{{}}}",
+        value,
+        doubles.join(", "),
+        floats.join(", ")
+    )
+}
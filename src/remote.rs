@@ -0,0 +1,537 @@
+//! The client side of the gdb remote serial protocol, to debug a program run by a stub such as
+//! `gdbserver :2345 ./app` on another machine or in a container where deet can't ptrace.
+
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+use crate::inferior::Status;
+use crate::target::TargetAccess;
+
+/// The most bytes of memory one `m` packet asks for
+const MAX_MEMORY_CHUNK: usize = 0x800;
+
+/// How many times a packet is sent again after the stub reported a bad checksum
+const MAX_RETRANSMITS: usize = 3;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The stub sent something deet doesn't understand
+    Protocol(String),
+    /// The stub answered `Exx`, usually with an errno value
+    Remote(u8),
+    /// The stub doesn't support the request: it answered with an empty packet
+    Unsupported(&'static str),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "connection to the remote stub failed: {}", err),
+            Error::Protocol(message) => write!(f, "unexpected reply from the remote stub: {}", message),
+            Error::Remote(code) => write!(f, "the remote stub reported error {:02x}", code),
+            Error::Unsupported(request) => write!(f, "the remote stub does not support {}", request),
+        }
+    }
+}
+
+impl From<Error> for nix::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Remote(code) => nix::Error::Sys(Errno::from_i32(code as i32)),
+            _ => nix::Error::Sys(Errno::EIO),
+        }
+    }
+}
+
+/// # brief
+/// Map a gdb signal number, as found in stop replies, to the Linux signal. The numbers agree
+/// for the classic signals only.
+fn gdb_signal(number: u8) -> Option<Signal> {
+    Some(match number {
+        1 => Signal::SIGHUP,
+        2 => Signal::SIGINT,
+        3 => Signal::SIGQUIT,
+        4 => Signal::SIGILL,
+        5 => Signal::SIGTRAP,
+        6 => Signal::SIGABRT,
+        8 => Signal::SIGFPE,
+        9 => Signal::SIGKILL,
+        10 => Signal::SIGBUS,
+        11 => Signal::SIGSEGV,
+        12 => Signal::SIGSYS,
+        13 => Signal::SIGPIPE,
+        14 => Signal::SIGALRM,
+        15 => Signal::SIGTERM,
+        17 => Signal::SIGSTOP,
+        18 => Signal::SIGTSTP,
+        19 => Signal::SIGCONT,
+        20 => Signal::SIGCHLD,
+        30 => Signal::SIGUSR1,
+        31 => Signal::SIGUSR2,
+        _ => return None,
+    })
+}
+
+/// # brief
+/// Decode a string of hex digit pairs. `xx`, which a stub sends for unavailable register
+/// bytes, decodes to 0.
+fn decode_hex(text: &str) -> Result<Vec<u8>, Error> {
+    if text.len() % 2 != 0 {
+        return Err(Error::Protocol(format!("odd number of hex digits in \"{}\"", text)));
+    }
+    let digit = |byte: u8| (byte as char).to_digit(16);
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| match (pair[0], pair[1]) {
+            (b'x', b'x') => Ok(0),
+            (high, low) => match (digit(high), digit(low)) {
+                (Some(high), Some(low)) => Ok((high * 16 + low) as u8),
+                _ => Err(Error::Protocol(format!("invalid hex \"{}\"", String::from_utf8_lossy(pair)))),
+            },
+        })
+        .collect()
+}
+
+/// # brief
+/// Undo the escaping (`}` followed by the byte xor 0x20) and the run-length encoding (`*`
+/// followed by the repeat count + 29) of a packet body.
+fn unescape(body: &[u8]) -> Result<String, Error> {
+    let mut out: Vec<u8> = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            b'}' => {
+                let byte = *body.get(i + 1).ok_or(Error::Protocol("truncated escape".to_string()))?;
+                out.push(byte ^ 0x20);
+                i += 2;
+            }
+            b'*' => {
+                let last = *out.last().ok_or(Error::Protocol("repeat without a character".to_string()))?;
+                let count = *body.get(i + 1).ok_or(Error::Protocol("truncated repeat".to_string()))?;
+                for _ in 0..(count as usize).saturating_sub(29) {
+                    out.push(last);
+                }
+                i += 2;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| Error::Protocol("packet is not text".to_string()))
+}
+
+/// The byte stream to the stub, a `TcpStream` outside the tests
+trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+/// A connection to a remote stub
+pub struct Remote {
+    stream: RefCell<BufReader<Box<dyn Connection>>>,
+    /// The offset the stub reports the program text is relocated by (`qOffsets`), 0 if it
+    /// doesn't say
+    pub text_offset: usize,
+}
+
+impl Remote {
+    /// # brief
+    /// Connect to the stub at `address` (`host:port`) and ask where the program stopped.
+    ///
+    /// # return
+    /// * The connection and the status of the program, or the `Error` of the exchange.
+    pub fn connect(address: &str) -> Result<(Remote, Status), Error> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let remote = Remote {
+            stream: RefCell::new(BufReader::new(Box::new(stream))),
+            text_offset: 0,
+        };
+        remote.request("qSupported:swbreak+")?;
+        let reply = remote.request("?")?;
+        let status = remote.stop_status(reply)?;
+        let offsets = remote.request("qOffsets").unwrap_or_default();
+        let text_offset = offsets
+            .split(';')
+            .find_map(|field| field.strip_prefix("Text="))
+            .and_then(|offset| usize::from_str_radix(offset, 16).ok())
+            .unwrap_or(0);
+        Ok((Remote { text_offset, ..remote }, status))
+    }
+
+    /// # brief
+    /// Send a packet, `$data#checksum`, until the stub acknowledges it.
+    fn send_packet(&self, data: &str) -> Result<(), Error> {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        let packet = format!("${}#{:02x}", data, checksum);
+        let mut stream = self.stream.borrow_mut();
+        for _ in 0..=MAX_RETRANSMITS {
+            stream.get_mut().write_all(packet.as_bytes())?;
+            let mut ack = [0u8];
+            stream.read_exact(&mut ack)?;
+            match ack[0] {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                other => return Err(Error::Protocol(format!("expected an acknowledgment, got {:?}", other as char))),
+            }
+        }
+        Err(Error::Protocol(format!("packet {} rejected {} times", data, MAX_RETRANSMITS + 1)))
+    }
+
+    /// # brief
+    /// Read the next packet of the stub, acknowledging it.
+    ///
+    /// # return
+    /// * The body of the packet, unescaped.
+    fn read_packet(&self) -> Result<String, Error> {
+        let mut stream = self.stream.borrow_mut();
+        loop {
+            // skip the acknowledgments and noise before the packet
+            let mut skipped = Vec::new();
+            stream.read_until(b'$', &mut skipped)?;
+            if skipped.last() != Some(&b'$') {
+                return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "the stub closed the connection")));
+            }
+            let mut body = Vec::new();
+            stream.read_until(b'#', &mut body)?;
+            body.pop();
+            let mut checksum = [0u8; 2];
+            stream.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if expected != Some(body.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))) {
+                stream.get_mut().write_all(b"-")?;
+                continue;
+            }
+            stream.get_mut().write_all(b"+")?;
+            return unescape(&body);
+        }
+    }
+
+    /// # brief
+    /// Send a request and read the reply.
+    ///
+    /// # return
+    /// * The reply, or `Error::Remote` for an `Exx` reply.
+    fn request(&self, data: &str) -> Result<String, Error> {
+        self.send_packet(data)?;
+        let reply = self.read_packet()?;
+        match reply.strip_prefix('E').map(|code| u8::from_str_radix(code, 16)) {
+            Some(Ok(code)) if reply.len() == 3 => Err(Error::Remote(code)),
+            _ => Ok(reply),
+        }
+    }
+
+    /// # brief
+    /// Turn a stop reply into a `Status`. Console output of the program (`O` packets) coming
+    /// first is printed, and the stop reply following it read.
+    fn stop_status(&self, mut reply: String) -> Result<Status, Error> {
+        loop {
+            let number = reply.get(1..3).and_then(|code| u8::from_str_radix(code, 16).ok());
+            match (reply.chars().next(), number) {
+                (Some('O'), _) if reply != "OK" => {
+                    print!("{}", String::from_utf8_lossy(&decode_hex(&reply[1..])?));
+                    io::stdout().flush().ok();
+                    reply = self.read_packet()?;
+                }
+                (Some('S'), Some(number)) | (Some('T'), Some(number)) => {
+                    let signal = gdb_signal(number).unwrap_or(Signal::SIGTRAP);
+                    // the reported location comes from the registers after the stop
+                    let rip = self.general_registers()?.rip as usize;
+                    return Ok(Status::Stopped(signal, rip));
+                }
+                (Some('W'), Some(code)) => return Ok(Status::Exited(code as i32)),
+                (Some('X'), Some(number)) => return Ok(Status::Signaled(gdb_signal(number).unwrap_or(Signal::SIGKILL), false)),
+                _ => return Err(Error::Protocol(format!("invalid stop reply \"{}\"", reply))),
+            }
+        }
+    }
+
+    /// # brief
+    /// Resume the program, for one instruction if `step` is set, and wait for it to stop.
+    pub fn resume(&self, step: bool) -> Result<Status, Error> {
+        self.send_packet(if step { "s" } else { "c" })?;
+        let reply = self.read_packet()?;
+        self.stop_status(reply)
+    }
+
+    /// # brief
+    /// Insert (`insert` set) or remove a software breakpoint at `addr`. The stub keeps the
+    /// original byte, hides the trap from memory reads and reports a stop at the breakpoint
+    /// address.
+    pub fn set_breakpoint(&self, addr: usize, insert: bool) -> Result<(), Error> {
+        let op = if insert { 'Z' } else { 'z' };
+        match self.request(&format!("{}0,{:x},1", op, addr))?.as_str() {
+            "OK" => Ok(()),
+            "" => Err(Error::Unsupported("software breakpoints")),
+            other => Err(Error::Protocol(format!("setting a breakpoint: \"{}\"", other))),
+        }
+    }
+
+    /// # brief
+    /// Read the general purpose registers. The `g` packet of x86-64 holds rax, rbx, rcx, rdx,
+    /// rsi, rdi, rbp, rsp, r8-r15 and rip as 8 bytes, then eflags, cs, ss, ds, es, fs and gs as
+    /// 4 bytes.
+    fn general_registers(&self) -> Result<libc::user_regs_struct, Error> {
+        let raw = decode_hex(&self.request("g")?)?;
+        let word = |index: usize| -> Result<u64, Error> {
+            let bytes = raw.get(index * 8..index * 8 + 8).ok_or(Error::Protocol("short register packet".to_string()))?;
+            Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        let half = |index: usize| -> Result<u64, Error> {
+            let start = 17 * 8 + index * 4;
+            let bytes = raw.get(start..start + 4).ok_or(Error::Protocol("short register packet".to_string()))?;
+            Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as u64)
+        };
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.rax = word(0)?;
+        regs.rbx = word(1)?;
+        regs.rcx = word(2)?;
+        regs.rdx = word(3)?;
+        regs.rsi = word(4)?;
+        regs.rdi = word(5)?;
+        regs.rbp = word(6)?;
+        regs.rsp = word(7)?;
+        regs.r8 = word(8)?;
+        regs.r9 = word(9)?;
+        regs.r10 = word(10)?;
+        regs.r11 = word(11)?;
+        regs.r12 = word(12)?;
+        regs.r13 = word(13)?;
+        regs.r14 = word(14)?;
+        regs.r15 = word(15)?;
+        regs.rip = word(16)?;
+        regs.eflags = half(0)?;
+        regs.cs = half(1)?;
+        regs.ss = half(2)?;
+        regs.ds = half(3)?;
+        regs.es = half(4)?;
+        regs.fs = half(5)?;
+        regs.gs = half(6)?;
+        Ok(regs)
+    }
+
+    /// # brief
+    /// Detach from the program, which keeps running, and close the connection.
+    pub fn detach(&self) -> Result<(), Error> {
+        self.request("D").map(|_| ())
+    }
+}
+
+impl TargetAccess for Remote {
+    fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            let chunk = (len - bytes.len()).min(MAX_MEMORY_CHUNK);
+            let data = decode_hex(&self.request(&format!("m{:x},{:x}", addr + bytes.len(), chunk))?)?;
+            if data.is_empty() {
+                return Err(nix::Error::Sys(Errno::EIO));
+            }
+            bytes.extend_from_slice(&data);
+        }
+        bytes.truncate(len);
+        Ok(bytes)
+    }
+
+    fn registers(&self) -> Result<libc::user_regs_struct, nix::Error> {
+        Ok(self.general_registers()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    /// A stub replying with canned bytes, keeping what deet sent
+    struct Canned {
+        replies: Cursor<Vec<u8>>,
+        sent: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl Read for Canned {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Canned {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.sent.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// # brief
+    /// Connect to a stub that sends `replies`.
+    ///
+    /// # return
+    /// * The connection and what deet sends over it.
+    fn stub(replies: &str) -> (Remote, Rc<RefCell<Vec<u8>>>) {
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let canned = Canned { replies: Cursor::new(replies.as_bytes().to_vec()), sent: sent.clone() };
+        let remote = Remote { stream: RefCell::new(BufReader::new(Box::new(canned))), text_offset: 0 };
+        (remote, sent)
+    }
+
+    /// # brief
+    /// Frame a packet body with its checksum.
+    fn packet(body: &str) -> String {
+        format!("${}#{:02x}", body, body.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte)))
+    }
+
+    /// # brief
+    /// Get what deet sent to the stub so far.
+    fn sent(sent: &Rc<RefCell<Vec<u8>>>) -> String {
+        String::from_utf8(sent.borrow().clone()).unwrap()
+    }
+
+    /// # brief
+    /// Get the message of a protocol error, failing the test for any other result.
+    fn protocol_error<T: fmt::Debug>(result: Result<T, Error>) -> String {
+        match result {
+            Err(Error::Protocol(message)) => message,
+            other => panic!("expected a protocol error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn hex_pairs_decode_to_bytes() {
+        assert_eq!(decode_hex("00ff7A").unwrap(), vec![0x00, 0xff, 0x7a]);
+        assert_eq!(decode_hex("01xx").unwrap(), vec![0x01, 0x00]);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn bad_hex_is_an_error() {
+        assert_eq!(protocol_error(decode_hex("abc")), "odd number of hex digits in \"abc\"");
+        assert_eq!(protocol_error(decode_hex("0g")), "invalid hex \"0g\"");
+        assert_eq!(protocol_error(decode_hex("+1")), "invalid hex \"+1\"");
+    }
+
+    #[test]
+    fn non_ascii_hex_is_an_error() {
+        // 'é' is two bytes, the first pair ends in the middle of it
+        assert!(protocol_error(decode_hex("aé0")).starts_with("invalid hex"));
+    }
+
+    #[test]
+    fn unescape_undoes_escapes_and_repeats() {
+        assert_eq!(unescape(b"OK").unwrap(), "OK");
+        assert_eq!(unescape(b"a}\x03b").unwrap(), "a#b");
+        assert_eq!(unescape(b"}]").unwrap(), "}");
+        // ' ' is 32: three more repeats
+        assert_eq!(unescape(b"0* 1").unwrap(), "00001");
+        // a count of 29 or less repeats nothing
+        assert_eq!(unescape(b"0*\x1d").unwrap(), "0");
+    }
+
+    #[test]
+    fn unescape_errors() {
+        assert_eq!(protocol_error(unescape(b"a}")), "truncated escape");
+        assert_eq!(protocol_error(unescape(b"*$")), "repeat without a character");
+        assert_eq!(protocol_error(unescape(b"a*")), "truncated repeat");
+        assert_eq!(protocol_error(unescape(b"\xff")), "packet is not text");
+    }
+
+    #[test]
+    fn packet_is_framed_with_its_checksum() {
+        let (remote, out) = stub("+");
+        remote.send_packet("g").unwrap();
+        assert_eq!(sent(&out), "$g#67");
+        let (remote, out) = stub("+");
+        remote.send_packet("m4015d0,8").unwrap();
+        assert_eq!(sent(&out), packet("m4015d0,8"));
+    }
+
+    #[test]
+    fn rejected_packet_is_sent_again() {
+        let (remote, out) = stub("--+");
+        remote.send_packet("?").unwrap();
+        assert_eq!(sent(&out), "$?#3f$?#3f$?#3f");
+        let (remote, _) = stub("----");
+        assert_eq!(protocol_error(remote.send_packet("?")), "packet ? rejected 4 times");
+        let (remote, _) = stub("x");
+        assert_eq!(protocol_error(remote.send_packet("?")), "expected an acknowledgment, got 'x'");
+    }
+
+    #[test]
+    fn packet_is_read_and_acknowledged() {
+        let (remote, out) = stub(&format!("+{}", packet("T05thread:1;")));
+        assert_eq!(remote.read_packet().unwrap(), "T05thread:1;");
+        assert_eq!(sent(&out), "+");
+    }
+
+    #[test]
+    fn packet_with_a_bad_checksum_is_asked_again() {
+        let (remote, out) = stub(&format!("$OK#00{}", packet("OK")));
+        assert_eq!(remote.read_packet().unwrap(), "OK");
+        assert_eq!(sent(&out), "-+");
+    }
+
+    #[test]
+    fn packet_is_run_length_decoded() {
+        let (remote, _) = stub(&packet("0* 1"));
+        assert_eq!(remote.read_packet().unwrap(), "00001");
+    }
+
+    #[test]
+    fn closed_connection_is_an_io_error() {
+        let (remote, _) = stub("+");
+        assert!(matches!(remote.read_packet(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn error_reply_is_a_remote_error() {
+        let (remote, _) = stub(&format!("+{}", packet("E0e")));
+        assert!(matches!(remote.request("m0,8"), Err(Error::Remote(0x0e))));
+        // a reply starting with E is not an error unless it is one
+        let (remote, _) = stub(&format!("+{}", packet("E0ex")));
+        assert_eq!(remote.request("m0,8").unwrap(), "E0ex");
+    }
+
+    #[test]
+    fn g_packet_layout() {
+        // 17 registers of 8 bytes, rax = 0 to rip = 16, then eflags and the segments
+        let mut body = String::new();
+        for index in 0..17u64 {
+            for byte in (0x1000 + index).to_le_bytes().iter() {
+                body.push_str(&format!("{:02x}", byte));
+            }
+        }
+        for index in 0..7u32 {
+            for byte in (0x200 + index).to_le_bytes().iter() {
+                body.push_str(&format!("{:02x}", byte));
+            }
+        }
+        let (remote, out) = stub(&format!("+{}", packet(&body)));
+        let regs = remote.general_registers().unwrap();
+        assert_eq!(sent(&out), "$g#67+");
+        assert_eq!((regs.rax, regs.rbx, regs.rcx, regs.rdx), (0x1000, 0x1001, 0x1002, 0x1003));
+        assert_eq!((regs.rsi, regs.rdi, regs.rbp, regs.rsp), (0x1004, 0x1005, 0x1006, 0x1007));
+        assert_eq!((regs.r8, regs.r15, regs.rip), (0x1008, 0x100f, 0x1010));
+        assert_eq!((regs.eflags, regs.cs, regs.ss), (0x200, 0x201, 0x202));
+        assert_eq!((regs.ds, regs.es, regs.fs, regs.gs), (0x203, 0x204, 0x205, 0x206));
+    }
+
+    #[test]
+    fn short_g_packet_is_an_error() {
+        let (remote, _) = stub(&format!("+{}", packet(&"00".repeat(17 * 8))));
+        assert_eq!(protocol_error(remote.general_registers()), "short register packet");
+    }
+}
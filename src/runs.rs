@@ -0,0 +1,162 @@
+use crate::debugger_command;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How a run of the program ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The process is still there
+    Running,
+    Exited(i32),
+    /// Terminated by the signal of this name, e.g. `SIGSEGV`
+    Signaled(String),
+    /// Killed by deet, by `kill`, a new `run` or `quit`
+    Killed,
+    /// Still running when the session was saved, the session it was restored from doesn't know
+    /// the end
+    Unfinished,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Running => write!(f, "running"),
+            Outcome::Exited(code) => write!(f, "exited {}", code),
+            Outcome::Signaled(signal) => write!(f, "signal {}", signal),
+            Outcome::Killed => write!(f, "killed"),
+            Outcome::Unfinished => write!(f, "unfinished"),
+        }
+    }
+}
+
+/// A launch of the program by `run`, as listed by `info runs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// The arguments it was started with, replayed by `run !N`
+    pub args: Vec<String>,
+    /// The process while it runs, to find the run when the process ends
+    pub pid: Option<i32>,
+    pub started: Option<Instant>,
+    /// How long it ran, known once it ended
+    pub duration: Option<Duration>,
+    pub outcome: Outcome,
+    /// The stops at breakpoints
+    pub stops: usize,
+}
+
+impl Run {
+    pub fn new(args: Vec<String>, pid: i32) -> Self {
+        Run { args, pid: Some(pid), started: Some(Instant::now()), duration: None, outcome: Outcome::Running, stops: 0 }
+    }
+
+    /// # brief
+    /// Record the end of the run.
+    pub fn end(&mut self, outcome: Outcome) {
+        self.duration = self.started.map(|started| started.elapsed());
+        self.outcome = outcome;
+        self.pid = None;
+    }
+
+    /// # brief
+    /// Render the run as the comment of a session file, e.g. `# run exited 1 in 2310 ms with 4
+    /// stops: -v input.txt`. A run still going is saved as unfinished.
+    pub fn to_comment(&self) -> String {
+        let outcome = if self.outcome == Outcome::Running { Outcome::Unfinished } else { self.outcome.clone() };
+        let duration = self.duration.or_else(|| self.started.map(|started| started.elapsed())).unwrap_or_default();
+        let args: Vec<String> = self.args.iter().map(|arg| debugger_command::quote(arg)).collect();
+        format!("# run {} in {} ms with {} stops: {}", outcome, duration.as_millis(), self.stops, args.join(" "))
+            .trim_end()
+            .to_string()
+    }
+
+    /// # brief
+    /// Parse a run saved by `to_comment`, given the comment without its `#`.
+    ///
+    /// # return
+    /// * The run, or `None` if the comment is not one.
+    pub fn from_comment(comment: &str) -> Option<Self> {
+        let rest = comment.trim().strip_prefix("run ")?;
+        let (summary, args) = rest.split_once(':')?;
+        let words: Vec<&str> = summary.split_whitespace().collect();
+        let (outcome, rest) = match words.as_slice() {
+            ["exited", code, rest @ ..] => (Outcome::Exited(code.parse().ok()?), rest),
+            ["signal", signal, rest @ ..] => (Outcome::Signaled(signal.to_string()), rest),
+            ["killed", rest @ ..] => (Outcome::Killed, rest),
+            ["unfinished", rest @ ..] => (Outcome::Unfinished, rest),
+            _ => return None,
+        };
+        let (millis, stops) = match rest {
+            ["in", millis, "ms", "with", stops, "stops"] => (millis.parse().ok()?, stops.parse().ok()?),
+            _ => return None,
+        };
+        Some(Run {
+            args: debugger_command::tokenize(args),
+            pid: None,
+            started: None,
+            duration: Some(Duration::from_millis(millis)),
+            outcome,
+            stops,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// A run that ended, as if it had lasted `millis`.
+    fn ended(args: &[&str], outcome: Outcome, millis: u64, stops: usize) -> Run {
+        let mut run = Run::new(args.iter().map(|arg| arg.to_string()).collect(), 42);
+        run.end(outcome);
+        run.duration = Some(Duration::from_millis(millis));
+        run.stops = stops;
+        run
+    }
+
+    #[test]
+    fn end_records_the_outcome_and_forgets_the_process() {
+        let mut run = Run::new(vec![], 42);
+        assert_eq!(run.outcome, Outcome::Running);
+        run.end(Outcome::Exited(3));
+        assert_eq!(run.outcome, Outcome::Exited(3));
+        assert_eq!(run.pid, None);
+        assert!(run.duration.is_some());
+    }
+
+    #[test]
+    fn comment_lists_outcome_duration_stops_and_arguments() {
+        let run = ended(&["-v", "input file.txt"], Outcome::Exited(1), 2310, 4);
+        assert_eq!(run.to_comment(), "# run exited 1 in 2310 ms with 4 stops: -v \"input file.txt\"");
+        assert_eq!(ended(&[], Outcome::Killed, 5, 0).to_comment(), "# run killed in 5 ms with 0 stops:");
+    }
+
+    #[test]
+    fn running_run_is_saved_as_unfinished() {
+        let run = Run::new(vec![], 42);
+        assert!(run.to_comment().starts_with("# run unfinished in "));
+    }
+
+    #[test]
+    fn comment_round_trips() {
+        let outcomes = [Outcome::Exited(0), Outcome::Signaled("SIGSEGV".to_string()), Outcome::Killed, Outcome::Unfinished];
+        for outcome in outcomes.iter() {
+            let run = ended(&["a b", "c"], outcome.clone(), 17, 2);
+            let comment = run.to_comment();
+            let parsed = Run::from_comment(comment.strip_prefix('#').unwrap()).unwrap();
+            assert_eq!(parsed.outcome, *outcome);
+            assert_eq!(parsed.args, run.args);
+            assert_eq!(parsed.duration, Some(Duration::from_millis(17)));
+            assert_eq!(parsed.stops, 2);
+            assert_eq!(parsed.pid, None);
+        }
+    }
+
+    #[test]
+    fn other_comments_are_not_runs() {
+        assert!(Run::from_comment(" saved by deet").is_none());
+        assert!(Run::from_comment(" run exploded in 3 ms with 0 stops:").is_none());
+        assert!(Run::from_comment(" run exited x in 3 ms with 0 stops:").is_none());
+        assert!(Run::from_comment(" run exited 0 in 3 ms").is_none());
+    }
+}
@@ -0,0 +1,452 @@
+//! The investigation saved by `save session` and brought back by `restore session` or
+//! `deet --session`, written as a small subset of TOML: `key = value` lines, `[table]` and
+//! `[[array of tables]]` headers, and strings, integers, booleans and arrays of strings as values.
+
+use crate::runs::Run;
+
+/// The first line of a file written by `save session`
+const HEADER: &str = "# deet session";
+
+/// A breakpoint of a saved session
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedBreakpoint {
+    /// The command that sets it again, e.g. `break main.c:12 if x > 3`
+    pub command: String,
+    /// Where it was when the session was saved, to tell the user it moved
+    pub addr: Option<usize>,
+}
+
+/// Everything a fresh deet needs to get back to an investigation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub target: String,
+    pub args: Vec<String>,
+    /// The variables `set environment` gives the program, and those `unset environment`
+    /// removes from it (`None`)
+    pub environment: Vec<(String, Option<String>)>,
+    /// The `set substitute-path` rules, from and to
+    pub substitute_paths: Vec<(String, String)>,
+    /// Each setting and the words that follow it in the `set` command that brings it back, e.g.
+    /// `("print pretty", ["on"])`
+    pub settings: Vec<(String, Vec<String>)>,
+    /// The arguments of each `skip`, e.g. `function drop`
+    pub skips: Vec<String>,
+    pub convenience: Vec<(String, u64)>,
+    /// The expressions of `display`
+    pub displays: Vec<String>,
+    pub breakpoints: Vec<SavedBreakpoint>,
+    pub runs: Vec<Run>,
+}
+
+/// A value on the right of `=`
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(u64),
+    Boolean(bool),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn string(self, key: &str) -> Result<String, String> {
+        match self {
+            Value::String(text) => Ok(text),
+            _ => Err(format!("{} must be a string", key)),
+        }
+    }
+
+    fn array(self, key: &str) -> Result<Vec<String>, String> {
+        match self {
+            Value::Array(words) => Ok(words),
+            _ => Err(format!("{} must be an array of strings", key)),
+        }
+    }
+}
+
+/// The table the following `key = value` lines belong to
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Table {
+    Top,
+    Environment,
+    Settings,
+    Convenience,
+    SubstitutePath,
+    Breakpoint,
+}
+
+impl Session {
+    /// # brief
+    /// Render the session as the text of a session file.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            HEADER.to_string(),
+            format!("target = {}", string(&self.target)),
+            format!("args = {}", array(&self.args)),
+            format!("skips = {}", array(&self.skips)),
+            format!("displays = {}", array(&self.displays)),
+        ];
+        let runs: Vec<String> = self.runs.iter().map(|run| run.to_comment().trim_start_matches('#').trim().to_string()).collect();
+        lines.push(format!("runs = {}", array(&runs)));
+        lines.push(String::new());
+        lines.push("[environment]".to_string());
+        for (name, value) in self.environment.iter() {
+            let value = value.as_ref().map_or("false".to_string(), |value| string(value));
+            lines.push(format!("{} = {}", key(name), value));
+        }
+        lines.push(String::new());
+        lines.push("[settings]".to_string());
+        for (name, words) in self.settings.iter() {
+            let value = match words.as_slice() {
+                [word] => string(word),
+                _ => array(words),
+            };
+            lines.push(format!("{} = {}", key(name), value));
+        }
+        lines.push(String::new());
+        lines.push("[convenience]".to_string());
+        for (name, value) in self.convenience.iter() {
+            lines.push(format!("{} = {:#x}", key(name), value));
+        }
+        for (from, to) in self.substitute_paths.iter() {
+            lines.push(String::new());
+            lines.push("[[substitute-path]]".to_string());
+            lines.push(format!("from = {}", string(from)));
+            lines.push(format!("to = {}", string(to)));
+        }
+        for bp in self.breakpoints.iter() {
+            lines.push(String::new());
+            lines.push("[[breakpoint]]".to_string());
+            lines.push(format!("command = {}", string(&bp.command)));
+            if let Some(addr) = bp.addr {
+                lines.push(format!("address = {:#x}", addr));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// # brief
+    /// Parse the text of a session file, see `to_text`.
+    ///
+    /// # return
+    /// * The session, or `Err(String)` naming the first line that is wrong and why.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut session = Session::default();
+        let mut target = None;
+        let mut table = Table::Top;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            session
+                .parse_line(line, &mut table, &mut target)
+                .map_err(|err| format!("line {}: {}", index + 1, err))?;
+        }
+        session.target = target.ok_or("no target")?;
+        Ok(session)
+    }
+
+    /// # brief
+    /// Read a session file.
+    ///
+    /// # return
+    /// * The session, or `Err(String)` if the file can't be read or is not a session.
+    pub fn read(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+        Session::parse(&text).map_err(|err| format!("Error in session {}: {}", path, err))
+    }
+
+    /// # brief
+    /// Parse a header or a `key = value` line of a session file.
+    ///
+    /// # param
+    /// - `table` : The table of the line, changed by a header.
+    /// - `target` : The target program once its line was seen.
+    fn parse_line(&mut self, line: &str, table: &mut Table, target: &mut Option<String>) -> Result<(), String> {
+        if let Some(name) = line.strip_prefix("[[").and_then(|line| line.strip_suffix("]]")) {
+            *table = match name.trim() {
+                "substitute-path" => {
+                    self.substitute_paths.push((String::new(), String::new()));
+                    Table::SubstitutePath
+                }
+                "breakpoint" => {
+                    self.breakpoints.push(SavedBreakpoint { command: String::new(), addr: None });
+                    Table::Breakpoint
+                }
+                other => return Err(format!("unknown array of tables [[{}]]", other)),
+            };
+            return Ok(());
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            *table = match name.trim() {
+                "environment" => Table::Environment,
+                "settings" => Table::Settings,
+                "convenience" => Table::Convenience,
+                other => return Err(format!("unknown table [{}]", other)),
+            };
+            return Ok(());
+        }
+        let mut chars = line.chars().peekable();
+        let name = parse_key(&mut chars)?;
+        skip_spaces(&mut chars);
+        if chars.next() != Some('=') {
+            return Err(format!("= expected after {}", name));
+        }
+        skip_spaces(&mut chars);
+        let value = parse_value(&mut chars)?;
+        skip_spaces(&mut chars);
+        if let Some(c) = chars.next() {
+            return Err(format!("unexpected '{}' after the value of {}", c, name));
+        }
+        match (*table, name.as_str()) {
+            (Table::Top, "target") => *target = Some(value.string(&name)?),
+            (Table::Top, "args") => self.args = value.array(&name)?,
+            (Table::Top, "skips") => self.skips = value.array(&name)?,
+            (Table::Top, "displays") => self.displays = value.array(&name)?,
+            (Table::Top, "runs") => {
+                for run in value.array(&name)? {
+                    self.runs.push(Run::from_comment(&run).ok_or(format!("\"{}\" is not a run", run))?);
+                }
+            }
+            (Table::Environment, _) => {
+                let value = match value {
+                    Value::String(text) => Some(text),
+                    Value::Boolean(false) => None,
+                    _ => return Err(format!("{} must be a string, or false for a variable that is unset", name)),
+                };
+                self.environment.push((name, value));
+            }
+            (Table::Settings, _) => {
+                let words = match value {
+                    Value::String(word) => vec![word],
+                    value => value.array(&name)?,
+                };
+                self.settings.push((name, words));
+            }
+            (Table::Convenience, _) => match value {
+                Value::Integer(value) => self.convenience.push((name, value)),
+                _ => return Err(format!("{} must be an integer", name)),
+            },
+            (Table::SubstitutePath, "from") => self.substitute_paths.last_mut().unwrap().0 = value.string(&name)?,
+            (Table::SubstitutePath, "to") => self.substitute_paths.last_mut().unwrap().1 = value.string(&name)?,
+            (Table::Breakpoint, "command") => self.breakpoints.last_mut().unwrap().command = value.string(&name)?,
+            (Table::Breakpoint, "address") => match value {
+                Value::Integer(addr) => self.breakpoints.last_mut().unwrap().addr = Some(addr as usize),
+                _ => return Err(format!("{} must be an integer", name)),
+            },
+            _ => return Err(format!("unknown key {}", name)),
+        }
+        Ok(())
+    }
+}
+
+/// # brief
+/// Write `text` as a quoted string.
+fn string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// # brief
+/// Write `words` as an array of strings.
+fn array(words: &[String]) -> String {
+    let words: Vec<String> = words.iter().map(|word| string(word)).collect();
+    format!("[{}]", words.join(", "))
+}
+
+/// # brief
+/// Write a key, quoted unless it is bare: letters, digits, `_` and `-`.
+fn key(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        name.to_string()
+    } else {
+        string(name)
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_spaces(chars: &mut Chars) {
+    while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+}
+
+/// # brief
+/// Parse a bare or quoted key.
+fn parse_key(chars: &mut Chars) -> Result<String, String> {
+    if chars.peek() == Some(&'"') {
+        return parse_string(chars);
+    }
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-') {
+        name.push(c);
+    }
+    if name.is_empty() {
+        return Err("key expected".to_string());
+    }
+    Ok(name)
+}
+
+/// # brief
+/// Parse a quoted string, its opening quote next.
+fn parse_string(chars: &mut Chars) -> Result<String, String> {
+    chars.next();
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('"') => text.push('"'),
+                Some('\\') => text.push('\\'),
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some(c) => return Err(format!("unknown escape \\{}", c)),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => text.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+/// # brief
+/// Parse a value: a string, a decimal or `0x` integer, `true`, `false`, or an array of strings.
+fn parse_value(chars: &mut Chars) -> Result<Value, String> {
+    match chars.peek() {
+        Some('"') => parse_string(chars).map(Value::String),
+        Some('[') => {
+            chars.next();
+            let mut words = Vec::new();
+            loop {
+                skip_spaces(chars);
+                match chars.peek() {
+                    Some(']') => {
+                        chars.next();
+                        return Ok(Value::Array(words));
+                    }
+                    Some('"') => words.push(parse_string(chars)?),
+                    _ => return Err("string or ] expected in an array".to_string()),
+                }
+                skip_spaces(chars);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Value::Array(words)),
+                    _ => return Err(", or ] expected in an array".to_string()),
+                }
+            }
+        }
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                word.push(c);
+            }
+            let integer = match word.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                None => word.parse().ok(),
+            };
+            match (word.as_str(), integer) {
+                ("true", _) => Ok(Value::Boolean(true)),
+                ("false", _) => Ok(Value::Boolean(false)),
+                (_, Some(value)) => Ok(Value::Integer(value)),
+                _ => Err(format!("value expected, not \"{}\"", word)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// A session with something in every field, and text that needs escaping.
+    fn full() -> Session {
+        let run = Run::from_comment("run exited 1 in 2310 ms with 4 stops: -v \"input file.txt\"").unwrap();
+        Session {
+            target: "/home/me/my prog".to_string(),
+            args: vec!["-v".to_string(), "say \"hi\"".to_string(), String::new()],
+            environment: vec![("LANG".to_string(), Some("C".to_string())), ("HOME".to_string(), None)],
+            substitute_paths: vec![("/build".to_string(), "/src\\tree".to_string())],
+            settings: vec![
+                ("print pretty".to_string(), vec!["off".to_string()]),
+                ("stop-hook".to_string(), vec!["bt; print $rax".to_string()]),
+                ("crash-report".to_string(), vec!["/tmp/crash.json".to_string(), "json".to_string()]),
+            ],
+            skips: vec!["function drop".to_string(), "file library/core/".to_string()],
+            convenience: vec![("count".to_string(), 0x10), ("max".to_string(), u64::MAX)],
+            displays: vec!["x + 1".to_string(), "$rip".to_string()],
+            breakpoints: vec![
+                SavedBreakpoint { command: "break main.c:12 if s == \"a\\tb\"".to_string(), addr: Some(0x401136) },
+                SavedBreakpoint { command: "break missing".to_string(), addr: None },
+            ],
+            runs: vec![run],
+        }
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let session = full();
+        assert_eq!(Session::parse(&session.to_text()), Ok(session));
+        let empty = Session { target: "prog".to_string(), ..Session::default() };
+        assert_eq!(Session::parse(&empty.to_text()), Ok(empty));
+    }
+
+    #[test]
+    fn text_is_tables_of_keys_and_values() {
+        let text = full().to_text();
+        assert!(text.starts_with("# deet session\ntarget = \"/home/me/my prog\"\n"));
+        assert!(text.contains("\n[environment]\nLANG = \"C\"\nHOME = false\n"));
+        assert!(text.contains("\n\"print pretty\" = \"off\"\n"));
+        assert!(text.contains("\ncrash-report = [\"/tmp/crash.json\", \"json\"]\n"));
+        assert!(text.contains("\n[convenience]\ncount = 0x10\n"));
+        assert!(text.contains("\n[[breakpoint]]\ncommand = \"break main.c:12 if s == \\\"a\\\\tb\\\"\"\naddress = 0x401136\n"));
+        assert!(text.contains("runs = [\"run exited 1 in 2310 ms with 4 stops: -v \\\"input file.txt\\\"\"]"));
+    }
+
+    #[test]
+    fn comments_blank_lines_and_spacing_are_ignored() {
+        let text = "# written by hand\n\n  target=\"prog\"  \nargs = [ \"a\" ,\"b\", ]\n[convenience]\nn = 12\n";
+        let session = Session::parse(text).unwrap();
+        assert_eq!(session.target, "prog");
+        assert_eq!(session.args, ["a", "b"]);
+        assert_eq!(session.convenience, [("n".to_string(), 12)]);
+        assert!(session.runs.is_empty());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("args = []", "no target"),
+            ("target = \"prog\"\ncolour = \"red\"", "line 2: unknown key colour"),
+            ("target = \"prog\"\n[colours]", "line 2: unknown table [colours]"),
+            ("target = \"prog\"\n[[watchpoint]]", "line 2: unknown array of tables [[watchpoint]]"),
+            ("target = prog", "line 1: value expected, not \"prog\""),
+            ("target = \"prog", "line 1: unterminated string"),
+            ("target = \"a\\qb\"", "line 1: unknown escape \\q"),
+            ("target \"prog\"", "line 1: = expected after target"),
+            ("target = \"a\" \"b\"", "line 1: unexpected '\"' after the value of target"),
+            ("target = 1", "line 1: target must be a string"),
+            ("args = \"a\"", "line 1: args must be an array of strings"),
+            ("args = [1]", "line 1: string or ] expected in an array"),
+            ("args = [\"a\" \"b\"]", "line 1: , or ] expected in an array"),
+            ("[environment]\nHOME = true", "line 2: HOME must be a string, or false for a variable that is unset"),
+            ("[convenience]\nn = \"1\"", "line 2: n must be an integer"),
+            ("runs = [\"ran\"]", "line 1: \"ran\" is not a run"),
+        ];
+        for (text, error) in cases.iter() {
+            assert_eq!(Session::parse(text), Err(error.to_string()), "{}", text);
+        }
+    }
+}
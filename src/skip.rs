@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// A function or source file `step --into` never enters, as set by `skip`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Skip {
+    /// A function, by name
+    Function(String),
+    /// A source file. A name ending with `/` skips every file below that directory, e.g.
+    /// `library/core/` for the Rust standard library.
+    File(String),
+}
+
+impl Skip {
+    /// # brief
+    /// Check whether code of `function` in `file` is skipped.
+    pub fn matches(&self, function: &str, file: &str) -> bool {
+        match self {
+            Skip::Function(name) => function == name,
+            Skip::File(name) if name.ends_with('/') => file.starts_with(name.as_str()) || file.contains(&format!("/{}", name)),
+            Skip::File(name) => file == name || file.ends_with(&format!("/{}", name)),
+        }
+    }
+}
+
+impl fmt::Display for Skip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Skip::Function(name) => write!(f, "function {}", name),
+            Skip::File(name) => write!(f, "file {}", name),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+/// A copy of a memory range of the inferior, taken by `snapshot <name> <addr> <len>` and
+/// checked against the current memory by `compare <name>`.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub addr: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// A run of consecutive changed bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Offset of the first changed byte from the start of the snapshot
+    pub offset: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// # brief
+/// Compare two copies of a memory range, merging consecutive changed bytes into one `Change`.
+///
+/// # return
+/// * The changes in address order.
+pub fn diff(old: &[u8], new: &[u8]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    for (offset, (a, b)) in old.iter().zip(new.iter()).enumerate() {
+        if a == b {
+            continue;
+        }
+        match changes.last_mut() {
+            Some(change) if change.offset + change.old.len() == offset => {
+                change.old.push(*a);
+                change.new.push(*b);
+            }
+            _ => changes.push(Change { offset, old: vec![*a], new: vec![*b] }),
+        }
+    }
+    changes
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+/// The `set substitute-path` rules of a debugging session.
+///
+/// Binaries are often built somewhere else (a container, a build farm), so the source paths
+/// recorded in the DWARF information don't exist on this machine. Every rule rewrites a leading
+/// directory of a path; the first rule that matches wins.
+#[derive(Debug, Default, Clone)]
+pub struct SubstitutePaths {
+    /// `(from, to)` pairs, in the order they were added
+    rules: Vec<(String, String)>,
+}
+
+/// # brief
+/// Replace the leading directory `from` of `path` with `to`. `from` only matches whole path
+/// components, so `/build` applies to `/build/main.c` but not to `/buildroot/main.c`.
+///
+/// # return
+/// * `Some(String)` - the rewritten path, or `None` if `from` is not a prefix of `path`.
+fn replace_prefix(path: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    let rest = path.strip_prefix(from)?;
+    if !rest.is_empty() && !rest.starts_with('/') && !from.is_empty() {
+        return None;
+    }
+    Some(format!("{}{}", to.trim_end_matches('/'), rest))
+}
+
+impl SubstitutePaths {
+    pub fn new() -> Self {
+        SubstitutePaths { rules: Vec::new() }
+    }
+
+    /// # brief
+    /// Add a rule rewriting `from` into `to`. A rule that already exists for `from` is replaced
+    /// in place, so it keeps its priority.
+    pub fn add(&mut self, from: &str, to: &str) {
+        if let Some(rule) = self.rules.iter_mut().find(|(f, _)| f == from) {
+            rule.1 = to.to_string();
+        } else {
+            self.rules.push((from.to_string(), to.to_string()));
+        }
+    }
+
+    pub fn rules(&self) -> &Vec<(String, String)> {
+        &self.rules
+    }
+
+    /// # brief
+    /// Map a path recorded in the debug information to the path on this machine.
+    pub fn to_local(&self, recorded: &str) -> String {
+        self.rules
+            .iter()
+            .find_map(|(from, to)| replace_prefix(recorded, from, to))
+            .unwrap_or_else(|| recorded.to_string())
+    }
+
+    /// # brief
+    /// Map a path typed by the user (e.g. in `break file:line`) back to the path recorded in the
+    /// debug information. This is the reverse direction of `to_local`.
+    pub fn to_recorded(&self, local: &str) -> String {
+        self.rules
+            .iter()
+            .find_map(|(from, to)| replace_prefix(local, to, from))
+            .unwrap_or_else(|| local.to_string())
+    }
+
+    /// # brief
+    /// Find a recorded source file on disk, after applying the substitution rules. Relative
+    /// paths are resolved against the compilation directory.
+    ///
+    /// # param
+    /// - `recorded` : The source path recorded in the debug information.
+    /// - `comp_dir` : The compilation directory of the unit containing the file, if known.
+    ///
+    /// # return
+    /// * `Some(PathBuf)` - the local path of the file, or `None` if it can't be found.
+    pub fn locate(&self, recorded: &str, comp_dir: Option<&str>) -> Option<PathBuf> {
+        let mut candidates = vec![PathBuf::from(self.to_local(recorded))];
+        if let (false, Some(dir)) = (recorded.starts_with('/'), comp_dir) {
+            candidates.push(PathBuf::from(self.to_local(&format!("{}/{}", dir, recorded))));
+        }
+        candidates.into_iter().find(|path| Path::new(path).is_file())
+    }
+}
+
+/// # brief
+/// Format a line of source as `list` shows it, after a gutter with `B` on a line with a
+/// breakpoint and `>` on the current line.
+pub fn gutter_line(number: usize, text: &str, breakpoint: bool, current: bool) -> String {
+    let marker = if breakpoint { 'B' } else { ' ' };
+    let arrow = if current { '>' } else { ' ' };
+    format!("{}{}{:<6}{}", marker, arrow, number, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gutter_marks_breakpoints_and_the_current_line() {
+        let source = ["int main() {", "    int x = 1;", "    return x;", "}"];
+        let breakpoints = [2, 3];
+        let listing: Vec<String> = (1..=4)
+            .map(|number| gutter_line(number, source[number - 1], breakpoints.contains(&number), number == 3))
+            .collect();
+        assert_eq!(
+            listing,
+            ["  1     int main() {", "B 2         int x = 1;", "B>3         return x;", "  4     }"]
+        );
+    }
+
+    #[test]
+    fn gutter_keeps_long_line_numbers_aligned() {
+        assert_eq!(gutter_line(120, "x++;", false, true), " >120   x++;");
+        assert_eq!(gutter_line(123456, "x++;", false, false), "  123456x++;");
+    }
+}
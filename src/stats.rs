@@ -0,0 +1,81 @@
+use crate::inferior::TraceOp;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// What deet did to the inferiors since the session started or `stats reset`, to tell where the
+/// time goes on a slow target. The requests are counted where every request is made, in the
+/// wrapper of `inferior`, and the commands by the debugger.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// When counting started
+    pub since: Instant,
+    pub runs: u64,
+    pub continues: u64,
+    /// `step`, `next`, `stepi` and `finish` commands
+    pub steps: u64,
+    /// The traps reached, by breakpoint number, whether or not the stop was reported
+    pub breakpoint_hits: BTreeMap<usize, u64>,
+    /// The instructions executed with PTRACE_SINGLESTEP
+    pub single_steps: u64,
+    pub requests: HashMap<TraceOp, u64>,
+    /// The bytes of the words read with PTRACE_PEEKDATA
+    pub bytes_read: u64,
+    /// The bytes of the words written with PTRACE_POKEDATA
+    pub bytes_written: u64,
+    /// The time spent in `waitpid`, mostly waiting for the inferior to stop
+    pub wait_time: Duration,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            since: Instant::now(),
+            runs: 0,
+            continues: 0,
+            steps: 0,
+            breakpoint_hits: BTreeMap::new(),
+            single_steps: 0,
+            requests: HashMap::new(),
+            bytes_read: 0,
+            bytes_written: 0,
+            wait_time: Duration::default(),
+        }
+    }
+}
+
+/// The counters of the session, started when the debugger starts
+static STATS: Mutex<Option<Stats>> = Mutex::new(None);
+
+/// # brief
+/// Update the counters of the session with `update`.
+pub fn record(update: impl FnOnce(&mut Stats)) {
+    update(STATS.lock().unwrap().get_or_insert_with(Stats::default));
+}
+
+/// # brief
+/// Count a request on the inferior that took `elapsed`.
+pub fn record_request(op: TraceOp, elapsed: Duration) {
+    record(|stats| {
+        *stats.requests.entry(op).or_insert(0) += 1;
+        match op {
+            TraceOp::Step => stats.single_steps += 1,
+            TraceOp::PeekData => stats.bytes_read += std::mem::size_of::<usize>() as u64,
+            TraceOp::PokeData => stats.bytes_written += std::mem::size_of::<usize>() as u64,
+            TraceOp::Wait => stats.wait_time += elapsed,
+            _ => {}
+        }
+    });
+}
+
+/// # brief
+/// Get a copy of the counters of the session.
+pub fn current() -> Stats {
+    STATS.lock().unwrap().get_or_insert_with(Stats::default).clone()
+}
+
+/// # brief
+/// Start counting again from zero, for `stats reset`.
+pub fn reset() {
+    *STATS.lock().unwrap() = Some(Stats::default());
+}
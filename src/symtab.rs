@@ -0,0 +1,218 @@
+use object::{Object, ObjectSection, SymbolKind};
+use std::ops::Range;
+
+/// A symbol of the ELF symbol table, or a global variable of the debug information
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub address: usize,
+    /// The size in bytes, 0 when unknown
+    pub size: usize,
+    /// Whether the symbol is code rather than data
+    pub is_function: bool,
+}
+
+/// The sections holding the stubs through which calls to shared libraries go
+const PLT_SECTIONS: [&str; 3] = [".plt", ".plt.sec", ".plt.got"];
+
+/// The size of a PLT stub on x86_64
+const PLT_ENTRY_SIZE: usize = 16;
+
+/// Symbols sorted by address, to find the one an address points into
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    /// The sections loaded in memory, by name, with the file offset of their contents (`None`
+    /// for `.bss`)
+    sections: Vec<(String, Range<usize>, Option<u64>)>,
+    /// The ranges of the PLT sections, looked up at every step
+    plt: Vec<Range<usize>>,
+}
+
+/// # brief
+/// Keep the code and data symbols defined by an executable.
+fn defined_symbols<'data>(symbols: impl Iterator<Item = (object::SymbolIndex, object::Symbol<'data>)>) -> Vec<Symbol> {
+    symbols
+        .filter(|(_, symbol)| !symbol.is_undefined() && symbol.address() != 0)
+        .filter_map(|(_, symbol)| {
+            let is_function = match symbol.kind() {
+                SymbolKind::Text => true,
+                SymbolKind::Data => false,
+                _ => return None,
+            };
+            Some(Symbol {
+                name: symbol.name().filter(|name| !name.is_empty())?.to_string(),
+                address: symbol.address() as usize,
+                size: symbol.size() as usize,
+                is_function,
+            })
+        })
+        .collect()
+}
+
+impl SymbolTable {
+    /// # brief
+    /// Collect the code and data symbols of an executable, from `.symtab` or, when it was
+    /// stripped, from `.dynsym`, and the ranges of its sections.
+    pub fn from_object(object: &object::File) -> Self {
+        let mut symbols = defined_symbols(object.symbols());
+        if symbols.is_empty() {
+            symbols = defined_symbols(object.dynamic_symbols());
+        }
+        let sections: Vec<(String, Range<usize>, Option<u64>)> = object
+            .sections()
+            .filter(|section| section.address() != 0 && section.size() != 0)
+            .filter_map(|section| {
+                let start = section.address() as usize;
+                let offset = section.file_range().map(|(offset, _)| offset);
+                Some((section.name()?.to_string(), start..start + section.size() as usize, offset))
+            })
+            .collect();
+        let mut table = SymbolTable::from_symbols(symbols);
+        table.plt = sections
+            .iter()
+            .filter(|(name, _, _)| PLT_SECTIONS.contains(&name.as_str()))
+            .map(|(_, range, _)| range.clone())
+            .collect();
+        table.sections = sections;
+        table
+    }
+
+    /// # brief
+    /// Read the symbols of an ELF file, e.g. of a shared library the program loaded.
+    ///
+    /// # return
+    /// * The table, or `None` if the file can't be read or is not an ELF file.
+    pub fn from_file(path: &str) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+        let object = object::File::parse(&data).ok()?;
+        Some(SymbolTable::from_object(&object))
+    }
+
+    /// # brief
+    /// Build a table of `symbols`, e.g. the global variables of the debug information.
+    pub fn from_symbols(mut symbols: Vec<Symbol>) -> Self {
+        symbols.sort_by_key(|symbol| symbol.address);
+        // aliases of one address, e.g. a local and a global name, are shown once
+        symbols.dedup_by(|later, earlier| later.address == earlier.address && later.size == earlier.size);
+        SymbolTable { symbols, sections: Vec::new(), plt: Vec::new() }
+    }
+
+    /// # brief
+    /// Find the symbol `addr` points into: the nearest symbol starting at or before it, as
+    /// long as `addr` is within its size. A symbol of unknown size covers the addresses up to
+    /// the end of its section.
+    ///
+    /// # return
+    /// * The symbol and the offset of `addr` into it.
+    pub fn lookup(&self, addr: usize) -> Option<(&Symbol, usize)> {
+        let end = self.symbols.partition_point(|symbol| symbol.address <= addr);
+        let nearest = self.symbols[..end].last()?.address;
+        let candidates = self.symbols[..end].iter().rev().take_while(|symbol| symbol.address == nearest);
+        let mut sizeless = None;
+        for symbol in candidates {
+            if symbol.size == 0 {
+                sizeless = Some(symbol);
+            } else if addr < symbol.address + symbol.size {
+                return Some((symbol, addr - symbol.address));
+            }
+        }
+        let symbol = sizeless?;
+        match (self.section_of(symbol.address), self.section_of(addr)) {
+            (Some(a), Some(b)) if a != b => None,
+            _ => Some((symbol, addr - symbol.address)),
+        }
+    }
+
+    /// # brief
+    /// Get the name of the section containing `addr`.
+    pub fn section_of(&self, addr: usize) -> Option<&str> {
+        self.sections
+            .iter()
+            .find(|(_, range, _)| range.contains(&addr))
+            .map(|(name, _, _)| name.as_str())
+    }
+
+    /// # brief
+    /// Check whether `addr` is in a PLT stub, the code a call to a shared library goes through.
+    ///
+    /// # return
+    /// * `Some(bool)` - whether `addr` is the first instruction of its stub, where the return
+    /// address of the call is on top of the stack, or `None` outside of the PLT.
+    pub fn plt_stub(&self, addr: usize) -> Option<bool> {
+        let range = self.plt.iter().find(|range| range.contains(&addr))?;
+        Some((addr - range.start) % PLT_ENTRY_SIZE == 0)
+    }
+
+    /// # brief
+    /// Check whether the table has no symbol, e.g. for a stripped library.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// # brief
+    /// Find the address of the function symbol `name`.
+    pub fn function_address(&self, name: &str) -> Option<usize> {
+        self.symbols.iter().find(|symbol| symbol.is_function && symbol.name == name).map(|symbol| symbol.address)
+    }
+
+    /// # brief
+    /// Find where the initial contents of the `len` bytes at `addr` are in the file.
+    ///
+    /// # return
+    /// * `Some(Some(offset))` - their offset in the file, `Some(None)` if they are in a section
+    /// without contents such as `.bss`, or `None` if they don't lie within one section.
+    pub fn file_offset(&self, addr: usize, len: usize) -> Option<Option<u64>> {
+        let (_, range, offset) = self
+            .sections
+            .iter()
+            .find(|(_, range, _)| range.contains(&addr) && addr + len <= range.end)?;
+        Some(offset.map(|offset| offset + (addr - range.start) as u64))
+    }
+}
+
+#[cfg(test)]
+impl SymbolTable {
+    /// # brief
+    /// Build a table of the sections of an executable, by name, with the file offset of their
+    /// contents.
+    pub fn from_sections(sections: Vec<(&str, Range<usize>, Option<u64>)>) -> Self {
+        let sections = sections.into_iter().map(|(name, range, offset)| (name.to_string(), range, offset)).collect();
+        SymbolTable { symbols: Vec::new(), sections, plt: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// # brief
+    /// The data sections of a small executable.
+    fn sections() -> SymbolTable {
+        SymbolTable::from_sections(vec![(".data", 0x4000..0x4010, Some(0x3000)), (".bss", 0x4010..0x4100, None)])
+    }
+
+    #[test]
+    fn file_offset_of_initialized_data() {
+        let table = sections();
+        assert_eq!(table.file_offset(0x4000, 8), Some(Some(0x3000)));
+        assert_eq!(table.file_offset(0x4004, 4), Some(Some(0x3004)));
+        assert_eq!(table.file_offset(0x400c, 4), Some(Some(0x300c)));
+    }
+
+    #[test]
+    fn file_offset_of_bss_is_none() {
+        let table = sections();
+        assert_eq!(table.file_offset(0x4010, 4), Some(None));
+        assert_eq!(table.file_offset(0x40fc, 4), Some(None));
+    }
+
+    #[test]
+    fn file_offset_outside_of_one_section() {
+        let table = sections();
+        // across the end of .data
+        assert_eq!(table.file_offset(0x400c, 8), None);
+        assert_eq!(table.file_offset(0x3ff8, 4), None);
+        assert_eq!(table.file_offset(0x4100, 4), None);
+    }
+}
@@ -0,0 +1,12 @@
+/// What inspecting a stopped program needs, whether it is a process traced with ptrace or one
+/// run by a remote stub such as gdbserver. The commands that only read the program (`print`,
+/// `x`, registers in expressions) go through it.
+pub trait TargetAccess {
+    /// # brief
+    /// Read `len` bytes of memory at `addr`.
+    fn read_bytes(&self, addr: usize, len: usize) -> Result<Vec<u8>, nix::Error>;
+
+    /// # brief
+    /// Get the general purpose registers.
+    fn registers(&self) -> Result<libc::user_regs_struct, nix::Error>;
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// The number of terminal rows the source pane takes, including its separator line
+const PANE_ROWS: usize = 16;
+
+/// # brief
+/// Get the size of the terminal on stdout.
+///
+/// # return
+/// * `Some((rows, columns))`, or `None` if stdout is not a terminal.
+pub fn terminal_size() -> Option<(usize, usize)> {
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return None;
+    }
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == -1 || size.ws_row == 0 {
+        return None;
+    }
+    Some((size.ws_row as usize, size.ws_col as usize))
+}
+
+/// A source pane at the top of the terminal, above the scrolling command line.
+///
+/// The pane is drawn with plain ANSI escapes: the rows below it are set as the scrolling
+/// region, so the prompt and everything printed (including the inferior's own output) scroll
+/// there and leave the pane alone. The terminal size is read again at every redraw, which
+/// takes care of resizes.
+#[derive(Debug, Default)]
+pub struct SourcePane {
+    enabled: bool,
+}
+
+impl SourcePane {
+    pub fn new() -> Self {
+        SourcePane { enabled: false }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// # brief
+    /// Switch the terminal to the pane layout.
+    ///
+    /// # return
+    /// * `Err(String)` if the terminal can't hold the pane, e.g. when stdout is not a terminal.
+    pub fn enable(&mut self) -> Result<(), String> {
+        let (rows, _) = terminal_size().ok_or("Cannot enable the TUI when output is not a terminal")?;
+        if rows < PANE_ROWS + 4 {
+            return Err(format!("The terminal is too small for the TUI ({} rows)", rows));
+        }
+        self.enabled = true;
+        // clear the screen and leave the cursor in the command area
+        print!("\x1b[2J\x1b[{};{}r\x1b[{};1H", PANE_ROWS + 1, rows, rows);
+        io::stdout().flush().ok();
+        Ok(())
+    }
+
+    /// # brief
+    /// Go back to the plain line mode.
+    pub fn disable(&mut self) {
+        if self.enabled {
+            self.enabled = false;
+            // reset the scrolling region and clear the screen
+            print!("\x1b[r\x1b[2J\x1b[H");
+            io::stdout().flush().ok();
+        }
+    }
+
+    /// # brief
+    /// Draw source lines with the stop line highlighted and breakpoint markers in the gutter.
+    ///
+    /// # param
+    /// - `title` : The name of the file shown.
+    /// - `source` : The lines of the file, or `None` if it is not available.
+    /// - `current` : The line the inferior is stopped at (1-based).
+    /// - `breakpoints` : The lines of the file holding a breakpoint.
+    pub fn draw(&self, title: &str, source: Option<&[String]>, current: usize, breakpoints: &HashSet<usize>) {
+        if !self.enabled {
+            return;
+        }
+        let (rows, columns) = match terminal_size() {
+            Some(size) => size,
+            None => return,
+        };
+        let height = PANE_ROWS - 1;
+        let mut out = String::new();
+        // save the cursor, keep the scrolling region in sync with the size, go to the pane
+        out.push_str(&format!("\x1b7\x1b[{};{}r\x1b[H", PANE_ROWS + 1, rows));
+        let first = current.saturating_sub(height / 2).max(1);
+        for number in first..first + height {
+            out.push_str("\x1b[2K");
+            let text = match source {
+                Some(lines) => lines.get(number - 1).map(|line| line.as_str()),
+                None if number == first => Some("[ No source available ]"),
+                None => None,
+            };
+            if let Some(text) = text {
+                let marker = if breakpoints.contains(&number) { 'b' } else { ' ' };
+                let mut row = format!("{}{:>5} {}", marker, number, text.replace('\t', "    "));
+                if row.chars().count() > columns {
+                    row = row.chars().take(columns).collect();
+                }
+                if number == current && source.is_some() {
+                    out.push_str(&format!("\x1b[7m{}\x1b[0m", row));
+                } else {
+                    out.push_str(&row);
+                }
+            }
+            out.push_str("\r\n");
+        }
+        let mut separator = format!("── {} ", title);
+        let width = separator.chars().count();
+        separator.push_str(&"─".repeat(columns.saturating_sub(width)));
+        out.push_str(&format!("\x1b[2K\x1b[1m{}\x1b[0m", separator.chars().take(columns).collect::<String>()));
+        // back to the command area
+        out.push_str("\x1b8");
+        print!("{}", out);
+        io::stdout().flush().ok();
+    }
+}
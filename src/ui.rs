@@ -0,0 +1,382 @@
+use crate::cancel::CancelToken;
+use crate::tui;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The width tables are fitted to when it can't be found from the terminal or `COLUMNS`
+const DEFAULT_WIDTH: usize = 80;
+
+/// The spaces between two columns
+const COLUMN_GAP: usize = 2;
+
+/// The narrowest a column is truncated to
+const MIN_COLUMN_WIDTH: usize = 6;
+
+/// Whether long output stops after each screenful, `set pagination`
+static PAGINATION: AtomicBool = AtomicBool::new(true);
+
+/// Whether someone is there to answer the pager: off in batch mode and when the input is not a
+/// terminal
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether output is the same from one run to the next, `--deterministic`: no times, and the
+/// addresses in the executable relative to its load base
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+
+/// Whether the startup messages are left out, `--quiet`
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// # brief
+/// Turn the startup messages and the progress of loading off or on.
+pub fn set_quiet(on: bool) {
+    QUIET.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Whether the startup messages and the progress of loading are left out.
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// # brief
+/// Print a notification: what deet says on its own rather than in response to a command, such
+/// as warnings and the progress of loading. Notifications go to stderr, so that only the
+/// responses to commands are on stdout, for scripts reading it.
+pub fn notify(text: &str) {
+    eprintln!("{}", text);
+}
+
+/// # brief
+/// Print a startup message, a notification that `--quiet` leaves out.
+pub fn announce(text: &str) {
+    if !quiet() {
+        notify(text);
+    }
+}
+
+/// # brief
+/// Turn the deterministic output of `--deterministic` on or off.
+pub fn set_deterministic(on: bool) {
+    DETERMINISTIC.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Whether times are left out of the output and addresses made relative, see `AddressFormat`.
+pub fn deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::Relaxed)
+}
+
+/// How the addresses in messages are shown: absolute, or, for golden tests comparing whole
+/// sessions, the addresses in the executable relative to where it was loaded, e.g.
+/// `prog+0x1a32`.
+#[derive(Debug, Clone, Default)]
+pub struct AddressFormat {
+    /// The name of the executable, its load base and the end of its last mapping
+    executable: Option<(String, usize, usize)>,
+}
+
+impl AddressFormat {
+    /// # brief
+    /// Show addresses from `base` to `end` as offsets into the executable `name`.
+    pub fn relative(name: &str, base: usize, end: usize) -> Self {
+        AddressFormat { executable: Some((name.to_string(), base, end)) }
+    }
+
+    /// # brief
+    /// Render an address, e.g. `0x401a32`, or `prog+0x1a32` when relative.
+    pub fn address(&self, addr: usize) -> String {
+        match &self.executable {
+            Some((name, base, end)) if *base <= addr && addr < *end => format!("{}+{:#x}", name, addr - base),
+            _ => format!("{:#x}", addr),
+        }
+    }
+}
+
+/// # brief
+/// Turn the pager on or off.
+pub fn set_pagination(on: bool) {
+    PAGINATION.store(on, Ordering::Relaxed);
+}
+
+/// # brief
+/// Whether the pager is on; it only pauses in an interactive session.
+pub fn pagination() -> bool {
+    PAGINATION.load(Ordering::Relaxed)
+}
+
+/// # brief
+/// Tell the pager whether it may ask questions.
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+/// # brief
+/// Get the width to fit output to: the width of the terminal on stdout, else the `COLUMNS`
+/// variable, else `DEFAULT_WIDTH`.
+pub fn output_width() -> usize {
+    if let Some((_, columns)) = tui::terminal_size().filter(|(_, columns)| *columns > 0) {
+        return columns;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|columns| *columns > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// # brief
+/// Cut `text` to `width` characters, ending it with `…` when it was longer.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// The rows of a listing with a header, printed with each column as wide as its content.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Table { headers: headers.iter().map(|header| header.to_string()).collect(), rows: Vec::new() }
+    }
+
+    /// # brief
+    /// Add a row. Missing cells at the end are empty.
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// # brief
+    /// Compute the width of each column from its content, narrowed to fit `width` in all: the
+    /// widest column is cut first, down to `MIN_COLUMN_WIDTH`.
+    fn column_widths(&self, width: usize) -> Vec<usize> {
+        let mut widths: Vec<usize> = self.headers.iter().map(|header| header.chars().count()).collect();
+        for row in self.rows.iter() {
+            for (index, cell) in row.iter().enumerate().take(widths.len()) {
+                widths[index] = widths[index].max(cell.chars().count());
+            }
+        }
+        let gaps = COLUMN_GAP * widths.len().saturating_sub(1);
+        while widths.iter().sum::<usize>() + gaps > width {
+            let (widest, &current) = widths.iter().enumerate().max_by_key(|(_, width)| **width).unwrap();
+            if current <= MIN_COLUMN_WIDTH {
+                break;
+            }
+            let excess = widths.iter().sum::<usize>() + gaps - width;
+            widths[widest] = current.saturating_sub(excess).max(MIN_COLUMN_WIDTH);
+        }
+        widths
+    }
+
+    /// # brief
+    /// Render the header and the rows, fitted to `width`. The cells that don't fit their column
+    /// are cut with `…`, and the last column is not padded.
+    pub fn render(&self, width: usize) -> String {
+        let widths = self.column_widths(width);
+        let line = |cells: &[String]| {
+            let mut out = String::new();
+            for (index, column_width) in widths.iter().enumerate() {
+                let cell = truncate(cells.get(index).map_or("", |cell| cell.as_str()), *column_width);
+                if index + 1 == widths.len() {
+                    out.push_str(&cell);
+                } else {
+                    out.push_str(&format!("{:<width$}", cell, width = column_width + COLUMN_GAP));
+                }
+            }
+            out.trim_end().to_string()
+        };
+        let mut out = line(&self.headers);
+        out.push('\n');
+        for row in self.rows.iter() {
+            out.push_str(&line(row));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// # brief
+    /// Print the table fitted to the output width, a screenful at a time.
+    pub fn print(&self) {
+        page(self.render(output_width()).lines());
+    }
+}
+
+/// # brief
+/// Print the lines of one command, a screenful at a time, see `Pager`.
+pub fn page<S: AsRef<str>>(lines: impl IntoIterator<Item = S>) {
+    let mut pager = Pager::new();
+    for line in lines {
+        if !pager.line(line.as_ref()) {
+            break;
+        }
+    }
+}
+
+/// The output of one command, printed line by line: in an interactive session, after each
+/// screenful it asks whether to go on, so a long listing can't flood the terminal.
+pub struct Pager<'a> {
+    /// The lines that fit between two questions, `None` when it never asks
+    page: Option<usize>,
+    printed: usize,
+    declined: bool,
+    /// The token of a command that can be cancelled, which stops the output and is cancelled
+    /// when the output is declined
+    cancel: Option<&'a CancelToken>,
+}
+
+impl<'a> Pager<'a> {
+    pub fn new() -> Self {
+        let asks = pagination() && INTERACTIVE.load(Ordering::Relaxed);
+        let page = tui::terminal_size().filter(|_| asks).map(|(rows, _)| rows.saturating_sub(1).max(1));
+        Pager { page, printed: 0, declined: false, cancel: None }
+    }
+
+    /// # brief
+    /// Create the pager of a command that `cancel` stops: Ctrl+C stops the output, and
+    /// answering "n" cancels the rest of the command.
+    pub fn with_cancel(cancel: &'a CancelToken) -> Self {
+        Pager { cancel: Some(cancel), ..Pager::new() }
+    }
+
+    /// # brief
+    /// Print a line, first asking whether to continue when a screenful was printed since the
+    /// last question.
+    ///
+    /// # return
+    /// * `false` if the rest of the output was declined, so the caller stops producing it.
+    pub fn line(&mut self, text: &str) -> bool {
+        if self.declined || self.cancel.map_or(false, |cancel| cancel.is_cancelled()) {
+            self.declined = true;
+            return false;
+        }
+        if self.page.map_or(false, |page| self.printed == page) {
+            self.printed = 0;
+            if !continue_printing() {
+                self.declined = true;
+                if let Some(cancel) = self.cancel {
+                    cancel.cancel();
+                }
+                return false;
+            }
+        }
+        println!("{}", text);
+        self.printed += 1;
+        true
+    }
+}
+
+/// # brief
+/// Ask whether to print the next screenful. An end of input stops the output.
+fn continue_printing() -> bool {
+    loop {
+        print!("Continue printing? (y/n) ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => {
+                println!();
+                return false;
+            }
+            Ok(_) => match answer.trim() {
+                "" | "y" | "yes" => return true,
+                "n" | "no" | "q" => return false,
+                _ => println!("Please answer y or n."),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: &[&[&str]]) -> Table {
+        let mut table = Table::new(&["Num", "What", "Where"]);
+        for row in rows {
+            table.row(row.iter().map(|cell| cell.to_string()).collect());
+        }
+        table
+    }
+
+    #[test]
+    fn columns_are_as_wide_as_their_content() {
+        let table = table(&[&["1", "breakpoint", "main.c:12"], &["10", "dprintf", "a_longer_file.c:1"]]);
+        assert_eq!(
+            table.render(80),
+            "Num  What        Where\n\
+             1    breakpoint  main.c:12\n\
+             10   dprintf     a_longer_file.c:1\n"
+        );
+    }
+
+    #[test]
+    fn empty_table_is_its_header() {
+        assert_eq!(table(&[]).render(80), "Num  What  Where\n");
+    }
+
+    #[test]
+    fn missing_cells_are_empty() {
+        assert_eq!(table(&[&["1"]]).render(80), "Num  What  Where\n1\n");
+    }
+
+    #[test]
+    fn wide_content_is_cut_to_the_width() {
+        let long = "x".repeat(100);
+        let rendered = table(&[&["1", "breakpoint", &long]]).render(40);
+        for line in rendered.lines() {
+            assert!(line.chars().count() <= 40, "{:?} is wider than 40", line);
+        }
+        assert!(rendered.lines().nth(1).unwrap().ends_with('…'));
+        // the narrow columns are left whole
+        assert!(rendered.lines().nth(1).unwrap().starts_with("1    breakpoint  "));
+    }
+
+    #[test]
+    fn narrow_terminal_keeps_the_minimum_width() {
+        // the columns can't all fit 10 characters, none is cut below the minimum
+        let table = table(&[&["1", "a breakpoint with a long description", "some_file.c:120"]]);
+        assert_eq!(table.column_widths(10), vec![3, MIN_COLUMN_WIDTH, MIN_COLUMN_WIDTH]);
+        assert_eq!(table.render(10).lines().nth(1).unwrap(), "1    a bre…  some_…");
+    }
+
+    #[test]
+    fn pager_prints_nothing_once_cancelled() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut pager = Pager::with_cancel(&cancel);
+        assert!(!pager.line("first"));
+        assert!(!pager.line("second"));
+        assert_eq!(pager.printed, 0);
+    }
+
+    #[test]
+    fn pager_stops_at_the_cancel() {
+        let cancel = CancelToken::new();
+        let mut pager = Pager::with_cancel(&cancel);
+        assert!(pager.line("first"));
+        cancel.cancel();
+        assert!(!pager.line("second"));
+        assert_eq!(pager.printed, 1);
+    }
+
+    #[test]
+    fn relative_addresses_are_offsets_into_the_executable() {
+        let format = AddressFormat::relative("prog", 0x5555_5555_4000, 0x5555_5555_9000);
+        assert_eq!(format.address(0x5555_5555_4000), "prog+0x0");
+        assert_eq!(format.address(0x5555_5555_5a32), "prog+0x1a32");
+        // outside of the executable, e.g. in a library or on the stack
+        assert_eq!(format.address(0x5555_5555_9000), "0x555555559000");
+        assert_eq!(format.address(0x7fff_0000_1000), "0x7fff00001000");
+    }
+
+    #[test]
+    fn absolute_addresses_by_default() {
+        assert_eq!(AddressFormat::default().address(0x401a32), "0x401a32");
+    }
+}
@@ -0,0 +1,82 @@
+use crate::ui;
+use nix::sys::signal::{signal, SigHandler, Signal};
+use nix::unistd::Pid;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often `/proc` is scanned by default. A process spawned by a service may only live for
+/// milliseconds, so the poll is tight.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Set by the SIGINT handler installed while waiting
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// # brief
+/// Check whether process `pid` runs the executable `name`, by its `comm` (which the kernel
+/// cuts to 15 bytes) or the file name of its executable.
+fn matches(pid: i32, name: &str) -> bool {
+    if let Ok(comm) = fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        let short: String = name.chars().take(15).collect();
+        if comm.trim_end_matches('\n') == short {
+            return true;
+        }
+    }
+    // the executable of another user's process can't be read, comm is all there is then
+    fs::read_link(format!("/proc/{}/exe", pid))
+        .map(|exe| exe.file_name().map_or(false, |file| file == name))
+        .unwrap_or(false)
+}
+
+/// # brief
+/// Get the pids of all processes running `name`.
+fn matching_processes(name: &str) -> HashSet<i32> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return HashSet::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| matches(*pid, name))
+        .collect()
+}
+
+/// # brief
+/// Wait for a new process running `name` to appear. Processes already running when the wait
+/// starts are ignored. Ctrl+C cancels the wait.
+///
+/// # param
+/// - `name` : The executable name, e.g. `worker`.
+/// - `interval` : The time between two scans of `/proc`.
+/// - `timeout` : Give up after this long, or wait forever when `None`.
+///
+/// # return
+/// * `Ok(Pid)` - the new process, or `Err(String)` if the wait timed out or was cancelled.
+pub fn wait_for_process(name: &str, interval: Duration, timeout: Option<Duration>) -> Result<Pid, String> {
+    let existing = matching_processes(name);
+    let start = Instant::now();
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    // deet ignores SIGINT so that it reaches the inferior, catch it for the time of the wait
+    let previous = unsafe { signal(Signal::SIGINT, SigHandler::Handler(interrupt)) }
+        .unwrap_or(SigHandler::SigIgn);
+    ui::announce(&format!("Waiting for a process named {}... (Ctrl+C to cancel)", name));
+    let result = loop {
+        if let Some(pid) = matching_processes(name).difference(&existing).min() {
+            break Ok(Pid::from_raw(*pid));
+        }
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break Err("Cancelled waiting for the process".to_string());
+        }
+        if timeout.map_or(false, |timeout| start.elapsed() >= timeout) {
+            break Err(format!("No process named {} appeared within {:?}", name, timeout.unwrap()));
+        }
+        std::thread::sleep(interval);
+    };
+    unsafe { signal(Signal::SIGINT, previous) }.ok();
+    result
+}